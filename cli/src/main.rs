@@ -1,14 +1,55 @@
+mod table_output;
+
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use demonax_core::database::Database;
-use demonax_core::file_utils::find_files_with_extension;
-use demonax_core::parsers::{parse_evt_file, parse_magic_cc, parse_map_sector_file, parse_npc_file, parse_npc_rune_selling, parse_npc_spell_teaching, parse_objects_srv};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use demonax_core::config::DemonaxConfig;
+use demonax_core::atlas::build_atlas;
+use demonax_core::audit::Invocation;
+use demonax_core::game_math::{death_loss, is_promoted, MAX_BLESSINGS};
+use demonax_core::database::{Database, ExistingSnapshots, UsrIngestOptions, SNAPSHOT_CHILD_TABLES};
+use demonax_core::export::{
+    Anonymizer, export_bestiary, export_creature_loot, export_harvesting_rules, export_house_list, export_hunting_spots,
+    export_events, export_item_list, export_log_events, export_online_days, export_quest_routes, export_raid_list, export_snapshot_list, export_world_stats,
+};
+use demonax_core::heatmap::{render_spawn_heatmap, HeatmapFilter, HeatmapFormat, DEFAULT_CELL_SIZE};
+use demonax_core::hunting::HuntingOverlay;
+use demonax_core::logs::tail_log;
+use demonax_core::online::{query_server_status, sample_online, OnlineSource};
+use demonax_core::player_selection::PlayerSelection;
+use demonax_core::geo::Position;
+use demonax_core::input::InputSource;
+use demonax_core::inventory::parse_item_list;
+use demonax_core::file_utils::{infer_snapshot_date, read_latin1_file, SnapshotDateSource};
+use demonax_core::parsers::{parse_evt_file, parse_evt_steps};
+use demonax_core::processors::{
+    combat_calc, give_presents, item_catalog_or_empty, level_calc, process_creatures, process_harvesting, process_items_core,
+    process_items_quests, process_quest_overview, process_raids, process_spells, process_towns, process_usr_batch,
+    render_equipment, update_all, CombatPlayer, ExcludeConfig, GamePaths, GiftOptions, LevelCalcRequest, ParserRegistry,
+    PresentSource, Reporter, UpdatePipeline, DEFAULT_SKIPPED_QUEST_REGIONS,
+};
+use demonax_core::bundle::BUNDLE_TABLES;
+use demonax_core::error::DemonaxError;
+use demonax_core::lint::{lint_content, LintSeverity};
+use demonax_core::loot::simulate_loot;
 use demonax_core::models::HarvestingData;
 use demonax_core::{generate_all_harvesting_rules, insert_harvesting_rules};
-use demonax_core::present::{apply_present_to_file, GiftResult, GiftSummary, PresentConfig};
-use demonax_core::rendering::{render_player_equipment, RenderConfig};
-use image::open;
-use rayon::prelude::*;
+use demonax_core::mon::{diff_lines, find_mon_files, CreatureEdit, MonFile};
+use demonax_core::output::OutputTarget;
+use demonax_core::overlay::import_overlay;
+use demonax_core::objects::{patch_objects_srv, ItemChange};
+use demonax_core::schema::{schema_dot, schema_markdown};
+use demonax_core::sector_edit::{edit_chest, ChestEdit};
+use demonax_core::spell_rules::{set_spell_rules, SpellRuleset};
+use demonax_core::remote::{SftpLocation, SftpSource};
+use demonax_core::raid::{evt_text, map_sectors, RaidSpec};
+use demonax_core::catalog::ItemCatalog;
+use demonax_core::gift_queue::flush_gift_queue;
+use demonax_core::present::{preview_present, read_winners_csv, GiftResult, PresentConfig};
+use demonax_core::sandbox::{clone_player, PlayerFilter};
+use demonax_core::save_hook::{PipelineLock, SaveHook};
+use demonax_core::rendering::{render_player_card, CardConfig, ImageFormat, ImageOutput, RenderConfig};
+use demonax_core::timing::Timings;
+use table_output::{Cell, Table, TableArgs};
 use tracing::info;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
@@ -39,24 +80,253 @@ struct Cli {
     )]
     log_file: std::path::PathBuf,
 
+    /// Format of the log file; stderr output stays human-readable
+    #[arg(long, global = true, env = "DEMONAX_LOG_FORMAT", value_enum, default_value = "text")]
+    log_format: LogFormat,
+
     /// Verbosity level (repeat for more verbose output)
     #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     verbose: u8,
 
+    /// Print how long each stage took when the command finishes
+    #[arg(long, global = true)]
+    timing: bool,
+
+    /// Number of worker threads for parallel parsing and rendering (default: all cores)
+    #[arg(long, global = true, env = "DEMONAX_THREADS")]
+    threads: Option<usize>,
+
+    /// Read at most this many game files per second, to leave IO for a game server on the same host
+    #[arg(long, global = true, env = "DEMONAX_IO_THROTTLE")]
+    io_throttle: Option<f64>,
+
+    /// TOML ruleset naming creature spells (default: the built-in rules)
+    #[arg(long, global = true, env = "DEMONAX_SPELL_RULES")]
+    spell_rules: Option<std::path::PathBuf>,
+
+    /// Also process the creature and raid files excluded by default or in the config's [exclude] section
+    #[arg(long, global = true)]
+    include_excluded: bool,
+
+    /// Answer yes to confirmation prompts of commands that replace or overwrite data
+    #[arg(short = 'y', long, global = true, env = "DEMONAX_YES", value_parser = clap::builder::FalseyValueParser::new())]
+    yes: bool,
+
+    /// Settings file (default: ./demonax.toml if it exists)
+    #[arg(long, global = true, env = "DEMONAX_CONFIG")]
+    config: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Formats of the log file
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Plain text lines, as on stderr
+    Text,
+    /// One JSON object per line, with the fields of the enclosing spans (command, stage, file, player)
+    Json,
+}
+
+/// Data sets that can be exported for the website
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportKind {
+    /// House list with current owners
+    Houses,
+    /// Item list with the best NPC buy and sell prices
+    Items,
+    /// Every player's latest level and skills
+    Snapshots,
+    /// Players seen, experience and levels per snapshot date, with the events running on it
+    WorldStats,
+    /// Double-exp weekends, patches and rollbacks from add-event
+    Events,
+    /// Deaths, level advances and rare loot from process-log since the latest snapshot date
+    LogEvents,
+    /// Peak and average players online per day, from sample-online
+    Online,
+    /// One loot table per creature, written as creatures/{short_name}.json below --output
+    CreatureLoot,
+    /// Raid list with seasons; disabled raids only with --include-disabled
+    Raids,
+    /// Per quest: chests, doors on the way, the keys they need and where to get them
+    QuestRoutes,
+    /// Kills per creature over every player's latest snapshot
+    Bestiary,
+    /// Harvesting rules with tool, creature and reward names
+    Harvesting,
+    /// One page per hunting spot of --spots, written as hunting/{slug}.json below --output
+    Hunting,
+}
+
+/// Output formats of the schema documentation
+#[derive(Clone, Copy, ValueEnum)]
+enum SchemaFormat {
+    /// Graphviz entity-relationship diagram
+    Dot,
+    /// Per-table column docs
+    Markdown,
+}
+
+/// Output formats of report commands
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// Human-readable table
+    Table,
+    Json,
+}
+
+/// File formats of rendered images
+#[derive(Clone, Copy, ValueEnum)]
+enum ImageFormatArg {
+    Png,
+    /// Lossless WebP
+    Webp,
+    /// Lossy AVIF (see --quality)
+    Avif,
+}
+
+/// Image encoding options of the render commands
+#[derive(Args)]
+struct ImageArgs {
+    /// Image file format
+    #[arg(long, value_enum, default_value_t = ImageFormatArg::Png)]
+    format: ImageFormatArg,
+
+    /// AVIF quality (1-100)
+    #[arg(long, default_value_t = 80, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+
+    /// Recompress PNGs with oxipng for smaller files (slower)
+    #[arg(long)]
+    optimize: bool,
+}
+
+impl ImageArgs {
+    fn output(&self) -> ImageOutput {
+        let format = match self.format {
+            ImageFormatArg::Png => ImageFormat::Png,
+            ImageFormatArg::Webp => ImageFormat::Webp,
+            ImageFormatArg::Avif => ImageFormat::Avif,
+        };
+        ImageOutput { format, quality: self.quality, optimize: self.optimize }
+    }
+}
+
+/// Kinds of content gaps
+#[derive(Clone, Copy, ValueEnum)]
+enum GapKind {
+    /// Spells no NPC teaches
+    UntaughtSpell,
+    /// Runes no NPC sells
+    UnsoldRune,
+}
+
+/// Kinds of events
+#[derive(Clone, Copy, ValueEnum)]
+enum EventKindArg {
+    /// Double experience, e.g. a weekend
+    DoubleExp,
+    /// A game update
+    Patch,
+    /// The world was reset to an earlier save
+    Rollback,
+    Other,
+}
+
+/// Account types, as in the rune sellers' account_type
+#[derive(Clone, Copy, ValueEnum)]
+enum AccountTypeArg {
+    /// No premium days left, or unknown
+    Free,
+    /// Premium days left
+    Premium,
+}
+
+impl From<AccountTypeArg> for demonax_core::models::AccountType {
+    fn from(account_type: AccountTypeArg) -> Self {
+        match account_type {
+            AccountTypeArg::Free => Self::Free,
+            AccountTypeArg::Premium => Self::Premium,
+        }
+    }
+}
+
+/// Lint rule severities
+#[derive(Clone, Copy, ValueEnum)]
+enum SeverityArg {
+    Info,
+    Warning,
+    Error,
+}
+
+impl From<SeverityArg> for LintSeverity {
+    fn from(severity: SeverityArg) -> Self {
+        match severity {
+            SeverityArg::Info => Self::Info,
+            SeverityArg::Warning => Self::Warning,
+            SeverityArg::Error => Self::Error,
+        }
+    }
+}
+
+impl From<EventKindArg> for demonax_core::models::EventKind {
+    fn from(kind: EventKindArg) -> Self {
+        match kind {
+            EventKindArg::DoubleExp => Self::DoubleExp,
+            EventKindArg::Patch => Self::Patch,
+            EventKindArg::Rollback => Self::Rollback,
+            EventKindArg::Other => Self::Other,
+        }
+    }
+}
+
+impl From<GapKind> for demonax_core::models::ContentGapKind {
+    fn from(kind: GapKind) -> Self {
+        match kind {
+            GapKind::UntaughtSpell => Self::UntaughtSpell,
+            GapKind::UnsoldRune => Self::UnsoldRune,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Process .usr files into database
     ProcessUsr {
-        /// Directory containing .usr files
+        /// Directory containing .usr files, a .tar.gz/.zip archive of one, or an
+        /// sftp://[user@]host[:port]/path URL
         #[arg(long)]
         input_dir: std::path::PathBuf,
-        /// Date for snapshot (YYYY-MM-DD format)
+        /// Date for snapshot (YYYY-MM-DD format, or "auto" to infer it from the
+        /// directory name such as usr-2024-05-01/ or the newest file's modification date)
         #[arg(long)]
         snapshot_date: String,
+        /// Replace players' snapshots that already exist for the date (e.g. after
+        /// fixing a parse) instead of skipping them
+        #[arg(long)]
+        overwrite_existing: bool,
+        /// Only process players whose name matches this glob, e.g. "A*" (any case)
+        #[arg(long)]
+        player_glob: Option<String>,
+        /// Only process the players whose IDs this file lists (one per line, or comma-separated)
+        #[arg(long)]
+        player_ids: Option<std::path::PathBuf>,
+        /// Quiet mode (0=show messages/warnings, 1=suppress messages, 2=suppress both)
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Process dated .usr backup folders in chronological order
+    ProcessUsrBatch {
+        /// Directory containing the dated backup folders (or .tar.gz/.zip archives)
+        #[arg(long)]
+        root: std::path::PathBuf,
+        /// Folder name pattern, {date} stands for YYYY-MM-DD; archives match without
+        /// their .tar.gz/.zip suffix
+        #[arg(long, default_value = "usr-{date}")]
+        pattern: String,
         /// Quiet mode (0=show messages/warnings, 1=suppress messages, 2=suppress both)
         #[arg(long, default_value_t = 0)]
         quiet: u8,
@@ -99,6 +369,9 @@ enum Commands {
             help = "Game directory with map files (env: DEMONAX_GAME_DIR)"
         )]
         game_path: std::path::PathBuf,
+        /// Regions whose quests aren't linked to their rewards; pass '' to link every quest
+        #[arg(long, value_delimiter = ',', default_values = DEFAULT_SKIPPED_QUEST_REGIONS)]
+        skip_regions: Vec<String>,
         /// Quiet mode
         #[arg(long, default_value_t = 0)]
         quiet: u8,
@@ -121,6 +394,75 @@ enum Commands {
         quiet: u8,
     },
 
+    /// Run a registered game file parser (see --list for available parsers)
+    Update {
+        /// Parser name, e.g. raids, npc-prices, quests
+        #[arg(required_unless_present = "list")]
+        parser: Option<String>,
+        /// Game directory
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: Option<std::path::PathBuf>,
+        /// List registered parsers and exit
+        #[arg(long)]
+        list: bool,
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Run all game data updates (and optionally player snapshots) in dependency order.
+    /// Completed stages are journaled so an interrupted run continues where it stopped.
+    UpdateAll {
+        /// Game directory
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: std::path::PathBuf,
+        /// Also process usr/*.usr as snapshots for this date (YYYY-MM-DD)
+        #[arg(long)]
+        snapshot_date: Option<String>,
+        /// Number of .usr files per journaled batch
+        #[arg(long, default_value_t = 500)]
+        batch_size: usize,
+        /// Clear the journal and run every stage again
+        #[arg(long)]
+        restart: bool,
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Export database contents as JSON for the website
+    Export {
+        /// What to export
+        #[arg(value_enum)]
+        kind: ExportKind,
+        /// Output JSON file (a directory for creature-loot and hunting), or an s3:// URL
+        #[arg(long)]
+        output: std::path::PathBuf,
+        /// Replace player names and IDs with hash tokens and drop source file paths
+        #[arg(long)]
+        anonymize: bool,
+        /// Salt for the anonymized tokens; keep it secret so tokens can't be reversed
+        #[arg(long, env = "DEMONAX_ANONYMIZE_SALT", requires = "anonymize")]
+        anonymize_salt: Option<String>,
+        /// Also export raids disabled in the config's [raids] section
+        #[arg(long)]
+        include_disabled: bool,
+        /// Hunting spot overlay (TOML) for the hunting export
+        #[arg(long, required_if_eq("kind", "hunting"))]
+        spots: Option<std::path::PathBuf>,
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
     /// Update raid data
     UpdateRaids {
         /// Game directory with raid files
@@ -130,6 +472,10 @@ enum Commands {
             help = "Game directory with raid files (env: DEMONAX_GAME_DIR)"
         )]
         game_path: std::path::PathBuf,
+        /// The game server's raid log to store raid starts from, instead of the
+        /// log in the settings file's [raids] section
+        #[arg(long)]
+        raid_log: Option<std::path::PathBuf>,
         /// Quiet mode
         #[arg(long, default_value_t = 0)]
         quiet: u8,
@@ -164,6 +510,9 @@ enum Commands {
         /// Custom path to magic.cc (optional)
         #[arg(long)]
         magic_cc: Option<std::path::PathBuf>,
+        /// TOML file mapping rune spells to their item type IDs, for runes the computed ID gets wrong
+        #[arg(long)]
+        rune_overrides: Option<std::path::PathBuf>,
         /// Quiet mode
         #[arg(long, default_value_t = 0)]
         quiet: u8,
@@ -186,20 +535,67 @@ enum Commands {
         usr_path: std::path::PathBuf,
 
         /// Path to TOML file defining present contents
-        #[arg(long)]
-        present_config: std::path::PathBuf,
+        #[arg(long, required_unless_present = "winners_csv")]
+        present_config: Option<std::path::PathBuf>,
+
+        /// CSV of winners with player_name and present_config columns, giving each
+        /// row's player that row's present instead of everyone the same one
+        #[arg(long, conflicts_with = "present_config")]
+        winners_csv: Option<std::path::PathBuf>,
 
         /// Inventory slot to place present (default: 10)
         #[arg(long, default_value_t = 10)]
         target_slot: i32,
 
+        /// Skip players the present would put over their carry capacity instead of warning
+        #[arg(long, default_value_t = false)]
+        skip_over_capacity: bool,
+
         /// Show what would be done without modifying files
         #[arg(long, default_value_t = false)]
         dry_run: bool,
 
+        /// Give one present per account (from the .usr Account line) instead of per
+        /// character: the most experienced character that can take it
+        #[arg(long, default_value_t = false, conflicts_with = "winners_csv")]
+        per_account: bool,
+
+        /// Only change the .usr files of these players, e.g. "Test Dummy,GM Bob"
+        #[arg(long, value_delimiter = ',')]
+        only_players: Vec<String>,
+
+        /// Print the inventory section diff (before/after slots) of the first N
+        /// players given a present, to check a sample before the real run
+        #[arg(long)]
+        show_diff: Option<usize>,
+
         /// Quiet mode (0=show messages/warnings, 1=suppress messages, 2=suppress both)
         #[arg(long, default_value_t = 0)]
         quiet: u8,
+
+        /// Output format; json prints every result (file, slot, item, error kind)
+        /// and the summary
+        #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+        format: ReportFormat,
+    },
+
+    /// Simulate a present config's lottery pools and show how often each item comes up
+    PresentPreview {
+        /// Path to TOML file defining present contents
+        #[arg(long)]
+        config: std::path::PathBuf,
+
+        /// Number of presents to draw
+        #[arg(long, default_value_t = 10_000)]
+        samples: u64,
+
+        /// Random seed for reproducible results
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
     },
 
     /// Render player equipment images from database
@@ -208,727 +604,2379 @@ enum Commands {
         #[arg(long)]
         data_dir: std::path::PathBuf,
 
-        /// Directory where output equipment images will be saved
+        /// Directory where output equipment images will be saved, or an s3://bucket/prefix URL
         #[arg(long)]
         output_dir: std::path::PathBuf,
 
-        /// Path to eq.png template (112x149 base image)
+        /// Path to eq.png template (112x149 base image); defaults to the built-in one
         #[arg(long)]
-        template: std::path::PathBuf,
+        template: Option<std::path::PathBuf>,
 
-        /// Path to blank.png for empty equipment slots
+        /// Path to blank.png for empty equipment slots; defaults to the built-in one
         #[arg(long)]
-        blank: std::path::PathBuf,
+        blank: Option<std::path::PathBuf>,
 
         /// Optional: render only this player ID (omit to render all)
         #[arg(long)]
         player_id: Option<i32>,
 
+        /// Only render players whose name matches this glob, e.g. "A*" (any case)
+        #[arg(long)]
+        player_glob: Option<String>,
+
+        /// Only render the players whose IDs this file lists (one per line, or comma-separated)
+        #[arg(long)]
+        player_ids: Option<std::path::PathBuf>,
+
+        /// Don't draw stack counts and charges on slots
+        #[arg(long)]
+        no_counts: bool,
+
+        /// Maximum number of item images kept in memory
+        #[arg(long, default_value_t = 4096)]
+        image_cache: usize,
+
+        #[command(flatten)]
+        image: ImageArgs,
+
         /// Quiet mode (0=show messages/warnings, 1=suppress messages, 2=suppress both)
         #[arg(long, default_value_t = 0)]
         quiet: u8,
     },
-}
 
-fn setup_logging(verbose: u8, log_file: &std::path::Path) -> Result<tracing_appender::non_blocking::WorkerGuard> {
-    let filter_level = match verbose {
-        0 => tracing::Level::ERROR,
-        1 => tracing::Level::WARN,
-        2 => tracing::Level::INFO,
-        3 => tracing::Level::DEBUG,
-        _ => tracing::Level::TRACE,
-    };
-    let filter = EnvFilter::from_default_env().add_directive(filter_level.into());
+    /// Simulate kills of a creature and report expected loot per kill
+    SimulateLoot {
+        /// Creature short name (file name) or name, e.g. dragon
+        #[arg(long)]
+        creature: String,
 
-    let file_appender = tracing_appender::rolling::never(
-        log_file.parent().unwrap_or(std::path::Path::new(".")),
-        log_file.file_name().unwrap_or(std::ffi::OsStr::new("demonax.log")),
-    );
-    let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+        /// Number of kills to simulate
+        #[arg(long, default_value_t = 1000)]
+        kills: u64,
 
-    let subscriber = tracing_subscriber::registry()
-        .with(filter)
-        .with(fmt::Layer::new().with_writer(std::io::stderr).with_ansi(true))
-        .with(fmt::Layer::new().with_writer(non_blocking).with_ansi(false));
+        /// Random seed for reproducible results
+        #[arg(long)]
+        seed: Option<u64>,
+    },
 
-    tracing::subscriber::set_global_default(subscriber)?;
+    /// Estimate the damage a creature does per turn against a player
+    CombatCalc {
+        /// Creature short name (file name) or name, e.g. dragon
+        #[arg(long)]
+        creature: String,
 
-    Ok(guard)
-}
+        /// Take level, vocation and shielding from this player's latest snapshot
+        #[arg(long, conflicts_with_all = ["level", "vocation"])]
+        player_id: Option<i32>,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+        /// Player level (when not using --player-id)
+        #[arg(long, default_value_t = 8)]
+        level: i32,
 
-    let _guard = setup_logging(cli.verbose, &cli.log_file)?;
+        /// Player vocation, e.g. knight (when not using --player-id)
+        #[arg(long, default_value = "None")]
+        vocation: String,
 
-    info!("Starting demonax CLI");
+        /// Shielding skill (defaults to the player's, or 10)
+        #[arg(long)]
+        shielding: Option<i32>,
 
-    // TODO: Implement command dispatch
-    match cli.command {
-        Commands::ProcessUsr { input_dir, snapshot_date, quiet } => {
-            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
-            let db = Database::new(&db_path)?;
-            let processed = db.process_usr_files(&input_dir, &snapshot_date, quiet)?;
-            info!("Successfully processed {} .usr files", processed);
-        }
-        Commands::UpdateCreatures { game_path, quiet } => {
-            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
-            let db = Database::new(&db_path)?;
-            let processed = db.process_mon_files(&game_path, quiet)?;
-            info!("Successfully processed {} .mon files", processed);
-            // TODO: Generate CSV exports for backward compatibility
-        }
-        Commands::UpdateItemsCore { game_path, quiet } => {
-            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
-            let db = Database::new(&db_path)?;
+        /// Total armor of the player's equipment
+        #[arg(long, default_value_t = 0)]
+        armor: i32,
 
-            if quiet == 0 {
-                info!("Processing item data from {:?}", game_path);
-            }
+        /// Defense value of the player's shield
+        #[arg(long, default_value_t = 0)]
+        shield_defense: i32,
 
-            // Parse objects.srv for item metadata
-            let objects_srv_path = game_path.join("dat").join("objects.srv");
-            if !objects_srv_path.exists() {
-                anyhow::bail!("objects.srv not found at {:?}", objects_srv_path);
-            }
+        /// Blessings for the experience lost on death (defaults to the player's, or 0)
+        #[arg(long)]
+        blessings: Option<i32>,
+    },
 
-            if quiet == 0 {
-                info!("Parsing objects.srv");
-            }
-            let items = parse_objects_srv(&objects_srv_path)?;
-            if quiet == 0 {
-                info!("Found {} items with 'Take' flag", items.len());
-            }
+    /// Estimate the experience, skills and items a player would lose on death
+    DeathCalc {
+        /// Player name or ID; uses the latest snapshot
+        #[arg(long)]
+        player: String,
 
-            // Insert items into database
-            let inserted_count = db.insert_or_update_items(&items)?;
-            if quiet == 0 {
-                info!("Inserted/updated {} items in database", inserted_count);
-            }
+        /// Blessings (defaults to the player's, or 0)
+        #[arg(long)]
+        blessings: Option<i32>,
 
-            // Parse .npc files for prices (in parallel)
-            let npc_dir = game_path.join("npc");
-            if !npc_dir.exists() {
-                if quiet < 2 {
-                    tracing::warn!("NPC directory not found at {:?}, skipping price processing", npc_dir);
-                }
-            } else {
-                if quiet == 0 {
-                    info!("Finding .npc files in {:?}", npc_dir);
-                }
-                let npc_files = find_files_with_extension(&npc_dir, "npc")?;
-                if quiet == 0 {
-                    info!("Found {} .npc files", npc_files.len());
-                }
-
-                // Parse all .npc files in parallel
-                let all_prices: Vec<_> = npc_files
-                    .par_iter()
-                    .filter_map(|path| {
-                        match parse_npc_file(path) {
-                            Ok(prices) => Some(prices),
-                            Err(e) => {
-                                if quiet < 2 {
-                                    tracing::warn!("Failed to parse {:?}: {}", path, e);
-                                }
-                                None
-                            }
-                        }
-                    })
-                    .flatten()
-                    .collect();
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
 
-                if quiet == 0 {
-                    info!("Parsed {} price entries from .npc files", all_prices.len());
-                }
+    /// Experience and time to reach a level under the configured experience stages
+    LevelCalc {
+        /// Player name or ID; starts from the latest snapshot and measures their pace
+        #[arg(long, conflicts_with = "level")]
+        player: Option<String>,
 
-                // Insert prices into database
-                let price_count = db.clear_and_insert_item_prices(&all_prices)?;
-                if quiet == 0 {
-                    info!("Inserted {} price entries in database", price_count);
-                }
-            }
+        /// Level to start from instead of a player's
+        #[arg(long, required_unless_present = "player")]
+        level: Option<i32>,
 
-            if quiet == 0 {
-                info!("Item processing complete. Data stored in database: {:?}", db_path);
-            }
-        }
-        Commands::UpdateItemsQuests { game_path: _, quiet } => {
-            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
-            let db = Database::new(&db_path)?;
+        /// Level to reach (default: the next level)
+        #[arg(long)]
+        target_level: Option<i32>,
 
-            if quiet == 0 {
-                info!("Updating items with quest reward information");
-            }
+        /// Creature experience per hour before rates, e.g. a hunting spot's experience_per_hour
+        #[arg(long)]
+        exp_per_hour: Option<i64>,
 
-            // Update items table with quest rewards from database
-            let updated_count = db.update_items_with_quest_rewards(quiet)?;
+        /// Days of snapshots the player's pace is measured over
+        #[arg(long, default_value_t = 30)]
+        days: i64,
 
-            if quiet == 0 {
-                info!("Successfully updated {} items with quest rewards", updated_count);
-                info!("Items table now includes 'rewarded_from' column with quest names");
-            }
-        }
-        Commands::UpdateQuestOverview { game_path, quest_csv, quiet } => {
-            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
-            let db = Database::new(&db_path)?;
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
 
-            if quiet == 0 {
-                info!("Processing quest overview from map files in {:?}", game_path);
-            }
+    /// Record an event (double-exp weekend, patch, rollback) shown on the time series
+    AddEvent {
+        #[arg(long, value_enum)]
+        kind: EventKindArg,
 
-            // Load quest names from CSV if provided
-            let quest_names = if let Some(csv_path) = quest_csv {
-                if quiet == 0 {
-                    info!("Loading quest names from CSV: {:?}", csv_path);
-                }
-                match Database::load_quest_names_from_csv(&csv_path) {
-                    Ok(names) => {
-                        if quiet == 0 {
-                            info!("Loaded {} quest names from CSV", names.len());
-                        }
-                        Some(names)
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to load quest CSV: {}. Continuing without quest names.", e);
-                        None
-                    }
-                }
-            } else {
-                None
-            };
+        /// Name shown on graphs, e.g. "Double XP weekend"
+        #[arg(long)]
+        name: String,
 
-            // Find all map sector files
-            let map_dir = game_path.join("map");
-            if !map_dir.exists() {
-                anyhow::bail!("Map directory not found at {:?}", map_dir);
-            }
+        /// First day, YYYY-MM-DD
+        #[arg(long)]
+        start: String,
 
-            if quiet == 0 {
-                info!("Scanning map directory: {:?}", map_dir);
-            }
+        /// Last day, YYYY-MM-DD (default: the first day)
+        #[arg(long)]
+        end: Option<String>,
 
-            let map_files = find_files_with_extension(&map_dir, "sec")?;
-            if quiet == 0 {
-                info!("Found {} map sector files", map_files.len());
-            }
-
-            // Parse all map files in parallel to extract quest chests
-            let all_chests: Vec<_> = map_files
-                .par_iter()
-                .filter_map(|path| {
-                    match parse_map_sector_file(path) {
-                        Ok(chests) if !chests.is_empty() => Some(chests),
-                        Ok(_) => None, // Empty chests
-                        Err(e) => {
-                            if quiet < 2 {
-                                tracing::warn!("Failed to parse {:?}: {}", path, e);
-                            }
-                            None
-                        }
-                    }
-                })
-                .flatten()
-                .collect();
+        #[arg(long)]
+        description: Option<String>,
+    },
 
-            if quiet == 0 {
-                info!("Extracted {} quest chests from map files", all_chests.len());
-            }
+    /// Delete an event recorded with add-event
+    RemoveEvent {
+        /// Event ID, as listed by events
+        #[arg(long)]
+        id: i64,
+    },
 
-            // Process quest chests into database
-            let processed = db.process_quest_chests(&all_chests, quest_names.as_ref(), quiet)?;
+    /// List the events recorded with add-event
+    Events {
+        #[command(flatten)]
+        table: TableArgs,
 
-            if quiet == 0 {
-                info!("Successfully processed {} quests into database: {:?}", processed, db_path);
-            }
-        }
-        Commands::UpdateRaids { game_path, quiet } => {
-            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
-            let db = Database::new(&db_path)?;
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
 
-            if quiet == 0 {
-                info!("Processing raid data from .evt files");
-            }
+    /// Store the deaths, level advances and rare loot added to the game server log
+    /// since the previous run
+    ProcessLog {
+        /// The game server log
+        #[arg(long)]
+        log: std::path::PathBuf,
 
-            // Find all .evt files in mon directory
-            let mon_dir = game_path.join("mon");
-            if !mon_dir.exists() {
-                anyhow::bail!("Mon directory not found at {:?}", mon_dir);
-            }
+        /// Keep reading the log as the server writes it, until interrupted
+        #[arg(long, default_value_t = false)]
+        follow: bool,
 
-            let evt_files = find_files_with_extension(&mon_dir, "evt")?;
+        /// Seconds between reads with --follow
+        #[arg(long, default_value_t = 10)]
+        poll_seconds: u64,
 
-            // Exclude halloweenhare.evt
-            let evt_files: Vec<_> = evt_files
-                .into_iter()
-                .filter(|p| {
-                    !p.file_name()
-                        .and_then(|n| n.to_str())
-                        .map(|n| n == "halloweenhare.evt")
-                        .unwrap_or(false)
-                })
-                .collect();
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
 
-            if quiet == 0 {
-                info!("Found {} raid files", evt_files.len());
-            }
-
-            // Parse all .evt files in parallel
-            let raids: Vec<_> = evt_files
-                .par_iter()
-                .filter_map(|path| {
-                    match parse_evt_file(path) {
-                        Ok(raid) => Some(raid),
-                        Err(e) => {
-                            if quiet < 2 {
-                                tracing::warn!("Failed to parse {:?}: {}", path, e);
-                            }
-                            None
-                        }
-                    }
-                })
-                .collect();
+    /// Record how many players are online, once or every few minutes
+    SampleOnline {
+        /// Who-is-online file (one player per line) or the status port as host:port
+        #[arg(long)]
+        source: OnlineSource,
 
-            if quiet == 0 {
-                info!("Parsed {} raids successfully", raids.len());
-            }
+        /// Keep sampling every this many minutes, until interrupted
+        #[arg(long)]
+        every: Option<u64>,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Ask the running game server's status port for its uptime, players and version
+    ServerStatus {
+        /// Status port as host:port
+        #[arg(long, env = "DEMONAX_STATUS_ADDRESS", default_value = "127.0.0.1:7171")]
+        address: String,
+
+        /// Also store the players online as a sample, like sample-online
+        #[arg(long, default_value_t = false)]
+        store: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// List stored raids with their season and whether they are enabled
+    Raids {
+        /// Only list raids of this season, e.g. halloween
+        #[arg(long)]
+        season: Option<String>,
+
+        #[command(flatten)]
+        table: TableArgs,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// Compare when raids started in the raid log with their .evt intervals
+    RaidActivity {
+        #[command(flatten)]
+        table: TableArgs,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// Show the timeline of a raid (.evt file) as a readable script
+    RaidPreview {
+        /// Game directory with raid files
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory with raid files (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: std::path::PathBuf,
+
+        /// Raid name (the .evt file name without extension), e.g. orcraid
+        #[arg(long)]
+        name: String,
+    },
+
+    /// Generate a raid (.evt) file from a TOML spec
+    GenerateRaid {
+        /// TOML raid spec (waves, creatures, messages, area)
+        #[arg(long)]
+        spec: std::path::PathBuf,
+
+        /// Output .evt file, e.g. <game>/mon/newraid.evt
+        #[arg(long)]
+        output: std::path::PathBuf,
+
+        /// Game directory; spawn areas are checked against its map sectors
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory with map sectors for validation (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: Option<std::path::PathBuf>,
+
+        /// Overwrite the output file if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Apply balance changes to .mon creature files
+    EditCreature {
+        /// Game directory with creature files
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory with creature files (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: std::path::PathBuf,
+
+        /// Creatures to edit: file name or name glob, e.g. demon or "orc*" (repeatable)
+        #[arg(long, required = true)]
+        creature: Vec<String>,
+
+        /// Change to apply, e.g. hp*1.1, armor=30, experience+100 or FistFighting*1.2 (repeatable)
+        #[arg(long, required = true)]
+        set: Vec<String>,
+
+        /// Show the changes without writing files
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Change item records in objects.srv
+    EditItems {
+        /// Game directory containing dat/objects.srv
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory containing dat/objects.srv (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: std::path::PathBuf,
+
+        /// Change to apply, e.g. Attributes.Weight=1500 (empty value removes an attribute; repeatable)
+        #[arg(long, required = true)]
+        set: Vec<String>,
+
+        /// SQL condition selecting rows of the items table, e.g. "name LIKE '%crossbow%'"
+        #[arg(long = "where")]
+        where_clause: String,
+
+        /// Write the changes to objects.srv (without it the changes are only shown)
+        #[arg(long)]
+        write_objects_srv: bool,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Change the contents or quest value of a quest chest in its map sector file
+    EditChest {
+        /// Game directory with map sector files
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory with map sector files (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: std::path::PathBuf,
+
+        /// Chest position as x,y,z
+        #[arg(long)]
+        coords: Position,
+
+        /// New chest contents, replacing the old ones, e.g. "3031 Amount=100, 3585"
+        #[arg(long, required_unless_present = "set_quest_value")]
+        set_content: Option<String>,
+
+        /// New ChestQuestNumber of the chest
+        #[arg(long)]
+        set_quest_value: Option<i32>,
+
+        /// Directory the sector file is copied to before it is changed
+        #[arg(long, default_value = "./map-backups")]
+        backup_dir: std::path::PathBuf,
+
+        /// Show the change without writing the sector file
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Document the database schema as an ERD or Markdown tables
+    Schema {
+        /// Output format
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: SchemaFormat,
+
+        /// Output file (default: stdout)
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Report how complete the data of each subsystem is
+    Coverage {
+        /// Directory with creature images (<image>.gif or .png, after the [images] overrides); creature images are skipped without it
+        #[arg(long)]
+        creature_images: Option<std::path::PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// List untaught spells and unsold runes recorded by update-spells
+    ContentGaps {
+        /// Only list gaps of this kind
+        #[arg(long, value_enum)]
+        kind: Option<GapKind>,
+
+        /// Only list spells whose name or words contain this text
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Only list spells up to this level
+        #[arg(long)]
+        max_level: Option<i32>,
+
+        /// Run to list (default: the latest)
+        #[arg(long, conflicts_with = "history")]
+        run: Option<i64>,
+
+        /// Show the number of gaps recorded by each run instead
+        #[arg(long)]
+        history: bool,
+
+        #[command(flatten)]
+        table: TableArgs,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// Check the content against the built-in and configured lint rules; fails on errors
+    LintContent {
+        /// Only run rules of this severity or worse
+        #[arg(long, value_enum, default_value = "info")]
+        min_severity: SeverityArg,
+
+        /// Number of offending rows listed per rule in the table
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// Import hand-written creature or item notes from CSV for the exports
+    ImportOverlay {
+        /// CSV with a short_name,note (creatures) or type_id,note (items) header
+        #[arg(long)]
+        csv: std::path::PathBuf,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Save the current items, creatures, spells, quests and raids under a label
+    TagContent {
+        /// Label of the content version, e.g. patch-1.3
+        #[arg(long)]
+        label: String,
+
+        /// Replace an existing tag with the same label
+        #[arg(long)]
+        force: bool,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Compare two tagged content versions as patch notes
+    ContentDiff {
+        /// Label of the older version
+        #[arg(long)]
+        from: String,
+
+        /// Label of the newer version (default: the current content)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Output format; table prints Markdown patch notes
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// Write the content tables (no player data) to a portable JSON bundle
+    DumpBundle {
+        /// Bundle file; zstd-compressed when it ends in .zst
+        #[arg(long, default_value = "demonax-data.json.zst")]
+        output: std::path::PathBuf,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Replace the content tables with the rows of a bundle from dump-bundle
+    LoadBundle {
+        /// Bundle file written by dump-bundle
+        #[arg(long)]
+        input: std::path::PathBuf,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Hide a player from the website (exports and renders), or show them again
+    SetPlayerVisibility {
+        /// Player ID
+        #[arg(long)]
+        player_id: i32,
+
+        /// Hide the player
+        #[arg(long, conflicts_with = "visible", required_unless_present = "visible")]
+        hidden: bool,
+
+        /// Show the player again
+        #[arg(long)]
+        visible: bool,
+    },
+
+    /// Render a shareable player card from the latest snapshot
+    RenderPlayerCard {
+        /// Player ID to render
+        #[arg(long)]
+        player_id: i32,
+
+        /// Directory with rendered equipment images (output of render-equipment)
+        #[arg(long)]
+        equipment_dir: std::path::PathBuf,
+
+        /// Directory where the card image will be saved, or an s3://bucket/prefix URL
+        #[arg(long)]
+        output_dir: std::path::PathBuf,
+
+        /// Optional background image for the card
+        #[arg(long)]
+        background: Option<std::path::PathBuf>,
+
+        #[command(flatten)]
+        image: ImageArgs,
+
+        /// Quiet mode (0=show messages/warnings, 1=suppress messages, 2=suppress both)
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Render per-floor spawn density heatmaps for the map, as images or GeoJSON
+    RenderSpawnHeatmap {
+        /// Directory for the floor files and heatmap.json, or an s3://bucket/prefix URL
+        #[arg(long)]
+        output_dir: std::path::PathBuf,
+
+        /// Only spawns of these creatures (name or short name; repeatable)
+        #[arg(long)]
+        creature: Vec<String>,
+
+        /// Only creatures with at least this recommended level
+        #[arg(long)]
+        min_level: Option<i32>,
+
+        /// Only creatures with at most this recommended level
+        #[arg(long)]
+        max_level: Option<i32>,
+
+        /// Fields per cell side; each cell is one pixel or one GeoJSON square
+        #[arg(long, default_value_t = DEFAULT_CELL_SIZE, value_parser = clap::value_parser!(i32).range(1..))]
+        cell_size: i32,
+
+        /// Write GeoJSON for the leaflet map instead of images
+        #[arg(long)]
+        geojson: bool,
+
+        #[command(flatten)]
+        image: ImageArgs,
+
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Pack the item images into sprite sheets with a JSON manifest and CSS
+    BuildAtlas {
+        /// Directory containing item PNG files (named {id}.png)
+        #[arg(long)]
+        data_dir: std::path::PathBuf,
+
+        /// Directory for the sheets, manifest and CSS, or an s3://bucket/prefix URL
+        #[arg(long)]
+        output_dir: std::path::PathBuf,
+
+        /// File name prefix: {name}-{n}.png, {name}.json and {name}.css
+        #[arg(long, default_value = "items")]
+        name: String,
+
+        /// Maximum sheet width and height in pixels
+        #[arg(long, default_value_t = 1024)]
+        max_size: u32,
+
+        #[command(flatten)]
+        image: ImageArgs,
+
+        /// Quiet mode (0=show messages/warnings, 1=suppress messages, 2=suppress both)
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Estimate the gold and items looted per creature on a snapshot date
+    EconomyReport {
+        /// Snapshot date (YYYY-MM-DD); defaults to the latest
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Number of creatures to list in the table
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// Account highscores and activity: the characters of each account, most total experience first
+    AccountReport {
+        /// Snapshot date (YYYY-MM-DD); defaults to the latest
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Characters with a snapshot in this many days before the date count as active
+        #[arg(long, default_value_t = 30)]
+        active_days: u32,
+
+        /// Only list free or premium accounts (from the .usr PremiumDays line)
+        #[arg(long, value_enum)]
+        account_type: Option<AccountTypeArg>,
+
+        /// Number of accounts to list in the table
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// List the players who learned a spell, from their latest snapshot
+    SpellCasters {
+        /// Spell words or name, e.g. "exevo gran mas vis" or "Ultimate Explosion"
+        #[arg(long)]
+        spell: String,
+
+        /// Table columns, sorting and row limit (default: 20 players)
+        #[command(flatten)]
+        table: TableArgs,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// List the NPCs nearest to a map position, or the nearest NPCs selling an item
+    Nearest {
+        /// Map position as x,y,z, e.g. 32369,32241,7
+        #[arg(long)]
+        position: Position,
+
+        /// Only list NPCs that sell this item type ID to players
+        #[arg(long)]
+        item: Option<i32>,
+
+        /// Number of NPCs to list
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+    },
+
+    /// Run from the game server's save script before the save: marks when the save started
+    /// and gives the queued presents
+    PreSaveHook {
+        /// Game directory the server saves to
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory the server saves to (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: std::path::PathBuf,
+        /// Directory the .usr files are copied to before presents change them
+        #[arg(long, default_value = "./gift-backups")]
+        backup_dir: std::path::PathBuf,
+        /// Only change the .usr files of these players, e.g. "Test Dummy,GM Bob"
+        #[arg(long, value_delimiter = ',')]
+        only_players: Vec<String>,
+        /// Seconds to wait for a running pipeline to finish
+        #[arg(long, default_value_t = 0)]
+        wait: u64,
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Queue a present for the next server save (see pre-save-hook and flush-gifts)
+    QueuePresent {
+        /// Path to TOML file defining present contents
+        #[arg(long, required_unless_present = "winners_csv")]
+        present_config: Option<std::path::PathBuf>,
+        /// Player to give the present to; repeat for several. Every player when left out
+        #[arg(long)]
+        player: Vec<String>,
+        /// CSV of winners with player_name and present_config columns, queuing one gift per row
+        #[arg(long, conflicts_with_all = ["present_config", "player"])]
+        winners_csv: Option<std::path::PathBuf>,
+        /// Inventory slot to place present (default: 10)
+        #[arg(long, default_value_t = 10)]
+        target_slot: i32,
+        /// Skip players the present would put over their carry capacity instead of warning
+        #[arg(long, default_value_t = false)]
+        skip_over_capacity: bool,
+    },
+
+    /// Give the queued presents now, outside a server save
+    FlushGifts {
+        /// Path to usr/ directory containing player files
+        #[arg(long)]
+        usr_path: std::path::PathBuf,
+        /// Directory the .usr files are copied to before presents change them
+        #[arg(long, default_value = "./gift-backups")]
+        backup_dir: std::path::PathBuf,
+        /// Only change the .usr files of these players, e.g. "Test Dummy,GM Bob"
+        #[arg(long, value_delimiter = ',')]
+        only_players: Vec<String>,
+        /// Seconds to wait for a running pipeline to finish
+        #[arg(long, default_value_t = 0)]
+        wait: u64,
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Run from the game server's save script after the save: stores the .usr files the
+    /// save wrote and runs update-all if other game files changed
+    PostSaveHook {
+        /// Game directory the server saved to
+        #[arg(
+            long,
+            env = "DEMONAX_GAME_DIR",
+            help = "Game directory the server saved to (env: DEMONAX_GAME_DIR)"
+        )]
+        game_path: std::path::PathBuf,
+        /// Snapshot date of the .usr files (YYYY-MM-DD); defaults to today
+        #[arg(long)]
+        snapshot_date: Option<String>,
+        /// Seconds to wait for a running pipeline to finish
+        #[arg(long, default_value_t = 600)]
+        wait: u64,
+        /// Quiet mode
+        #[arg(long, default_value_t = 0)]
+        quiet: u8,
+    },
+
+    /// Copy a player's .usr file under a new name and ID, to try gifts and edits on
+    /// (see --only-players)
+    ClonePlayer {
+        /// Path to usr/ directory containing player files
+        #[arg(long)]
+        usr_path: std::path::PathBuf,
+        /// Player to copy
+        #[arg(long)]
+        name: String,
+        /// Name of the copy
+        #[arg(long = "as")]
+        new_name: String,
+        /// ID of the copy; one above the highest player ID when left out
+        #[arg(long)]
+        id: Option<i32>,
+    },
+}
+
+fn setup_logging(
+    verbose: u8,
+    log_file: &std::path::Path,
+    log_format: LogFormat,
+    timings: Option<&Timings>,
+) -> Result<tracing_appender::non_blocking::WorkerGuard> {
+    let filter_level = match verbose {
+        0 => tracing::Level::ERROR,
+        1 => tracing::Level::WARN,
+        2 => tracing::Level::INFO,
+        3 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    // Filters are per output so the timing layer sees spans at any verbosity
+    let filter = || EnvFilter::from_default_env().add_directive(filter_level.into());
+
+    let file_appender = tracing_appender::rolling::never(
+        log_file.parent().unwrap_or(std::path::Path::new(".")),
+        log_file.file_name().unwrap_or(std::ffi::OsStr::new("demonax.log")),
+    );
+    let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+
+    let file_layer = match log_format {
+        LogFormat::Text => fmt::Layer::new().with_writer(non_blocking).with_ansi(false).boxed(),
+        LogFormat::Json => fmt::Layer::new()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(non_blocking)
+            .boxed(),
+    };
+
+    let subscriber = tracing_subscriber::registry()
+        .with(timings.map(Timings::layer))
+        .with(fmt::Layer::new().with_writer(std::io::stderr).with_ansi(true).with_filter(filter()))
+        .with(file_layer.with_filter(filter()));
+
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    Ok(guard)
+}
 
-            // Insert into database
-            let inserted = db.insert_or_update_raids(&raids)?;
+/// Data a command is about to replace or overwrite
+enum Destroys {
+    /// Every row of a database table
+    Table(&'static str),
+    /// A file rewritten in place, or a directory whose files are
+    File(std::path::PathBuf),
+}
+
+/// What each command replaces or overwrites. Every destructive command is listed
+/// here so [`confirm_destructive`] can ask before any of them runs.
+fn destroyed_by(command: &Commands) -> Vec<Destroys> {
+    let tables: Vec<&'static str> = match command {
+        Commands::UpdateCreatures { .. } => UpdatePipeline::Creatures.replaces(),
+        Commands::UpdateItemsCore { .. } => UpdatePipeline::ItemsCore.replaces(),
+        Commands::UpdateQuestOverview { .. } => UpdatePipeline::QuestOverview.replaces(),
+        Commands::UpdateRaids { .. } => UpdatePipeline::Raids.replaces(),
+        Commands::Update { parser: Some(parser), list: false, .. } => UpdatePipeline::Parser(parser).replaces(),
+        Commands::UpdateAll { .. } => UpdatePipeline::All.replaces(),
+        Commands::UpdateHarvesting { .. } => UpdatePipeline::Harvesting.replaces(),
+        Commands::UpdateSpells { .. } => UpdatePipeline::Spells.replaces(),
+        Commands::LoadBundle { .. } => BUNDLE_TABLES.to_vec(),
+        Commands::ProcessUsr { overwrite_existing: true, .. } => [&["daily_snapshots"][..], SNAPSHOT_CHILD_TABLES].concat(),
+        Commands::UpdateMoveUseHarvesting { moveuse_path, .. } => return vec![Destroys::File(moveuse_path.clone())],
+        Commands::EditCreature { game_path, dry_run: false, .. } => return vec![Destroys::File(game_path.join("mon"))],
+        Commands::EditItems { game_path, write_objects_srv: true, .. } => {
+            return vec![Destroys::File(game_path.join("dat").join("objects.srv"))];
+        }
+        Commands::EditChest { game_path, dry_run: false, .. } => return vec![Destroys::File(game_path.join("map"))],
+        Commands::GivePresent { usr_path, dry_run: false, .. } => return vec![Destroys::File(usr_path.clone())],
+        _ => Vec::new(),
+    };
+    tables.into_iter().map(Destroys::Table).collect()
+}
+
+/// What of the data [`destroyed_by`] names a command would lose, one line each.
+/// Tables without rows and missing files are not worth asking about.
+fn data_lost_by(command: &Commands, db_path: &std::path::Path) -> Result<Vec<String>> {
+    let targets = destroyed_by(command);
+    if targets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let db = if db_path.exists() { Some(Database::open_read_only(db_path)?) } else { None };
+    let mut lost = Vec::new();
+    for target in targets {
+        match target {
+            Destroys::Table(table) => {
+                let rows = match db.as_ref().map(|db| db.row_count(table)) {
+                    Some(Ok(rows)) => rows,
+                    // Not created yet by an older schema
+                    None | Some(Err(DemonaxError::NotFound(_))) => 0,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                if rows > 0 {
+                    lost.push(format!("{} rows of {} in {:?}", rows, table, db_path));
+                }
+            }
+            Destroys::File(path) => {
+                if path.is_dir() {
+                    lost.push(format!("files in {:?}, rewritten in place", path));
+                } else if path.exists() {
+                    lost.push(format!("{:?}, rewritten in place", path));
+                }
+            }
+        }
+    }
+    Ok(lost)
+}
+
+/// Ask before running a command that replaces or overwrites data, listing what
+/// would be lost (see [`data_lost_by`]). Without a terminal to ask on, `--yes`
+/// is required.
+fn confirm_destructive(command: &Commands, db_path: &std::path::Path, yes: bool) -> Result<()> {
+    use std::io::IsTerminal;
+
+    if yes {
+        return Ok(());
+    }
+    let lost = data_lost_by(command, db_path)?;
+    if lost.is_empty() {
+        return Ok(());
+    }
+
+    let listing = lost.iter().map(|line| format!("  {}\n", line)).collect::<String>();
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("This command replaces:\n{}Pass --yes (or set DEMONAX_YES=1) to run it without a terminal", listing);
+    }
+    eprint!("This command replaces:\n{}Continue? [y/N] ", listing);
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        anyhow::bail!("Aborted, nothing was changed");
+    }
+    Ok(())
+}
+
+/// Mirror an `sftp://` game path into the configured cache and return the local
+/// copy; local paths are returned as they are.
+fn resolve_game_path(game_path: std::path::PathBuf, config: &DemonaxConfig, quiet: u8) -> Result<std::path::PathBuf> {
+    let Some(location) = SftpLocation::parse(&game_path)? else {
+        return Ok(game_path);
+    };
+    let cache_dir = config.sftp.cache_dir.join(&location.host).join(location.root.trim_start_matches('/'));
+
+    let _mirror = tracing::info_span!("sftp_mirror").entered();
+    let stats = SftpSource::connect(&location, &config.sftp)?.mirror(&cache_dir)?;
+    if quiet == 0 {
+        info!(
+            "Mirrored {} to {:?}: {} downloaded ({} bytes), {} unchanged, {} removed",
+            location, cache_dir, stats.downloaded, stats.bytes, stats.unchanged, stats.removed
+        );
+    }
+    Ok(cache_dir)
+}
+
+/// Resolve the game path (see [`resolve_game_path`]) and check which of the
+/// expected files it has
+fn discover_game(game_path: std::path::PathBuf, config: &DemonaxConfig, quiet: u8) -> Result<GamePaths> {
+    let mut game = GamePaths::discover(resolve_game_path(game_path, config, quiet)?)?;
+    game.exclude = config.exclude.clone();
+    game.raids = config.raids.clone();
+    game.bestiary = config.bestiary.clone();
+    game.images = config.images.clone();
+    game.regions = config.regions.clone();
+    game.towns = config.towns.clone();
+    if quiet == 0 && !game.missing().is_empty() {
+        let missing: Vec<String> = game.missing().iter().map(ToString::to_string).collect();
+        info!("Not found in game directory {:?}: {}", game.root, missing.join(", "));
+    }
+    Ok(game)
+}
+
+/// Upload an `s3://` output after the command has written it to the staging directory
+fn publish_output(target: &OutputTarget, quiet: u8) -> Result<()> {
+    let OutputTarget::S3 { location, .. } = target else {
+        return Ok(());
+    };
+
+    let _publish = tracing::info_span!("s3_publish").entered();
+    let stats = target.publish()?;
+    if quiet == 0 {
+        info!(
+            "Published to {}: {} uploaded ({} bytes), {} unchanged",
+            location, stats.uploaded, stats.bytes, stats.unchanged
+        );
+    }
+    Ok(())
+}
+
+/// Prints the `--timing` breakdown when the command ends, including on errors
+struct TimingReport(Option<Timings>);
+
+impl Drop for TimingReport {
+    fn drop(&mut self) {
+        if let Some(timings) = &self.0 {
+            eprint!("\n{}", timings.report());
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    let timings = cli.timing.then(Timings::new);
+    let _guard = setup_logging(cli.verbose, &cli.log_file, cli.log_format, timings.as_ref())?;
+    // Declared after the report so the span closes before it is printed
+    let _timing_report = TimingReport(timings.clone());
+    let command_name = matches.subcommand_name().unwrap_or_default();
+    let _command = tracing::info_span!("command", command = command_name).entered();
+    let arguments: Vec<_> = std::env::args_os().skip(1).map(|arg| arg.to_string_lossy().into_owned()).collect();
+    let invocation = Invocation::new(command_name, arguments.join(" "));
+
+    info!("Starting demonax CLI");
+
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads.max(1)).build_global()?;
+    }
+    if let Some(files_per_second) = cli.io_throttle {
+        demonax_core::file_utils::set_read_rate_limit(files_per_second)?;
+    }
+    if let Some(path) = &cli.spell_rules {
+        set_spell_rules(SpellRuleset::from_file(path)?)?;
+    }
+    let mut config = DemonaxConfig::load(cli.config.as_deref())?;
+    if cli.include_excluded {
+        config.exclude = ExcludeConfig::none();
+    }
+    let default_db = std::path::PathBuf::from("./demonax.sqlite");
+    confirm_destructive(&cli.command, cli.database.as_ref().unwrap_or(&default_db), cli.yes)?;
+    let hooks_db = cli.database.clone().unwrap_or(default_db);
+    let hooks = config.hooks.clone();
+
+    // TODO: Implement command dispatch
+    match cli.command {
+        Commands::ProcessUsr { input_dir, snapshot_date, overwrite_existing, player_glob, player_ids, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            let snapshot_date = if snapshot_date == "auto" {
+                let (date, source) = infer_snapshot_date(&input_dir)?;
+                let date = date.format("%Y-%m-%d").to_string();
+                if !overwrite_existing {
+                    db.validate_new_snapshot_date(&date, source == SnapshotDateSource::DirectoryName)?;
+                }
+                if quiet == 0 {
+                    info!("Using snapshot date {} (inferred from {:?})", date, source);
+                }
+                date
+            } else {
+                snapshot_date
+            };
+
+            let options = UsrIngestOptions {
+                existing: if overwrite_existing { ExistingSnapshots::Overwrite } else { ExistingSnapshots::Skip },
+                players: PlayerSelection::new(player_glob.as_deref(), player_ids.as_deref())?,
+            };
+            let processed = match SftpLocation::parse(&input_dir)? {
+                Some(location) => db.process_usr_source(
+                    &InputSource::Sftp(location, config.sftp.clone()),
+                    &snapshot_date,
+                    quiet,
+                    &options,
+                )?,
+                None => db.process_usr_files_with(&input_dir, &snapshot_date, quiet, &options)?,
+            };
+            info!("Successfully processed {} .usr files", processed);
+        }
+        Commands::ProcessUsrBatch { root, pattern, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            let (total, folders) = process_usr_batch(&db, &root, &pattern, &Reporter::new(quiet))?;
+            info!("Successfully processed {} .usr files from {} folders", total, folders);
+        }
+        Commands::UpdateCreatures { game_path, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+            let processed = process_creatures(&db, &game, &Reporter::new(quiet))?;
+            info!("Successfully processed {} .mon files", processed);
+        }
+        Commands::UpdateItemsCore { game_path, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            if quiet == 0 {
+                info!("Processing item data from {:?}", game.root);
+            }
+            process_items_core(&db, &game, &Reporter::new(quiet))?;
+            if quiet == 0 {
+                info!("Item processing complete. Data stored in database: {:?}", db_path);
+            }
+        }
+        Commands::UpdateItemsQuests { game_path: _, skip_regions, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            if quiet == 0 {
+                info!("Updating items with quest reward information");
+            }
+            let updated_count = process_items_quests(&db, &skip_regions, &Reporter::new(quiet))?;
+            if quiet == 0 {
+                info!("Successfully updated {} items with quest rewards", updated_count);
+                info!("Items table now includes 'rewarded_from' column with quest names");
+            }
+        }
+        Commands::UpdateQuestOverview { game_path, quest_csv, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            if quiet == 0 {
+                info!("Processing quest overview from map files in {:?}", game.root);
+            }
+            let processed = process_quest_overview(&db, &game, quest_csv.as_deref(), &Reporter::new(quiet))?;
+            if quiet == 0 {
+                info!("Successfully processed {} quests into database: {:?}", processed, db_path);
+            }
+        }
+        Commands::Update { parser, game_path, list, quiet } => {
+            let registry = ParserRegistry::with_defaults();
+
+            if list {
+                for processor in registry.iter() {
+                    println!("{:<12} {}", processor.name(), processor.source());
+                }
+                return Ok(());
+            }
+
+            let parser = parser.expect("clap requires parser unless --list");
+            let Some(game_path) = game_path else {
+                anyhow::bail!("--game-path (or DEMONAX_GAME_DIR) is required");
+            };
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            let written = ParserRegistry::for_game(&game).process(&parser, &game.root, &db, quiet)?;
+            process_towns(&db, &game, &Reporter::new(quiet))?;
+            if quiet == 0 {
+                info!("Parser '{}' wrote {} rows to database: {:?}", parser, written, db_path);
+            }
+        }
+        Commands::UpdateAll { game_path, snapshot_date, batch_size, restart, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            update_all(&db, &game, snapshot_date.as_deref(), batch_size, restart, &Reporter::new(quiet))?;
+            info!("All stages completed. Data stored in database: {:?}", db_path);
+        }
+        Commands::Export { kind, output, anonymize, anonymize_salt, include_disabled, spots, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let anonymizer = anonymize.then(|| Anonymizer::new(anonymize_salt.as_deref().unwrap_or_default()));
+            let target = OutputTarget::new(&output, &config.s3)?;
+            let path = target.local_path();
+            let written = match kind {
+                ExportKind::Houses => export_house_list(&db, path, anonymizer.as_ref())?,
+                ExportKind::Items => export_item_list(&db, path, anonymizer.as_ref())?,
+                ExportKind::Snapshots => export_snapshot_list(&db, path, anonymizer.as_ref())?,
+                ExportKind::WorldStats => export_world_stats(&db, path, anonymizer.as_ref())?,
+                ExportKind::Events => export_events(&db, path, anonymizer.as_ref())?,
+                ExportKind::LogEvents => export_log_events(&db, path, anonymizer.as_ref())?,
+                ExportKind::Online => export_online_days(&db, path, anonymizer.as_ref())?,
+                ExportKind::CreatureLoot => export_creature_loot(&db, path, anonymizer.as_ref())?,
+                ExportKind::Raids => export_raid_list(&db, path, include_disabled, anonymizer.as_ref())?,
+                ExportKind::QuestRoutes => export_quest_routes(&db, path, anonymizer.as_ref())?,
+                ExportKind::Bestiary => export_bestiary(&db, path, anonymizer.as_ref())?,
+                ExportKind::Harvesting => export_harvesting_rules(&db, path, anonymizer.as_ref())?,
+                ExportKind::Hunting => {
+                    let Some(spots) = spots else {
+                        anyhow::bail!("--spots is required for the hunting export");
+                    };
+                    export_hunting_spots(&db, &HuntingOverlay::from_file(&spots)?, path, anonymizer.as_ref())?
+                }
+            };
+            if quiet == 0 {
+                info!("Exported {} records to {:?}", written, output);
+            }
+            publish_output(&target, quiet)?;
+        }
+        Commands::UpdateRaids { game_path, raid_log, quiet } => {
+            let mut game = discover_game(game_path, &config, quiet)?;
+            if let Some(raid_log) = raid_log {
+                game.raids.log = Some(std::path::absolute(raid_log)?);
+            }
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
+            if quiet == 0 {
+                info!("Processing raid data from .evt files");
+            }
+            let inserted = process_raids(&db, &game, &Reporter::new(quiet))?;
             if quiet == 0 {
                 info!("Inserted/updated {} raids in database: {:?}", inserted, db_path);
                 info!("Note: Creature names can be enriched by querying creatures table");
             }
         }
         Commands::UpdateHarvesting { game_path, harvesting_csv, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
             let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
             let db = Database::new(&db_path)?;
 
             if quiet == 0 {
                 info!("Processing harvesting data");
             }
+            process_harvesting(&db, &game, harvesting_csv.as_deref(), &Reporter::new(quiet))?;
 
-            // Use custom path if provided, otherwise search common locations
-            let harvesting_csv_path = if let Some(custom_path) = harvesting_csv {
-                if custom_path.exists() {
-                    Some(custom_path)
-                } else {
-                    anyhow::bail!("Custom harvesting CSV path does not exist: {:?}", custom_path);
+            // Note: The R implementation also writes to moveuse.dat file
+            // For now, we store in database only. moveuse.dat generation
+            // can be added as a separate command if needed.
+            if quiet == 0 {
+                info!("Harvesting data stored in database: {:?}", db_path);
+                info!("Note: moveuse.dat file generation not implemented (data in DB only)");
+            }
+        }
+        Commands::UpdateSpells { game_path, magic_cc, rune_overrides, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            if quiet == 0 {
+                info!("Processing spell data");
+            }
+            process_spells(&db, &game, magic_cc.as_deref(), rune_overrides.as_deref(), &Reporter::new(quiet))?;
+            if quiet == 0 {
+                info!("Spell processing complete. Data stored in database: {:?}", db_path);
+            }
+        }
+        Commands::UpdateMoveUseHarvesting { csv_path, moveuse_path } => {
+            info!("Updating moveuse.dat with harvesting rules");
+
+            // Check paths exist
+            if !csv_path.exists() {
+                anyhow::bail!("CSV file not found: {:?}", csv_path);
+            }
+            if !moveuse_path.exists() {
+                anyhow::bail!("moveuse.dat not found: {:?}", moveuse_path);
+            }
+
+            // Read and parse CSV
+            info!("Reading harvesting data from {:?}", csv_path);
+            let mut reader = csv::Reader::from_path(&csv_path)?;
+            let mut harvesting_data = Vec::new();
+
+            for result in reader.deserialize() {
+                let record: HarvestingData = result?;
+                harvesting_data.push(record);
+            }
+
+            info!("Parsed {} harvesting entries from CSV", harvesting_data.len());
+
+            // Generate rules
+            let rules = generate_all_harvesting_rules(&harvesting_data);
+            info!("Generated {} rule pairs ({} lines)", harvesting_data.len(), rules.lines().count());
+
+            // Read moveuse.dat
+            let moveuse_content = std::fs::read_to_string(&moveuse_path)?;
+
+            // Insert rules
+            let updated_content = insert_harvesting_rules(&moveuse_content, &rules)?;
+
+            // Write back
+            std::fs::write(&moveuse_path, updated_content)?;
+
+            info!("Successfully updated {:?} with harvesting rules", moveuse_path);
+        }
+        Commands::GivePresent {
+            usr_path,
+            present_config,
+            winners_csv,
+            target_slot,
+            skip_over_capacity,
+            dry_run,
+            per_account,
+            only_players,
+            show_diff,
+            quiet,
+            format,
+        } => {
+            let source = match (&winners_csv, &present_config) {
+                (Some(winners_csv), _) => PresentSource::WinnersCsv(winners_csv),
+                (None, Some(present_config)) => PresentSource::Config(present_config),
+                (None, None) => anyhow::bail!("--present-config or --winners-csv is required"),
+            };
+            let reporter = Reporter::new(quiet);
+            // Container capacities and weights come from the items in the database, when there is one
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let catalog = item_catalog_or_empty(&db_path, &reporter)?;
+            let options = GiftOptions {
+                target_slot,
+                skip_over_capacity,
+                dry_run,
+                per_account,
+                only_players: PlayerFilter::only(&only_players),
+            };
+            let run = give_presents(&usr_path, source, &catalog, &options, &reporter)?;
+
+            if let Some(sample) = show_diff {
+                for (result, diff) in run.results.iter().filter(|(_, diff)| !diff.is_empty()).take(sample) {
+                    println!("--- {}", result.player_name());
+                    for line in diff {
+                        println!("{}", line);
+                    }
                 }
+            }
+            if let ReportFormat::Json = format {
+                let results: Vec<&GiftResult> = run.results.iter().map(|(result, _)| result).collect();
+                let json = serde_json::json!({ "results": results, "summary": run.summary, "dry_run": dry_run });
+                println!("{}", serde_json::to_string_pretty(&json)?);
+            }
+        }
+        Commands::PresentPreview { config, samples, seed, format } => {
+            let present = PresentConfig::from_file(&config)
+                .map_err(|e| anyhow::anyhow!("Failed to load present config: {}", e))?;
+
+            // Item names come from the database, when there is one
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let catalog = if db_path.exists() {
+                Database::open_read_only(&db_path)?.get_item_catalog()?
             } else {
-                let possible_paths = vec![
-                    game_path.join("harvesting.csv"),
-                    game_path.join("dat/harvesting.csv"),
-                    std::path::PathBuf::from("./harvesting.csv"),
-                ];
-                possible_paths.iter().find(|p| p.exists()).cloned()
+                ItemCatalog::new()
             };
+            present
+                .validate(&catalog)
+                .map_err(|e| anyhow::anyhow!("Present does not fit its container: {}", e))?;
+
+            let preview = preview_present(&present, samples, seed);
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&preview)?),
+                ReportFormat::Table => {
+                    println!(
+                        "{} presents: {} fixed items, {} pools",
+                        preview.samples,
+                        present.items.len(),
+                        present.pools.len()
+                    );
+                    let mut table = Table::new(&["Item", "Name", "Presents", "Chance %", "Count", "Expected", "Amount"]);
+                    for item in &preview.items {
+                        let chance = if preview.samples == 0 { 0.0 } else { 100.0 * item.presents as f64 / preview.samples as f64 };
+                        table.row(vec![
+                            item.type_id.into(),
+                            catalog.name(item.type_id).unwrap_or("?").into(),
+                            (item.presents as i64).into(),
+                            Cell::Real(chance, 2),
+                            (item.count as i64).into(),
+                            Cell::Real(item.expected_count, 1),
+                            (item.amount as i64).into(),
+                        ]);
+                    }
+                    table.print(&TableArgs::default())?;
+                }
+            }
+        }
+        Commands::RenderEquipment {
+            data_dir,
+            output_dir,
+            template,
+            blank,
+            player_id,
+            player_glob,
+            player_ids,
+            no_counts,
+            image_cache,
+            image,
+            quiet,
+        } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-            if let Some(csv_path) = harvesting_csv_path {
-                if quiet == 0 {
-                    info!("Reading harvesting data from {:?}", csv_path);
+            let target = OutputTarget::new(&output_dir, &config.s3)?;
+            let config = RenderConfig {
+                data_dir,
+                output_dir: target.local_path().to_path_buf(),
+                template_path: template,
+                blank_path: blank,
+                show_counts: !no_counts,
+                image: image.output(),
+                catalog: ItemCatalog::new(),
+            };
+            let players = PlayerSelection::new(player_glob.as_deref(), player_ids.as_deref())?;
+            render_equipment(&db, config, player_id, &players, image_cache, &Reporter::new(quiet))?;
+            publish_output(&target, quiet)?;
+        }
+        Commands::RenderPlayerCard { player_id, equipment_dir, output_dir, background, image, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            if db.is_player_hidden(player_id)? {
+                anyhow::bail!("Player {} is hidden from the website", player_id);
+            }
+            let Some(card) = db.get_player_card(player_id)? else {
+                anyhow::bail!("No snapshots found for player {}", player_id);
+            };
+
+            let target = OutputTarget::new(&output_dir, &config.s3)?;
+            let config = CardConfig {
+                equipment_dir,
+                output_dir: target.local_path().to_path_buf(),
+                background_path: background,
+                image: image.output(),
+            };
+            let output_path = render_player_card(&card, &config)?;
+            if quiet == 0 {
+                info!("Rendered player card for {} to {:?}", card.player_name, output_path);
+                if !card.missing_spells.is_empty() {
+                    info!("{} can learn: {}", card.player_name, card.missing_spells.join(", "));
+                }
+            }
+            publish_output(&target, quiet)?;
+        }
+        Commands::RenderSpawnHeatmap { output_dir, creature, min_level, max_level, cell_size, geojson, image, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let filter = HeatmapFilter { creatures: creature, min_level, max_level };
+            let format = if geojson { HeatmapFormat::GeoJson } else { HeatmapFormat::Image(image.output()) };
+            let target = OutputTarget::new(&output_dir, &config.s3)?;
+            let floors = render_spawn_heatmap(&db, &filter, cell_size, target.local_path(), format)?;
+            if floors.is_empty() {
+                tracing::warn!("No spawns match; run update-creatures and `update spawns` first or widen the filter");
+            } else if quiet == 0 {
+                for floor in &floors {
+                    info!("Floor {}: {} (densest cell {} creatures)", floor.floor, floor.file, floor.max_density);
                 }
+                info!("Wrote {} floor heatmaps to {:?}", floors.len(), output_dir);
+            }
+            publish_output(&target, quiet)?;
+        }
+        Commands::SimulateLoot { creature, kills, seed } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-                // Read harvesting CSV
-                let mut reader = csv::Reader::from_path(&csv_path)?;
-                let mut harvesting_data = Vec::new();
+            let Some((name, loot)) = db.get_creature_loot(&creature)? else {
+                anyhow::bail!("Creature '{}' not found (run update-creatures first)", creature);
+            };
+            let catalog = db.get_item_catalog()?;
+            let sim = simulate_loot(&loot, &catalog, kills, seed);
+
+            println!("{} - {} kills", name, sim.kills);
+            println!("{:<6} {:<28} {:>8} {:>10} {:>21} {:>8}", "Item", "Name", "Drops", "Per kill", "95% CI", "Value");
+            for item in &sim.items {
+                let item_name = catalog.name(item.item_id).unwrap_or("?");
+                let value = item.unit_value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+                println!(
+                    "{:<6} {:<28} {:>8} {:>10.3} {:>10.3}-{:<10.3} {:>8}",
+                    item.item_id, item_name, item.drops, item.mean_per_kill, item.ci_low, item.ci_high, value
+                );
+            }
+            println!(
+                "Gold per kill: {:.1} (95% CI {:.1}-{:.1})",
+                sim.value_per_kill, sim.value_ci_low, sim.value_ci_high
+            );
+        }
+        Commands::CombatCalc { creature, player_id, level, vocation, shielding, armor, shield_defense, blessings } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let player = CombatPlayer { player_id, level, vocation, shielding, armor, shield_defense, blessings };
+            print!("{}", combat_calc(&db, &creature, &player)?);
+        }
+        Commands::DeathCalc { player, blessings, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-                for result in reader.deserialize() {
-                    let record: HarvestingData = result?;
-                    harvesting_data.push(record);
+            let Some(player_id) = db.find_player_id(&player)? else {
+                anyhow::bail!("Player '{}' not found (run process-usr first)", player);
+            };
+            let (Some(card), Some((skills, equipment))) =
+                (db.get_player_card(player_id)?, db.get_latest_skills_and_equipment(player_id)?)
+            else {
+                anyhow::bail!("No snapshots found for player {}", player);
+            };
+            let promoted = is_promoted(&card.vocation);
+            let blessings = blessings.or(card.blessings).unwrap_or(0).clamp(0, MAX_BLESSINGS);
+            let loss = death_loss(card.level, card.experience, promoted, blessings, &skills, &equipment);
+
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&loss)?),
+                ReportFormat::Table => {
+                    println!(
+                        "{} (level {} {}, {} of {} blessings, {})",
+                        card.player_name, card.level, card.vocation, blessings, MAX_BLESSINGS, card.snapshot_date
+                    );
+                    println!(
+                        "Experience: {} of {} lost ({:.1}%), level {} -> {}",
+                        loss.experience_lost, card.experience, loss.loss_fraction * 100.0, loss.level_before, loss.level_after
+                    );
+                    if !loss.skills.is_empty() {
+                        println!("{:<12} {:>6} {:>12} {:>12}", "Skill", "Value", "Tries", "Tries lost");
+                        for skill in &loss.skills {
+                            println!("{:<12} {:>6} {:>12} {:>12}", skill.name, skill.value, skill.tries, skill.tries_lost);
+                        }
+                    }
+                    println!(
+                        "Items: {:.0}% chance per equipped item ({:.1} expected), backpack {:.0}%",
+                        loss.item_loss_chance * 100.0, loss.expected_items_lost, loss.container_loss_chance * 100.0
+                    );
+                }
+            }
+        }
+        Commands::LevelCalc { player, level, target_level, exp_per_hour, days, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let request = LevelCalcRequest { player, level, target_level, exp_per_hour, days };
+            let report = level_calc(&db_path, &request, &config.experience)?;
+            let plan = &report.plan;
+
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                ReportFormat::Table => {
+                    println!(
+                        "{} (level {}, {} experience) to level {}: {} experience, {:.0} before rates",
+                        report.name, plan.level, plan.experience, plan.target_level, plan.experience_needed, plan.base_experience_needed
+                    );
+                    let mut table = Table::new(&["Levels", "Rate", "Experience", "Base experience", "Hours"]);
+                    for stage in &plan.stages {
+                        table.row(vec![
+                            format!("{}-{}", stage.from_level, stage.to_level).into(),
+                            Cell::Real(stage.rate, 2),
+                            stage.experience.into(),
+                            Cell::Real(stage.base_experience, 0),
+                            Cell::optional(stage.hours.map(|hours| Cell::Real(hours, 1))),
+                        ]);
+                    }
+                    table.print(&TableArgs::default())?;
+                    if let Some(hours) = plan.hours {
+                        println!("Time at {} base experience per hour: {:.1} hours", exp_per_hour.unwrap_or_default(), hours);
+                    }
+                    match (report.base_experience_per_day, report.days) {
+                        (Some(per_day), Some(days_needed)) => println!(
+                            "At the pace of the last {} days ({:.0} base experience per day): {:.1} days",
+                            days, per_day, days_needed
+                        ),
+                        _ if request.player.is_some() => println!("No experience gained in the last {} days of snapshots", days),
+                        _ => {}
+                    }
                 }
+            }
+        }
+        Commands::AddEvent { kind, name, start, end, description } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            let end = end.unwrap_or_else(|| start.clone());
+            let id = db.add_event(kind.into(), &name, &start, &end, description.as_deref())?;
+            info!("Added event {} ({} to {})", id, start, end);
+        }
+        Commands::RemoveEvent { id } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+
+            if !db.remove_event(id)? {
+                anyhow::bail!("Event {} not found", id);
+            }
+            info!("Removed event {}", id);
+        }
+        Commands::ProcessLog { log, follow, poll_seconds, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
-                if quiet == 0 {
-                    info!("Parsed {} harvesting entries from CSV", harvesting_data.len());
+            loop {
+                let read = tail_log(&db, &log)?;
+                if read.rotated && quiet < 2 {
+                    tracing::warn!("{:?} is shorter than before and was read from the start", log);
                 }
-
-                // Insert into database
-                let inserted = db.insert_harvesting_data(&harvesting_data)?;
-
-                if quiet == 0 {
-                    info!("Inserted {} harvesting entries into database", inserted);
+                if quiet == 0 && (read.events > 0 || !follow) {
+                    info!("Stored {} log events from {} bytes of {:?}", read.events, read.bytes_read, log);
                 }
-
-                // Note: The R implementation also writes to moveuse.dat file
-                // For now, we store in database only. moveuse.dat generation
-                // can be added as a separate command if needed.
-                if quiet == 0 {
-                    info!("Harvesting data stored in database: {:?}", db_path);
-                    info!("Note: moveuse.dat file generation not implemented (data in DB only)");
+                if !follow {
+                    break;
                 }
-            } else {
-                anyhow::bail!("harvesting.csv not found in any standard location");
+                std::thread::sleep(std::time::Duration::from_secs(poll_seconds.max(1)));
             }
         }
-        Commands::UpdateSpells { game_path, magic_cc, quiet } => {
+        Commands::SampleOnline { source, every, quiet } => {
             let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
             let db = Database::new(&db_path)?;
 
-            if quiet == 0 {
-                info!("Processing spell data");
-            }
-
-            // Use custom path if provided, otherwise search common locations
-            let magic_cc_path = if let Some(custom_path) = magic_cc {
-                if custom_path.exists() {
-                    Some(custom_path)
-                } else {
-                    if quiet < 2 {
-                        tracing::warn!("Custom magic.cc path does not exist: {:?}", custom_path);
+            loop {
+                let sampled_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                match sample_online(&source) {
+                    Ok(count) => {
+                        db.insert_online_sample(&sampled_at, &count, &source.to_string())?;
+                        if quiet == 0 {
+                            info!("{} players online at {}", count.players, sampled_at);
+                        }
                     }
-                    None
+                    // A server that is down for a restart shouldn't end the sampling
+                    Err(e) if every.is_some() => {
+                        if quiet < 2 {
+                            tracing::warn!("Failed to sample {}: {}", source, e);
+                        }
+                    }
+                    Err(e) => return Err(e.into()),
                 }
-            } else {
-                let possible_paths = vec![
-                    game_path.join("src/magic.cc"),
-                    game_path.join("magic.cc"),
-                    game_path.parent().and_then(|p| Some(p.join("src/magic.cc"))).unwrap_or_default(),
-                ];
-                possible_paths.iter().find(|p| p.exists()).cloned()
-            };
+                let Some(minutes) = every else {
+                    break;
+                };
+                std::thread::sleep(std::time::Duration::from_secs(minutes.max(1) * 60));
+            }
+        }
+        Commands::ServerStatus { address, store, format } => {
+            let sampled_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let status = query_server_status(&address)?;
+            if store {
+                let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+                let db = Database::new(&db_path)?;
+                let source = OnlineSource::StatusPort(address.clone());
+                db.insert_online_sample(&sampled_at, &status.online_count(), &source.to_string())?;
+            }
 
-            let spells = if let Some(magic_path) = magic_cc_path {
-                if quiet == 0 {
-                    info!("Parsing magic.cc from {:?}", magic_path);
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+                ReportFormat::Table => {
+                    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+                    println!("Server:   {}", optional(status.server_name.clone()));
+                    println!("Version:  {}", optional(status.version.clone()));
+                    println!("Client:   {}", optional(status.client_version.clone()));
+                    println!(
+                        "Uptime:   {}",
+                        optional(status.uptime_seconds.map(|s| format!("{}d {}h {}m", s / 86400, s % 86400 / 3600, s % 3600 / 60)))
+                    );
+                    println!(
+                        "Players:  {} online of {} (peak {})",
+                        status.players_online,
+                        optional(status.max_players.map(|max| max.to_string())),
+                        optional(status.peak_players.map(|peak| peak.to_string()))
+                    );
+                    println!("Monsters: {}", optional(status.monsters.map(|total| total.to_string())));
+                    println!("Map:      {}", optional(status.map_name.clone()));
+                    println!("MOTD:     {}", optional(status.motd.clone()));
                 }
-                parse_magic_cc(&magic_path)?
-            } else {
-                if quiet < 2 {
-                    tracing::warn!("magic.cc not found, skipping spell parsing");
-                    tracing::warn!("Note: Spell data requires access to game source code");
+            }
+        }
+        Commands::Events { table, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let events = db.get_events()?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&events)?),
+                ReportFormat::Table if events.is_empty() => println!("No events"),
+                ReportFormat::Table => {
+                    let mut output = Table::new(&["ID", "Kind", "Name", "Start", "End", "Description"]);
+                    for event in &events {
+                        output.row(vec![
+                            event.id.into(),
+                            event.kind.as_str().into(),
+                            event.name.as_str().into(),
+                            event.start_date.as_str().into(),
+                            event.end_date.as_str().into(),
+                            Cell::optional(event.description.as_deref()),
+                        ]);
+                    }
+                    output.print(&table)?;
                 }
-                vec![]
-            };
-
-            if !spells.is_empty() {
-                let inserted = db.insert_or_update_spells(&spells)?;
-                if quiet == 0 {
-                    info!("Inserted/updated {} spells", inserted);
+            }
+        }
+        Commands::Raids { season, table, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let raids = db.get_raid_list(season.as_deref(), true)?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&raids)?),
+                ReportFormat::Table => {
+                    if raids.is_empty() {
+                        println!("No matching raids");
+                        return Ok(());
+                    }
+                    let mut output = Table::new(&["Name", "Type", "Interval days", "Season", "Town", "Enabled", "Creatures"]);
+                    for raid in &raids {
+                        output.row(vec![
+                            raid.name.as_str().into(),
+                            raid.raid_type.as_str().into(),
+                            Cell::optional(raid.interval_days.map(|days| Cell::Real(days, 1))),
+                            Cell::optional(raid.season.as_deref()),
+                            Cell::optional(raid.town.as_deref()),
+                            raid.enabled.into(),
+                            raid.creatures.as_str().into(),
+                        ]);
+                    }
+                    output.print(&table)?;
                 }
             }
-
-            // Parse .npc files for spell teaching
-            let npc_dir = game_path.join("npc");
-            if npc_dir.exists() {
-                if quiet == 0 {
-                    info!("Parsing .npc files for spell teaching data");
-                }
-
-                let npc_files = find_files_with_extension(&npc_dir, "npc")?;
-                let all_teachers: Vec<_> = npc_files
-                    .par_iter()
-                    .filter_map(|path| {
-                        match parse_npc_spell_teaching(path) {
-                            Ok(teachers) if !teachers.is_empty() => Some(teachers),
-                            Ok(_) => None,
-                            Err(e) => {
-                                if quiet < 2 {
-                                    tracing::warn!("Failed to parse {:?}: {}", path, e);
-                                }
-                                None
-                            }
-                        }
-                    })
-                    .flatten()
-                    .collect();
-
-                if quiet == 0 {
-                    info!("Found {} spell teaching entries", all_teachers.len());
+        }
+        Commands::RaidActivity { table, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let activity = db.get_raid_activity()?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&activity)?),
+                ReportFormat::Table if activity.is_empty() => println!("No raids"),
+                ReportFormat::Table => {
+                    let mut output = Table::new(&[
+                        "Name",
+                        "Interval days",
+                        "Seen",
+                        "Observed days",
+                        "First seen",
+                        "Last seen",
+                        "Expected next",
+                    ]);
+                    for raid in &activity {
+                        output.row(vec![
+                            raid.name.as_str().into(),
+                            Cell::optional(raid.interval_days.map(|days| Cell::Real(days, 1))),
+                            raid.times_seen.into(),
+                            Cell::optional(raid.observed_interval_days.map(|days| Cell::Real(days, 1))),
+                            Cell::optional(raid.first_seen.as_deref()),
+                            Cell::optional(raid.last_seen.as_deref()),
+                            Cell::optional(raid.expected_next.as_deref()),
+                        ]);
+                    }
+                    output.print(&table)?;
                 }
+            }
+        }
+        Commands::RaidPreview { game_path, name } => {
+            let evt_path = game_path.join("mon").join(format!("{}.evt", name));
+            if !evt_path.exists() {
+                anyhow::bail!("Raid file not found at {:?}", evt_path);
+            }
+            let raid = parse_evt_file(&evt_path)?;
+            let steps = parse_evt_steps(&read_latin1_file(&evt_path)?)?;
 
-                let teacher_count = db.clear_and_insert_spell_teachers(&all_teachers)?;
-                if quiet == 0 {
-                    info!("Processed {} spell teachers", teacher_count);
+            // Creature names are optional: without a database spawns show race numbers
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let creature_names = match Database::open_read_only(&db_path).and_then(|db| db.get_creature_names_by_race()) {
+                Ok(names) => names,
+                Err(e) => {
+                    tracing::warn!("Creature names unavailable ({}), showing race numbers", e);
+                    Default::default()
                 }
+            };
 
-                // Parse rune/wand/rod sellers
-                if quiet == 0 {
-                    info!("Parsing .npc files for rune/wand/rod seller data");
-                }
-
-                let all_sellers: Vec<_> = npc_files
-                    .par_iter()
-                    .filter_map(|path| {
-                        match parse_npc_rune_selling(path) {
-                            Ok(sellers) if !sellers.is_empty() => Some(sellers),
-                            Ok(_) => None,
-                            Err(e) => {
-                                if quiet < 2 {
-                                    tracing::warn!("Failed to parse rune sellers from {:?}: {}", path, e);
-                                }
-                                None
-                            }
-                        }
-                    })
-                    .flatten()
-                    .collect();
-
-                if quiet == 0 {
-                    info!("Found {} rune/wand/rod seller entries", all_sellers.len());
+            let interval = raid
+                .interval_days
+                .map(|days| format!(", every {:.1} days", days))
+                .unwrap_or_default();
+            println!("{} ({}, waves: {}{})", raid.name, raid.raid_type, raid.waves, interval);
+            for step in &steps {
+                let time = format!("+{}:{:02}", step.delay_seconds / 60, step.delay_seconds % 60);
+                if let Some(message) = &step.message {
+                    println!("  {:>8}  Message  \"{}\"", time, message);
                 }
-
-                let seller_count = db.clear_and_insert_rune_sellers(&all_sellers)?;
-                if quiet == 0 {
-                    info!("Processed {} rune/wand/rod sellers", seller_count);
+                if let Some(race) = step.race {
+                    let creature = creature_names
+                        .get(&race)
+                        .cloned()
+                        .unwrap_or_else(|| format!("Race {}", race));
+                    let count = match step.count {
+                        Some((min, max)) if min == max => min.to_string(),
+                        Some((min, max)) => format!("{}-{}", min, max),
+                        None => "?".to_string(),
+                    };
+                    let position = step
+                        .position
+                        .map(|(x, y, z)| format!(" at [{},{},{}]", x, y, z))
+                        .unwrap_or_default();
+                    let spread = step.spread.map(|s| format!(" (spread {})", s)).unwrap_or_default();
+                    println!("  {:>8}  Spawn    {} {}{}{}", time, count, creature, position, spread);
                 }
             }
+        }
+        Commands::GenerateRaid { spec, output, game_path, force, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-            // Log untaught spells
-            if !spells.is_empty() && quiet == 0 {
-                let untaught_spells = db.get_untaught_spells()?;
-
-                if !untaught_spells.is_empty() {
-                    info!("Found {} spells without teachers:", untaught_spells.len());
-                    for spell in untaught_spells {
-                        info!(
-                            "  - {} (ID: {}): {} - Level {}, {} mana",
-                            spell.name,
-                            spell.spell_id,
-                            spell.words,
-                            spell.level,
-                            spell.mana
-                        );
-                    }
-                }
-
-                // Log unsold runes
-                let unsold_runes = db.get_unsold_runes()?;
+            if output.exists() && !force {
+                anyhow::bail!("Output file {:?} already exists (use --force to overwrite)", output);
+            }
 
-                if !unsold_runes.is_empty() {
-                    info!("Found {} runes without sellers:", unsold_runes.len());
-                    for rune in unsold_runes {
-                        info!(
-                            "  - {} (ID: {}): {} - Rune Type ID: {:?}",
-                            rune.name,
-                            rune.spell_id,
-                            rune.words,
-                            rune.rune_type_id
-                        );
+            let raid = RaidSpec::from_file(&spec)?;
+            let sectors = match game_path.map(|path| path.join("map")).filter(|dir| dir.exists()) {
+                Some(map_dir) => Some(map_sectors(&map_dir)?),
+                None => {
+                    if quiet < 2 {
+                        tracing::warn!("No map directory given, spawn areas are only checked against floor bounds");
                     }
+                    None
                 }
-            }
+            };
+            let creatures = db.get_creature_catalog()?;
+            let steps = raid.to_steps(|creature| Ok(creatures.find(creature).map(|c| c.race)), sectors.as_ref())?;
 
+            std::fs::write(&output, evt_text(&raid.raid_type, raid.interval, raid.waves.len(), &steps))?;
             if quiet == 0 {
-                info!("Spell processing complete. Data stored in database: {:?}", db_path);
+                info!("Wrote raid with {} waves ({} steps) to {:?}", raid.waves.len(), steps.len(), output);
             }
         }
-        Commands::UpdateMoveUseHarvesting { csv_path, moveuse_path } => {
-            info!("Updating moveuse.dat with harvesting rules");
-
-            // Check paths exist
-            if !csv_path.exists() {
-                anyhow::bail!("CSV file not found: {:?}", csv_path);
-            }
-            if !moveuse_path.exists() {
-                anyhow::bail!("moveuse.dat not found: {:?}", moveuse_path);
+        Commands::EditCreature { game_path, creature, set, dry_run, quiet } => {
+            let edits = set.iter().map(|s| s.parse::<CreatureEdit>()).collect::<Result<Vec<_>, _>>()?;
+            let files = find_mon_files(&game_path.join("mon"), &creature)?;
+            if files.is_empty() {
+                anyhow::bail!("No creatures match {:?}", creature);
             }
 
-            // Read and parse CSV
-            info!("Reading harvesting data from {:?}", csv_path);
-            let mut reader = csv::Reader::from_path(&csv_path)?;
-            let mut harvesting_data = Vec::new();
+            let mut changed = 0;
+            for path in &files {
+                let original = MonFile::from_file(path)?;
+                let mut mon = original.clone();
+                for edit in &edits {
+                    edit.apply(&mut mon).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))?;
+                }
 
-            for result in reader.deserialize() {
-                let record: HarvestingData = result?;
-                harvesting_data.push(record);
+                let diff = diff_lines(&original.to_string(), &mon.to_string());
+                if diff.is_empty() {
+                    continue;
+                }
+                changed += 1;
+                if dry_run || quiet == 0 {
+                    println!("--- {}", path.display());
+                    for line in &diff {
+                        println!("{}", line);
+                    }
+                }
+                if !dry_run {
+                    mon.write(path)?;
+                }
             }
 
-            info!("Parsed {} harvesting entries from CSV", harvesting_data.len());
-
-            // Generate rules
-            let rules = generate_all_harvesting_rules(&harvesting_data);
-            info!("Generated {} rule pairs ({} lines)", harvesting_data.len(), rules.lines().count());
-
-            // Read moveuse.dat
-            let moveuse_content = std::fs::read_to_string(&moveuse_path)?;
-
-            // Insert rules
-            let updated_content = insert_harvesting_rules(&moveuse_content, &rules)?;
-
-            // Write back
-            std::fs::write(&moveuse_path, updated_content)?;
-
-            info!("Successfully updated {:?} with harvesting rules", moveuse_path);
-        }
-        Commands::GivePresent { usr_path, present_config, target_slot, dry_run, quiet } => {
             if quiet == 0 {
                 if dry_run {
-                    info!("Giving presents (DRY RUN) from {:?}", present_config);
+                    info!("Dry run: {} of {} creature files would change", changed, files.len());
                 } else {
-                    info!("Giving presents from {:?}", present_config);
+                    info!("Updated {} of {} creature files; run update-creatures to refresh the database", changed, files.len());
                 }
             }
+        }
+        Commands::EditItems { game_path, set, where_clause, write_objects_srv, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-            // Validate paths
-            if !usr_path.exists() {
-                anyhow::bail!("usr path not found: {:?}", usr_path);
+            let changes = set.iter().map(|s| s.parse::<ItemChange>()).collect::<Result<Vec<_>, _>>()?;
+            let items = db.find_items_where(&where_clause)?;
+            if items.is_empty() {
+                anyhow::bail!("No items match: {}", where_clause);
             }
-            if !present_config.exists() {
-                anyhow::bail!("Present config not found: {:?}", present_config);
+            if quiet == 0 {
+                for (type_id, name) in &items {
+                    info!("Selected {} {}", type_id, name);
+                }
             }
 
-            // Load present configuration
-            let config = PresentConfig::from_file(&present_config)
-                .map_err(|e| anyhow::anyhow!("Failed to load present config: {}", e))?;
+            let objects_srv_path = game_path.join("dat").join("objects.srv");
+            if !objects_srv_path.exists() {
+                anyhow::bail!("objects.srv not found at {:?}", objects_srv_path);
+            }
+            let text = std::fs::read_to_string(&objects_srv_path)?;
+            let type_ids = items.iter().map(|(type_id, _)| *type_id).collect();
+            let (patched, changed) = patch_objects_srv(&text, &type_ids, &changes);
 
-            if quiet == 0 {
-                info!(
-                    "Present: container {} with {} items, target slot {}",
-                    config.container.type_id,
-                    config.items.len(),
-                    target_slot
-                );
+            if !write_objects_srv || quiet == 0 {
+                for line in diff_lines(&text, &patched) {
+                    println!("{}", line);
+                }
+            }
+            if write_objects_srv {
+                std::fs::write(&objects_srv_path, patched)?;
+                if quiet == 0 {
+                    info!("Changed {} of {} items in {:?}; run update-items-core to refresh the database", changed, items.len(), objects_srv_path);
+                }
+            } else if quiet == 0 {
+                info!("{} of {} items would change (use --write-objects-srv to apply)", changed, items.len());
+            }
+        }
+        Commands::EditChest { game_path, coords, set_content, set_quest_value, backup_dir, dry_run, quiet } => {
+            let items = set_content.as_deref().map(parse_item_list).transpose()?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            if let Some(items) = &items
+                && db_path.exists()
+            {
+                let catalog = Database::open_read_only(&db_path)?.get_item_catalog()?;
+                for item in items {
+                    item.validate(&catalog)?;
+                }
             }
 
-            // Find all .usr files (recursively in XX/ subdirectories)
-            let usr_files = find_files_with_extension(&usr_path, "usr")?;
+            let edit = ChestEdit { content: set_content, quest_value: set_quest_value };
+            let change = edit_chest(&game_path.join("map"), coords, &edit, &backup_dir, dry_run)?;
+            if dry_run || quiet == 0 {
+                println!("--- {}:{}", change.sector_file.display(), change.line_number);
+                println!("-{}", change.old_line);
+                println!("+{}", change.new_line);
+            }
 
             if quiet == 0 {
-                info!("Found {} .usr files", usr_files.len());
+                match &change.backup {
+                    Some(backup) => info!(
+                        "Changed the chest at {} (backup at {:?}); run update-quest-overview to refresh the database",
+                        coords, backup
+                    ),
+                    None => info!("Dry run: the chest at {} was not changed", coords),
+                }
             }
+        }
+        Commands::Schema { format, output } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-            // Process files and collect results
-            let results: Vec<GiftResult> = usr_files
-                .par_iter()
-                .map(|path| apply_present_to_file(path, &config, target_slot, dry_run))
-                .collect();
-
-            // Aggregate summary
-            let mut summary = GiftSummary::new();
-            for result in &results {
-                summary.add_result(result);
-
-                // Log individual results based on quiet level
-                match result {
-                    GiftResult::Gifted { player_name } => {
-                        if quiet == 0 {
-                            info!("Gifted: {}", player_name);
-                        }
-                    }
-                    GiftResult::SlotOccupied { player_name } => {
-                        if quiet == 0 {
-                            info!("Skipped (slot occupied): {}", player_name);
+            let tables = db.describe_schema()?;
+            let text = match format {
+                SchemaFormat::Dot => schema_dot(&tables),
+                SchemaFormat::Markdown => schema_markdown(&tables),
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, text)?;
+                    info!("Wrote schema of {} tables to {:?}", tables.len(), path);
+                }
+                None => print!("{}", text),
+            }
+        }
+        Commands::Coverage { creature_images, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let report = db.get_coverage(creature_images.as_deref())?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                ReportFormat::Table => {
+                    println!("{:<10} {:<38} {:>7} {:>8} {:>9}", "Subsystem", "Check", "Total", "Missing", "Complete");
+                    for row in &report {
+                        println!(
+                            "{:<10} {:<38} {:>7} {:>8} {:>8.1}%",
+                            row.subsystem, row.check, row.total, row.missing, row.percent_complete
+                        );
+                        if !row.examples.is_empty() {
+                            println!("{:<10} e.g. {}", "", row.examples.join(", "));
                         }
                     }
-                    GiftResult::Error { player_name, error } => {
-                        if quiet < 2 {
-                            tracing::warn!("Error for {}: {}", player_name, error);
+                }
+            }
+        }
+        Commands::ContentGaps { kind, name, max_level, run, history, table, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            if history {
+                let runs = db.get_content_gap_runs()?;
+                match format {
+                    ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&runs)?),
+                    ReportFormat::Table => {
+                        let mut output = Table::new(&["Run", "Recorded", "Untaught", "Unsold"]);
+                        for run in &runs {
+                            output.row(vec![
+                                run.run_id.into(),
+                                run.recorded_at.as_str().into(),
+                                run.untaught_spells.into(),
+                                run.unsold_runes.into(),
+                            ]);
                         }
+                        output.print(&table)?;
                     }
                 }
+                return Ok(());
             }
 
-            // Print summary
-            if quiet == 0 {
-                info!("--- Summary ---");
-                info!("Total processed: {}", summary.total_processed);
-                info!("Gifted: {}", summary.gifted);
-                info!("Skipped (slot occupied): {}", summary.skipped);
-                info!("Errors: {}", summary.errors);
-                if dry_run {
-                    info!("(DRY RUN - no files were modified)");
+            let filter = demonax_core::models::ContentGapFilter {
+                kind: kind.map(Into::into),
+                name,
+                max_level,
+                run_id: run,
+            };
+            let gaps = db.get_content_gaps(&filter)?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&gaps)?),
+                ReportFormat::Table => {
+                    if gaps.is_empty() {
+                        println!("No matching content gaps");
+                        return Ok(());
+                    }
+                    let mut output = Table::new(&["Kind", "ID", "Name", "Words", "Level", "First recorded", "Runs"]);
+                    for gap in &gaps {
+                        output.row(vec![
+                            gap.kind.as_str().into(),
+                            gap.spell_id.into(),
+                            gap.spell_name.as_str().into(),
+                            gap.words.as_str().into(),
+                            gap.level.into(),
+                            gap.first_recorded.as_str().into(),
+                            gap.runs.into(),
+                        ]);
+                    }
+                    output.print(&table)?;
+                }
+            }
+        }
+        Commands::LintContent { min_severity, limit, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let findings = lint_content(&db, &config.lint.ruleset(), min_severity.into())?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&findings)?),
+                ReportFormat::Table => {
+                    if findings.is_empty() {
+                        println!("No lint findings");
+                    }
+                    for finding in &findings {
+                        println!("{:<7} {} ({}): {}", finding.severity, finding.rule, finding.subjects.len(), finding.message);
+                        for subject in finding.subjects.iter().take(limit) {
+                            println!("        {}", subject);
+                        }
+                        if finding.subjects.len() > limit {
+                            println!("        ... and {} more", finding.subjects.len() - limit);
+                        }
+                    }
                 }
             }
+            let errors = findings.iter().filter(|finding| finding.severity == LintSeverity::Error).count();
+            if errors > 0 {
+                anyhow::bail!("{} error-level lint rules found problems", errors);
+            }
         }
-        Commands::RenderEquipment { data_dir, output_dir, template, blank, player_id, quiet } => {
+        Commands::ImportOverlay { csv, quiet } => {
             let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
             let db = Database::new(&db_path)?;
 
-            if quiet == 0 {
-                info!("Rendering player equipment images");
-            }
+            let summary = import_overlay(&db, &csv, &Reporter::new(quiet))?;
+            info!(
+                "Stored {} {} notes, removed {}, {} with unknown keys",
+                summary.stored,
+                summary.kind.noun(),
+                summary.removed,
+                summary.unknown_keys.len()
+            );
+        }
+        Commands::TagContent { label, force, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
-            // Validate input paths
-            if !data_dir.exists() {
-                anyhow::bail!("Data directory not found: {:?}", data_dir);
-            }
-            if !template.exists() {
-                anyhow::bail!("Template image not found: {:?}", template);
+            let counts = db.tag_content(&label, force)?;
+            if quiet == 0 {
+                for (table, count) in &counts {
+                    info!("Tagged {} {} as {}", count, table, label);
+                }
             }
-            if !blank.exists() {
-                anyhow::bail!("Blank image not found: {:?}", blank);
+            info!("Tagged content as {}", label);
+        }
+        Commands::ContentDiff { from, to, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let old = db.get_content_rows(Some(&from))?;
+            let new = db.get_content_rows(to.as_deref())?;
+            let changes = demonax_core::content::diff_content(&old, &new);
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&changes)?),
+                ReportFormat::Table => print!(
+                    "{}",
+                    demonax_core::content::patch_notes(&from, to.as_deref().unwrap_or("current"), &changes)
+                ),
             }
+        }
+        Commands::DumpBundle { output, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-            // Load template and blank images once
+            let bundle = db.dump_bundle()?;
+            demonax_core::bundle::write_bundle(&output, &bundle)?;
             if quiet == 0 {
-                info!("Loading template and blank images");
+                for table in &bundle.tables {
+                    info!("Bundled {} rows of {}", table.rows.len(), table.name);
+                }
             }
-            let template_img = open(&template)
-                .map_err(|e| anyhow::anyhow!("Failed to load template: {}", e))?
-                .to_rgba8();
-            let blank_img = open(&blank)
-                .map_err(|e| anyhow::anyhow!("Failed to load blank image: {}", e))?
-                .to_rgba8();
+            info!("Wrote bundle {:?}", output);
+        }
+        Commands::LoadBundle { input, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
-            // Get latest snapshots from database
+            let bundle = demonax_core::bundle::read_bundle(&input)?;
+            let counts = db.load_bundle(&bundle)?;
             if quiet == 0 {
-                if let Some(pid) = player_id {
-                    info!("Fetching snapshot for player ID {}", pid);
-                } else {
-                    info!("Fetching all player snapshots from database");
+                for (table, count) in &counts {
+                    info!("Loaded {} rows into {}", count, table);
                 }
             }
-            let snapshots = db.get_latest_snapshots(player_id)?;
+            info!("Loaded bundle {:?} created at {}", input, bundle.created_at);
+        }
+        Commands::SetPlayerVisibility { player_id, hidden, .. } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
-            if snapshots.is_empty() {
-                anyhow::bail!("No snapshots found in database");
+            if !db.set_player_hidden(player_id, hidden)? {
+                anyhow::bail!("Player {} not found", player_id);
             }
-
+            info!("Player {} is now {}", player_id, if hidden { "hidden" } else { "visible" });
+        }
+        Commands::BuildAtlas { data_dir, output_dir, name, max_size, image, quiet } => {
+            let target = OutputTarget::new(&output_dir, &config.s3)?;
+            let manifest = build_atlas(&data_dir, target.local_path(), &name, max_size, &image.output())?;
             if quiet == 0 {
-                info!("Found {} player snapshot(s) to render", snapshots.len());
+                info!("Packed {} item images into {} sheets in {:?}", manifest.items.len(), manifest.sheets.len(), output_dir);
             }
+            publish_output(&target, quiet)?;
+        }
+        Commands::PreSaveHook { game_path, backup_dir, only_players, wait, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
-            // Create render config
-            let config = RenderConfig {
-                data_dir,
-                output_dir,
-                template_path: template,
-                blank_path: blank,
-            };
+            let summary = SaveHook::for_database(&db_path).pre_save(
+                &db,
+                &game,
+                &backup_dir,
+                &PlayerFilter::only(&only_players),
+                std::time::Duration::from_secs(wait),
+                &Reporter::new(quiet),
+            )?;
+            if quiet == 0 && summary.total_processed > 0 {
+                info!(
+                    "Queued presents: {} gifted, {} skipped, {} errors, {} verification failures",
+                    summary.gifted, summary.skipped, summary.errors, summary.verification_failures
+                );
+            }
+        }
+        Commands::QueuePresent { present_config, player, winners_csv, target_slot, skip_over_capacity } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
+            let catalog = db.get_item_catalog()?;
+
+            if let Some(winners_csv) = winners_csv {
+                let winners = read_winners_csv(&winners_csv)?;
+                for winner in &winners {
+                    winner.present.validate(&catalog).map_err(|e| {
+                        anyhow::anyhow!("Winners CSV line {}: present does not fit its container: {}", winner.line, e)
+                    })?;
+                }
+                for winner in &winners {
+                    let id = db.queue_gift(Some(&winner.player_name), &winner.present, target_slot, skip_over_capacity)?;
+                    info!("Line {}: queued gift {} for {} ({:?})", winner.line, id, winner.player_name, winner.present_config);
+                }
+            } else {
+                let Some(present_config) = present_config else {
+                    anyhow::bail!("--present-config or --winners-csv is required");
+                };
+                let config = PresentConfig::from_file(&present_config)
+                    .map_err(|e| anyhow::anyhow!("Failed to load present config: {}", e))?;
+                config
+                    .validate(&catalog)
+                    .map_err(|e| anyhow::anyhow!("Present does not fit its container: {}", e))?;
+
+                if player.is_empty() {
+                    let id = db.queue_gift(None, &config, target_slot, skip_over_capacity)?;
+                    info!("Queued gift {} for every player", id);
+                }
+                for name in &player {
+                    let id = db.queue_gift(Some(name), &config, target_slot, skip_over_capacity)?;
+                    info!("Queued gift {} for {}", id, name);
+                }
+            }
+        }
+        Commands::FlushGifts { usr_path, backup_dir, only_players, wait, quiet } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
-            // Render equipment images in parallel
-            let results: Vec<_> = snapshots
-                .par_iter()
-                .map(|snapshot| {
-                    match render_player_equipment(snapshot, &config, &template_img, &blank_img, quiet) {
-                        Ok(output_path) => Ok((snapshot.player_name.clone(), output_path)),
-                        Err(e) => Err((snapshot.player_name.clone(), e)),
-                    }
-                })
-                .collect();
+            let _lock = PipelineLock::acquire(&SaveHook::for_database(&db_path).lock, std::time::Duration::from_secs(wait))?;
+            let summary = flush_gift_queue(
+                &db,
+                &usr_path,
+                &db.get_item_catalog()?,
+                &backup_dir,
+                &PlayerFilter::only(&only_players),
+                &Reporter::new(quiet),
+            )?;
+            info!(
+                "Queued presents: {} gifted, {} skipped, {} errors, {} verification failures",
+                summary.gifted, summary.skipped, summary.errors, summary.verification_failures
+            );
+        }
+        Commands::PostSaveHook { game_path, snapshot_date, wait, quiet } => {
+            let game = discover_game(game_path, &config, quiet)?;
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::new(&db_path)?;
 
-            // Summarize results
-            let mut success_count = 0;
-            let mut error_count = 0;
+            let summary = SaveHook::for_database(&db_path).post_save(
+                &db,
+                &game,
+                snapshot_date.as_deref(),
+                std::time::Duration::from_secs(wait),
+                &Reporter::new(quiet),
+            )?;
+            if quiet == 0 {
+                info!(
+                    "Save stored: {} new snapshots from {} changed .usr files, {} changed game files",
+                    summary.snapshots, summary.usr_files, summary.game_files
+                );
+            }
+        }
+        Commands::ClonePlayer { usr_path, name, new_name, id } => {
+            let path = clone_player(&usr_path, &name, &new_name, id)?;
+            info!("Copied {} to {} at {:?}", name, new_name, path);
+        }
+        Commands::EconomyReport { date, limit, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let inflow = db.get_economy_inflow(date.as_deref())?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&inflow)?),
+                ReportFormat::Table => {
+                    let Some(first) = inflow.first() else {
+                        anyhow::bail!("No economy inflow recorded (run process-usr on two dates and update-creatures first)");
+                    };
+                    println!("Loot entering the economy on {}", first.snapshot_date);
+                    println!("{:<28} {:>9} {:>12} {:>10} {:>12}", "Creature", "Kills", "Gold", "Items", "Item value");
+                    for row in inflow.iter().take(limit) {
+                        println!(
+                            "{:<28} {:>9} {:>12} {:>10.1} {:>12}",
+                            row.creature, row.kills, row.gold, row.items, row.item_value
+                        );
+                    }
+                    println!(
+                        "{:<28} {:>9} {:>12} {:>10.1} {:>12}",
+                        format!("Total ({} creatures)", inflow.len()),
+                        inflow.iter().map(|r| r.kills).sum::<i64>(),
+                        inflow.iter().map(|r| r.gold).sum::<i64>(),
+                        inflow.iter().map(|r| r.items).sum::<f64>(),
+                        inflow.iter().map(|r| r.item_value).sum::<i64>()
+                    );
+                }
+            }
+        }
+        Commands::AccountReport { date, active_days, account_type, limit, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let accounts = db.get_account_summaries(date.as_deref(), active_days, account_type.map(Into::into))?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&accounts)?),
+                ReportFormat::Table => {
+                    if accounts.is_empty() {
+                        anyhow::bail!("No accounts recorded (the .usr files need an Account line)");
+                    }
+                    println!(
+                        "{:>9} {:<7} {:>6} {:>7} {:<24} {:>6} {:>14} {:<10}",
+                        "Account", "Type", "Chars", "Active", "Best character", "Level", "Experience", "Last seen"
+                    );
+                    for account in accounts.iter().take(limit) {
+                        let account_type = match account.premium {
+                            Some(true) => "Premium",
+                            Some(false) => "Free",
+                            None => "-",
+                        };
+                        println!(
+                            "{:>9} {:<7} {:>6} {:>7} {:<24} {:>6} {:>14} {:<10}",
+                            account.account_id,
+                            account_type,
+                            account.characters,
+                            account.active_characters,
+                            account.best_character,
+                            account.best_level,
+                            account.total_experience,
+                            account.last_seen
+                        );
+                    }
+                    println!(
+                        "{} accounts, {} active (a character seen in the last {} days)",
+                        accounts.len(),
+                        accounts.iter().filter(|a| a.active_characters > 0).count(),
+                        active_days
+                    );
+                }
+            }
+        }
+        Commands::SpellCasters { spell, table, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
 
-            for result in results {
-                match result {
-                    Ok((player_name, output_path)) => {
-                        success_count += 1;
-                        if quiet == 0 {
-                            info!("Rendered: {} -> {:?}", player_name, output_path);
-                        }
+            let Some((spell_name, casters)) = db.get_spell_casters(&spell)? else {
+                anyhow::bail!("Spell '{}' not found (run update-spells first)", spell);
+            };
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&casters)?),
+                ReportFormat::Table => {
+                    println!("{} - {} players", spell_name, casters.len());
+                    let mut output = Table::new(&["Player", "Level", "Magic", "Vocation", "Last seen"]);
+                    for caster in &casters {
+                        output.row(vec![
+                            caster.player_name.as_str().into(),
+                            caster.level.into(),
+                            caster.magic_level.into(),
+                            caster.vocation.as_str().into(),
+                            caster.snapshot_date.as_str().into(),
+                        ]);
                     }
-                    Err((player_name, error)) => {
-                        error_count += 1;
-                        if quiet < 2 {
-                            tracing::warn!("Failed to render {}: {}", player_name, error);
+                    output.print(&table.with_default_limit(20))?;
+                }
+            }
+        }
+        Commands::Nearest { position, item, limit, format } => {
+            let db_path = cli.database.unwrap_or_else(|| std::path::PathBuf::from("./demonax.sqlite"));
+            let db = Database::open_read_only(&db_path)?;
+
+            let Some(item_id) = item else {
+                let npcs = db.nearest_npcs(position, limit)?;
+                match format {
+                    ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&npcs)?),
+                    ReportFormat::Table => {
+                        println!("{:<22} {:<14} {:<19} {:>8} {:>6}", "NPC", "Town", "Home", "Distance", "Floors");
+                        for npc in &npcs {
+                            println!(
+                                "{:<22} {:<14} {:<19} {:>8} {:>6}",
+                                npc.npc_name,
+                                npc.town.as_deref().unwrap_or("-"),
+                                npc.home.to_string(),
+                                npc.distance,
+                                npc.floors
+                            );
                         }
                     }
                 }
-            }
+                return Ok(());
+            };
 
-            if quiet == 0 {
-                info!("--- Summary ---");
-                info!("Successfully rendered: {}", success_count);
-                info!("Errors: {}", error_count);
-                info!("Output directory: {:?}", config.output_dir);
+            let offers = db.nearest_item_sellers(item_id, position, limit)?;
+            match format {
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&offers)?),
+                ReportFormat::Table => {
+                    if offers.is_empty() {
+                        println!("No NPC sells item {}", item_id);
+                        return Ok(());
+                    }
+                    println!("{:<22} {:<14} {:<19} {:>8} {:>6} {:>8}", "NPC", "Town", "Home", "Distance", "Floors", "Price");
+                    for offer in &offers {
+                        let price = if offer.amount > 1 { format!("{}/{}", offer.price, offer.amount) } else { offer.price.to_string() };
+                        println!(
+                            "{:<22} {:<14} {:<19} {:>8} {:>6} {:>8}",
+                            offer.npc.npc_name,
+                            offer.npc.town.as_deref().unwrap_or("-"),
+                            offer.npc.home.to_string(),
+                            offer.npc.distance,
+                            offer.npc.floors,
+                            price
+                        );
+                    }
+                }
             }
         }
     }
 
+    let hooks_run = demonax_core::hooks::run_hooks(&hooks_db, &hooks, command_name, &Reporter::new(0))?;
+    if hooks_run > 0 {
+        info!("Ran {} hook scripts", hooks_run);
+    }
+
+    let rows_changed = demonax_core::audit::rows_changed();
+    if rows_changed > 0 {
+        Database::new(&hooks_db)?.record_audit(&invocation, rows_changed)?;
+    }
+
     info!("Demonax CLI finished");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use demonax_core::testdata::{SyntheticGame, UsrSpec};
+
+    #[test]
+    fn test_overwrite_existing_asks_when_snapshots_exist() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let game = SyntheticGame::create(temp_dir.path()).unwrap();
+        game.add_usr(&UsrSpec::new(1001, "Alice")).unwrap();
+        let db_path = temp_dir.path().join("demonax.sqlite");
+        let process_usr = |extra: &[&str]| {
+            let input_dir = game.usr_dir().to_string_lossy().into_owned();
+            let args = ["demonax", "process-usr", "--input-dir", &input_dir, "--snapshot-date", "2026-01-08"];
+            Cli::try_parse_from(args.iter().chain(extra)).unwrap().command
+        };
+
+        // Nothing to lose before the first run
+        assert!(data_lost_by(&process_usr(&["--overwrite-existing"]), &db_path).unwrap().is_empty());
+
+        let db = Database::new(&db_path).unwrap();
+        assert_eq!(db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap(), 1);
+        drop(db);
+
+        let lost = data_lost_by(&process_usr(&["--overwrite-existing"]), &db_path).unwrap();
+        assert!(lost.iter().any(|line| line.starts_with("1 rows of daily_snapshots")), "{:?}", lost);
+        assert!(lost.iter().any(|line| line.contains("of daily_skills")), "{:?}", lost);
+        // Without the flag existing snapshots are skipped, not replaced
+        assert!(data_lost_by(&process_usr(&[]), &db_path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+//! Aligned tables for the report commands, with `--columns`, `--sort` and
+//! `--limit` shared by all of them.
+//!
+//! Commands build a [`Table`] of typed [`Cell`]s and print it with the
+//! [`TableArgs`] flattened into their arguments. Numeric columns are right
+//! aligned and sort by value; text columns sort case-insensitively.
+
+use clap::Args;
+use std::cmp::Ordering;
+
+/// Column selection, sorting and row limit of a report table
+#[derive(Args, Debug, Clone, Default)]
+pub struct TableArgs {
+    /// Columns to show, comma-separated, e.g. name,level (default: all)
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Column to sort by; prefix it with - to sort descending, e.g. -level
+    #[arg(long, allow_hyphen_values = true)]
+    pub sort: Option<String>,
+
+    /// Number of rows to show
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+impl TableArgs {
+    /// These arguments with `limit` used when no `--limit` is given
+    pub fn with_default_limit(&self, limit: usize) -> Self {
+        Self { limit: self.limit.or(Some(limit)), ..self.clone() }
+    }
+}
+
+/// A table value; numbers are right aligned and sorted numerically
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Int(i64),
+    /// A number shown with the given decimals
+    Real(f64, usize),
+}
+
+impl Cell {
+    /// `Text` for present values, "-" for missing ones
+    pub fn optional<T: Into<Cell>>(value: Option<T>) -> Self {
+        value.map_or_else(|| Cell::Text("-".to_string()), Into::into)
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(self, Cell::Text(_))
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Cell::Text(_) => None,
+            Cell::Int(n) => Some(*n as f64),
+            Cell::Real(x, _) => Some(*x),
+        }
+    }
+
+    fn compare(&self, other: &Cell) -> Ordering {
+        match (self.number(), other.number()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => self.to_string().to_lowercase().cmp(&other.to_string().to_lowercase()),
+        }
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Text(text) => f.write_str(text),
+            Cell::Int(n) => write!(f, "{}", n),
+            Cell::Real(x, decimals) => write!(f, "{:.*}", decimals, x),
+        }
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::Text(text.to_string())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl From<i32> for Cell {
+    fn from(n: i32) -> Self {
+        Cell::Int(n as i64)
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(n: i64) -> Self {
+        Cell::Int(n)
+    }
+}
+
+impl From<bool> for Cell {
+    fn from(value: bool) -> Self {
+        Cell::Text(if value { "yes" } else { "no" }.to_string())
+    }
+}
+
+/// Rows under fixed headers
+#[derive(Debug, Clone)]
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self { headers: headers.to_vec(), rows: Vec::new() }
+    }
+
+    /// Add a row with one cell per header
+    pub fn row(&mut self, cells: Vec<Cell>) {
+        debug_assert_eq!(cells.len(), self.headers.len());
+        self.rows.push(cells);
+    }
+
+    /// Print the table with `args` applied
+    pub fn print(&self, args: &TableArgs) -> anyhow::Result<()> {
+        print!("{}", self.render(args)?);
+        Ok(())
+    }
+
+    /// The table as aligned text with `args` applied. Unknown column names
+    /// are an error that lists the known ones.
+    pub fn render(&self, args: &TableArgs) -> anyhow::Result<String> {
+        let columns: Vec<usize> = if args.columns.is_empty() {
+            (0..self.headers.len()).collect()
+        } else {
+            args.columns.iter().map(|name| self.column(name)).collect::<anyhow::Result<_>>()?
+        };
+
+        let mut rows: Vec<&Vec<Cell>> = self.rows.iter().collect();
+        if let Some(sort) = &args.sort {
+            let (descending, name) = match sort.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, sort.as_str()),
+            };
+            let column = self.column(name)?;
+            rows.sort_by(|a, b| {
+                let order = a[column].compare(&b[column]);
+                if descending { order.reverse() } else { order }
+            });
+        }
+        rows.truncate(args.limit.unwrap_or(usize::MAX));
+
+        let numeric: Vec<bool> = columns
+            .iter()
+            .map(|&c| !self.rows.is_empty() && self.rows.iter().all(|row| row[c].is_numeric()))
+            .collect();
+        let texts: Vec<Vec<String>> =
+            rows.iter().map(|row| columns.iter().map(|&c| row[c].to_string()).collect()).collect();
+        let widths: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| {
+                texts.iter().map(|row| row[i].chars().count()).chain([self.headers[c].len()]).max().unwrap_or(0)
+            })
+            .collect();
+
+        let line = |cells: Vec<&str>| {
+            let padded: Vec<String> = cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    if numeric[i] { format!("{:>1$}", cell, widths[i]) } else { format!("{:<1$}", cell, widths[i]) }
+                })
+                .collect();
+            format!("{}\n", padded.join(" ").trim_end())
+        };
+        let mut out = line(columns.iter().map(|&c| self.headers[c]).collect());
+        for row in &texts {
+            out.push_str(&line(row.iter().map(String::as_str).collect()));
+        }
+        Ok(out)
+    }
+
+    /// Index of a column by header, ignoring case and with `-` or `_` for spaces
+    fn column(&self, name: &str) -> anyhow::Result<usize> {
+        let key = |s: &str| s.trim().to_lowercase().replace([' ', '_'], "-");
+        self.headers.iter().position(|header| key(header) == key(name)).ok_or_else(|| {
+            let known: Vec<String> = self.headers.iter().map(|header| key(header)).collect();
+            anyhow::anyhow!("Unknown column '{}' (columns: {})", name, known.join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_sort_and_limit() {
+        let mut table = Table::new(&["Name", "Level", "Last seen"]);
+        table.row(vec!["bob".into(), 9.into(), "2026-01-02".into()]);
+        table.row(vec!["Alice".into(), 120.into(), "2026-01-01".into()]);
+        table.row(vec!["carol".into(), 35.into(), "2026-01-03".into()]);
+
+        let all = table.render(&TableArgs::default()).unwrap();
+        assert_eq!(all.lines().next().unwrap(), "Name  Level Last seen");
+        assert_eq!(all.lines().nth(2).unwrap(), "Alice   120 2026-01-01");
+
+        let args = TableArgs { columns: vec!["last_seen".into(), "name".into()], sort: Some("-level".into()), limit: Some(2) };
+        assert_eq!(table.render(&args).unwrap(), "Last seen  Name\n2026-01-01 Alice\n2026-01-03 carol\n");
+
+        let by_name = TableArgs { sort: Some("name".into()), ..TableArgs::default() };
+        assert!(table.render(&by_name).unwrap().lines().nth(1).unwrap().starts_with("Alice"));
+        assert!(table.render(&TableArgs { columns: vec!["town".into()], ..TableArgs::default() }).is_err());
+    }
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use demonax_core::file_utils::decode_latin1;
+use demonax_core::parsers::parse_houses_str;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Use the same input as all three files so cross-file lookups get exercised
+    if let Ok(text) = decode_latin1(data) {
+        let _ = parse_houses_str(&text, Some(&text), Some(&text));
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use demonax_core::file_utils::decode_latin1;
+use demonax_core::parsers::parse_map_sector_str;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // First line is used as the sector name so the filename parsing gets fuzzed too
+    if let Ok(text) = decode_latin1(data) {
+        let (sector_name, body) = text.split_once('\n').unwrap_or(("1000-1000-7", &text));
+        let _ = parse_map_sector_str(body, sector_name);
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use demonax_core::file_utils::decode_latin1;
+use demonax_core::parsers::{
+    parse_creature_flags, parse_creature_loot_str, parse_creature_skills, parse_creature_spells, parse_mon_str,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = decode_latin1(data) {
+        let _ = parse_mon_str(&text, "fuzz");
+        let _ = parse_creature_loot_str(&text);
+        let _ = parse_creature_flags(&text);
+        let _ = parse_creature_skills(&text);
+        let _ = parse_creature_spells(&text);
+    }
+});
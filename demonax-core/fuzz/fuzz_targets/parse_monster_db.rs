@@ -0,0 +1,11 @@
+#![no_main]
+
+use demonax_core::file_utils::decode_latin1;
+use demonax_core::parsers::parse_monster_db_str;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = decode_latin1(data) {
+        let _ = parse_monster_db_str(&text);
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use demonax_core::file_utils::decode_latin1;
+use demonax_core::parsers::{parse_npc_rune_selling_str, parse_npc_spell_teaching_str, parse_npc_str};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = decode_latin1(data) {
+        let _ = parse_npc_str(&text, "fuzz");
+        let _ = parse_npc_spell_teaching_str(&text);
+        let _ = parse_npc_rune_selling_str(&text, "fuzz-prem-.npc");
+    }
+});
@@ -0,0 +1,197 @@
+//! Sprite sheets of the item images, for the website.
+//!
+//! The `{type_id}.png` item images (the same directory `render-equipment` reads)
+//! are packed into a few large sheets, so a page needs a handful of requests
+//! instead of one per item. A JSON manifest and a CSS file give each item's
+//! position on its sheet.
+
+use crate::error::{DemonaxError, Result};
+use crate::rendering::{save_image, ImageOutput};
+use image::{imageops, RgbaImage};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+
+/// Where an image goes: sheet index and top-left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub sheet: usize,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Pack `(width, height)` rectangles into `max_size` x `max_size` sheets, in rows
+/// of equal height, tallest first. Returns a placement per input, in input order.
+pub fn pack(sizes: &[(u32, u32)], max_size: u32) -> Result<Vec<Placement>> {
+    if let Some((width, height)) = sizes.iter().find(|(w, h)| *w > max_size || *h > max_size) {
+        return Err(DemonaxError::Validation(format!(
+            "A {}x{} image doesn't fit on a {}x{} sheet",
+            width, height, max_size, max_size
+        )));
+    }
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut placements = vec![Placement { sheet: 0, x: 0, y: 0 }; sizes.len()];
+    let (mut sheet, mut x, mut y, mut row_height) = (0, 0, 0, 0);
+    for i in order {
+        let (width, height) = sizes[i];
+        if x + width > max_size {
+            (x, y, row_height) = (0, y + row_height, 0);
+        }
+        if y + height > max_size {
+            (sheet, x, y, row_height) = (sheet + 1, 0, 0, 0);
+        }
+        placements[i] = Placement { sheet, x, y };
+        x += width;
+        row_height = row_height.max(height);
+    }
+    Ok(placements)
+}
+
+/// An item's position in the atlas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AtlasEntry {
+    pub type_id: i32,
+    pub sheet: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The `{name}.json` manifest: sheet file names and item positions by type ID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AtlasManifest {
+    pub sheets: Vec<String>,
+    pub items: Vec<AtlasEntry>,
+}
+
+impl AtlasManifest {
+    /// One `.item-{type_id}` rule per item
+    pub fn css(&self) -> String {
+        let offset = |pixels: u32| if pixels == 0 { "0".to_string() } else { format!("-{}px", pixels) };
+        self.items
+            .iter()
+            .map(|item| {
+                format!(
+                    ".item-{}{{background:url({}) {} {};width:{}px;height:{}px}}\n",
+                    item.type_id,
+                    self.sheets[item.sheet],
+                    offset(item.x),
+                    offset(item.y),
+                    item.width,
+                    item.height
+                )
+            })
+            .collect()
+    }
+}
+
+/// Pack the `{type_id}.png` images in `image_dir` into sheets named
+/// `{name}-{n}.{extension}` in `output_dir`, and write `{name}.json` and
+/// `{name}.css` next to them.
+pub fn build_atlas(image_dir: &Path, output_dir: &Path, name: &str, max_size: u32, output: &ImageOutput) -> Result<AtlasManifest> {
+    let mut paths: Vec<(i32, std::path::PathBuf)> = std::fs::read_dir(image_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .filter_map(|path| Some((path.file_stem()?.to_str()?.parse().ok()?, path)))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(DemonaxError::NotFound(format!("No {{type_id}}.png images in {}", image_dir.display())));
+    }
+
+    let images = paths
+        .par_iter()
+        .map(|(type_id, path)| {
+            let image = image::open(path)
+                .map_err(|e| DemonaxError::Io(std::io::Error::other(format!("Failed to load {}: {}", path.display(), e))))?
+                .to_rgba8();
+            Ok((*type_id, image))
+        })
+        .collect::<Result<Vec<(i32, RgbaImage)>>>()?;
+
+    let placements = pack(&images.iter().map(|(_, image)| image.dimensions()).collect::<Vec<_>>(), max_size)?;
+    let sheet_count = placements.iter().map(|p| p.sheet + 1).max().unwrap_or(0);
+
+    let mut items = Vec::with_capacity(images.len());
+    let mut sheets = Vec::with_capacity(sheet_count);
+    for sheet in 0..sheet_count {
+        let on_sheet: Vec<(&(i32, RgbaImage), &Placement)> =
+            images.iter().zip(&placements).filter(|(_, p)| p.sheet == sheet).collect();
+        // Trim the sheet to what is used
+        let width = on_sheet.iter().map(|((_, image), p)| p.x + image.width()).max().unwrap_or(1);
+        let height = on_sheet.iter().map(|((_, image), p)| p.y + image.height()).max().unwrap_or(1);
+
+        let mut canvas = RgbaImage::new(width, height);
+        for ((type_id, image), placement) in on_sheet {
+            imageops::replace(&mut canvas, image, placement.x as i64, placement.y as i64);
+            items.push(AtlasEntry {
+                type_id: *type_id,
+                sheet,
+                x: placement.x,
+                y: placement.y,
+                width: image.width(),
+                height: image.height(),
+            });
+        }
+
+        let path = save_image(&canvas, output_dir, format!("{}-{}", name, sheet), output)?;
+        sheets.push(path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default());
+    }
+    items.sort_by_key(|item| item.type_id);
+
+    let manifest = AtlasManifest { sheets, items };
+    std::fs::write(output_dir.join(format!("{}.json", name)), serde_json::to_string_pretty(&manifest)?)?;
+    std::fs::write(output_dir.join(format!("{}.css", name)), manifest.css())?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_fills_rows_then_sheets() {
+        let sizes = [(32, 32), (64, 64), (32, 32), (32, 32), (32, 32)];
+        let placements = pack(&sizes, 96).unwrap();
+
+        // The 64x64 image goes first; the first row then fits one more 32x32
+        assert_eq!(placements[1], Placement { sheet: 0, x: 0, y: 0 });
+        assert_eq!(placements[0], Placement { sheet: 0, x: 64, y: 0 });
+        assert_eq!(placements[2], Placement { sheet: 0, x: 0, y: 64 });
+        assert_eq!(placements[4], Placement { sheet: 0, x: 64, y: 64 });
+
+        let placements = pack(&sizes, 64).unwrap();
+        assert_eq!(placements.iter().map(|p| p.sheet).max(), Some(1));
+        assert!(pack(&[(65, 10)], 64).is_err());
+    }
+
+    #[test]
+    fn test_build_atlas_writes_sheets_and_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let image_dir = temp_dir.path().join("items");
+        std::fs::create_dir_all(&image_dir).unwrap();
+        for (type_id, shade) in [(3031, 10u8), (3349, 20), (2854, 30)] {
+            RgbaImage::from_pixel(32, 32, image::Rgba([shade, 0, 0, 255])).save(image_dir.join(format!("{}.png", type_id))).unwrap();
+        }
+        std::fs::write(image_dir.join("eq.png"), b"not an item").unwrap();
+
+        let output_dir = temp_dir.path().join("atlas");
+        let manifest = build_atlas(&image_dir, &output_dir, "items", 64, &ImageOutput::default()).unwrap();
+
+        assert_eq!(manifest.sheets, vec!["items-0.png".to_string()]);
+        assert_eq!(manifest.items.iter().map(|i| i.type_id).collect::<Vec<_>>(), vec![2854, 3031, 3349]);
+        let sheet = image::open(output_dir.join("items-0.png")).unwrap().to_rgba8();
+        assert_eq!(sheet.dimensions(), (64, 64));
+        let item = &manifest.items[2];
+        assert_eq!(sheet.get_pixel(item.x, item.y).0, [20, 0, 0, 255]);
+
+        let css = std::fs::read_to_string(output_dir.join("items.css")).unwrap();
+        assert!(css.contains(".item-2854{background:url(items-0.png) 0 0;width:32px;height:32px}"));
+        assert!(css.contains(&format!(".item-3349{{background:url(items-0.png) 0 -{}px;width:32px;height:32px}}", item.y)));
+        assert!(output_dir.join("items.json").exists());
+    }
+}
@@ -0,0 +1,60 @@
+//! Who ran which command, for the `audit_log` table.
+//!
+//! Every writable [`Database`] counts the rows its connections change, per
+//! handle and for the whole process. The CLI creates the [`Invocation`] at
+//! startup and, once the command has succeeded, records it with the process
+//! count and how long it ran.
+//!
+//! [`Database`]: crate::database::Database
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A command run against the database
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    /// Subcommand name, e.g. update-items-core
+    pub command: String,
+    /// Command line after the program name
+    pub arguments: String,
+    /// From DEMONAX_USER, falling back to USER or USERNAME
+    pub user: Option<String>,
+    started: Instant,
+}
+
+impl Invocation {
+    /// An invocation starting now, by the user named in the environment
+    pub fn new(command: impl Into<String>, arguments: impl Into<String>) -> Self {
+        Self { command: command.into(), arguments: arguments.into(), user: current_user(), started: Instant::now() }
+    }
+
+    /// Time since the invocation started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// The user named in DEMONAX_USER, falling back to USER or USERNAME
+pub fn current_user() -> Option<String> {
+    ["DEMONAX_USER", "USER", "USERNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+static ROWS_CHANGED: AtomicU64 = AtomicU64::new(0);
+
+/// Rows changed through every [`Database`] of this process so far, excluding schema migrations
+///
+/// [`Database`]: crate::database::Database
+pub fn rows_changed() -> u64 {
+    ROWS_CHANGED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn add_rows_changed(rows: u64) {
+    ROWS_CHANGED.fetch_add(rows, Ordering::Relaxed);
+}
+
+/// Take back rows counted by [`add_rows_changed`] that weren't the command's doing
+pub(crate) fn discount_rows_changed(rows: u64) {
+    ROWS_CHANGED.fetch_sub(rows, Ordering::Relaxed);
+}
@@ -0,0 +1,157 @@
+//! Portable JSON bundles of the content tables.
+//!
+//! A bundle holds the game data (creatures, items, NPC offers, spells, quests,
+//! raids, houses, ...) but no player data, so web builds and staging databases
+//! can be synced without copying a SQLite file. Files ending in `.zst` are
+//! zstd-compressed.
+
+use crate::error::{DemonaxError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const BUNDLE_FORMAT: &str = "demonax-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Tables in a bundle, parents before the tables referencing them
+pub const BUNDLE_TABLES: &[&str] = &[
+    "creatures",
+    "creature_loot",
+    "creature_flags",
+    "creature_skills",
+    "creature_spells",
+    "items",
+    "item_loot_sources",
+    "towns",
+    "npcs",
+    "item_prices",
+    "quests",
+    "quest_keys",
+    "doors",
+    "raids",
+    "spawns",
+    "spells",
+    "spell_teachers",
+    "harvesting_data",
+    "rune_sellers",
+    "houses",
+    "creature_notes",
+    "item_notes",
+    "image_overrides",
+    "bestiary_races",
+];
+
+/// Tables of the schema left out of bundles: player data and what is derived
+/// from it, the server's history, and the bookkeeping of one database
+pub const BUNDLE_EXCLUDED_TABLES: &[&str] = &[
+    "players",
+    "accounts",
+    "player_name_history",
+    "daily_snapshots",
+    "daily_skills",
+    "player_spells",
+    "daily_quests",
+    "daily_bestiary",
+    "daily_harvesting",
+    "house_owners",
+    "daily_house_owners",
+    "pending_gifts",
+    "world_stats",
+    "economy_inflow",
+    "raid_executions",
+    "events",
+    "log_events",
+    "log_offsets",
+    "online_samples",
+    "run_journal",
+    "data_migrations",
+    "content_gap_runs",
+    "content_gaps",
+    "content_tags",
+    "content_tag_rows",
+    "audit_log",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub version: u32,
+    pub created_at: String,
+    pub tables: Vec<BundleTable>,
+}
+
+/// The rows of one table; each row has a value per column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleTable {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+impl Bundle {
+    pub fn new(tables: Vec<BundleTable>) -> Self {
+        Self {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            tables,
+        }
+    }
+}
+
+/// Write a bundle as JSON, zstd-compressed when the path ends in `.zst`.
+pub fn write_bundle(path: &Path, bundle: &Bundle) -> Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_vec(bundle)?;
+    let data = if is_compressed(path) { zstd::encode_all(json.as_slice(), 19)? } else { json };
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+/// Read a bundle written by [`write_bundle`], checking its format and version.
+pub fn read_bundle(path: &Path) -> Result<Bundle> {
+    let mut data = std::fs::read(path)?;
+    if is_compressed(path) {
+        data = zstd::decode_all(data.as_slice())?;
+    }
+    let bundle: Bundle = serde_json::from_slice(&data)
+        .map_err(|e| DemonaxError::Parse(format!("Invalid bundle {}: {}", path.display(), e)))?;
+
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(DemonaxError::Validation(format!("{} is not a demonax bundle", path.display())));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(DemonaxError::Validation(format!(
+            "bundle version {} is newer than the supported version {}",
+            bundle.version, BUNDLE_VERSION
+        )));
+    }
+    Ok(bundle)
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "zst")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TABLE_DOCS;
+
+    #[test]
+    fn test_every_table_is_bundled_or_excluded() {
+        for (table, _) in TABLE_DOCS {
+            assert!(
+                BUNDLE_TABLES.contains(table) != BUNDLE_EXCLUDED_TABLES.contains(table),
+                "table {} must be in exactly one of BUNDLE_TABLES and BUNDLE_EXCLUDED_TABLES",
+                table
+            );
+        }
+        for table in BUNDLE_TABLES.iter().chain(BUNDLE_EXCLUDED_TABLES) {
+            assert!(TABLE_DOCS.iter().any(|(name, _)| name == table), "table {} has no entry in TABLE_DOCS", table);
+        }
+    }
+}
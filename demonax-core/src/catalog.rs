@@ -0,0 +1,285 @@
+//! In-memory item and creature metadata, for features that look up many items
+//! or creatures at once without a database query per lookup.
+//!
+//! An [`ItemCatalog`] is loaded once, from the database with
+//! [`Database::get_item_catalog`] or straight from objects.srv with
+//! [`ItemCatalog::from_objects_srv`], and shares its entries: clones are cheap
+//! and can be handed to every rayon worker. A [`CreatureCatalog`] does the same
+//! for creatures by race number, from [`Database::get_creature_catalog`].
+//!
+//! [`Database::get_item_catalog`]: crate::database::Database::get_item_catalog
+//! [`Database::get_creature_catalog`]: crate::database::Database::get_creature_catalog
+
+use crate::error::Result;
+use crate::loot::CURRENCY_VALUES;
+use crate::models::{Item, ItemFlags};
+use crate::parsers::parse_objects_srv;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Metadata of one item type
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogItem {
+    pub type_id: i32,
+    pub name: String,
+    /// Weight in hundredths of an ounce, from the Weight attribute; 0 when not set
+    pub weight: i32,
+    /// Slot count of containers, from the Capacity attribute
+    pub capacity: Option<usize>,
+    pub flags: ItemFlags,
+    /// Gold value of a single item: the best NPC price, or face value for coins
+    pub value: Option<i64>,
+}
+
+impl CatalogItem {
+    /// An item without weight, flags or value
+    pub fn new(type_id: i32, name: &str) -> Self {
+        Self { type_id, name: name.to_string(), weight: 0, capacity: None, flags: ItemFlags::default(), value: None }
+    }
+
+    /// Metadata of a parsed objects.srv item; only coins have a value
+    pub fn from_item(item: &Item) -> Self {
+        let attributes: HashMap<String, String> = serde_json::from_str(&item.attributes).unwrap_or_default();
+        let attribute = |key: &str| attributes.get(key).and_then(|value| value.parse::<i64>().ok());
+        Self {
+            type_id: item.type_id,
+            name: item.name.clone(),
+            weight: attribute("Weight").unwrap_or(0) as i32,
+            capacity: attribute("Capacity").map(|capacity| capacity.max(0) as usize),
+            flags: item.flag_set(),
+            value: CURRENCY_VALUES.iter().find(|(id, _)| *id == item.type_id).map(|(_, value)| *value),
+        }
+    }
+}
+
+/// Item metadata by type ID
+#[derive(Debug, Clone, Default)]
+pub struct ItemCatalog {
+    items: Arc<HashMap<i32, CatalogItem>>,
+}
+
+impl ItemCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Catalog of parsed objects.srv items
+    pub fn from_items(items: &[Item]) -> Self {
+        items.iter().map(CatalogItem::from_item).collect()
+    }
+
+    /// Parse objects.srv into a catalog, for tools that run without a database
+    pub fn from_objects_srv(path: &Path) -> Result<Self> {
+        Ok(Self::from_items(&parse_objects_srv(path)?))
+    }
+
+    pub fn get(&self, type_id: i32) -> Option<&CatalogItem> {
+        self.items.get(&type_id)
+    }
+
+    pub fn name(&self, type_id: i32) -> Option<&str> {
+        self.get(type_id).map(|item| item.name.as_str())
+    }
+
+    /// Weight of one item of a type; unknown types weigh nothing
+    pub fn weight(&self, type_id: i32) -> i32 {
+        self.get(type_id).map_or(0, |item| item.weight)
+    }
+
+    /// Slot count of a container type; None for other and unknown types
+    pub fn capacity(&self, type_id: i32) -> Option<usize> {
+        self.get(type_id).and_then(|item| item.capacity)
+    }
+
+    /// Flags of a type; unknown types have none
+    pub fn flags(&self, type_id: i32) -> ItemFlags {
+        self.get(type_id).map_or_else(ItemFlags::default, |item| item.flags)
+    }
+
+    /// Gold value of one item of a type, if known
+    pub fn value(&self, type_id: i32) -> Option<i64> {
+        self.get(type_id).and_then(|item| item.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl FromIterator<CatalogItem> for ItemCatalog {
+    fn from_iter<I: IntoIterator<Item = CatalogItem>>(iter: I) -> Self {
+        Self { items: Arc::new(iter.into_iter().map(|item| (item.type_id, item)).collect()) }
+    }
+}
+
+/// A creature of the [`CreatureCatalog`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogCreature {
+    pub race: i32,
+    pub name: String,
+    /// File stem of the .mon file
+    pub short_name: String,
+    pub hp: i32,
+    pub experience: i32,
+}
+
+/// Creatures by race number. Where several .mon files share a race, the first
+/// one loaded stands for it.
+#[derive(Debug, Clone, Default)]
+pub struct CreatureCatalog {
+    creatures: Arc<HashMap<i32, CatalogCreature>>,
+}
+
+impl CreatureCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, race: i32) -> Option<&CatalogCreature> {
+        self.creatures.get(&race)
+    }
+
+    pub fn name(&self, race: i32) -> Option<&str> {
+        self.get(race).map(|creature| creature.name.as_str())
+    }
+
+    /// Find a creature by name or short name, as people write them in configs:
+    /// case, articles and punctuation don't matter, a plural ("dragons") finds
+    /// the creature, and so does a name with a typo or two when only one
+    /// creature is that close.
+    pub fn find(&self, name: &str) -> Option<&CatalogCreature> {
+        let wanted = normalize_name(name);
+        if wanted.is_empty() {
+            return None;
+        }
+        let mut creatures: Vec<&CatalogCreature> = self.creatures.values().collect();
+        creatures.sort_by_key(|creature| creature.race);
+        let names = |creature: &CatalogCreature| [normalize_name(&creature.name), normalize_name(&creature.short_name)];
+
+        let singular = [wanted.strip_suffix("es"), wanted.strip_suffix('s')];
+        let candidates = std::iter::once(wanted.as_str()).chain(singular.into_iter().flatten());
+        for candidate in candidates {
+            if let Some(creature) = creatures.iter().find(|creature| names(creature).iter().any(|n| n == candidate)) {
+                return Some(*creature);
+            }
+        }
+
+        // Closest name within the allowed typos, if no other creature is as close
+        let allowed = (wanted.len() / 4).clamp(1, 2);
+        let mut distances: Vec<(usize, &CatalogCreature)> = creatures
+            .iter()
+            .map(|creature| (names(creature).iter().map(|n| edit_distance(n, &wanted)).min().unwrap_or(usize::MAX), *creature))
+            .filter(|(distance, _)| *distance <= allowed)
+            .collect();
+        distances.sort_by_key(|(distance, _)| *distance);
+        match distances.as_slice() {
+            [(best, creature), rest @ ..] if rest.first().is_none_or(|(next, _)| next > best) => Some(*creature),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.creatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.creatures.is_empty()
+    }
+}
+
+impl FromIterator<CatalogCreature> for CreatureCatalog {
+    fn from_iter<I: IntoIterator<Item = CatalogCreature>>(iter: I) -> Self {
+        let mut creatures = HashMap::new();
+        for creature in iter {
+            creatures.entry(creature.race).or_insert(creature);
+        }
+        Self { creatures: Arc::new(creatures) }
+    }
+}
+
+/// Lower-case words of a name without a leading article or punctuation
+fn normalize_name(name: &str) -> String {
+    let lower = name.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    match words.as_slice() {
+        ["a" | "an" | "the", rest @ ..] if !rest.is_empty() => rest.join(" "),
+        _ => words.join(" "),
+    }
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_objects_srv_str;
+
+    #[test]
+    fn test_catalog_from_objects_srv_items() {
+        let text = "TypeID      = 2853\nName        = \"a bag\"\nFlags       = {Container,Take}\nAttributes  = {Capacity=8,Weight=800}\n\n\
+                    TypeID      = 3031\nName        = \"a gold coin\"\nFlags       = {Cumulative,Take}\nAttributes  = {Weight=10}\n";
+        let catalog = ItemCatalog::from_items(&parse_objects_srv_str(text).unwrap());
+        let shared = catalog.clone();
+
+        assert_eq!(shared.len(), 2);
+        assert_eq!(shared.name(2853), Some("Bag"));
+        assert_eq!((shared.capacity(2853), shared.weight(2853)), (Some(8), 800));
+        assert_eq!(shared.capacity(3031), None);
+        assert!(shared.flags(3031).contains(ItemFlags::CUMULATIVE));
+        assert_eq!((shared.value(3031), shared.value(2853)), (Some(1), None));
+        assert_eq!(shared.weight(9999), 0);
+    }
+
+    #[test]
+    fn test_creature_catalog_fuzzy_find() {
+        let creature = |race: i32, name: &str, short_name: &str| CatalogCreature {
+            race,
+            name: name.to_string(),
+            short_name: short_name.to_string(),
+            hp: 100,
+            experience: 50,
+        };
+        let catalog: CreatureCatalog = [
+            creature(34, "Dragon", "dragon"),
+            creature(39, "Dragon Lord", "dragonlord"),
+            creature(35, "Demon", "demon"),
+            creature(35, "Demon (Quest)", "demonquest"),
+            creature(76, "Orc Warlord", "orcwarlord"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(catalog.len(), 4);
+        assert_eq!(catalog.name(35), Some("Demon"));
+        assert_eq!(catalog.find("a Dragon").map(|c| c.race), Some(34));
+        assert_eq!(catalog.find("DRAGONLORD").map(|c| c.race), Some(39));
+        assert_eq!(catalog.find("dragon lords").map(|c| c.race), Some(39));
+        assert_eq!(catalog.find("demons").map(|c| c.race), Some(35));
+        assert_eq!(catalog.find("orc warlrod").map(|c| c.race), Some(76));
+        assert_eq!(catalog.find("dragn").map(|c| c.race), Some(34));
+        assert!(catalog.find("rat").is_none());
+        assert!(catalog.find("").is_none());
+    }
+}
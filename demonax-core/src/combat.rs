@@ -0,0 +1,219 @@
+//! Combat estimates between a creature and a player.
+//!
+//! Per creature turn the server tries a melee hit (when adjacent) and then
+//! each spell, a spell firing with a 1-in-`priority` chance. Melee hits are
+//! reduced by the defender's shield block and armor; spell damage is not.
+//!
+//! Formulas follow the classic server:
+//! - maximum melee (and block) value: `0.085 * attack * skill`, rolled uniformly from 0
+//! - armor above 3 absorbs a uniform roll between `armor / 2` and `armor - 1`
+//! - hit points: 185 at level 8, plus 15 (knight), 10 (paladin) or 5 per level after
+
+use serde::Serialize;
+
+/// Melee damage factor of the attack formula
+const MELEE_FACTOR: f64 = 0.085;
+
+/// Damage spell of a creature
+#[derive(Debug, Clone, Serialize)]
+pub struct DamageSpell {
+    pub name: String,
+    pub damage_type: Option<String>,
+    pub min_value: i32,
+    pub max_value: i32,
+    /// The spell fires with a 1-in-priority chance each turn
+    pub priority: i32,
+}
+
+/// Combat values of a creature
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatureCombat {
+    pub name: String,
+    pub hp: i32,
+    pub attack: i32,
+    /// Melee skill (FistFighting)
+    pub skill: i32,
+    pub spells: Vec<DamageSpell>,
+}
+
+/// The defending player
+#[derive(Debug, Clone, Serialize)]
+pub struct DefenderProfile {
+    pub hp: i32,
+    /// Total armor of worn equipment
+    pub armor: i32,
+    pub shielding: i32,
+    /// Defense value of the shield (or weapon) used to block
+    pub shield_defense: i32,
+}
+
+/// Expected damage of one spell per turn
+#[derive(Debug, Clone, Serialize)]
+pub struct SpellEstimate {
+    pub name: String,
+    pub chance: f64,
+    pub average_per_turn: f64,
+    pub max_damage: i32,
+}
+
+/// Damage a creature does to a defender
+#[derive(Debug, Clone, Serialize)]
+pub struct CombatEstimate {
+    pub melee_max: i32,
+    /// Average melee damage per turn after block and armor
+    pub melee_average: f64,
+    pub spells: Vec<SpellEstimate>,
+    pub average_per_turn: f64,
+    /// Every damage source hitting for its maximum in the same turn
+    pub max_per_turn: i32,
+    /// Average number of turns until the defender dies, if the creature does any damage
+    pub turns_to_kill: Option<f64>,
+    /// Turns until the defender dies when every hit is a maximum hit
+    pub worst_case_turns: Option<i32>,
+}
+
+/// Maximum hit points of a player for a vocation name (e.g. "Elite Knight", case-insensitive)
+pub fn player_max_hp(level: i32, vocation: &str) -> i32 {
+    if level < 8 {
+        return 5 * (level + 29);
+    }
+    let vocation = vocation.to_ascii_lowercase();
+    let per_level = if vocation.contains("knight") {
+        15
+    } else if vocation.contains("paladin") {
+        10
+    } else {
+        5
+    };
+    185 + per_level * (level - 8)
+}
+
+/// Maximum damage of a melee hit (or block) for an attack (or defense) value and skill
+pub fn melee_max(attack: i32, skill: i32) -> i32 {
+    (MELEE_FACTOR * attack.max(0) as f64 * skill.max(0) as f64).round() as i32
+}
+
+/// Probability distribution of the damage absorbed by block plus armor, indexed by amount
+fn reduction_distribution(defender: &DefenderProfile) -> Vec<f64> {
+    let block_max = melee_max(defender.shield_defense, defender.shielding) as usize;
+    let block = vec![1.0 / (block_max + 1) as f64; block_max + 1];
+
+    let armor: Vec<f64> = if defender.armor > 3 {
+        let (low, high) = ((defender.armor / 2) as usize, (defender.armor - 1) as usize);
+        let p = 1.0 / (high - low + 1) as f64;
+        (0..=high).map(|a| if a >= low { p } else { 0.0 }).collect()
+    } else {
+        vec![1.0]
+    };
+
+    let mut combined = vec![0.0; block.len() + armor.len() - 1];
+    for (b, pb) in block.iter().enumerate() {
+        for (a, pa) in armor.iter().enumerate() {
+            combined[a + b] += pb * pa;
+        }
+    }
+    combined
+}
+
+/// Estimate the damage a creature does per turn to a defender.
+pub fn estimate_combat(creature: &CreatureCombat, defender: &DefenderProfile) -> CombatEstimate {
+    let melee_max = melee_max(creature.attack, creature.skill);
+    let reduction = reduction_distribution(defender);
+
+    let melee_average = if melee_max > 0 {
+        let total: f64 = (0..=melee_max)
+            .map(|hit| {
+                reduction
+                    .iter()
+                    .enumerate()
+                    .map(|(r, p)| p * (hit - r as i32).max(0) as f64)
+                    .sum::<f64>()
+            })
+            .sum();
+        total / (melee_max + 1) as f64
+    } else {
+        0.0
+    };
+    // A maximum hit that isn't blocked still loses the smallest armor roll
+    let min_armor_reduction = if defender.armor > 3 { defender.armor / 2 } else { 0 };
+    let melee_worst = (melee_max - min_armor_reduction).max(0);
+
+    let spells: Vec<SpellEstimate> = creature
+        .spells
+        .iter()
+        .map(|spell| {
+            let chance = 1.0 / spell.priority.max(1) as f64;
+            SpellEstimate {
+                name: spell.name.clone(),
+                chance,
+                average_per_turn: chance * (spell.min_value + spell.max_value) as f64 / 2.0,
+                max_damage: spell.max_value,
+            }
+        })
+        .collect();
+
+    let average_per_turn = melee_average + spells.iter().map(|s| s.average_per_turn).sum::<f64>();
+    let max_per_turn = melee_worst + spells.iter().map(|s| s.max_damage).sum::<i32>();
+
+    CombatEstimate {
+        melee_max,
+        melee_average,
+        spells,
+        average_per_turn,
+        max_per_turn,
+        turns_to_kill: (average_per_turn > 0.0).then(|| defender.hp as f64 / average_per_turn),
+        worst_case_turns: (max_per_turn > 0).then(|| (defender.hp + max_per_turn - 1) / max_per_turn),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defender(armor: i32, shielding: i32, shield_defense: i32) -> DefenderProfile {
+        DefenderProfile { hp: 500, armor, shielding, shield_defense }
+    }
+
+    #[test]
+    fn test_player_max_hp() {
+        assert_eq!(player_max_hp(8, "Knight"), 185);
+        assert_eq!(player_max_hp(20, "Elite Knight"), 365);
+        assert_eq!(player_max_hp(20, "paladin"), 305);
+        assert_eq!(player_max_hp(20, "Druid"), 245);
+        assert_eq!(player_max_hp(1, "None"), 150);
+    }
+
+    #[test]
+    fn test_unprotected_melee_is_half_of_max() {
+        let creature = CreatureCombat { name: "Rat".to_string(), hp: 20, attack: 10, skill: 20, spells: Vec::new() };
+        let estimate = estimate_combat(&creature, &defender(0, 0, 0));
+
+        assert_eq!(estimate.melee_max, 17);
+        assert!((estimate.melee_average - 8.5).abs() < 1e-9);
+        assert_eq!(estimate.max_per_turn, 17);
+        assert_eq!(estimate.worst_case_turns, Some(30));
+    }
+
+    #[test]
+    fn test_armor_and_shield_reduce_melee_but_not_spells() {
+        let creature = CreatureCombat {
+            name: "Dragon".to_string(),
+            hp: 1000,
+            attack: 45,
+            skill: 45,
+            spells: vec![DamageSpell {
+                name: "Fire Wave".to_string(),
+                damage_type: Some("Fire".to_string()),
+                min_value: 100,
+                max_value: 160,
+                priority: 9,
+            }],
+        };
+        let bare = estimate_combat(&creature, &defender(0, 0, 0));
+        let armored = estimate_combat(&creature, &defender(20, 60, 30));
+
+        assert!(armored.melee_average < bare.melee_average);
+        assert!((armored.spells[0].average_per_turn - 130.0 / 9.0).abs() < 1e-9);
+        assert_eq!(armored.spells[0].average_per_turn, bare.spells[0].average_per_turn);
+    }
+}
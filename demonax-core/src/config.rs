@@ -0,0 +1,193 @@
+//! Settings from `demonax.toml`.
+//!
+//! ```toml
+//! [sftp]
+//! user = "tibia"
+//! identity_file = "/home/web/.ssh/id_ed25519"
+//! cache_dir = "/var/cache/demonax/game"
+//! exclude = ["usr", "log"]
+//!
+//! [s3]
+//! endpoint = "https://s3.eu-central-1.amazonaws.com"
+//! region = "eu-central-1"
+//!
+//! [exclude]
+//! creatures = ["gamemaster.mon", "human.mon"]
+//! raids = ["halloweenhare.evt", "santa.evt"]
+//!
+//! [raids]
+//! seasons = { halloween = ["ghostship.evt"] }
+//! disabled = ["oldorcs.evt"]
+//! log = "log/raids.log"  # raid starts for raid-activity and the raid export
+//!
+//! [bestiary]
+//! races = [[1001, 35]]  # monster ID 1001 counts kills of race 35
+//!
+//! [images]
+//! overrides = { demodras = "dragonlord", ferumbras = "archmage" }
+//!
+//! [experience]
+//! stages = [{ from_level = 1, rate = 5.0 }, { from_level = 50, rate = 2.0 }]
+//!
+//! [[lint.rules]]
+//! name = "bosses-need-loot"
+//! table = "creatures"
+//! condition = "type = 'Boss' AND NOT has_loot"
+//! severity = "error"
+//! message = "All bosses must have loot"
+//!
+//! [hooks]
+//! dir = "/etc/demonax/hooks"
+//! output_dir = "/var/www/exports"
+//!
+//! [[regions]]
+//! name = "rookgaard"
+//! polygon = [[31872, 32128], [32160, 32128], [32160, 32320], [31872, 32320]]
+//!
+//! [[towns]]
+//! name = "Thais"
+//! temple = [32369, 32241, 7]
+//! ```
+
+use crate::error::{DemonaxError, Result};
+use crate::game_math::ExperienceConfig;
+use crate::output::S3Config;
+use crate::processors::{BestiaryConfig, ExcludeConfig, ImageConfig, RaidConfig};
+use crate::geo::{Region, Town, default_regions, default_towns};
+use crate::hooks::HooksConfig;
+use crate::lint::LintConfig;
+use crate::remote::SftpConfig;
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+
+/// File read when no `--config` is given, if it exists
+pub const DEFAULT_CONFIG_FILE: &str = "demonax.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemonaxConfig {
+    /// Connection settings for `sftp://` game paths
+    #[serde(default)]
+    pub sftp: SftpConfig,
+    /// Bucket access for `s3://` outputs
+    #[serde(default)]
+    pub s3: S3Config,
+    /// Game files to skip instead of the built-in lists
+    #[serde(default)]
+    pub exclude: ExcludeConfig,
+    /// Seasonal and disabled raids
+    #[serde(default)]
+    pub raids: RaidConfig,
+    /// Bestiary monster IDs that aren't race numbers
+    #[serde(default)]
+    pub bestiary: BestiaryConfig,
+    /// Creature image names instead of the built-in overrides
+    #[serde(default)]
+    pub images: ImageConfig,
+    /// Experience rate and stages for the level calculators
+    #[serde(default)]
+    pub experience: ExperienceConfig,
+    /// Scripts run after commands
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// Content checks added to the built-in ones
+    #[serde(default)]
+    pub lint: LintConfig,
+    /// Map regions that quests are classified into, replacing the built-in ones
+    #[serde(default = "default_regions")]
+    pub regions: Vec<Region>,
+    /// Towns that NPCs, quests, raids, spawns and houses are assigned to, replacing the built-in ones
+    #[serde(default = "default_towns")]
+    pub towns: Vec<Town>,
+}
+
+impl Default for DemonaxConfig {
+    fn default() -> Self {
+        Self {
+            sftp: SftpConfig::default(),
+            s3: S3Config::default(),
+            exclude: ExcludeConfig::default(),
+            raids: RaidConfig::default(),
+            bestiary: BestiaryConfig::default(),
+            images: ImageConfig::default(),
+            experience: ExperienceConfig::default(),
+            hooks: HooksConfig::default(),
+            lint: LintConfig::default(),
+            regions: default_regions(),
+            towns: default_towns(),
+        }
+    }
+}
+
+impl DemonaxConfig {
+    /// Load the configuration from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DemonaxError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read config from {:?}: {}", path, e),
+            ))
+        })?;
+
+        content.parse()
+    }
+
+    /// Load the given file, or `demonax.toml` in the working directory if it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_FILE)),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+impl FromStr for DemonaxConfig {
+    type Err = DemonaxError;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let config: Self =
+            toml::from_str(content).map_err(|e| DemonaxError::Config(format!("Failed to parse config TOML: {}", e)))?;
+        config.experience.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sftp_settings_default_when_missing() {
+        let config: DemonaxConfig = "".parse().unwrap();
+        assert_eq!(config.sftp, SftpConfig::default());
+
+        let config: DemonaxConfig = "[sftp]\nuser = \"tibia\"\nexclude = [\"usr\"]\n".parse().unwrap();
+        assert_eq!(config.sftp.user.as_deref(), Some("tibia"));
+        assert_eq!(config.sftp.exclude, vec!["usr".to_string()]);
+        assert_eq!(config.sftp.cache_dir, SftpConfig::default().cache_dir);
+
+        assert!("[sftp]\nhost = \"game\"\n".parse::<DemonaxConfig>().is_err());
+    }
+
+    #[test]
+    fn test_exclude_lists_keep_defaults_when_missing() {
+        let config: DemonaxConfig = "".parse().unwrap();
+        assert!(config.exclude.creatures.contains(&"human.mon".to_string()));
+
+        let config: DemonaxConfig = "[exclude]\nraids = []\n".parse().unwrap();
+        assert!(config.exclude.raids.is_empty());
+        assert_eq!(config.exclude.creatures, ExcludeConfig::default().creatures);
+    }
+
+    #[test]
+    fn test_regions_replace_the_built_in_ones() {
+        let config: DemonaxConfig = "".parse().unwrap();
+        assert_eq!(config.regions, default_regions());
+
+        let config: DemonaxConfig = "[[regions]]\nname = \"isle\"\npolygon = [[0, 0], [9, 0], [9, 9]]\n".parse().unwrap();
+        assert_eq!(config.regions.len(), 1);
+        assert_eq!(config.regions[0].name, "isle");
+    }
+}
@@ -0,0 +1,196 @@
+//! Tagged content versions and patch-notes style diffs between them.
+//!
+//! `tag-content` stores every row of the content tables as a JSON object under a
+//! label; two labels (or a label and the live tables) are compared row by row
+//! on each table's natural key.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Content tables as (table, key column, heading in patch notes). Surrogate `id`
+/// columns that aren't the key are left out of the stored rows.
+pub const CONTENT_TABLES: &[(&str, &str, &str)] = &[
+    ("items", "type_id", "Items"),
+    ("creatures", "short_name", "Creatures"),
+    ("spells", "id", "Spells"),
+    ("quests", "id", "Quests"),
+    ("raids", "name", "Raids"),
+];
+
+/// One content row as stored by `tag-content`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentRow {
+    pub table: String,
+    pub key: String,
+    pub name: String,
+    /// Column values as a JSON object
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A changed column of a row
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// A row that was added, removed or changed between two versions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContentChange {
+    pub table: String,
+    pub key: String,
+    pub name: String,
+    pub kind: ChangeKind,
+    /// Changed columns, for `Changed` only
+    pub fields: Vec<FieldChange>,
+}
+
+/// Compare two versions of the content rows. Changes are ordered by table (as in
+/// [`CONTENT_TABLES`]), kind and name.
+pub fn diff_content(old: &[ContentRow], new: &[ContentRow]) -> Vec<ContentChange> {
+    let index = |rows: &[ContentRow]| -> BTreeMap<(String, String), ContentRow> {
+        rows.iter().map(|row| ((row.table.clone(), row.key.clone()), row.clone())).collect()
+    };
+    let (old, new) = (index(old), index(new));
+
+    let mut changes = Vec::new();
+    for ((table, key), row) in &new {
+        let Some(before) = old.get(&(table.clone(), key.clone())) else {
+            changes.push(ContentChange {
+                table: table.clone(),
+                key: key.clone(),
+                name: row.name.clone(),
+                kind: ChangeKind::Added,
+                fields: Vec::new(),
+            });
+            continue;
+        };
+
+        let mut fields: Vec<FieldChange> = row
+            .data
+            .iter()
+            .filter(|(field, value)| before.data.get(*field) != Some(value))
+            .map(|(field, value)| FieldChange {
+                field: field.clone(),
+                old: before.data.get(field).cloned().unwrap_or(serde_json::Value::Null),
+                new: value.clone(),
+            })
+            .collect();
+        // Columns that no longer exist
+        fields.extend(before.data.iter().filter(|(field, _)| !row.data.contains_key(*field)).map(|(field, value)| {
+            FieldChange { field: field.clone(), old: value.clone(), new: serde_json::Value::Null }
+        }));
+
+        if !fields.is_empty() {
+            changes.push(ContentChange {
+                table: table.clone(),
+                key: key.clone(),
+                name: row.name.clone(),
+                kind: ChangeKind::Changed,
+                fields,
+            });
+        }
+    }
+    for ((table, key), row) in &old {
+        if !new.contains_key(&(table.clone(), key.clone())) {
+            changes.push(ContentChange {
+                table: table.clone(),
+                key: key.clone(),
+                name: row.name.clone(),
+                kind: ChangeKind::Removed,
+                fields: Vec::new(),
+            });
+        }
+    }
+
+    let table_order = |table: &str| CONTENT_TABLES.iter().position(|(t, _, _)| *t == table).unwrap_or(usize::MAX);
+    changes.sort_by(|a, b| {
+        (table_order(&a.table), a.kind, &a.name, &a.key).cmp(&(table_order(&b.table), b.kind, &b.name, &b.key))
+    });
+    changes
+}
+
+/// Render changes as Markdown patch notes.
+pub fn patch_notes(from: &str, to: &str, changes: &[ContentChange]) -> String {
+    let mut text = format!("# Content changes from {} to {}\n", from, to);
+    if changes.is_empty() {
+        text.push_str("\nNo changes.\n");
+        return text;
+    }
+
+    for (table, _, heading) in CONTENT_TABLES {
+        let table_changes: Vec<&ContentChange> = changes.iter().filter(|c| c.table == *table).collect();
+        if table_changes.is_empty() {
+            continue;
+        }
+        text.push_str(&format!("\n## {}\n\n", heading));
+        for change in table_changes {
+            match change.kind {
+                ChangeKind::Added => text.push_str(&format!("- New: {} ({})\n", change.name, change.key)),
+                ChangeKind::Removed => text.push_str(&format!("- Removed: {} ({})\n", change.name, change.key)),
+                ChangeKind::Changed => {
+                    let fields: Vec<String> = change
+                        .fields
+                        .iter()
+                        .map(|f| format!("{} {} → {}", f.field, display_value(&f.old), display_value(&f.new)))
+                        .collect();
+                    text.push_str(&format!("- {} ({}): {}\n", change.name, change.key, fields.join("; ")));
+                }
+            }
+        }
+    }
+    text
+}
+
+fn display_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "none".to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(table: &str, key: &str, name: &str, data: serde_json::Value) -> ContentRow {
+        let serde_json::Value::Object(data) = data else { unreachable!() };
+        ContentRow { table: table.to_string(), key: key.to_string(), name: name.to_string(), data }
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_and_changed_rows() {
+        let old = vec![
+            row("creatures", "dragon", "Dragon", serde_json::json!({"hp": 1000, "experience": 700})),
+            row("items", "3349", "a crossbow", serde_json::json!({"weight": 4000})),
+            row("items", "3350", "a bow", serde_json::json!({"weight": 3100})),
+        ];
+        let new = vec![
+            row("creatures", "dragon", "Dragon", serde_json::json!({"hp": 1200, "experience": 700})),
+            row("items", "3349", "a crossbow", serde_json::json!({"weight": 4000})),
+            row("items", "3351", "a shovel", serde_json::json!({"weight": null})),
+        ];
+
+        let changes = diff_content(&old, &new);
+        let summary: Vec<(&str, ChangeKind)> = changes.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![("a shovel", ChangeKind::Added), ("a bow", ChangeKind::Removed), ("Dragon", ChangeKind::Changed)]
+        );
+
+        let notes = patch_notes("patch-1.2", "patch-1.3", &changes);
+        assert!(notes.contains("## Items\n\n- New: a shovel (3351)\n- Removed: a bow (3350)\n"));
+        assert!(notes.contains("## Creatures\n\n- Dragon (dragon): hp 1000 → 1200\n"));
+        assert!(notes.find("## Items").unwrap() < notes.find("## Creatures").unwrap());
+    }
+}
@@ -1,38 +1,423 @@
 use crate::error::{DemonaxError, Result};
 use crate::file_utils;
 use crate::models::{
-    Creature, CreatureLoot, CreatureSpell, ParsedUsrFile, PlayerSnapshot,
+    Creature, CreatureLoot, CreatureSpell, Equipment, ParsedUsrFile, PlayerCard, PlayerSnapshot, vocation_name,
 };
 use crate::parsers;
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{Connection, params, OptionalExtension};
+use rusqlite::{Connection, OpenFlags, params, OptionalExtension};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub struct Database {
     pool: Pool<SqliteConnectionManager>,
+    /// Rows changed through this handle since it was opened, for the audit log
+    changes: Arc<AtomicU64>,
+}
+
+/// A pooled connection that adds the rows it changed to its database's count
+/// when it is returned to the pool.
+pub struct DbConnection {
+    conn: PooledConnection<SqliteConnectionManager>,
+    changes_at_checkout: u64,
+    changes: Arc<AtomicU64>,
+}
+
+impl std::ops::Deref for DbConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl std::ops::DerefMut for DbConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        &mut self.conn
+    }
+}
+
+impl Drop for DbConnection {
+    fn drop(&mut self) {
+        let changed = self.conn.total_changes().saturating_sub(self.changes_at_checkout);
+        self.changes.fetch_add(changed, Ordering::Relaxed);
+        crate::audit::add_rows_changed(changed);
+    }
+}
+
+/// What .usr ingestion does with a player's snapshot that already exists for the date
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExistingSnapshots {
+    /// Keep it and skip the file
+    #[default]
+    Skip,
+    /// Replace it, with its skills, spells, quests, bestiary and harvesting rows.
+    /// A run stores all its files in one transaction, so the date is replaced
+    /// as a whole; players whose file isn't stored keep their old snapshot and
+    /// are logged.
+    Overwrite,
+}
+
+/// How .usr files are ingested
+#[derive(Debug, Clone, Default)]
+pub struct UsrIngestOptions {
+    pub existing: ExistingSnapshots,
+    /// Players to ingest; files of other players are skipped
+    pub players: crate::player_selection::PlayerSelection,
+}
+
+/// Counts of one .usr ingestion run
+#[derive(Debug, Default)]
+struct UsrBatch {
+    /// New or replaced snapshots
+    stored: u32,
+    errors: u32,
+    /// Players whose snapshot was stored
+    players: HashSet<i32>,
+}
+
+/// Tables with one row per snapshot and something of it, replaced with the snapshot
+pub const SNAPSHOT_CHILD_TABLES: &[&str] = &["daily_skills", "player_spells", "daily_quests", "daily_bestiary", "daily_harvesting"];
+
+/// `data_migrations` name of the economy_inflow backfill for databases that predate it.
+const ECONOMY_INFLOW_BACKFILL: &str = "economy_inflow_backfill";
+
+/// Players with an earlier snapshot needed before a loss of experience counts
+/// as a rollback, so a few deaths on a quiet day aren't mistaken for one
+pub const ROLLBACK_MIN_PLAYERS: i64 = 5;
+
+/// What each table holds, for `demonax schema`. Keep in sync with `run_migrations`.
+pub const TABLE_DOCS: &[(&str, &str)] = &[
+    ("players", "One row per player character seen in any .usr snapshot"),
+    ("accounts", "One row per account number seen in any .usr snapshot"),
+    ("player_name_history", "Renames of a player, with the date the new name was first seen"),
+    ("daily_snapshots", "A player's level, skills and equipment on one snapshot date"),
+    ("world_stats", "Players seen, experience and levels per snapshot date"),
+    ("daily_skills", "Every Skill tuple of a snapshot, with its tries"),
+    ("player_spells", "Spells a player had learned on a snapshot date, from the .usr SpellList"),
+    ("daily_quests", "Quest values of a snapshot"),
+    ("daily_bestiary", "Kill counts per creature race of a snapshot"),
+    ("bestiary_races", "Bestiary monster IDs that aren't the race number of their creature, from [bestiary] in demonax.toml"),
+    ("economy_inflow", "Estimated gold and items looted per creature per snapshot date"),
+    ("daily_harvesting", "Harvest counts per creature race of a snapshot"),
+    ("creatures", "Creatures from mon/*.mon"),
+    ("image_overrides", "Creatures drawn with an image not named after them, from [images] in demonax.toml"),
+    ("creature_loot", "Loot table entries of a creature"),
+    ("creature_flags", "Flags of a creature (e.g. Unpushable)"),
+    ("creature_skills", "Skills of a creature (HitPoints, FistFighting, ...)"),
+    ("creature_spells", "Spells of a creature, interpreted from the .mon spell lines"),
+    ("items", "Takeable items from dat/objects.srv"),
+    ("item_loot_sources", "Creatures dropping an item, for item pages"),
+    ("item_prices", "NPC trade offers from npc/*.npc"),
+    ("npcs", "Home positions of the NPCs with trade offers"),
+    ("towns", "Towns with their temple and bounds, from the settings file"),
+    ("quests", "Quest chests found in map sectors"),
+    ("quest_keys", "Keys with a key number in quest chests"),
+    ("doors", "Locked, level and quest doors found in map sectors"),
+    ("raids", "Raids from mon/*.evt"),
+    ("raid_executions", "Raid starts from the game server's raid log ([raids] log in demonax.toml)"),
+    ("spawns", "Creature spawns from dat/monster.db"),
+    ("spells", "Player spells and runes from magic.cc"),
+    ("spell_teachers", "NPCs teaching spells, per vocation"),
+    ("harvesting_data", "Corpse harvesting rules (tool, corpse and reward)"),
+    ("rune_sellers", "NPCs selling runes, wands and rods"),
+    ("houses", "Houses from dat/houses.dat"),
+    ("house_owners", "Current house owners from dat/owners.dat"),
+    ("daily_house_owners", "House owners per snapshot date"),
+    ("run_journal", "Completed stages of interrupted update-all runs"),
+    ("data_migrations", "One-off data migrations already run on this database"),
+    ("pending_gifts", "Presents queued with queue-present and the result of applying them at the next save"),
+    ("content_gap_runs", "One row per update-spells run that recorded content gaps"),
+    ("content_gaps", "Spells without teachers and runes without sellers, per run"),
+    ("content_tags", "Labelled versions of the content tables, from tag-content"),
+    ("content_tag_rows", "Rows of the content tables as they were when tagged"),
+    ("creature_notes", "Hand-written notes on creatures for the website, from import-overlay"),
+    ("events", "Double-exp weekends, patches and rollbacks, from add-event, to annotate time series"),
+    ("log_events", "Deaths, level advances and rare loot from the game server log, from process-log"),
+    ("log_offsets", "How far process-log has read each game server log"),
+    ("online_samples", "Players online every few minutes, from sample-online"),
+    ("item_notes", "Hand-written notes on items for the website, from import-overlay"),
+    ("audit_log", "Commands that changed the database: who ran them, how many rows and how long"),
+];
+
+/// Notes on columns whose meaning isn't obvious from the name, as (table, column, doc)
+pub const COLUMN_DOCS: &[(&str, &str, &str)] = &[
+    ("players", "id", "Character ID from the .usr file"),
+    ("players", "hidden", "1 if the player opted out of the website; exports and renders skip them"),
+    ("players", "account_id", "Account number from the .usr Account line of the latest snapshot; NULL when not given"),
+    ("daily_snapshots", "snapshot_date", "YYYY-MM-DD"),
+    ("world_stats", "new_players", "Players first seen on this date"),
+    ("world_stats", "total_gold", "Gold in circulation; NULL until depots are parsed"),
+    ("daily_snapshots", "equipment_json", r#"{"version":2,"slots":[...]} in slot order helmet..ammo"#),
+    ("daily_snapshots", "profession", "1-4 (knight, paladin, sorcerer, druid), +10 when promoted"),
+    ("daily_snapshots", "blessings", "Number of blessings from the .usr Blessings block; NULL = unknown"),
+    ("daily_snapshots", "premium_days", "Premium days left on the account from the .usr PremiumDays line; 0 = free account, NULL = unknown"),
+    ("daily_snapshots", "after_rollback", "1 on the first snapshot date after a detected rollback; changes since the previous snapshot are meaningless"),
+    ("daily_skills", "skill_id", "0 level, 1 magic level, 6 shielding, 7 distance, 8 sword, 9 club, 10 axe, 11 fist, 13 fishing"),
+    ("daily_skills", "modifier", "Bonus on top of the value, e.g. from equipment"),
+    ("daily_skills", "tries", "Tries gathered so far; experience for the level"),
+    ("daily_skills", "next_level", "Tries needed for the next value, for skill percentages"),
+    ("player_spells", "spell_id", "Spell number, as in spells.id"),
+    ("daily_quests", "quest_id", "Quest value number from the .usr QuestValues"),
+    ("daily_bestiary", "monster_id", "Creature race number, unless bestiary_races maps it to another race"),
+    ("economy_inflow", "kills", "Growth of the bestiary counts of players who have an earlier snapshot"),
+    ("economy_inflow", "gold", "Expected face value of the coins dropped by these kills"),
+    ("economy_inflow", "items", "Expected number of other items dropped"),
+    ("economy_inflow", "item_value", "Expected NPC sell value of those items; items no NPC buys count as 0"),
+    ("daily_harvesting", "race_id", "Creature race number"),
+    ("creatures", "short_name", "Lower-case name without spaces"),
+    ("creatures", "race", "Race number used by raids, bestiary and harvesting"),
+    ("creatures", "html_name", ".mon file name without extension"),
+    ("creatures", "spell_damage_per_turn", "Expected spell damage per turn, for sorting by danger"),
+    ("creature_loot", "item_id", "Item type ID"),
+    ("creature_loot", "chance_raw", "0-999; drop rate is (chance_raw + 1) / 999"),
+    ("creature_spells", "priority", "The spell is cast with a 1-in-priority chance each turn"),
+    ("creature_spells", "area_tiles", "Number of tiles the spell affects"),
+    ("creature_spells", "summon_name", "Name of the summoned creature, from summon_race_id"),
+    ("creature_spells", "area_mask", r#"JSON array of rows, "x" affected, "c" the caster of a cone (facing up)"#),
+    ("items", "attributes", "JSON object of objects.srv attributes"),
+    ("items", "flag_bits", "Known flags as an ItemFlags bitset; other flags are only in flags"),
+    ("items", "category", "rune, container, liquid_container, weapon, ammunition, shield, armor, clothing, food, key or other"),
+    ("item_loot_sources", "item_id", "Item type ID"),
+    ("item_prices", "item_id", "Item type ID"),
+    ("item_prices", "mode", "buy: players buy from the NPC; sell: players sell to the NPC"),
+    ("item_prices", "price", "Gold for `amount` items"),
+    ("item_prices", "amount", "Number of items traded for the price"),
+    ("item_prices", "data", "Fluid type or charges of the traded item"),
+    ("item_prices", "bulk", "1 if players can also trade any count at the same price per item"),
+    ("npcs", "name", "NPC name as in item_prices.npc_name"),
+    ("houses", "pos_x", "First field of the house, used to place NPCs in towns"),
+    ("raids", "spawn_composition_json", r#"[{"race":..,"min":..,"max":..}]"#),
+    ("quests", "town", "Town of the quest's first chest"),
+    ("npcs", "town", "Town of the NPC's home; see the npc_towns view for the fallback"),
+    ("towns", "bounds_json", "JSON array of [x, y] polygon corners; empty when the town covers what is nearest its temple"),
+    ("houses", "town", "Area name from houseareas.dat, or the town of the house's position when the area is unnamed"),
+    ("raids", "pos_x", "Center of the raid's first spawn"),
+    ("raids", "town", "Town of the raid's first spawn"),
+    ("quest_keys", "key_number", "Keyhole number of the doors the key opens"),
+    ("doors", "keyhole_number", "Key number of the key that opens the door"),
+    ("doors", "level", "Lowest level that may pass"),
+    ("doors", "quest_number", "Quest value that must be set to pass, with quest_value"),
+    ("spawns", "race", "Creature race number"),
+    ("spawns", "radius", "Fields around the center the creatures appear in"),
+    ("spawns", "amount", "Creatures alive at once"),
+    ("spawns", "regen_seconds", "Seconds until a killed creature respawns"),
+    ("spawns", "town", "Town of the spawn's center"),
+    ("quests", "region", "Map region of the quest's first chest, e.g. rookgaard; from the [[regions]] config"),
+    ("raids", "season", "Season the raid runs in, e.g. halloween; from the [raids] config or the file name"),
+    ("raids", "enabled", "0 for raids disabled in the [raids] config; left out of exports"),
+    ("spells", "spell_type", "attack, area, healing, summon, support, utility or other; from the spell's handler in magic.cc when found"),
+    ("spells", "damage_element", "Element of the DAMAGE_* constant in the spell's handler"),
+    ("spells", "flags", "Spell->Flags bits from magic.cc; known bits are also decoded into columns"),
+    ("spell_teachers", "unclassified_line", "NPC line whose vocation condition could not be parsed"),
+    ("harvesting_data", "race_id", "Creature race number"),
+    ("rune_sellers", "item_id", "Item type ID"),
+    ("house_owners", "owner_id", "Player ID"),
+    ("daily_house_owners", "owner_id", "Player ID, NULL when unowned"),
+    ("run_journal", "batch", "Batch key within a stage, e.g. 2024-05-01#0"),
+    ("pending_gifts", "player_name", "Recipient; NULL gives the present to every player"),
+    ("pending_gifts", "present_json", "The present config (container and items) as JSON"),
+    ("pending_gifts", "queued_by", "DEMONAX_USER, USER or USERNAME of the queue-present process"),
+    ("pending_gifts", "applied_at", "NULL while the gift is pending"),
+    ("pending_gifts", "results_json", r#"[{"player_name":...,"outcome":"gifted"}, ...] once applied"#),
+    ("content_gaps", "kind", "untaught_spell or unsold_rune"),
+    ("content_tag_rows", "content_table", "Source table: items, creatures, spells, quests or raids"),
+    ("content_tag_rows", "row_key", "Natural key of the row, e.g. items.type_id or creatures.short_name"),
+    ("content_tag_rows", "data_json", "The row's columns as a JSON object"),
+    ("creatures", "image_name", "The short name; image_overrides may replace it, see the creature_images view"),
+    ("image_overrides", "image_name", "Image file name without the .gif or .png extension"),
+    ("creature_notes", "short_name", "creatures.short_name; kept when no creature has it, so notes survive a creature being renamed"),
+    ("item_notes", "type_id", "items.type_id; kept when no item has it"),
+    ("raid_executions", "started_at", "YYYY-MM-DD HH:MM:SS, server time"),
+    ("events", "kind", "double_exp, patch, rollback or other"),
+    ("events", "end_date", "Last day of the event (inclusive); the start date for one-day events"),
+    ("log_events", "kind", "death, level_up or rare_loot"),
+    ("log_events", "level", "Level died at or advanced to"),
+    ("log_events", "creature", "Killer of a death (a creature or player name) or the creature a rare item was looted from"),
+    ("online_samples", "sampled_at", "YYYY-MM-DD HH:MM:SS, local time"),
+    ("online_samples", "max_players", "Player limit the status port reported; null for who-is-online files"),
+    ("online_samples", "source", "Who-is-online file or status://host:port the sample came from"),
+    ("log_offsets", "read_bytes", "Bytes of complete lines read; a shorter log was rotated and is read from the start"),
+    ("audit_log", "arguments", "Command line after the program name"),
+    ("audit_log", "user", "DEMONAX_USER, USER or USERNAME of the process"),
+    ("audit_log", "rows_affected", "Rows inserted, updated or deleted, including by rolled back transactions"),
+];
+
+/// Relationships that aren't declared as foreign keys, as (table, column, referenced table, referenced column)
+pub const IMPLICIT_REFERENCES: &[(&str, &str, &str, &str)] = &[
+    ("creature_loot", "item_id", "items", "type_id"),
+    ("item_loot_sources", "item_id", "items", "type_id"),
+    ("item_loot_sources", "creature_id", "creatures", "id"),
+    ("item_prices", "item_id", "items", "type_id"),
+    ("item_prices", "npc_name", "npcs", "name"),
+    ("daily_bestiary", "monster_id", "creatures", "race"),
+    ("bestiary_races", "race", "creatures", "race"),
+    ("daily_harvesting", "race_id", "creatures", "race"),
+    ("harvesting_data", "race_id", "creatures", "race"),
+    ("harvesting_data", "tool_id", "items", "type_id"),
+    ("harvesting_data", "reward_id", "items", "type_id"),
+    ("spawns", "race", "creatures", "race"),
+    ("raid_executions", "raid_name", "raids", "name"),
+    ("quest_keys", "quest_id", "quests", "id"),
+    ("quest_keys", "type_id", "items", "type_id"),
+    ("quest_keys", "key_number", "doors", "keyhole_number"),
+    ("creature_spells", "summon_race_id", "creatures", "race"),
+    ("spell_teachers", "spell_id", "spells", "id"),
+    ("player_spells", "spell_id", "spells", "id"),
+    ("rune_sellers", "item_id", "items", "type_id"),
+    ("rune_sellers", "spell_id", "spells", "id"),
+    ("content_gaps", "spell_id", "spells", "id"),
+    ("house_owners", "owner_id", "players", "id"),
+    ("daily_house_owners", "owner_id", "players", "id"),
+    ("players", "account_id", "accounts", "id"),
+    ("image_overrides", "short_name", "creatures", "short_name"),
+    ("creature_notes", "short_name", "creatures", "short_name"),
+    ("item_notes", "type_id", "items", "type_id"),
+    ("log_events", "player_name", "players", "name"),
+];
+
+/// How a file database is opened.
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// Open without write access and skip migrations (reporting and export)
+    pub read_only: bool,
+    /// Use WAL journaling so readers (e.g. the website) aren't blocked while ingestion writes
+    pub wal: bool,
+    /// Maximum number of pooled connections
+    pub max_connections: u32,
+    /// How long a connection waits for a lock held by another process
+    pub busy_timeout: std::time::Duration,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            wal: true,
+            max_connections: 10,
+            busy_timeout: std::time::Duration::from_secs(5),
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// Configuration for read-only access to an existing database
+    pub fn read_only() -> Self {
+        Self {
+            read_only: true,
+            ..Self::default()
+        }
+    }
 }
 
 impl Database {
+    /// Open (or create) a database for writing with the default configuration.
     pub fn new(path: &std::path::Path) -> Result<Self> {
-        let manager = SqliteConnectionManager::file(path)
+        Self::with_config(path, &DatabaseConfig::default())
+    }
+
+    /// Open an existing database read-only. Fails if the file does not exist.
+    pub fn open_read_only(path: &std::path::Path) -> Result<Self> {
+        Self::with_config(path, &DatabaseConfig::read_only())
+    }
+
+    pub fn with_config(path: &std::path::Path, config: &DatabaseConfig) -> Result<Self> {
+        if config.read_only && !path.exists() {
+            return Err(DemonaxError::NotFound(format!("Database not found: {}", path.display())));
+        }
+
+        let busy_timeout = config.busy_timeout;
+        let mut manager = SqliteConnectionManager::file(path)
+            .with_init(move |conn| {
+                conn.pragma_update(None, "foreign_keys", "ON")?;
+                conn.busy_timeout(busy_timeout)?;
+                Ok(())
+            });
+        if config.read_only {
+            manager = manager.with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX);
+        }
+        let pool = Pool::builder()
+            .max_size(config.max_connections)
+            .build(manager)
+            .map_err(DemonaxError::Pool)?;
+
+        let db = Self { pool, changes: Arc::default() };
+        if !config.read_only {
+            if config.wal {
+                // journal_mode is stored in the file, so setting it once covers every connection
+                db.connection()?
+                    .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+            }
+            db.run_migrations()?;
+            // Schema upgrades aren't the command's doing
+            crate::audit::discount_rows_changed(db.changes.swap(0, Ordering::Relaxed));
+        }
+        Ok(db)
+    }
+
+    /// Open a private in-memory database (used by tests and fixtures).
+    ///
+    /// Every SQLite `:memory:` connection is a separate database, so the pool
+    /// holds exactly one connection that is never recycled.
+    pub fn in_memory() -> Result<Self> {
+        let manager = SqliteConnectionManager::memory()
             .with_init(|conn| {
                 conn.pragma_update(None, "foreign_keys", "ON")?;
                 Ok(())
             });
         let pool = Pool::builder()
-            .max_size(10)
+            .max_size(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
             .build(manager)
-            .map_err(|e| DemonaxError::Pool(e))?;
+            .map_err(DemonaxError::Pool)?;
 
-        let db = Self { pool };
+        let db = Self { pool, changes: Arc::default() };
         db.run_migrations()?;
+        crate::audit::discount_rows_changed(db.changes.swap(0, Ordering::Relaxed));
         Ok(db)
     }
 
-    pub fn connection(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
-        self.pool.get().map_err(|e| DemonaxError::Pool(e))
+    pub fn connection(&self) -> Result<DbConnection> {
+        let conn = self.pool.get().map_err(DemonaxError::Pool)?;
+        Ok(DbConnection { changes_at_checkout: conn.total_changes(), conn, changes: self.changes.clone() })
+    }
+
+    /// Rows changed through this handle so far, excluding schema migrations
+    pub fn rows_changed(&self) -> u64 {
+        self.changes.load(Ordering::Relaxed)
+    }
+
+    /// Record a command that changed the database in `audit_log`
+    pub fn record_audit(&self, invocation: &crate::audit::Invocation, rows_affected: u64) -> Result<()> {
+        self.connection()?.execute(
+            "INSERT INTO audit_log (command, arguments, user, rows_affected, duration_ms) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                invocation.command,
+                invocation.arguments,
+                invocation.user,
+                rows_affected as i64,
+                invocation.elapsed().as_millis() as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Number of rows in a table of the schema
+    pub fn row_count(&self, table: &str) -> Result<i64> {
+        let conn = self.connection()?;
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            params![table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(DemonaxError::NotFound(format!("No table named '{}'", table)));
+        }
+        Ok(conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))?)
     }
 
     fn run_migrations(&self) -> Result<()> {
@@ -52,6 +437,24 @@ impl Database {
                 last_seen DATE NOT NULL
             );
 
+            -- Accounts of the characters, from the .usr Account line
+            CREATE TABLE IF NOT EXISTS accounts (
+                id INTEGER PRIMARY KEY,
+                first_seen DATE NOT NULL,
+                last_seen DATE NOT NULL
+            );
+
+            -- Old and new name of each rename, for redirecting old profile URLs
+            CREATE TABLE IF NOT EXISTS player_name_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                player_id INTEGER NOT NULL,
+                old_name TEXT NOT NULL,
+                new_name TEXT NOT NULL,
+                effective_date DATE NOT NULL,
+                FOREIGN KEY (player_id) REFERENCES players(id) ON DELETE CASCADE,
+                UNIQUE(player_id, effective_date)
+            );
+
             CREATE TABLE IF NOT EXISTS daily_snapshots (
                 id INTEGER PRIMARY KEY,
                 player_id INTEGER NOT NULL,
@@ -73,6 +476,42 @@ impl Database {
                 UNIQUE(player_id, snapshot_date)
             );
 
+            CREATE INDEX IF NOT EXISTS idx_daily_snapshots_snapshot_date ON daily_snapshots(snapshot_date, level);
+
+            -- Aggregates per snapshot date, maintained by process-usr
+            CREATE TABLE IF NOT EXISTS world_stats (
+                snapshot_date DATE PRIMARY KEY,
+                players_seen INTEGER NOT NULL,
+                total_experience INTEGER NOT NULL,
+                median_level REAL NOT NULL,
+                new_players INTEGER NOT NULL,
+                total_gold INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS daily_skills (
+                id INTEGER PRIMARY KEY,
+                snapshot_id INTEGER NOT NULL,
+                skill_id INTEGER NOT NULL,
+                value INTEGER NOT NULL,
+                max INTEGER NOT NULL,
+                min INTEGER NOT NULL,
+                modifier INTEGER NOT NULL,
+                tries BIGINT NOT NULL,
+                next_level BIGINT NOT NULL,
+                FOREIGN KEY (snapshot_id) REFERENCES daily_snapshots(id) ON DELETE CASCADE,
+                UNIQUE(snapshot_id, skill_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS player_spells (
+                id INTEGER PRIMARY KEY,
+                snapshot_id INTEGER NOT NULL,
+                spell_id INTEGER NOT NULL,
+                FOREIGN KEY (snapshot_id) REFERENCES daily_snapshots(id) ON DELETE CASCADE,
+                UNIQUE(snapshot_id, spell_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_player_spells_spell_id ON player_spells(spell_id);
+
             CREATE TABLE IF NOT EXISTS daily_quests (
                 id INTEGER PRIMARY KEY,
                 snapshot_id INTEGER NOT NULL,
@@ -82,6 +521,8 @@ impl Database {
                 UNIQUE(snapshot_id, quest_id)
             );
 
+            CREATE INDEX IF NOT EXISTS idx_daily_quests_quest_id ON daily_quests(quest_id);
+
             CREATE TABLE IF NOT EXISTS daily_bestiary (
                 id INTEGER PRIMARY KEY,
                 snapshot_id INTEGER NOT NULL,
@@ -91,6 +532,28 @@ impl Database {
                 UNIQUE(snapshot_id, monster_id)
             );
 
+            CREATE TABLE IF NOT EXISTS bestiary_races (
+                monster_id INTEGER PRIMARY KEY,
+                race INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS image_overrides (
+                short_name TEXT PRIMARY KEY,
+                image_name TEXT NOT NULL
+            );
+
+            -- Loot entering the economy, maintained by process-usr, update-creatures and update-items-core
+            CREATE TABLE IF NOT EXISTS economy_inflow (
+                snapshot_date DATE NOT NULL,
+                creature_id INTEGER NOT NULL,
+                kills INTEGER NOT NULL,
+                gold INTEGER NOT NULL,
+                items REAL NOT NULL,
+                item_value INTEGER NOT NULL,
+                PRIMARY KEY (snapshot_date, creature_id),
+                FOREIGN KEY (creature_id) REFERENCES creatures(id) ON DELETE CASCADE
+            );
+
             CREATE TABLE IF NOT EXISTS daily_harvesting (
                 id INTEGER PRIMARY KEY,
                 snapshot_id INTEGER NOT NULL,
@@ -167,6 +630,8 @@ impl Database {
                 range INTEGER,
                 area_size TEXT,
                 angle INTEGER,
+                area_tiles INTEGER,
+                area_mask TEXT,
 
                 -- Impact details
                 impact_type INTEGER NOT NULL,
@@ -186,6 +651,7 @@ impl Database {
                 -- Summon specific
                 summon_race_id INTEGER,
                 summon_count INTEGER,
+                summon_name TEXT,
 
                 -- Misc
                 priority INTEGER NOT NULL,
@@ -214,7 +680,9 @@ impl Database {
                 worth INTEGER,
                 flags TEXT,
                 attributes TEXT,
-                image_link TEXT
+                image_link TEXT,
+                flag_bits INTEGER NOT NULL DEFAULT 0,
+                category TEXT
             );
 
             CREATE TABLE IF NOT EXISTS item_loot_sources (
@@ -235,6 +703,22 @@ impl Database {
 
             CREATE INDEX IF NOT EXISTS idx_item_loot_sources_item_id ON item_loot_sources(item_id);
             CREATE INDEX IF NOT EXISTS idx_item_loot_sources_creature_id ON item_loot_sources(creature_id);
+            CREATE TABLE IF NOT EXISTS npcs (
+                name TEXT PRIMARY KEY,
+                home_x INTEGER NOT NULL,
+                home_y INTEGER NOT NULL,
+                home_z INTEGER NOT NULL,
+                town TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS towns (
+                name TEXT PRIMARY KEY,
+                temple_x INTEGER NOT NULL,
+                temple_y INTEGER NOT NULL,
+                temple_z INTEGER NOT NULL,
+                bounds_json TEXT NOT NULL DEFAULT '[]'
+            );
+
             CREATE INDEX IF NOT EXISTS idx_item_prices_item_id ON item_prices(item_id);
             CREATE INDEX IF NOT EXISTS idx_item_prices_npc_name ON item_prices(npc_name);
 
@@ -244,7 +728,29 @@ impl Database {
                 name TEXT NOT NULL,
                 description TEXT,
                 chest_location TEXT,
-                reward_items_json TEXT NOT NULL DEFAULT '[]'
+                reward_items_json TEXT NOT NULL DEFAULT '[]',
+                region TEXT,
+                town TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS quest_keys (
+                quest_id INTEGER NOT NULL,
+                key_number INTEGER NOT NULL,
+                type_id INTEGER NOT NULL,
+                PRIMARY KEY (quest_id, key_number)
+            );
+
+            -- Doors from map sectors, for quest routes
+            CREATE TABLE IF NOT EXISTS doors (
+                pos_x INTEGER NOT NULL,
+                pos_y INTEGER NOT NULL,
+                pos_z INTEGER NOT NULL,
+                type_id INTEGER NOT NULL,
+                keyhole_number INTEGER,
+                level INTEGER,
+                quest_number INTEGER,
+                quest_value INTEGER,
+                PRIMARY KEY (pos_x, pos_y, pos_z, type_id)
             );
 
             CREATE TABLE IF NOT EXISTS raids (
@@ -256,9 +762,36 @@ impl Database {
                 interval_days REAL,
                 message TEXT NOT NULL DEFAULT '',
                 creatures TEXT NOT NULL DEFAULT '',
-                spawn_composition_json TEXT NOT NULL DEFAULT '[]'
+                spawn_composition_json TEXT NOT NULL DEFAULT '[]',
+                season TEXT,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                pos_x INTEGER,
+                pos_y INTEGER,
+                pos_z INTEGER,
+                town TEXT
             );
 
+            -- Raid starts from the game server's raid log, kept across update-raids runs
+            CREATE TABLE IF NOT EXISTS raid_executions (
+                raid_name TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                PRIMARY KEY (raid_name, started_at)
+            );
+
+            CREATE TABLE IF NOT EXISTS spawns (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                race INTEGER NOT NULL,
+                pos_x INTEGER NOT NULL,
+                pos_y INTEGER NOT NULL,
+                pos_z INTEGER NOT NULL,
+                radius INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                regen_seconds INTEGER NOT NULL,
+                town TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_spawns_race ON spawns(race);
+
             CREATE TABLE IF NOT EXISTS spells (
                 id INTEGER PRIMARY KEY,
                 name TEXT NOT NULL,
@@ -279,10 +812,12 @@ impl Database {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 npc_name TEXT NOT NULL,
                 spell_name TEXT NOT NULL,
+                spell_words TEXT,
                 spell_id INTEGER NOT NULL,
                 vocation TEXT NOT NULL,
                 price INTEGER NOT NULL,
                 level_required INTEGER,
+                unclassified_line TEXT,
                 UNIQUE(npc_name, spell_id, vocation)
             );
 
@@ -318,10 +853,393 @@ impl Database {
             CREATE INDEX IF NOT EXISTS idx_rune_sellers_item_id ON rune_sellers(item_id);
             CREATE INDEX IF NOT EXISTS idx_rune_sellers_spell_id ON rune_sellers(spell_id);
             CREATE INDEX IF NOT EXISTS idx_rune_sellers_npc_name ON rune_sellers(npc_name);
+
+            -- House schema
+            CREATE TABLE IF NOT EXISTS houses (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                town TEXT NOT NULL,
+                rent INTEGER NOT NULL,
+                size INTEGER NOT NULL,
+                guild_house INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS house_owners (
+                house_id INTEGER PRIMARY KEY,
+                owner_id INTEGER NOT NULL,
+                last_payment INTEGER,
+                FOREIGN KEY (house_id) REFERENCES houses(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS daily_house_owners (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                snapshot_date DATE NOT NULL,
+                house_id INTEGER NOT NULL,
+                owner_id INTEGER,
+                FOREIGN KEY (house_id) REFERENCES houses(id) ON DELETE CASCADE,
+                UNIQUE(snapshot_date, house_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_house_owners_owner_id ON house_owners(owner_id);
+            CREATE INDEX IF NOT EXISTS idx_daily_house_owners_owner_id ON daily_house_owners(owner_id);
+
+            -- Commands that changed the database, written when the Database handle is dropped
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                command TEXT NOT NULL,
+                arguments TEXT NOT NULL,
+                user TEXT,
+                rows_affected INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            -- Completed stages of long multi-stage runs, used to resume after an interruption
+            CREATE TABLE IF NOT EXISTS run_journal (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_name TEXT NOT NULL,
+                stage TEXT NOT NULL,
+                batch TEXT NOT NULL DEFAULT '',
+                rows_written INTEGER NOT NULL,
+                completed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(run_name, stage, batch)
+            );
+
+            -- One-off data migrations already run on this database
+            CREATE TABLE IF NOT EXISTS data_migrations (
+                name TEXT PRIMARY KEY,
+                applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            -- Presents waiting for the next server save, kept after they are applied as the gift ledger
+            CREATE TABLE IF NOT EXISTS pending_gifts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                player_name TEXT,
+                present_json TEXT NOT NULL,
+                target_slot INTEGER NOT NULL,
+                skip_over_capacity INTEGER NOT NULL DEFAULT 0,
+                queued_by TEXT,
+                queued_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                applied_at TEXT,
+                gifted INTEGER,
+                skipped INTEGER,
+                errors INTEGER,
+                results_json TEXT
+            );
+
+            -- Spells nobody teaches and runes nobody sells, recorded on every update-spells run
+            CREATE TABLE IF NOT EXISTS content_gap_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS content_gaps (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id INTEGER NOT NULL,
+                kind TEXT NOT NULL CHECK(kind IN ('untaught_spell', 'unsold_rune')),
+                spell_id INTEGER NOT NULL,
+                spell_name TEXT NOT NULL,
+                words TEXT NOT NULL,
+                level INTEGER NOT NULL,
+                FOREIGN KEY (run_id) REFERENCES content_gap_runs(id) ON DELETE CASCADE,
+                UNIQUE(run_id, kind, spell_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_content_gaps_kind_spell_id ON content_gaps(kind, spell_id);
+
+            -- Labelled copies of the content tables, compared by content-diff
+            CREATE TABLE IF NOT EXISTS content_tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                label TEXT NOT NULL UNIQUE,
+                tagged_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS content_tag_rows (
+                tag_id INTEGER NOT NULL,
+                content_table TEXT NOT NULL,
+                row_key TEXT NOT NULL,
+                name TEXT NOT NULL,
+                data_json TEXT NOT NULL,
+                PRIMARY KEY (tag_id, content_table, row_key),
+                FOREIGN KEY (tag_id) REFERENCES content_tags(id) ON DELETE CASCADE
+            );
+
+            -- Windows of dates shown on the time series, maintained with add-event
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                name TEXT NOT NULL,
+                start_date DATE NOT NULL,
+                end_date DATE NOT NULL,
+                description TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            -- Deaths, level advances and rare loot from the game server log, see logs.rs
+            CREATE TABLE IF NOT EXISTS log_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                occurred_at TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                player_name TEXT NOT NULL,
+                level INTEGER,
+                creature TEXT,
+                item TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_log_events_occurred_at ON log_events(occurred_at);
+
+            -- Players online, sampled by sample-online
+            CREATE TABLE IF NOT EXISTS online_samples (
+                sampled_at TEXT PRIMARY KEY,
+                players INTEGER NOT NULL,
+                max_players INTEGER,
+                source TEXT NOT NULL
+            );
+
+            -- Where the previous process-log run stopped reading each log
+            CREATE TABLE IF NOT EXISTS log_offsets (
+                path TEXT PRIMARY KEY,
+                read_bytes INTEGER NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            -- Hand-written overlays, not replaced by the update commands
+            CREATE TABLE IF NOT EXISTS creature_notes (
+                short_name TEXT PRIMARY KEY,
+                note TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS item_notes (
+                type_id INTEGER PRIMARY KEY,
+                note TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            "#,
+        )?;
+
+        // Columns added after the initial schema
+        Self::add_column_if_missing(&tx, "spell_teachers", "unclassified_line", "TEXT")?;
+        Self::add_column_if_missing(&tx, "spell_teachers", "spell_words", "TEXT")?;
+        Self::add_column_if_missing(&tx, "daily_snapshots", "profession", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "daily_snapshots", "premium_days", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "daily_snapshots", "blessings", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "daily_snapshots", "after_rollback", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "creatures", "attack", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "creatures", "defend", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "creatures", "armor", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "creatures", "spell_damage_per_turn", "REAL NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "item_prices", "amount", "INTEGER NOT NULL DEFAULT 1")?;
+        Self::add_column_if_missing(&tx, "item_prices", "data", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "item_prices", "bulk", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "creature_spells", "area_tiles", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "creature_spells", "area_mask", "TEXT")?;
+        Self::add_column_if_missing(&tx, "creature_spells", "summon_name", "TEXT")?;
+        Self::add_column_if_missing(&tx, "quests", "region", "TEXT")?;
+        Self::add_column_if_missing(&tx, "quests", "town", "TEXT")?;
+        Self::add_column_if_missing(&tx, "npcs", "town", "TEXT")?;
+        Self::add_column_if_missing(&tx, "raids", "season", "TEXT")?;
+        Self::add_column_if_missing(&tx, "raids", "enabled", "INTEGER NOT NULL DEFAULT 1")?;
+        Self::add_column_if_missing(&tx, "raids", "pos_x", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "raids", "pos_y", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "raids", "pos_z", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "raids", "town", "TEXT")?;
+        Self::add_column_if_missing(&tx, "houses", "pos_x", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "houses", "pos_y", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "houses", "pos_z", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "spells", "damage_element", "TEXT")?;
+        Self::add_column_if_missing(&tx, "players", "hidden", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "players", "account_id", "INTEGER")?;
+        Self::add_column_if_missing(&tx, "items", "flag_bits", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&tx, "items", "category", "TEXT")?;
+        tx.execute("CREATE INDEX IF NOT EXISTS idx_items_category ON items(category)", [])?;
+        for (_, column, _) in crate::models::SPELL_FLAGS {
+            Self::add_column_if_missing(&tx, "spells", column, "INTEGER NOT NULL DEFAULT 0")?;
+        }
+        Self::decode_spell_flags(&tx)?;
+        Self::backfill_item_categories(&tx)?;
+        Self::backfill_spell_areas(&tx)?;
+
+        // Snapshot dates ingested before world_stats existed
+        let missing: Vec<String> = tx
+            .prepare(
+                "SELECT DISTINCT snapshot_date FROM daily_snapshots
+                 WHERE snapshot_date NOT IN (SELECT snapshot_date FROM world_stats)
+                 ORDER BY snapshot_date",
+            )?
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        for snapshot_date in &missing {
+            Self::refresh_world_stats_for(&tx, snapshot_date)?;
+        }
+
+        // Views are recreated on every start so they follow the columns above
+        tx.execute_batch(
+            r#"
+            -- An NPC belongs to its assigned town, or else to the town of the nearest house within 256 fields
+            DROP VIEW IF EXISTS npc_towns;
+            CREATE VIEW npc_towns AS
+            SELECT npc_name, COALESCE(assigned_town, town) AS town FROM (
+                SELECT n.name AS npc_name, n.town AS assigned_town, h.town,
+                       ROW_NUMBER() OVER (
+                           PARTITION BY n.name
+                           ORDER BY (h.pos_x - n.home_x) * (h.pos_x - n.home_x) + (h.pos_y - n.home_y) * (h.pos_y - n.home_y)
+                       ) AS rank
+                FROM npcs n
+                LEFT JOIN houses h ON h.pos_x IS NOT NULL
+                    AND ABS(h.pos_x - n.home_x) <= 256 AND ABS(h.pos_y - n.home_y) <= 256
+            ) WHERE rank = 1;
+
+            -- Cheapest offer per item that players can buy
+            DROP VIEW IF EXISTS best_buy_price;
+            CREATE VIEW best_buy_price AS
+            SELECT item_id, npc_name, town, price, amount FROM (
+                SELECT p.item_id, p.npc_name, t.town, p.price, p.amount,
+                       ROW_NUMBER() OVER (PARTITION BY p.item_id ORDER BY CAST(p.price AS REAL) / p.amount, p.npc_name) AS rank
+                FROM item_prices p
+                LEFT JOIN npc_towns t ON t.npc_name = p.npc_name
+                WHERE p.mode = 'buy'
+            ) WHERE rank = 1;
+
+            -- Highest offer per item that players can sell to
+            DROP VIEW IF EXISTS best_sell_price;
+            CREATE VIEW best_sell_price AS
+            SELECT item_id, npc_name, town, price, amount FROM (
+                SELECT p.item_id, p.npc_name, t.town, p.price, p.amount,
+                       ROW_NUMBER() OVER (PARTITION BY p.item_id ORDER BY CAST(p.price AS REAL) / p.amount DESC, p.npc_name) AS rank
+                FROM item_prices p
+                LEFT JOIN npc_towns t ON t.npc_name = p.npc_name
+                WHERE p.mode = 'sell'
+            ) WHERE rank = 1;
+
+            -- Creature of each monster ID in the bestiary: the race bestiary_races maps it to, or else
+            -- the race with the same number; creature_id is NULL when no creature has that race
+            DROP VIEW IF EXISTS bestiary_creatures;
+            CREATE VIEW bestiary_creatures AS
+            SELECT m.monster_id, COALESCE(r.race, m.monster_id) AS race, c.id AS creature_id, c.name, c.short_name
+            FROM (SELECT DISTINCT monster_id FROM daily_bestiary) m
+            LEFT JOIN bestiary_races r ON r.monster_id = m.monster_id
+            LEFT JOIN (SELECT race, MIN(id) AS id FROM creatures GROUP BY race) rc
+                ON rc.race = COALESCE(r.race, m.monster_id)
+            LEFT JOIN creatures c ON c.id = rc.id;
+
+            -- Image of each creature: its image_overrides entry, or else the image named after it
+            DROP VIEW IF EXISTS creature_images;
+            CREATE VIEW creature_images AS
+            SELECT c.id AS creature_id, c.short_name, COALESCE(o.image_name, c.image_name) AS image_name
+            FROM creatures c
+            LEFT JOIN image_overrides o ON o.short_name = c.short_name;
+
+            -- Harvesting rules with their tool, reward and creature; names are NULL when an ID resolves to nothing
+            DROP VIEW IF EXISTS harvesting_rules;
+            CREATE VIEW harvesting_rules AS
+            SELECT h.id, h.tool_id, tool.name AS tool_name, h.corpse_id, h.next_corpse_id, h.percent_chance,
+                   h.reward_id, reward.name AS reward_name, h.race_id, c.name AS creature_name, c.short_name AS creature_short_name
+            FROM harvesting_data h
+            LEFT JOIN items tool ON tool.type_id = h.tool_id
+            LEFT JOIN items reward ON reward.type_id = h.reward_id
+            LEFT JOIN (SELECT race, MIN(id) AS id FROM creatures GROUP BY race) rc ON rc.race = h.race_id
+            LEFT JOIN creatures c ON c.id = rc.id;
+
+            -- Rune, wand and rod offers each player can buy, by the vocation and premium days of their
+            -- latest snapshot; players whose premium status is unknown count as free accounts
+            DROP VIEW IF EXISTS player_rune_offers;
+            CREATE VIEW player_rune_offers AS
+            SELECT s.player_id, r.npc_name, r.item_id, r.spell_id, r.price, r.charges, r.item_category, r.account_type
+            FROM daily_snapshots s
+            JOIN (SELECT player_id, MAX(snapshot_date) AS snapshot_date FROM daily_snapshots GROUP BY player_id) l
+                ON l.player_id = s.player_id AND l.snapshot_date = s.snapshot_date
+            JOIN rune_sellers r
+                ON (r.vocation IS NULL OR r.vocation = CASE s.profession % 10
+                        WHEN 1 THEN 'Knight' WHEN 2 THEN 'Paladin' WHEN 3 THEN 'Sorcerer' WHEN 4 THEN 'Druid' END)
+                AND (r.account_type IS NOT 'Premium' OR COALESCE(s.premium_days, 0) > 0);
             "#,
         )?;
 
+        // Databases with kill history from before economy_inflow existed, checked once
+        let backfill_inflow = !Self::migration_applied(&tx, ECONOMY_INFLOW_BACKFILL)?;
+        let has_kill_history: bool = backfill_inflow
+            && tx.query_row(
+                "SELECT NOT EXISTS (SELECT 1 FROM economy_inflow)
+                        AND EXISTS (SELECT 1 FROM creature_loot)
+                        AND (SELECT COUNT(DISTINCT snapshot_date) FROM daily_snapshots) > 1",
+                [],
+                |row| row.get(0),
+            )?;
+
         tx.commit()?;
+        drop(conn);
+        if backfill_inflow {
+            if has_kill_history {
+                self.refresh_economy_inflow("")?;
+            }
+            self.connection()?
+                .execute("INSERT OR IGNORE INTO data_migrations (name) VALUES (?1)", params![ECONOMY_INFLOW_BACKFILL])?;
+        }
+        Ok(())
+    }
+
+    /// Set the spells flag columns from `spells.flags`.
+    fn decode_spell_flags(conn: &Connection) -> Result<()> {
+        for (bit, column, _) in crate::models::SPELL_FLAGS {
+            conn.execute(&format!("UPDATE spells SET {} = (flags & ?1) != 0", column), params![bit])?;
+        }
+        Ok(())
+    }
+
+    /// Set the flag bits and category of items stored before they existed, from `items.flags`.
+    fn backfill_item_categories(conn: &Connection) -> Result<()> {
+        let rows: Vec<(i32, Option<String>)> = conn
+            .prepare("SELECT type_id, flags FROM items WHERE category IS NULL")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+
+        let mut update = conn.prepare("UPDATE items SET flag_bits = ?1, category = ?2 WHERE type_id = ?3")?;
+        for (type_id, flags) in rows {
+            let flags = crate::models::ItemFlags::parse(flags.as_deref().unwrap_or_default());
+            update.execute(params![flags.0, flags.category().as_str(), type_id])?;
+        }
+        Ok(())
+    }
+
+    /// Set the area columns of creature spells stored before they existed, from
+    /// their raw shape parameters.
+    fn backfill_spell_areas(conn: &Connection) -> Result<()> {
+        let rows: Vec<(i64, i32, String)> = conn
+            .prepare("SELECT id, shape_type, raw_shape_params FROM creature_spells WHERE area_tiles IS NULL")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+
+        let mut update = conn.prepare("UPDATE creature_spells SET area_tiles = ?1, area_mask = ?2 WHERE id = ?3")?;
+        for (id, shape_type, raw_shape_params) in rows {
+            let Some(shape_type) = crate::models::SpellShapeType::from_i32(shape_type) else {
+                continue;
+            };
+            let shape_params: Vec<i32> = serde_json::from_str(&raw_shape_params).unwrap_or_default();
+            let area = crate::spell_area::spell_area(shape_type, &shape_params);
+            update.execute(params![area.tiles, area.mask_json(), id])?;
+        }
+        Ok(())
+    }
+
+    /// Add a column to an existing table unless it is already present.
+    /// Whether the one-off data migration `name` already ran on this database.
+    fn migration_applied(conn: &Connection, name: &str) -> Result<bool> {
+        Ok(conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM data_migrations WHERE name = ?1)",
+            params![name],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: bool = conn.query_row(
+            &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1", table),
+            params![column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
         Ok(())
     }
 
@@ -334,26 +1252,52 @@ impl Database {
         snapshot_date: &str,
     ) -> Result<i32> {
         // Check if player exists by ID
-        let existing: Option<(String, String)> = conn
+        let existing: Option<(String, String, String)> = conn
             .query_row(
-                "SELECT first_seen, last_seen FROM players WHERE id = ?",
+                "SELECT name, first_seen, last_seen FROM players WHERE id = ?",
                 params![player_id],
-                |row| Ok((row.get(0)?, row.get(1)?)),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
             )
             .optional()?;
 
         match existing {
-            Some((first_seen, last_seen)) => {
-                // Update last_seen if snapshot_date > last_seen
-                let new_last_seen = if snapshot_date > last_seen.as_str() {
-                    snapshot_date
+            Some((name, first_seen, last_seen)) => {
+                // Widen the seen range; backfilled snapshots can be older than first_seen.
+                // UPDATE rather than INSERT OR REPLACE: replacing deletes the row and
+                // cascades to all of the player's earlier snapshots.
+                let new_first_seen = first_seen.as_str().min(snapshot_date);
+                let new_last_seen = last_seen.as_str().max(snapshot_date);
+
+                // A rename takes effect on the first date the new name is seen. Only
+                // snapshots outside the known range can tell which name came first.
+                let first_name: String = conn
+                    .query_row(
+                        "SELECT old_name FROM player_name_history WHERE player_id = ?1 ORDER BY effective_date LIMIT 1",
+                        params![player_id],
+                        |row| row.get(0),
+                    )
+                    .optional()?
+                    .unwrap_or_else(|| name.clone());
+                let rename = if snapshot_date >= last_seen.as_str() && name != player_name {
+                    Some((name.as_str(), player_name, snapshot_date))
+                } else if snapshot_date < first_seen.as_str() && first_name != player_name {
+                    Some((player_name, first_name.as_str(), first_seen.as_str()))
                 } else {
-                    last_seen.as_str()
+                    None
                 };
+                if let Some((old_name, new_name, effective_date)) = rename {
+                    conn.execute(
+                        "INSERT OR IGNORE INTO player_name_history (player_id, old_name, new_name, effective_date)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![player_id, old_name, new_name, effective_date],
+                    )?;
+                }
+                // Older snapshots don't undo a rename
+                let current_name = if snapshot_date >= last_seen.as_str() { player_name } else { name.as_str() };
 
                 conn.execute(
-                    "INSERT OR REPLACE INTO players (id, name, first_seen, last_seen) VALUES (?, ?, ?, ?)",
-                    params![player_id, player_name, first_seen, new_last_seen],
+                    "UPDATE players SET name = ?, first_seen = ?, last_seen = ? WHERE id = ?",
+                    params![current_name, new_first_seen, new_last_seen, player_id],
                 )?;
             }
             None => {
@@ -368,6 +1312,23 @@ impl Database {
         Ok(player_id)
     }
 
+    /// Widen the seen range of an account and, unless an older snapshot is being
+    /// backfilled, assign the player to it.
+    fn insert_or_update_account(&self, conn: &Connection, player_id: i32, account_id: i32, snapshot_date: &str) -> Result<()> {
+        conn.execute(
+            "INSERT INTO accounts (id, first_seen, last_seen) VALUES (?1, ?2, ?2)
+             ON CONFLICT(id) DO UPDATE SET
+                first_seen = MIN(first_seen, excluded.first_seen),
+                last_seen = MAX(last_seen, excluded.last_seen)",
+            params![account_id, snapshot_date],
+        )?;
+        conn.execute(
+            "UPDATE players SET account_id = ?1 WHERE id = ?2 AND (account_id IS NULL OR last_seen <= ?3)",
+            params![account_id, player_id, snapshot_date],
+        )?;
+        Ok(())
+    }
+
     /// Check if snapshot already exists for player on given date.
     fn snapshot_exists(&self, conn: &Connection, player_id: i32, snapshot_date: &str) -> Result<bool> {
         let count: i32 = conn.query_row(
@@ -394,8 +1355,8 @@ impl Database {
             "INSERT INTO daily_snapshots (
                 player_id, snapshot_date, level, experience, magic_level,
                 fist_fighting, club_fighting, sword_fighting, axe_fighting,
-                distance_fighting, shielding, fishing, equipment_json, source_file
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                distance_fighting, shielding, fishing, equipment_json, source_file, profession, premium_days, blessings
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 player_id,
                 snapshot_date,
@@ -411,11 +1372,46 @@ impl Database {
                 skills.fishing,
                 equipment_json,
                 parsed.source_file,
+                parsed.profession,
+                parsed.premium_days,
+                parsed.blessings,
             ],
         )?;
         Ok(conn.last_insert_rowid() as i32)
     }
 
+    /// Insert the Skill tuples of a snapshot. A skill number listed twice keeps its first tuple.
+    fn insert_daily_skills(&self, conn: &Connection, snapshot_id: i32, parsed: &ParsedUsrFile) -> Result<()> {
+        for entry in &parsed.skill_entries {
+            conn.execute(
+                "INSERT OR IGNORE INTO daily_skills (snapshot_id, skill_id, value, max, min, modifier, tries, next_level)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    snapshot_id,
+                    entry.skill_id,
+                    entry.value,
+                    entry.max,
+                    entry.min,
+                    entry.modifier,
+                    entry.tries,
+                    entry.next_level,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Insert the learned spells of a snapshot.
+    fn insert_player_spells(&self, conn: &Connection, snapshot_id: i32, parsed: &ParsedUsrFile) -> Result<()> {
+        for spell_id in &parsed.spells {
+            conn.execute(
+                "INSERT OR IGNORE INTO player_spells (snapshot_id, spell_id) VALUES (?, ?)",
+                params![snapshot_id, spell_id],
+            )?;
+        }
+        Ok(())
+    }
+
     /// Insert daily quest completions.
     fn insert_daily_quests(&self, conn: &Connection, snapshot_id: i32, parsed: &ParsedUsrFile) -> Result<()> {
         for quest in &parsed.quest_values {
@@ -455,33 +1451,71 @@ impl Database {
         &self,
         parsed: &ParsedUsrFile,
         snapshot_date: &str,
+    ) -> Result<bool> {
+        self.store_player_snapshot(parsed, snapshot_date, ExistingSnapshots::Skip)
+    }
+
+    /// Insert a player snapshot, or with [`ExistingSnapshots::Overwrite`] replace
+    /// the one of the date in the same transaction. Returns false if an existing
+    /// snapshot was kept.
+    pub fn store_player_snapshot(
+        &self,
+        parsed: &ParsedUsrFile,
+        snapshot_date: &str,
+        existing: ExistingSnapshots,
     ) -> Result<bool> {
         let mut conn = self.connection()?;
         let tx = conn.transaction()?;
+        let stored = self.store_snapshot_in(&tx, parsed, snapshot_date, existing)?;
+        tx.commit()?;
+        Ok(stored)
+    }
 
+    /// [`store_player_snapshot`](Self::store_player_snapshot) inside the caller's
+    /// transaction or savepoint
+    fn store_snapshot_in(
+        &self,
+        conn: &Connection,
+        parsed: &ParsedUsrFile,
+        snapshot_date: &str,
+        existing: ExistingSnapshots,
+    ) -> Result<bool> {
         let player_id = self.insert_or_update_player(
-            &tx,
+            conn,
             parsed.player_id,
             &parsed.skills.name,
             snapshot_date
         )?;
-
-        if self.snapshot_exists(&tx, player_id, snapshot_date)? {
-            // Snapshot already exists, skip inserting snapshot but keep player update
-            tx.commit()?;
-            return Ok(false);
+        if let Some(account_id) = parsed.account_id {
+            self.insert_or_update_account(conn, player_id, account_id, snapshot_date)?;
         }
 
-        let snapshot_id = self.insert_daily_snapshot(&tx, player_id, snapshot_date, parsed)?;
-        self.insert_daily_quests(&tx, snapshot_id, parsed)?;
-        self.insert_bestiary(&tx, snapshot_id, parsed)?;
-        self.insert_harvesting(&tx, snapshot_id, parsed)?;
+        if self.snapshot_exists(conn, player_id, snapshot_date)? {
+            if existing == ExistingSnapshots::Skip {
+                // Snapshot already exists, skip inserting snapshot but keep player update
+                return Ok(false);
+            }
+            let snapshot_id: i32 = conn.query_row(
+                "SELECT id FROM daily_snapshots WHERE player_id = ?1 AND snapshot_date = ?2",
+                params![player_id, snapshot_date],
+                |row| row.get(0),
+            )?;
+            for table in SNAPSHOT_CHILD_TABLES {
+                conn.execute(&format!("DELETE FROM {} WHERE snapshot_id = ?1", table), [snapshot_id])?;
+            }
+            conn.execute("DELETE FROM daily_snapshots WHERE id = ?1", [snapshot_id])?;
+        }
 
-        tx.commit()?;
+        let snapshot_id = self.insert_daily_snapshot(conn, player_id, snapshot_date, parsed)?;
+        self.insert_daily_skills(conn, snapshot_id, parsed)?;
+        self.insert_player_spells(conn, snapshot_id, parsed)?;
+        self.insert_daily_quests(conn, snapshot_id, parsed)?;
+        self.insert_bestiary(conn, snapshot_id, parsed)?;
+        self.insert_harvesting(conn, snapshot_id, parsed)?;
         Ok(true)
     }
 
-    /// Process .usr files from a directory.
+    /// Process .usr files from a directory or a .tar.gz/.zip archive of one.
     /// Returns number of successfully processed files.
     pub fn process_usr_files(
         &self,
@@ -489,6 +1523,23 @@ impl Database {
         snapshot_date: &str,
         quiet: u8,
     ) -> Result<u32> {
+        self.process_usr_files_with(input_dir, snapshot_date, quiet, &UsrIngestOptions::default())
+    }
+
+    /// [`process_usr_files`](Self::process_usr_files), choosing the players and
+    /// what happens to snapshots that already exist for the date
+    pub fn process_usr_files_with(
+        &self,
+        input_dir: &std::path::Path,
+        snapshot_date: &str,
+        quiet: u8,
+        options: &UsrIngestOptions,
+    ) -> Result<u32> {
+        let source = crate::input::InputSource::new(input_dir);
+        if source.is_archive() {
+            return self.process_usr_source(&source, snapshot_date, quiet, options);
+        }
+
         let files = file_utils::find_files_with_extension(input_dir, "usr")?;
         if files.is_empty() {
             if quiet == 0 {
@@ -501,40 +1552,202 @@ impl Database {
             tracing::info!("Found {} .usr files to process", files.len());
         }
 
-        let mut success_count = 0;
-        let mut error_count = 0;
+        let files: Vec<std::path::PathBuf> =
+            files.into_iter().filter(|path| options.players.may_match_file(&path.to_string_lossy())).collect();
+        if quiet == 0 && !options.players.is_all() {
+            tracing::info!("{} .usr files match the player filter", files.len());
+        }
+        self.store_usr_paths(&files, snapshot_date, quiet, options)
+    }
 
-        for file_path in files {
-            match parsers::parse_usr_file(&file_path) {
-                Ok(parsed) => {
-                    match self.insert_player_snapshot(&parsed, snapshot_date) {
-                        Ok(true) => {
-                            success_count += 1;
-                            if quiet == 0 {
-                                tracing::info!("Processed {} successfully", parsed.skills.name);
-                            }
+    /// Parse and store the given .usr files as snapshots for `snapshot_date`.
+    /// Returns the number of new snapshots.
+    pub fn process_usr_file_list(
+        &self,
+        files: &[std::path::PathBuf],
+        snapshot_date: &str,
+        quiet: u8,
+    ) -> Result<u32> {
+        self.store_usr_paths(files, snapshot_date, quiet, &UsrIngestOptions::default())
+    }
+
+    fn store_usr_paths(
+        &self,
+        files: &[std::path::PathBuf],
+        snapshot_date: &str,
+        quiet: u8,
+        options: &UsrIngestOptions,
+    ) -> Result<u32> {
+        let mut counts = UsrBatch::default();
+
+        let _batch = tracing::info_span!("usr_batch").entered();
+        {
+            let mut conn = self.connection()?;
+            let mut tx = conn.transaction()?;
+            for file_path in files {
+                let parsed = tracing::info_span!("usr_parse", file = %file_path.display())
+                    .in_scope(|| parsers::parse_usr_file(file_path));
+                self.store_usr_file(&mut tx, parsed, &file_path.to_string_lossy(), snapshot_date, quiet, options, &mut counts);
+            }
+            Self::report_kept_snapshots(&tx, snapshot_date, quiet, options, &counts)?;
+            tx.commit()?;
+        }
+
+        self.finish_usr_batch(counts, snapshot_date, quiet)
+    }
+
+    /// Parse and store the .usr files of an input source, reading archives in
+    /// place without unpacking them. Returns the number of new or replaced snapshots.
+    pub fn process_usr_source(
+        &self,
+        source: &crate::input::InputSource,
+        snapshot_date: &str,
+        quiet: u8,
+        options: &UsrIngestOptions,
+    ) -> Result<u32> {
+        let mut counts = UsrBatch::default();
+
+        let _batch = tracing::info_span!("usr_batch").entered();
+        let found = {
+            let mut conn = self.connection()?;
+            let mut tx = conn.transaction()?;
+            let found = source.for_each_file("usr", |name, bytes| {
+                if !options.players.may_match_file(name) {
+                    return Ok(());
+                }
+                let parsed = tracing::info_span!("usr_parse", file = name)
+                    .in_scope(|| file_utils::decode_latin1(bytes).and_then(|text| parsers::parse_usr_str(&text, name)));
+                self.store_usr_file(&mut tx, parsed, name, snapshot_date, quiet, options, &mut counts);
+                Ok(())
+            })?;
+            Self::report_kept_snapshots(&tx, snapshot_date, quiet, options, &counts)?;
+            tx.commit()?;
+            found
+        };
+
+        if found == 0 {
+            if quiet == 0 {
+                tracing::info!("No .usr files found in {}", source.path().display());
+            }
+            return Ok(0);
+        }
+        if quiet == 0 {
+            tracing::info!("Read {} .usr files from {}", found, source.path().display());
+        }
+
+        self.finish_usr_batch(counts, snapshot_date, quiet)
+    }
+
+    /// Store one parsed .usr file in the batch transaction `tx`, under a savepoint
+    /// of its own so a file that fails leaves the others, and count it in `counts`
+    #[allow(clippy::too_many_arguments)]
+    fn store_usr_file(
+        &self,
+        tx: &mut rusqlite::Transaction,
+        parsed: Result<ParsedUsrFile>,
+        source_file: &str,
+        snapshot_date: &str,
+        quiet: u8,
+        options: &UsrIngestOptions,
+        counts: &mut UsrBatch,
+    ) {
+        match parsed {
+            Ok(parsed) if !options.players.matches(parsed.player_id, &parsed.skills.name) => {
+                tracing::debug!("Skipped {} - not matched by the player filter", parsed.skills.name);
+            }
+            Ok(parsed) => {
+                let stored = tracing::info_span!("usr_insert", player = %parsed.skills.name).in_scope(|| {
+                    let savepoint = tx.savepoint()?;
+                    let stored = self.store_snapshot_in(&savepoint, &parsed, snapshot_date, options.existing)?;
+                    savepoint.commit()?;
+                    Ok::<_, DemonaxError>(stored)
+                });
+                match stored {
+                    Ok(true) => {
+                        counts.stored += 1;
+                        counts.players.insert(parsed.player_id);
+                        if quiet == 0 {
+                            tracing::info!("Processed {} successfully", parsed.skills.name);
                         }
-                        Ok(false) => {
-                            if quiet == 0 {
-                                tracing::debug!("Skipped {} - snapshot already exists", parsed.skills.name);
-                            }
+                    }
+                    Ok(false) => {
+                        if quiet == 0 {
+                            tracing::debug!("Skipped {} - snapshot already exists", parsed.skills.name);
                         }
-                        Err(e) => {
-                            error_count += 1;
-                            if quiet < 2 {
-                                tracing::warn!("Failed to insert snapshot for {}: {}", parsed.skills.name, e);
-                            }
+                    }
+                    Err(e) => {
+                        counts.errors += 1;
+                        if quiet < 2 {
+                            tracing::warn!(player = %parsed.skills.name, "Failed to insert snapshot for {}: {}", parsed.skills.name, e);
                         }
                     }
                 }
-                Err(e) => {
-                    error_count += 1;
-                    if quiet < 2 {
-                        tracing::warn!("Failed to parse {}: {}", file_path.display(), e);
-                    }
+            }
+            Err(e) => {
+                counts.errors += 1;
+                if quiet < 2 {
+                    tracing::warn!(file = source_file, "Failed to parse {}: {}", source_file, e);
                 }
             }
         }
+    }
+
+    /// With [`ExistingSnapshots::Overwrite`], warn about the selected players whose
+    /// snapshot of the date was not replaced, e.g. because their file no longer
+    /// parses, so their old snapshot is still the one of the date
+    fn report_kept_snapshots(
+        conn: &Connection,
+        snapshot_date: &str,
+        quiet: u8,
+        options: &UsrIngestOptions,
+        counts: &UsrBatch,
+    ) -> Result<()> {
+        if options.existing != ExistingSnapshots::Overwrite || quiet >= 2 {
+            return Ok(());
+        }
+        let mut stmt = conn.prepare(
+            "SELECT p.id, p.name FROM daily_snapshots ds
+             JOIN players p ON p.id = ds.player_id
+             WHERE ds.snapshot_date = ?1
+             ORDER BY p.name",
+        )?;
+        let kept: Vec<String> = stmt
+            .query_map([snapshot_date], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|(id, name)| !counts.players.contains(id) && options.players.matches(*id, name))
+            .map(|(_, name)| name)
+            .collect();
+        if !kept.is_empty() {
+            tracing::warn!(
+                "{} players keep their old snapshot of {} because it was not replaced: {}",
+                kept.len(),
+                snapshot_date,
+                kept.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    fn finish_usr_batch(&self, counts: UsrBatch, snapshot_date: &str, quiet: u8) -> Result<u32> {
+        let (success_count, error_count) = (counts.stored, counts.errors);
+        if success_count > 0 {
+            tracing::info_span!("world_stats").in_scope(|| self.refresh_world_stats(snapshot_date))?;
+            // Detected again from the date's snapshots as they are now
+            self.clear_rollback(snapshot_date)?;
+            if let Some(rollback) = self.detect_rollback(snapshot_date)?
+                && quiet < 2
+            {
+                tracing::warn!(
+                    "Rollback detected on {}: {} of {} players lost experience since their previous snapshot (event {})",
+                    snapshot_date,
+                    rollback.players_lost_experience,
+                    rollback.players_compared,
+                    rollback.event_id
+                );
+            }
+            tracing::info_span!("economy_inflow").in_scope(|| self.refresh_economy_inflow(snapshot_date))?;
+        }
 
         if quiet == 0 {
             tracing::info!("Processed {} files successfully, {} errors", success_count, error_count);
@@ -543,6 +1756,536 @@ impl Database {
         Ok(success_count)
     }
 
+    /// Undo what [`detect_rollback`](Self::detect_rollback) recorded for a date: its
+    /// snapshots' `after_rollback` marks and the rollback event it added for the date
+    pub fn clear_rollback(&self, snapshot_date: &str) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute("UPDATE daily_snapshots SET after_rollback = 0 WHERE snapshot_date = ?1", [snapshot_date])?;
+        conn.execute(
+            "DELETE FROM events
+             WHERE kind = 'rollback' AND start_date = ?1 AND end_date = ?1 AND description LIKE 'Detected by process-usr:%'",
+            [snapshot_date],
+        )?;
+        Ok(())
+    }
+
+    /// Check whether most players of a snapshot date have less experience than in
+    /// their previous snapshot, as after the server restored a backup. If so,
+    /// record a rollback event for the date (unless one covers it already) and
+    /// mark the date's snapshots `after_rollback`, so changes since the previous
+    /// snapshots are left out of reports. Needs [`ROLLBACK_MIN_PLAYERS`] players
+    /// with an earlier snapshot.
+    pub fn detect_rollback(&self, snapshot_date: &str) -> Result<Option<crate::models::Rollback>> {
+        let (players_compared, players_lost_experience): (i64, i64) = {
+            let conn = self.connection()?;
+            conn.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(ds.experience < (
+                            SELECT ps.experience FROM daily_snapshots ps
+                            WHERE ps.player_id = ds.player_id AND ps.snapshot_date < ds.snapshot_date
+                            ORDER BY ps.snapshot_date DESC LIMIT 1
+                        )), 0)
+                 FROM daily_snapshots ds
+                 WHERE ds.snapshot_date = ?1
+                   AND EXISTS (SELECT 1 FROM daily_snapshots ps
+                               WHERE ps.player_id = ds.player_id AND ps.snapshot_date < ds.snapshot_date)",
+                [snapshot_date],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+        };
+        if players_compared < ROLLBACK_MIN_PLAYERS || players_lost_experience * 2 <= players_compared {
+            return Ok(None);
+        }
+
+        let existing: Option<i64> = self
+            .connection()?
+            .query_row(
+                "SELECT id FROM events WHERE kind = 'rollback' AND start_date <= ?1 AND end_date >= ?1 ORDER BY id LIMIT 1",
+                [snapshot_date],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let event_id = match existing {
+            Some(id) => id,
+            None => self.add_event(
+                crate::models::EventKind::Rollback,
+                "Rollback",
+                snapshot_date,
+                snapshot_date,
+                Some(&format!(
+                    "Detected by process-usr: {} of {} players lost experience since their previous snapshot",
+                    players_lost_experience, players_compared
+                )),
+            )?,
+        };
+        self.connection()?
+            .execute("UPDATE daily_snapshots SET after_rollback = 1 WHERE snapshot_date = ?1", [snapshot_date])?;
+
+        Ok(Some(crate::models::Rollback {
+            snapshot_date: snapshot_date.to_string(),
+            players_compared,
+            players_lost_experience,
+            event_id,
+        }))
+    }
+
+    /// Recompute the `world_stats` row of a snapshot date. Backfilling an older
+    /// date moves players' first_seen, so it also recounts later dates' new players.
+    pub fn refresh_world_stats(&self, snapshot_date: &str) -> Result<()> {
+        let conn = self.connection()?;
+        Self::refresh_world_stats_for(&conn, snapshot_date)
+    }
+
+    fn refresh_world_stats_for(conn: &Connection, snapshot_date: &str) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO world_stats
+                 (snapshot_date, players_seen, total_experience, median_level, new_players, total_gold)
+             SELECT ?1, COUNT(*), COALESCE(SUM(experience), 0),
+                    COALESCE((SELECT AVG(level) FROM (
+                        SELECT level, ROW_NUMBER() OVER (ORDER BY level) AS n, COUNT(*) OVER () AS total
+                        FROM daily_snapshots WHERE snapshot_date = ?1
+                    ) WHERE n IN ((total + 1) / 2, (total + 2) / 2)), 0),
+                    (SELECT COUNT(*) FROM players WHERE first_seen = ?1),
+                    NULL
+             FROM daily_snapshots
+             WHERE snapshot_date = ?1",
+            params![snapshot_date],
+        )?;
+        conn.execute(
+            "UPDATE world_stats SET new_players = (SELECT COUNT(*) FROM players WHERE first_seen = world_stats.snapshot_date)
+             WHERE snapshot_date > ?1",
+            params![snapshot_date],
+        )?;
+        Ok(())
+    }
+
+    /// Get the world statistics of every snapshot date, oldest first
+    pub fn get_world_stats(&self) -> Result<Vec<crate::models::WorldStats>> {
+        let events = self.get_events()?;
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT snapshot_date, players_seen, total_experience, median_level, new_players, total_gold
+             FROM world_stats
+             ORDER BY snapshot_date",
+        )?;
+        let mut stats = stmt
+            .query_map([], |row| {
+                Ok(crate::models::WorldStats {
+                    snapshot_date: row.get(0)?,
+                    players_seen: row.get(1)?,
+                    total_experience: row.get(2)?,
+                    median_level: row.get(3)?,
+                    new_players: row.get(4)?,
+                    total_gold: row.get(5)?,
+                    events: Vec::new(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for day in &mut stats {
+            day.events = events
+                .iter()
+                .filter(|event| event.start_date <= day.snapshot_date && day.snapshot_date <= event.end_date)
+                .map(|event| event.name.clone())
+                .collect();
+        }
+        Ok(stats)
+    }
+
+    /// Record an event from `start_date` to `end_date` (YYYY-MM-DD, inclusive).
+    /// Returns its ID.
+    pub fn add_event(
+        &self,
+        kind: crate::models::EventKind,
+        name: &str,
+        start_date: &str,
+        end_date: &str,
+        description: Option<&str>,
+    ) -> Result<i64> {
+        let parse = |date: &str| {
+            chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| DemonaxError::Validation(format!("Event date {:?} is not YYYY-MM-DD", date)))
+        };
+        if name.trim().is_empty() {
+            return Err(DemonaxError::Validation("Event name is empty".to_string()));
+        }
+        if parse(end_date)? < parse(start_date)? {
+            return Err(DemonaxError::Validation(format!("Event ends ({}) before it starts ({})", end_date, start_date)));
+        }
+
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO events (kind, name, start_date, end_date, description) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![kind.as_str(), name.trim(), start_date, end_date, description],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Delete an event; false if there is none with that ID
+    pub fn remove_event(&self, id: i64) -> Result<bool> {
+        let conn = self.connection()?;
+        Ok(conn.execute("DELETE FROM events WHERE id = ?1", [id])? > 0)
+    }
+
+    /// All events, by start date
+    pub fn get_events(&self) -> Result<Vec<crate::models::Event>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, name, start_date, end_date, description FROM events ORDER BY start_date, end_date, id",
+        )?;
+        let events = stmt
+            .query_map([], |row| {
+                Ok(crate::models::Event {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    name: row.get(2)?,
+                    start_date: row.get(3)?,
+                    end_date: row.get(4)?,
+                    description: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    /// Bytes of a game server log read by the previous [`store_log_events`](Self::store_log_events), 0 if none
+    pub fn get_log_offset(&self, path: &str) -> Result<u64> {
+        let read_bytes: Option<i64> = self
+            .connection()?
+            .query_row("SELECT read_bytes FROM log_offsets WHERE path = ?1", params![path], |row| row.get(0))
+            .optional()?;
+        Ok(read_bytes.unwrap_or(0) as u64)
+    }
+
+    /// Store the events read from a game server log together with how far it was
+    /// read, so an interrupted run neither loses nor repeats events
+    pub fn store_log_events(&self, path: &str, events: &[crate::models::LogEvent], read_bytes: u64) -> Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        for event in events {
+            tx.execute(
+                "INSERT INTO log_events (occurred_at, kind, player_name, level, creature, item)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![event.occurred_at, event.kind.as_str(), event.player_name, event.level, event.creature, event.item],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO log_offsets (path, read_bytes) VALUES (?1, ?2)
+             ON CONFLICT(path) DO UPDATE SET read_bytes = excluded.read_bytes, updated_at = CURRENT_TIMESTAMP",
+            params![path, read_bytes as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Log events at or after `since` (a date or timestamp; by default the latest
+    /// snapshot date, so the events the snapshots don't show yet), newest first, without
+    /// hidden players. Killers that are players are given as `killer_name`, or
+    /// not at all when they are hidden.
+    pub fn get_log_events(&self, since: Option<&str>) -> Result<Vec<crate::models::LogEventListing>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT e.occurred_at, e.kind, p.id, e.player_name, e.level, e.creature, k.name, e.item, COALESCE(k.hidden, 0)
+             FROM log_events e
+             LEFT JOIN players p ON p.name = e.player_name COLLATE NOCASE
+             LEFT JOIN players k ON e.kind = 'death' AND k.name = e.creature COLLATE NOCASE
+             WHERE e.occurred_at >= COALESCE(?1, (SELECT MAX(snapshot_date) FROM daily_snapshots), '')
+               AND NOT COALESCE(p.hidden, 0)
+             ORDER BY e.occurred_at DESC, e.id DESC",
+        )?;
+        let events = stmt
+            .query_map(params![since], |row| {
+                let killer_name: Option<String> = row.get(6)?;
+                let killer_hidden: bool = row.get(8)?;
+                Ok(crate::models::LogEventListing {
+                    occurred_at: row.get(0)?,
+                    kind: row.get(1)?,
+                    player_id: row.get(2)?,
+                    player_name: row.get(3)?,
+                    level: row.get(4)?,
+                    creature: if killer_name.is_some() { None } else { row.get(5)? },
+                    killer_name: killer_name.filter(|_| !killer_hidden),
+                    item: row.get(7)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    /// Store an online player count; a second sample of the same second replaces the first
+    pub fn insert_online_sample(&self, sampled_at: &str, count: &crate::online::OnlineCount, source: &str) -> Result<()> {
+        self.connection()?.execute(
+            "INSERT OR REPLACE INTO online_samples (sampled_at, players, max_players, source) VALUES (?1, ?2, ?3, ?4)",
+            params![sampled_at, count.players, count.max_players, source],
+        )?;
+        Ok(())
+    }
+
+    /// Peak and average players online per day, by date
+    pub fn get_online_days(&self) -> Result<Vec<crate::models::OnlineDay>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT date(sampled_at), COUNT(*), MAX(players), AVG(players), MAX(max_players)
+             FROM online_samples
+             GROUP BY date(sampled_at)
+             ORDER BY date(sampled_at)",
+        )?;
+        let days = stmt
+            .query_map([], |row| {
+                Ok(crate::models::OnlineDay {
+                    date: row.get(0)?,
+                    samples: row.get(1)?,
+                    peak_players: row.get(2)?,
+                    average_players: row.get(3)?,
+                    max_players: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(days)
+    }
+
+    /// Kills and hunters of each bestiary monster ID over every visible player's
+    /// latest snapshot, with the creature it resolves to. Most killed first.
+    pub fn get_bestiary_totals(&self) -> Result<Vec<crate::models::BestiaryTotal>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT b.monster_id, bc.race, bc.name, bc.short_name, SUM(b.kill_count), COUNT(DISTINCT ds.player_id), n.note
+             FROM daily_bestiary b
+             JOIN daily_snapshots ds ON ds.id = b.snapshot_id
+             JOIN players p ON p.id = ds.player_id
+             JOIN bestiary_creatures bc ON bc.monster_id = b.monster_id
+             LEFT JOIN creature_notes n ON n.short_name = bc.short_name
+             WHERE NOT p.hidden
+               AND ds.snapshot_date = (SELECT MAX(snapshot_date) FROM daily_snapshots WHERE player_id = ds.player_id)
+             GROUP BY b.monster_id
+             ORDER BY SUM(b.kill_count) DESC, b.monster_id",
+        )?;
+        let totals = stmt
+            .query_map([], |row| {
+                Ok(crate::models::BestiaryTotal {
+                    monster_id: row.get(0)?,
+                    race: row.get(1)?,
+                    name: row.get(2)?,
+                    short_name: row.get(3)?,
+                    kills: row.get(4)?,
+                    players: row.get(5)?,
+                    note: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(totals)
+    }
+
+    /// Recompute `economy_inflow` for `since` and every later snapshot date (pass
+    /// "" for all dates). A day's kills are how much each player's bestiary counts
+    /// grew since their previous snapshot; players seen for the first time and dates
+    /// after a rollback are left out, since their counts don't follow from the
+    /// previous snapshot. Each kill is credited
+    /// with the expected drops of the creature's loot table. Returns the number of rows.
+    pub fn refresh_economy_inflow(&self, since: &str) -> Result<usize> {
+        let values = self.get_item_values()?;
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+
+        // Expected (gold, items, item value) per kill; creatures sharing a race count once
+        let mut per_kill: HashMap<i32, (f64, f64, f64)> = HashMap::new();
+        {
+            let mut stmt = tx.prepare(
+                "SELECT l.creature_id, l.item_id, l.min_amount, l.max_amount, l.chance_raw, l.chance_percent
+                 FROM creature_loot l",
+            )?;
+            let loot = stmt.query_map([], |row| {
+                Ok(CreatureLoot {
+                    creature_id: row.get(0)?,
+                    item_id: row.get(1)?,
+                    min_amount: row.get(2)?,
+                    max_amount: row.get(3)?,
+                    chance_raw: row.get(4)?,
+                    chance_percent: row.get(5)?,
+                })
+            })?;
+            for entry in loot {
+                let entry = entry?;
+                let amount = crate::loot::expected_amount_per_kill(&entry);
+                let (gold, items, item_value) = per_kill.entry(entry.creature_id).or_default();
+                match crate::loot::CURRENCY_VALUES.iter().find(|(id, _)| *id == entry.item_id) {
+                    Some((_, face_value)) => *gold += amount * *face_value as f64,
+                    None => {
+                        *items += amount;
+                        *item_value += amount * values.get(&entry.item_id).copied().unwrap_or(0) as f64;
+                    }
+                }
+            }
+        }
+
+        tx.execute("DELETE FROM economy_inflow WHERE snapshot_date >= ?1", params![since])?;
+        let kills: Vec<(String, i32, i64)> = tx
+            .prepare(
+                "SELECT k.snapshot_date, c.creature_id, SUM(MAX(k.kills, 0))
+                 FROM (
+                     SELECT ds.snapshot_date, b.monster_id,
+                            b.kill_count - COALESCE((
+                                SELECT pb.kill_count FROM daily_snapshots ps
+                                JOIN daily_bestiary pb ON pb.snapshot_id = ps.id AND pb.monster_id = b.monster_id
+                                WHERE ps.player_id = ds.player_id AND ps.snapshot_date < ds.snapshot_date
+                                ORDER BY ps.snapshot_date DESC LIMIT 1
+                            ), 0) AS kills
+                     FROM daily_bestiary b
+                     JOIN daily_snapshots ds ON ds.id = b.snapshot_id
+                     WHERE ds.snapshot_date >= ?1
+                       AND NOT ds.after_rollback
+                       AND EXISTS (SELECT 1 FROM daily_snapshots ps
+                                   WHERE ps.player_id = ds.player_id AND ps.snapshot_date < ds.snapshot_date)
+                 ) k
+                 JOIN bestiary_creatures c ON c.monster_id = k.monster_id AND c.creature_id IS NOT NULL
+                 GROUP BY k.snapshot_date, c.creature_id
+                 HAVING SUM(MAX(k.kills, 0)) > 0",
+            )?
+            .query_map(params![since], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+
+        for (snapshot_date, creature_id, kills) in &kills {
+            let (gold, items, item_value) = per_kill.get(creature_id).copied().unwrap_or_default();
+            let kills_f = *kills as f64;
+            tx.execute(
+                "INSERT INTO economy_inflow (snapshot_date, creature_id, kills, gold, items, item_value)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    snapshot_date,
+                    creature_id,
+                    kills,
+                    (gold * kills_f).round() as i64,
+                    items * kills_f,
+                    (item_value * kills_f).round() as i64
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(kills.len())
+    }
+
+    /// Get the `economy_inflow` rows of a snapshot date (default: the latest),
+    /// most valuable creatures first
+    pub fn get_economy_inflow(&self, snapshot_date: Option<&str>) -> Result<Vec<crate::models::EconomyInflow>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT e.snapshot_date, c.name, e.kills, e.gold, e.items, e.item_value
+             FROM economy_inflow e
+             JOIN creatures c ON c.id = e.creature_id
+             WHERE e.snapshot_date = COALESCE(?1, (SELECT MAX(snapshot_date) FROM economy_inflow))
+             ORDER BY e.gold + e.item_value DESC, c.name",
+        )?;
+        let rows = stmt
+            .query_map(params![snapshot_date], |row| {
+                Ok(crate::models::EconomyInflow {
+                    snapshot_date: row.get(0)?,
+                    creature: row.get(1)?,
+                    kills: row.get(2)?,
+                    gold: row.get(3)?,
+                    items: row.get(4)?,
+                    item_value: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Accounts with their characters' latest snapshot on or before `snapshot_date`
+    /// (default: the latest date), most total experience first. A character is
+    /// active with a snapshot in the `active_days` before that date. Hidden
+    /// players and characters without an account are left out, and with an
+    /// `account_type` so are the accounts of the other type (unknown counts as free).
+    pub fn get_account_summaries(
+        &self,
+        snapshot_date: Option<&str>,
+        active_days: u32,
+        account_type: Option<crate::models::AccountType>,
+    ) -> Result<Vec<crate::models::AccountSummary>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "WITH as_of AS (SELECT COALESCE(?1, (SELECT MAX(snapshot_date) FROM daily_snapshots)) AS date),
+             latest AS (
+                 SELECT s.player_id, s.snapshot_date, s.level, s.experience, s.premium_days,
+                        ROW_NUMBER() OVER (PARTITION BY s.player_id ORDER BY s.snapshot_date DESC) AS rank
+                 FROM daily_snapshots s, as_of
+                 WHERE s.snapshot_date <= as_of.date
+             ),
+             characters AS (
+                 SELECT p.account_id, p.name, l.snapshot_date, l.level, l.experience, l.premium_days,
+                        ROW_NUMBER() OVER (PARTITION BY p.account_id ORDER BY l.experience DESC, p.name) AS best
+                 FROM players p
+                 JOIN latest l ON l.player_id = p.id AND l.rank = 1
+                 WHERE p.account_id IS NOT NULL AND NOT p.hidden
+             )
+             SELECT c.account_id, COUNT(*),
+                    SUM(c.snapshot_date > DATE(as_of.date, '-' || ?2 || ' days')),
+                    MAX(CASE WHEN c.best = 1 THEN c.name END), MAX(CASE WHEN c.best = 1 THEN c.level END),
+                    SUM(c.experience), MAX(c.snapshot_date), MAX(c.premium_days > 0)
+             FROM characters c, as_of
+             GROUP BY c.account_id
+             HAVING ?3 IS NULL OR COALESCE(MAX(c.premium_days > 0), 0) = (?3 = 'Premium')
+             ORDER BY SUM(c.experience) DESC, c.account_id",
+        )?;
+        let rows = stmt
+            .query_map(params![snapshot_date, active_days, account_type.map(|t| t.as_str())], |row| {
+                Ok(crate::models::AccountSummary {
+                    account_id: row.get(0)?,
+                    characters: row.get(1)?,
+                    active_characters: row.get(2)?,
+                    best_character: row.get(3)?,
+                    best_level: row.get(4)?,
+                    total_experience: row.get(5)?,
+                    last_seen: row.get(6)?,
+                    premium: row.get(7)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Players whose latest snapshot lists the spell with these words or this
+    /// name (case-insensitive), highest level first, with the spell's name.
+    /// None if no spell matches; hidden players are left out.
+    pub fn get_spell_casters(&self, spell: &str) -> Result<Option<(String, Vec<crate::models::SpellCaster>)>> {
+        let conn = self.connection()?;
+        let Some((spell_id, spell_name)) = conn
+            .query_row(
+                "SELECT id, name FROM spells
+                 WHERE words = ?1 COLLATE NOCASE OR name = ?1 COLLATE NOCASE
+                 ORDER BY words = ?1 COLLATE NOCASE DESC, id
+                 LIMIT 1",
+                [spell.trim()],
+                |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT s.player_id, p.name, s.level, s.magic_level, s.profession, s.snapshot_date
+             FROM daily_snapshots s
+             JOIN (SELECT player_id, MAX(snapshot_date) AS snapshot_date FROM daily_snapshots GROUP BY player_id) l
+                 ON l.player_id = s.player_id AND l.snapshot_date = s.snapshot_date
+             JOIN players p ON p.id = s.player_id
+             JOIN player_spells ps ON ps.snapshot_id = s.id AND ps.spell_id = ?1
+             WHERE NOT p.hidden
+             ORDER BY s.level DESC, s.experience DESC, p.name",
+        )?;
+        let casters = stmt
+            .query_map([spell_id], |row| {
+                Ok(crate::models::SpellCaster {
+                    player_id: row.get(0)?,
+                    player_name: row.get(1)?,
+                    level: row.get(2)?,
+                    magic_level: row.get(3)?,
+                    vocation: row.get::<_, Option<i32>>(4)?.map_or("Unknown", vocation_name).to_string(),
+                    snapshot_date: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Some((spell_name, casters)))
+    }
+
     /// Insert or update a creature record. Returns creature ID.
     fn insert_or_update_creature(&self, conn: &Connection, creature: &Creature) -> Result<i32> {
         // Check if creature exists by short_name (unique)
@@ -558,7 +2301,7 @@ impl Database {
             Some(id) => {
                 // Update creature stats
                 conn.execute(
-                    "UPDATE creatures SET name = ?, race = ?, hp = ?, experience = ?, type = ?, image_name = ?, has_loot = ?, article = ? WHERE id = ?",
+                    "UPDATE creatures SET name = ?, race = ?, hp = ?, experience = ?, type = ?, image_name = ?, has_loot = ?, article = ?, attack = ?, defend = ?, armor = ? WHERE id = ?",
                     params![
                         creature.name,
                         creature.race,
@@ -568,6 +2311,9 @@ impl Database {
                         creature.image_name,
                         creature.has_loot,
                         creature.article,
+                        creature.attack,
+                        creature.defend,
+                        creature.armor,
                         id,
                     ],
                 )?;
@@ -576,7 +2322,7 @@ impl Database {
             None => {
                 // Insert new creature
                 conn.execute(
-                    "INSERT INTO creatures (name, short_name, race, hp, experience, type, image_name, has_loot, article) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    "INSERT INTO creatures (name, short_name, race, hp, experience, type, image_name, has_loot, article, attack, defend, armor) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                     params![
                         creature.name,
                         creature.short_name,
@@ -587,6 +2333,9 @@ impl Database {
                         creature.image_name,
                         creature.has_loot,
                         creature.article,
+                        creature.attack,
+                        creature.defend,
+                        creature.armor,
                     ],
                 )?;
                 Ok(conn.last_insert_rowid() as i32)
@@ -668,14 +2417,14 @@ impl Database {
             conn.execute(
                 "INSERT INTO creature_spells (
                     creature_id, spell_order, spell_name, spell_category,
-                    shape_type, shape_name, range, area_size, angle,
+                    shape_type, shape_name, range, area_size, angle, area_tiles, area_mask,
                     impact_type, impact_name,
                     damage_type, base_value, variation, min_value, max_value,
                     speed_modifier, duration,
                     summon_race_id, summon_count,
                     priority, effect_id, missile_effect_id,
                     raw_shape_params, raw_impact_params
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 params![
                     creature_id,
                     spell.spell_order,
@@ -686,6 +2435,8 @@ impl Database {
                     spell.range,
                     spell.area_size.as_ref(),
                     spell.angle,
+                    spell.area_tiles,
+                    spell.area_mask.as_ref(),
                     spell.impact_type as i32,
                     &spell.impact_name,
                     spell.damage_type.as_ref(),
@@ -730,40 +2481,22 @@ impl Database {
         Ok(rows_affected)
     }
 
-    /// Process .mon files from a directory.
+    /// Process .mon files from a directory, skipping the `excluded` file names.
     /// Returns number of successfully processed files.
     pub fn process_mon_files(
         &self,
         game_path: &std::path::Path,
+        excluded: &[String],
         quiet: u8,
     ) -> Result<u32> {
         let mon_dir = game_path.join("mon");
         let files = file_utils::find_files_with_extension(&mon_dir, "mon")?;
 
-        // Exclude list from R code
-        let exclude = vec![
-            "deathslicer.mon",
-            "slime2.mon",
-            "illusion.mon",
-            "butterflyblue.mon",
-            "butterflyyellow.mon",
-            "butterflyred.mon",
-            "butterflypurple.mon",
-            "mimic.mon",
-            "halloweenhare.mon",
-            "flamethrower.mon",
-            "magicthrower.mon",
-            "plaguethrower.mon",
-            "shredderthrower.mon",
-            "gamemaster.mon",
-            "human.mon",
-        ];
-
         let files: Vec<_> = files
             .into_iter()
             .filter(|path| {
                 let filename = path.file_name().unwrap_or_default().to_string_lossy();
-                !exclude.contains(&filename.as_ref())
+                !excluded.iter().any(|name| *name == filename)
             })
             .collect();
 
@@ -782,7 +2515,8 @@ impl Database {
         let mut error_count = 0;
 
         for file_path in files {
-            match parsers::parse_mon_file(&file_path) {
+            let _file = tracing::info_span!("mon_file", file = %file_path.display()).entered();
+            match tracing::info_span!("mon_parse").in_scope(|| parsers::parse_mon_file(&file_path)) {
                 Ok(creature) => {
                     let mut conn = self.connection()?;
                     let tx = conn.transaction()?;
@@ -805,15 +2539,15 @@ impl Database {
                             // Parse and insert flags
                             match parsers::parse_creature_flags(&text) {
                                 Ok(flags) => {
-                                    if !flags.is_empty() {
-                                        if let Err(e) = self.insert_creature_flags(&tx, creature_id, &flags) {
-                                            error_count += 1;
-                                            if quiet < 2 {
-                                                tracing::warn!("Failed to insert flags for {}: {}", creature.name, e);
-                                            }
-                                            tx.rollback()?;
-                                            continue;
+                                    if !flags.is_empty()
+                                        && let Err(e) = self.insert_creature_flags(&tx, creature_id, &flags)
+                                    {
+                                        error_count += 1;
+                                        if quiet < 2 {
+                                            tracing::warn!("Failed to insert flags for {}: {}", creature.name, e);
                                         }
+                                        tx.rollback()?;
+                                        continue;
                                     }
                                 }
                                 Err(e) => {
@@ -829,15 +2563,15 @@ impl Database {
                             // Parse and insert skills
                             match parsers::parse_creature_skills(&text) {
                                 Ok(skills) => {
-                                    if !skills.is_empty() {
-                                        if let Err(e) = self.insert_creature_skills(&tx, creature_id, &skills) {
-                                            error_count += 1;
-                                            if quiet < 2 {
-                                                tracing::warn!("Failed to insert skills for {}: {}", creature.name, e);
-                                            }
-                                            tx.rollback()?;
-                                            continue;
+                                    if !skills.is_empty()
+                                        && let Err(e) = self.insert_creature_skills(&tx, creature_id, &skills)
+                                    {
+                                        error_count += 1;
+                                        if quiet < 2 {
+                                            tracing::warn!("Failed to insert skills for {}: {}", creature.name, e);
                                         }
+                                        tx.rollback()?;
+                                        continue;
                                     }
                                 }
                                 Err(e) => {
@@ -882,15 +2616,15 @@ impl Database {
                             // Parse loot
                             match parsers::parse_creature_loot(&file_path) {
                                 Ok(loot) => {
-                                    if !loot.is_empty() {
-                                        if let Err(e) = self.insert_creature_loot(&tx, creature_id, &loot) {
-                                            error_count += 1;
-                                            if quiet < 2 {
-                                                tracing::warn!("Failed to insert loot for {}: {}", creature.name, e);
-                                            }
-                                            tx.rollback()?;
-                                            continue;
+                                    if !loot.is_empty()
+                                        && let Err(e) = self.insert_creature_loot(&tx, creature_id, &loot)
+                                    {
+                                        error_count += 1;
+                                        if quiet < 2 {
+                                            tracing::warn!("Failed to insert loot for {}: {}", creature.name, e);
                                         }
+                                        tx.rollback()?;
+                                        continue;
                                     }
                                     if let Err(e) = tx.commit() {
                                         error_count += 1;
@@ -953,9 +2687,33 @@ impl Database {
             }
         }
 
+        let rated = self.update_creature_spell_damage(&conn)?;
+        if quiet == 0 {
+            tracing::info!("Updated spell damage per turn for {} creatures", rated);
+        }
+        drop(conn);
+
+        // Loot tables changed, so every day's estimate did too
+        self.refresh_economy_inflow("")?;
+
         Ok(success_count)
     }
 
+    /// Recompute creatures.spell_damage_per_turn: the expected damage of all damage
+    /// spells in one turn, each spell's average hit weighted by its 1-in-priority
+    /// chance (see [`crate::combat`]). Creatures without damage spells get 0.
+    fn update_creature_spell_damage(&self, conn: &Connection) -> Result<usize> {
+        let rows_affected = conn.execute(
+            "UPDATE creatures SET spell_damage_per_turn = COALESCE((
+                 SELECT SUM((COALESCE(min_value, 0) + max_value) / 2.0 / MAX(priority, 1))
+                 FROM creature_spells
+                 WHERE creature_id = creatures.id AND impact_name LIKE '%Damage' AND max_value IS NOT NULL
+             ), 0)",
+            [],
+        )?;
+        Ok(rows_affected)
+    }
+
     // Item-related methods
 
     /// Insert or update items from objects.srv
@@ -966,14 +2724,16 @@ impl Database {
 
         for item in items {
             conn.execute(
-                "INSERT INTO items (type_id, name, description, flags, attributes, image_link)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "INSERT INTO items (type_id, name, description, flags, attributes, image_link, flag_bits, category)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
                  ON CONFLICT(type_id) DO UPDATE SET
                     name = excluded.name,
                     description = excluded.description,
                     flags = excluded.flags,
                     attributes = excluded.attributes,
-                    image_link = excluded.image_link",
+                    image_link = excluded.image_link,
+                    flag_bits = excluded.flag_bits,
+                    category = excluded.category",
                 (
                     item.type_id,
                     &item.name,
@@ -981,6 +2741,8 @@ impl Database {
                     &item.flags,
                     &item.attributes,
                     "", // image_link will be updated later or during export
+                    item.flag_set().0,
+                    item.category().as_str(),
                 ),
             )?;
             inserted_count += 1;
@@ -996,24 +2758,38 @@ impl Database {
 
         // Clear existing prices
         tx.execute("DELETE FROM item_prices", ())?;
+        tx.execute("DELETE FROM npcs", ())?;
 
         let mut inserted_count = 0;
 
         for price in prices {
             tx.execute(
-                "INSERT INTO item_prices (item_id, npc_name, price, mode)
-                 VALUES (?1, ?2, ?3, ?4)",
+                "INSERT INTO item_prices (item_id, npc_name, price, mode, amount, data, bulk)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 (
                     price.item_id,
                     &price.npc_name,
                     price.price,
                     &price.mode,
+                    price.amount,
+                    price.data,
+                    price.bulk,
                 ),
             )?;
+            if let Some((x, y, z)) = price.npc_home {
+                tx.execute(
+                    "INSERT OR REPLACE INTO npcs (name, home_x, home_y, home_z) VALUES (?1, ?2, ?3, ?4)",
+                    params![price.npc_name, x, y, z],
+                )?;
+            }
             inserted_count += 1;
         }
 
         tx.commit()?;
+        drop(conn);
+
+        // Item values come from these prices
+        self.refresh_economy_inflow("")?;
         Ok(inserted_count)
     }
 
@@ -1056,10 +2832,12 @@ impl Database {
     ///
     /// Note: This clears existing quest chest data and replaces it with new data.
     /// Quest metadata (names, descriptions) can be provided via quest_names map.
+    /// Each quest gets the region of its first chest.
     pub fn process_quest_chests(
         &self,
         chests: &[crate::models::QuestChest],
         quest_names: Option<&HashMap<i32, String>>,
+        regions: &[crate::geo::Region],
         quiet: u8
     ) -> Result<usize> {
         let mut conn = self.connection()?;
@@ -1072,9 +2850,8 @@ impl Database {
         let mut processed = 0;
 
         for chest in chests {
-            // Filter out Rook-only quests (values 17-35, 58, 59, 223, 224) and 255
-            let rook_only = matches!(chest.quest_value, 17..=35 | 58 | 59 | 223 | 224 | 255);
-            if rook_only {
+            // 255 is not a quest value
+            if chest.quest_value == 255 {
                 continue;
             }
 
@@ -1086,18 +2863,21 @@ impl Database {
 
             let reward_items_json = serde_json::to_string(&chest.item_ids)?;
             let chest_location = format!("{} ({})", chest.ingame_coords, chest.sector_name);
+            let position = crate::geo::Position::new(chest.ingame_x, chest.ingame_y, chest.sector_level);
+            let region = crate::geo::region_of(regions, position);
 
             // Get quest name from map or use default
             let quest_name = quest_names
                 .and_then(|map| map.get(&chest.quest_value))
-                .map(|s| s.clone())
+                .cloned()
                 .unwrap_or_else(|| format!("Quest {}", chest.quest_value));
 
             // Insert or update quest
             tx.execute(
-                "INSERT INTO quests (id, chest_location, reward_items_json, name, description)
-                 VALUES (?1, ?2, ?3, ?4, ?5)
+                "INSERT INTO quests (id, chest_location, reward_items_json, name, description, region)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
                  ON CONFLICT(id) DO UPDATE SET
+                    region = COALESCE(region, excluded.region),
                     chest_location = COALESCE(
                         CASE WHEN chest_location IS NULL OR chest_location = ''
                         THEN excluded.chest_location
@@ -1119,8 +2899,15 @@ impl Database {
                     &reward_items_json,
                     &quest_name,
                     "", // Empty description for now
+                    region,
                 ),
             )?;
+            for key in &chest.keys {
+                tx.execute(
+                    "INSERT OR IGNORE INTO quest_keys (quest_id, key_number, type_id) VALUES (?1, ?2, ?3)",
+                    params![chest.quest_value, key.key_number, key.type_id],
+                )?;
+            }
 
             processed += 1;
         }
@@ -1134,11 +2921,86 @@ impl Database {
         Ok(processed)
     }
 
+    /// Store the towns and assign NPCs, quests, raids, spawns and houses to them by position.
+    /// Houses keep the name of their house area unless it is unnamed ("Area <n>").
+    /// Returns the number of rows given a town.
+    pub fn assign_towns(&self, towns: &[crate::geo::Town]) -> Result<usize> {
+        use crate::geo::{Position, town_of};
+
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM towns", ())?;
+        for town in towns {
+            tx.execute(
+                "INSERT INTO towns (name, temple_x, temple_y, temple_z, bounds_json) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![town.name, town.temple[0], town.temple[1], town.temple[2], serde_json::to_string(&town.bounds)?],
+            )?;
+        }
+
+        let positions = |sql: &str| -> Result<Vec<(String, Position)>> {
+            let mut stmt = tx.prepare(sql)?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, Position::new(row.get(1)?, row.get(2)?, row.get(3)?))))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(rows)
+        };
+        let npcs = positions("SELECT name, home_x, home_y, home_z FROM npcs")?;
+        let raids = positions("SELECT name, pos_x, pos_y, pos_z FROM raids WHERE pos_x IS NOT NULL")?;
+        let spawns = positions("SELECT CAST(id AS TEXT), pos_x, pos_y, pos_z FROM spawns")?;
+        let houses = positions(
+            "SELECT CAST(id AS TEXT), pos_x, pos_y, pos_z FROM houses WHERE pos_x IS NOT NULL AND town LIKE 'Area %'",
+        )?;
+        // Quests are placed by their first chest, stored as "x,y,z (sector); ..."
+        let quests: Vec<(i32, Position)> = {
+            let mut stmt = tx.prepare("SELECT id, chest_location FROM quests WHERE chest_location IS NOT NULL")?;
+            let locations = stmt
+                .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            locations
+                .into_iter()
+                .filter_map(|(id, location)| Some((id, location.split(" (").next()?.parse().ok()?)))
+                .collect()
+        };
+
+        let mut assigned = 0;
+        for (name, home) in &npcs {
+            let town = town_of(towns, *home);
+            assigned += usize::from(town.is_some());
+            tx.execute("UPDATE npcs SET town = ?1 WHERE name = ?2", params![town, name])?;
+        }
+        for (id, chest) in &quests {
+            let town = town_of(towns, *chest);
+            assigned += usize::from(town.is_some());
+            tx.execute("UPDATE quests SET town = ?1 WHERE id = ?2", params![town, id])?;
+        }
+        for (name, spawn) in &raids {
+            let town = town_of(towns, *spawn);
+            assigned += usize::from(town.is_some());
+            tx.execute("UPDATE raids SET town = ?1 WHERE name = ?2", params![town, name])?;
+        }
+        for (id, center) in &spawns {
+            let town = town_of(towns, *center);
+            assigned += usize::from(town.is_some());
+            tx.execute("UPDATE spawns SET town = ?1 WHERE id = ?2", params![town, id])?;
+        }
+        for (id, position) in &houses {
+            if let Some(town) = town_of(towns, *position) {
+                assigned += 1;
+                tx.execute("UPDATE houses SET town = ?1 WHERE id = ?2", params![town, id])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(assigned)
+    }
+
     /// Update items table with quest reward information
     ///
     /// Reads quest data from database and updates items with which quests reward them.
     /// Adds a 'rewarded_from' column to items table if it doesn't exist.
-    pub fn update_items_with_quest_rewards(&self, quiet: u8) -> Result<usize> {
+    /// Quests in `skipped_regions` are left out; quests without a region are kept.
+    pub fn update_items_with_quest_rewards(&self, skipped_regions: &[String], quiet: u8) -> Result<usize> {
         let mut conn = self.connection()?;
 
         // Add rewarded_from column if it doesn't exist
@@ -1154,10 +3016,12 @@ impl Database {
         // Query all quests with their rewards
         let quests: Vec<(i32, String, String)> = {
             let mut stmt = conn.prepare(
-                "SELECT id, name, reward_items_json FROM quests WHERE reward_items_json IS NOT NULL AND reward_items_json != '[]'"
+                "SELECT id, name, reward_items_json FROM quests
+                 WHERE reward_items_json IS NOT NULL AND reward_items_json != '[]'
+                   AND (region IS NULL OR region NOT IN (SELECT value FROM json_each(?1)))"
             )?;
 
-            stmt.query_map([], |row| {
+            stmt.query_map([serde_json::to_string(skipped_regions)?], |row| {
                 Ok((
                     row.get(0)?,
                     row.get(1)?,
@@ -1182,7 +3046,7 @@ impl Database {
             for item_id in item_ids {
                 item_to_quests
                     .entry(item_id)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(quest_name.clone());
             }
         }
@@ -1193,6 +3057,8 @@ impl Database {
 
         // Update items table (stmt is already dropped, so we can now borrow mutably)
         let tx = conn.transaction()?;
+        // Links of quests skipped this time must not linger
+        tx.execute("UPDATE items SET rewarded_from = NULL WHERE rewarded_from IS NOT NULL", ())?;
         let mut updated_count = 0;
 
         for (item_id, quest_names) in item_to_quests {
@@ -1223,8 +3089,8 @@ impl Database {
         for spell in spells {
             conn.execute(
                 "INSERT INTO spells (id, name, words, level, magic_level, mana, soul_points,
-                                    flags, is_rune, rune_type_id, charges, spell_type, premium)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                                    flags, is_rune, rune_type_id, charges, spell_type, premium, damage_element)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
                  ON CONFLICT(id) DO UPDATE SET
                     name = excluded.name,
                     words = excluded.words,
@@ -1237,7 +3103,8 @@ impl Database {
                     rune_type_id = excluded.rune_type_id,
                     charges = excluded.charges,
                     spell_type = excluded.spell_type,
-                    premium = excluded.premium",
+                    premium = excluded.premium,
+                    damage_element = excluded.damage_element",
                 (
                     spell.spell_id,
                     &spell.name,
@@ -1252,10 +3119,12 @@ impl Database {
                     spell.charges,
                     &spell.spell_type,
                     spell.premium,
+                    &spell.damage_element,
                 ),
             )?;
             inserted_count += 1;
         }
+        Self::decode_spell_flags(&conn)?;
 
         Ok(inserted_count)
     }
@@ -1270,29 +3139,37 @@ impl Database {
 
         let mut inserted_count = 0;
         for teacher in teachers {
-            // Look up spell level from spells table
-            let level_required: Option<i32> = tx
+            // Look up spell name, words and level from spells table (run update-spells' magic.cc step first)
+            let spell: Option<(String, String, i32)> = tx
                 .query_row(
-                    "SELECT level FROM spells WHERE id = ?1",
+                    "SELECT name, words, level FROM spells WHERE id = ?1",
                     [teacher.spell_id],
-                    |row| row.get(0)
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
                 )
-                .ok();
+                .optional()?;
+            let (spell_name, spell_words, level_required) = match spell {
+                Some((name, words, level)) => (name, Some(words), Some(level)),
+                None => (format!("Spell {}", teacher.spell_id), None, None),
+            };
 
             tx.execute(
-                "INSERT INTO spell_teachers (npc_name, spell_name, spell_id, vocation, price, level_required)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "INSERT INTO spell_teachers (npc_name, spell_name, spell_words, spell_id, vocation, price, level_required, unclassified_line)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
                  ON CONFLICT(npc_name, spell_id, vocation) DO UPDATE SET
                     spell_name = excluded.spell_name,
+                    spell_words = excluded.spell_words,
                     price = excluded.price,
-                    level_required = excluded.level_required",
+                    level_required = excluded.level_required,
+                    unclassified_line = excluded.unclassified_line",
                 (
                     &teacher.npc_name,
-                    format!("Spell {}", teacher.spell_id), // Default spell name, can be joined with spells table
+                    spell_name,
+                    spell_words,
                     teacher.spell_id,
                     &teacher.vocation,
                     teacher.teaching_price,
                     level_required,
+                    &teacher.unclassified_line,
                 ),
             )?;
             inserted_count += 1;
@@ -1362,7 +3239,7 @@ impl Database {
         let mut stmt = conn.prepare(
             "SELECT s.id, s.name, s.words, s.level, s.magic_level, s.mana,
                     s.soul_points, s.flags, s.is_rune, s.rune_type_id,
-                    s.charges, s.spell_type, s.premium
+                    s.charges, s.spell_type, s.premium, s.damage_element
              FROM spells s
              LEFT JOIN rune_sellers rs ON s.id = rs.spell_id
              WHERE s.is_rune = 1 AND rs.spell_id IS NULL
@@ -1384,6 +3261,7 @@ impl Database {
                 charges: row.get(10)?,
                 spell_type: row.get(11)?,
                 premium: row.get(12)?,
+                damage_element: row.get(13)?,
             })
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -1398,7 +3276,7 @@ impl Database {
         let mut stmt = conn.prepare(
             "SELECT s.id, s.name, s.words, s.level, s.magic_level, s.mana,
                     s.soul_points, s.flags, s.is_rune, s.rune_type_id,
-                    s.charges, s.spell_type, s.premium
+                    s.charges, s.spell_type, s.premium, s.damage_element
              FROM spells s
              LEFT JOIN spell_teachers st ON s.id = st.spell_id
              WHERE st.spell_id IS NULL
@@ -1420,6 +3298,7 @@ impl Database {
                 charges: row.get(10)?,
                 spell_type: row.get(11)?,
                 premium: row.get(12)?,
+                damage_element: row.get(13)?,
             })
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -1458,6 +3337,46 @@ impl Database {
         Ok(inserted_count)
     }
 
+    /// Harvesting rules with the names of their tool, creature and reward, by
+    /// creature and then chance, most likely first
+    pub fn get_harvesting_rules(&self) -> Result<Vec<crate::models::HarvestingRule>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT h.tool_id, h.tool_name, h.corpse_id, h.next_corpse_id, h.percent_chance, h.reward_id, h.reward_name,
+                    h.race_id, h.creature_name, h.creature_short_name, cn.note, i.note
+             FROM harvesting_rules h
+             LEFT JOIN creature_notes cn ON cn.short_name = h.creature_short_name
+             LEFT JOIN item_notes i ON i.type_id = h.reward_id
+             ORDER BY COALESCE(creature_name, ''), race_id, percent_chance DESC, reward_id",
+        )?;
+        let rules = stmt
+            .query_map([], |row| {
+                Ok(crate::models::HarvestingRule {
+                    tool_id: row.get(0)?,
+                    tool_name: row.get(1)?,
+                    corpse_id: row.get(2)?,
+                    next_corpse_id: row.get(3)?,
+                    percent_chance: row.get(4)?,
+                    reward_id: row.get(5)?,
+                    reward_name: row.get(6)?,
+                    race_id: row.get(7)?,
+                    creature_name: row.get(8)?,
+                    creature_short_name: row.get(9)?,
+                    description: String::new(),
+                    creature_note: row.get(10)?,
+                    reward_note: row.get(11)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rules
+            .into_iter()
+            .map(|mut rule| {
+                rule.description = crate::harvesting::describe_harvesting(&rule);
+                rule
+            })
+            .collect())
+    }
+
     /// Insert or update raids from .evt files
     pub fn insert_or_update_raids(&self, raids: &[crate::models::Raid]) -> Result<usize> {
         let conn = self.connection()?;
@@ -1466,8 +3385,8 @@ impl Database {
         for raid in raids {
             conn.execute(
                 "INSERT INTO raids (name, type, waves, interval_seconds, interval_days,
-                                   message, creatures, spawn_composition_json)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                                   message, creatures, spawn_composition_json, season, enabled, pos_x, pos_y, pos_z)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
                  ON CONFLICT(name) DO UPDATE SET
                     type = excluded.type,
                     waves = excluded.waves,
@@ -1475,7 +3394,12 @@ impl Database {
                     interval_days = excluded.interval_days,
                     message = excluded.message,
                     creatures = excluded.creatures,
-                    spawn_composition_json = excluded.spawn_composition_json",
+                    spawn_composition_json = excluded.spawn_composition_json,
+                    season = excluded.season,
+                    enabled = excluded.enabled,
+                    pos_x = excluded.pos_x,
+                    pos_y = excluded.pos_y,
+                    pos_z = excluded.pos_z",
                 (
                     &raid.name,
                     &raid.raid_type,
@@ -1485,6 +3409,11 @@ impl Database {
                     &raid.message,
                     &raid.creatures,
                     &raid.spawn_composition_json,
+                    &raid.season,
+                    raid.enabled,
+                    raid.position.map(|p| p.0),
+                    raid.position.map(|p| p.1),
+                    raid.position.map(|p| p.2),
                 ),
             )?;
             inserted_count += 1;
@@ -1493,67 +3422,1900 @@ impl Database {
         Ok(inserted_count)
     }
 
-    /// Get the latest snapshot date from the database
-    pub fn get_latest_snapshot_date(&self) -> Result<String> {
+    /// Store raid starts from the raid log, skipping those already stored.
+    /// Returns the number of new starts.
+    pub fn insert_raid_executions(&self, executions: &[crate::models::RaidExecution]) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        for execution in executions {
+            inserted += tx.execute(
+                "INSERT OR IGNORE INTO raid_executions (raid_name, started_at) VALUES (?1, ?2)",
+                params![execution.raid_name, execution.started_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Every raid with how often and when the raid log saw it start, by name.
+    /// The observed interval is the mean time between the first and last start.
+    pub fn get_raid_activity(&self) -> Result<Vec<crate::models::RaidActivity>> {
         let conn = self.connection()?;
-        let date: String = conn.query_row(
-            "SELECT MAX(snapshot_date) FROM daily_snapshots",
-            [],
-            |row| row.get(0),
+        let mut stmt = conn.prepare(
+            "SELECT r.name, r.interval_days, COUNT(e.started_at), MIN(e.started_at), MAX(e.started_at),
+                    CASE WHEN COUNT(e.started_at) > 1
+                         THEN (julianday(MAX(e.started_at)) - julianday(MIN(e.started_at))) / (COUNT(e.started_at) - 1)
+                    END,
+                    datetime(MAX(e.started_at), '+' || CAST(ROUND(r.interval_days * 86400) AS INTEGER) || ' seconds')
+             FROM raids r
+             LEFT JOIN raid_executions e ON e.raid_name = lower(r.name)
+             GROUP BY r.id
+             ORDER BY r.name",
         )?;
-        Ok(date)
+
+        let activity = stmt
+            .query_map([], |row| {
+                Ok(crate::models::RaidActivity {
+                    name: row.get(0)?,
+                    interval_days: row.get(1)?,
+                    times_seen: row.get(2)?,
+                    first_seen: row.get(3)?,
+                    last_seen: row.get(4)?,
+                    observed_interval_days: row.get(5)?,
+                    expected_next: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(activity)
     }
 
-    /// Get latest snapshots for all players (or a specific player if player_id is provided)
-    pub fn get_latest_snapshots(&self, player_id: Option<i32>) -> Result<Vec<PlayerSnapshot>> {
+    /// Queue a present for the next save, for `player_name` or (with `None`) every
+    /// player. Returns the gift's ID.
+    pub fn queue_gift(
+        &self,
+        player_name: Option<&str>,
+        present: &crate::present::PresentConfig,
+        target_slot: i32,
+        skip_over_capacity: bool,
+    ) -> Result<i64> {
         let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO pending_gifts (player_name, present_json, target_slot, skip_over_capacity, queued_by)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                player_name,
+                serde_json::to_string(present)?,
+                target_slot,
+                skip_over_capacity,
+                crate::audit::current_user(),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
 
-        let query = if player_id.is_some() {
-            "SELECT ds.player_id, p.name, ds.snapshot_date, ds.equipment_json
-             FROM daily_snapshots ds
-             INNER JOIN players p ON ds.player_id = p.id
-             WHERE ds.snapshot_date = (SELECT MAX(snapshot_date) FROM daily_snapshots)
-             AND ds.player_id = ?"
-        } else {
-            "SELECT ds.player_id, p.name, ds.snapshot_date, ds.equipment_json
-             FROM daily_snapshots ds
-             INNER JOIN players p ON ds.player_id = p.id
-             WHERE ds.snapshot_date = (SELECT MAX(snapshot_date) FROM daily_snapshots)"
-        };
+    /// Gifts not applied yet, oldest first
+    pub fn get_pending_gifts(&self) -> Result<Vec<crate::gift_queue::PendingGift>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, player_name, present_json, target_slot, skip_over_capacity, queued_by, queued_at
+             FROM pending_gifts
+             WHERE applied_at IS NULL
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i32>(3)?,
+                    row.get::<_, bool>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, String>(6)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(id, player_name, present_json, target_slot, skip_over_capacity, queued_by, queued_at)| {
+                Ok(crate::gift_queue::PendingGift {
+                    id,
+                    player_name,
+                    present: serde_json::from_str(&present_json)?,
+                    target_slot,
+                    skip_over_capacity,
+                    queued_by,
+                    queued_at,
+                })
+            })
+            .collect()
+    }
 
-        let mut stmt = conn.prepare(query)?;
+    /// Mark a gift as applied, with its counts and per-player outcomes
+    pub fn record_gift_results(
+        &self,
+        gift_id: i64,
+        summary: &crate::present::GiftSummary,
+        outcomes: &[crate::gift_queue::GiftOutcome],
+    ) -> Result<()> {
+        self.connection()?.execute(
+            "UPDATE pending_gifts
+             SET applied_at = CURRENT_TIMESTAMP, gifted = ?2, skipped = ?3, errors = ?4, results_json = ?5
+             WHERE id = ?1",
+            params![
+                gift_id,
+                summary.gifted as i64,
+                summary.skipped as i64,
+                summary.errors as i64,
+                serde_json::to_string(outcomes)?
+            ],
+        )?;
+        Ok(())
+    }
 
-        let snapshots = if let Some(pid) = player_id {
-            stmt.query_map([pid], |row| {
-                let equipment_json: String = row.get(3)?;
-                let equipment: Vec<i32> = serde_json::from_str(&equipment_json)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    /// Replace the bestiary monster ID to race mapping with `races`, as (monster ID, race) pairs
+    pub fn replace_bestiary_races(&self, races: &[[i32; 2]]) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
 
-                Ok(PlayerSnapshot {
-                    player_id: row.get(0)?,
-                    player_name: row.get(1)?,
-                    snapshot_date: row.get(2)?,
-                    equipment,
+        tx.execute("DELETE FROM bestiary_races", ())?;
+        for [monster_id, race] in races {
+            tx.execute(
+                "INSERT OR REPLACE INTO bestiary_races (monster_id, race) VALUES (?1, ?2)",
+                params![monster_id, race],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(races.len())
+    }
+
+    /// Replace the creature image overrides with `overrides`, as short name to image name
+    pub fn replace_image_overrides(&self, overrides: &BTreeMap<String, String>) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM image_overrides", ())?;
+        for (short_name, image_name) in overrides {
+            tx.execute(
+                "INSERT INTO image_overrides (short_name, image_name) VALUES (?1, ?2)",
+                params![short_name, image_name],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(overrides.len())
+    }
+
+    /// Replace all spawns with those from dat/monster.db
+    pub fn replace_spawns(&self, spawns: &[crate::models::Spawn]) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM spawns", ())?;
+        for spawn in spawns {
+            let (x, y, z) = spawn.position;
+            tx.execute(
+                "INSERT INTO spawns (race, pos_x, pos_y, pos_z, radius, amount, regen_seconds)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![spawn.race, x, y, z, spawn.radius, spawn.amount, spawn.regen_seconds],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(spawns.len())
+    }
+
+    /// Replace the stored doors. Returns the number of doors stored.
+    pub fn replace_doors(&self, doors: &[crate::models::MapDoor]) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM doors", ())?;
+        for door in doors {
+            let (x, y, z) = door.position;
+            tx.execute(
+                "INSERT OR REPLACE INTO doors (pos_x, pos_y, pos_z, type_id, keyhole_number, level, quest_number, quest_value)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![x, y, z, door.type_id, door.keyhole_number, door.level, door.quest_number, door.quest_value],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(doors.len())
+    }
+
+    /// Insert or update houses and replace current ownership from house files
+    pub fn insert_or_update_houses(&self, houses: &[crate::models::House]) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+
+        // Ownership is a full replacement: houses missing an owner were given up
+        tx.execute("DELETE FROM house_owners", [])?;
+
+        for house in houses {
+            tx.execute(
+                "INSERT INTO houses (id, name, town, rent, size, guild_house, pos_x, pos_y, pos_z)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(id) DO UPDATE SET
+                    name = excluded.name,
+                    town = excluded.town,
+                    rent = excluded.rent,
+                    size = excluded.size,
+                    guild_house = excluded.guild_house,
+                    pos_x = excluded.pos_x,
+                    pos_y = excluded.pos_y,
+                    pos_z = excluded.pos_z",
+                params![
+                    house.house_id,
+                    house.name,
+                    house.town,
+                    house.rent,
+                    house.size,
+                    house.guild_house,
+                    house.position.map(|p| p.0),
+                    house.position.map(|p| p.1),
+                    house.position.map(|p| p.2),
+                ],
+            )?;
+
+            if let Some(owner_id) = house.owner_id {
+                tx.execute(
+                    "INSERT INTO house_owners (house_id, owner_id, last_payment) VALUES (?1, ?2, ?3)",
+                    params![house.house_id, owner_id, house.last_payment],
+                )?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(houses.len())
+    }
+
+    /// Record current house ownership (including unowned houses) for a date.
+    /// Re-running for the same date overwrites that day's snapshot.
+    pub fn snapshot_house_owners(&self, snapshot_date: &str) -> Result<usize> {
+        let conn = self.connection()?;
+        let count = conn.execute(
+            "INSERT INTO daily_house_owners (snapshot_date, house_id, owner_id)
+             SELECT ?1, h.id, o.owner_id
+             FROM houses h
+             LEFT JOIN house_owners o ON o.house_id = h.id
+             WHERE 1
+             ON CONFLICT(snapshot_date, house_id) DO UPDATE SET owner_id = excluded.owner_id",
+            params![snapshot_date],
+        )?;
+        Ok(count)
+    }
+
+    /// Run one stage of a journaled run unless the journal already has it.
+    ///
+    /// Returns `None` when the stage was completed by an earlier, interrupted run;
+    /// otherwise runs `stage_fn` and records the stage once it succeeds.
+    pub fn run_journaled<F>(&self, run_name: &str, stage: &str, batch: &str, stage_fn: F) -> Result<Option<usize>>
+    where
+        F: FnOnce() -> Result<usize>,
+    {
+        let done = self
+            .connection()?
+            .query_row(
+                "SELECT 1 FROM run_journal WHERE run_name = ?1 AND stage = ?2 AND batch = ?3",
+                params![run_name, stage, batch],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if done {
+            return Ok(None);
+        }
+
+        // The stage takes its own connections, so none is held while it runs
+        let rows = tracing::info_span!("stage", stage).in_scope(stage_fn)?;
+        self.connection()?.execute(
+            "INSERT INTO run_journal (run_name, stage, batch, rows_written) VALUES (?1, ?2, ?3, ?4)",
+            params![run_name, stage, batch, rows as i64],
+        )?;
+        Ok(Some(rows))
+    }
+
+    /// Forget all completed stages of a run so the next run starts over
+    pub fn clear_run_journal(&self, run_name: &str) -> Result<usize> {
+        let conn = self.connection()?;
+        Ok(conn.execute("DELETE FROM run_journal WHERE run_name = ?1", params![run_name])?)
+    }
+
+    /// Record the current untaught spells and unsold runes as a new content gap run.
+    /// Returns the run ID and the number of gaps recorded.
+    pub fn record_content_gaps(&self) -> Result<(i64, usize)> {
+        use crate::models::ContentGapKind;
+
+        let untaught = self.get_untaught_spells()?;
+        let unsold = self.get_unsold_runes()?;
+
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        tx.execute("INSERT INTO content_gap_runs DEFAULT VALUES", [])?;
+        let run_id = tx.last_insert_rowid();
+
+        let gaps = untaught
+            .iter()
+            .map(|spell| (ContentGapKind::UntaughtSpell, spell))
+            .chain(unsold.iter().map(|spell| (ContentGapKind::UnsoldRune, spell)));
+        let mut count = 0;
+        for (kind, spell) in gaps {
+            count += tx.execute(
+                "INSERT INTO content_gaps (run_id, kind, spell_id, spell_name, words, level) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![run_id, kind.as_str(), spell.spell_id, spell.name, spell.words, spell.level],
+            )?;
+        }
+        tx.commit()?;
+        Ok((run_id, count))
+    }
+
+    /// Get the content gaps of one run (the latest by default), with the time each gap was first recorded
+    pub fn get_content_gaps(&self, filter: &crate::models::ContentGapFilter) -> Result<Vec<crate::models::ContentGap>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT g.run_id, g.kind, g.spell_id, g.spell_name, g.words, g.level,
+                    (SELECT MIN(r.recorded_at) FROM content_gaps e JOIN content_gap_runs r ON r.id = e.run_id
+                     WHERE e.kind = g.kind AND e.spell_id = g.spell_id),
+                    (SELECT COUNT(*) FROM content_gaps e WHERE e.kind = g.kind AND e.spell_id = g.spell_id)
+             FROM content_gaps g
+             WHERE g.run_id = COALESCE(?1, (SELECT MAX(id) FROM content_gap_runs))
+               AND (?2 IS NULL OR g.kind = ?2)
+               AND (?3 IS NULL OR g.spell_name LIKE '%' || ?3 || '%' OR g.words LIKE '%' || ?3 || '%')
+               AND (?4 IS NULL OR g.level <= ?4)
+             ORDER BY g.kind, g.level, g.spell_name",
+        )?;
+        let gaps = stmt
+            .query_map(
+                params![filter.run_id, filter.kind.map(|k| k.as_str()), filter.name, filter.max_level],
+                |row| {
+                    Ok(crate::models::ContentGap {
+                        run_id: row.get(0)?,
+                        kind: row.get(1)?,
+                        spell_id: row.get(2)?,
+                        spell_name: row.get(3)?,
+                        words: row.get(4)?,
+                        level: row.get(5)?,
+                        first_recorded: row.get(6)?,
+                        runs: row.get(7)?,
+                    })
+                },
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(gaps)
+    }
+
+    /// Number of content gaps of each kind per run, oldest run first
+    pub fn get_content_gap_runs(&self) -> Result<Vec<crate::models::ContentGapRun>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT r.id, r.recorded_at,
+                    COUNT(CASE WHEN g.kind = 'untaught_spell' THEN 1 END),
+                    COUNT(CASE WHEN g.kind = 'unsold_rune' THEN 1 END)
+             FROM content_gap_runs r
+             LEFT JOIN content_gaps g ON g.run_id = r.id
+             GROUP BY r.id
+             ORDER BY r.id",
+        )?;
+        let runs = stmt
+            .query_map([], |row| {
+                Ok(crate::models::ContentGapRun {
+                    run_id: row.get(0)?,
+                    recorded_at: row.get(1)?,
+                    untaught_spells: row.get(2)?,
+                    unsold_runes: row.get(3)?,
                 })
             })?
-            .collect::<std::result::Result<Vec<_>, _>>()?
-        } else {
-            stmt.query_map([], |row| {
-                let equipment_json: String = row.get(3)?;
-                let equipment: Vec<i32> = serde_json::from_str(&equipment_json)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(runs)
+    }
 
-                Ok(PlayerSnapshot {
-                    player_id: row.get(0)?,
-                    player_name: row.get(1)?,
-                    snapshot_date: row.get(2)?,
-                    equipment,
-                })
+    /// `subject` of the rows of `table` matching `condition`, for a lint rule.
+    /// The statement must be read-only.
+    pub fn get_lint_matches(&self, table: &str, condition: &str, subject: &str) -> Result<Vec<String>> {
+        if table.is_empty() || !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(DemonaxError::Validation(format!("Invalid table name {:?}", table)));
+        }
+        let sql = format!("SELECT CAST(({}) AS TEXT) FROM {} WHERE {} ORDER BY 1", subject, table, condition);
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&sql)?;
+        if !stmt.readonly() {
+            return Err(DemonaxError::Validation(format!("Lint statements must be read-only: {}", sql)));
+        }
+        let subjects = stmt
+            .query_map([], |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(subjects)
+    }
+
+    /// Copy the content tables into `content_tag_rows` under a new label. An existing
+    /// label is an error unless `replace` is set. Returns the rows tagged per table.
+    pub fn tag_content(&self, label: &str, replace: bool) -> Result<Vec<(&'static str, usize)>> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+
+        let existing: Option<i64> = tx
+            .query_row("SELECT id FROM content_tags WHERE label = ?1", params![label], |row| row.get(0))
+            .optional()?;
+        if let Some(tag_id) = existing {
+            if !replace {
+                return Err(DemonaxError::Validation(format!("content tag '{}' already exists", label)));
+            }
+            tx.execute("DELETE FROM content_tag_rows WHERE tag_id = ?1", params![tag_id])?;
+            tx.execute("DELETE FROM content_tags WHERE id = ?1", params![tag_id])?;
+        }
+        tx.execute("INSERT INTO content_tags (label) VALUES (?1)", params![label])?;
+        let tag_id = tx.last_insert_rowid();
+
+        let mut counts = Vec::new();
+        for (table, key, _) in crate::content::CONTENT_TABLES {
+            let sql = format!(
+                "INSERT INTO content_tag_rows (tag_id, content_table, row_key, name, data_json) {}",
+                Self::content_rows_select(&tx, table, key, &tag_id.to_string())?
+            );
+            counts.push((*table, tx.execute(&sql, [])?));
+        }
+        tx.commit()?;
+        Ok(counts)
+    }
+
+    /// `SELECT tag_id, table, key, name, json` over the current rows of a content table
+    fn content_rows_select(conn: &Connection, table: &str, key: &str, tag_id: &str) -> Result<String> {
+        let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let fields: Vec<String> = columns
+            .iter()
+            .filter(|column| column.as_str() != "id" || column.as_str() == key)
+            .map(|column| format!("'{}', \"{}\"", column, column))
+            .collect();
+        Ok(format!(
+            "SELECT {}, '{}', CAST({} AS TEXT), name, json_object({}) FROM {}",
+            tag_id,
+            table,
+            key,
+            fields.join(", "),
+            table
+        ))
+    }
+
+    /// Content rows of a tag, or of the live tables when `label` is `None`
+    pub fn get_content_rows(&self, label: Option<&str>) -> Result<Vec<crate::content::ContentRow>> {
+        let conn = self.connection()?;
+        let sql = match label {
+            Some(label) => {
+                let tag_id: Option<i64> = conn
+                    .query_row("SELECT id FROM content_tags WHERE label = ?1", params![label], |row| row.get(0))
+                    .optional()?;
+                let tag_id = tag_id.ok_or_else(|| DemonaxError::Validation(format!("no content tag '{}'", label)))?;
+                format!(
+                    "SELECT tag_id, content_table, row_key, name, data_json FROM content_tag_rows WHERE tag_id = {}",
+                    tag_id
+                )
+            }
+            None => crate::content::CONTENT_TABLES
+                .iter()
+                .map(|(table, key, _)| Self::content_rows_select(&conn, table, key, "NULL"))
+                .collect::<Result<Vec<_>>>()?
+                .join(" UNION ALL "),
+        };
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?, row.get::<_, String>(4)?))
             })?
-            .collect::<std::result::Result<Vec<_>, _>>()?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|(table, key, name, data)| {
+                let data = serde_json::from_str(&data)
+                    .map_err(|e| DemonaxError::Parse(format!("Invalid content row {}/{}: {}", table, key, e)))?;
+                Ok(crate::content::ContentRow { table, key, name, data })
+            })
+            .collect()
+    }
+
+    /// A JSON scalar as an SQLite value: booleans as 0/1, whole numbers as
+    /// integers. `None` for arrays and objects, which each caller handles.
+    fn sql_value(value: &serde_json::Value) -> Option<rusqlite::types::Value> {
+        use rusqlite::types::Value;
+
+        match value {
+            serde_json::Value::Null => Some(Value::Null),
+            serde_json::Value::Bool(b) => Some(Value::Integer(*b as i64)),
+            serde_json::Value::Number(n) => {
+                Some(n.as_i64().map(Value::Integer).unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or_default())))
+            }
+            serde_json::Value::String(s) => Some(Value::Text(s.clone())),
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+        }
+    }
+
+    /// Rows of a read-only statement as JSON objects by column name, for hook
+    /// scripts. Statements that would change the database are rejected.
+    pub fn query_json_rows(
+        &self,
+        sql: &str,
+        params: &[serde_json::Value],
+    ) -> Result<Vec<serde_json::Map<String, serde_json::Value>>> {
+        use rusqlite::types::ValueRef;
+
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(sql)?;
+        if !stmt.readonly() {
+            return Err(DemonaxError::Validation(format!("Only read-only statements can be queried: {}", sql)));
+        }
+        let values = params
+            .iter()
+            .map(|value| {
+                Self::sql_value(value)
+                    .ok_or_else(|| DemonaxError::Validation(format!("unsupported query parameter {}", value)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = Vec::new();
+        let mut result = stmt.query(rusqlite::params_from_iter(values))?;
+        while let Some(row) = result.next()? {
+            let mut object = serde_json::Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                let value = match row.get_ref(i)? {
+                    ValueRef::Null | ValueRef::Blob(_) => serde_json::Value::Null,
+                    ValueRef::Integer(n) => n.into(),
+                    ValueRef::Real(x) => x.into(),
+                    ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+                };
+                object.insert(column.clone(), value);
+            }
+            rows.push(object);
+        }
+        Ok(rows)
+    }
+
+    /// Replace the table `hook_<name>` with the given rows, for hook scripts.
+    /// Its columns are the keys of all rows, in the rows' key order; nested
+    /// values are stored as JSON text. Returns the number of rows written.
+    pub fn replace_hook_table(&self, name: &str, rows: &[serde_json::Map<String, serde_json::Value>]) -> Result<usize> {
+        use rusqlite::types::Value;
+
+        let is_identifier = |s: &str| {
+            !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && !s.starts_with(|c: char| c.is_ascii_digit())
         };
+        if !is_identifier(name) {
+            return Err(DemonaxError::Validation(format!("Invalid hook table name {:?}", name)));
+        }
+        let mut columns: Vec<&str> = Vec::new();
+        for key in rows.iter().flat_map(|row| row.keys()) {
+            if !columns.contains(&key.as_str()) {
+                if !is_identifier(key) {
+                    return Err(DemonaxError::Validation(format!("Invalid column name {:?} for hook_{}", key, name)));
+                }
+                columns.push(key);
+            }
+        }
 
-        Ok(snapshots)
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        tx.execute(&format!("DROP TABLE IF EXISTS \"hook_{}\"", name), [])?;
+        if columns.is_empty() {
+            tx.commit()?;
+            return Ok(0);
+        }
+        // Quoted, so keys such as `order` or `group` work as column names
+        let quoted = columns.iter().map(|column| format!("\"{}\"", column)).collect::<Vec<_>>().join(", ");
+        tx.execute(&format!("CREATE TABLE \"hook_{}\" ({})", name, quoted), [])?;
+        let sql = format!("INSERT INTO \"hook_{}\" ({}) VALUES ({})", name, quoted, vec!["?"; columns.len()].join(", "));
+        {
+            let mut stmt = tx.prepare(&sql)?;
+            for row in rows {
+                let values = columns.iter().map(|column| match row.get(*column) {
+                    None => Value::Null,
+                    Some(value) => Self::sql_value(value).unwrap_or_else(|| Value::Text(value.to_string())),
+                });
+                stmt.execute(rusqlite::params_from_iter(values))?;
+            }
+        }
+        tx.commit()?;
+        Ok(rows.len())
+    }
+
+    /// Read the content tables into a bundle. Player data isn't included.
+    pub fn dump_bundle(&self) -> Result<crate::bundle::Bundle> {
+        use rusqlite::types::ValueRef;
+
+        let conn = self.connection()?;
+        let mut tables = Vec::new();
+        for table in crate::bundle::BUNDLE_TABLES {
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY rowid", table))?;
+            let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+            let mut rows = Vec::new();
+            let mut result = stmt.query([])?;
+            while let Some(row) = result.next()? {
+                let values = (0..columns.len())
+                    .map(|i| match row.get_ref(i)? {
+                        ValueRef::Null => Ok(serde_json::Value::Null),
+                        ValueRef::Integer(n) => Ok(n.into()),
+                        ValueRef::Real(x) => Ok(x.into()),
+                        ValueRef::Text(text) => Ok(String::from_utf8_lossy(text).into()),
+                        ValueRef::Blob(_) => Err(DemonaxError::Validation(format!(
+                            "{}.{} holds a BLOB, which bundles don't support",
+                            table, columns[i]
+                        ))),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                rows.push(values);
+            }
+            tables.push(crate::bundle::BundleTable { name: table.to_string(), columns, rows });
+        }
+        Ok(crate::bundle::Bundle::new(tables))
+    }
+
+    /// Replace the content tables with the rows of a bundle. Columns this database
+    /// doesn't have are skipped. Returns the rows loaded per table.
+    ///
+    /// Foreign keys are off while loading, so replacing `houses` keeps the player
+    /// data in `house_owners`; the bundle tables are checked before committing.
+    pub fn load_bundle(&self, bundle: &crate::bundle::Bundle) -> Result<Vec<(String, usize)>> {
+        use crate::bundle::BUNDLE_TABLES;
+
+        if let Some(table) = bundle.tables.iter().find(|t| !BUNDLE_TABLES.contains(&t.name.as_str())) {
+            return Err(DemonaxError::Validation(format!("bundle table {} is not a content table", table.name)));
+        }
+
+        let mut conn = self.connection()?;
+        conn.pragma_update(None, "foreign_keys", "OFF")?;
+        let result = Self::load_bundle_tables(&mut conn, bundle);
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        drop(conn);
+
+        let counts = result?;
+        self.refresh_economy_inflow("")?;
+        Ok(counts)
+    }
+
+    fn load_bundle_tables(conn: &mut Connection, bundle: &crate::bundle::Bundle) -> Result<Vec<(String, usize)>> {
+        use crate::bundle::BUNDLE_TABLES;
+        use rusqlite::types::Value;
+
+        let tx = conn.transaction()?;
+        for table in BUNDLE_TABLES.iter().rev() {
+            tx.execute(&format!("DELETE FROM {}", table), [])?;
+        }
+
+        let mut counts = Vec::new();
+        for table in BUNDLE_TABLES {
+            let Some(data) = bundle.tables.iter().find(|t| t.name == *table) else {
+                continue;
+            };
+            let existing: Vec<String> = tx
+                .prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?
+                .query_map([], |row| row.get(0))?
+                .collect::<std::result::Result<_, _>>()?;
+            let indices: Vec<usize> = (0..data.columns.len()).filter(|&i| existing.contains(&data.columns[i])).collect();
+            if indices.is_empty() {
+                continue;
+            }
+
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                indices.iter().map(|&i| format!("\"{}\"", data.columns[i])).collect::<Vec<_>>().join(", "),
+                vec!["?"; indices.len()].join(", ")
+            );
+            let mut stmt = tx.prepare(&sql)?;
+            for row in &data.rows {
+                let values = indices
+                    .iter()
+                    .map(|&i| match row.get(i) {
+                        None => Ok(Value::Null),
+                        Some(value) => Self::sql_value(value).ok_or_else(|| {
+                            DemonaxError::Validation(format!("unsupported value {} in bundle table {}", value, table))
+                        }),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                stmt.execute(rusqlite::params_from_iter(values))?;
+            }
+            counts.push((table.to_string(), data.rows.len()));
+        }
+
+        for table in BUNDLE_TABLES {
+            let violation: Option<String> = tx
+                .query_row(&format!("PRAGMA foreign_key_check({})", table), [], |row| row.get(2))
+                .optional()?;
+            if let Some(parent) = violation {
+                return Err(DemonaxError::Validation(format!(
+                    "bundle table {} references rows missing from {}",
+                    table, parent
+                )));
+            }
+        }
+        tx.commit()?;
+        Ok(counts)
+    }
+
+    /// Describe the tables of the live database with their columns, foreign keys
+    /// and the docs from [`TABLE_DOCS`], [`COLUMN_DOCS`] and [`IMPLICIT_REFERENCES`]
+    pub fn describe_schema(&self) -> Result<Vec<crate::schema::TableSchema>> {
+        use crate::schema::{ColumnSchema, Reference, TableSchema};
+
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY rowid",
+        )?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut tables = Vec::new();
+        for name in names {
+            let mut stmt = conn.prepare("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1) ORDER BY cid")?;
+            let columns = stmt
+                .query_map([&name], |row| {
+                    let column: String = row.get(0)?;
+                    Ok(ColumnSchema {
+                        doc: COLUMN_DOCS
+                            .iter()
+                            .find(|(t, c, _)| *t == name && *c == column)
+                            .map(|(_, _, doc)| doc.to_string())
+                            .or_else(|| {
+                                let (bit, _, meaning) = crate::models::SPELL_FLAGS
+                                    .iter()
+                                    .find(|(_, c, _)| name == "spells" && *c == column)?;
+                                Some(format!("Flags bit {:#04x}: {}", bit, meaning))
+                            }),
+                        name: column,
+                        decl_type: row.get(1)?,
+                        not_null: row.get(2)?,
+                        default: row.get(3)?,
+                        primary_key: row.get::<_, i32>(4)? > 0,
+                    })
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            let mut stmt = conn.prepare("SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?1) ORDER BY id, seq")?;
+            let mut references = stmt
+                .query_map([&name], |row| {
+                    Ok(Reference {
+                        column: row.get(0)?,
+                        table: row.get(1)?,
+                        // NULL means the referenced table's primary key
+                        ref_column: row.get::<_, Option<String>>(2)?.unwrap_or_else(|| "id".to_string()),
+                        declared: true,
+                    })
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            references.extend(IMPLICIT_REFERENCES.iter().filter(|(t, ..)| *t == name).map(|(_, column, table, ref_column)| {
+                Reference {
+                    column: column.to_string(),
+                    table: table.to_string(),
+                    ref_column: ref_column.to_string(),
+                    declared: false,
+                }
+            }));
+
+            tables.push(TableSchema {
+                doc: TABLE_DOCS.iter().find(|(t, _)| *t == name).map(|(_, doc)| doc.to_string()),
+                name,
+                columns,
+                references,
+            });
+        }
+        Ok(tables)
+    }
+
+    /// Report how complete each subsystem's data is.
+    ///
+    /// Creature images are only checked when `creature_image_dir` is given: a
+    /// creature counts as covered if `<image_name>.gif` or `.png` exists there.
+    pub fn get_coverage(&self, creature_image_dir: Option<&std::path::Path>) -> Result<Vec<crate::models::Coverage>> {
+        const EXAMPLES: usize = 5;
+        let conn = self.connection()?;
+
+        // Each check is (subsystem, description, total query, query listing the incomplete records)
+        let checks = [
+            (
+                "items",
+                "items without NPC prices",
+                "SELECT COUNT(*) FROM items",
+                "SELECT i.name FROM items i
+                 WHERE NOT EXISTS (SELECT 1 FROM item_prices p WHERE p.item_id = i.type_id)
+                 ORDER BY i.type_id",
+            ),
+            (
+                "spells",
+                "spells without teachers",
+                "SELECT COUNT(*) FROM spells",
+                "SELECT s.name FROM spells s
+                 WHERE NOT EXISTS (SELECT 1 FROM spell_teachers st WHERE st.spell_id = s.id)
+                 ORDER BY s.level, s.name",
+            ),
+            (
+                "runes",
+                "runes without sellers",
+                "SELECT COUNT(*) FROM spells WHERE is_rune = 1",
+                "SELECT s.name FROM spells s
+                 WHERE s.is_rune = 1 AND NOT EXISTS (SELECT 1 FROM rune_sellers rs WHERE rs.spell_id = s.id)
+                 ORDER BY s.level, s.name",
+            ),
+            (
+                "quests",
+                "quests without names",
+                "SELECT COUNT(*) FROM quests",
+                "SELECT name FROM quests WHERE name = 'Quest ' || id OR TRIM(name) = '' ORDER BY id",
+            ),
+            (
+                "quests",
+                "quest values players set without a known chest (script-only quests)",
+                "SELECT COUNT(DISTINCT quest_id) FROM daily_quests WHERE completion_count > 0",
+                "SELECT 'Quest value ' || dq.quest_id || ': ' || COUNT(DISTINCT s.player_id)
+                        || CASE WHEN COUNT(DISTINCT s.player_id) = 1 THEN ' player' ELSE ' players' END
+                 FROM daily_quests dq
+                 JOIN daily_snapshots s ON s.id = dq.snapshot_id
+                 WHERE dq.completion_count > 0 AND NOT EXISTS (SELECT 1 FROM quests q WHERE q.id = dq.quest_id)
+                 GROUP BY dq.quest_id
+                 ORDER BY COUNT(DISTINCT s.player_id) DESC, dq.quest_id",
+            ),
+            (
+                "quests",
+                "quest chests no player has opened",
+                "SELECT COUNT(*) FROM quests",
+                "SELECT q.name FROM quests q
+                 WHERE NOT EXISTS (SELECT 1 FROM daily_quests dq WHERE dq.quest_id = q.id AND dq.completion_count > 0)
+                 ORDER BY q.id",
+            ),
+            (
+                "raids",
+                "raids spawning unknown creature races",
+                "SELECT COUNT(*) FROM raids",
+                "SELECT r.name FROM raids r
+                 WHERE EXISTS (
+                     SELECT 1 FROM json_each(r.spawn_composition_json) spawn
+                     WHERE NOT EXISTS (SELECT 1 FROM creatures c WHERE c.race = json_extract(spawn.value, '$.race'))
+                 )
+                 ORDER BY r.name",
+            ),
+            (
+                "bestiary",
+                "bestiary monster IDs without a creature",
+                "SELECT COUNT(*) FROM bestiary_creatures",
+                "SELECT 'Monster ' || monster_id FROM bestiary_creatures WHERE creature_id IS NULL ORDER BY monster_id",
+            ),
+            (
+                "harvesting",
+                "harvesting rules for unknown creature races",
+                "SELECT COUNT(*) FROM harvesting_rules",
+                "SELECT 'Race ' || race_id || ' (corpse ' || corpse_id || ')' FROM harvesting_rules
+                 WHERE creature_name IS NULL ORDER BY race_id, corpse_id",
+            ),
+            (
+                "harvesting",
+                "harvesting rules with unknown tools or rewards",
+                "SELECT COUNT(*) FROM harvesting_rules",
+                "SELECT COALESCE(creature_name, 'Race ' || race_id) || ': '
+                        || CASE WHEN tool_name IS NULL THEN 'tool ' || tool_id ELSE 'reward ' || reward_id END
+                 FROM harvesting_rules
+                 WHERE tool_name IS NULL OR reward_name IS NULL
+                 ORDER BY race_id, corpse_id",
+            ),
+        ];
+
+        let coverage = |subsystem: &str, check: &str, total: i64, incomplete: Vec<String>| crate::models::Coverage {
+            subsystem: subsystem.to_string(),
+            check: check.to_string(),
+            total,
+            missing: incomplete.len() as i64,
+            percent_complete: if total > 0 {
+                100.0 * (total - incomplete.len() as i64) as f64 / total as f64
+            } else {
+                100.0
+            },
+            examples: incomplete.into_iter().take(EXAMPLES).collect(),
+        };
+
+        let mut report = Vec::new();
+        if let Some(dir) = creature_image_dir {
+            let mut stmt = conn.prepare(
+                "SELECT c.name, i.image_name FROM creatures c JOIN creature_images i ON i.creature_id = c.id ORDER BY c.name",
+            )?;
+            let creatures = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let incomplete = creatures
+                .iter()
+                .filter(|(_, image)| !["gif", "png"].iter().any(|ext| dir.join(format!("{}.{}", image, ext)).exists()))
+                .map(|(name, _)| name.clone())
+                .collect();
+            report.push(coverage("creatures", "creatures without images", creatures.len() as i64, incomplete));
+        }
+
+        for (subsystem, check, total_sql, incomplete_sql) in checks {
+            let total: i64 = conn.query_row(total_sql, [], |row| row.get(0))?;
+            let mut stmt = conn.prepare(incomplete_sql)?;
+            let incomplete = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            report.push(coverage(subsystem, check, total, incomplete));
+        }
+        Ok(report)
+    }
+
+    /// Get all houses with owner names for the website house list
+    pub fn get_house_list(&self) -> Result<Vec<crate::models::HouseListing>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT h.id, h.name, h.town, h.rent, h.size, h.guild_house,
+                    CASE WHEN p.hidden THEN NULL ELSE p.name END, o.last_payment
+             FROM houses h
+             LEFT JOIN house_owners o ON o.house_id = h.id
+             LEFT JOIN players p ON p.id = o.owner_id
+             ORDER BY h.town, h.name",
+        )?;
+
+        let houses = stmt
+            .query_map([], |row| {
+                let last_payment: Option<i64> = row.get(7)?;
+                Ok(crate::models::HouseListing {
+                    house_id: row.get(0)?,
+                    name: row.get(1)?,
+                    town: row.get(2)?,
+                    rent: row.get(3)?,
+                    size: row.get(4)?,
+                    guild_house: row.get(5)?,
+                    owner_name: row.get(6)?,
+                    last_payment: last_payment
+                        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                        .map(|dt| dt.format("%Y-%m-%d").to_string()),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(houses)
+    }
+
+    /// Get raids by name, optionally only those of one season. Disabled raids
+    /// are left out unless `include_disabled` is set.
+    pub fn get_raid_list(&self, season: Option<&str>, include_disabled: bool) -> Result<Vec<crate::models::RaidListing>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT name, type, waves, interval_days, creatures, season, enabled, town,
+                    (SELECT MAX(started_at) FROM raid_executions WHERE raid_name = lower(raids.name))
+             FROM raids
+             WHERE (?1 IS NULL OR season = ?1) AND (?2 OR enabled)
+             ORDER BY name",
+        )?;
+
+        let raids = stmt
+            .query_map((season, include_disabled), |row| {
+                Ok(crate::models::RaidListing {
+                    name: row.get(0)?,
+                    raid_type: row.get(1)?,
+                    waves: row.get(2)?,
+                    interval_days: row.get(3)?,
+                    creatures: row.get(4)?,
+                    season: row.get(5)?,
+                    enabled: row.get(6)?,
+                    town: row.get(7)?,
+                    last_seen: row.get(8)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(raids)
+    }
+
+    /// Get every player's latest snapshot, highest level first
+    pub fn get_snapshot_list(&self) -> Result<Vec<crate::models::SnapshotListing>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT ds.player_id, p.name, ds.snapshot_date, ds.level, ds.experience, ds.profession, ds.magic_level,
+                    ds.fist_fighting, ds.club_fighting, ds.sword_fighting, ds.axe_fighting, ds.distance_fighting,
+                    ds.shielding, ds.fishing, ds.source_file, ds.blessings
+             FROM daily_snapshots ds
+             JOIN players p ON p.id = ds.player_id
+             WHERE NOT p.hidden
+               AND ds.snapshot_date = (SELECT MAX(snapshot_date) FROM daily_snapshots WHERE player_id = ds.player_id)
+             ORDER BY ds.level DESC, ds.experience DESC, p.name",
+        )?;
+
+        let snapshots = stmt
+            .query_map([], |row| {
+                Ok(crate::models::SnapshotListing {
+                    player_id: row.get(0)?,
+                    player_name: row.get(1)?,
+                    snapshot_date: row.get(2)?,
+                    level: row.get(3)?,
+                    experience: row.get(4)?,
+                    profession: row.get(5)?,
+                    magic_level: row.get(6)?,
+                    fist_fighting: row.get(7)?,
+                    club_fighting: row.get(8)?,
+                    sword_fighting: row.get(9)?,
+                    axe_fighting: row.get(10)?,
+                    distance_fighting: row.get(11)?,
+                    shielding: row.get(12)?,
+                    fishing: row.get(13)?,
+                    blessings: row.get(15)?,
+                    source_file: row.get(14)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(snapshots)
+    }
+
+    /// Check that an inferred snapshot date won't ingest the same data twice.
+    ///
+    /// A date that already has snapshots is always rejected. A date older than
+    /// the latest snapshot is rejected unless `allow_older` is set (backfilling
+    /// dated backup folders); for dates taken from file times it usually means
+    /// the files haven't changed since the last run.
+    pub fn validate_new_snapshot_date(&self, snapshot_date: &str, allow_older: bool) -> Result<()> {
+        let conn = self.connection()?;
+        let (existing, latest): (i64, Option<String>) = conn.query_row(
+            "SELECT SUM(snapshot_date = ?1), MAX(snapshot_date) FROM daily_snapshots",
+            params![snapshot_date],
+            |row| Ok((row.get::<_, Option<i64>>(0)?.unwrap_or(0), row.get(1)?)),
+        )?;
+
+        if existing > 0 {
+            return Err(DemonaxError::DuplicateEntry(format!(
+                "{} snapshots already exist for {}",
+                existing, snapshot_date
+            )));
+        }
+        if let Some(latest) = latest
+            && !allow_older
+            && snapshot_date < latest.as_str()
+        {
+            return Err(DemonaxError::Validation(format!(
+                "Snapshot date {} is older than the latest snapshot {}",
+                snapshot_date, latest
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get all dates that have player snapshots, oldest first
+    pub fn get_snapshot_dates(&self) -> Result<Vec<String>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare("SELECT DISTINCT snapshot_date FROM daily_snapshots ORDER BY snapshot_date")?;
+        let dates = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(dates)
+    }
+
+    /// Get the latest snapshot date from the database
+    pub fn get_latest_snapshot_date(&self) -> Result<String> {
+        let conn = self.connection()?;
+        let date: String = conn.query_row(
+            "SELECT MAX(snapshot_date) FROM daily_snapshots",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(date)
+    }
+
+    /// Get latest snapshots for all visible players (or a specific player if player_id is provided)
+    pub fn get_latest_snapshots(&self, player_id: Option<i32>) -> Result<Vec<PlayerSnapshot>> {
+        let conn = self.connection()?;
+
+        let query = if player_id.is_some() {
+            "SELECT ds.player_id, p.name, ds.snapshot_date, ds.equipment_json
+             FROM daily_snapshots ds
+             INNER JOIN players p ON ds.player_id = p.id
+             WHERE ds.snapshot_date = (SELECT MAX(snapshot_date) FROM daily_snapshots)
+             AND NOT p.hidden
+             AND ds.player_id = ?"
+        } else {
+            "SELECT ds.player_id, p.name, ds.snapshot_date, ds.equipment_json
+             FROM daily_snapshots ds
+             INNER JOIN players p ON ds.player_id = p.id
+             WHERE ds.snapshot_date = (SELECT MAX(snapshot_date) FROM daily_snapshots)
+             AND NOT p.hidden"
+        };
+
+        let mut stmt = conn.prepare(query)?;
+
+        let snapshots = if let Some(pid) = player_id {
+            stmt.query_map([pid], |row| {
+                let equipment_json: String = row.get(3)?;
+                let equipment: Equipment = serde_json::from_str(&equipment_json)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+                Ok(PlayerSnapshot {
+                    player_id: row.get(0)?,
+                    player_name: row.get(1)?,
+                    snapshot_date: row.get(2)?,
+                    equipment,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+        } else {
+            stmt.query_map([], |row| {
+                let equipment_json: String = row.get(3)?;
+                let equipment: Equipment = serde_json::from_str(&equipment_json)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+                Ok(PlayerSnapshot {
+                    player_id: row.get(0)?,
+                    player_name: row.get(1)?,
+                    snapshot_date: row.get(2)?,
+                    equipment,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+        };
+
+        Ok(snapshots)
+    }
+
+    /// Get a player's renames, oldest first
+    pub fn get_name_history(&self, player_id: i32) -> Result<Vec<crate::models::NameChange>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT old_name, new_name, effective_date FROM player_name_history
+             WHERE player_id = ?1
+             ORDER BY effective_date",
+        )?;
+        let history = stmt
+            .query_map([player_id], |row| {
+                Ok(crate::models::NameChange {
+                    old_name: row.get(0)?,
+                    new_name: row.get(1)?,
+                    effective_date: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(history)
+    }
+
+    /// Hide a player from the website (exports and renders) or show them again.
+    /// Returns false if there is no such player.
+    pub fn set_player_hidden(&self, player_id: i32, hidden: bool) -> Result<bool> {
+        let conn = self.connection()?;
+        let updated = conn.execute("UPDATE players SET hidden = ?1 WHERE id = ?2", params![hidden, player_id])?;
+        Ok(updated > 0)
+    }
+
+    /// Whether a player opted out of the website
+    pub fn is_player_hidden(&self, player_id: i32) -> Result<bool> {
+        let conn = self.connection()?;
+        let hidden: Option<bool> = conn
+            .query_row("SELECT hidden FROM players WHERE id = ?1", [player_id], |row| row.get(0))
+            .optional()?;
+        Ok(hidden.unwrap_or(false))
+    }
+
+    /// A player's total experience per snapshot date, over the `days` days up to
+    /// their latest snapshot and since the latest rollback, oldest first
+    pub fn get_experience_history(&self, player_id: i32, days: i64) -> Result<Vec<(String, i64)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT snapshot_date, experience FROM daily_snapshots
+             WHERE player_id = ?1
+               AND snapshot_date >= date((SELECT MAX(snapshot_date) FROM daily_snapshots WHERE player_id = ?1), '-' || ?2 || ' days')
+               AND snapshot_date >= COALESCE(
+                   (SELECT MAX(snapshot_date) FROM daily_snapshots WHERE player_id = ?1 AND after_rollback), '')
+             ORDER BY snapshot_date",
+        )?;
+        let history = stmt
+            .query_map(params![player_id, days], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(history)
+    }
+
+    /// Get card data from a player's latest snapshot
+    pub fn get_player_card(&self, player_id: i32) -> Result<Option<PlayerCard>> {
+        let conn = self.connection()?;
+        let card = conn
+            .query_row(
+                "SELECT p.name, ds.snapshot_date, ds.level, ds.profession, ds.magic_level,
+                        ds.fist_fighting, ds.club_fighting, ds.sword_fighting, ds.axe_fighting,
+                        ds.distance_fighting, ds.shielding, ds.id, ds.experience, ds.blessings
+                 FROM daily_snapshots ds
+                 INNER JOIN players p ON ds.player_id = p.id
+                 WHERE ds.player_id = ?1
+                 ORDER BY ds.snapshot_date DESC
+                 LIMIT 1",
+                [player_id],
+                |row| {
+                    let names = ["Fist", "Club", "Sword", "Axe", "Distance", "Shielding"];
+                    let mut skills = Vec::with_capacity(names.len());
+                    for (i, name) in names.iter().enumerate() {
+                        skills.push((name.to_string(), row.get::<_, i32>(5 + i)?));
+                    }
+                    // Stable sort keeps the listed order for equal values
+                    skills.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+
+                    let profession: Option<i32> = row.get(3)?;
+                    let card = PlayerCard {
+                        player_id,
+                        player_name: row.get(0)?,
+                        snapshot_date: row.get(1)?,
+                        level: row.get(2)?,
+                        experience: row.get(12)?,
+                        vocation: profession.map_or("Unknown", vocation_name).to_string(),
+                        magic_level: row.get(4)?,
+                        blessings: row.get(13)?,
+                        skills,
+                        missing_spells: Vec::new(),
+                    };
+                    Ok((card, row.get::<_, i32>(11)?, profession))
+                },
+            )
+            .optional()?;
+        let Some((mut card, snapshot_id, profession)) = card else {
+            return Ok(None);
+        };
+
+        // Spells the vocation is taught at the player's level and magic level but didn't learn
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT sp.words
+             FROM spells sp
+             JOIN spell_teachers t ON t.spell_id = sp.id
+             WHERE t.vocation = ?1 AND sp.level <= ?2 AND COALESCE(sp.magic_level, 0) <= ?3
+               AND EXISTS (SELECT 1 FROM player_spells WHERE snapshot_id = ?4)
+               AND NOT EXISTS (SELECT 1 FROM player_spells WHERE snapshot_id = ?4 AND spell_id = sp.id)
+             ORDER BY sp.level, sp.words",
+        )?;
+        let vocation = vocation_name(profession.unwrap_or(0) % 10);
+        card.missing_spells = stmt
+            .query_map(params![vocation, card.level, card.magic_level, snapshot_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(Some(card))
+    }
+
+    /// ID of the player with this ID or name (case-insensitive)
+    pub fn find_player_id(&self, player: &str) -> Result<Option<i32>> {
+        let conn = self.connection()?;
+        let player = player.trim();
+        let id = conn
+            .query_row(
+                "SELECT id FROM players WHERE id = ?1 OR name = ?2 COLLATE NOCASE ORDER BY id = ?1 DESC LIMIT 1",
+                params![player.parse::<i32>().ok(), player],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(id)
+    }
+
+    /// Skill tuples and equipment of a player's latest snapshot
+    pub fn get_latest_skills_and_equipment(
+        &self,
+        player_id: i32,
+    ) -> Result<Option<(Vec<crate::models::SkillEntry>, Equipment)>> {
+        let conn = self.connection()?;
+        let Some((snapshot_id, equipment_json)) = conn
+            .query_row(
+                "SELECT id, equipment_json FROM daily_snapshots WHERE player_id = ?1 ORDER BY snapshot_date DESC LIMIT 1",
+                [player_id],
+                |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let equipment: Equipment = serde_json::from_str(&equipment_json)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT skill_id, value, max, min, modifier, tries, next_level
+             FROM daily_skills WHERE snapshot_id = ? ORDER BY skill_id",
+        )?;
+        let skills = stmt
+            .query_map([snapshot_id], |row| {
+                Ok(crate::models::SkillEntry {
+                    skill_id: row.get(0)?,
+                    value: row.get(1)?,
+                    max: row.get(2)?,
+                    min: row.get(3)?,
+                    modifier: row.get(4)?,
+                    tries: row.get(5)?,
+                    next_level: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Some((skills, equipment)))
+    }
+
+    /// Get a creature's display name and loot table by short name or name (case-insensitive)
+    pub fn get_creature_loot(&self, creature: &str) -> Result<Option<(String, Vec<CreatureLoot>)>> {
+        let conn = self.connection()?;
+        let Some((creature_id, name)) = conn
+            .query_row(
+                "SELECT id, name FROM creatures
+                 WHERE short_name = ?1 COLLATE NOCASE OR name = ?1 COLLATE NOCASE
+                 ORDER BY short_name = ?1 COLLATE NOCASE DESC
+                 LIMIT 1",
+                [creature],
+                |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT item_id, min_amount, max_amount, chance_raw, chance_percent
+             FROM creature_loot WHERE creature_id = ? ORDER BY id",
+        )?;
+        let loot = stmt
+            .query_map([creature_id], |row| {
+                Ok(CreatureLoot {
+                    creature_id,
+                    item_id: row.get(0)?,
+                    min_amount: row.get(1)?,
+                    max_amount: row.get(2)?,
+                    chance_raw: row.get(3)?,
+                    chance_percent: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Some((name, loot)))
+    }
+
+    /// Get a creature's melee values and damage spells by short name or name (case-insensitive)
+    pub fn get_creature_combat(&self, creature: &str) -> Result<Option<crate::combat::CreatureCombat>> {
+        let conn = self.connection()?;
+        let Some((creature_id, name, hp, attack, skill)) = conn
+            .query_row(
+                "SELECT c.id, c.name, c.hp, c.attack,
+                        COALESCE((SELECT skill_value FROM creature_skills
+                                  WHERE creature_id = c.id AND skill_name = 'FistFighting'), 0)
+                 FROM creatures c
+                 WHERE c.short_name = ?1 COLLATE NOCASE OR c.name = ?1 COLLATE NOCASE
+                 ORDER BY c.short_name = ?1 COLLATE NOCASE DESC
+                 LIMIT 1",
+                [creature],
+                |row| Ok((row.get::<_, i32>(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT spell_name, damage_type, min_value, max_value, priority
+             FROM creature_spells
+             WHERE creature_id = ? AND impact_name LIKE '%Damage' AND max_value IS NOT NULL
+             ORDER BY spell_order",
+        )?;
+        let spells = stmt
+            .query_map([creature_id], |row| {
+                Ok(crate::combat::DamageSpell {
+                    name: row.get(0)?,
+                    damage_type: row.get(1)?,
+                    min_value: row.get::<_, Option<i32>>(2)?.unwrap_or(0),
+                    max_value: row.get(3)?,
+                    priority: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(Some(crate::combat::CreatureCombat { name, hp, attack, skill, spells }))
+    }
+
+    /// Loot tables of all creatures that drop anything, by creature name
+    pub fn get_creature_loot_tables(&self) -> Result<Vec<crate::models::CreatureLootTable>> {
+        use crate::models::{CreatureLootTable, LootTableEntry};
+
+        let values = self.get_item_values()?;
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.name, c.short_name, l.item_id, i.name, l.chance_percent, l.min_amount, l.max_amount, c.race, cn.note, n.note,
+                    ci.image_name
+             FROM creature_loot l
+             JOIN creatures c ON c.id = l.creature_id
+             JOIN creature_images ci ON ci.creature_id = c.id
+             LEFT JOIN items i ON i.type_id = l.item_id
+             LEFT JOIN creature_notes cn ON cn.short_name = c.short_name
+             LEFT JOIN item_notes n ON n.type_id = l.item_id
+             ORDER BY c.name, c.id, l.chance_percent DESC, l.item_id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                let (min_amount, max_amount): (i32, i32) = (row.get(5)?, row.get(6)?);
+                let item: i32 = row.get(2)?;
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i32>(7)?,
+                    row.get::<_, String>(10)?,
+                    row.get::<_, Option<String>>(8)?,
+                    LootTableEntry {
+                        item,
+                        name: row.get(3)?,
+                        chance: row.get(4)?,
+                        amount: if min_amount == max_amount {
+                            max_amount.to_string()
+                        } else {
+                            format!("{}-{}", min_amount, max_amount)
+                        },
+                        value: values.get(&item).copied(),
+                        note: row.get(9)?,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut tables: Vec<CreatureLootTable> = Vec::new();
+        for (name, short_name, race, image, note, entry) in rows {
+            match tables.last_mut() {
+                Some(table) if table.short_name == short_name => table.loot.push(entry),
+                _ => tables.push(CreatureLootTable { name, short_name, race, image, note, loot: vec![entry], summons: Vec::new() }),
+            }
+        }
+        Ok(tables)
+    }
+
+    /// Load all creatures into memory by race number
+    pub fn get_creature_catalog(&self) -> Result<crate::catalog::CreatureCatalog> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare("SELECT race, name, short_name, hp, experience FROM creatures ORDER BY id")?;
+        let catalog = stmt
+            .query_map([], |row| {
+                Ok(crate::catalog::CatalogCreature {
+                    race: row.get(0)?,
+                    name: row.get(1)?,
+                    short_name: row.get(2)?,
+                    hp: row.get(3)?,
+                    experience: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(catalog)
+    }
+
+    /// Summon spells as (creature short name, summoned race, count), in spell order
+    pub fn get_creature_summons(&self) -> Result<Vec<(String, i32, i32)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.short_name, s.summon_race_id, COALESCE(s.summon_count, 1)
+             FROM creature_spells s
+             JOIN creatures c ON c.id = s.creature_id
+             WHERE s.summon_race_id IS NOT NULL
+             ORDER BY c.short_name, s.spell_order",
+        )?;
+        let summons = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(summons)
+    }
+
+    /// Set `creature_spells.summon_name` from the summoned race. Returns the number
+    /// of summon spells whose creature is known.
+    pub fn name_summoned_creatures(&self, creatures: &crate::catalog::CreatureCatalog) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let races: Vec<(i64, i32)> = tx
+            .prepare("SELECT id, summon_race_id FROM creature_spells WHERE summon_race_id IS NOT NULL")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+
+        let mut named = 0;
+        {
+            let mut update = tx.prepare("UPDATE creature_spells SET summon_name = ?1 WHERE id = ?2")?;
+            for (id, race) in races {
+                let name = creatures.name(race);
+                named += usize::from(name.is_some());
+                update.execute(params![name, id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(named)
+    }
+
+    /// Spawns with the stats of their creature; spawns of unknown races are left out
+    pub fn get_spawn_creatures(&self) -> Result<Vec<crate::hunting::SpawnCreature>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT s.pos_x, s.pos_y, s.pos_z, s.amount, s.regen_seconds, s.town,
+                    c.race, c.name, c.short_name, c.hp, c.experience, n.note, s.radius
+             FROM spawns s
+             JOIN creatures c ON c.id = (SELECT id FROM creatures WHERE race = s.race ORDER BY id LIMIT 1)
+             LEFT JOIN creature_notes n ON n.short_name = c.short_name
+             ORDER BY s.id",
+        )?;
+        let spawns = stmt
+            .query_map([], |row| {
+                Ok(crate::hunting::SpawnCreature {
+                    position: crate::geo::Position::new(row.get(0)?, row.get(1)?, row.get(2)?),
+                    amount: row.get(3)?,
+                    regen_seconds: row.get(4)?,
+                    town: row.get(5)?,
+                    race: row.get(6)?,
+                    name: row.get(7)?,
+                    short_name: row.get(8)?,
+                    hp: row.get(9)?,
+                    experience: row.get(10)?,
+                    note: row.get(11)?,
+                    radius: row.get(12)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(spawns)
+    }
+
+    /// Quests with their chest positions and reward item IDs, by ID
+    pub fn get_quest_rows(&self) -> Result<Vec<crate::models::QuestRow>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, region, town, chest_location, reward_items_json FROM quests ORDER BY id",
+        )?;
+        let quests = stmt
+            .query_map([], |row| {
+                // Chests are stored as "x,y,z (sector); ..."
+                let chests = row
+                    .get::<_, Option<String>>(4)?
+                    .unwrap_or_default()
+                    .split("; ")
+                    .filter_map(|chest| chest.split(" (").next()?.parse().ok())
+                    .collect();
+                Ok(crate::models::QuestRow {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    region: row.get(2)?,
+                    town: row.get(3)?,
+                    chests,
+                    reward_item_ids: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(quests)
+    }
+
+    /// Keys found in quest chests, as (quest ID, key)
+    pub fn get_quest_keys(&self) -> Result<Vec<(i32, crate::models::KeyItem)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare("SELECT quest_id, type_id, key_number FROM quest_keys ORDER BY quest_id, key_number")?;
+        let keys = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, crate::models::KeyItem { type_id: row.get(1)?, key_number: row.get(2)? }))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(keys)
+    }
+
+    /// Stored doors, by position
+    pub fn get_doors(&self) -> Result<Vec<crate::models::MapDoor>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT pos_x, pos_y, pos_z, type_id, keyhole_number, level, quest_number, quest_value
+             FROM doors ORDER BY pos_z, pos_y, pos_x",
+        )?;
+        let doors = stmt
+            .query_map([], |row| {
+                Ok(crate::models::MapDoor {
+                    position: (row.get(0)?, row.get(1)?, row.get(2)?),
+                    type_id: row.get(3)?,
+                    keyhole_number: row.get(4)?,
+                    level: row.get(5)?,
+                    quest_number: row.get(6)?,
+                    quest_value: row.get(7)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(doors)
+    }
+
+    /// Creatures dropping an item with their drop chance in percent, most likely first
+    pub fn get_item_droppers(&self, item_id: i32) -> Result<Vec<(String, f64)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.name, s.drop_chance
+             FROM item_loot_sources s
+             JOIN creatures c ON c.id = s.creature_id
+             WHERE s.item_id = ?1
+             ORDER BY s.drop_chance DESC, c.name",
+        )?;
+        let droppers = stmt
+            .query_map(params![item_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(droppers)
+    }
+
+    /// Gold value of items: the best price an NPC pays for them, and face value for coins
+    pub fn get_item_values(&self) -> Result<HashMap<i32, i64>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare("SELECT item_id, price / amount FROM best_sell_price")?;
+        let mut values = stmt
+            .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i64>(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        values.extend(crate::loot::CURRENCY_VALUES);
+        Ok(values)
+    }
+
+    /// Cheapest NPC offer for players buying an item
+    pub fn best_buy_price(&self, item_id: i32) -> Result<Option<crate::models::BestPrice>> {
+        self.best_price("best_buy_price", item_id)
+    }
+
+    /// Highest NPC offer for players selling an item
+    pub fn best_sell_price(&self, item_id: i32) -> Result<Option<crate::models::BestPrice>> {
+        self.best_price("best_sell_price", item_id)
+    }
+
+    fn best_price(&self, view: &str, item_id: i32) -> Result<Option<crate::models::BestPrice>> {
+        let conn = self.connection()?;
+        let price = conn
+            .query_row(
+                &format!("SELECT npc_name, town, price, amount FROM {} WHERE item_id = ?1", view),
+                params![item_id],
+                |row| {
+                    Ok(crate::models::BestPrice {
+                        npc_name: row.get(0)?,
+                        town: row.get(1)?,
+                        price: row.get(2)?,
+                        amount: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(price)
+    }
+
+    /// The `limit` NPCs nearest to a position, nearest first. NPCs on other
+    /// floors rank after those on the position's floor at the same distance.
+    pub fn nearest_npcs(&self, position: crate::geo::Position, limit: usize) -> Result<Vec<crate::models::NearbyNpc>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT n.name, t.town, n.home_x, n.home_y, n.home_z
+             FROM npcs n
+             LEFT JOIN npc_towns t ON t.npc_name = n.name",
+        )?;
+        let mut npcs = stmt
+            .query_map([], |row| Self::nearby_npc(row, position))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        npcs.sort_by(|a, b| (a.distance, a.floors, &a.npc_name).cmp(&(b.distance, b.floors, &b.npc_name)));
+        npcs.truncate(limit);
+        Ok(npcs)
+    }
+
+    /// The `limit` NPCs nearest to a position that sell an item to players,
+    /// nearest first; NPCs at the same distance are ordered by price per item.
+    pub fn nearest_item_sellers(
+        &self,
+        item_id: i32,
+        position: crate::geo::Position,
+        limit: usize,
+    ) -> Result<Vec<crate::models::NearbyOffer>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT n.name, t.town, n.home_x, n.home_y, n.home_z, p.price, p.amount
+             FROM item_prices p
+             JOIN npcs n ON n.name = p.npc_name
+             LEFT JOIN npc_towns t ON t.npc_name = n.name
+             WHERE p.item_id = ?1 AND p.mode = 'buy'",
+        )?;
+        let mut offers = stmt
+            .query_map(params![item_id], |row| {
+                Ok(crate::models::NearbyOffer {
+                    npc: Self::nearby_npc(row, position)?,
+                    price: row.get(5)?,
+                    amount: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let unit_price = |offer: &crate::models::NearbyOffer| offer.price as f64 / offer.amount.max(1) as f64;
+        offers.sort_by(|a, b| {
+            (a.npc.distance, a.npc.floors)
+                .cmp(&(b.npc.distance, b.npc.floors))
+                .then(unit_price(a).total_cmp(&unit_price(b)))
+        });
+        offers.truncate(limit);
+        Ok(offers)
+    }
+
+    /// NPC from the first five columns: name, town, home_x, home_y, home_z
+    fn nearby_npc(row: &rusqlite::Row, position: crate::geo::Position) -> rusqlite::Result<crate::models::NearbyNpc> {
+        let home = crate::geo::Position::new(row.get(2)?, row.get(3)?, row.get(4)?);
+        Ok(crate::models::NearbyNpc {
+            npc_name: row.get(0)?,
+            town: row.get(1)?,
+            home,
+            distance: position.distance(home),
+            floors: (position.z - home.z).abs(),
+        })
+    }
+
+    /// Get all items with their best NPC prices for the website item list
+    pub fn get_item_list(&self) -> Result<Vec<crate::models::ItemListing>> {
+        use crate::models::BestPrice;
+
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT i.type_id, i.name,
+                    b.npc_name, b.town, b.price, b.amount,
+                    s.npc_name, s.town, s.price, s.amount, n.note
+             FROM items i
+             LEFT JOIN best_buy_price b ON b.item_id = i.type_id
+             LEFT JOIN best_sell_price s ON s.item_id = i.type_id
+             LEFT JOIN item_notes n ON n.type_id = i.type_id
+             ORDER BY i.type_id",
+        )?;
+
+        let best = |row: &rusqlite::Row, first: usize| -> rusqlite::Result<Option<BestPrice>> {
+            let Some(npc_name) = row.get::<_, Option<String>>(first)? else {
+                return Ok(None);
+            };
+            Ok(Some(BestPrice {
+                npc_name,
+                town: row.get(first + 1)?,
+                price: row.get(first + 2)?,
+                amount: row.get(first + 3)?,
+            }))
+        };
+        let items = stmt
+            .query_map([], |row| {
+                Ok(crate::models::ItemListing {
+                    type_id: row.get(0)?,
+                    name: row.get(1)?,
+                    best_buy: best(row, 2)?,
+                    best_sell: best(row, 6)?,
+                    note: row.get(10)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(items)
+    }
+
+    /// Store creature notes by short name; an empty note removes it. Returns the
+    /// short names (of non-empty notes) that no creature has.
+    pub fn upsert_creature_notes(&self, notes: &[(String, String)]) -> Result<Vec<String>> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let mut unknown = Vec::new();
+        for (short_name, note) in notes {
+            if note.is_empty() {
+                tx.execute("DELETE FROM creature_notes WHERE short_name = ?1", params![short_name])?;
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO creature_notes (short_name, note) VALUES (?1, ?2)
+                 ON CONFLICT(short_name) DO UPDATE SET note = excluded.note, updated_at = CURRENT_TIMESTAMP",
+                params![short_name, note],
+            )?;
+            let known: bool =
+                tx.query_row("SELECT EXISTS (SELECT 1 FROM creatures WHERE short_name = ?1)", params![short_name], |row| row.get(0))?;
+            if !known {
+                unknown.push(short_name.clone());
+            }
+        }
+        tx.commit()?;
+        Ok(unknown)
+    }
+
+    /// Store item notes by type ID; an empty note removes it. Returns the type
+    /// IDs (of non-empty notes) that no item has.
+    pub fn upsert_item_notes(&self, notes: &[(i32, String)]) -> Result<Vec<i32>> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let mut unknown = Vec::new();
+        for (type_id, note) in notes {
+            if note.is_empty() {
+                tx.execute("DELETE FROM item_notes WHERE type_id = ?1", params![type_id])?;
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO item_notes (type_id, note) VALUES (?1, ?2)
+                 ON CONFLICT(type_id) DO UPDATE SET note = excluded.note, updated_at = CURRENT_TIMESTAMP",
+                params![type_id, note],
+            )?;
+            let known: bool = tx.query_row("SELECT EXISTS (SELECT 1 FROM items WHERE type_id = ?1)", params![type_id], |row| row.get(0))?;
+            if !known {
+                unknown.push(*type_id);
+            }
+        }
+        tx.commit()?;
+        Ok(unknown)
+    }
+
+    /// Get item names keyed by type ID
+    pub fn get_item_names(&self) -> Result<HashMap<i32, String>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare("SELECT type_id, name FROM items")?;
+        let names = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(names)
+    }
+
+    /// Get a creature's race number by short name or name (case-insensitive)
+    pub fn get_creature_race(&self, creature: &str) -> Result<Option<i32>> {
+        let conn = self.connection()?;
+        let race = conn
+            .query_row(
+                "SELECT race FROM creatures
+                 WHERE short_name = ?1 COLLATE NOCASE OR name = ?1 COLLATE NOCASE
+                 ORDER BY short_name = ?1 COLLATE NOCASE DESC
+                 LIMIT 1",
+                [creature],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(race)
+    }
+
+    /// Get the items of a category, by type ID
+    pub fn list_items_by_category(&self, category: crate::models::ItemCategory) -> Result<Vec<crate::models::Item>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT type_id, name, COALESCE(flags, ''), COALESCE(attributes, '{}'), description
+             FROM items
+             WHERE category = ?1
+             ORDER BY type_id",
+        )?;
+        let items = stmt
+            .query_map([category.as_str()], |row| {
+                Ok(crate::models::Item {
+                    type_id: row.get(0)?,
+                    name: row.get(1)?,
+                    flags: row.get(2)?,
+                    attributes: row.get(3)?,
+                    description: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(items)
+    }
+
+    /// Load the metadata of all items into memory, with their values from
+    /// [`get_item_values`](Self::get_item_values)
+    pub fn get_item_catalog(&self) -> Result<crate::catalog::ItemCatalog> {
+        use crate::catalog::CatalogItem;
+
+        let values = self.get_item_values()?;
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT type_id, name, flag_bits,
+                    CASE WHEN json_valid(attributes)
+                         THEN CAST(json_extract(attributes, '$.Weight') AS INTEGER) END,
+                    CASE WHEN json_valid(attributes)
+                         THEN CAST(json_extract(attributes, '$.Capacity') AS INTEGER) END
+             FROM items",
+        )?;
+        let catalog = stmt
+            .query_map([], |row| {
+                let type_id: i32 = row.get(0)?;
+                Ok(CatalogItem {
+                    type_id,
+                    name: row.get(1)?,
+                    flags: crate::models::ItemFlags(row.get(2)?),
+                    weight: row.get::<_, Option<i32>>(3)?.unwrap_or(0),
+                    capacity: row.get::<_, Option<i64>>(4)?.map(|capacity| capacity.max(0) as usize),
+                    value: values.get(&type_id).copied(),
+                })
+            })?
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(catalog)
+    }
+
+    /// Get the type IDs and names of items matching an SQL condition on the items table,
+    /// e.g. `name LIKE '%crossbow%'`
+    pub fn find_items_where(&self, condition: &str) -> Result<Vec<(i32, String)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!("SELECT type_id, name FROM items WHERE {} ORDER BY type_id", condition))?;
+        let items = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(items)
+    }
+
+    /// Get creature names keyed by race number
+    pub fn get_creature_names_by_race(&self) -> Result<HashMap<i32, String>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare("SELECT race, name FROM creatures ORDER BY id")?;
+        let names = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        Ok(names)
     }
 
     // Additional helper methods will be added as needed
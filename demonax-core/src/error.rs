@@ -11,6 +11,12 @@ pub enum DemonaxError {
     #[error("Connection pool error: {0}")]
     Pool(#[from] r2d2::Error),
 
+    #[error("SSH error: {0}")]
+    Ssh(#[from] ssh2::Error),
+
+    #[error("S3 error: {0}")]
+    S3(String),
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
@@ -20,6 +26,9 @@ pub enum DemonaxError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Container {type_id} has {capacity} slots but would hold {count} items")]
+    ContainerOverflow { type_id: i32, capacity: usize, count: usize },
+
     #[error("Invalid file format: {0}")]
     InvalidFileFormat(String),
 
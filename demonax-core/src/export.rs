@@ -0,0 +1,247 @@
+//! JSON exports for the website.
+//!
+//! Every export goes through [`write_json`], which applies the [`Anonymizer`]
+//! when one is given, so no export can leak player names, IDs or file paths
+//! in anonymized mode.
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::hunting::{HuntingOverlay, build_hunting_spot};
+use crate::quest_route::quest_routes;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Fields replaced by a hash token in anonymized exports
+pub const PLAYER_FIELDS: &[&str] = &["player_id", "player_name", "owner_id", "owner_name", "killer_name"];
+
+/// Fields removed from anonymized exports
+pub const DROPPED_FIELDS: &[&str] = &["source_file"];
+
+/// Replaces player names and IDs with stable tokens and drops file paths.
+///
+/// The same value always gets the same token for the same salt, so exports made
+/// at different times can still be joined. Without a secret salt a token can be
+/// reversed by hashing candidate names.
+#[derive(Debug, Clone, Default)]
+pub struct Anonymizer {
+    salt: String,
+}
+
+impl Anonymizer {
+    pub fn new(salt: &str) -> Self {
+        Self { salt: salt.to_string() }
+    }
+
+    /// Token for a player name or ID
+    pub fn token(&self, value: &str) -> String {
+        // 64-bit FNV-1a, which (unlike std's hasher) is stable across Rust versions
+        let hash = self.salt.bytes().chain([0]).chain(value.bytes()).fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        format!("p-{:016x}", hash)
+    }
+
+    /// Anonymize every object in a JSON value
+    pub fn apply(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.retain(|key, _| !DROPPED_FIELDS.contains(&key.as_str()));
+                for (key, field) in map.iter_mut() {
+                    if PLAYER_FIELDS.contains(&key.as_str()) {
+                        *field = match &*field {
+                            serde_json::Value::Null => serde_json::Value::Null,
+                            serde_json::Value::String(s) => self.token(s).into(),
+                            other => self.token(&other.to_string()).into(),
+                        };
+                    } else {
+                        self.apply(field);
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|v| self.apply(v)),
+            _ => {}
+        }
+    }
+}
+
+/// Write the house list (with current owners) as a JSON array.
+/// Returns the number of houses written.
+pub fn export_house_list(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let houses = db.get_house_list()?;
+    write_json(output, &houses, anonymizer, true)?;
+    Ok(houses.len())
+}
+
+/// Write the item list (with the best NPC buy and sell offers) as a JSON array.
+/// Returns the number of items written.
+pub fn export_item_list(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let items = db.get_item_list()?;
+    write_json(output, &items, anonymizer, true)?;
+    Ok(items.len())
+}
+
+/// Write the raid list as a JSON array, without disabled raids unless
+/// `include_disabled` is set. Returns the number of raids written.
+pub fn export_raid_list(db: &Database, output: &Path, include_disabled: bool, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let raids = db.get_raid_list(None, include_disabled)?;
+    write_json(output, &raids, anonymizer, true)?;
+    Ok(raids.len())
+}
+
+/// Write every player's latest snapshot (level and skills) as a JSON array.
+/// Returns the number of players written.
+pub fn export_snapshot_list(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let snapshots = db.get_snapshot_list()?;
+    write_json(output, &snapshots, anonymizer, true)?;
+    Ok(snapshots.len())
+}
+
+/// Write the world statistics of every snapshot date, with the events running
+/// on it, as a JSON array. Returns the number of dates written.
+pub fn export_world_stats(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let stats = db.get_world_stats()?;
+    write_json(output, &stats, anonymizer, true)?;
+    Ok(stats.len())
+}
+
+/// Write the events from add-event as a JSON array, so graphs can shade their
+/// windows. Returns the number of events written.
+pub fn export_events(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let events = db.get_events()?;
+    write_json(output, &events, anonymizer, true)?;
+    Ok(events.len())
+}
+
+/// Write the deaths, level advances and rare loot from the game server log since
+/// the latest snapshot date, newest first, as a JSON array. Returns the number
+/// of events written.
+pub fn export_log_events(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let events = db.get_log_events(None)?;
+    write_json(output, &events, anonymizer, true)?;
+    Ok(events.len())
+}
+
+/// Write the peak and average players online per day as a JSON array, for the
+/// population graph. Returns the number of days written.
+pub fn export_online_days(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let days = db.get_online_days()?;
+    write_json(output, &days, anonymizer, true)?;
+    Ok(days.len())
+}
+
+/// Write every quest's route (chests, doors, keys and where to get them, and a
+/// recommended level) as a JSON array. Returns the number of quests written.
+pub fn export_quest_routes(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let routes = quest_routes(db)?;
+    write_json(output, &routes, anonymizer, true)?;
+    Ok(routes.len())
+}
+
+/// Write the bestiary totals (kills per creature over every player's latest
+/// snapshot) as a JSON array. Returns the number of monster IDs written.
+pub fn export_bestiary(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let totals = db.get_bestiary_totals()?;
+    write_json(output, &totals, anonymizer, true)?;
+    Ok(totals.len())
+}
+
+/// Write the harvesting rules, with their tool, creature and reward resolved
+/// and described in words, as a JSON array. Returns the number of rules written.
+pub fn export_harvesting_rules(db: &Database, output: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let rules = db.get_harvesting_rules()?;
+    write_json(output, &rules, anonymizer, true)?;
+    Ok(rules.len())
+}
+
+/// Write one compact `creatures/{short_name}.json` loot table per creature below
+/// `output_dir`, small enough to embed in a bestiary page, with the creatures
+/// it summons. Returns the number of creatures written.
+pub fn export_creature_loot(db: &Database, output_dir: &Path, anonymizer: Option<&Anonymizer>) -> Result<usize> {
+    let mut tables = db.get_creature_loot_tables()?;
+    let creatures = db.get_creature_catalog()?;
+    for (short_name, race, count) in db.get_creature_summons()? {
+        if let Some(table) = tables.iter_mut().find(|table| table.short_name == short_name) {
+            let name = creatures.name(race).map_or_else(|| format!("Race {}", race), str::to_string);
+            table.summons.push(format!("{} {}", count, name));
+        }
+    }
+    let creatures_dir = output_dir.join("creatures");
+    for table in &tables {
+        write_json(&creatures_dir.join(format!("{}.json", table.short_name)), table, anonymizer, false)?;
+    }
+    Ok(tables.len())
+}
+
+/// Write one `hunting/{slug}.json` page per spot of the overlay below `output_dir`.
+/// Returns the number of spots written.
+pub fn export_hunting_spots(
+    db: &Database,
+    overlay: &HuntingOverlay,
+    output_dir: &Path,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<usize> {
+    let spawns = db.get_spawn_creatures()?;
+    let mut combat = HashMap::new();
+    for spawn in &spawns {
+        if !combat.contains_key(&spawn.short_name)
+            && let Some(creature) = db.get_creature_combat(&spawn.short_name)?
+        {
+            combat.insert(spawn.short_name.clone(), creature);
+        }
+    }
+
+    let hunting_dir = output_dir.join("hunting");
+    for spec in &overlay.spots {
+        let spot = build_hunting_spot(spec, &spawns, &combat);
+        write_json(&hunting_dir.join(format!("{}.json", spot.slug)), &spot, anonymizer, true)?;
+    }
+    Ok(overlay.spots.len())
+}
+
+fn write_json<T: serde::Serialize>(output: &Path, value: &T, anonymizer: Option<&Anonymizer>, pretty: bool) -> Result<()> {
+    fn to_string<T: serde::Serialize + ?Sized>(value: &T, pretty: bool) -> serde_json::Result<String> {
+        if pretty { serde_json::to_string_pretty(value) } else { serde_json::to_string(value) }
+    }
+
+    let json = match anonymizer {
+        Some(anonymizer) => {
+            let mut value = serde_json::to_value(value)?;
+            anonymizer.apply(&mut value);
+            to_string(&value, pretty)?
+        }
+        None => to_string(value, pretty)?,
+    };
+
+    if let Some(parent) = output.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymizer_hashes_players_consistently() {
+        let anonymizer = Anonymizer::new("secret");
+        let mut value = serde_json::json!([
+            {"player_id": 7, "player_name": "Bubble", "source_file": "/srv/usr/7.usr", "level": 30},
+            {"house_id": 1, "owner_name": "Bubble"},
+            {"house_id": 2, "owner_name": null},
+        ]);
+        anonymizer.apply(&mut value);
+
+        let token = anonymizer.token("Bubble");
+        assert_eq!(value[0]["player_name"], token);
+        assert_eq!(value[1]["owner_name"], token);
+        assert_eq!(value[0]["player_id"], anonymizer.token("7"));
+        assert!(value[0].get("source_file").is_none());
+        assert_eq!(value[0]["level"], 30);
+        assert!(value[2]["owner_name"].is_null());
+        assert_ne!(token, Anonymizer::new("other").token("Bubble"));
+    }
+}
@@ -1,6 +1,8 @@
 //! File utility functions.
 
 use crate::error::{DemonaxError, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use regex::Regex;
 use std::path::Path;
 use walkdir::WalkDir;
 
@@ -15,21 +17,75 @@ pub fn find_files_with_extension(dir: &Path, extension: &str) -> Result<Vec<std:
 
     let mut files = Vec::new();
     for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
-        if entry.file_type().is_file() {
-            if let Some(ext) = entry.path().extension() {
-                if ext == extension {
-                    files.push(entry.path().to_path_buf());
-                }
-            }
+        if entry.file_type().is_file()
+            && let Some(ext) = entry.path().extension()
+            && ext == extension
+        {
+            files.push(entry.path().to_path_buf());
         }
     }
     Ok(files)
 }
 
+/// Spaces out file reads to a maximum rate, shared by all threads.
+#[derive(Debug)]
+pub struct ReadThrottle {
+    interval: std::time::Duration,
+    next: std::sync::Mutex<std::time::Instant>,
+}
+
+impl ReadThrottle {
+    pub fn new(files_per_second: f64) -> Result<Self> {
+        if !(files_per_second > 0.0 && files_per_second.is_finite()) {
+            return Err(DemonaxError::Validation(format!(
+                "read rate must be a positive number of files per second, got {}",
+                files_per_second
+            )));
+        }
+        Ok(Self {
+            interval: std::time::Duration::from_secs_f64(1.0 / files_per_second),
+            next: std::sync::Mutex::new(std::time::Instant::now()),
+        })
+    }
+
+    /// Block until the next read slot
+    pub fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let slot = (*next).max(std::time::Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        std::thread::sleep(slot.saturating_duration_since(std::time::Instant::now()));
+    }
+}
+
+static READ_THROTTLE: std::sync::OnceLock<ReadThrottle> = std::sync::OnceLock::new();
+
+/// Limit game file reads (through [`read_latin1_file`] and [`read_utf8_file`]) to
+/// `files_per_second` for the rest of the process, so ingestion doesn't starve a
+/// game server on the same host. Can only be set once.
+pub fn set_read_rate_limit(files_per_second: f64) -> Result<()> {
+    READ_THROTTLE
+        .set(ReadThrottle::new(files_per_second)?)
+        .map_err(|_| DemonaxError::Validation("read rate limit is already set".to_string()))
+}
+
+pub(crate) fn throttle_read() {
+    if let Some(throttle) = READ_THROTTLE.get() {
+        throttle.wait();
+    }
+}
+
 /// Read file with Latin1 encoding (Windows-1252).
 pub fn read_latin1_file(path: &Path) -> Result<String> {
-    let bytes = std::fs::read(path)?;
-    let (text, _, had_errors) = encoding_rs::WINDOWS_1252.decode(&bytes);
+    throttle_read();
+    decode_latin1(&std::fs::read(path)?)
+}
+
+/// Decode Latin1 (Windows-1252) bytes into a string.
+pub fn decode_latin1(bytes: &[u8]) -> Result<String> {
+    let (text, _, had_errors) = encoding_rs::WINDOWS_1252.decode(bytes);
     if had_errors {
         return Err(DemonaxError::Parse("Failed to decode Latin1 text".to_string()));
     }
@@ -38,5 +94,150 @@ pub fn read_latin1_file(path: &Path) -> Result<String> {
 
 /// Read file with UTF-8 encoding.
 pub fn read_utf8_file(path: &Path) -> Result<String> {
+    throttle_read();
     Ok(std::fs::read_to_string(path)?)
-}
\ No newline at end of file
+}
+
+/// Where an inferred snapshot date came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotDateSource {
+    /// A YYYY-MM-DD date in the usr directory's (or its parent's) name, e.g. `usr-2024-05-01/`
+    DirectoryName,
+    /// The modification date of the newest .usr file
+    NewestFile,
+}
+
+/// Find the first YYYY-MM-DD date in a file or directory name.
+pub fn find_date_in_name(name: &str) -> Option<NaiveDate> {
+    let date_re = Regex::new(r"(\d{4}-\d{2}-\d{2})").ok()?;
+    date_re
+        .captures_iter(name)
+        .find_map(|caps| NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d").ok())
+}
+
+/// Infer the snapshot date for a usr directory or archive.
+///
+/// Backup folders are named after their date, so the directory name (or its
+/// parent's, for layouts like `usr-2024-05-01/usr/`) is used first. Otherwise
+/// the local date of the newest .usr file's modification time is used (for an
+/// archive, the archive's own).
+pub fn infer_snapshot_date(usr_dir: &Path) -> Result<(NaiveDate, SnapshotDateSource)> {
+    for dir in usr_dir.ancestors().take(2) {
+        if let Some(date) = dir.file_name().and_then(|n| n.to_str()).and_then(find_date_in_name) {
+            return Ok((date, SnapshotDateSource::DirectoryName));
+        }
+    }
+
+    // An archive's own modification time stands in for its newest file
+    if crate::input::InputSource::new(usr_dir).is_archive() {
+        let modified = usr_dir.metadata()?.modified()?;
+        return Ok((DateTime::<Local>::from(modified).date_naive(), SnapshotDateSource::NewestFile));
+    }
+
+    let newest = find_files_with_extension(usr_dir, "usr")?
+        .iter()
+        .filter_map(|path| path.metadata().and_then(|m| m.modified()).ok())
+        .max()
+        .ok_or_else(|| {
+            DemonaxError::NotFound(format!("No .usr files to infer a snapshot date from in {}", usr_dir.display()))
+        })?;
+
+    Ok((DateTime::<Local>::from(newest).date_naive(), SnapshotDateSource::NewestFile))
+}
+
+/// Find the subdirectories (or .tar.gz/.zip archives) of `root` whose names match
+/// `pattern`, where `{date}` stands for a YYYY-MM-DD date (e.g. "usr-{date}").
+///
+/// Returns (date, directory) pairs sorted by date.
+pub fn find_dated_directories(root: &Path, pattern: &str) -> Result<Vec<(NaiveDate, std::path::PathBuf)>> {
+    let Some((prefix, suffix)) = pattern.split_once("{date}") else {
+        return Err(DemonaxError::Config(format!("Pattern '{}' must contain {{date}}", pattern)));
+    };
+    let name_re = Regex::new(&format!(
+        r"^{}(\d{{4}}-\d{{2}}-\d{{2}}){}$",
+        regex::escape(prefix),
+        regex::escape(suffix)
+    ))
+    .map_err(|e| DemonaxError::Config(format!("Invalid pattern '{}': {}", pattern, e)))?;
+
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        // Archives match by their name without the archive suffix
+        let name = match crate::input::InputSource::new(&path) {
+            source if source.is_archive() => crate::input::archive_stem(name).unwrap_or(name),
+            _ if path.is_dir() => name,
+            _ => continue,
+        };
+        if let Some(caps) = name_re.captures(name)
+            && let Ok(date) = NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d")
+        {
+            dirs.push((date, path));
+        }
+    }
+
+    dirs.sort();
+    Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_throttle_spaces_out_reads() {
+        let throttle = ReadThrottle::new(100.0).unwrap();
+        let start = std::time::Instant::now();
+        std::thread::scope(|scope| {
+            for _ in 0..2 {
+                scope.spawn(|| (0..3).for_each(|_| throttle.wait()));
+            }
+        });
+        // Six reads at 10ms intervals, the first one immediately
+        assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+        assert!(ReadThrottle::new(0.0).is_err());
+    }
+
+    #[test]
+    fn test_infer_snapshot_date_from_directory_names() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let nested = temp_dir.path().join("usr-2024-05-01").join("usr");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let (date, source) = infer_snapshot_date(&nested).unwrap();
+        assert_eq!(date, NaiveDate::from_ymd_opt(2024, 5, 1).unwrap());
+        assert_eq!(source, SnapshotDateSource::DirectoryName);
+
+        assert_eq!(find_date_in_name("backup-2024-13-01-2024-06-02"), NaiveDate::from_ymd_opt(2024, 6, 2));
+    }
+
+    #[test]
+    fn test_infer_snapshot_date_from_newest_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let usr_dir = temp_dir.path().join("usr");
+        std::fs::create_dir_all(&usr_dir).unwrap();
+        assert!(infer_snapshot_date(&usr_dir).is_err());
+
+        std::fs::write(usr_dir.join("1.usr"), "ID = 1").unwrap();
+        let (date, source) = infer_snapshot_date(&usr_dir).unwrap();
+        assert_eq!(date, Local::now().date_naive());
+        assert_eq!(source, SnapshotDateSource::NewestFile);
+    }
+
+    #[test]
+    fn test_find_dated_directories_sorted() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for name in ["usr-2024-05-02", "usr-2024-05-01", "usr-latest", "other-2024-05-03"] {
+            std::fs::create_dir(temp_dir.path().join(name)).unwrap();
+        }
+        std::fs::write(temp_dir.path().join("usr-2024-05-04"), "not a directory").unwrap();
+
+        let dirs = find_dated_directories(temp_dir.path(), "usr-{date}").unwrap();
+        let names: Vec<_> = dirs.iter().map(|(_, p)| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["usr-2024-05-01", "usr-2024-05-02"]);
+        assert!(find_dated_directories(temp_dir.path(), "usr").is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! Game formulas shared by the calculators: experience per level, experience
+//! stages and what a death costs.
+//!
+//! Formulas follow the classic server:
+//! - experience for a level: `50 / 3 * (level³ - 6 level² + 17 level - 12)`
+//! - experience gained is the creature's experience times the rate of the
+//!   player's level, from the `[experience]` section of `demonax.toml`:
+//!   ```toml
+//!   [experience]
+//!   rate = 1.0
+//!   stages = [{ from_level = 1, rate = 5.0 }, { from_level = 50, rate = 3.0 }, { from_level = 100, rate = 1.0 }]
+//!   ```
+//! - death costs 10% of the experience and of every skill's tries, 30% less
+//!   when promoted and another 8% less per blessing (at most [`MAX_BLESSINGS`])
+//! - on death each equipped item drops with a 10% chance and the backpack
+//!   always; every blessing lowers both chances by a fifth, so a player with
+//!   all blessings keeps everything
+
+use crate::error::{DemonaxError, Result};
+use crate::models::{Equipment, EquipmentSlot, SkillEntry};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Number of blessings a player can have
+pub const MAX_BLESSINGS: i32 = 5;
+
+/// Share of the experience and skill tries lost on death without protection
+const DEATH_LOSS: f64 = 0.10;
+
+/// Death loss of promoted vocations, relative to unpromoted ones
+const PROMOTED_DEATH_LOSS: f64 = 0.7;
+
+/// Share of the death loss each blessing takes away
+const BLESSING_DEATH_LOSS_REDUCTION: f64 = 0.08;
+
+/// Chance of an equipped item to drop on death without blessings
+const ITEM_LOSS_CHANCE: f64 = 0.10;
+
+/// Chance of the backpack to drop on death without blessings
+const CONTAINER_LOSS_CHANCE: f64 = 1.0;
+
+/// Display name of a .usr skill number, as in `daily_skills.skill_id`
+pub fn skill_name(skill_id: i32) -> &'static str {
+    match skill_id {
+        0 => "Level",
+        1 => "Magic level",
+        6 => "Shielding",
+        7 => "Distance",
+        8 => "Sword",
+        9 => "Club",
+        10 => "Axe",
+        11 => "Fist",
+        13 => "Fishing",
+        _ => "Unknown",
+    }
+}
+
+/// Whether a vocation name (e.g. "Elite Knight") is a promoted vocation
+pub fn is_promoted(vocation: &str) -> bool {
+    let vocation = vocation.to_ascii_lowercase();
+    ["elite", "royal", "master", "elder"].iter().any(|title| vocation.starts_with(title))
+}
+
+/// Total experience needed for a level
+pub fn experience_for_level(level: i32) -> i64 {
+    let level = level.max(1) as i64;
+    (50 * (level * level * level - 6 * level * level + 17 * level - 12)) / 3
+}
+
+/// Highest level reached with the given experience
+pub fn level_for_experience(experience: i64) -> i32 {
+    let mut level = 1;
+    while experience_for_level(level + 1) <= experience {
+        level += 1;
+    }
+    level
+}
+
+/// Experience rate from a level up to the next stage
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperienceStage {
+    pub from_level: i32,
+    pub rate: f64,
+}
+
+/// The `[experience]` section of `demonax.toml`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExperienceConfig {
+    /// Rate below the first stage, and at every level when there are no stages
+    pub rate: f64,
+    pub stages: Vec<ExperienceStage>,
+}
+
+impl Default for ExperienceConfig {
+    fn default() -> Self {
+        Self { rate: 1.0, stages: Vec::new() }
+    }
+}
+
+impl ExperienceConfig {
+    /// Reject rates that aren't positive
+    pub fn validate(&self) -> Result<()> {
+        for rate in std::iter::once(self.rate).chain(self.stages.iter().map(|stage| stage.rate)) {
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(DemonaxError::Config(format!("Experience rate {} must be positive", rate)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Multiplier of the experience gained at a level: that of the highest
+    /// stage starting at or below it
+    pub fn rate_at(&self, level: i32) -> f64 {
+        self.stages
+            .iter()
+            .filter(|stage| stage.from_level <= level)
+            .max_by_key(|stage| stage.from_level)
+            .map_or(self.rate, |stage| stage.rate)
+    }
+
+    /// Creature experience (before rates) it takes to go from `from` to `to`
+    /// total experience, level by level
+    pub fn base_experience_between(&self, from: i64, to: i64) -> f64 {
+        let mut level = level_for_experience(from);
+        let (mut current, mut base) = (from, 0.0);
+        while current < to {
+            let next = experience_for_level(level + 1).min(to);
+            base += (next - current) as f64 / self.rate_at(level);
+            current = next;
+            level += 1;
+        }
+        base
+    }
+
+    /// The climb from `experience` to `target_level`, split where the rate changes.
+    /// `base_per_hour` is creature experience per hour before rates, e.g. a hunting
+    /// spot's `experience_per_hour`.
+    pub fn level_plan(&self, experience: i64, target_level: i32, base_per_hour: Option<f64>) -> LevelPlan {
+        let level = level_for_experience(experience);
+        let target_experience = experience_for_level(target_level).max(experience);
+        let mut stages: Vec<StagePlan> = Vec::new();
+        let mut current = experience;
+        for from_level in level..target_level.max(level) {
+            let next = experience_for_level(from_level + 1).min(target_experience);
+            let rate = self.rate_at(from_level);
+            let base = (next - current) as f64 / rate;
+            match stages.last_mut() {
+                Some(stage) if stage.rate == rate => {
+                    stage.to_level = from_level + 1;
+                    stage.experience += next - current;
+                    stage.base_experience += base;
+                }
+                _ => stages.push(StagePlan {
+                    from_level,
+                    to_level: from_level + 1,
+                    rate,
+                    experience: next - current,
+                    base_experience: base,
+                    hours: None,
+                }),
+            }
+            current = next;
+        }
+        for stage in &mut stages {
+            stage.hours = base_per_hour.filter(|&per_hour| per_hour > 0.0).map(|per_hour| stage.base_experience / per_hour);
+        }
+
+        LevelPlan {
+            level,
+            experience,
+            target_level,
+            experience_needed: target_experience - experience,
+            base_experience_needed: stages.iter().map(|stage| stage.base_experience).sum(),
+            hours: base_per_hour.filter(|&per_hour| per_hour > 0.0).map(|_| stages.iter().filter_map(|stage| stage.hours).sum()),
+            stages,
+        }
+    }
+
+    /// Creature experience per day before rates, from (YYYY-MM-DD, total
+    /// experience) snapshots in date order. `None` without two dated snapshots
+    /// or without a gain.
+    pub fn base_experience_per_day(&self, history: &[(String, i64)]) -> Option<f64> {
+        let date = |(day, _): &(String, i64)| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok();
+        let (first, last) = (history.first()?, history.last()?);
+        let days = (date(last)? - date(first)?).num_days();
+        let base = self.base_experience_between(first.1, last.1);
+        (days > 0 && base > 0.0).then(|| base / days as f64)
+    }
+}
+
+/// Experience needed within one stretch of levels with the same rate
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StagePlan {
+    pub from_level: i32,
+    pub to_level: i32,
+    pub rate: f64,
+    /// Experience gained, i.e. after the rate
+    pub experience: i64,
+    /// Creature experience it takes, i.e. before the rate
+    pub base_experience: f64,
+    /// Hours at the given base experience per hour
+    pub hours: Option<f64>,
+}
+
+/// What it takes to reach a level under the configured experience stages
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LevelPlan {
+    pub level: i32,
+    pub experience: i64,
+    pub target_level: i32,
+    pub experience_needed: i64,
+    pub base_experience_needed: f64,
+    /// Hours at the given base experience per hour
+    pub hours: Option<f64>,
+    pub stages: Vec<StagePlan>,
+}
+
+/// Fraction of the experience and skill tries lost on death
+pub fn death_loss_fraction(promoted: bool, blessings: i32) -> f64 {
+    let promotion = if promoted { PROMOTED_DEATH_LOSS } else { 1.0 };
+    DEATH_LOSS * promotion * (1.0 - BLESSING_DEATH_LOSS_REDUCTION * blessings.clamp(0, MAX_BLESSINGS) as f64)
+}
+
+/// Experience lost on death with the given total experience
+pub fn death_experience_loss(experience: i64, promoted: bool, blessings: i32) -> i64 {
+    (experience.max(0) as f64 * death_loss_fraction(promoted, blessings)).round() as i64
+}
+
+/// Chances of an equipped item and of the backpack to drop on death
+pub fn item_loss_chances(blessings: i32) -> (f64, f64) {
+    let unblessed = 1.0 - blessings.clamp(0, MAX_BLESSINGS) as f64 / MAX_BLESSINGS as f64;
+    (ITEM_LOSS_CHANCE * unblessed, CONTAINER_LOSS_CHANCE * unblessed)
+}
+
+/// Tries of one skill lost on death
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkillLoss {
+    pub skill_id: i32,
+    pub name: String,
+    pub value: i32,
+    pub tries: i64,
+    pub tries_lost: i64,
+}
+
+/// What a death would cost a player
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeathLoss {
+    /// Fraction of the experience and skill tries lost
+    pub loss_fraction: f64,
+    pub experience_lost: i64,
+    pub level_before: i32,
+    pub level_after: i32,
+    /// Skills other than the level, in the order given
+    pub skills: Vec<SkillLoss>,
+    /// Chance of each equipped item (other than the backpack) to drop
+    pub item_loss_chance: f64,
+    /// Chance of the backpack and everything in it to drop
+    pub container_loss_chance: f64,
+    /// Expected number of equipped items dropped, not counting the backpack
+    pub expected_items_lost: f64,
+}
+
+/// What a death would cost a player with this level, experience, skills and
+/// equipment. Skill entries of the level (skill 0) are left out.
+pub fn death_loss(
+    level: i32,
+    experience: i64,
+    promoted: bool,
+    blessings: i32,
+    skills: &[SkillEntry],
+    equipment: &Equipment,
+) -> DeathLoss {
+    let loss_fraction = death_loss_fraction(promoted, blessings);
+    let experience_lost = death_experience_loss(experience, promoted, blessings);
+    let (item_loss_chance, container_loss_chance) = item_loss_chances(blessings);
+    let equipped = equipment
+        .iter()
+        .filter(|(slot, item)| *slot != EquipmentSlot::Backpack && item.is_some())
+        .count();
+
+    DeathLoss {
+        loss_fraction,
+        experience_lost,
+        level_before: level,
+        level_after: level_for_experience(experience - experience_lost).min(level),
+        skills: skills
+            .iter()
+            .filter(|skill| skill.skill_id != 0)
+            .map(|skill| SkillLoss {
+                skill_id: skill.skill_id,
+                name: skill_name(skill.skill_id).to_string(),
+                value: skill.value,
+                tries: skill.tries,
+                tries_lost: (skill.tries.max(0) as f64 * loss_fraction).round() as i64,
+            })
+            .collect(),
+        item_loss_chance,
+        container_loss_chance,
+        expected_items_lost: equipped as f64 * item_loss_chance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EquipmentItem;
+
+    #[test]
+    fn test_death_experience_loss() {
+        assert_eq!(experience_for_level(8), 4200);
+        assert_eq!(experience_for_level(20), 98800);
+        assert_eq!((level_for_experience(98799), level_for_experience(98800)), (19, 20));
+        assert!(is_promoted("Master Sorcerer") && !is_promoted("Sorcerer"));
+
+        assert_eq!(death_experience_loss(100_000, false, 0), 10_000);
+        assert_eq!(death_experience_loss(100_000, true, 0), 7_000);
+        assert_eq!(death_experience_loss(100_000, true, 5), 4_200);
+        assert_eq!(death_experience_loss(100_000, false, 9), death_experience_loss(100_000, false, MAX_BLESSINGS));
+    }
+
+    #[test]
+    fn test_death_loss_of_skills_and_items() {
+        let skill = |skill_id: i32, tries: i64| SkillEntry { skill_id, value: 50, max: 0, min: 10, modifier: 0, tries, next_level: 0 };
+        let mut equipment = Equipment::default();
+        equipment.set(EquipmentSlot::Backpack, Some(EquipmentItem::new(2854)));
+        equipment.set(EquipmentSlot::Armor, Some(EquipmentItem::new(3357)));
+        equipment.set(EquipmentSlot::Helmet, Some(EquipmentItem::new(3354)));
+
+        let loss = death_loss(20, 98800, false, 0, &[skill(0, 98800), skill(8, 5000)], &equipment);
+        assert_eq!((loss.experience_lost, loss.level_after), (9880, 19));
+        assert_eq!(loss.skills.len(), 1);
+        assert_eq!((loss.skills[0].name.as_str(), loss.skills[0].tries_lost), ("Sword", 500));
+        assert_eq!(loss.container_loss_chance, 1.0);
+        assert!((loss.expected_items_lost - 0.2).abs() < 1e-9);
+
+        let blessed = death_loss(20, 98800, false, MAX_BLESSINGS, &[], &equipment);
+        assert_eq!((blessed.item_loss_chance, blessed.container_loss_chance), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_experience_stages() {
+        let stages = ExperienceConfig {
+            rate: 1.0,
+            stages: vec![ExperienceStage { from_level: 1, rate: 5.0 }, ExperienceStage { from_level: 8, rate: 2.0 }],
+        };
+        assert_eq!((stages.rate_at(7), stages.rate_at(8), stages.rate_at(200)), (5.0, 2.0, 2.0));
+        assert_eq!(ExperienceConfig::default().rate_at(50), 1.0);
+
+        // Level 1 to 8 at 5x, 8 to 9 at 2x
+        let plan = stages.level_plan(0, 9, Some(100.0));
+        assert_eq!(plan.experience_needed, experience_for_level(9));
+        assert_eq!(plan.stages.len(), 2);
+        assert_eq!((plan.stages[0].from_level, plan.stages[0].to_level, plan.stages[0].experience), (1, 8, 4200));
+        assert_eq!(plan.stages[0].base_experience, 840.0);
+        assert_eq!(plan.stages[1].base_experience, (6400.0 - 4200.0) / 2.0);
+        assert_eq!(plan.hours, Some((840.0 + 1100.0) / 100.0));
+        assert_eq!(stages.base_experience_between(0, 6400), plan.base_experience_needed);
+
+        let history = [("2026-01-01".to_string(), 0), ("2026-01-05".to_string(), 4200)];
+        assert_eq!(stages.base_experience_per_day(&history), Some(210.0));
+        assert_eq!(stages.base_experience_per_day(&history[..1]), None);
+        assert!(ExperienceConfig { rate: 0.0, stages: Vec::new() }.validate().is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Map coordinates, sectors and named regions.
+//!
+//! The map is stored as 32x32 field sectors, one `<x>-<y>-<z>.sec` file each,
+//! where sector (x, y) holds the fields from (32·x, 32·y). Floors run from 0
+//! (highest) to 15, with 7 at ground level.
+//!
+//! Regions are polygons of (x, y) map coordinates, set in the `[[regions]]`
+//! tables of demonax.toml. A position belongs to the first region containing
+//! it, so smaller regions go before the ones around them:
+//! ```toml
+//! [[regions]]
+//! name = "rookgaard"
+//! polygon = [[31872, 32128], [32160, 32128], [32160, 32320], [31872, 32320]]
+//! ```
+//!
+//! Towns, from the `[[towns]]` tables, have a temple and optional bounds. A
+//! position belongs to the first town whose bounds contain it, or else to the
+//! town with the nearest temple within [`TOWN_RANGE`] fields:
+//! ```toml
+//! [[towns]]
+//! name = "Thais"
+//! temple = [32369, 32241, 7]
+//! bounds = [[32300, 32150], [32450, 32150], [32450, 32300], [32300, 32300]]
+//! ```
+
+use crate::error::DemonaxError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Fields along each side of a sector
+pub const SECTOR_SIZE: i32 = 32;
+
+/// Lowest floor of the map
+pub const MAX_FLOOR: i32 = 15;
+
+/// Fields from a temple within which positions outside all town bounds belong to its town
+pub const TOWN_RANGE: i32 = 256;
+
+/// A field on the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Whether the position can be on the map at all (non-negative, valid floor)
+    pub fn is_valid(self) -> bool {
+        self.x >= 0 && self.y >= 0 && (0..=MAX_FLOOR).contains(&self.z)
+    }
+
+    /// The sector holding this field
+    pub fn sector(self) -> Sector {
+        Sector { x: self.x.div_euclid(SECTOR_SIZE), y: self.y.div_euclid(SECTOR_SIZE), z: self.z }
+    }
+
+    /// Position moved by (dx, dy) on the same floor
+    pub fn offset(self, dx: i32, dy: i32) -> Self {
+        Self { x: self.x.saturating_add(dx), y: self.y.saturating_add(dy), z: self.z }
+    }
+
+    /// Steps between two positions when diagonal steps count as one, ignoring floors
+    pub fn distance(self, other: Position) -> i32 {
+        (self.x - other.x).abs().max((self.y - other.y).abs())
+    }
+
+    /// Straight-line distance in fields, ignoring floors
+    pub fn euclidean_distance(self, other: Position) -> f64 {
+        ((self.x - other.x) as f64).hypot((self.y - other.y) as f64)
+    }
+}
+
+impl From<(i32, i32, i32)> for Position {
+    fn from((x, y, z): (i32, i32, i32)) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl From<[i32; 3]> for Position {
+    fn from([x, y, z]: [i32; 3]) -> Self {
+        Self { x, y, z }
+    }
+}
+
+/// As written in game files and quest locations: `x,y,z`
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.x, self.y, self.z)
+    }
+}
+
+impl FromStr for Position {
+    type Err = DemonaxError;
+
+    /// Parse `x,y,z`, optionally in brackets as in game files: `[32369,32241,7]`
+    fn from_str(text: &str) -> std::result::Result<Self, DemonaxError> {
+        let inner = text.trim().trim_start_matches('[').trim_end_matches(']');
+        let parts: Vec<i32> = inner
+            .split(',')
+            .map(|part| part.trim().parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| DemonaxError::Parse(format!("Invalid position '{}', expected x,y,z", text)))?;
+        match parts[..] {
+            [x, y, z] => Ok(Self { x, y, z }),
+            _ => Err(DemonaxError::Parse(format!("Invalid position '{}', expected x,y,z", text))),
+        }
+    }
+}
+
+/// A 32x32 field block of one floor, stored as one .sec file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Sector {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Sector {
+    /// Parse a .sec file stem such as `1000-1000-7` or `1018-0997-03`
+    pub fn from_file_stem(stem: &str) -> Option<Self> {
+        let mut parts = stem.split('-').map(|part| part.parse::<i32>().ok());
+        let (Some(Some(x)), Some(Some(y)), Some(Some(z)), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return None;
+        };
+        Some(Self { x, y, z })
+    }
+
+    /// Top-left field of the sector
+    pub fn origin(self) -> Position {
+        Position { x: self.x.saturating_mul(SECTOR_SIZE), y: self.y.saturating_mul(SECTOR_SIZE), z: self.z }
+    }
+}
+
+impl fmt::Display for Sector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.x, self.y, self.z)
+    }
+}
+
+/// A named area of the map, on every floor
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Region {
+    pub name: String,
+    /// Corners in order; the last corner connects back to the first
+    pub polygon: Vec<[i32; 2]>,
+}
+
+impl Region {
+    /// Whether (x, y) is inside the polygon or on its edge
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        polygon_contains(&self.polygon, x, y)
+    }
+}
+
+/// Whether (x, y) is inside a polygon or on its edge. An empty polygon contains nothing.
+pub fn polygon_contains(corners: &[[i32; 2]], x: i32, y: i32) -> bool {
+    let mut inside = false;
+    for (i, &[x1, y1]) in corners.iter().enumerate() {
+        let [x2, y2] = corners[(i + 1) % corners.len()];
+        let on_edge = (x - x1) as i64 * (y2 - y1) as i64 == (y - y1) as i64 * (x2 - x1) as i64
+            && x >= x1.min(x2)
+            && x <= x1.max(x2)
+            && y >= y1.min(y2)
+            && y <= y1.max(y2);
+        if on_edge {
+            return true;
+        }
+        if (y1 > y) != (y2 > y) {
+            let crossing = x1 as f64 + (y - y1) as f64 * (x2 - x1) as f64 / (y2 - y1) as f64;
+            if (x as f64) < crossing {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Rookgaard, then the rest of the map as the mainland
+pub fn default_regions() -> Vec<Region> {
+    vec![
+        Region {
+            name: "rookgaard".to_string(),
+            polygon: vec![[31872, 32128], [32160, 32128], [32160, 32320], [31872, 32320]],
+        },
+        Region {
+            name: "mainland".to_string(),
+            polygon: vec![[31744, 30976], [34048, 30976], [34048, 33280], [31744, 33280]],
+        },
+    ]
+}
+
+/// Name of the first region containing the position
+pub fn region_of(regions: &[Region], position: Position) -> Option<&str> {
+    regions
+        .iter()
+        .find(|region| region.contains(position.x, position.y))
+        .map(|region| region.name.as_str())
+}
+
+/// A town with its temple and, optionally, the area it covers
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Town {
+    pub name: String,
+    /// Temple position as [x, y, z]
+    pub temple: [i32; 3],
+    /// Polygon of (x, y) corners; without bounds the town covers what is nearest its temple
+    #[serde(default)]
+    pub bounds: Vec<[i32; 2]>,
+}
+
+impl Town {
+    pub fn temple(&self) -> Position {
+        Position::from(self.temple)
+    }
+}
+
+/// The towns of the 7.7 map, by temple
+pub fn default_towns() -> Vec<Town> {
+    [
+        ("Rookgaard", [32097, 32219, 7]),
+        ("Thais", [32369, 32241, 7]),
+        ("Carlin", [32360, 31782, 7]),
+        ("Ab'Dendriel", [32732, 31634, 7]),
+        ("Kazordoon", [32649, 31925, 11]),
+        ("Venore", [32957, 32076, 7]),
+        ("Edron", [33217, 31814, 8]),
+        ("Darashia", [33213, 32454, 1]),
+        ("Ankrahmun", [33194, 32853, 8]),
+        ("Port Hope", [32594, 32745, 7]),
+    ]
+    .into_iter()
+    .map(|(name, temple)| Town { name: name.to_string(), temple, bounds: Vec::new() })
+    .collect()
+}
+
+/// Name of the town a position belongs to: the first whose bounds contain it,
+/// or else the one with the nearest temple within [`TOWN_RANGE`] fields
+pub fn town_of(towns: &[Town], position: Position) -> Option<&str> {
+    if let Some(town) = towns.iter().find(|town| polygon_contains(&town.bounds, position.x, position.y)) {
+        return Some(&town.name);
+    }
+    towns
+        .iter()
+        .map(|town| (position.distance(town.temple()), town))
+        .filter(|(distance, _)| *distance <= TOWN_RANGE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, town)| town.name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sector_math() {
+        let position = Position::new(32097, 32219, 7);
+        assert_eq!(position.sector(), Sector { x: 1003, y: 1006, z: 7 });
+        assert_eq!(position.sector().origin(), Position::new(32096, 32192, 7));
+        assert_eq!(Sector::from_file_stem("1018-0997-03"), Some(Sector { x: 1018, y: 997, z: 3 }));
+        assert_eq!(Sector::from_file_stem("1018-0997"), None);
+        assert_eq!(Sector::from_file_stem("1-2-3-4"), None);
+        assert_eq!(Position::new(-1, 5, 7).sector().x, -1);
+
+        assert_eq!(position.distance(position.offset(3, -5)), 5);
+        assert_eq!(position.euclidean_distance(position.offset(3, -4)), 5.0);
+        assert!(!Position::new(100, 100, 16).is_valid());
+        assert_eq!("[32369, 32241, 7]".parse::<Position>().unwrap(), Position::new(32369, 32241, 7));
+        assert!("32369,32241".parse::<Position>().is_err());
+    }
+
+    #[test]
+    fn test_first_containing_region_wins() {
+        let regions = default_regions();
+        assert_eq!(region_of(&regions, Position::new(32097, 32219, 7)), Some("rookgaard"));
+        assert_eq!(region_of(&regions, Position::new(32369, 32241, 7)), Some("mainland"));
+        assert_eq!(region_of(&regions, Position::new(31872, 32200, 8)), Some("rookgaard"));
+        assert_eq!(region_of(&regions, Position::new(20000, 20000, 7)), None);
+    }
+
+    #[test]
+    fn test_towns_by_bounds_then_nearest_temple() {
+        let mut towns = default_towns();
+        assert_eq!(town_of(&towns, Position::new(32380, 32220, 7)), Some("Thais"));
+        assert_eq!(town_of(&towns, Position::new(32120, 32230, 7)), Some("Rookgaard"));
+        assert_eq!(town_of(&towns, Position::new(31000, 31000, 7)), None);
+
+        // Bounds win over a nearer temple
+        towns[1].bounds = vec![[32100, 32200], [32130, 32200], [32130, 32240], [32100, 32240]];
+        assert_eq!(town_of(&towns, Position::new(32120, 32230, 7)), Some("Thais"));
+    }
+
+    #[test]
+    fn test_concave_polygons() {
+        // An L shape: the notch at the bottom right is outside
+        let region = Region { name: "l".to_string(), polygon: vec![[0, 0], [10, 0], [10, 4], [4, 4], [4, 10], [0, 10]] };
+        assert!(region.contains(2, 8));
+        assert!(region.contains(8, 2));
+        assert!(region.contains(4, 7));
+        assert!(!region.contains(8, 8));
+    }
+}
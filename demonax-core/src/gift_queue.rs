@@ -0,0 +1,126 @@
+//! Presents queued with `demonax queue-present` and given at the next server save.
+//!
+//! Queued gifts wait in the `pending_gifts` table until [`flush_gift_queue`]
+//! applies all of them in one pass over the .usr files, usually from the
+//! pre-save hook. Every file is copied to a backup directory before its first
+//! change, and each gift's row keeps who queued it, when it was applied and what
+//! happened per player, so the table doubles as the gift ledger.
+
+use crate::catalog::ItemCatalog;
+use crate::database::Database;
+use crate::error::Result;
+use crate::present::{
+    apply_present_to_file, find_usr_files_by_player, GiftErrorKind, GiftResult, GiftSummary, PresentConfig,
+};
+use crate::processors::Reporter;
+use crate::sandbox::PlayerFilter;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A queued present, from [`Database::get_pending_gifts`]
+///
+/// [`Database::get_pending_gifts`]: crate::database::Database::get_pending_gifts
+#[derive(Debug, Clone)]
+pub struct PendingGift {
+    pub id: i64,
+    /// Recipient; `None` gives the present to every player
+    pub player_name: Option<String>,
+    pub present: PresentConfig,
+    pub target_slot: i32,
+    pub skip_over_capacity: bool,
+    pub queued_by: Option<String>,
+    pub queued_at: String,
+}
+
+/// What a gift did for one player, stored in `pending_gifts.results_json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GiftOutcome {
+    pub player_name: String,
+    /// e.g. "gifted" or "skipped: slot occupied"
+    pub outcome: String,
+}
+
+/// Apply every pending gift to the .usr files below `usr_dir` and record the
+/// results. Files are copied below `backup_dir/<timestamp>/` before they are
+/// changed. Returns the summary over all gifts.
+///
+/// Only the players of `only_players` get presents. Gifts for other players
+/// stay queued untouched, and gifts for every player are given to the allowed
+/// ones but stay queued, so the real flush still reaches everyone.
+pub fn flush_gift_queue(
+    db: &Database,
+    usr_dir: &Path,
+    catalog: &ItemCatalog,
+    backup_dir: &Path,
+    only_players: &PlayerFilter,
+    reporter: &Reporter,
+) -> Result<GiftSummary> {
+    let gifts = db.get_pending_gifts()?;
+    let mut summary = GiftSummary::new();
+    if gifts.is_empty() {
+        return Ok(summary);
+    }
+
+    let files = only_players.filter_files(find_usr_files_by_player(usr_dir)?);
+
+    let backup_root = backup_dir.join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+    let mut backed_up: HashSet<PathBuf> = HashSet::new();
+    let mut backup = |path: &Path| -> Result<()> {
+        if backed_up.insert(path.to_path_buf()) {
+            let target = backup_root.join(path.strip_prefix(usr_dir).unwrap_or(path));
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(path, &target)?;
+        }
+        Ok(())
+    };
+
+    for gift in &gifts {
+        if gift.player_name.as_deref().is_some_and(|name| !only_players.allows(name)) {
+            continue;
+        }
+        let targets: Vec<&PathBuf> = match &gift.player_name {
+            Some(name) => files.iter().filter(|(player, _)| *player == name.to_lowercase()).map(|(_, path)| path).collect(),
+            None => files.iter().map(|(_, path)| path).collect(),
+        };
+
+        let mut gift_summary = GiftSummary::new();
+        let mut outcomes = Vec::new();
+        if targets.is_empty()
+            && let Some(name) = &gift.player_name
+        {
+            let result = GiftResult::Error {
+                player_name: name.clone(),
+                path: None,
+                kind: GiftErrorKind::NotFound,
+                error: "no .usr file with that name".to_string(),
+            };
+            gift_summary.add_result(&result);
+            outcomes.push(GiftOutcome { player_name: name.clone(), outcome: result.outcome() });
+        }
+        for path in targets {
+            backup(path)?;
+            let result = apply_present_to_file(path, &gift.present, gift.target_slot, catalog, gift.skip_over_capacity, false);
+            gift_summary.add_result(&result);
+            outcomes.push(GiftOutcome { player_name: result.player_name().to_string(), outcome: result.outcome() });
+        }
+
+        reporter.info(format_args!(
+            "Gift {} queued by {}: {} gifted, {} skipped, {} errors, {} verification failures",
+            gift.id,
+            gift.queued_by.as_deref().unwrap_or("unknown"),
+            gift_summary.gifted,
+            gift_summary.skipped,
+            gift_summary.errors,
+            gift_summary.verification_failures
+        ));
+        if gift.player_name.is_some() || only_players.is_all() {
+            db.record_gift_results(gift.id, &gift_summary, &outcomes)?;
+        }
+        summary.add_summary(&gift_summary);
+    }
+
+    Ok(summary)
+}
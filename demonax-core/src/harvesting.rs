@@ -1,8 +1,26 @@
-//! Harvesting rule generation for moveuse.dat files.
+//! Harvesting rule generation for moveuse.dat files, and descriptions of the
+//! stored rules for the website.
 
-use crate::models::HarvestingData;
+use crate::models::{HarvestingData, HarvestingRule};
 use crate::error::DemonaxError;
 
+/// Verb for using a tool on a corpse, by a word of the tool's name
+pub const HARVESTING_VERBS: &[(&str, &str)] = &[("knife", "skinning"), ("stake", "dusting")];
+
+/// Describe a rule in words, e.g. "skinning a dead dragon yields green dragon leather".
+/// Unknown tools, creatures and rewards are named by their ID.
+pub fn describe_harvesting(rule: &HarvestingRule) -> String {
+    let tool = rule.tool_name.as_deref().map(str::to_lowercase);
+    let action = tool
+        .as_deref()
+        .and_then(|tool| HARVESTING_VERBS.iter().find(|(word, _)| tool.contains(word)))
+        .map(|(_, verb)| verb.to_string())
+        .unwrap_or_else(|| format!("using {} on", tool.unwrap_or_else(|| format!("item {}", rule.tool_id))));
+    let creature = rule.creature_name.as_deref().map_or_else(|| format!("race {}", rule.race_id), str::to_lowercase);
+    let reward = rule.reward_name.as_deref().map_or_else(|| format!("item {}", rule.reward_id), str::to_lowercase);
+    format!("{} a dead {} yields {}", action, creature, reward)
+}
+
 /// Generate a pair of MultiUse rules (success + failure) for one harvesting entry.
 ///
 /// Success rule: Random passes - create reward, change corpse, green shimmer, increment harvesting
@@ -123,6 +141,31 @@ mod tests {
         assert!(lines[1].contains("Change(Obj2, 5518, 0)"));
     }
 
+    #[test]
+    fn test_describe_harvesting() {
+        let mut rule = HarvestingRule {
+            tool_id: 5908,
+            tool_name: Some("Obsidian Knife".to_string()),
+            corpse_id: 4025,
+            next_corpse_id: 4026,
+            percent_chance: 10,
+            reward_id: 5877,
+            reward_name: Some("Green Dragon Leather".to_string()),
+            race_id: 34,
+            creature_name: Some("Dragon".to_string()),
+            creature_short_name: Some("dragon".to_string()),
+            description: String::new(),
+            creature_note: None,
+            reward_note: None,
+        };
+        assert_eq!(describe_harvesting(&rule), "skinning a dead dragon yields green dragon leather");
+
+        rule.tool_name = Some("Pick".to_string());
+        rule.creature_name = None;
+        rule.reward_name = None;
+        assert_eq!(describe_harvesting(&rule), "using pick on a dead race 34 yields item 5877");
+    }
+
     #[test]
     fn test_insert_harvesting_rules() {
         let moveuse_content = r#"# Header
@@ -0,0 +1,305 @@
+//! Spawn density heatmaps for the website's leaflet map.
+//!
+//! The creatures of every spawn (dat/monster.db) are spread evenly over the
+//! square of fields its radius covers and summed into cells of `cell_size` x
+//! `cell_size` fields, per floor. A floor is written either as an image with
+//! one pixel per cell, transparent where nothing spawns and from yellow to red
+//! towards the densest cell, or as GeoJSON with one square per cell in map
+//! coordinates (`[x, y]`). `heatmap.json` lists the floors with the map bounds
+//! each file covers, for placing the images as overlays.
+
+use crate::combat::CreatureCombat;
+use crate::database::Database;
+use crate::error::Result;
+use crate::hunting::{recommended_level, SpawnCreature};
+use crate::rendering::{save_image, ImageOutput};
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Fields per cell side when none is given
+pub const DEFAULT_CELL_SIZE: i32 = 8;
+
+/// Which spawns go into the heatmap
+#[derive(Debug, Clone, Default)]
+pub struct HeatmapFilter {
+    /// Creature names or short names, case-insensitive; empty means every creature
+    pub creatures: Vec<String>,
+    /// Lowest recommended level of the creatures, see [`recommended_level`]
+    pub min_level: Option<i32>,
+    /// Highest recommended level of the creatures
+    pub max_level: Option<i32>,
+}
+
+impl HeatmapFilter {
+    /// Whether the filter needs the creatures' recommended levels
+    pub fn has_level_band(&self) -> bool {
+        self.min_level.is_some() || self.max_level.is_some()
+    }
+
+    /// Whether `spawn` passes. `level` is the recommended level of its creature;
+    /// creatures without one are left out of a level band.
+    pub fn allows(&self, spawn: &SpawnCreature, level: Option<i32>) -> bool {
+        let creature = self.creatures.is_empty()
+            || self
+                .creatures
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&spawn.name) || name.eq_ignore_ascii_case(&spawn.short_name));
+        let in_band = !self.has_level_band()
+            || level.is_some_and(|level| {
+                self.min_level.is_none_or(|min| level >= min) && self.max_level.is_none_or(|max| level <= max)
+            });
+        creature && in_band
+    }
+}
+
+/// Creatures per cell of one floor
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityGrid {
+    pub floor: i32,
+    pub cell_size: i32,
+    /// Top-left field of the top-left cell
+    pub origin_x: i32,
+    pub origin_y: i32,
+    /// Cells across
+    pub width: u32,
+    /// Cells down
+    pub height: u32,
+    /// Creatures per cell, row by row
+    pub cells: Vec<f64>,
+}
+
+impl DensityGrid {
+    /// Creatures in the cell at `column`, `row`
+    pub fn get(&self, column: u32, row: u32) -> f64 {
+        self.cells[(row * self.width + column) as usize]
+    }
+
+    /// Creatures in the densest cell
+    pub fn max(&self) -> f64 {
+        self.cells.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// Top-left field and the field just past the bottom-right corner
+    pub fn bounds(&self) -> [[i32; 2]; 2] {
+        [
+            [self.origin_x, self.origin_y],
+            [
+                self.origin_x + self.width as i32 * self.cell_size,
+                self.origin_y + self.height as i32 * self.cell_size,
+            ],
+        ]
+    }
+}
+
+/// A written floor, as listed in `heatmap.json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeatmapFloor {
+    pub floor: i32,
+    /// File name in the output directory
+    pub file: String,
+    pub cell_size: i32,
+    pub bounds: [[i32; 2]; 2],
+    pub max_density: f64,
+}
+
+/// How floors are written
+#[derive(Debug, Clone, Copy)]
+pub enum HeatmapFormat {
+    Image(ImageOutput),
+    GeoJson,
+}
+
+/// Sum the creatures of `spawns` into cells, one grid per floor, lowest floor first
+pub fn spawn_density<'a>(spawns: impl IntoIterator<Item = &'a SpawnCreature>, cell_size: i32) -> Vec<DensityGrid> {
+    let cell_size = cell_size.max(1);
+    let mut floors: BTreeMap<i32, HashMap<(i32, i32), f64>> = BTreeMap::new();
+    for spawn in spawns {
+        let radius = spawn.radius.max(0);
+        let per_field = spawn.amount as f64 / ((2 * radius + 1) * (2 * radius + 1)) as f64;
+        let (left, top) = (spawn.position.x - radius, spawn.position.y - radius);
+        let (right, bottom) = (spawn.position.x + radius, spawn.position.y + radius);
+        let cells = floors.entry(spawn.position.z).or_default();
+
+        // Fields of the spawn square inside each cell it touches
+        for row in top.div_euclid(cell_size)..=bottom.div_euclid(cell_size) {
+            let rows = (bottom.min(row * cell_size + cell_size - 1) - top.max(row * cell_size) + 1) as f64;
+            for column in left.div_euclid(cell_size)..=right.div_euclid(cell_size) {
+                let columns = (right.min(column * cell_size + cell_size - 1) - left.max(column * cell_size) + 1) as f64;
+                *cells.entry((column, row)).or_default() += per_field * rows * columns;
+            }
+        }
+    }
+
+    floors
+        .into_iter()
+        .filter(|(_, cells)| !cells.is_empty())
+        .map(|(floor, cells)| {
+            let min_column = cells.keys().map(|(column, _)| *column).min().unwrap_or_default();
+            let max_column = cells.keys().map(|(column, _)| *column).max().unwrap_or_default();
+            let min_row = cells.keys().map(|(_, row)| *row).min().unwrap_or_default();
+            let max_row = cells.keys().map(|(_, row)| *row).max().unwrap_or_default();
+            let (width, height) = ((max_column - min_column + 1) as u32, (max_row - min_row + 1) as u32);
+
+            let mut grid = vec![0.0; (width * height) as usize];
+            for ((column, row), density) in cells {
+                grid[((row - min_row) as u32 * width + (column - min_column) as u32) as usize] = density;
+            }
+            DensityGrid {
+                floor,
+                cell_size,
+                origin_x: min_column * cell_size,
+                origin_y: min_row * cell_size,
+                width,
+                height,
+                cells: grid,
+            }
+        })
+        .collect()
+}
+
+/// One pixel per cell: transparent where nothing spawns, yellow to red and
+/// more opaque towards the densest cell
+pub fn render_density(grid: &DensityGrid) -> RgbaImage {
+    let max = grid.max();
+    RgbaImage::from_fn(grid.width, grid.height, |column, row| {
+        let density = grid.get(column, row);
+        if density <= 0.0 || max <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let t = density / max;
+        Rgba([255, (255.0 * (1.0 - t)).round() as u8, 0, (64.0 + 191.0 * t).round() as u8])
+    })
+}
+
+/// GeoJSON FeatureCollection with a square per non-empty cell, in map coordinates
+pub fn density_geojson(grid: &DensityGrid) -> serde_json::Value {
+    let mut features = Vec::new();
+    for row in 0..grid.height {
+        for column in 0..grid.width {
+            let density = grid.get(column, row);
+            if density <= 0.0 {
+                continue;
+            }
+            let x = grid.origin_x + column as i32 * grid.cell_size;
+            let y = grid.origin_y + row as i32 * grid.cell_size;
+            let (x2, y2) = (x + grid.cell_size, y + grid.cell_size);
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "Polygon", "coordinates": [[[x, y], [x2, y], [x2, y2], [x, y2], [x, y]]] },
+                "properties": { "floor": grid.floor, "density": (density * 100.0).round() / 100.0 },
+            }));
+        }
+    }
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+/// Write a file per floor and `heatmap.json` to `output_dir`
+pub fn write_heatmaps(grids: &[DensityGrid], output_dir: &Path, format: HeatmapFormat) -> Result<Vec<HeatmapFloor>> {
+    std::fs::create_dir_all(output_dir)?;
+    let mut floors = Vec::new();
+    for grid in grids {
+        let stem = format!("spawns-floor-{:02}", grid.floor);
+        let path = match format {
+            HeatmapFormat::Image(output) => save_image(&render_density(grid), output_dir, &stem, &output)?,
+            HeatmapFormat::GeoJson => {
+                let path = output_dir.join(format!("{}.geojson", stem));
+                std::fs::write(&path, serde_json::to_string(&density_geojson(grid))?)?;
+                path
+            }
+        };
+        floors.push(HeatmapFloor {
+            floor: grid.floor,
+            file: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            cell_size: grid.cell_size,
+            bounds: grid.bounds(),
+            max_density: (grid.max() * 100.0).round() / 100.0,
+        });
+    }
+    std::fs::write(output_dir.join("heatmap.json"), serde_json::to_string_pretty(&floors)?)?;
+    Ok(floors)
+}
+
+/// Build and write the heatmaps of the stored spawns that pass `filter`
+pub fn render_spawn_heatmap(
+    db: &Database,
+    filter: &HeatmapFilter,
+    cell_size: i32,
+    output_dir: &Path,
+    format: HeatmapFormat,
+) -> Result<Vec<HeatmapFloor>> {
+    let spawns = db.get_spawn_creatures()?;
+
+    let mut levels: HashMap<String, Option<i32>> = HashMap::new();
+    if filter.has_level_band() {
+        for spawn in &spawns {
+            if !levels.contains_key(&spawn.short_name) {
+                let combat: Option<CreatureCombat> = db.get_creature_combat(&spawn.short_name)?;
+                levels.insert(spawn.short_name.clone(), combat.and_then(|combat| recommended_level(&[&combat])));
+            }
+        }
+    }
+
+    let selected = spawns
+        .iter()
+        .filter(|spawn| filter.allows(spawn, levels.get(&spawn.short_name).copied().flatten()));
+    write_heatmaps(&spawn_density(selected, cell_size), output_dir, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Position;
+
+    fn spawn(name: &str, position: Position, radius: i32, amount: i32) -> SpawnCreature {
+        SpawnCreature {
+            position,
+            radius,
+            amount,
+            regen_seconds: 60,
+            town: None,
+            race: 1,
+            name: name.to_string(),
+            short_name: name.to_lowercase(),
+            hp: 10,
+            experience: 5,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_spawns_are_spread_over_cells() {
+        // A radius-1 square of 9 fields: 3 in the left cell column, 6 in the right
+        let spawns = [
+            spawn("Rat", Position::new(32008, 32004, 7), 1, 9),
+            spawn("Rat", Position::new(32000, 32000, 8), 0, 2),
+        ];
+        let grids = spawn_density(&spawns, 8);
+        assert_eq!(grids.len(), 2);
+
+        let ground = &grids[0];
+        assert_eq!((ground.floor, ground.width, ground.height), (7, 2, 1));
+        assert_eq!(ground.bounds(), [[32000, 32000], [32016, 32008]]);
+        assert_eq!(ground.cells, vec![3.0, 6.0]);
+        assert_eq!(grids[1].cells, vec![2.0]);
+
+        let image = render_density(ground);
+        assert_eq!(*image.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(density_geojson(ground)["features"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_filter_by_creature_and_level_band() {
+        let rat = spawn("Rat", Position::new(32000, 32000, 7), 0, 1);
+        let by_name = HeatmapFilter { creatures: vec!["RAT".to_string()], ..HeatmapFilter::default() };
+        assert!(by_name.allows(&rat, None));
+        assert!(!by_name.allows(&spawn("Dragon", rat.position, 0, 1), None));
+
+        let band = HeatmapFilter { min_level: Some(10), max_level: Some(30), ..HeatmapFilter::default() };
+        assert!(band.allows(&rat, Some(20)));
+        assert!(!band.allows(&rat, Some(5)));
+        assert!(!band.allows(&rat, None));
+    }
+}
@@ -0,0 +1,178 @@
+//! User scripts run after commands, for derived tables and one-off exports
+//! without forking the crate.
+//!
+//! Scripts are [Rhai](https://rhai.rs) files in a subdirectory of the hooks
+//! directory named after the command, e.g. `hooks/process-usr/top_levels.rhai`,
+//! and run in file name order after the command succeeds. They see a small API
+//! instead of the crate:
+//!
+//! - `query(sql)` and `query(sql, [params])`: rows of a read-only statement as maps
+//! - `write_table(name, rows)`: replace the table `hook_<name>` with an array of maps
+//! - `export_json(file, value)` and `export_csv(file, rows)`: write a file below
+//!   the output directory
+//! - `log(message)`
+//!
+//! ```rhai
+//! let rows = query("SELECT p.name, MAX(s.level) AS level FROM daily_snapshots s
+//!                   JOIN players p ON p.id = s.player_id GROUP BY p.id ORDER BY level DESC LIMIT 10");
+//! write_table("top_levels", rows);
+//! export_csv("top_levels.csv", rows);
+//! ```
+
+use crate::database::Database;
+use crate::error::{DemonaxError, Result};
+use crate::processors::Reporter;
+use rhai::{Array, Dynamic, Engine, EvalAltResult};
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// Operations a script may run before it is stopped, so a runaway loop can't
+/// hang the pipeline
+pub const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// The `[hooks]` section of `demonax.toml`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Directory with a subdirectory of scripts per command
+    pub dir: PathBuf,
+    /// Directory that `export_json` and `export_csv` write into
+    pub output_dir: PathBuf,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self { dir: PathBuf::from("./hooks"), output_dir: PathBuf::from("./hooks/output") }
+    }
+}
+
+impl HooksConfig {
+    /// Scripts to run after `command`, in file name order
+    pub fn scripts_for(&self, command: &str) -> Result<Vec<PathBuf>> {
+        let dir = self.dir.join(command);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut scripts: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        scripts.sort();
+        Ok(scripts)
+    }
+}
+
+/// Run the scripts for `command` against the database at `db_path`. Stops at
+/// the first failing script. Returns the number of scripts run.
+pub fn run_hooks(db_path: &Path, config: &HooksConfig, command: &str, reporter: &Reporter) -> Result<usize> {
+    let scripts = config.scripts_for(command)?;
+    if scripts.is_empty() {
+        return Ok(0);
+    }
+
+    let engine = hook_engine(Rc::new(Database::new(db_path)?), config.output_dir.clone(), *reporter);
+    for script in &scripts {
+        reporter.info(format_args!("Running hook {:?}", script));
+        engine
+            .run_file(script.clone())
+            .map_err(|e| DemonaxError::Validation(format!("Hook {:?} failed: {}", script, e)))?;
+    }
+    Ok(scripts.len())
+}
+
+/// A Rhai engine with the hook API on `db`
+fn hook_engine(db: Rc<Database>, output_dir: PathBuf, reporter: Reporter) -> Engine {
+    fn script_error(e: impl std::fmt::Display) -> Box<EvalAltResult> {
+        e.to_string().into()
+    }
+    let rows_of = |rows: Array| -> std::result::Result<Vec<serde_json::Map<String, serde_json::Value>>, Box<EvalAltResult>> {
+        rhai::serde::from_dynamic(&Dynamic::from_array(rows))
+    };
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let query_db = db.clone();
+    let query = move |sql: &str, params: Array| -> std::result::Result<Dynamic, Box<EvalAltResult>> {
+        let params: Vec<serde_json::Value> = rhai::serde::from_dynamic(&Dynamic::from_array(params))?;
+        rhai::serde::to_dynamic(query_db.query_json_rows(sql, &params).map_err(script_error)?)
+    };
+    let query_without_params = query.clone();
+    engine.register_fn("query", query);
+    engine.register_fn("query", move |sql: &str| query_without_params(sql, Array::new()));
+
+    engine.register_fn("write_table", move |name: &str, rows: Array| -> std::result::Result<i64, Box<EvalAltResult>> {
+        let written = db.replace_hook_table(name, &rows_of(rows)?).map_err(script_error)?;
+        Ok(written as i64)
+    });
+
+    let json_dir = output_dir.clone();
+    engine.register_fn("export_json", move |file: &str, value: Dynamic| -> std::result::Result<(), Box<EvalAltResult>> {
+        let value: serde_json::Value = rhai::serde::from_dynamic(&value)?;
+        let path = output_path(&json_dir, file).map_err(script_error)?;
+        let json = serde_json::to_string_pretty(&value).map_err(script_error)?;
+        std::fs::write(path, json).map_err(script_error)
+    });
+
+    engine.register_fn("export_csv", move |file: &str, rows: Array| -> std::result::Result<(), Box<EvalAltResult>> {
+        let path = output_path(&output_dir, file).map_err(script_error)?;
+        write_csv(&path, &rows_of(rows)?).map_err(script_error)
+    });
+
+    engine.register_fn("log", move |message: &str| reporter.info(message));
+    engine
+}
+
+/// `file` below `output_dir`, with its parent directories created. Absolute
+/// paths and `..` are rejected so scripts can't write elsewhere.
+fn output_path(output_dir: &Path, file: &str) -> Result<PathBuf> {
+    let relative = Path::new(file);
+    if file.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(DemonaxError::Validation(format!("Hook output {:?} must be a relative path without ..", file)));
+    }
+    let path = output_dir.join(relative);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+/// Write rows as CSV with the keys of all rows as header, in the rows' key order
+fn write_csv(path: &Path, rows: &[serde_json::Map<String, serde_json::Value>]) -> Result<()> {
+    let mut columns: Vec<&String> = Vec::new();
+    for key in rows.iter().flat_map(|row| row.keys()) {
+        if !columns.contains(&key) {
+            columns.push(key);
+        }
+    }
+
+    let csv_error = |e: csv::Error| DemonaxError::Io(e.into());
+    let mut writer = csv::Writer::from_path(path).map_err(csv_error)?;
+    writer.write_record(&columns).map_err(csv_error)?;
+    for row in rows {
+        let record = columns.iter().map(|column| match row.get(*column) {
+            None | Some(serde_json::Value::Null) => String::new(),
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        });
+        writer.write_record(record).map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path_stays_in_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(output_path(dir.path(), "reports/top.csv").unwrap(), dir.path().join("reports/top.csv"));
+        assert!(dir.path().join("reports").is_dir());
+        assert!(output_path(dir.path(), "../top.csv").is_err());
+        assert!(output_path(dir.path(), "/tmp/top.csv").is_err());
+        assert!(output_path(dir.path(), "").is_err());
+    }
+}
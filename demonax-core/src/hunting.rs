@@ -0,0 +1,264 @@
+//! Hunting spots for the website: named map areas with the creatures that
+//! spawn there, a recommended level and the experience they give per hour.
+//!
+//! Spots are defined in a TOML overlay:
+//! ```toml
+//! [[spots]]
+//! name = "Thais Rotworm Cave"
+//! bounds = [[32380, 32160], [32440, 32160], [32440, 32210], [32380, 32210]]
+//! floors = [8, 9]          # default: every floor
+//! recommended_level = 15   # default: estimated from the creatures
+//! description = "Two floors of rotworms below the graveyard"
+//! ```
+//!
+//! The creatures come from the spawns (dat/monster.db) inside the bounds and
+//! their stats from the creatures table. Experience per hour assumes every
+//! creature is killed as soon as it respawns. Without a given level, the
+//! recommended level is the lowest at which a knight without armor survives
+//! [`RECOMMENDED_TURNS`] average turns against each creature of the spot.
+
+use crate::combat::{CreatureCombat, DefenderProfile, estimate_combat, player_max_hp};
+use crate::error::{DemonaxError, Result};
+use crate::geo::{Position, polygon_contains};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Average turns a player at the recommended level survives against any creature of the spot
+pub const RECOMMENDED_TURNS: f64 = 20.0;
+
+/// Highest recommended level estimated; spots needing more get none
+pub const MAX_RECOMMENDED_LEVEL: i32 = 300;
+
+/// A hunting spot as written in the overlay
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpotSpec {
+    pub name: String,
+    /// Polygon of (x, y) corners
+    pub bounds: Vec<[i32; 2]>,
+    /// Topmost and lowest floor of the spot; all floors when not set
+    pub floors: Option<[i32; 2]>,
+    /// Overrides the estimated level
+    pub recommended_level: Option<i32>,
+    pub description: Option<String>,
+}
+
+impl SpotSpec {
+    /// Whether a position lies within the bounds and floors of the spot
+    pub fn contains(&self, position: Position) -> bool {
+        polygon_contains(&self.bounds, position.x, position.y)
+            && self.floors.is_none_or(|[top, bottom]| (top..=bottom).contains(&position.z))
+    }
+}
+
+/// The hunting spot overlay file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HuntingOverlay {
+    #[serde(default)]
+    pub spots: Vec<SpotSpec>,
+}
+
+impl HuntingOverlay {
+    /// Load the overlay from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DemonaxError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read hunting spots from {:?}: {}", path, e),
+            ))
+        })?;
+
+        content.parse()
+    }
+}
+
+impl FromStr for HuntingOverlay {
+    type Err = DemonaxError;
+
+    /// Parse the overlay from a TOML string.
+    fn from_str(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| DemonaxError::Parse(format!("Failed to parse hunting spots TOML: {}", e)))
+    }
+}
+
+/// A spawn with the stats of its creature, from [`Database::get_spawn_creatures`]
+///
+/// [`Database::get_spawn_creatures`]: crate::database::Database::get_spawn_creatures
+#[derive(Debug, Clone, Serialize)]
+pub struct SpawnCreature {
+    pub position: Position,
+    /// Fields around the position the creatures appear in
+    pub radius: i32,
+    pub amount: i32,
+    pub regen_seconds: i32,
+    pub town: Option<String>,
+    pub race: i32,
+    pub name: String,
+    pub short_name: String,
+    pub hp: i32,
+    pub experience: i32,
+    /// Hand-written creature note from import-overlay
+    pub note: Option<String>,
+}
+
+/// A creature of a hunting spot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HuntingCreature {
+    pub name: String,
+    pub short_name: String,
+    pub race: i32,
+    /// Creatures alive at once, over all spawns of the spot
+    pub count: i32,
+    pub hp: i32,
+    pub experience: i32,
+    pub experience_per_hour: i64,
+    /// Hand-written creature note from import-overlay
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// A hunting spot page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HuntingSpot {
+    pub name: String,
+    /// File name of the page, from the name
+    pub slug: String,
+    pub description: Option<String>,
+    /// Town most of the spot's creatures spawn in
+    pub town: Option<String>,
+    pub recommended_level: Option<i32>,
+    pub experience_per_hour: i64,
+    /// Most numerous first
+    pub creatures: Vec<HuntingCreature>,
+}
+
+/// Lower-case name with runs of other characters replaced by a dash, e.g. "thais-rotworm-cave"
+pub fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Lowest level at which a knight without armor survives [`RECOMMENDED_TURNS`]
+/// average turns against each creature, or None above [`MAX_RECOMMENDED_LEVEL`]
+pub fn recommended_level(creatures: &[&CreatureCombat]) -> Option<i32> {
+    (1..=MAX_RECOMMENDED_LEVEL).find(|&level| {
+        let defender = DefenderProfile { hp: player_max_hp(level, "knight"), armor: 0, shielding: 10, shield_defense: 0 };
+        creatures.iter().all(|creature| {
+            estimate_combat(creature, &defender).turns_to_kill.is_none_or(|turns| turns >= RECOMMENDED_TURNS)
+        })
+    })
+}
+
+/// Build the hunting spot of `spec` from the spawns inside it. `combat` holds the
+/// combat values of creatures by short name, for estimating the recommended level.
+pub fn build_hunting_spot(spec: &SpotSpec, spawns: &[SpawnCreature], combat: &HashMap<String, CreatureCombat>) -> HuntingSpot {
+    let mut creatures: Vec<HuntingCreature> = Vec::new();
+    let mut towns: BTreeMap<&str, i32> = BTreeMap::new();
+    for spawn in spawns.iter().filter(|spawn| spec.contains(spawn.position)) {
+        if let Some(town) = &spawn.town {
+            *towns.entry(town).or_default() += spawn.amount;
+        }
+        let per_hour = if spawn.regen_seconds > 0 {
+            spawn.amount as i64 * spawn.experience as i64 * 3600 / spawn.regen_seconds as i64
+        } else {
+            0
+        };
+        match creatures.iter_mut().find(|creature| creature.race == spawn.race) {
+            Some(creature) => {
+                creature.count += spawn.amount;
+                creature.experience_per_hour += per_hour;
+            }
+            None => creatures.push(HuntingCreature {
+                name: spawn.name.clone(),
+                short_name: spawn.short_name.clone(),
+                race: spawn.race,
+                count: spawn.amount,
+                hp: spawn.hp,
+                experience: spawn.experience,
+                experience_per_hour: per_hour,
+                note: spawn.note.clone(),
+            }),
+        }
+    }
+    creatures.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+    let recommended = spec.recommended_level.or_else(|| {
+        let combats: Vec<&CreatureCombat> =
+            creatures.iter().filter_map(|creature| combat.get(&creature.short_name)).collect();
+        if combats.is_empty() { None } else { recommended_level(&combats) }
+    });
+
+    HuntingSpot {
+        name: spec.name.clone(),
+        slug: slug(&spec.name),
+        description: spec.description.clone(),
+        town: towns.into_iter().max_by_key(|(_, count)| *count).map(|(town, _)| town.to_string()),
+        recommended_level: recommended,
+        experience_per_hour: creatures.iter().map(|creature| creature.experience_per_hour).sum(),
+        creatures,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERLAY: &str = r#"
+[[spots]]
+name = "Thais Rotworm Cave"
+bounds = [[32380, 32160], [32440, 32160], [32440, 32210], [32380, 32210]]
+floors = [8, 9]
+"#;
+
+    fn spawn(race: i32, name: &str, position: Position, amount: i32, regen_seconds: i32) -> SpawnCreature {
+        SpawnCreature {
+            position,
+            radius: 1,
+            amount,
+            regen_seconds,
+            town: Some("Thais".to_string()),
+            race,
+            name: name.to_string(),
+            short_name: name.to_lowercase(),
+            hp: 65,
+            experience: 40,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_spot_from_spawns_in_bounds_and_floors() {
+        let overlay: HuntingOverlay = OVERLAY.parse().unwrap();
+        let spawns = [
+            spawn(26, "Rotworm", Position::new(32400, 32180, 8), 4, 60),
+            spawn(26, "Rotworm", Position::new(32420, 32190, 9), 2, 120),
+            spawn(21, "Rat", Position::new(32410, 32185, 8), 1, 60),
+            // Same x and y, but on the surface
+            spawn(21, "Rat", Position::new(32400, 32180, 7), 5, 60),
+        ];
+        let rat = CreatureCombat { name: "Rat".to_string(), hp: 20, attack: 10, skill: 20, spells: Vec::new() };
+        let combat: HashMap<String, CreatureCombat> = [("rat".to_string(), rat)].into_iter().collect();
+
+        let spot = build_hunting_spot(&overlay.spots[0], &spawns, &combat);
+        assert_eq!(spot.slug, "thais-rotworm-cave");
+        assert_eq!(spot.town.as_deref(), Some("Thais"));
+        let counts: Vec<(&str, i32)> = spot.creatures.iter().map(|c| (c.name.as_str(), c.count)).collect();
+        assert_eq!(counts, [("Rotworm", 6), ("Rat", 1)]);
+        // 4 * 40 * 60 + 2 * 40 * 30 + 1 * 40 * 60
+        assert_eq!(spot.experience_per_hour, 14_400);
+        // A rat hits 8.5 on average: 170 HP (level 5) last 20 turns
+        assert_eq!(spot.recommended_level, Some(5));
+    }
+
+    #[test]
+    fn test_slug_and_overlay_errors() {
+        assert_eq!(slug("Ab'Dendriel  Orc Fortress (upper)"), "ab-dendriel-orc-fortress-upper");
+        assert!("[[spots]]\nname = \"x\"\nbounds = []\nlevel = 3\n".parse::<HuntingOverlay>().is_err());
+    }
+}
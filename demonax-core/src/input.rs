@@ -0,0 +1,181 @@
+//! Input sources: a directory, a .tar.gz / .zip archive read in place, or a
+//! directory on an SFTP server.
+//!
+//! Nightly usr backups are tarballs; reading their entries directly avoids
+//! unpacking them to disk first. Entries are streamed one at a time, so memory
+//! use doesn't grow with the archive size.
+
+use crate::error::{DemonaxError, Result};
+use crate::file_utils::{find_files_with_extension, throttle_read};
+use crate::remote::{SftpConfig, SftpLocation, SftpSource};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const TAR_GZ_SUFFIXES: &[&str] = &[".tar.gz", ".tgz"];
+const ZIP_SUFFIXES: &[&str] = &[".zip"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    Directory(PathBuf),
+    TarGz(PathBuf),
+    Zip(PathBuf),
+    Sftp(SftpLocation, SftpConfig),
+}
+
+impl InputSource {
+    /// Pick the source type from the file name: `.tar.gz`/`.tgz` and `.zip` files
+    /// are archives, anything else is a directory.
+    pub fn new(path: &Path) -> Self {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if path.is_file() && TAR_GZ_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+            Self::TarGz(path.to_path_buf())
+        } else if path.is_file() && ZIP_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+            Self::Zip(path.to_path_buf())
+        } else {
+            Self::Directory(path.to_path_buf())
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Directory(path) | Self::TarGz(path) | Self::Zip(path) => path,
+            Self::Sftp(location, _) => Path::new(&location.root),
+        }
+    }
+
+    pub fn is_archive(&self) -> bool {
+        matches!(self, Self::TarGz(_) | Self::Zip(_))
+    }
+
+    /// Call `f` with the path (inside the archive, or on disk) and contents of
+    /// every file with the given extension. Directories (local or remote) are read
+    /// in sorted order, archives in the order of their entries. Returns the number
+    /// of files.
+    pub fn for_each_file<F>(&self, extension: &str, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&str, &[u8]) -> Result<()>,
+    {
+        let matches = |name: &str| Path::new(name).extension().is_some_and(|ext| ext == extension);
+        let mut count = 0;
+
+        match self {
+            Self::Directory(dir) => {
+                let mut files = find_files_with_extension(dir, extension)?;
+                files.sort();
+                for path in files {
+                    throttle_read();
+                    f(&path.to_string_lossy(), &std::fs::read(&path)?)?;
+                    count += 1;
+                }
+            }
+            Self::TarGz(path) => {
+                let gz = flate2::read::GzDecoder::new(std::fs::File::open(path)?);
+                let mut archive = tar::Archive::new(gz);
+                let mut contents = Vec::new();
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    let name = entry.path()?.to_string_lossy().into_owned();
+                    if !entry.header().entry_type().is_file() || !matches(&name) {
+                        continue;
+                    }
+                    throttle_read();
+                    contents.clear();
+                    entry.read_to_end(&mut contents)?;
+                    f(&name, &contents)?;
+                    count += 1;
+                }
+            }
+            Self::Zip(path) => {
+                let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?).map_err(zip_error(path))?;
+                let mut contents = Vec::new();
+                for index in 0..archive.len() {
+                    let mut entry = archive.by_index(index).map_err(zip_error(path))?;
+                    let name = entry.name().to_string();
+                    if !entry.is_file() || !matches(&name) {
+                        continue;
+                    }
+                    throttle_read();
+                    contents.clear();
+                    entry.read_to_end(&mut contents)?;
+                    f(&name, &contents)?;
+                    count += 1;
+                }
+            }
+            Self::Sftp(location, config) => {
+                let remote = SftpSource::connect(location, config)?;
+                for file in remote.list_files()? {
+                    let name = file.path.to_string_lossy();
+                    if matches(&name) {
+                        f(&name, &remote.read(&file.path)?)?;
+                        count += 1;
+                    }
+                }
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// The name of an archive without its archive suffix, e.g. `usr-2024-05-01` for
+/// `usr-2024-05-01.tar.gz`; `None` for other names.
+pub fn archive_stem(name: &str) -> Option<&str> {
+    let lower = name.to_lowercase();
+    TAR_GZ_SUFFIXES
+        .iter()
+        .chain(ZIP_SUFFIXES)
+        .find(|suffix| lower.ends_with(*suffix))
+        .map(|suffix| &name[..name.len() - suffix.len()])
+}
+
+fn zip_error(path: &Path) -> impl Fn(zip::result::ZipError) -> DemonaxError + '_ {
+    move |e| DemonaxError::Parse(format!("Invalid zip archive {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn read_all(source: &InputSource) -> Vec<(String, String)> {
+        let mut files = Vec::new();
+        source
+            .for_each_file("usr", |name, bytes| {
+                files.push((name.to_string(), String::from_utf8_lossy(bytes).into_owned()));
+                Ok(())
+            })
+            .unwrap();
+        files
+    }
+
+    #[test]
+    fn test_archives_are_read_in_place() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let tar_path = temp_dir.path().join("usr-2024-05-01.tar.gz");
+        let gz = flate2::write::GzEncoder::new(std::fs::File::create(&tar_path).unwrap(), flate2::Compression::fast());
+        let mut builder = tar::Builder::new(gz);
+        for (name, text) in [("usr/01/1001.usr", "ID = 1001"), ("usr/readme.txt", "skip me")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(text.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, text.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let zip_path = temp_dir.path().join("usr.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        zip.start_file("02/1002.usr", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"ID = 1002").unwrap();
+        zip.finish().unwrap();
+
+        let source = InputSource::new(&tar_path);
+        assert!(matches!(source, InputSource::TarGz(_)));
+        assert_eq!(read_all(&source), vec![("usr/01/1001.usr".to_string(), "ID = 1001".to_string())]);
+        assert_eq!(read_all(&InputSource::new(&zip_path)), vec![("02/1002.usr".to_string(), "ID = 1002".to_string())]);
+
+        assert!(!InputSource::new(temp_dir.path()).is_archive());
+        assert_eq!(archive_stem("usr-2024-05-01.tar.gz"), Some("usr-2024-05-01"));
+        assert_eq!(archive_stem("usr-2024-05-01"), None);
+    }
+}
@@ -7,9 +7,29 @@
 //!                10 Content={2854 Content={3449 Amount=100, 3155 Charges=35}}}
 //! ```
 
+use crate::catalog::ItemCatalog;
 use crate::error::{DemonaxError, Result};
 use regex::Regex;
 
+/// Carry capacity of a character in hundredths of an ounce, like item weights.
+/// 400 oz at level 1 plus 10 per level, and from level 8 on 25 per level for
+/// knights and 20 for paladins.
+pub fn carry_capacity(level: i32, vocation: &str) -> i64 {
+    let level = level.max(1) as i64;
+    if level < 8 {
+        return 100 * (400 + 10 * (level - 1));
+    }
+    let vocation = vocation.to_ascii_lowercase();
+    let per_level = if vocation.contains("knight") {
+        25
+    } else if vocation.contains("paladin") {
+        20
+    } else {
+        10
+    };
+    100 * (470 + per_level * (level - 8))
+}
+
 /// Represents an item in the inventory, potentially with nested contents (containers).
 #[derive(Debug, Clone, PartialEq)]
 pub struct InventoryItem {
@@ -60,6 +80,39 @@ impl InventoryItem {
         }
     }
 
+    /// Weight of the item, its amount and everything inside it, in hundredths of an ounce
+    pub fn weight(&self, catalog: &ItemCatalog) -> i64 {
+        let own = catalog.weight(self.type_id) as i64 * self.amount.unwrap_or(1).max(1) as i64;
+        own + self.contents.iter().map(|item| item.weight(catalog)).sum::<i64>()
+    }
+
+    /// Check that this item and every nested container hold no more items than they have slots.
+    ///
+    /// An empty catalog checks nothing; otherwise a type without a capacity is
+    /// not a container and cannot hold items.
+    pub fn validate(&self, catalog: &ItemCatalog) -> Result<()> {
+        if !self.contents.is_empty() && !catalog.is_empty() {
+            match catalog.capacity(self.type_id) {
+                Some(capacity) if self.contents.len() > capacity => {
+                    return Err(DemonaxError::ContainerOverflow {
+                        type_id: self.type_id,
+                        capacity,
+                        count: self.contents.len(),
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    return Err(DemonaxError::Validation(format!(
+                        "Item {} is not a container but holds {} items",
+                        self.type_id,
+                        self.contents.len()
+                    )));
+                }
+            }
+        }
+        self.contents.iter().try_for_each(|item| item.validate(catalog))
+    }
+
     /// Serialize item to the inventory format string.
     pub fn serialize(&self) -> String {
         let mut result = self.type_id.to_string();
@@ -208,13 +261,24 @@ impl InventorySection {
     }
 
     /// Set an item in a specific slot (replaces if exists).
-    pub fn set_slot(&mut self, slot: i32, item: InventoryItem) {
+    ///
+    /// Fails without changing the inventory if a container of the item would
+    /// hold more items than its capacity.
+    pub fn set_slot(&mut self, slot: i32, item: InventoryItem, catalog: &ItemCatalog) -> Result<()> {
+        item.validate(catalog)?;
         // Remove existing slot if present
         self.slots.retain(|s| s.slot_number != slot);
         // Add new slot
         self.slots.push(InventorySlot::new(slot, item));
         // Sort by slot number
         self.slots.sort_by_key(|s| s.slot_number);
+        Ok(())
+    }
+
+    /// Weight of everything in the inventory, in hundredths of an ounce.
+    /// Types missing from the catalog weigh nothing.
+    pub fn total_weight(&self, catalog: &ItemCatalog) -> i64 {
+        self.slots.iter().map(|slot| slot.item.weight(catalog)).sum()
     }
 
     /// Serialize the inventory section back to the file format.
@@ -318,8 +382,8 @@ fn parse_item(content: &str) -> Result<InventoryItem> {
     Ok(item)
 }
 
-/// Parse a comma-separated list of items.
-fn parse_item_list(content: &str) -> Result<Vec<InventoryItem>> {
+/// Parse a comma-separated list of items, e.g. `3031 Amount=100, 2853 Content={3585}`.
+pub fn parse_item_list(content: &str) -> Result<Vec<InventoryItem>> {
     let content = content.trim();
     if content.is_empty() {
         return Ok(Vec::new());
@@ -487,11 +551,55 @@ mod tests {
         let mut inv = InventorySection::new();
         assert!(inv.is_slot_empty(10));
 
-        inv.set_slot(10, InventoryItem::new(3354));
+        inv.set_slot(10, InventoryItem::new(3354), &ItemCatalog::new()).unwrap();
         assert!(!inv.is_slot_empty(10));
         assert!(inv.is_slot_empty(5));
     }
 
+    #[test]
+    fn test_set_slot_enforces_container_capacity() {
+        use crate::catalog::CatalogItem;
+
+        // A bag (2853) with 8 slots inside a backpack (2854) with 20
+        let capacities: ItemCatalog = [(2853, Some(8)), (2854, Some(20)), (3031, None)]
+            .into_iter()
+            .map(|(type_id, capacity)| CatalogItem { capacity, ..CatalogItem::new(type_id, "") })
+            .collect();
+        let mut inv = InventorySection::new();
+
+        let full_bag = InventoryItem::container(2853, vec![InventoryItem::new(3031); 8]);
+        inv.set_slot(3, InventoryItem::container(2854, vec![full_bag]), &capacities).unwrap();
+
+        let overfull_bag = InventoryItem::container(2853, vec![InventoryItem::new(3031); 9]);
+        let err = inv.set_slot(10, InventoryItem::container(2854, vec![overfull_bag]), &capacities).unwrap_err();
+        assert!(matches!(err, DemonaxError::ContainerOverflow { type_id: 2853, capacity: 8, count: 9 }));
+        assert!(inv.is_slot_empty(10));
+
+        // A gold coin is no container
+        let coin = InventoryItem::container(3031, vec![InventoryItem::new(3031)]);
+        assert!(matches!(inv.set_slot(10, coin.clone(), &capacities), Err(DemonaxError::Validation(_))));
+        // Without capacities nothing is checked
+        inv.set_slot(10, coin, &ItemCatalog::new()).unwrap();
+    }
+
+    #[test]
+    fn test_total_weight_and_carry_capacity() {
+        use crate::catalog::CatalogItem;
+
+        let catalog: ItemCatalog = [(2854, 1800), (3031, 10), (3354, 2700)]
+            .into_iter()
+            .map(|(type_id, weight)| CatalogItem { weight, ..CatalogItem::new(type_id, "") })
+            .collect();
+        let inv = InventorySection::parse("1 Content={3354},\n               3 Content={2854 Content={3031 Amount=40, 3155 Charges=35}}").unwrap();
+        // Helmet, backpack and 40 coins; the rune is unknown
+        assert_eq!(inv.total_weight(&catalog), 2700 + 1800 + 400);
+
+        assert_eq!(carry_capacity(1, "None"), 40_000);
+        assert_eq!(carry_capacity(8, "Knight"), 47_000);
+        assert_eq!(carry_capacity(20, "Elite Knight"), 77_000);
+        assert_eq!(carry_capacity(20, "Sorcerer"), 59_000);
+    }
+
     #[test]
     fn test_roundtrip_complex_inventory() {
         let original = "1 Content={3354},\n               3 Content={2854 Content={2853, 3031 Amount=40}},\n               10 Content={2854 Content={3449 Amount=100, 3155 Charges=35}}";
@@ -1,15 +1,52 @@
 //! Core library for Demonax game server metadata management.
 
+pub mod atlas;
+pub mod audit;
+pub mod bundle;
+pub mod catalog;
+pub mod combat;
+pub mod config;
+pub mod content;
 pub mod database;
 pub mod error;
+pub mod export;
 pub mod file_utils;
+pub mod game_math;
+pub mod geo;
+pub mod gift_queue;
 pub mod harvesting;
+pub mod heatmap;
+pub mod hooks;
+pub mod hunting;
+pub mod input;
 pub mod inventory;
+pub mod lint;
+pub mod logs;
+pub mod loot;
 pub mod parsers;
+pub mod player_selection;
 pub mod present;
 pub mod processors;
+pub mod quest_route;
+pub mod raid;
+pub mod remote;
 pub mod models;
+pub mod mon;
+pub mod objects;
+pub mod online;
+pub mod overlay;
+pub mod output;
 pub mod rendering;
+pub mod runes;
+pub mod sandbox;
+pub mod save_hook;
+pub mod schema;
+pub mod sector_edit;
+pub mod spell_area;
+pub mod spell_rules;
+#[cfg(any(test, feature = "testdata"))]
+pub mod testdata;
+pub mod timing;
 
 pub use error::{Result, DemonaxError};
 pub use harvesting::{generate_harvesting_rule, generate_all_harvesting_rules, insert_harvesting_rules};
\ No newline at end of file
@@ -0,0 +1,165 @@
+//! Content checks declared as SQL conditions, run by `demonax lint-content`.
+//!
+//! A built-in ruleset (`assets/lint_rules.toml`) ships with the binary. Servers
+//! add their own checks in the `[lint]` section of `demonax.toml` without a
+//! rebuild:
+//! ```toml
+//! [[lint.rules]]
+//! name = "bosses-need-loot"
+//! table = "creatures"
+//! condition = "type = 'Boss' AND NOT has_loot"
+//! severity = "error"
+//! message = "All bosses must have loot"
+//! ```
+
+use crate::database::Database;
+use crate::error::{DemonaxError, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+const BUILT_IN_RULES: &str = include_str!("../assets/lint_rules.toml");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LintSeverity::Info => "info",
+            LintSeverity::Warning => "warning",
+            LintSeverity::Error => "error",
+        })
+    }
+}
+
+fn default_subject() -> String {
+    "name".to_string()
+}
+
+fn default_severity() -> LintSeverity {
+    LintSeverity::Warning
+}
+
+/// One check: the rows of `table` matching `condition` are reported.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintRule {
+    pub name: String,
+    pub table: String,
+    /// SQL condition the offending rows match
+    pub condition: String,
+    /// SQL expression naming an offending row
+    #[serde(default = "default_subject")]
+    pub subject: String,
+    #[serde(default = "default_severity")]
+    pub severity: LintSeverity,
+    pub message: String,
+}
+
+/// Lint rules, run in order
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintRuleset {
+    pub rules: Vec<LintRule>,
+}
+
+impl LintRuleset {
+    /// The ruleset shipped with demonax-tools
+    pub fn built_in() -> &'static LintRuleset {
+        static BUILT_IN: LazyLock<LintRuleset> =
+            LazyLock::new(|| BUILT_IN_RULES.parse().expect("built-in lint rules are valid"));
+        &BUILT_IN
+    }
+}
+
+impl FromStr for LintRuleset {
+    type Err = DemonaxError;
+
+    fn from_str(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| DemonaxError::Config(format!("Failed to parse lint rules TOML: {}", e)))
+    }
+}
+
+/// The `[lint]` section of `demonax.toml`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Whether the built-in rules run before the ones below
+    pub built_in: bool,
+    pub rules: Vec<LintRule>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self { built_in: true, rules: Vec::new() }
+    }
+}
+
+impl LintConfig {
+    /// The rules to run: the built-in ones (unless turned off), then the configured ones
+    pub fn ruleset(&self) -> LintRuleset {
+        let built_in = if self.built_in { LintRuleset::built_in().rules.clone() } else { Vec::new() };
+        LintRuleset { rules: built_in.into_iter().chain(self.rules.iter().cloned()).collect() }
+    }
+}
+
+/// The rows a rule matched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    pub rule: String,
+    pub severity: LintSeverity,
+    pub message: String,
+    pub subjects: Vec<String>,
+}
+
+/// Run every rule of `ruleset` at or above `min_severity`. Rules without
+/// matches are left out. A rule with invalid SQL fails the whole run.
+pub fn lint_content(db: &Database, ruleset: &LintRuleset, min_severity: LintSeverity) -> Result<Vec<LintFinding>> {
+    let mut findings = Vec::new();
+    for rule in ruleset.rules.iter().filter(|rule| rule.severity >= min_severity) {
+        let subjects = db
+            .get_lint_matches(&rule.table, &rule.condition, &rule.subject)
+            .map_err(|e| DemonaxError::Validation(format!("Lint rule {:?} failed: {}", rule.name, e)))?;
+        if !subjects.is_empty() {
+            findings.push(LintFinding {
+                rule: rule.name.clone(),
+                severity: rule.severity,
+                message: rule.message.clone(),
+                subjects,
+            });
+        }
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_rules_parse() {
+        let rules = &LintRuleset::built_in().rules;
+        let boss = rules.iter().find(|rule| rule.name == "boss-without-loot").unwrap();
+        assert_eq!((boss.severity, boss.subject.as_str()), (LintSeverity::Error, "name"));
+        assert!(rules.iter().any(|rule| rule.severity == LintSeverity::Warning));
+    }
+
+    #[test]
+    fn test_configured_rules_follow_built_in_ones() {
+        let config: LintConfig =
+            toml::from_str("built_in = false\n[[rules]]\nname = \"x\"\ntable = \"items\"\ncondition = \"1\"\nmessage = \"m\"\n")
+                .unwrap();
+        assert_eq!(config.ruleset().rules.len(), 1);
+        assert_eq!(config.ruleset().rules[0].severity, LintSeverity::Warning);
+
+        let config = LintConfig { rules: config.rules, ..LintConfig::default() };
+        assert_eq!(config.ruleset().rules.len(), LintRuleset::built_in().rules.len() + 1);
+        assert_eq!(config.ruleset().rules.last().unwrap().name, "x");
+    }
+}
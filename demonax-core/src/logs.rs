@@ -0,0 +1,137 @@
+//! The game server log: deaths, level advances and rare loot, stored in
+//! `log_events` so the website can show them between the daily snapshots.
+//!
+//! Every line starts with a timestamp (`2026-10-14 18:03:12` or
+//! `Tue Oct 14 18:03:12 2026`, optionally in brackets); the lines stored are
+//! ```text
+//! 2026-10-14 18:03:12 Alice died at level 42, killed by a dragon lord.
+//! 2026-10-14 18:05:40 Bob advanced from level 29 to level 30.
+//! 2026-10-14 18:07:02 Carol looted a magic plate armor from a demon.
+//! ```
+//! and every other line is skipped.
+//!
+//! [`tail_log`] continues where its previous run on the same file stopped (kept
+//! in `log_offsets`), so it can run every few minutes or follow the log. A log
+//! shorter than where the previous run stopped was rotated and is read from the
+//! start.
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::file_utils::decode_latin1;
+use crate::models::{LogEvent, LogEventKind};
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// A timestamp at the start of a line, and the rest of the line
+static LINE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\W*(\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}|[a-z]{3} [a-z]{3} +\d{1,2} \d{2}:\d{2}:\d{2} \d{4})\W+(.*)$")
+        .unwrap()
+});
+
+static DEATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+?) died at level (\d+)(?:,? (?:killed )?by (?:an? |the )?(.+?))?\.?$").unwrap()
+});
+
+static LEVEL_UP_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+?) advanced from level \d+ to level (\d+)\.?$").unwrap());
+
+static LOOT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+?) looted (?:an? |\d+ )?(.+?) from (?:an? |the )?(.+?)\.?$").unwrap()
+});
+
+/// Parse a log timestamp, `2026-10-14 18:03:12`, `2026-10-14T18:03:12` or
+/// `Tue Oct 14 18:03:12 2026`
+pub fn parse_log_timestamp(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%a %b %e %H:%M:%S %Y"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+}
+
+/// The event of one log line, if it is a death, level advance or rare drop
+pub fn parse_log_line(line: &str) -> Option<LogEvent> {
+    let caps = LINE_RE.captures(line.trim_end())?;
+    let occurred_at = parse_log_timestamp(&caps[1])?.format("%Y-%m-%d %H:%M:%S").to_string();
+    let message = &caps[2];
+
+    let (kind, player_name, level, creature, item) = if let Some(death) = DEATH_RE.captures(message) {
+        (LogEventKind::Death, &death[1], death[2].parse().ok(), death.get(3).map(|m| m.as_str()), None)
+    } else if let Some(level_up) = LEVEL_UP_RE.captures(message) {
+        (LogEventKind::LevelUp, &level_up[1], level_up[2].parse().ok(), None, None)
+    } else if let Some(loot) = LOOT_RE.captures(message) {
+        (LogEventKind::RareLoot, &loot[1], None, Some(&loot[3]), Some(&loot[2]))
+    } else {
+        return None;
+    };
+    Some(LogEvent {
+        occurred_at,
+        kind,
+        player_name: player_name.trim().to_string(),
+        level,
+        creature: creature.map(str::to_string),
+        item: item.map(str::to_string),
+    })
+}
+
+/// The events of a log, in the order of the log
+pub fn parse_log_str(text: &str) -> Vec<LogEvent> {
+    text.lines().filter_map(parse_log_line).collect()
+}
+
+/// What one [`tail_log`] run read
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TailSummary {
+    /// Bytes of complete lines read
+    pub bytes_read: u64,
+    pub events: usize,
+    /// Whether the log was shorter than before and read from the start
+    pub rotated: bool,
+}
+
+/// Store the events of the lines added to a log since the previous run. A line
+/// still being written (without its newline) is left for the next run.
+pub fn tail_log(db: &Database, path: &Path) -> Result<TailSummary> {
+    let key = std::path::absolute(path)?.to_string_lossy().into_owned();
+    let mut file = std::fs::File::open(path)?;
+    let length = file.metadata()?.len();
+
+    let previous = db.get_log_offset(&key)?;
+    let rotated = previous > length;
+    let start = if rotated { 0 } else { previous };
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let complete = bytes.iter().rposition(|&byte| byte == b'\n').map_or(0, |newline| newline + 1);
+    let events = parse_log_str(&decode_latin1(&bytes[..complete])?);
+    db.store_log_events(&key, &events, start + complete as u64)?;
+    Ok(TailSummary { bytes_read: complete as u64, events: events.len(), rotated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_lines() {
+        let death = parse_log_line("2026-10-14 18:03:12 Alice died at level 42, killed by a dragon lord.").unwrap();
+        assert_eq!((death.kind, death.level, death.creature.as_deref()), (LogEventKind::Death, Some(42), Some("dragon lord")));
+
+        assert!(parse_log_timestamp("Tue Oct 13 18:05:40 2026").is_some());
+        let level_up = parse_log_line("[Wed Oct 14 18:05:40 2026] Bob advanced from level 29 to level 30.").unwrap();
+        assert_eq!((level_up.occurred_at.as_str(), level_up.player_name.as_str()), ("2026-10-14 18:05:40", "Bob"));
+        assert_eq!(level_up.level, Some(30));
+
+        let loot = parse_log_line("2026-10-14 18:07:02 Carol Ann looted a magic plate armor from a demon.").unwrap();
+        assert_eq!(
+            (loot.kind, loot.player_name.as_str(), loot.item.as_deref(), loot.creature.as_deref()),
+            (LogEventKind::RareLoot, "Carol Ann", Some("magic plate armor"), Some("demon"))
+        );
+
+        assert!(parse_log_line("2026-10-14 18:07:03 Player Carol logged out.").is_none());
+        assert!(parse_log_line("Alice died at level 42").is_none());
+    }
+}
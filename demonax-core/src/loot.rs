@@ -0,0 +1,191 @@
+//! Monte Carlo loot simulation for creatures.
+//!
+//! Each loot entry is rolled independently per kill, the way the server does:
+//! a roll in `0..999` drops the item when it is `<= chance_raw` (so the drop
+//! rate is `(chance_raw + 1) / 999`, matching [`get_loot_percent`]), and a
+//! dropped stack has a uniform amount between 1 and the entry's maximum.
+//!
+//! [`get_loot_percent`]: crate::parsers::get_loot_percent
+
+use crate::catalog::ItemCatalog;
+use crate::models::CreatureLoot;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+/// Coins are worth their face value rather than an NPC price
+pub const CURRENCY_VALUES: [(i32, i64); 3] = [(3031, 1), (3035, 100), (3043, 10_000)];
+
+/// z-score for a 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// Simulated drops of one loot entry
+#[derive(Debug, Clone, Serialize)]
+pub struct LootItemStats {
+    pub item_id: i32,
+    pub chance_raw: i32,
+    pub max_amount: i32,
+    /// Number of kills that dropped the item
+    pub drops: u64,
+    /// Average amount per kill
+    pub mean_per_kill: f64,
+    /// 95% confidence interval of the average amount per kill
+    pub ci_low: f64,
+    pub ci_high: f64,
+    /// Gold value of a single item, if known
+    pub unit_value: Option<i64>,
+}
+
+/// Result of a loot simulation
+#[derive(Debug, Clone, Serialize)]
+pub struct LootSimulation {
+    pub kills: u64,
+    pub items: Vec<LootItemStats>,
+    /// Average gold value per kill over items with a known value
+    pub value_per_kill: f64,
+    pub value_ci_low: f64,
+    pub value_ci_high: f64,
+}
+
+/// Running mean and variance (Welford's algorithm)
+#[derive(Default)]
+struct Accumulator {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// 95% confidence interval of the mean (normal approximation)
+    fn interval(&self) -> (f64, f64) {
+        if self.count < 2 {
+            return (self.mean, self.mean);
+        }
+        let variance = self.m2 / (self.count - 1) as f64;
+        let half_width = Z_95 * (variance / self.count as f64).sqrt();
+        (self.mean - half_width, self.mean + half_width)
+    }
+}
+
+/// Roll one loot entry. Returns the dropped amount (0 if nothing dropped).
+pub fn roll_loot_entry<R: Rng>(rng: &mut R, entry: &CreatureLoot) -> i32 {
+    if rng.random_range(0..999) > entry.chance_raw {
+        return 0;
+    }
+    rng.random_range(1..=entry.max_amount.max(1))
+}
+
+/// Simulate `kills` kills of a creature with the given loot table.
+///
+/// Items are valued by the catalog (see [`CURRENCY_VALUES`] for coins); items
+/// without a value count as worthless in the per-kill value. Pass a seed for
+/// reproducible results.
+pub fn simulate_loot(loot: &[CreatureLoot], catalog: &ItemCatalog, kills: u64, seed: Option<u64>) -> LootSimulation {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+
+    let mut per_item: Vec<(Accumulator, u64)> = loot.iter().map(|_| (Accumulator::default(), 0)).collect();
+    let mut value = Accumulator::default();
+
+    for _ in 0..kills {
+        let mut kill_value = 0.0;
+        for (entry, (amounts, drops)) in loot.iter().zip(per_item.iter_mut()) {
+            let amount = roll_loot_entry(&mut rng, entry);
+            if amount > 0 {
+                *drops += 1;
+                kill_value += amount as f64 * catalog.value(entry.item_id).unwrap_or(0) as f64;
+            }
+            amounts.add(amount as f64);
+        }
+        value.add(kill_value);
+    }
+
+    let items = loot
+        .iter()
+        .zip(per_item)
+        .map(|(entry, (amounts, drops))| {
+            let (ci_low, ci_high) = amounts.interval();
+            LootItemStats {
+                item_id: entry.item_id,
+                chance_raw: entry.chance_raw,
+                max_amount: entry.max_amount,
+                drops,
+                mean_per_kill: amounts.mean,
+                ci_low,
+                ci_high,
+                unit_value: catalog.value(entry.item_id),
+            }
+        })
+        .collect();
+
+    let (value_ci_low, value_ci_high) = value.interval();
+    LootSimulation {
+        kills,
+        items,
+        value_per_kill: value.mean,
+        value_ci_low,
+        value_ci_high,
+    }
+}
+
+/// Expected amount per kill of a loot entry, for checking simulations
+pub fn expected_amount_per_kill(entry: &CreatureLoot) -> f64 {
+    let chance = ((entry.chance_raw + 1) as f64 / 999.0).clamp(0.0, 1.0);
+    chance * (1 + entry.max_amount.max(1)) as f64 / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::CatalogItem;
+
+    fn entry(item_id: i32, max_amount: i32, chance_raw: i32) -> CreatureLoot {
+        CreatureLoot {
+            creature_id: 1,
+            item_id,
+            min_amount: 1,
+            max_amount,
+            chance_raw,
+            chance_percent: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_simulation_matches_expected_values() {
+        let loot = vec![entry(3031, 100, 999), entry(3361, 1, 200)];
+        let catalog: ItemCatalog = [(3031, 1), (3361, 25)]
+            .into_iter()
+            .map(|(type_id, value)| CatalogItem { value: Some(value), ..CatalogItem::new(type_id, "") })
+            .collect();
+
+        let sim = simulate_loot(&loot, &catalog, 20_000, Some(7));
+        // chance_raw 999 always drops, so every kill has coins
+        assert_eq!(sim.items[0].drops, 20_000);
+        for (stats, entry) in sim.items.iter().zip(&loot) {
+            let expected = expected_amount_per_kill(entry);
+            assert!(stats.ci_low <= expected && expected <= stats.ci_high, "{:?}", stats);
+        }
+        let expected_value = 50.5 + 201.0 / 999.0 * 25.0;
+        assert!(sim.value_ci_low <= expected_value && expected_value <= sim.value_ci_high);
+    }
+
+    #[test]
+    fn test_simulation_is_reproducible_with_seed() {
+        let loot = vec![entry(3031, 30, 500), entry(3354, 1, 10)];
+        let catalog = ItemCatalog::new();
+        let a = simulate_loot(&loot, &catalog, 500, Some(42));
+        let b = simulate_loot(&loot, &catalog, 500, Some(42));
+        assert_eq!(a.items[0].drops, b.items[0].drops);
+        assert_eq!(a.items[1].mean_per_kill, b.items[1].mean_per_kill);
+        assert_eq!(a.value_per_kill, 0.0);
+    }
+}
@@ -19,6 +19,25 @@ pub struct PlayerSkills {
     pub fishing: i32,
 }
 
+/// One `Skill = (...)` tuple of a .usr file. The tuple has 15 fields:
+/// number, value, max, min, modifier, magic modifier, four cycle counters,
+/// add level, tries, factor percent, tries for the next level and delta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkillEntry {
+    /// Skill number: 0 level, 1 magic level, 6 shielding, 7 distance, 8 sword,
+    /// 9 club, 10 axe, 11 fist, 13 fishing
+    pub skill_id: i32,
+    pub value: i32,
+    pub max: i32,
+    pub min: i32,
+    /// Bonus on top of the value, e.g. from equipment
+    pub modifier: i32,
+    /// Tries (experience for the level) gathered so far
+    pub tries: i64,
+    /// Tries needed to reach the next value
+    pub next_level: i64,
+}
+
 /// Quest completion entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuestCompletion {
@@ -40,15 +59,203 @@ pub struct HarvestingEntry {
     pub harvest_count: i32,
 }
 
+/// Equipment slot, in .usr inventory order (file slot 1 is the helmet)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EquipmentSlot {
+    Helmet,
+    Amulet,
+    Backpack,
+    Armor,
+    RightHand,
+    LeftHand,
+    Legs,
+    Boots,
+    Ring,
+    Ammo,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 10] = [
+        EquipmentSlot::Helmet,
+        EquipmentSlot::Amulet,
+        EquipmentSlot::Backpack,
+        EquipmentSlot::Armor,
+        EquipmentSlot::RightHand,
+        EquipmentSlot::LeftHand,
+        EquipmentSlot::Legs,
+        EquipmentSlot::Boots,
+        EquipmentSlot::Ring,
+        EquipmentSlot::Ammo,
+    ];
+
+    /// Slot for a 1-based inventory slot number from a .usr file
+    pub fn from_usr_slot(slot: usize) -> Option<Self> {
+        slot.checked_sub(1).and_then(|idx| Self::ALL.get(idx).copied())
+    }
+
+    /// 0-based position in the stored equipment array
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EquipmentSlot::Helmet => "helmet",
+            EquipmentSlot::Amulet => "amulet",
+            EquipmentSlot::Backpack => "backpack",
+            EquipmentSlot::Armor => "armor",
+            EquipmentSlot::RightHand => "right_hand",
+            EquipmentSlot::LeftHand => "left_hand",
+            EquipmentSlot::Legs => "legs",
+            EquipmentSlot::Boots => "boots",
+            EquipmentSlot::Ring => "ring",
+            EquipmentSlot::Ammo => "ammo",
+        }
+    }
+}
+
+/// Item in an equipment slot. Stackables (arrows, coins) carry an amount,
+/// charged items (rings, wands, runes) carry charges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EquipmentItem {
+    pub type_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charges: Option<i32>,
+}
+
+impl EquipmentItem {
+    pub fn new(type_id: i32) -> Self {
+        Self { type_id, amount: None, charges: None }
+    }
+
+    /// Stack size or charges, whichever the item has
+    pub fn count(&self) -> Option<i32> {
+        self.amount.or(self.charges)
+    }
+}
+
+/// Items worn by a player, one optional item per slot.
+///
+/// Serialized in the versioned format
+/// `{"version":2,"slots":[{"type_id":3354},null,...,{"type_id":3447,"amount":50}]}`.
+/// The original 10-element array of type IDs with -1 for empty slots
+/// (`[3354,-1,2854,...]`) is still accepted so older snapshots stay readable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "EquipmentJson", into = "EquipmentJson")]
+pub struct Equipment {
+    pub helmet: Option<EquipmentItem>,
+    pub amulet: Option<EquipmentItem>,
+    pub backpack: Option<EquipmentItem>,
+    pub armor: Option<EquipmentItem>,
+    pub right_hand: Option<EquipmentItem>,
+    pub left_hand: Option<EquipmentItem>,
+    pub legs: Option<EquipmentItem>,
+    pub boots: Option<EquipmentItem>,
+    pub ring: Option<EquipmentItem>,
+    pub ammo: Option<EquipmentItem>,
+}
+
+impl Equipment {
+    /// Current `equipment_json` format version
+    pub const FORMAT_VERSION: u32 = 2;
+
+    pub fn get(&self, slot: EquipmentSlot) -> Option<EquipmentItem> {
+        match slot {
+            EquipmentSlot::Helmet => self.helmet,
+            EquipmentSlot::Amulet => self.amulet,
+            EquipmentSlot::Backpack => self.backpack,
+            EquipmentSlot::Armor => self.armor,
+            EquipmentSlot::RightHand => self.right_hand,
+            EquipmentSlot::LeftHand => self.left_hand,
+            EquipmentSlot::Legs => self.legs,
+            EquipmentSlot::Boots => self.boots,
+            EquipmentSlot::Ring => self.ring,
+            EquipmentSlot::Ammo => self.ammo,
+        }
+    }
+
+    pub fn set(&mut self, slot: EquipmentSlot, item: Option<EquipmentItem>) {
+        let field = match slot {
+            EquipmentSlot::Helmet => &mut self.helmet,
+            EquipmentSlot::Amulet => &mut self.amulet,
+            EquipmentSlot::Backpack => &mut self.backpack,
+            EquipmentSlot::Armor => &mut self.armor,
+            EquipmentSlot::RightHand => &mut self.right_hand,
+            EquipmentSlot::LeftHand => &mut self.left_hand,
+            EquipmentSlot::Legs => &mut self.legs,
+            EquipmentSlot::Boots => &mut self.boots,
+            EquipmentSlot::Ring => &mut self.ring,
+            EquipmentSlot::Ammo => &mut self.ammo,
+        };
+        *field = item;
+    }
+
+    /// All slots in inventory order, including empty ones
+    pub fn iter(&self) -> impl Iterator<Item = (EquipmentSlot, Option<EquipmentItem>)> + '_ {
+        EquipmentSlot::ALL.into_iter().map(|slot| (slot, self.get(slot)))
+    }
+}
+
+/// Stored forms of [`Equipment`]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EquipmentJson {
+    Versioned { version: u32, slots: Vec<Option<EquipmentItem>> },
+    Legacy(Vec<i32>),
+}
+
+impl TryFrom<EquipmentJson> for Equipment {
+    type Error = String;
+
+    fn try_from(json: EquipmentJson) -> std::result::Result<Self, Self::Error> {
+        let slots: Vec<Option<EquipmentItem>> = match json {
+            EquipmentJson::Versioned { version, slots } if version == Equipment::FORMAT_VERSION => slots,
+            EquipmentJson::Versioned { version, .. } => {
+                return Err(format!("Unsupported equipment format version {}", version));
+            }
+            EquipmentJson::Legacy(ids) => ids.into_iter().map(|id| (id >= 0).then(|| EquipmentItem::new(id))).collect(),
+        };
+
+        let mut equipment = Equipment::default();
+        for (slot, item) in EquipmentSlot::ALL.into_iter().zip(slots) {
+            equipment.set(slot, item);
+        }
+        Ok(equipment)
+    }
+}
+
+impl From<Equipment> for EquipmentJson {
+    fn from(equipment: Equipment) -> Self {
+        EquipmentJson::Versioned {
+            version: Equipment::FORMAT_VERSION,
+            slots: equipment.iter().map(|(_, item)| item).collect(),
+        }
+    }
+}
+
 /// Parsed data from a .usr file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedUsrFile {
     pub player_id: i32,
     pub skills: PlayerSkills,
+    /// Every Skill tuple of the file, in file order
+    pub skill_entries: Vec<SkillEntry>,
+    /// Numbers of the learned spells, from the SpellList block
+    pub spells: Vec<i32>,
     pub quest_values: Vec<QuestCompletion>,
     pub bestiary: Vec<BestiaryEntry>,
     pub harvesting: Vec<HarvestingEntry>,
-    pub equipment: Vec<i32>, // 10 slots, NA represented as -1?
+    pub equipment: Equipment,
+    /// Profession number (1-4, +10 once promoted); None if the file has no Profession line
+    pub profession: Option<i32>,
+    /// Account number; None if the file has no Account line
+    pub account_id: Option<i32>,
+    /// Premium days left on the account; None if the file has no PremiumDays line
+    pub premium_days: Option<i32>,
+    /// Number of blessings in the Blessings block; None if the file has none
+    pub blessings: Option<i32>,
     pub source_file: String,
 }
 
@@ -123,6 +330,10 @@ pub struct Creature {
     pub has_loot: bool,
     pub article: String,
     pub html_name: String,
+    /// Melee attack value (top-level `Attack =`)
+    pub attack: i32,
+    pub defend: i32,
+    pub armor: i32,
 }
 
 /// Creature loot entry parsed from .mon file
@@ -188,6 +399,20 @@ pub enum SpellShapeType {
     Angle = 4,        // Cone-shaped
 }
 
+impl SpellShapeType {
+    /// The shape stored as `creature_spells.shape_type`
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Actor),
+            1 => Some(Self::Victim),
+            2 => Some(Self::Origin),
+            3 => Some(Self::Destination),
+            4 => Some(Self::Angle),
+            _ => None,
+        }
+    }
+}
+
 /// Spell Impact Types (matches tibia-game SpellImpactType enum)
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum SpellImpactType {
@@ -214,6 +439,22 @@ pub mod damage_types {
     pub const MANA_DRAIN: i32 = 512;
 }
 
+/// Vocation name for a .usr profession number
+pub fn vocation_name(profession: i32) -> &'static str {
+    match profession {
+        0 => "None",
+        1 => "Knight",
+        2 => "Paladin",
+        3 => "Sorcerer",
+        4 => "Druid",
+        11 => "Elite Knight",
+        12 => "Royal Paladin",
+        13 => "Master Sorcerer",
+        14 => "Elder Druid",
+        _ => "Unknown",
+    }
+}
+
 /// Helper function to decode damage type to human-readable name
 pub fn damage_type_name(damage_type: i32) -> String {
     match damage_type {
@@ -246,6 +487,10 @@ pub struct CreatureSpell {
     pub range: Option<i32>,
     pub area_size: Option<String>,
     pub angle: Option<i32>,
+    /// Number of tiles the spell affects
+    pub area_tiles: Option<i32>,
+    /// JSON array of mask rows, see [`crate::spell_area`]
+    pub area_mask: Option<String>,
 
     // Impact details
     pub impact_type: SpellImpactType,
@@ -286,6 +531,185 @@ pub struct Item {
     pub description: Option<String>,
 }
 
+impl Item {
+    /// The known flags of the item
+    pub fn flag_set(&self) -> ItemFlags {
+        ItemFlags::parse(&self.flags)
+    }
+
+    pub fn category(&self) -> ItemCategory {
+        self.flag_set().category()
+    }
+}
+
+/// Known objects.srv item flags as a bitset, stored in `items.flag_bits`.
+/// Other flags are only kept in `items.flags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemFlags(pub u32);
+
+impl ItemFlags {
+    pub const TAKE: Self = Self(1 << 0);
+    pub const CUMULATIVE: Self = Self(1 << 1);
+    pub const CONTAINER: Self = Self(1 << 2);
+    pub const CHEST: Self = Self(1 << 3);
+    pub const LIQUID_CONTAINER: Self = Self(1 << 4);
+    pub const WEAPON: Self = Self(1 << 5);
+    pub const BOW: Self = Self(1 << 6);
+    pub const THROW: Self = Self(1 << 7);
+    pub const WAND: Self = Self(1 << 8);
+    pub const AMMO: Self = Self(1 << 9);
+    pub const SHIELD: Self = Self(1 << 10);
+    pub const ARMOR: Self = Self(1 << 11);
+    pub const CLOTHES: Self = Self(1 << 12);
+    pub const RUNE: Self = Self(1 << 13);
+    pub const FOOD: Self = Self(1 << 14);
+    pub const KEY: Self = Self(1 << 15);
+    pub const LIGHT: Self = Self(1 << 16);
+    pub const WRITE: Self = Self(1 << 17);
+    pub const EXPIRE: Self = Self(1 << 18);
+    pub const WEAR_OUT: Self = Self(1 << 19);
+
+    /// Every known flag with its objects.srv name
+    pub const NAMES: &'static [(ItemFlags, &'static str)] = &[
+        (Self::TAKE, "Take"),
+        (Self::CUMULATIVE, "Cumulative"),
+        (Self::CONTAINER, "Container"),
+        (Self::CHEST, "Chest"),
+        (Self::LIQUID_CONTAINER, "LiquidContainer"),
+        (Self::WEAPON, "Weapon"),
+        (Self::BOW, "Bow"),
+        (Self::THROW, "Throw"),
+        (Self::WAND, "Wand"),
+        (Self::AMMO, "Ammo"),
+        (Self::SHIELD, "Shield"),
+        (Self::ARMOR, "Armor"),
+        (Self::CLOTHES, "Clothes"),
+        (Self::RUNE, "Rune"),
+        (Self::FOOD, "Food"),
+        (Self::KEY, "Key"),
+        (Self::LIGHT, "Light"),
+        (Self::WRITE, "Write"),
+        (Self::EXPIRE, "Expire"),
+        (Self::WEAR_OUT, "WearOut"),
+    ];
+
+    /// Known flags of a comma-separated flag list such as `items.flags` (case-insensitive)
+    pub fn parse(flags: &str) -> Self {
+        flags
+            .split(',')
+            .filter_map(|flag| Self::NAMES.iter().find(|(_, name)| name.eq_ignore_ascii_case(flag.trim())))
+            .fold(Self::default(), |set, (flag, _)| set | *flag)
+    }
+
+    /// Whether all flags of `other` are set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any flag of `other` is set
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// objects.srv names of the set flags
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMES.iter().filter(|(flag, _)| self.contains(*flag)).map(|(_, name)| *name).collect()
+    }
+
+    /// Category of an item with these flags; the first matching category in
+    /// [`ItemCategory::ALL`] order wins
+    pub fn category(self) -> ItemCategory {
+        let category_flags = |category: ItemCategory| match category {
+            ItemCategory::Rune => Self::RUNE,
+            ItemCategory::Container => Self::CONTAINER | Self::CHEST,
+            ItemCategory::LiquidContainer => Self::LIQUID_CONTAINER,
+            ItemCategory::Weapon => Self::WEAPON | Self::BOW | Self::THROW | Self::WAND,
+            ItemCategory::Ammunition => Self::AMMO,
+            ItemCategory::Shield => Self::SHIELD,
+            ItemCategory::Armor => Self::ARMOR,
+            ItemCategory::Clothing => Self::CLOTHES,
+            ItemCategory::Food => Self::FOOD,
+            ItemCategory::Key => Self::KEY,
+            ItemCategory::Other => Self::default(),
+        };
+        ItemCategory::ALL
+            .into_iter()
+            .find(|category| self.intersects(category_flags(*category)))
+            .unwrap_or(ItemCategory::Other)
+    }
+}
+
+impl std::ops::BitOr for ItemFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// What kind of item something is, derived from its flags and stored in `items.category`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemCategory {
+    Rune,
+    Container,
+    LiquidContainer,
+    Weapon,
+    Ammunition,
+    Shield,
+    Armor,
+    /// Worn items without armor value: rings, amulets, ...
+    Clothing,
+    Food,
+    Key,
+    Other,
+}
+
+impl ItemCategory {
+    pub const ALL: [ItemCategory; 11] = [
+        ItemCategory::Rune,
+        ItemCategory::Container,
+        ItemCategory::LiquidContainer,
+        ItemCategory::Weapon,
+        ItemCategory::Ammunition,
+        ItemCategory::Shield,
+        ItemCategory::Armor,
+        ItemCategory::Clothing,
+        ItemCategory::Food,
+        ItemCategory::Key,
+        ItemCategory::Other,
+    ];
+
+    /// Value stored in `items.category`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ItemCategory::Rune => "rune",
+            ItemCategory::Container => "container",
+            ItemCategory::LiquidContainer => "liquid_container",
+            ItemCategory::Weapon => "weapon",
+            ItemCategory::Ammunition => "ammunition",
+            ItemCategory::Shield => "shield",
+            ItemCategory::Armor => "armor",
+            ItemCategory::Clothing => "clothing",
+            ItemCategory::Food => "food",
+            ItemCategory::Key => "key",
+            ItemCategory::Other => "other",
+        }
+    }
+}
+
+impl std::str::FromStr for ItemCategory {
+    type Err = crate::error::DemonaxError;
+
+    fn from_str(text: &str) -> crate::error::Result<Self> {
+        Self::ALL.into_iter().find(|category| category.as_str() == text).ok_or_else(|| {
+            let names: Vec<&str> = Self::ALL.iter().map(|category| category.as_str()).collect();
+            crate::error::DemonaxError::Parse(format!("Unknown item category '{}', expected one of {}", text, names.join(", ")))
+        })
+    }
+}
+
 /// Item loot source (aggregated from creature_loot)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemLootSource {
@@ -301,6 +725,10 @@ pub struct ItemPrice {
     pub npc_name: String,
     pub price: i32,
     pub mode: String,           // "buy" or "sell"
+    pub amount: i32,            // Items traded for the price
+    pub data: Option<i32>,      // Fluid type or charges of the item
+    pub bulk: bool,             // The player can name a count, at `price` per item
+    pub npc_home: Option<(i32, i32, i32)>,
 }
 
 /// Quest chest data parsed from map files
@@ -318,6 +746,42 @@ pub struct QuestChest {
     pub ingame_x: i32,
     pub ingame_y: i32,
     pub ingame_coords: String,  // "X,Y,Z" format
+    /// Keys in the chest with the number of the keyholes they open
+    #[serde(default)]
+    pub keys: Vec<KeyItem>,
+}
+
+/// A key item and the number of the keyholes it opens (`KeyNumber=` in map files)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyItem {
+    pub type_id: i32,
+    pub key_number: i32,
+}
+
+/// A door parsed from map sectors: locked (`KeyholeNumber=`), level (`DoorLevel=`)
+/// or quest (`DoorQuestNumber=`/`DoorQuestValue=`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapDoor {
+    pub position: (i32, i32, i32),
+    pub type_id: i32,
+    /// Key number of the key that opens it
+    pub keyhole_number: Option<i32>,
+    /// Lowest level that may pass
+    pub level: Option<i32>,
+    /// Quest value that must be set to pass
+    pub quest_number: Option<i32>,
+    pub quest_value: Option<i32>,
+}
+
+/// A stored quest with its chests and rewards, for building quest routes
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestRow {
+    pub id: i32,
+    pub name: String,
+    pub region: Option<String>,
+    pub town: Option<String>,
+    pub chests: Vec<crate::geo::Position>,
+    pub reward_item_ids: Vec<i32>,
 }
 
 /// Spell data parsed from magic.cc
@@ -336,6 +800,20 @@ pub struct Spell {
     pub charges: Option<i32>,   // Rune charges
     pub spell_type: String,     // "healing", "attack", "support", etc.
     pub premium: bool,          // Derived from flags
+    pub damage_element: Option<String>, // e.g. "Fire", from the DAMAGE_* constant of the spell's handler
+}
+
+/// Known bits of magic.cc `Spell->Flags` as (bit, column in `spells`, meaning).
+/// The columns are derived from `spells.flags`; other bits are only kept there.
+pub const SPELL_FLAGS: &[(i32, &str, &str)] = &[
+    (0x01, "aggressive", "Aggressive spell"),
+    (0x02, "premium", "Requires a premium account"),
+    (0x08, "full_multiplier", "Damage or healing is never scaled below 100%"),
+];
+
+/// Whether the flag with the given column name is set in a spell's flags
+pub fn spell_flag(flags: i32, name: &str) -> bool {
+    SPELL_FLAGS.iter().any(|(bit, column, _)| *column == name && flags & bit != 0)
 }
 
 /// Spell teaching data from .npc files
@@ -346,6 +824,7 @@ pub struct SpellTeacher {
     pub vocation: String,       // "Knight", "Paladin", "Druid", "Sorcerer"
     pub teaching_price: i32,
     pub level_required: Option<i32>,  // Populated during database insertion from spells table
+    pub unclassified_line: Option<String>,  // Source line if the vocation condition couldn't be parsed
 }
 
 /// Rune/wand/rod seller data from .npc files
@@ -372,6 +851,29 @@ pub struct HarvestingData {
     pub race_id: i32,
 }
 
+/// A harvesting rule with the names of its tool, creature and reward, from the
+/// `harvesting_rules` view; names are `None` when the ID resolves to nothing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarvestingRule {
+    pub tool_id: i32,
+    pub tool_name: Option<String>,
+    pub corpse_id: i32,
+    pub next_corpse_id: i32,
+    pub percent_chance: i32,
+    pub reward_id: i32,
+    pub reward_name: Option<String>,
+    pub race_id: i32,
+    pub creature_name: Option<String>,
+    pub creature_short_name: Option<String>,
+    /// e.g. "skinning a dead dragon yields green dragon leather"
+    pub description: String,
+    /// Hand-written notes on the creature and the reward, from import-overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creature_note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward_note: Option<String>,
+}
+
 /// Raid data parsed from .evt files
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Raid {
@@ -383,6 +885,512 @@ pub struct Raid {
     pub message: String,         // Aggregated messages
     pub creatures: String,       // "5 to 10 Dragon, 2 Demon, ..."
     pub spawn_composition_json: String, // JSON with detailed spawn data
+    pub season: Option<String>,  // e.g. "halloween"; None for year-round raids
+    pub enabled: bool,
+    pub position: Option<(i32, i32, i32)>, // Center of the first spawn
+}
+
+/// One step of a raid script in a .evt file: a message or a creature spawn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaidStep {
+    /// Seconds after the raid starts
+    pub delay_seconds: i64,
+    pub message: Option<String>,
+    /// Spawn center (x, y, z)
+    pub position: Option<(i32, i32, i32)>,
+    /// Spawn radius around the position
+    pub spread: Option<i32>,
+    pub race: Option<i32>,
+    /// (min, max) creatures spawned
+    pub count: Option<(i32, i32)>,
+}
+
+/// A raid start from the game server's raid log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaidExecution {
+    /// Raid name (the .evt file stem)
+    pub raid_name: String,
+    /// YYYY-MM-DD HH:MM:SS, server time
+    pub started_at: String,
+}
+
+/// How often a raid was seen in the raid log, next to its .evt interval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidActivity {
+    pub name: String,
+    /// Interval of the .evt file
+    pub interval_days: Option<f64>,
+    pub times_seen: i64,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    /// Mean days between the starts in the log
+    pub observed_interval_days: Option<f64>,
+    /// `last_seen` plus the .evt interval
+    pub expected_next: Option<String>,
+}
+
+/// A creature spawn from dat/monster.db
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Spawn {
+    pub race: i32,
+    /// Spawn center (x, y, z)
+    pub position: (i32, i32, i32),
+    /// Fields around the center the creatures appear in
+    pub radius: i32,
+    /// Creatures alive at once
+    pub amount: i32,
+    /// Seconds until a killed creature respawns
+    pub regen_seconds: i32,
+}
+
+/// House data parsed from dat/houses.dat, merged with owners.dat and houseareas.dat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct House {
+    pub house_id: i32,
+    pub name: String,
+    pub town: String,               // Area name, or "Area <n>" if unknown
+    pub rent: i32,                  // RentOffset + size * area price per field
+    pub size: i32,                  // Number of fields
+    pub guild_house: bool,
+    pub owner_id: Option<i32>,      // None if unowned
+    pub last_payment: Option<i64>,  // Unix timestamp
+    pub position: Option<(i32, i32, i32)>, // First field of the house
+}
+
+/// House list entry as shown on the website
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HouseListing {
+    pub house_id: i32,
+    pub name: String,
+    pub town: String,
+    pub rent: i32,
+    pub size: i32,
+    pub guild_house: bool,
+    pub owner_name: Option<String>,
+    pub last_payment: Option<String>, // YYYY-MM-DD
+}
+
+/// Raid list entry as shown on the website
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaidListing {
+    pub name: String,
+    pub raid_type: String,
+    pub waves: String,
+    pub interval_days: Option<f64>,
+    pub creatures: String,
+    pub season: Option<String>,
+    pub enabled: bool,
+    pub town: Option<String>,
+    /// Latest start in the raid log, YYYY-MM-DD HH:MM:SS
+    #[serde(default)]
+    pub last_seen: Option<String>,
+}
+
+/// A player's latest snapshot as exported for the website
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotListing {
+    pub player_id: i32,
+    pub player_name: String,
+    pub snapshot_date: String,
+    pub level: i32,
+    pub experience: i64,
+    pub profession: Option<i32>,
+    pub magic_level: i32,
+    pub fist_fighting: i32,
+    pub club_fighting: i32,
+    pub sword_fighting: i32,
+    pub axe_fighting: i32,
+    pub distance_fighting: i32,
+    pub shielding: i32,
+    pub fishing: i32,
+    /// Number of blessings; None when the snapshot doesn't say
+    pub blessings: Option<i32>,
+    pub source_file: String,
+}
+
+/// Best NPC offer for an item, from the `best_buy_price` / `best_sell_price` views
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BestPrice {
+    pub npc_name: String,
+    pub town: Option<String>,   // Town of the house nearest to the NPC
+    pub price: i32,
+    pub amount: i32,
+}
+
+/// An NPC and how far its home is from a position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearbyNpc {
+    pub npc_name: String,
+    pub town: Option<String>,
+    pub home: crate::geo::Position,
+    /// Fields to walk on the map, diagonal steps counting as one; floors are ignored
+    pub distance: i32,
+    /// Floors between the position and the NPC's home
+    pub floors: i32,
+}
+
+/// An NPC selling an item, near a position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearbyOffer {
+    #[serde(flatten)]
+    pub npc: NearbyNpc,
+    pub price: i32,
+    pub amount: i32,
+}
+
+/// Item list entry as shown on the website
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemListing {
+    pub type_id: i32,
+    pub name: String,
+    pub best_buy: Option<BestPrice>,   // Cheapest offer players can buy from
+    pub best_sell: Option<BestPrice>,  // Highest offer players can sell to
+    /// Hand-written note from import-overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// One item of a creature's loot table on the website
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LootTableEntry {
+    pub item: i32,
+    pub name: Option<String>,
+    /// Drop chance in percent
+    pub chance: f64,
+    /// Stack size, e.g. "1" or "1-100"
+    pub amount: String,
+    /// Gold value of a single item (face value for coins), if known
+    pub value: Option<i64>,
+    /// Hand-written item note from import-overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// A creature's loot table, most likely drops first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreatureLootTable {
+    pub name: String,
+    pub short_name: String,
+    pub race: i32,
+    /// Image file name without extension, with image_overrides applied
+    #[serde(default)]
+    pub image: String,
+    /// Hand-written creature note from import-overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub loot: Vec<LootTableEntry>,
+    /// Creatures it summons, e.g. "2 Fire Elemental"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summons: Vec<String>,
+}
+
+/// How complete one kind of data is, for `demonax coverage`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Coverage {
+    pub subsystem: String,
+    /// What counts as missing, e.g. "items without NPC prices"
+    pub check: String,
+    pub total: i64,
+    pub missing: i64,
+    pub percent_complete: f64,
+    /// Names of a few incomplete records
+    pub examples: Vec<String>,
+}
+
+/// Kind of content gap recorded by update-spells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentGapKind {
+    /// A spell no NPC teaches
+    UntaughtSpell,
+    /// A rune no NPC sells
+    UnsoldRune,
+}
+
+impl ContentGapKind {
+    /// Value stored in `content_gaps.kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentGapKind::UntaughtSpell => "untaught_spell",
+            ContentGapKind::UnsoldRune => "unsold_rune",
+        }
+    }
+}
+
+/// A spell or rune that players can't get, as recorded in one run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentGap {
+    pub run_id: i64,
+    pub kind: String,
+    pub spell_id: i32,
+    pub spell_name: String,
+    pub words: String,
+    pub level: i32,
+    /// When any run first recorded this gap
+    pub first_recorded: String,
+    /// Number of runs that recorded this gap
+    pub runs: i64,
+}
+
+/// Filters for listing content gaps; `None` fields don't filter
+#[derive(Debug, Clone, Default)]
+pub struct ContentGapFilter {
+    pub kind: Option<ContentGapKind>,
+    /// Substring of the spell name or words
+    pub name: Option<String>,
+    pub max_level: Option<i32>,
+    /// Run to list (default: the latest)
+    pub run_id: Option<i64>,
+}
+
+/// Number of content gaps recorded by one run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentGapRun {
+    pub run_id: i64,
+    pub recorded_at: String,
+    pub untaught_spells: i64,
+    pub unsold_runes: i64,
+}
+
+/// Aggregates of one snapshot date from `world_stats`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldStats {
+    pub snapshot_date: String,
+    pub players_seen: i64,
+    pub total_experience: i64,
+    pub median_level: f64,
+    pub new_players: i64,
+    pub total_gold: Option<i64>,
+    /// Names of the events running on the date, from add-event
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+}
+
+/// Kind of event recorded with add-event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// Double experience, e.g. a weekend
+    DoubleExp,
+    /// A game update
+    Patch,
+    /// The world was reset to an earlier save
+    Rollback,
+    Other,
+}
+
+impl EventKind {
+    /// Value stored in `events.kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::DoubleExp => "double_exp",
+            EventKind::Patch => "patch",
+            EventKind::Rollback => "rollback",
+            EventKind::Other => "other",
+        }
+    }
+}
+
+/// Online players of one day, from the samples of sample-online
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnlineDay {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub samples: i64,
+    /// Most players online in one sample
+    pub peak_players: i64,
+    pub average_players: f64,
+    /// Player limit of the server, when the source tells
+    pub max_players: Option<i64>,
+}
+
+/// Kind of a game server log line stored in `log_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogEventKind {
+    Death,
+    LevelUp,
+    RareLoot,
+}
+
+impl LogEventKind {
+    /// Value stored in `log_events.kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogEventKind::Death => "death",
+            LogEventKind::LevelUp => "level_up",
+            LogEventKind::RareLoot => "rare_loot",
+        }
+    }
+}
+
+/// A death, level advance or rare drop from the game server log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEvent {
+    /// YYYY-MM-DD HH:MM:SS, server time
+    pub occurred_at: String,
+    pub kind: LogEventKind,
+    pub player_name: String,
+    /// Level died at or advanced to
+    pub level: Option<i32>,
+    /// Killer of a death or the creature a rare item was looted from
+    pub creature: Option<String>,
+    /// Rare item looted
+    pub item: Option<String>,
+}
+
+/// A log event as exported for the website's live widgets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEventListing {
+    pub occurred_at: String,
+    pub kind: String,
+    /// None if no player of that name has been processed yet
+    pub player_id: Option<i32>,
+    pub player_name: String,
+    pub level: Option<i32>,
+    /// Killer or loot source; None when the killer was a player
+    pub creature: Option<String>,
+    /// Killing player of a death, kept apart so anonymized exports hide it
+    pub killer_name: Option<String>,
+    pub item: Option<String>,
+}
+
+/// A server-wide loss of experience since the previous snapshots, as after the
+/// server restored a backup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rollback {
+    pub snapshot_date: String,
+    /// Players with a snapshot on the date and an earlier one
+    pub players_compared: i64,
+    pub players_lost_experience: i64,
+    /// The rollback event recorded for the date
+    pub event_id: i64,
+}
+
+/// A window of dates that explains changes in the time series, e.g. a
+/// double-exp weekend; `end_date` is inclusive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub id: i64,
+    pub kind: String,
+    pub name: String,
+    pub start_date: String,
+    pub end_date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Kills of one bestiary monster ID over every player's latest snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BestiaryTotal {
+    pub monster_id: i32,
+    /// Race the monster ID stands for
+    pub race: i32,
+    /// `None` when no creature has the race
+    pub name: Option<String>,
+    pub short_name: Option<String>,
+    pub kills: i64,
+    /// Players who killed at least one
+    pub players: i64,
+    /// Hand-written creature note from import-overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Estimated loot of one creature's kills on one snapshot date, from `economy_inflow`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EconomyInflow {
+    pub snapshot_date: String,
+    pub creature: String,
+    pub kills: i64,
+    /// Face value of the coins dropped
+    pub gold: i64,
+    /// Number of other items dropped
+    pub items: f64,
+    /// NPC sell value of those items
+    pub item_value: i64,
+}
+
+/// Characters of one account as of a snapshot date, from [`Database::get_account_summaries`]
+///
+/// [`Database::get_account_summaries`]: crate::database::Database::get_account_summaries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountSummary {
+    pub account_id: i32,
+    pub characters: i64,
+    /// Characters with a snapshot within the activity window
+    pub active_characters: i64,
+    /// Character with the most experience
+    pub best_character: String,
+    pub best_level: i32,
+    pub total_experience: i64,
+    /// Latest snapshot of any of the characters
+    pub last_seen: String,
+    /// Whether any character had premium days left; None when no snapshot says
+    pub premium: Option<bool>,
+}
+
+/// Free or premium account, as in `rune_sellers.account_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountType {
+    Free,
+    Premium,
+}
+
+impl AccountType {
+    /// Premium with any premium days left
+    pub fn from_premium_days(premium_days: i32) -> Self {
+        if premium_days > 0 { AccountType::Premium } else { AccountType::Free }
+    }
+
+    /// Value stored in `rune_sellers.account_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Free => "Free",
+            AccountType::Premium => "Premium",
+        }
+    }
+}
+
+/// A player who learned a spell, from [`Database::get_spell_casters`]
+///
+/// [`Database::get_spell_casters`]: crate::database::Database::get_spell_casters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpellCaster {
+    pub player_id: i32,
+    pub player_name: String,
+    pub level: i32,
+    pub magic_level: i32,
+    pub vocation: String,
+    /// Latest snapshot of the player
+    pub snapshot_date: String,
+}
+
+/// A player rename from `player_name_history`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NameChange {
+    pub old_name: String,
+    pub new_name: String,
+    pub effective_date: String, // YYYY-MM-DD, first snapshot with the new name
+}
+
+/// Latest character data shown on a player card
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerCard {
+    pub player_id: i32,
+    pub player_name: String,
+    pub snapshot_date: String,
+    pub level: i32,
+    pub experience: i64,
+    pub vocation: String,
+    pub magic_level: i32,
+    /// Number of blessings; None when the snapshot doesn't say
+    pub blessings: Option<i32>,
+    /// Fighting skills as (name, value), highest first
+    pub skills: Vec<(String, i32)>,
+    /// Words of the spells an NPC teaches the player's vocation at their level
+    /// that they haven't learned; empty when the snapshot has no spell list
+    pub missing_spells: Vec<String>,
 }
 
 /// Player snapshot data for equipment rendering
@@ -391,5 +1399,5 @@ pub struct PlayerSnapshot {
     pub player_id: i32,
     pub player_name: String,
     pub snapshot_date: String,
-    pub equipment: Vec<i32>,  // Deserialized from equipment_json
+    pub equipment: Equipment, // Deserialized from equipment_json
 }
\ No newline at end of file
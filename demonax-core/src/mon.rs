@@ -0,0 +1,507 @@
+//! Round-trip reading and writing of .mon creature files.
+//!
+//! [`MonFile`] keeps every line of the original file, so writing it back
+//! without changes reproduces the input byte for byte. Typed accessors read
+//! and replace stats, flags, skills, spells and loot; a change only rewrites
+//! the value it touches, which keeps diffs of balance changes small.
+
+use crate::error::{DemonaxError, Result};
+use crate::file_utils::read_latin1_file;
+use crate::parsers::parse_mon_str;
+use crate::models::Creature;
+use encoding_rs::WINDOWS_1252;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A line (or several, for multi-line blocks) of a .mon file
+#[derive(Debug, Clone)]
+enum MonEntry {
+    /// Comment, blank or unrecognized line, kept verbatim
+    Raw(String),
+    /// `Key = value`; `head` is everything before the value, `tail` the line ending
+    Field { key: String, head: String, value: String, tail: String },
+}
+
+/// A skill tuple, e.g. `(HitPoints, 60000, 0, 60000, 0, 0, 0)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonSkill {
+    pub name: String,
+    /// Value, minimum, maximum and the remaining tuple fields
+    pub values: Vec<i32>,
+}
+
+/// A .mon file that can be edited and written back.
+#[derive(Debug, Clone)]
+pub struct MonFile {
+    entries: Vec<MonEntry>,
+}
+
+impl MonFile {
+    /// Read a .mon file (Latin-1).
+    pub fn from_file(path: &Path) -> Result<Self> {
+        read_latin1_file(path)?.parse()
+    }
+
+    /// Write the file back as Latin-1.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let text = self.to_string();
+        let (encoded, _, had_errors) = WINDOWS_1252.encode(&text);
+        if had_errors {
+            return Err(DemonaxError::Parse(format!("Failed to encode {:?} to Windows-1252", path)));
+        }
+        std::fs::write(path, &*encoded)?;
+        Ok(())
+    }
+
+    /// Parse the creature overview from the current contents.
+    pub fn creature(&self, file_stem: &str) -> Result<Creature> {
+        parse_mon_str(&self.to_string(), file_stem)
+    }
+
+    /// Raw value of a top-level key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find_map(|entry| match entry {
+            MonEntry::Field { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Replace the raw value of a top-level key, appending the key if it is missing
+    pub fn set(&mut self, key: &str, value: &str) {
+        for entry in &mut self.entries {
+            if let MonEntry::Field { key: k, value: v, .. } = entry
+                && k == key
+            {
+                *v = value.to_string();
+                return;
+            }
+        }
+        self.entries.push(MonEntry::Field {
+            key: key.to_string(),
+            head: format!("{:<12}= ", key),
+            value: value.to_string(),
+            tail: "\n".to_string(),
+        });
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i32> {
+        self.get(key)?.trim().parse().ok()
+    }
+
+    pub fn set_int(&mut self, key: &str, value: i32) {
+        self.set(key, &value.to_string());
+    }
+
+    pub fn flags(&self) -> Vec<String> {
+        self.list("Flags").into_iter().map(|(_, flag)| flag).collect()
+    }
+
+    pub fn set_flags(&mut self, flags: &[String]) {
+        self.set_list("Flags", flags);
+    }
+
+    pub fn skills(&self) -> Vec<MonSkill> {
+        self.list("Skills").into_iter().filter_map(|(_, entry)| parse_skill(&entry)).collect()
+    }
+
+    /// Set the value of a skill (case-insensitive name). The minimum and
+    /// maximum fields follow when they were equal to the old value, as for
+    /// `(HitPoints, 100, 0, 100, ...)`. Returns false if the skill is missing.
+    pub fn set_skill(&mut self, name: &str, value: i32) -> bool {
+        let Some((range, mut skill)) = self
+            .list("Skills")
+            .into_iter()
+            .filter_map(|(range, entry)| parse_skill(&entry).map(|skill| (range, skill)))
+            .find(|(_, skill)| skill.name.eq_ignore_ascii_case(name))
+        else {
+            return false;
+        };
+
+        let old = skill.values.first().copied().unwrap_or(0);
+        for (i, v) in skill.values.iter_mut().enumerate().take(3) {
+            if i == 0 || *v == old {
+                *v = value;
+            }
+        }
+        let fields: Vec<String> = skill.values.iter().map(|v| v.to_string()).collect();
+        let text = format!("({}, {})", skill.name, fields.join(", "));
+        self.replace_in_value("Skills", range, &text);
+        true
+    }
+
+    /// Hit points (the HitPoints skill)
+    pub fn hit_points(&self) -> Option<i32> {
+        self.skills()
+            .into_iter()
+            .find(|skill| skill.name == "HitPoints")
+            .and_then(|skill| skill.values.first().copied())
+    }
+
+    pub fn set_hit_points(&mut self, hp: i32) -> bool {
+        self.set_skill("HitPoints", hp)
+    }
+
+    /// Raw spell entries, e.g. `Victim (7, 0, 13) -> Damage (8, 800, 200) : 6`
+    pub fn spells(&self) -> Vec<String> {
+        self.list("Spells").into_iter().map(|(_, spell)| spell).collect()
+    }
+
+    pub fn set_spells(&mut self, spells: &[String]) {
+        self.set_list("Spells", spells);
+    }
+
+    /// Loot entries as (item_id, amount, chance_raw)
+    pub fn loot(&self) -> Vec<(i32, i32, i32)> {
+        self.list("Inventory")
+            .into_iter()
+            .filter_map(|(_, entry)| {
+                let numbers: Vec<i32> = entry
+                    .trim_matches(|c| c == '(' || c == ')')
+                    .split(',')
+                    .filter_map(|n| n.trim().parse().ok())
+                    .collect();
+                match numbers[..] {
+                    [item, amount, chance] => Some((item, amount, chance)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    pub fn set_loot(&mut self, loot: &[(i32, i32, i32)]) {
+        let entries: Vec<String> = loot
+            .iter()
+            .map(|(item, amount, chance)| format!("({}, {}, {})", item, amount, chance))
+            .collect();
+        self.set_list("Inventory", &entries);
+    }
+
+    /// Top-level entries of a `Key = {a, b, ...}` list with their byte ranges in the value
+    fn list(&self, key: &str) -> Vec<(std::ops::Range<usize>, String)> {
+        let Some(value) = self.get(key) else {
+            return Vec::new();
+        };
+        let (Some(open), Some(close)) = (value.find('{'), value.rfind('}')) else {
+            return Vec::new();
+        };
+        split_top_level(&value[open + 1..close])
+            .into_iter()
+            .map(|range| {
+                let range = range.start + open + 1..range.end + open + 1;
+                let text = value[range.clone()].to_string();
+                (range, text)
+            })
+            .collect()
+    }
+
+    /// Rewrite a list, one entry per line aligned under the first like the game files
+    fn set_list(&mut self, key: &str, entries: &[String]) {
+        let head_len = self
+            .entries
+            .iter()
+            .find_map(|entry| match entry {
+                MonEntry::Field { key: k, head, .. } if k == key => Some(head.len()),
+                _ => None,
+            })
+            .unwrap_or(14);
+        let separator = format!(",\n{}", " ".repeat(head_len + 1));
+        self.set(key, &format!("{{{}}}", entries.join(&separator)));
+    }
+
+    fn replace_in_value(&mut self, key: &str, range: std::ops::Range<usize>, text: &str) {
+        if let Some(value) = self.get(key) {
+            let mut value = value.to_string();
+            value.replace_range(range, text);
+            self.set(key, &value);
+        }
+    }
+}
+
+impl FromStr for MonFile {
+    type Err = DemonaxError;
+
+    /// Split .mon contents into fields. Values that open a brace continue
+    /// until it is closed, so multi-line blocks stay one field.
+    fn from_str(text: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut lines = text.split_inclusive('\n').peekable();
+
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim_start();
+            let key_len = trimmed.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(trimmed.len());
+            let rest = &trimmed[key_len..];
+            if trimmed.starts_with('#') || key_len == 0 || !rest.trim_start().starts_with('=') {
+                entries.push(MonEntry::Raw(line.to_string()));
+                continue;
+            }
+
+            let value_start = line.len() - rest.trim_start()[1..].trim_start().len();
+            let (head, first) = line.split_at(value_start);
+            let mut value = first.to_string();
+            let mut depth = brace_depth(&value);
+            while depth > 0 {
+                let Some(next) = lines.next() else {
+                    break;
+                };
+                depth += brace_depth(next);
+                value.push_str(next);
+            }
+
+            let body_len = value.trim_end_matches(['\n', '\r']).len();
+            let tail = value.split_off(body_len);
+            entries.push(MonEntry::Field {
+                key: trimmed[..key_len].to_string(),
+                head: head.to_string(),
+                value,
+                tail,
+            });
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl fmt::Display for MonFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            match entry {
+                MonEntry::Raw(line) => f.write_str(line)?,
+                MonEntry::Field { head, value, tail, .. } => write!(f, "{}{}{}", head, value, tail)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Net brace depth of a line, ignoring braces inside quoted strings
+fn brace_depth(text: &str) -> i32 {
+    let mut depth = 0;
+    let mut quoted = false;
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted = !quoted,
+            '{' if !quoted => depth += 1,
+            '}' if !quoted => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Byte ranges of the comma-separated entries of a list, trimmed, skipping
+/// commas nested in parentheses or braces
+fn split_top_level(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut push = |start: usize, end: usize| {
+        let entry = &text[start..end];
+        let lead = entry.len() - entry.trim_start().len();
+        let trimmed = entry.trim();
+        if !trimmed.is_empty() {
+            ranges.push(start + lead..start + lead + trimmed.len());
+        }
+    };
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                push(start, i);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    push(start, text.len());
+    ranges
+}
+
+fn parse_skill(entry: &str) -> Option<MonSkill> {
+    let inner = entry.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let mut parts = inner.split(',').map(str::trim);
+    let name = parts.next()?.to_string();
+    let values = parts.map(|v| v.parse().ok()).collect::<Option<Vec<i32>>>()?;
+    Some(MonSkill { name, values })
+}
+
+/// How an edit changes a value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditOp {
+    Set,
+    Add,
+    Multiply,
+}
+
+/// A balance change such as `hp*1.1`, `armor=30` or `experience+100`.
+///
+/// Fields are `hp`, `experience`, `attack`, `defend`, `armor` or a skill
+/// name (e.g. `FistFighting`). Results are rounded and never negative.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatureEdit {
+    pub field: String,
+    pub op: EditOp,
+    pub value: f64,
+}
+
+impl FromStr for CreatureEdit {
+    type Err = DemonaxError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some(pos) = s.find(['=', '+', '*']) else {
+            return Err(DemonaxError::Parse(format!("Invalid edit '{}', expected e.g. hp*1.1, armor=30 or experience+100", s)));
+        };
+        let op = match &s[pos..pos + 1] {
+            "=" => EditOp::Set,
+            "+" => EditOp::Add,
+            _ => EditOp::Multiply,
+        };
+        let field = s[..pos].trim().to_string();
+        let value = s[pos + 1..]
+            .trim()
+            .parse()
+            .map_err(|_| DemonaxError::Parse(format!("Invalid number in edit '{}'", s)))?;
+        if field.is_empty() {
+            return Err(DemonaxError::Parse(format!("Missing field in edit '{}'", s)));
+        }
+        Ok(Self { field, op, value })
+    }
+}
+
+impl CreatureEdit {
+    fn apply_to(&self, old: i32) -> i32 {
+        let new = match self.op {
+            EditOp::Set => self.value,
+            EditOp::Add => old as f64 + self.value,
+            EditOp::Multiply => old as f64 * self.value,
+        };
+        new.round().max(0.0) as i32
+    }
+
+    /// Apply the edit to a .mon file. Fails if the creature has no such field.
+    pub fn apply(&self, mon: &mut MonFile) -> Result<()> {
+        let top_level = match self.field.to_ascii_lowercase().as_str() {
+            "hp" | "hitpoints" => None,
+            "experience" => Some("Experience"),
+            "attack" => Some("Attack"),
+            "defend" => Some("Defend"),
+            "armor" => Some("Armor"),
+            _ => {
+                let skill = mon.skills().into_iter().find(|s| s.name.eq_ignore_ascii_case(&self.field));
+                let Some(old) = skill.and_then(|s| s.values.first().copied()) else {
+                    return Err(DemonaxError::NotFound(format!("field or skill '{}'", self.field)));
+                };
+                mon.set_skill(&self.field, self.apply_to(old));
+                return Ok(());
+            }
+        };
+
+        match top_level {
+            Some(key) => {
+                let old = mon.get_int(key).ok_or_else(|| DemonaxError::NotFound(format!("field '{}'", key)))?;
+                mon.set_int(key, self.apply_to(old));
+            }
+            None => {
+                let old = mon.hit_points().ok_or_else(|| DemonaxError::NotFound("HitPoints skill".to_string()))?;
+                mon.set_hit_points(self.apply_to(old));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find .mon files whose file stem or creature name matches any of the glob
+/// patterns (case-insensitive), e.g. `demon*` or `"Orc *"`.
+pub fn find_mon_files(mon_dir: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let patterns = patterns
+        .iter()
+        .map(|p| glob::Pattern::new(p).map_err(|e| DemonaxError::Parse(format!("Invalid pattern '{}': {}", p, e))))
+        .collect::<Result<Vec<_>>>()?;
+    let options = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+
+    let mut files = Vec::new();
+    for path in crate::file_utils::find_files_with_extension(mon_dir, "mon")? {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let name = MonFile::from_file(&path)?.get("Name").map(|n| n.trim_matches('"').to_string()).unwrap_or_default();
+        if patterns.iter().any(|p| p.matches_with(stem, options) || p.matches_with(&name, options)) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Changed lines between two texts, as `-old` / `+new` lines
+pub fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMON: &str = "# Demon\nName        = \"Demon\"\nArticle     = \"a\"\nRaceNumber  = 35\nExperience  = 6000\nSkills      = {(HitPoints, 1450, 0, 1450, 0, 0, 0),\n               (FistFighting, 90, 90, 90, 50, 1000, 1)\n              }\nAttack      = 80\nSpells      = {Victim (7, 0, 13) -> Damage (4, 250, 50) : 4,\n               Actor (13) -> Outfit ({0, 0, 0, 0, 0}, 20) : 15}\nInventory   = {(3031, 100, 999), (3035, 6, 800)}\n";
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        let mon: MonFile = DEMON.parse().unwrap();
+        assert_eq!(mon.to_string(), DEMON);
+        assert_eq!(mon.hit_points(), Some(1450));
+        assert_eq!(mon.spells().len(), 2);
+        assert_eq!(mon.loot(), vec![(3031, 100, 999), (3035, 6, 800)]);
+        assert_eq!(mon.creature("demon").unwrap().hp, 1450);
+    }
+
+    #[test]
+    fn test_edits_only_touch_their_values() {
+        let mut mon: MonFile = DEMON.parse().unwrap();
+        "hp*1.1".parse::<CreatureEdit>().unwrap().apply(&mut mon).unwrap();
+        "experience+500".parse::<CreatureEdit>().unwrap().apply(&mut mon).unwrap();
+        "fistfighting=100".parse::<CreatureEdit>().unwrap().apply(&mut mon).unwrap();
+        assert!("defend=5".parse::<CreatureEdit>().unwrap().apply(&mut mon).is_err());
+
+        let creature = mon.creature("demon").unwrap();
+        assert_eq!(creature.hp, 1595);
+        assert_eq!(creature.experience, 6500);
+        assert_eq!(mon.skills()[1].values, vec![100, 100, 100, 50, 1000, 1]);
+
+        let diff = diff_lines(DEMON, &mon.to_string());
+        assert_eq!(diff.len(), 6, "{:#?}", diff);
+        assert!(diff.contains(&"+Skills      = {(HitPoints, 1595, 0, 1595, 0, 0, 0),".to_string()));
+    }
+
+    #[test]
+    fn test_set_loot_rewrites_the_block() {
+        let mut mon: MonFile = DEMON.parse().unwrap();
+        mon.set_loot(&[(3031, 50, 999)]);
+        mon.set_flags(&["Unpushable".to_string()]);
+        let reparsed: MonFile = mon.to_string().parse().unwrap();
+        assert_eq!(reparsed.loot(), vec![(3031, 50, 999)]);
+        assert_eq!(reparsed.flags(), vec!["Unpushable"]);
+    }
+}
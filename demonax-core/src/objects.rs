@@ -0,0 +1,184 @@
+//! Patching item records in objects.srv.
+//!
+//! Changes are applied to the text of the file: only the lines of the
+//! selected records that a change touches are rewritten, so comments,
+//! record order and untouched records stay exactly as they were.
+
+use crate::error::{DemonaxError, Result};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// A change to an item record: `Attributes.Weight=1500` sets one attribute
+/// (an empty value removes it), `Description="..."` replaces a top-level value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemChange {
+    /// Attribute name for `Attributes.<name>`, otherwise `None`
+    pub attribute: Option<String>,
+    /// Top-level key (`Attributes` for attribute changes)
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for ItemChange {
+    type Err = DemonaxError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((key, value)) = s.split_once('=') else {
+            return Err(DemonaxError::Parse(format!("Invalid change '{}', expected e.g. Attributes.Weight=1500", s)));
+        };
+        let (key, value) = (key.trim(), value.trim().to_string());
+        if key.eq_ignore_ascii_case("TypeID") {
+            return Err(DemonaxError::Validation("TypeID can't be changed".to_string()));
+        }
+        match key.split_once('.') {
+            Some((group, attribute)) if group == "Attributes" && !attribute.is_empty() => Ok(Self {
+                attribute: Some(attribute.to_string()),
+                key: group.to_string(),
+                value,
+            }),
+            None if !key.is_empty() => Ok(Self { attribute: None, key: key.to_string(), value }),
+            _ => Err(DemonaxError::Parse(format!("Invalid key in change '{}'", s))),
+        }
+    }
+}
+
+/// Apply changes to the records with the given type IDs.
+///
+/// Returns the patched text and the number of records that changed.
+pub fn patch_objects_srv(text: &str, type_ids: &HashSet<i32>, changes: &[ItemChange]) -> (String, usize) {
+    let mut output = String::with_capacity(text.len());
+    let mut record: Vec<String> = Vec::new();
+    let mut changed = 0;
+
+    let mut flush = |record: &mut Vec<String>, output: &mut String| {
+        if record_type_id(record).is_some_and(|id| type_ids.contains(&id)) {
+            let before = record.concat();
+            patch_record(record, changes);
+            if record.concat() != before {
+                changed += 1;
+            }
+        }
+        output.push_str(&record.concat());
+        record.clear();
+    };
+
+    for line in text.split_inclusive('\n') {
+        if line_key(line).is_some_and(|(key, _)| key == "TypeID") {
+            flush(&mut record, &mut output);
+        }
+        record.push(line.to_string());
+    }
+    flush(&mut record, &mut output);
+
+    (output, changed)
+}
+
+/// `(key, byte offset of the value)` of a `Key = value` line
+fn line_key(line: &str) -> Option<(&str, usize)> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') {
+        return None;
+    }
+    let (key, rest) = trimmed.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let value_start = line.len() - rest.trim_start().len();
+    Some((key, value_start))
+}
+
+fn record_type_id(record: &[String]) -> Option<i32> {
+    record.iter().find_map(|line| match line_key(line)? {
+        ("TypeID", start) => line[start..].trim().parse().ok(),
+        _ => None,
+    })
+}
+
+fn patch_record(record: &mut Vec<String>, changes: &[ItemChange]) {
+    for change in changes {
+        let existing = record.iter().position(|line| line_key(line).is_some_and(|(key, _)| key == change.key));
+
+        match (existing, &change.attribute) {
+            (Some(index), None) => set_value(&mut record[index], &change.value),
+            (Some(index), Some(attribute)) => {
+                let (_, start) = line_key(&record[index]).unwrap_or(("", 0));
+                let value = record[index][start..].trim_end().to_string();
+                set_value(&mut record[index], &set_attribute(&value, attribute, &change.value));
+            }
+            (None, _) if change.value.is_empty() => {}
+            (None, attribute) => {
+                let value = match attribute {
+                    Some(attribute) => format!("{{{}={}}}", attribute, change.value),
+                    None => change.value.clone(),
+                };
+                // New keys go after the last line with content, before trailing blanks and comments
+                let index = record
+                    .iter()
+                    .rposition(|line| line_key(line).is_some())
+                    .map_or(record.len(), |i| i + 1);
+                record.insert(index, format!("{:<12}= {}\n", change.key, value));
+            }
+        }
+    }
+}
+
+/// Replace the value of a `Key = value` line, keeping its key, spacing and line ending
+fn set_value(line: &mut String, value: &str) {
+    let Some((_, start)) = line_key(line) else {
+        return;
+    };
+    let end = line.trim_end_matches(['\n', '\r']).len();
+    line.replace_range(start..end, value);
+}
+
+/// Set (or with an empty value, remove) one `Name=value` entry of an `{...}` attribute list
+fn set_attribute(list: &str, attribute: &str, value: &str) -> String {
+    let inner = list.trim().trim_start_matches('{').trim_end_matches('}');
+    let separator = if inner.contains(", ") { ", " } else { "," };
+
+    let mut entries: Vec<String> = inner.split(',').map(|e| e.trim().to_string()).filter(|e| !e.is_empty()).collect();
+    let position = entries
+        .iter()
+        .position(|e| e.split_once('=').is_some_and(|(k, _)| k.trim() == attribute));
+    match (position, value.is_empty()) {
+        (Some(i), true) => {
+            entries.remove(i);
+        }
+        (Some(i), false) => entries[i] = format!("{}={}", attribute, value),
+        (None, false) => entries.push(format!("{}={}", attribute, value)),
+        (None, true) => {}
+    }
+    format!("{{{}}}", entries.join(separator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBJECTS: &str = "# Generated objects.srv\n\nTypeID      = 3349\nName        = \"a crossbow\"\nFlags       = {Take,Distance}\nAttributes  = {Weight=4000,Range=5}\n\n# The bow\n\nTypeID      = 3350\nName        = \"a bow\"\nFlags       = {Take,Distance}\nAttributes  = {Weight=3100}\n\nTypeID      = 3351\nName        = \"a shovel\"\nFlags       = {Take}\n";
+
+    #[test]
+    fn test_patch_only_touches_selected_records() {
+        let changes: Vec<ItemChange> = vec!["Attributes.Weight=1500".parse().unwrap(), "Attributes.Range=".parse().unwrap()];
+        let ids: HashSet<i32> = [3349, 3351].into_iter().collect();
+
+        let (patched, changed) = patch_objects_srv(OBJECTS, &ids, &changes);
+        assert_eq!(changed, 2);
+        assert!(patched.contains("Attributes  = {Weight=1500}\n\n# The bow"));
+        assert!(patched.contains("Attributes  = {Weight=3100}\n"));
+        assert!(patched.ends_with("Flags       = {Take}\nAttributes  = {Weight=1500}\n"));
+
+        let items = crate::parsers::parse_objects_srv_str(&patched).unwrap();
+        assert_eq!(items.len(), 3);
+        assert!(items[0].attributes.contains("\"Weight\":\"1500\""));
+    }
+
+    #[test]
+    fn test_no_changes_keeps_text_identical() {
+        let ids: HashSet<i32> = [3350].into_iter().collect();
+        let changes: Vec<ItemChange> = vec!["Attributes.Weight=3100".parse().unwrap()];
+        assert_eq!(patch_objects_srv(OBJECTS, &ids, &changes), (OBJECTS.to_string(), 0));
+        assert!("TypeID=1".parse::<ItemChange>().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Samples of how many players are online, for the website's population graph.
+//!
+//! A sample is taken from one of two sources:
+//! - a who-is-online file the server rewrites while it runs, one player name
+//!   per line (blank lines and `#` comments are skipped)
+//! - the server's status port (`host:port`), asked with the `info` request
+//!   that answers with `<players online="12" max="100" .../>`
+//!
+//! `sample-online` stores each sample in `online_samples`; `export online`
+//! turns them into daily peak and average curves.
+//!
+//! The status port's full answer ([`ServerStatus`]) also has the uptime, the
+//! server version and the map, for `server-status`:
+//! ```text
+//! <tsqp version="1.0">
+//!   <serverinfo uptime="3600" servername="Demonax" server="Demonax" version="1.2" client="7.72"/>
+//!   <players online="12" max="100" peak="40"/>
+//!   <monsters total="5231"/>
+//!   <map name="Demonax" author="CipSoft" width="2048" height="2048"/>
+//!   <motd>Welcome!</motd>
+//! </tsqp>
+//! ```
+
+use crate::error::{DemonaxError, Result};
+use regex::{escape, Regex};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long the status port may take to connect and answer
+pub const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `info` request of the status protocol: length 6, 0xFF 0xFF, "info"
+const STATUS_REQUEST: &[u8] = &[0x06, 0x00, 0xFF, 0xFF, b'i', b'n', b'f', b'o'];
+
+/// Where the online count comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnlineSource {
+    WhoisFile(PathBuf),
+    /// host:port of the status port
+    StatusPort(String),
+}
+
+impl FromStr for OnlineSource {
+    type Err = DemonaxError;
+
+    /// `host:port` (optionally `status://host:port`) is the status port, and
+    /// anything else a who-is-online file
+    fn from_str(source: &str) -> Result<Self> {
+        let address = source.strip_prefix("status://");
+        let looks_like_address = |text: &str| {
+            text.rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && !host.contains(['/', '\\']) && port.parse::<u16>().is_ok())
+        };
+        match address {
+            Some(address) if looks_like_address(address) => Ok(OnlineSource::StatusPort(address.to_string())),
+            Some(_) => Err(DemonaxError::Validation(format!("Invalid status address {:?}, expected host:port", source))),
+            None if looks_like_address(source) && !std::path::Path::new(source).exists() => {
+                Ok(OnlineSource::StatusPort(source.to_string()))
+            }
+            None => Ok(OnlineSource::WhoisFile(PathBuf::from(source))),
+        }
+    }
+}
+
+impl std::fmt::Display for OnlineSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OnlineSource::WhoisFile(path) => write!(f, "{}", path.display()),
+            OnlineSource::StatusPort(address) => write!(f, "status://{}", address),
+        }
+    }
+}
+
+/// Players online at one moment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnlineCount {
+    pub players: i64,
+    /// Player limit of the server, when the source tells
+    pub max_players: Option<i64>,
+}
+
+/// What the status port says about the running server; everything but the
+/// online players is optional, as servers leave out what they don't track
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServerStatus {
+    pub server_name: Option<String>,
+    /// Server software and version, e.g. "Demonax 1.2"
+    pub version: Option<String>,
+    /// Client version the server speaks, e.g. "7.72"
+    pub client_version: Option<String>,
+    pub uptime_seconds: Option<i64>,
+    pub players_online: i64,
+    pub max_players: Option<i64>,
+    /// Most players online since the server started
+    pub peak_players: Option<i64>,
+    pub monsters: Option<i64>,
+    pub map_name: Option<String>,
+    pub motd: Option<String>,
+}
+
+impl ServerStatus {
+    pub fn online_count(&self) -> OnlineCount {
+        OnlineCount { players: self.players_online, max_players: self.max_players }
+    }
+}
+
+/// Count the players online now
+pub fn sample_online(source: &OnlineSource) -> Result<OnlineCount> {
+    match source {
+        OnlineSource::WhoisFile(path) => {
+            let text = crate::file_utils::read_latin1_file(path)?;
+            Ok(OnlineCount { players: count_whois_players(&text), max_players: None })
+        }
+        OnlineSource::StatusPort(address) => Ok(query_server_status(address)?.online_count()),
+    }
+}
+
+/// Ask the status port at `host:port` about the running server
+pub fn query_server_status(address: &str) -> Result<ServerStatus> {
+    parse_server_status(&query_status_port(address)?)
+}
+
+/// Players in a who-is-online file
+pub fn count_whois_players(text: &str) -> i64 {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .count() as i64
+}
+
+/// The attributes of the first `<element ...>` of an XML answer
+fn element_attributes<'a>(xml: &'a str, element: &str) -> Option<&'a str> {
+    Regex::new(&format!(r"<{}\b([^>]*)>", escape(element))).unwrap().captures(xml).map(|caps| caps.get(1).unwrap().as_str())
+}
+
+/// The value of `name="..."` among an element's attributes
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    Regex::new(&format!(r#"\b{}\s*=\s*"([^"]*)""#, escape(name)))
+        .unwrap()
+        .captures(attributes)
+        .map(|caps| caps[1].to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Parse a status port's XML answer to the `info` request
+pub fn parse_server_status(xml: &str) -> Result<ServerStatus> {
+    let players = element_attributes(xml, "players")
+        .ok_or_else(|| DemonaxError::Parse("Status answer has no <players> element".to_string()))?;
+    let number = |attributes: Option<&str>, name: &str| attributes.and_then(|a| attribute(a, name)).and_then(|v| v.parse().ok());
+    let players_online = number(Some(players), "online")
+        .ok_or_else(|| DemonaxError::Parse("Status answer has no online player count".to_string()))?;
+
+    let info = element_attributes(xml, "serverinfo");
+    let text = |name: &str| info.and_then(|a| attribute(a, name));
+    let version = match (text("server"), text("version")) {
+        (Some(server), Some(version)) => Some(format!("{} {}", server, version)),
+        (server, version) => server.or(version),
+    };
+    let motd = Regex::new(r"(?s)<motd>(.*?)</motd>")
+        .unwrap()
+        .captures(xml)
+        .map(|caps| caps[1].trim().to_string())
+        .filter(|motd| !motd.is_empty());
+    Ok(ServerStatus {
+        server_name: text("servername"),
+        version,
+        client_version: text("client"),
+        uptime_seconds: number(info, "uptime"),
+        players_online,
+        max_players: number(Some(players), "max"),
+        peak_players: number(Some(players), "peak"),
+        monsters: number(element_attributes(xml, "monsters"), "total"),
+        map_name: element_attributes(xml, "map").and_then(|a| attribute(a, "name")),
+        motd,
+    })
+}
+
+fn query_status_port(address: &str) -> Result<String> {
+    let socket = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| DemonaxError::NotFound(format!("Status address {:?} did not resolve", address)))?;
+    let mut stream = TcpStream::connect_timeout(&socket, STATUS_TIMEOUT)?;
+    stream.set_read_timeout(Some(STATUS_TIMEOUT))?;
+    stream.set_write_timeout(Some(STATUS_TIMEOUT))?;
+    stream.write_all(STATUS_REQUEST)?;
+    let mut answer = Vec::new();
+    stream.read_to_end(&mut answer)?;
+    Ok(String::from_utf8_lossy(&answer).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_and_answers() {
+        assert_eq!("127.0.0.1:7171".parse::<OnlineSource>().unwrap(), OnlineSource::StatusPort("127.0.0.1:7171".to_string()));
+        assert_eq!(
+            "status://game.example.org:7171".parse::<OnlineSource>().unwrap(),
+            OnlineSource::StatusPort("game.example.org:7171".to_string())
+        );
+        assert_eq!("game/whoisonline.txt".parse::<OnlineSource>().unwrap(), OnlineSource::WhoisFile("game/whoisonline.txt".into()));
+        assert!("status://game".parse::<OnlineSource>().is_err());
+
+        assert_eq!(count_whois_players("# online\nAlice\n\nBob\n"), 2);
+        let xml = r#"<?xml version="1.0"?><tsqp version="1.0"><players online="12" max="100" peak="40"/></tsqp>"#;
+        let status = parse_server_status(xml).unwrap();
+        assert_eq!(status.online_count(), OnlineCount { players: 12, max_players: Some(100) });
+        assert_eq!((status.peak_players, status.uptime_seconds, status.version), (Some(40), None, None));
+        assert!(parse_server_status("<tsqp/>").is_err());
+
+        let full = r#"<tsqp version="1.0">
+  <serverinfo uptime="3600" servername="Demonax" server="Demonax" version="1.2" client="7.72"/>
+  <players online="12" max="100" peak="40"/>
+  <monsters total="5231"/>
+  <map name="Demonax" author="CipSoft" width="2048" height="2048"/>
+  <motd>
+    Welcome!
+  </motd>
+</tsqp>"#;
+        let status = parse_server_status(full).unwrap();
+        assert_eq!(status.uptime_seconds, Some(3600));
+        assert_eq!(status.version.as_deref(), Some("Demonax 1.2"));
+        assert_eq!((status.client_version.as_deref(), status.monsters), (Some("7.72"), Some(5231)));
+        assert_eq!((status.map_name.as_deref(), status.motd.as_deref()), (Some("Demonax"), Some("Welcome!")));
+    }
+
+    #[test]
+    fn test_status_port_is_queried() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 8];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!(&request, STATUS_REQUEST);
+            stream.write_all(br#"<tsqp><players online="3" max="50"/></tsqp>"#).unwrap();
+        });
+        let count = sample_online(&OnlineSource::StatusPort(address)).unwrap();
+        server.join().unwrap();
+        assert_eq!(count, OnlineCount { players: 3, max_players: Some(50) });
+    }
+}
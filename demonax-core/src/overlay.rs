@@ -0,0 +1,102 @@
+//! Hand-written notes on creatures and items, imported from CSV with
+//! `demonax import-overlay` and merged into the website exports.
+//!
+//! The CSV's first header picks the kind of overlay:
+//! ```csv
+//! short_name,note
+//! dragon,"Guards the treasure of Dragonblaze Peaks."
+//! ```
+//! or `type_id,note` for items. Notes are kept across updates; a row with an
+//! empty note removes that note. Keys that match no creature or item are
+//! stored anyway, with a warning, so notes can be written before the content
+//! they describe is ingested.
+
+use crate::database::Database;
+use crate::error::{DemonaxError, Result};
+use crate::processors::Reporter;
+use std::path::Path;
+
+/// Which table an overlay CSV fills
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayKind {
+    /// `short_name,note` rows, stored in `creature_notes`
+    Creatures,
+    /// `type_id,note` rows, stored in `item_notes`
+    Items,
+}
+
+impl OverlayKind {
+    /// What the notes are on, e.g. "creature"
+    pub fn noun(self) -> &'static str {
+        match self {
+            OverlayKind::Creatures => "creature",
+            OverlayKind::Items => "item",
+        }
+    }
+}
+
+/// What [`import_overlay`] stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlaySummary {
+    pub kind: OverlayKind,
+    /// Notes added or changed
+    pub stored: usize,
+    /// Notes removed by rows with an empty note
+    pub removed: usize,
+    /// Keys that match no creature or item
+    pub unknown_keys: Vec<String>,
+}
+
+/// Read an overlay CSV as its kind and (key, note) rows, trimmed. Item keys
+/// must be numbers.
+pub fn read_overlay_csv(path: &Path) -> Result<(OverlayKind, Vec<(String, String)>)> {
+    let mut reader = csv::Reader::from_path(path)
+        .map_err(|e| DemonaxError::Parse(format!("Failed to open overlay CSV {:?}: {}", path, e)))?;
+    let headers = reader
+        .headers()
+        .map_err(|e| DemonaxError::Parse(format!("Overlay CSV {:?}: {}", path, e)))?
+        .clone();
+    let kind = match (headers.get(0).map(str::trim), headers.get(1).map(str::trim)) {
+        (Some("short_name"), Some("note")) => OverlayKind::Creatures,
+        (Some("type_id"), Some("note")) => OverlayKind::Items,
+        _ => {
+            return Err(DemonaxError::Validation(format!(
+                "Overlay CSV {:?} must start with a short_name,note or type_id,note header",
+                path
+            )));
+        }
+    };
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let line = index + 2;
+        let record = record.map_err(|e| DemonaxError::Parse(format!("Overlay CSV line {}: {}", line, e)))?;
+        let key = record.get(0).unwrap_or("").trim().to_string();
+        if key.is_empty() {
+            return Err(DemonaxError::Validation(format!("Overlay CSV line {}: no {}", line, &headers[0])));
+        }
+        if kind == OverlayKind::Items && key.parse::<i32>().is_err() {
+            return Err(DemonaxError::Validation(format!("Overlay CSV line {}: type_id {:?} is not a number", line, key)));
+        }
+        rows.push((key, record.get(1).unwrap_or("").trim().to_string()));
+    }
+    Ok((kind, rows))
+}
+
+/// Store the notes of an overlay CSV, warning about keys that match no
+/// creature or item
+pub fn import_overlay(db: &Database, path: &Path, reporter: &Reporter) -> Result<OverlaySummary> {
+    let (kind, rows) = read_overlay_csv(path)?;
+    let removed = rows.iter().filter(|(_, note)| note.is_empty()).count();
+    let unknown_keys = match kind {
+        OverlayKind::Creatures => db.upsert_creature_notes(&rows)?,
+        OverlayKind::Items => {
+            let rows: Vec<(i32, String)> = rows.iter().map(|(key, note)| (key.parse().unwrap_or_default(), note.clone())).collect();
+            db.upsert_item_notes(&rows)?.iter().map(i32::to_string).collect()
+        }
+    };
+    for key in &unknown_keys {
+        reporter.warn(format_args!("Overlay key {:?} matches no {}", key, kind.noun()));
+    }
+    Ok(OverlaySummary { kind, stored: rows.len() - removed, removed, unknown_keys })
+}
@@ -2,10 +2,11 @@
 
 use crate::error::{DemonaxError, Result};
 use crate::file_utils::{read_latin1_file, read_utf8_file};
+use crate::geo::Sector;
 use crate::models::{
-    BestiaryEntry, Creature, CreatureLoot, CreatureSpell, HarvestingEntry, Item, ItemPrice, ParsedUsrFile, PlayerSkills,
-    QuestChest, QuestCompletion, Raid, RuneSeller, Spell, SpellImpactType, SpellShapeType, SpellTeacher,
-    damage_type_name,
+    BestiaryEntry, Creature, CreatureLoot, CreatureSpell, Equipment, EquipmentItem, EquipmentSlot, HarvestingEntry, House, Item, ItemPrice, ParsedUsrFile, PlayerSkills,
+    KeyItem, MapDoor, QuestChest, QuestCompletion, Raid, RaidExecution, RaidStep, RuneSeller, SkillEntry, Spawn, Spell, SpellImpactType, SpellShapeType, SpellTeacher,
+    damage_type_name, spell_flag,
 };
 use regex::{Regex, escape};
 use serde_json::json;
@@ -62,7 +63,12 @@ fn resolve_ndb_includes(text: &str, file_path: &Path) -> String {
 /// Parse a .usr file and extract player data.
 pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
     let text = read_latin1_file(file_path)?;
+    parse_usr_str(&text, &file_path.to_string_lossy())
+}
 
+/// Parse .usr file contents. `source_file` is only used for error messages
+/// and the `source_file` field of the result.
+pub fn parse_usr_str(text: &str, source_file: &str) -> Result<ParsedUsrFile> {
     // Helper function to extract value for a key
     fn extract_value(text: &str, key: &str) -> Option<String> {
         let pattern = format!(r"{}\s*=\s*([^\n]*)", regex::escape(key));
@@ -84,11 +90,11 @@ pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
         extract_value(text, key).map(|v| v.trim_matches('"').to_string())
     }
 
-    let player_id = get_int(&text, "ID").ok_or_else(|| {
-        DemonaxError::Parse(format!("Missing ID field in {:?}", file_path))
+    let player_id = get_int(text, "ID").ok_or_else(|| {
+        DemonaxError::Parse(format!("Missing ID field in {:?}", source_file))
     })?;
-    let player_name = get_string(&text, "Name").ok_or_else(|| {
-        DemonaxError::Parse(format!("Missing Name field in {:?}", file_path))
+    let player_name = get_string(text, "Name").ok_or_else(|| {
+        DemonaxError::Parse(format!("Missing Name field in {:?}", source_file))
     })?;
 
     // Initialize skills with defaults (use -1 for unknown?)
@@ -108,34 +114,26 @@ pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
     };
 
     // Parse skill lines
-    let skill_re = Regex::new(r"Skill\s*=\s*\([^)]+\)").unwrap();
-    for skill_line in skill_re.find_iter(&text) {
-        let line = skill_line.as_str();
-        // Extract content inside parentheses
-        let content_re = Regex::new(r"\((.*?)\)").unwrap();
-        if let Some(caps) = content_re.captures(line) {
-            let content = caps.get(1).unwrap().as_str();
-            let parts: Vec<&str> = content.split(',').map(|s| s.trim()).collect();
-            if parts.len() < 15 {
-                continue;
-            }
-            let skill_id: i32 = parts[0].parse().unwrap_or(-1);
-            let skill_value: i32 = parts[1].parse().unwrap_or(-1);
-            match skill_id {
-                0 => {
-                    skills.level = skill_value;
-                    skills.experience = parts[11].parse().unwrap_or(-1); // index 12 in R is 11 zero-based
-                }
-                1 => skills.magic_level = skill_value,
-                6 => skills.shielding = skill_value,
-                7 => skills.distance_fighting = skill_value,
-                8 => skills.sword_fighting = skill_value,
-                9 => skills.club_fighting = skill_value,
-                10 => skills.axe_fighting = skill_value,
-                11 => skills.fist_fighting = skill_value,
-                13 => skills.fishing = skill_value,
-                _ => {}
+    let skill_re = Regex::new(r"Skill\s*=\s*\(([^)]+)\)").unwrap();
+    let skill_entries: Vec<SkillEntry> = skill_re
+        .captures_iter(text)
+        .filter_map(|caps| parse_skill_tuple(caps.get(1).unwrap().as_str()))
+        .collect();
+    for entry in &skill_entries {
+        match entry.skill_id {
+            0 => {
+                skills.level = entry.value;
+                skills.experience = entry.tries;
             }
+            1 => skills.magic_level = entry.value,
+            6 => skills.shielding = entry.value,
+            7 => skills.distance_fighting = entry.value,
+            8 => skills.sword_fighting = entry.value,
+            9 => skills.club_fighting = entry.value,
+            10 => skills.axe_fighting = entry.value,
+            11 => skills.fist_fighting = entry.value,
+            13 => skills.fishing = entry.value,
+            _ => {}
         }
     }
 
@@ -156,10 +154,10 @@ pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
                 for pair in pairs {
                     let pair = pair.trim_matches(|c| c == '(' || c == ')');
                     let parts: Vec<&str> = pair.split(',').map(|s| s.trim()).collect();
-                    if parts.len() == 2 {
-                        if let (Ok(id), Ok(count)) = (parts[0].parse(), parts[1].parse()) {
-                            result.push((id, count));
-                        }
+                    if parts.len() == 2
+                        && let (Ok(id), Ok(count)) = (parts[0].parse(), parts[1].parse())
+                    {
+                        result.push((id, count));
                     }
                 }
             }
@@ -167,7 +165,7 @@ pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
         result
     }
 
-    let quest_values: Vec<QuestCompletion> = parse_pair_list(&text, "QuestValues")
+    let quest_values: Vec<QuestCompletion> = parse_pair_list(text, "QuestValues")
         .into_iter()
         .map(|(quest_id, completion_count)| QuestCompletion {
             quest_id,
@@ -175,7 +173,7 @@ pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
         })
         .collect();
 
-    let bestiary: Vec<BestiaryEntry> = parse_pair_list(&text, "Bestiary")
+    let bestiary: Vec<BestiaryEntry> = parse_pair_list(text, "Bestiary")
         .into_iter()
         .map(|(monster_id, kill_count)| BestiaryEntry {
             monster_id,
@@ -183,7 +181,7 @@ pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
         })
         .collect();
 
-    let harvesting: Vec<HarvestingEntry> = parse_pair_list(&text, "Harvesting")
+    let harvesting: Vec<HarvestingEntry> = parse_pair_list(text, "Harvesting")
         .into_iter()
         .map(|(race_id, harvest_count)| HarvestingEntry {
             race_id,
@@ -191,84 +189,118 @@ pub fn parse_usr_file(file_path: &Path) -> Result<ParsedUsrFile> {
         })
         .collect();
 
+    // Learned spells: SpellList = {1,2,...}
+    let spells: Vec<i32> = extract_braced_block(text, "SpellList")
+        .map(|list| list.split(',').filter_map(|id| id.trim().parse().ok()).collect())
+        .unwrap_or_default();
+
     // Parse equipment
-    let equipment = parse_equipment(&text);
+    let equipment = parse_equipment(text);
 
     Ok(ParsedUsrFile {
         player_id,
         skills,
+        skill_entries,
+        spells,
         quest_values,
         bestiary,
         harvesting,
         equipment,
-        source_file: file_path.to_string_lossy().to_string(),
+        profession: get_int(text, "Profession"),
+        account_id: get_int(text, "Account"),
+        premium_days: get_int(text, "PremiumDays"),
+        // Blessings = {1,3,5}: the numbers of the blessings the character has
+        blessings: extract_braced_block(text, "Blessings")
+            .map(|list| list.split(',').filter(|id| id.trim().parse::<i32>().is_ok()).count() as i32),
+        source_file: source_file.to_string(),
     })
 }
 
-/// Parse equipment from Inventory section.
-fn parse_equipment(text: &str) -> Vec<i32> {
-    let mut equipment = vec![-1; 10]; // 10 slots, -1 for NA
+/// Parse the fields of a 15-field Skill tuple (without the parentheses).
+/// Returns `None` for shorter tuples; unparsable fields become -1.
+fn parse_skill_tuple(content: &str) -> Option<SkillEntry> {
+    let parts: Vec<&str> = content.split(',').map(|s| s.trim()).collect();
+    if parts.len() < 15 {
+        return None;
+    }
+    let int = |i: usize| parts[i].parse::<i32>().unwrap_or(-1);
+    let long = |i: usize| parts[i].parse::<i64>().unwrap_or(-1);
+    Some(SkillEntry {
+        skill_id: int(0),
+        value: int(1),
+        max: int(2),
+        min: int(3),
+        modifier: int(4),
+        tries: long(11),
+        next_level: long(13),
+    })
+}
 
-    // Find Inventory = {
-    let inv_re = Regex::new(r"Inventory\s*=\s*\{").unwrap();
-    let Some(inv_match) = inv_re.find(text) else {
-        return equipment;
-    };
+/// Extract the content of a `Key = {...}` block using brace matching.
+///
+/// Returns the text between the opening brace and its matching closing brace,
+/// so nested braces and blocks spanning several lines are kept intact.
+/// Returns `None` if the key is missing or the braces are unbalanced.
+fn extract_braced_block<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let block_re = Regex::new(&format!(r"\b{}\s*=\s*\{{", escape(key))).ok()?;
+    let start = block_re.find(text)?.end();
 
-    // Find matching closing brace
-    let start_pos = inv_match.end();
     let mut brace_count = 1;
-    let chars: Vec<char> = text[start_pos..].chars().collect();
-    let mut i = 0;
-    while brace_count > 0 && i < chars.len() {
-        match chars[i] {
+    for (i, ch) in text[start..].char_indices() {
+        match ch {
             '{' => brace_count += 1,
-            '}' => brace_count -= 1,
+            '}' => {
+                brace_count -= 1;
+                if brace_count == 0 {
+                    return Some(&text[start..start + i]);
+                }
+            }
             _ => {}
         }
-        i += 1;
     }
-    if brace_count != 0 {
-        return equipment;
-    }
-    let end_pos = start_pos + i;
+    None
+}
 
-    // Extract just the content between the braces (excluding "Inventory = {" and final "}")
-    let inv_content = &text[start_pos..end_pos - 1];
+/// Parse equipment from Inventory section.
+fn parse_equipment(text: &str) -> Equipment {
+    let mut equipment = Equipment::default();
+
+    let Some(inv_content) = extract_braced_block(text, "Inventory") else {
+        return equipment;
+    };
 
     // Parse line by line to avoid matching nested Content={} structures
     for line in inv_content.lines() {
         let line = line.trim();
 
-        // Check if line starts with a digit (slot number)
-        if let Some(first_char) = line.chars().next() {
-            if first_char.is_ascii_digit() {
-                // Extract slot number
-                let digits: String = line.chars()
-                    .take_while(|c| c.is_ascii_digit())
-                    .collect();
-
-                if let Ok(slot) = digits.parse::<usize>() {
-                    if slot >= 1 && slot <= 10 {
-                        // Look for "Content={" on this line only
-                        if let Some(content_pos) = line.find("Content=") {
-                            // Find the opening brace
-                            if let Some(brace_pos) = line[content_pos..].find('{') {
-                                let start = content_pos + brace_pos + 1;
-                                // Extract the first number after the brace
-                                let after_brace = &line[start..];
-                                let item_digits: String = after_brace.trim()
-                                    .chars()
-                                    .take_while(|c| c.is_ascii_digit())
-                                    .collect();
-
-                                if let Ok(item_id) = item_digits.parse::<i32>() {
-                                    equipment[slot - 1] = item_id;
-                                }
-                            }
-                        }
-                    }
-                }
+        // Extract slot number (lines start with a digit)
+        let digits: String = line.chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let Some(slot) = digits.parse::<usize>().ok().and_then(EquipmentSlot::from_usr_slot) else {
+            continue;
+        };
+
+        // Look for "Content={" on this line only
+        if let Some(content_pos) = line.find("Content=")
+            && let Some(brace_pos) = line[content_pos..].find('{')
+        {
+            let start = content_pos + brace_pos + 1;
+            // The slot item's own attributes come before any nested container contents
+            let item_text = line[start..].split(['{', '}']).next().unwrap_or_default();
+            let item_text = item_text.strip_suffix("Content=").unwrap_or(item_text).trim();
+
+            let item_digits: String = item_text
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+
+            if let Ok(item_id) = item_digits.parse::<i32>() {
+                equipment.set(slot, Some(EquipmentItem {
+                    type_id: item_id,
+                    amount: inventory_attribute(item_text, "Amount"),
+                    charges: inventory_attribute(item_text, "Charges"),
+                }));
             }
         }
     }
@@ -276,6 +308,14 @@ fn parse_equipment(text: &str) -> Vec<i32> {
     equipment
 }
 
+/// Value of a `Key=123` attribute in an inventory item entry.
+fn inventory_attribute(item_text: &str, key: &str) -> Option<i32> {
+    item_text.split_whitespace().find_map(|token| {
+        let (name, value) = token.split_once('=')?;
+        if name == key { value.trim_end_matches(',').parse().ok() } else { None }
+    })
+}
+
 /// Calculate loot drop percentage from raw chance value.
 /// Formula: (chance + 1) / 999 * 100
 /// If percentage < 1, round to 1 decimal place, else round to integer.
@@ -292,7 +332,17 @@ pub fn get_loot_percent(chance_raw: i32) -> f64 {
 /// Returns a Creature struct with basic stats.
 pub fn parse_mon_file(file_path: &Path) -> Result<Creature> {
     let text = read_latin1_file(file_path)?;
+    let file_stem = file_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    parse_mon_str(&text, &file_stem)
+}
 
+/// Parse .mon file contents. `file_stem` is the file name without extension,
+/// used as the creature's `html_name`.
+pub fn parse_mon_str(text: &str, file_stem: &str) -> Result<Creature> {
     // Helper functions similar to .usr parsing
     fn extract_value(text: &str, key: &str) -> Option<String> {
         let pattern = format!(r"{}\s*=\s*([^\n]*)", escape(key));
@@ -321,13 +371,26 @@ pub fn parse_mon_file(file_path: &Path) -> Result<Creature> {
         caps.get(1)?.as_str().parse().ok()
     }
 
-    let name = get_string(&text, "Name").ok_or_else(|| {
-        DemonaxError::Parse(format!("Missing Name field in {:?}", file_path))
+    let name = get_string(text, "Name").ok_or_else(|| {
+        DemonaxError::Parse(format!("Missing Name field in {:?}", file_stem))
     })?;
-    let article = get_string(&text, "Article").unwrap_or_default();
-    let race = get_int(&text, "RaceNumber").unwrap_or(0);
-    let hp = get_hitpoints(&text).unwrap_or(0);
-    let experience = get_int(&text, "Experience").unwrap_or(0);
+    let article = get_string(text, "Article").unwrap_or_default();
+    let race = get_int(text, "RaceNumber").unwrap_or(0);
+    let hp = get_hitpoints(text).unwrap_or(0);
+    let experience = get_int(text, "Experience").unwrap_or(0);
+
+    // Combat values are top-level keys; anchor them so e.g. "Armor" inside a
+    // description or spell line isn't picked up
+    let get_top_level_int = |key: &str| -> i32 {
+        Regex::new(&format!(r"(?m)^\s*{}\s*=\s*(-?[0-9]+)", escape(key)))
+            .ok()
+            .and_then(|re| re.captures(text))
+            .and_then(|caps| caps[1].parse().ok())
+            .unwrap_or(0)
+    };
+    let attack = get_top_level_int("Attack");
+    let defend = get_top_level_int("Defend");
+    let armor = get_top_level_int("Armor");
 
     // Determine creature type based on article (as per R code)
     let creature_type = if article == "A" || article == "An" {
@@ -337,30 +400,8 @@ pub fn parse_mon_file(file_path: &Path) -> Result<Creature> {
     };
 
     let short_name = name.replace(" ", "").to_lowercase();
-    let file_stem = file_path
-        .file_stem()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-
-    // Image name mapping (from R code)
-    let img_map = vec![
-        ("demodras", "dragonlord"),
-        ("dharalion", "elfarcanist"),
-        ("loraith", "yalaharipriest"),
-        ("leon", "heroguardian"),
-        ("grorlam", "stonegolem"),
-        ("necropharus", "necromancer"),
-        ("beholder", "bonelord"),
-        ("theoldwidow", "giantspider"),
-    ];
-    let mut image_name = short_name.clone();
-    for (from, to) in img_map {
-        if short_name == from {
-            image_name = to.to_string();
-            break;
-        }
-    }
+    // Images that differ are mapped at export time, see image_overrides
+    let image_name = short_name.clone();
 
     let has_loot = text.contains("Inventory");
 
@@ -379,7 +420,10 @@ pub fn parse_mon_file(file_path: &Path) -> Result<Creature> {
         image_name,
         has_loot,
         article,
-        html_name: file_stem,
+        html_name: file_stem.to_string(),
+        attack,
+        defend,
+        armor,
     })
 }
 
@@ -388,41 +432,19 @@ pub fn parse_mon_file(file_path: &Path) -> Result<Creature> {
 /// Note: Average loot values are calculated in R code when needed, not stored in the database.
 pub fn parse_creature_loot(file_path: &Path) -> Result<Vec<CreatureLoot>> {
     let text = read_latin1_file(file_path)?;
+    parse_creature_loot_str(&text)
+}
 
+/// Parse creature loot from .mon file contents.
+pub fn parse_creature_loot_str(text: &str) -> Result<Vec<CreatureLoot>> {
     // First, get creature ID (placeholder)
     let creature_id = 0;
 
-    // Find Inventory section
-    let inv_re = Regex::new(r"Inventory\s*=\s*\{").unwrap();
-    let Some(inv_match) = inv_re.find(&text) else {
-        return Ok(Vec::new()); // No loot
-    };
-
-    // Extract content between braces (simplified)
-    let start = inv_match.end();
-    let mut brace_count = 1;
-    let chars: Vec<char> = text[start..].chars().collect();
-    let mut i = 0;
-    while brace_count > 0 && i < chars.len() {
-        match chars[i] {
-            '{' => brace_count += 1,
-            '}' => brace_count -= 1,
-            _ => {}
-        }
-        i += 1;
-    }
-    if brace_count != 0 {
+    // Find Inventory section (no section or unbalanced braces means no loot)
+    let Some(content) = extract_braced_block(text, "Inventory") else {
         return Ok(Vec::new());
-    }
-    let end = start + i;
-    let inv_content = &text[inv_match.start()..end];
-
-    // Remove "Inventory = {" prefix and trailing "}"
-    let content = inv_content
-        .strip_prefix("Inventory = {")
-        .and_then(|s| s.strip_suffix('}'))
-        .unwrap_or("")
-        .trim();
+    };
+    let content = content.trim();
 
     // Split by "),(" or ")," ?
     // In R code they do: gsub("\\(", "\n", inv) then parse as CSV.
@@ -464,10 +486,7 @@ pub fn parse_creature_loot(file_path: &Path) -> Result<Vec<CreatureLoot>> {
 /// Parse creature flags from .mon file.
 /// Returns a vector of flag names.
 pub fn parse_creature_flags(text: &str) -> Result<Vec<String>> {
-    let flags_re = Regex::new(r"Flags\s*=\s*\{([^}]+)\}").unwrap();
-
-    if let Some(caps) = flags_re.captures(text) {
-        let content = caps.get(1).unwrap().as_str();
+    if let Some(content) = extract_braced_block(text, "Flags") {
         let flags: Vec<String> = content
             .split(',')
             .map(|s| s.trim().to_string())
@@ -482,10 +501,7 @@ pub fn parse_creature_flags(text: &str) -> Result<Vec<String>> {
 /// Parse creature skills from .mon file.
 /// Returns a vector of (skill_name, skill_value) tuples.
 pub fn parse_creature_skills(text: &str) -> Result<Vec<(String, i32)>> {
-    let skills_section_re = Regex::new(r"Skills\s*=\s*\{([^}]+)\}").unwrap();
-
-    if let Some(caps) = skills_section_re.captures(text) {
-        let content = caps.get(1).unwrap().as_str();
+    if let Some(content) = extract_braced_block(text, "Skills") {
         let mut skills = Vec::new();
 
         // Match pattern: (SkillName, value, ...)
@@ -531,7 +547,8 @@ fn parse_spell_impact_type(impact_str: &str) -> Option<SpellImpactType> {
     }
 }
 
-/// Interpret spell and generate human-readable fields
+/// Interpret spell and generate human-readable fields; the name and category
+/// come from the active [`crate::spell_rules`] ruleset
 fn interpret_spell(
     shape_type: SpellShapeType,
     _shape_params: &[i32],
@@ -547,103 +564,30 @@ fn interpret_spell(
         SpellShapeType::Angle => "Cone",
     }.to_string();
 
-    let (impact_name, spell_category) = match impact_type {
+    let impact_name = match impact_type {
         SpellImpactType::Damage => {
-            let dmg_type = impact_params.get(0).copied().unwrap_or(1);
+            let dmg_type = impact_params.first().copied().unwrap_or(1);
             let dmg_name = damage_type_name(dmg_type);
-            (format!("{} Damage", dmg_name), "Attack".to_string())
+            format!("{} Damage", dmg_name)
         },
-        SpellImpactType::Healing => ("Healing".to_string(), "Heal".to_string()),
+        SpellImpactType::Healing => "Healing".to_string(),
         SpellImpactType::Speed => {
-            let modifier = impact_params.get(0).copied().unwrap_or(0);
+            let modifier = impact_params.first().copied().unwrap_or(0);
             if modifier > 0 {
-                ("Speed Boost".to_string(), "Buff".to_string())
+                "Speed Boost".to_string()
             } else {
-                ("Paralyze".to_string(), "Debuff".to_string())
+                "Paralyze".to_string()
             }
         },
-        SpellImpactType::Summon => ("Summon".to_string(), "Summon".to_string()),
-        SpellImpactType::Field => ("Field".to_string(), "Attack".to_string()),
-        SpellImpactType::Drunken => ("Drunken".to_string(), "Debuff".to_string()),
-        SpellImpactType::Strength => ("Strength Boost".to_string(), "Buff".to_string()),
-        SpellImpactType::Outfit => ("Transform".to_string(), "Buff".to_string()),
+        SpellImpactType::Summon => "Summon".to_string(),
+        SpellImpactType::Field => "Field".to_string(),
+        SpellImpactType::Drunken => "Drunken".to_string(),
+        SpellImpactType::Strength => "Strength Boost".to_string(),
+        SpellImpactType::Outfit => "Transform".to_string(),
     };
 
-    // Generate spell name based on shape + impact combination
-    let spell_name = match (shape_type, impact_type) {
-        // Damage spells
-        (SpellShapeType::Victim, SpellImpactType::Damage) => {
-            let dmg_type = impact_params.get(0).copied().unwrap_or(1);
-            match dmg_type {
-                1 => "Strike",
-                2 => "Poison Strike",
-                4 => "Fire Strike",
-                8 => "Energy Strike",
-                256 => "Life Drain",
-                512 => "Mana Drain",
-                _ => "Magic Strike",
-            }.to_string()
-        },
-        (SpellShapeType::Destination, SpellImpactType::Damage) => {
-            let dmg_type = impact_params.get(0).copied().unwrap_or(1);
-            match dmg_type {
-                4 => "Fireball",
-                8 => "Energy Ball",
-                2 => "Poison Bomb",
-                _ => "Explosion",
-            }.to_string()
-        },
-        (SpellShapeType::Angle, SpellImpactType::Damage) => {
-            let dmg_type = impact_params.get(0).copied().unwrap_or(1);
-            match dmg_type {
-                4 => "Fire Wave",
-                8 => "Energy Wave",
-                2 => "Poison Wave",
-                1 => "Physical Wave",
-                _ => "Magic Wave",
-            }.to_string()
-        },
-        (SpellShapeType::Origin, SpellImpactType::Damage) => {
-            let dmg_type = impact_params.get(0).copied().unwrap_or(1);
-            match dmg_type {
-                4 => "Fire Burst",
-                8 => "Energy Burst",
-                2 => "Poison Burst",
-                32 => "Poison Field (DoT)",
-                64 => "Fire Field (DoT)",
-                128 => "Energy Field (DoT)",
-                _ => "Explosion",
-            }.to_string()
-        },
-
-        // Healing spells
-        (SpellShapeType::Actor, SpellImpactType::Healing) => "Self Heal".to_string(),
-        (SpellShapeType::Victim, SpellImpactType::Healing) => "Heal Other".to_string(),
-        (SpellShapeType::Origin, SpellImpactType::Healing) => "Area Heal".to_string(),
-
-        // Speed spells
-        (SpellShapeType::Actor, SpellImpactType::Speed) => {
-            let modifier = impact_params.get(0).copied().unwrap_or(0);
-            if modifier > 0 { "Haste" } else { "Self Slow" }.to_string()
-        },
-        (SpellShapeType::Victim, SpellImpactType::Speed) => {
-            let modifier = impact_params.get(0).copied().unwrap_or(0);
-            if modifier > 0 { "Speed Boost" } else { "Paralyze" }.to_string()
-        },
-        (SpellShapeType::Origin, SpellImpactType::Speed) => {
-            let modifier = impact_params.get(0).copied().unwrap_or(0);
-            if modifier > 0 { "Mass Haste" } else { "Mass Paralyze" }.to_string()
-        },
-
-        // Summon spells
-        (_, SpellImpactType::Summon) => "Summon Creature".to_string(),
-
-        // Field spells
-        (SpellShapeType::Destination, SpellImpactType::Field) => "Magic Field".to_string(),
-
-        // Other combinations
-        _ => format!("{} {}", shape_name, impact_name),
-    };
+    let (spell_name, spell_category) =
+        crate::spell_rules::spell_rules().interpret(shape_type, impact_type, impact_params, &shape_name, &impact_name);
 
     (spell_name, spell_category, shape_name, impact_name)
 }
@@ -656,12 +600,13 @@ fn extract_spell_details(
     impact_type: SpellImpactType,
     impact_params: &[i32],
 ) -> (Option<i32>, Option<String>, Option<i32>, Option<String>, Option<i32>, Option<i32>,
-      Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>) {
+      Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>,
+      crate::spell_area::SpellArea) {
 
     // Extract range (varies by shape type)
     let range = match shape_type {
-        SpellShapeType::Victim => shape_params.get(0).copied(),
-        SpellShapeType::Destination => shape_params.get(0).copied(),
+        SpellShapeType::Victim => shape_params.first().copied(),
+        SpellShapeType::Destination => shape_params.first().copied(),
         SpellShapeType::Angle => shape_params.get(1).copied(),
         _ => None,
     };
@@ -669,26 +614,26 @@ fn extract_spell_details(
     // Extract area size
     let area_size = match shape_type {
         SpellShapeType::Origin => {
-            let radius = shape_params.get(0).copied().unwrap_or(0);
+            let radius = shape_params.first().copied().unwrap_or(0);
             Some(format!("Radius {}", radius))
         },
         SpellShapeType::Destination => {
-            let param1 = shape_params.get(1).copied().unwrap_or(0);
-            let param2 = shape_params.get(2).copied().unwrap_or(0);
-            Some(format!("{}x{}", param1, param2))
+            // (range, missile, radius, effect)
+            let radius = shape_params.get(2).copied().unwrap_or(0);
+            Some(format!("Radius {}", radius))
         },
         _ => None,
     };
 
     // Extract angle (for cone spells)
     let angle = match shape_type {
-        SpellShapeType::Angle => shape_params.get(0).copied(),
+        SpellShapeType::Angle => shape_params.first().copied(),
         _ => None,
     };
 
     // Extract visual effect IDs
     let (effect_id, missile_effect_id) = match shape_type {
-        SpellShapeType::Actor => (shape_params.get(0).copied(), None),
+        SpellShapeType::Actor => (shape_params.first().copied(), None),
         SpellShapeType::Victim => (
             shape_params.get(2).copied(),  // Hit effect
             shape_params.get(1).copied()   // Missile sprite ID
@@ -703,31 +648,31 @@ fn extract_spell_details(
          duration, summon_race_id, summon_count) = match impact_type {
 
         SpellImpactType::Damage => {
-            let dmg_type_id = impact_params.get(0).copied().unwrap_or(1);
+            let dmg_type_id = impact_params.first().copied().unwrap_or(1);
             let dmg_type = Some(damage_type_name(dmg_type_id));
             let base = impact_params.get(1).copied();
             let var = impact_params.get(2).copied();
-            let min = base.and_then(|b| var.map(|v| b - v));
-            let max = base.and_then(|b| var.map(|v| b + v));
+            let min = base.and_then(|b| var.map(|v| b.saturating_sub(v)));
+            let max = base.and_then(|b| var.map(|v| b.saturating_add(v)));
             (dmg_type, base, var, min, max, None, None, None, None)
         },
 
         SpellImpactType::Healing => {
-            let base = impact_params.get(0).copied();
+            let base = impact_params.first().copied();
             let var = impact_params.get(1).copied();
-            let min = base.and_then(|b| var.map(|v| b - v));
-            let max = base.and_then(|b| var.map(|v| b + v));
+            let min = base.and_then(|b| var.map(|v| b.saturating_sub(v)));
+            let max = base.and_then(|b| var.map(|v| b.saturating_add(v)));
             (None, base, var, min, max, None, None, None, None)
         },
 
         SpellImpactType::Speed => {
-            let modifier = impact_params.get(0).copied();
+            let modifier = impact_params.first().copied();
             let dur = impact_params.get(1).copied();
             (None, None, None, None, None, modifier, dur, None, None)
         },
 
         SpellImpactType::Summon => {
-            let race_id = impact_params.get(0).copied();
+            let race_id = impact_params.first().copied();
             let count = impact_params.get(1).copied();
             (None, None, None, None, None, None, None, race_id, count)
         },
@@ -735,17 +680,17 @@ fn extract_spell_details(
         _ => (None, None, None, None, None, None, None, None, None),
     };
 
+    // Affected tiles, for area overlays
+    let area = crate::spell_area::spell_area(shape_type, shape_params);
+
     (range, area_size, angle, damage_type, base_value, variation, min_value, max_value,
-     speed_modifier, duration, summon_race_id, summon_count, effect_id, missile_effect_id)
+     speed_modifier, duration, summon_race_id, summon_count, effect_id, missile_effect_id, area)
 }
 
 /// Parse creature spells from .mon file.
 /// Returns a vector of CreatureSpell with human-readable interpretation.
 pub fn parse_creature_spells(text: &str) -> Result<Vec<CreatureSpell>> {
-    let spells_section_re = Regex::new(r"Spells\s*=\s*\{([^}]+)\}").unwrap();
-
-    if let Some(caps) = spells_section_re.captures(text) {
-        let content = caps.get(1).unwrap().as_str();
+    if let Some(content) = extract_braced_block(text, "Spells") {
         let spell_re = Regex::new(r"(\w+)\s*\(([^)]*)\)\s*->\s*(\w+)\s*\(([^)]*)\)\s*:\s*(\d+)").unwrap();
         let mut spells = Vec::new();
         let mut spell_order = 0;
@@ -790,7 +735,7 @@ pub fn parse_creature_spells(text: &str) -> Result<Vec<CreatureSpell>> {
 
             // Extract detailed parameters
             let (range, area_size, angle, damage_type, base_value, variation, min_value, max_value,
-                 speed_modifier, duration, summon_race_id, summon_count, effect_id, missile_effect_id) =
+                 speed_modifier, duration, summon_race_id, summon_count, effect_id, missile_effect_id, area) =
                 extract_spell_details(shape_type, &shape_params, impact_type, &impact_params);
 
             let raw_shape_params = serde_json::to_string(&shape_params).unwrap_or_else(|_| "[]".to_string());
@@ -806,6 +751,8 @@ pub fn parse_creature_spells(text: &str) -> Result<Vec<CreatureSpell>> {
                 range,
                 area_size,
                 angle,
+                area_tiles: Some(area.tiles),
+                area_mask: Some(area.mask_json()),
                 impact_type,
                 impact_name: impact_name_readable,
                 damage_type,
@@ -836,7 +783,11 @@ pub fn parse_creature_spells(text: &str) -> Result<Vec<CreatureSpell>> {
 /// Only includes items with "Take" flag, excludes type IDs 1-10.
 pub fn parse_objects_srv(file_path: &Path) -> Result<Vec<Item>> {
     let text = read_utf8_file(file_path)?;
+    parse_objects_srv_str(&text)
+}
 
+/// Parse objects.srv contents.
+pub fn parse_objects_srv_str(text: &str) -> Result<Vec<Item>> {
     let mut items = Vec::new();
 
     // Split by double newlines to get individual object records
@@ -971,10 +922,8 @@ fn title_case_item_name(name: &str) -> String {
         .collect();
 
     // Remove leading "A" or "An" if present
-    if !words.is_empty() {
-        if words[0].eq_ignore_ascii_case("a") || words[0].eq_ignore_ascii_case("an") {
-            words.remove(0);
-        }
+    if !words.is_empty() && (words[0].eq_ignore_ascii_case("a") || words[0].eq_ignore_ascii_case("an")) {
+        words.remove(0);
     }
 
     words.join(" ")
@@ -984,59 +933,133 @@ fn title_case_item_name(name: &str) -> String {
 pub fn parse_npc_file(file_path: &Path) -> Result<Vec<ItemPrice>> {
     let text = read_latin1_file(file_path)?;
     let text = resolve_ndb_includes(&text, file_path);
+    let file_stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("Unknown");
+    parse_npc_str(&text, file_stem)
+}
 
+/// Parse .npc file contents for item prices. `.ndb` includes must already be
+/// resolved; `file_stem` is the fallback NPC name.
+pub fn parse_npc_str(text: &str, file_stem: &str) -> Result<Vec<ItemPrice>> {
     let mut prices = Vec::new();
 
     // Extract NPC name
     let name_re = Regex::new(r#"Name\s*=\s*"([^"]+)""#)
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
 
-    let npc_name = name_re.captures(&text)
+    let npc_name = name_re.captures(text)
         .and_then(|cap| cap.get(1))
         .map(|m| m.as_str().to_string())
-        .unwrap_or_else(|| {
-            file_path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("Unknown")
-                .to_string()
-        });
+        .unwrap_or_else(|| file_stem.to_string());
 
-    // Find all lines with Type= and Price=
-    let type_price_re = Regex::new(r"Type\s*=\s*(\d+).*?Price\s*=\s*(\d+)")
+    let home_re = Regex::new(r"Home\s*=\s*\[\s*(\d+)\s*,\s*(\d+)\s*,\s*(\d+)\s*\]")
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let npc_home = home_re.captures(text).and_then(|caps| {
+        Some((caps[1].parse().ok()?, caps[2].parse().ok()?, caps[3].parse().ok()?))
+    });
 
-    for line in text.lines() {
-        // Skip lines with variable placeholders (%1, %2, etc.) - these are bulk trading templates
-        if line.contains("%1") || line.contains("%2") || line.contains("%3") {
-            continue;
+    let rules: Vec<(Vec<&str>, Vec<&str>)> = text
+        .lines()
+        .filter_map(|line| match split_outside_quotes(line, "->")[..] {
+            [conditions, actions] => Some((npc_rule_tokens(conditions), npc_rule_tokens(actions))),
+            _ => None,
+        })
+        .collect();
+
+    // The "yes" rules of a topic carry the money actions that decide the direction
+    // of the offers leading to it; topics used both ways decide nothing
+    let mut topic_modes: HashMap<i32, Option<&str>> = HashMap::new();
+    for (conditions, actions) in &rules {
+        let topic = conditions.iter().find_map(|c| rule_value(c, "Topic")).and_then(|v| v.parse().ok());
+        if let (Some(topic), Some(mode)) = (topic, trade_mode(actions)) {
+            topic_modes
+                .entry(topic)
+                .and_modify(|known| if *known != Some(mode) { *known = None })
+                .or_insert(Some(mode));
         }
+    }
 
-        if let Some(caps) = type_price_re.captures(line) {
-            let type_id: i32 = caps.get(1)
-                .and_then(|m| m.as_str().parse().ok())
-                .unwrap_or(0);
-            let price: i32 = caps.get(2)
-                .and_then(|m| m.as_str().parse().ok())
-                .unwrap_or(0);
+    for (conditions, actions) in &rules {
+        let value = |key: &str| actions.iter().find_map(|a| rule_value(a, key));
+        let Some(item_id) = value("Type").and_then(|v| v.parse::<i32>().ok()) else {
+            continue;
+        };
+        let Some((price, bulk_price)) = value("Price").and_then(bulk_number) else {
+            continue;
+        };
+        let (amount, bulk_amount) = value("Amount").and_then(bulk_number).unwrap_or((1, false));
+        let data = value("Data").and_then(|v| v.parse().ok());
 
-            // Determine mode: "sell" means NPC is selling (player buying)
-            // No "sell" means NPC is buying (player selling)
-            let mode = if line.to_lowercase().contains("sell") {
-                "sell".to_string()
-            } else {
-                "buy".to_string()
-            };
+        let keyword = |word: &str| conditions.iter().any(|c| c.trim_matches('"').eq_ignore_ascii_case(word) && c.starts_with('"'));
+        let mode = trade_mode(actions)
+            .or_else(|| value("Topic").and_then(|t| t.parse().ok()).and_then(|t: i32| topic_modes.get(&t).copied().flatten()))
+            .unwrap_or(if keyword("sell") { "sell" } else { "buy" });
 
-            prices.push(ItemPrice {
-                item_id: type_id,
-                npc_name: npc_name.clone(),
-                price,
-                mode,
-            });
+        prices.push(ItemPrice {
+            item_id,
+            npc_name: npc_name.clone(),
+            price,
+            mode: mode.to_string(),
+            // Bulk offers are priced per item, the player chooses how many
+            amount: if bulk_amount { 1 } else { amount },
+            data,
+            bulk: bulk_price || bulk_amount,
+            npc_home,
+        });
+    }
+
+    // A bulk offer at the single-item price only extends the single offer
+    let mut merged: Vec<ItemPrice> = Vec::with_capacity(prices.len());
+    for price in prices {
+        let single = merged.iter_mut().find(|p| {
+            price.bulk
+                && !p.bulk
+                && (p.item_id, &p.mode, p.data) == (price.item_id, &price.mode, price.data)
+                && p.price == price.price * p.amount
+        });
+        match single {
+            Some(single) => single.bulk = true,
+            None => merged.push(price),
         }
     }
 
-    Ok(prices)
+    Ok(merged)
+}
+
+/// Comma-separated tokens of one side of an NPC behaviour rule
+fn npc_rule_tokens(text: &str) -> Vec<&str> {
+    split_outside_quotes(text, ",").into_iter().map(str::trim).filter(|t| !t.is_empty()).collect()
+}
+
+/// Value of a `Key=value` token
+fn rule_value<'a>(token: &'a str, key: &str) -> Option<&'a str> {
+    let (name, value) = token.split_once('=')?;
+    (name.trim() == key).then(|| value.trim())
+}
+
+/// Trade direction of a rule's money actions, seen from the player: "buy" when the
+/// NPC takes money and creates the item, "sell" when it deletes the item and pays
+fn trade_mode(actions: &[&str]) -> Option<&'static str> {
+    actions.iter().find_map(|action| {
+        if *action == "DeleteMoney" || action.starts_with("Create(") {
+            Some("buy")
+        } else if *action == "CreateMoney" || action.starts_with("Delete(") {
+            Some("sell")
+        } else {
+            None
+        }
+    })
+}
+
+/// Parse `900`, `%1` or `900*%1`, returning the number and whether it scales with
+/// the amount the player asked for
+fn bulk_number(value: &str) -> Option<(i32, bool)> {
+    let factors: Vec<&str> = value.split('*').map(str::trim).collect();
+    let bulk = factors.contains(&"%1");
+    let mut number = 1i32;
+    for factor in factors.iter().filter(|f| **f != "%1") {
+        number = number.checked_mul(factor.parse().ok()?)?;
+    }
+    Some((number, bulk))
 }
 
 /// Recursively extract all item IDs from a Content block string
@@ -1047,7 +1070,6 @@ fn extract_item_ids_from_content(content_str: &str) -> Vec<i32> {
     let mut item_ids = Vec::new();
     let mut current_num = String::new();
     let mut depth = 0;
-    let mut in_nested_content = false;
     let mut nested_content = String::new();
 
     for ch in content_str.chars() {
@@ -1057,7 +1079,6 @@ fn extract_item_ids_from_content(content_str: &str) -> Vec<i32> {
                     nested_content.push(ch);
                 }
                 depth += 1;
-                in_nested_content = true;
 
                 // Save the current number before entering nested content
                 if !current_num.is_empty() && depth == 1 {
@@ -1078,7 +1099,6 @@ fn extract_item_ids_from_content(content_str: &str) -> Vec<i32> {
                         item_ids.extend(nested_ids);
                         nested_content.clear();
                     }
-                    in_nested_content = false;
                 }
             }
             ',' | ' ' => {
@@ -1089,10 +1109,11 @@ fn extract_item_ids_from_content(content_str: &str) -> Vec<i32> {
                     if !current_num.is_empty() {
                         let trimmed = current_num.trim();
                         // Skip if it contains "Content" or "=" (it's a keyword or property)
-                        if !trimmed.contains("Content") && !trimmed.contains('=') {
-                            if let Ok(id) = trimmed.parse::<i32>() {
-                                item_ids.push(id);
-                            }
+                        if !trimmed.contains("Content")
+                            && !trimmed.contains('=')
+                            && let Ok(id) = trimmed.parse::<i32>()
+                        {
+                            item_ids.push(id);
                         }
                         current_num.clear();
                     }
@@ -1111,10 +1132,11 @@ fn extract_item_ids_from_content(content_str: &str) -> Vec<i32> {
     // Don't forget the last number
     if !current_num.is_empty() {
         let trimmed = current_num.trim();
-        if !trimmed.contains("Content") && !trimmed.contains('=') {
-            if let Ok(id) = trimmed.parse::<i32>() {
-                item_ids.push(id);
-            }
+        if !trimmed.contains("Content")
+            && !trimmed.contains('=')
+            && let Ok(id) = trimmed.parse::<i32>()
+        {
+            item_ids.push(id);
         }
     }
 
@@ -1134,14 +1156,16 @@ pub fn parse_map_sector_file(file_path: &Path) -> Result<Vec<QuestChest>> {
         .and_then(|s| s.to_str())
         .ok_or_else(|| DemonaxError::Parse("Invalid sector filename".to_string()))?;
 
-    let parts: Vec<&str> = sector_name.split('-').collect();
-    if parts.len() != 3 {
-        return Ok(Vec::new()); // Not a valid sector file
-    }
+    parse_map_sector_str(&text, sector_name)
+}
 
-    let sector_x: i32 = parts[0].parse().unwrap_or(0) * 32;
-    let sector_y: i32 = parts[1].parse().unwrap_or(0) * 32;
-    let sector_level: i32 = parts[2].parse().unwrap_or(0);
+/// Parse map sector contents. `sector_name` is the file stem, e.g. "100-200-7".
+pub fn parse_map_sector_str(text: &str, sector_name: &str) -> Result<Vec<QuestChest>> {
+    let Some(sector) = Sector::from_file_stem(sector_name) else {
+        return Ok(Vec::new()); // Not a valid sector file
+    };
+    let origin = sector.origin();
+    let (sector_x, sector_y, sector_level) = (origin.x, origin.y, origin.z);
 
     // Find all lines with ChestQuestNumber
     let chest_lines: Vec<&str> = text
@@ -1162,7 +1186,8 @@ pub fn parse_map_sector_file(file_path: &Path) -> Result<Vec<QuestChest>> {
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
     let key_number_re = Regex::new(r"KeyNumber\s*=\s*(\d+)")
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
-    let content_re = Regex::new(r"Content\s*=\s*\{([^}]+)\}")
+    // A key item: type ID, then attributes up to its KeyNumber
+    let key_item_re = Regex::new(r"[{,]\s*(\d+)\s+(?:\w+\s*=\s*\d+\s+)*KeyNumber\s*=\s*(\d+)")
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
 
     for line in chest_lines {
@@ -1202,7 +1227,7 @@ pub fn parse_map_sector_file(file_path: &Path) -> Result<Vec<QuestChest>> {
                     let mut depth = 0;
                     let mut end_pos = None;
 
-                    for (i, ch) in after_content[brace_start..].chars().enumerate() {
+                    for (i, ch) in after_content[brace_start..].char_indices() {
                         match ch {
                             '{' => depth += 1,
                             '}' => {
@@ -1233,10 +1258,22 @@ pub fn parse_map_sector_file(file_path: &Path) -> Result<Vec<QuestChest>> {
             Vec::new()
         };
 
+        let keys = line
+            .find("ChestQuestNumber")
+            .map(|quest_pos| {
+                key_item_re
+                    .captures_iter(&line[quest_pos..])
+                    .filter_map(|caps| {
+                        Some(KeyItem { type_id: caps[1].parse().ok()?, key_number: caps[2].parse().ok()? })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
         // Calculate in-game coordinates
-        let ingame_x = sector_x + offset_x;
-        let ingame_y = sector_y + offset_y;
-        let ingame_coords = format!("{},{},{}", ingame_x, ingame_y, sector_level);
+        let position = origin.offset(offset_x, offset_y);
+        let (ingame_x, ingame_y) = (position.x, position.y);
+        let ingame_coords = position.to_string();
 
         chests.push(QuestChest {
             quest_value,
@@ -1251,20 +1288,104 @@ pub fn parse_map_sector_file(file_path: &Path) -> Result<Vec<QuestChest>> {
             ingame_x,
             ingame_y,
             ingame_coords,
+            keys,
         });
     }
 
     Ok(chests)
 }
 
+/// Parse the locked, level and quest doors of a map sector file
+pub fn parse_map_doors_file(file_path: &Path) -> Result<Vec<MapDoor>> {
+    let text = read_latin1_file(file_path)?;
+    let sector_name = file_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| DemonaxError::Parse("Invalid sector filename".to_string()))?;
+    parse_map_doors_str(&text, sector_name)
+}
+
+/// Parse doors from map sector contents: items with a `KeyholeNumber`,
+/// `DoorLevel` or `DoorQuestNumber`. `sector_name` is the file stem, e.g. "100-200-7".
+pub fn parse_map_doors_str(text: &str, sector_name: &str) -> Result<Vec<MapDoor>> {
+    let Some(sector) = Sector::from_file_stem(sector_name) else {
+        return Ok(Vec::new());
+    };
+    let origin = sector.origin();
+
+    let coord_re = Regex::new(r"^(\d+)-(\d+):")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    // An item: type ID followed by numeric attributes
+    let item_re = Regex::new(r"[{,]\s*(\d+)((?:\s+\w+\s*=\s*\d+)+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let attribute_re = Regex::new(r"(\w+)\s*=\s*(\d+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+
+    let mut doors = Vec::new();
+    for line in text.lines().filter(|line| {
+        ["KeyholeNumber", "DoorLevel", "DoorQuestNumber"].iter().any(|attribute| line.contains(attribute))
+    }) {
+        let Some(caps) = coord_re.captures(line) else {
+            continue;
+        };
+        let (Ok(offset_x), Ok(offset_y)) = (caps[1].parse::<i32>(), caps[2].parse::<i32>()) else {
+            continue;
+        };
+        let position = origin.offset(offset_x, offset_y);
+
+        for item in item_re.captures_iter(line) {
+            let Ok(type_id) = item[1].parse() else {
+                continue;
+            };
+            let mut door = MapDoor {
+                position: (position.x, position.y, position.z),
+                type_id,
+                keyhole_number: None,
+                level: None,
+                quest_number: None,
+                quest_value: None,
+            };
+            for attribute in attribute_re.captures_iter(&item[2]) {
+                let value = attribute[2].parse().ok();
+                match &attribute[1] {
+                    "KeyholeNumber" => door.keyhole_number = value,
+                    "DoorLevel" => door.level = value,
+                    "DoorQuestNumber" => door.quest_number = value,
+                    "DoorQuestValue" => door.quest_value = value,
+                    _ => {}
+                }
+            }
+            if door.keyhole_number.is_some() || door.level.is_some() || door.quest_number.is_some() {
+                doors.push(door);
+            }
+        }
+    }
+    Ok(doors)
+}
+
 /// Parse magic.cc C++ source file to extract spell definitions
 ///
 /// Looks for CreateSpell() calls and extracts spell metadata including
 /// all properties (Mana, Level, RuneGr, RuneNr, Flags, etc.)
 pub fn parse_magic_cc(file_path: &Path) -> Result<Vec<Spell>> {
     let text = read_utf8_file(file_path)?;
+    parse_magic_cc_str(&text)
+}
 
+/// Parse magic.cc source text.
+///
+/// Spell types and damage elements come from each spell's handler: the
+/// `case <spell id>:` block of the `switch` on the spell number, e.g.
+/// ```text
+/// switch (SpellNr) {
+///     case 19: // Fire Wave
+///         AngleCombat(Actor, Mana, SoulPoints, Damage, EFFECT_FIRE, DAMAGE_FIRE);
+///         break;
+/// ```
+/// Spells without a recognizable handler are classified by their words.
+pub fn parse_magic_cc_str(text: &str) -> Result<Vec<Spell>> {
     let mut spells = Vec::new();
+    let handlers = spell_handlers(text);
 
     let init_spells_start = text.find("static void InitSpells")
         .ok_or_else(|| DemonaxError::Parse("InitSpells function not found".to_string()))?;
@@ -1310,6 +1431,7 @@ pub fn parse_magic_cc(file_path: &Path) -> Result<Vec<Spell>> {
                 charges: None,
                 spell_type: String::new(),
                 premium: false,
+                damage_element: None,
             };
 
             let mut rune_gr: Option<i32> = None;
@@ -1367,15 +1489,20 @@ pub fn parse_magic_cc(file_path: &Path) -> Result<Vec<Spell>> {
                 i += 1;
             }
 
-            if let (Some(gr), Some(nr)) = (rune_gr, rune_nr) {
-                if gr != 0 {
-                    spell.is_rune = true;
-                    spell.rune_type_id = Some(calculate_rune_type_id(gr, nr));
-                }
+            if let (Some(gr), Some(nr)) = (rune_gr, rune_nr)
+                && gr != 0
+            {
+                spell.is_rune = true;
+                spell.rune_type_id = Some(calculate_rune_type_id(gr, nr));
             }
 
-            spell.premium = is_premium(spell.flags);
-            spell.spell_type = classify_spell_by_flags(spell.flags, &spell.words, &spell.name);
+            spell.premium = spell_flag(spell.flags, "premium");
+            let handler = handlers.get(&spell_id).map(|body| classify_spell_handler(body));
+            spell.damage_element = handler.as_ref().and_then(|(_, element)| element.clone());
+            spell.spell_type = match handler {
+                Some((Some(spell_type), _)) => spell_type.to_string(),
+                _ => classify_spell_by_flags(spell.flags, &spell.words, &spell.name),
+            };
 
             spells.push(spell);
         } else {
@@ -1387,29 +1514,122 @@ pub fn parse_magic_cc(file_path: &Path) -> Result<Vec<Spell>> {
 }
 
 /// Calculate rune type ID from RuneGr and RuneNr
-/// Formula: 3147 + RuneNr (based on magic.cc comments)
+/// Formula: 3147 + RuneNr (based on magic.cc comments). Check the result with
+/// [`crate::runes::check_rune_items`], fix it with [`crate::runes::RuneOverrides`].
 fn calculate_rune_type_id(_rune_gr: i32, rune_nr: i32) -> i32 {
-    3147 + rune_nr
+    rune_nr.saturating_add(3147)
+}
+
+/// Handler bodies by spell ID: the `case` blocks of every `switch` on a spell
+/// number. Labels sharing a body (`case 4: case 5:`) all get it.
+fn spell_handlers(text: &str) -> HashMap<i32, &str> {
+    let mut handlers = HashMap::new();
+    let Ok(switch_re) = Regex::new(r"switch\s*\(\s*\w*(?i:spell)\w*\s*\)\s*\{") else {
+        return handlers;
+    };
+    let Ok(case_re) = Regex::new(r"\bcase\s+(\d+)\s*:|\bdefault\s*:") else {
+        return handlers;
+    };
+
+    for switch in switch_re.find_iter(text) {
+        // The switch body runs to the matching closing brace
+        let body_start = switch.end();
+        let mut depth = 1;
+        let mut body_end = text.len();
+        for (i, ch) in text[body_start..].char_indices() {
+            match ch {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                body_end = body_start + i;
+                break;
+            }
+        }
+        let body = &text[body_start..body_end];
+
+        // Labels of nested switches belong to their handler
+        let mut depth = 0;
+        let mut scanned = 0;
+        let labels: Vec<_> = case_re
+            .captures_iter(body)
+            .filter(|caps| {
+                let start = caps.get(0).map_or(0, |m| m.start());
+                for ch in body[scanned..start].chars() {
+                    match ch {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                }
+                scanned = start;
+                depth == 0
+            })
+            .collect();
+        let mut pending = Vec::new();
+        for (index, caps) in labels.iter().enumerate() {
+            let label = caps.get(0).map_or(0..0, |m| m.range());
+            let end = labels.get(index + 1).and_then(|next| next.get(0)).map_or(body.len(), |m| m.start());
+            if let Some(id) = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok()) {
+                pending.push(id);
+            }
+            let block = &body[label.end..end];
+            if !block.trim().is_empty() {
+                for id in pending.drain(..) {
+                    handlers.entry(id).or_insert(block);
+                }
+            }
+        }
+    }
+    handlers
 }
 
-/// Check if spell requires premium account based on flags
-fn is_premium(flags: i32) -> bool {
-    (flags & 0x02) != 0
+/// Spell type and damage element of a spell's handler. The type is `None`
+/// when the handler shows no damage, healing or summoning.
+fn classify_spell_handler(body: &str) -> (Option<&'static str>, Option<String>) {
+    let calls = |pattern: &str| {
+        Regex::new(&format!(r"\b\w*(?:{})\w*\s*\(", pattern)).is_ok_and(|re| re.is_match(body))
+    };
+
+    let element = Regex::new(r"\bDAMAGE_(\w+)").ok().and_then(|re| {
+        re.captures(body).map(|caps| match &caps[1] {
+            "PHYSICAL" => "Physical".to_string(),
+            "POISON" => "Poison".to_string(),
+            "FIRE" => "Fire".to_string(),
+            "ENERGY" => "Energy".to_string(),
+            "LIFEDRAIN" | "LIFE_DRAIN" => "Life Drain".to_string(),
+            "MANADRAIN" | "MANA_DRAIN" => "Mana Drain".to_string(),
+            other => other.to_string(),
+        })
+    });
+
+    let spell_type = if calls("Summon") {
+        Some("summon")
+    } else if element.is_some() {
+        Some(if calls("Mass|Angle|Field|Wall") { "area" } else { "attack" })
+    } else if calls("Heal") {
+        Some("healing")
+    } else {
+        None
+    };
+    (spell_type, element)
 }
 
-/// Classify spell type based on flags and spell words
+/// Classify spell type based on flags and spell words. Fallback for spells
+/// whose handler in magic.cc isn't recognized.
 fn classify_spell_by_flags(flags: i32, words: &str, _name: &str) -> String {
-    // Flag 0x01: Aggressive spells (check first, takes priority)
-    if flags & 0x01 != 0 {
+    // Aggressive spells (check first, takes priority)
+    if spell_flag(flags, "aggressive") {
         if words.contains("mas") || words.contains("grav") {
             return "area".to_string();
         }
         return "attack".to_string();
     }
 
-    // Flag 0x08: Healing spells (minimum 100% multiplier)
+    // Healing spells (minimum 100% multiplier)
     // Only classify as healing if not aggressive
-    if flags & 0x08 != 0 || words.contains("ura") {
+    if spell_flag(flags, "full_multiplier") || words.contains("ura") {
         return "healing".to_string();
     }
 
@@ -1438,14 +1658,18 @@ fn classify_spell_by_flags(flags: i32, words: &str, _name: &str) -> String {
 pub fn parse_npc_spell_teaching(file_path: &Path) -> Result<Vec<SpellTeacher>> {
     let text = read_latin1_file(file_path)?;
     let text = resolve_ndb_includes(&text, file_path);
+    parse_npc_spell_teaching_str(&text)
+}
 
+/// Parse .npc file contents for spell teaching. `.ndb` includes must already be resolved.
+pub fn parse_npc_spell_teaching_str(text: &str) -> Result<Vec<SpellTeacher>> {
     let mut teachers = Vec::new();
 
     // Extract NPC name
     let name_re = Regex::new(r#"Name\s*=\s*"([^"]+)""#)
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
 
-    let npc_name = name_re.captures(&text)
+    let npc_name = name_re.captures(text)
         .and_then(|cap| cap.get(1))
         .map(|m| m.as_str().to_string())
         .unwrap_or_else(|| "Unknown".to_string());
@@ -1460,13 +1684,13 @@ pub fn parse_npc_spell_teaching(file_path: &Path) -> Result<Vec<SpellTeacher>> {
         })
         .collect();
 
+    let type_re = Regex::new(r"Type\s*=\s*(\d+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let price_re = Regex::new(r"Price\s*=\s*(\d+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+
     for line in spell_lines {
         // Extract Type and Price
-        let type_re = Regex::new(r"Type\s*=\s*(\d+)")
-            .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
-        let price_re = Regex::new(r"Price\s*=\s*(\d+)")
-            .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
-
         let spell_id = type_re.captures(line)
             .and_then(|c| c.get(1))
             .and_then(|m| m.as_str().parse().ok())
@@ -1477,8 +1701,18 @@ pub fn parse_npc_spell_teaching(file_path: &Path) -> Result<Vec<SpellTeacher>> {
             .and_then(|m| m.as_str().parse().ok())
             .unwrap_or(0);
 
-        // Determine vocation(s)
-        let vocations = extract_vocations_from_line(line);
+        // Determine vocation(s) from the behaviour condition
+        let Some(vocations) = parse_vocation_condition(line) else {
+            teachers.push(SpellTeacher {
+                npc_name: npc_name.clone(),
+                spell_id,
+                vocation: "Unknown".to_string(),
+                teaching_price,
+                level_required: None,
+                unclassified_line: Some(line.trim().to_string()),
+            });
+            continue;
+        };
 
         for vocation in vocations {
             teachers.push(SpellTeacher {
@@ -1487,6 +1721,7 @@ pub fn parse_npc_spell_teaching(file_path: &Path) -> Result<Vec<SpellTeacher>> {
                 vocation,
                 teaching_price,
                 level_required: None,  // Will be populated during DB insertion
+                unclassified_line: None,
             });
         }
     }
@@ -1494,35 +1729,79 @@ pub fn parse_npc_spell_teaching(file_path: &Path) -> Result<Vec<SpellTeacher>> {
     Ok(teachers)
 }
 
-/// Extract vocations from a spell teaching line
-fn extract_vocations_from_line(line: &str) -> Vec<String> {
-    let mut vocations = Vec::new();
+const VOCATIONS: [&str; 4] = ["Knight", "Paladin", "Druid", "Sorcerer"];
 
-    // Check for explicit vocation patterns
-    if line.contains("Knight,") || line.to_lowercase().contains("knight,") {
-        vocations.push("Knight".to_string());
-    }
-    if line.contains("Paladin,") || line.to_lowercase().contains("paladin,") {
-        vocations.push("Paladin".to_string());
-    }
-    if line.contains("Druid,") || line.to_lowercase().contains("druid,") {
-        vocations.push("Druid".to_string());
-    }
-    if line.contains("Sorcerer,") || line.to_lowercase().contains("sorcerer,") {
-        vocations.push("Sorcerer".to_string());
+/// Bare condition flags that are not profession checks
+const NON_PROFESSION_FLAGS: [&str; 7] = ["Premium", "PZBlock", "Male", "Female", "Burning", "Poison", "Drunk"];
+
+/// Resolve the vocations a behaviour line applies to from its condition.
+///
+/// NPC behaviour lines have the form `condition,condition,... -> actions`,
+/// where conditions are quoted keywords (`"learn"`), profession checks
+/// (`Knight`, `!Druid`), flags (`Premium`) or expressions (`Topic=2`,
+/// `Level<SpellLevel(String)`). Returns `None` if the line has no `->`
+/// or contains a bare condition that is not understood.
+pub fn parse_vocation_condition(line: &str) -> Option<Vec<String>> {
+    let parts = split_outside_quotes(line, "->");
+    if parts.len() < 2 {
+        return None;
     }
+    let condition = parts[0];
 
-    // If no specific vocation found, assume all vocations
-    if vocations.is_empty() {
-        vocations = vec![
-            "Knight".to_string(),
-            "Paladin".to_string(),
-            "Druid".to_string(),
-            "Sorcerer".to_string(),
-        ];
+    let mut required = Vec::new();
+    let mut excluded = Vec::new();
+
+    for token in split_outside_quotes(condition, ",") {
+        let token = token.trim();
+        if token.is_empty() || token.starts_with('"') {
+            continue;
+        }
+
+        let (negated, name) = match token.strip_prefix('!') {
+            Some(rest) => (true, rest.trim()),
+            None => (false, token),
+        };
+
+        if let Some(vocation) = VOCATIONS.iter().find(|v| v.eq_ignore_ascii_case(name)) {
+            if negated {
+                excluded.push(*vocation);
+            } else {
+                required.push(*vocation);
+            }
+        } else if name.chars().all(|c| c.is_ascii_alphanumeric())
+            && !NON_PROFESSION_FLAGS.iter().any(|f| f.eq_ignore_ascii_case(name))
+        {
+            // Unknown bare identifier, possibly a profession check we don't understand
+            return None;
+        }
     }
 
-    vocations
+    let candidates: Vec<&str> = if required.is_empty() { VOCATIONS.to_vec() } else { required };
+    Some(
+        candidates
+            .into_iter()
+            .filter(|v| !excluded.contains(v))
+            .map(|v| v.to_string())
+            .collect(),
+    )
+}
+
+/// Split text on a separator that is not inside a double-quoted string.
+fn split_outside_quotes<'a>(text: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, ch) in text.char_indices() {
+        if ch == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && text[i..].starts_with(separator) && i >= start {
+            parts.push(&text[start..i]);
+            start = i + separator.len();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
 }
 
 /// Parse .npc files to extract rune/wand/rod seller data
@@ -1532,28 +1811,34 @@ fn extract_vocations_from_line(line: &str) -> Vec<String> {
 pub fn parse_npc_rune_selling(file_path: &Path) -> Result<Vec<RuneSeller>> {
     let text = read_latin1_file(file_path)?;
     let text = resolve_ndb_includes(&text, file_path);
+    let file_name = file_path.file_name().and_then(|s| s.to_str()).unwrap_or("Unknown");
+    parse_npc_rune_selling_str(&text, file_name)
+}
+
+/// Parse .npc file contents for rune/wand/rod offers. `.ndb` includes must
+/// already be resolved; `file_name` carries the account type marker
+/// (`-free-`, `-prem-`, `-max-`) and its stem is the fallback NPC name.
+pub fn parse_npc_rune_selling_str(text: &str, file_name: &str) -> Result<Vec<RuneSeller>> {
     let mut sellers = Vec::new();
 
     // Extract NPC name (same pattern as spell teaching)
     let name_re = Regex::new(r#"Name\s*=\s*"([^"]+)""#)
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
 
-    let npc_name = name_re.captures(&text)
+    let npc_name = name_re.captures(text)
         .and_then(|cap| cap.get(1))
         .map(|m| m.as_str().to_string())
         .unwrap_or_else(|| {
-            file_path.file_stem()
+            Path::new(file_name).file_stem()
                 .and_then(|s| s.to_str())
                 .unwrap_or("Unknown")
                 .to_string()
         });
 
     // Account type from filename
-    let account_type = if let Some(name) = file_path.file_name().and_then(|s| s.to_str()) {
-        if name.contains("-free-") { Some("Free".to_string()) }
-        else if name.contains("-prem-") || name.contains("-max-") { Some("Premium".to_string()) }
-        else { None }
-    } else { None };
+    let account_type = if file_name.contains("-free-") { Some("Free".to_string()) }
+        else if file_name.contains("-prem-") || file_name.contains("-max-") { Some("Premium".to_string()) }
+        else { None };
 
     // Find selling lines: contains (rune OR wand OR rod) AND Type= AND Price=
     // Skip bulk purchase lines (contain %1)
@@ -1565,15 +1850,15 @@ pub fn parse_npc_rune_selling(file_path: &Path) -> Result<Vec<RuneSeller>> {
         })
         .collect();
 
-    for line in selling_lines {
-        // Extract Type, Price, Data using regex
-        let type_re = Regex::new(r"Type\s*=\s*(\d+)")
-            .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
-        let price_re = Regex::new(r"Price\s*=\s*(\d+)")
-            .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
-        let data_re = Regex::new(r"Data\s*=\s*(\d+)")
-            .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    // Extract Type, Price, Data using regex
+    let type_re = Regex::new(r"Type\s*=\s*(\d+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let price_re = Regex::new(r"Price\s*=\s*(\d+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let data_re = Regex::new(r"Data\s*=\s*(\d+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
 
+    for line in selling_lines {
         let item_id = match type_re.captures(line)
             .and_then(|c| c.get(1))
             .and_then(|m| m.as_str().parse().ok()) {
@@ -1626,11 +1911,12 @@ pub fn parse_npc_rune_selling(file_path: &Path) -> Result<Vec<RuneSeller>> {
 fn extract_rune_vocations(line: &str) -> Vec<Option<String>> {
     let lower = line.to_lowercase();
 
-    // Check line prefix (e.g., 'sorcerer,"wand"...')
-    if lower.starts_with("sorcerer,") { return vec![Some("Sorcerer".to_string())]; }
-    if lower.starts_with("druid,") { return vec![Some("Druid".to_string())]; }
-    if lower.starts_with("knight,") { return vec![Some("Knight".to_string())]; }
-    if lower.starts_with("paladin,") { return vec![Some("Paladin".to_string())]; }
+    // Check profession conditions (e.g., 'sorcerer,"wand"...')
+    if let Some(vocations) = parse_vocation_condition(line)
+        && vocations.len() < VOCATIONS.len()
+    {
+        return vocations.into_iter().map(Some).collect();
+    }
 
     // Check description text
     if lower.contains("only for sorcerer") { return vec![Some("Sorcerer".to_string())]; }
@@ -1647,18 +1933,22 @@ fn extract_rune_vocations(line: &str) -> Vec<Option<String>> {
 /// Extracts raid information including type, interval, messages, and creature spawns
 pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
     let text = read_latin1_file(file_path)?;
-
     let name = file_path
         .file_stem()
         .and_then(|s| s.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+        .unwrap_or("unknown");
+    parse_evt_str(&text, name)
+}
+
+/// Parse .evt file contents. `name` is the raid name (the file stem).
+pub fn parse_evt_str(text: &str, name: &str) -> Result<Raid> {
+    let name = name.to_string();
 
     // Extract Type
     let type_re = Regex::new(r"(?m)^Type\s*=\s*(.+)$")
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
     let raid_type = type_re
-        .captures(&text)
+        .captures(text)
         .and_then(|c| c.get(1))
         .map(|m| m.as_str().trim().to_string())
         .unwrap_or_else(|| "unknown".to_string());
@@ -1667,7 +1957,7 @@ pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
     let interval_re = Regex::new(r"(?m)^Interval\s*=\s*(\d+)")
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
     let interval_seconds = interval_re
-        .captures(&text)
+        .captures(text)
         .and_then(|c| c.get(1))
         .and_then(|m| m.as_str().parse::<f64>().ok());
 
@@ -1676,7 +1966,7 @@ pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
     // Extract waves from "# Process:" comment
     let process_re = Regex::new(r"(?mi)^#\s*Process:.*")
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
-    let waves = if let Some(process_match) = process_re.find(&text) {
+    let waves = if let Some(process_match) = process_re.find(text) {
         let process_text = process_match.as_str().to_lowercase();
 
         if process_text.contains("one") {
@@ -1710,7 +2000,7 @@ pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
     let message_re = Regex::new(r#"(?m)^Message\s*=\s*"?([^"\n]+)"?"#)
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
     let messages: Vec<String> = message_re
-        .captures_iter(&text)
+        .captures_iter(text)
         .filter_map(|c| c.get(1).map(|m| m.as_str().trim().to_string()))
         .collect();
     let message = messages.join("; ");
@@ -1722,12 +2012,12 @@ pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
         .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
 
     let races: Vec<i32> = race_re
-        .captures_iter(&text)
+        .captures_iter(text)
         .filter_map(|c| c.get(1).and_then(|m| m.as_str().parse().ok()))
         .collect();
 
     let counts: Vec<(i32, i32)> = count_re
-        .captures_iter(&text)
+        .captures_iter(text)
         .filter_map(|c| {
             let min = c.get(1).and_then(|m| m.as_str().parse().ok())?;
             let max = c.get(2).and_then(|m| m.as_str().parse().ok())?;
@@ -1739,8 +2029,8 @@ pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
     let mut spawn_map: HashMap<i32, (i32, i32)> = HashMap::new();
     for (race, (min, max)) in races.iter().zip(counts.iter()) {
         let entry = spawn_map.entry(*race).or_insert((0, 0));
-        entry.0 += min;
-        entry.1 += max;
+        entry.0 = entry.0.saturating_add(*min);
+        entry.1 = entry.1.saturating_add(*max);
     }
 
     // Convert to JSON
@@ -1774,6 +2064,8 @@ pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
             .join(", ")
     };
 
+    let position = parse_evt_steps(text)?.into_iter().find_map(|step| step.position);
+
     Ok(Raid {
         name,
         raid_type,
@@ -1783,6 +2075,255 @@ pub fn parse_evt_file(file_path: &Path) -> Result<Raid> {
         message,
         creatures,
         spawn_composition_json,
+        season: None,
+        enabled: true,
+        position,
+    })
+}
+
+/// Parse the steps of a .evt raid script in file order.
+///
+/// Every `Delay = <seconds>` line starts a new step; the `Message`, `Position`,
+/// `Spread`, `Race` and `Count` lines after it belong to that step. Lines
+/// before the first `Delay` (Type, Interval, ...) are the raid header.
+pub fn parse_evt_steps(text: &str) -> Result<Vec<RaidStep>> {
+    let key_re = Regex::new(r"^(\w+)\s*=\s*(.*)$")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let numbers_re = Regex::new(r"-?\d+")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let numbers = |value: &str| -> Vec<i32> {
+        numbers_re.find_iter(value).filter_map(|m| m.as_str().parse().ok()).collect()
+    };
+
+    let mut steps: Vec<RaidStep> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some(caps) = key_re.captures(line) else {
+            continue;
+        };
+        let value = caps[2].trim();
+
+        if &caps[1] == "Delay" {
+            steps.push(RaidStep {
+                delay_seconds: value.parse().unwrap_or(0),
+                message: None,
+                position: None,
+                spread: None,
+                race: None,
+                count: None,
+            });
+            continue;
+        }
+        let Some(step) = steps.last_mut() else {
+            continue;
+        };
+        match &caps[1] {
+            "Message" => step.message = Some(value.trim_matches('"').to_string()),
+            "Position" => {
+                if let [x, y, z] = numbers(value)[..] {
+                    step.position = Some((x, y, z));
+                }
+            }
+            "Spread" => step.spread = value.parse().ok(),
+            "Race" => step.race = value.parse().ok(),
+            "Count" => {
+                if let [min, max] = numbers(value)[..] {
+                    step.count = Some((min, max));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(steps)
+}
+
+/// Parse the game server's raid log: when raids actually started
+pub fn parse_raid_log(file_path: &Path) -> Result<Vec<RaidExecution>> {
+    let text = read_latin1_file(file_path)?;
+    parse_raid_log_str(&text)
+}
+
+/// Parse raid log contents. A raid start is a line with a timestamp
+/// (`2026-10-14 18:03:12` or `Tue Oct 14 18:03:12 2026`) followed by
+/// `Raid <name> started` (also `begins`, `executed` or `fired`, the name
+/// optionally quoted); `<name>` is the .evt file stem. Other lines are skipped,
+/// so the general game log can be given as well.
+pub fn parse_raid_log_str(text: &str) -> Result<Vec<RaidExecution>> {
+    let line_re = Regex::new(
+        r#"(?i)^\W*(\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}|[a-z]{3} [a-z]{3} +\d{1,2} \d{2}:\d{2}:\d{2} \d{4})\W.*?\braid\s+"?([\w-]+)"?\s+(?:started|begins|executed|fired)"#,
+    )
+    .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+
+    let mut executions = Vec::new();
+    for line in text.lines() {
+        let Some(caps) = line_re.captures(line) else {
+            continue;
+        };
+        let started_at = crate::logs::parse_log_timestamp(&caps[1])
+            .ok_or_else(|| DemonaxError::Parse(format!("Invalid raid log timestamp {:?}", &caps[1])))?;
+        executions.push(RaidExecution {
+            raid_name: caps[2].to_lowercase(),
+            started_at: started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        });
+    }
+    Ok(executions)
+}
+
+/// Parse dat/monster.db, the creature spawns of the map
+pub fn parse_monster_db(file_path: &Path) -> Result<Vec<Spawn>> {
+    let text = read_latin1_file(file_path)?;
+    parse_monster_db_str(&text)
+}
+
+/// Parse monster.db contents: one spawn per line as `race x y z radius amount regen`,
+/// with `#` starting a comment. Lines without exactly seven numbers (such as the
+/// closing `0`) are skipped.
+pub fn parse_monster_db_str(text: &str) -> Result<Vec<Spawn>> {
+    let spawns = text
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next().unwrap_or_default();
+            let numbers: Vec<i32> = line.split_whitespace().map(|field| field.parse().ok()).collect::<Option<_>>()?;
+            match numbers[..] {
+                [race, x, y, z, radius, amount, regen_seconds] => {
+                    Some(Spawn { race, position: (x, y, z), radius, amount, regen_seconds })
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(spawns)
+}
+
+/// Parse dat/houses.dat together with owners.dat and houseareas.dat from the same directory.
+///
+/// Missing owners.dat or houseareas.dat is not an error: houses are then
+/// reported as unowned and their town as "Area <n>".
+pub fn parse_house_files(houses_path: &Path) -> Result<Vec<House>> {
+    let houses_text = read_latin1_file(houses_path)?;
+    let dir = houses_path.parent().unwrap_or(Path::new("."));
+
+    let read_optional = |name: &str| -> Result<Option<String>> {
+        let path = dir.join(name);
+        if path.exists() {
+            Ok(Some(read_latin1_file(&path)?))
+        } else {
+            Ok(None)
+        }
+    };
+    let owners_text = read_optional("owners.dat")?;
+    let areas_text = read_optional("houseareas.dat")?;
+
+    parse_houses_str(&houses_text, owners_text.as_deref(), areas_text.as_deref())
+}
+
+/// Parse house definitions, owner records and house areas.
+///
+/// houses.dat holds one record per house starting at `ID = <n>`:
+/// ```text
+/// ID = 101
+/// Name = "Market Street 1"
+/// RentOffset = 200
+/// Area = 1
+/// GuildHouse = false
+/// Fields = {[32368,32240,7],[32369,32240,7]}
+/// ```
+/// owners.dat holds `ID`, `Owner` (player ID, 0 = unowned) and `LastPayment`
+/// (unix time) records; houseareas.dat holds `Area = (id,"Town",price,depot)` lines.
+pub fn parse_houses_str(houses_text: &str, owners_text: Option<&str>, areas_text: Option<&str>) -> Result<Vec<House>> {
+    // Area id -> (town name, price per field)
+    let mut areas: HashMap<i32, (String, i32)> = HashMap::new();
+    if let Some(text) = areas_text {
+        let area_re = Regex::new(r#"Area\s*=\s*\(\s*(\d+)\s*,\s*"([^"]*)"\s*,\s*(\d+)"#)
+            .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+        for caps in area_re.captures_iter(text) {
+            if let (Ok(id), Ok(price)) = (caps[1].parse::<i32>(), caps[3].parse::<i32>()) {
+                areas.insert(id, (caps[2].to_string(), price));
+            }
+        }
+    }
+
+    // House id -> (owner id, last payment)
+    let mut owners: HashMap<i32, (Option<i32>, Option<i64>)> = HashMap::new();
+    if let Some(text) = owners_text {
+        for record in split_id_records(text) {
+            let Some(house_id) = record_value(record, "ID").and_then(|v| v.parse().ok()) else {
+                continue;
+            };
+            let owner_id = record_value(record, "Owner")
+                .and_then(|v| v.parse::<i32>().ok())
+                .filter(|id| *id > 0);
+            let last_payment = record_value(record, "LastPayment")
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|t| *t > 0);
+            owners.insert(house_id, (owner_id, last_payment));
+        }
+    }
+
+    let mut houses = Vec::new();
+    for record in split_id_records(houses_text) {
+        let Some(house_id) = record_value(record, "ID").and_then(|v| v.parse::<i32>().ok()) else {
+            continue;
+        };
+        let name = record_value(record, "Name")
+            .map(|v| v.trim_matches('"').to_string())
+            .unwrap_or_else(|| format!("House {}", house_id));
+        let rent_offset: i32 = record_value(record, "RentOffset").and_then(|v| v.parse().ok()).unwrap_or(0);
+        let area_id: i32 = record_value(record, "Area").and_then(|v| v.parse().ok()).unwrap_or(0);
+        let guild_house = record_value(record, "GuildHouse").is_some_and(|v| v.eq_ignore_ascii_case("true"));
+        let fields = extract_braced_block(record, "Fields");
+        let size = fields.map(|fields| fields.matches('[').count() as i32).unwrap_or(0);
+        let position = fields.and_then(|fields| {
+            let (start, end) = (fields.find('[')?, fields.find(']')?);
+            let coords: Vec<i32> = fields.get(start + 1..end)?.split(',').filter_map(|c| c.trim().parse().ok()).collect();
+            match coords[..] {
+                [x, y, z] => Some((x, y, z)),
+                _ => None,
+            }
+        });
+
+        let (town, price_per_field) = match areas.get(&area_id) {
+            Some((town, price)) => (town.clone(), *price),
+            None => (format!("Area {}", area_id), 0),
+        };
+        let rent = rent_offset.saturating_add(size.saturating_mul(price_per_field));
+        let (owner_id, last_payment) = owners.get(&house_id).copied().unwrap_or((None, None));
+
+        houses.push(House {
+            house_id,
+            name,
+            town,
+            rent,
+            size,
+            guild_house,
+            owner_id,
+            last_payment,
+            position,
+        });
+    }
+
+    Ok(houses)
+}
+
+/// Split a file of `ID = <n>` records into one slice per record.
+fn split_id_records(text: &str) -> Vec<&str> {
+    let id_re = Regex::new(r"(?m)^\s*ID\s*=").unwrap();
+    let starts: Vec<usize> = id_re.find_iter(text).map(|m| m.start()).collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| &text[start..starts.get(i + 1).copied().unwrap_or(text.len())])
+        .collect()
+}
+
+/// Value of a `Key = value` line within a record.
+fn record_value<'a>(record: &'a str, key: &str) -> Option<&'a str> {
+    record.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key).then(|| v.trim())
     })
 }
 
@@ -1791,6 +2332,77 @@ mod tests {
     use super::*;
     use std::io::Write;
 
+    /// Boss creature with multi-line blocks and a nested brace inside the spell list.
+    const BOSS_MON: &str = r#"
+Name        = "Ghazbaran"
+Article     = ""
+RaceNumber  = 298
+Flags       = {KickBoxes, KickCreatures,
+               SeeInvisible, Unpushable,
+               DistanceFighting}
+Skills      = {(HitPoints, 60000, 0, 60000, 0, 0, 0),
+               (GoStrength, 90, 0, 90, 0, 0, 0),
+               (FistFighting, 150, 150, 150, 100, 1500, 1)
+              }
+Spells      = {Actor (13) -> Outfit ({0, 0, 0, 0, 0}, 20) : 15,
+               Victim (7, 0, 13) -> Damage (8, 800, 200) : 6,
+               Origin (6, 13) -> Damage (1, 650, 150) : 3
+              }
+Inventory   = {(3031, 100, 999), (3035, 20, 500),
+               (3361, 1, 30)}
+"#;
+
+    #[test]
+    fn test_extract_braced_block_nested() {
+        let text = "Spells = {A ({1, 2}) -> B (3) : 4,\n C (5) -> D (6) : 7\n}\nInventory = {}";
+        let block = extract_braced_block(text, "Spells").unwrap();
+        assert!(block.contains("C (5) -> D (6) : 7"));
+        assert!(!block.contains("Inventory"));
+        assert_eq!(extract_braced_block(text, "Inventory"), Some(""));
+        assert_eq!(extract_braced_block("Spells = {A (1", "Spells"), None);
+    }
+
+    #[test]
+    fn test_parse_multiline_boss_flags() {
+        let flags = parse_creature_flags(BOSS_MON).unwrap();
+        assert_eq!(
+            flags,
+            vec!["KickBoxes", "KickCreatures", "SeeInvisible", "Unpushable", "DistanceFighting"]
+        );
+    }
+
+    #[test]
+    fn test_parse_multiline_boss_skills() {
+        let skills = parse_creature_skills(BOSS_MON).unwrap();
+        assert_eq!(skills.len(), 3);
+        assert_eq!(skills[0], ("HitPoints".to_string(), 60000));
+        assert_eq!(skills[2], ("FistFighting".to_string(), 150));
+    }
+
+    #[test]
+    fn test_parse_multiline_boss_spells_after_nested_brace() {
+        let spells = parse_creature_spells(BOSS_MON).unwrap();
+        assert_eq!(spells.len(), 3, "Spells after a nested brace must not be dropped");
+        assert_eq!(spells[0].impact_type, SpellImpactType::Outfit);
+        assert_eq!(spells[1].spell_name, "Energy Strike");
+        assert_eq!(spells[1].base_value, Some(800));
+        assert_eq!(spells[2].priority, 3);
+        assert_eq!(spells[2].area_size.as_deref(), Some("Radius 6"));
+    }
+
+    #[test]
+    fn test_parse_multiline_boss_loot() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("ghazbaran.mon");
+        std::fs::write(&file_path, BOSS_MON).unwrap();
+
+        let loot = parse_creature_loot(&file_path).unwrap();
+        assert_eq!(loot.len(), 3);
+        assert_eq!(loot[0].item_id, 3031);
+        assert_eq!(loot[0].max_amount, 100);
+        assert_eq!(loot[2].item_id, 3361);
+    }
+
     #[test]
     fn test_parse_objects_srv_single_flag() {
         let content = r#"
@@ -1875,13 +2487,14 @@ Flags       = {Take}
     }
 
     #[test]
-    fn test_parse_npc_file_filters_variable_amount() {
+    fn test_parse_npc_file_merges_bulk_offers() {
         let content = r#"
 Name = "Winston"
 
 "sell","war","drum" -> Type=2966, Amount=1, Price=900000
 "sell",%1,1<%1,"war","drum" -> Type=2966, Amount=%1, Price=900000*%1
 "buy","life","ring" -> Type=3052, Amount=1, Price=900
+"sell",%1,1<%1,"bolt" -> Type=3446, Amount=%1, Price=4*%1
 "#;
 
         let temp_dir = tempfile::tempdir().unwrap();
@@ -1891,10 +2504,8 @@ Name = "Winston"
 
         let prices = parse_npc_file(&file_path).unwrap();
 
-        // Should only have 2 entries (war drum single and life ring)
-        assert_eq!(prices.len(), 2, "Should have 2 entries, not 3");
-
-        // Check war drum only appears once
+        // The bulk war drum offer extends the single one
+        assert_eq!(prices.len(), 3, "Should have 3 entries, not 4");
         let war_drum_count = prices.iter().filter(|p| p.item_id == 2966).count();
         assert_eq!(war_drum_count, 1, "War drum should only appear once");
 
@@ -1907,7 +2518,283 @@ Name = "Winston"
         assert_eq!(prices[1].item_id, 3052);
 
         // Verify correct prices
-        assert_eq!(prices[0].price, 900000);
-        assert_eq!(prices[1].price, 900);
+        assert_eq!((prices[0].price, prices[0].bulk), (900000, true));
+        assert_eq!((prices[1].price, prices[1].bulk), (900, false));
+
+        // Bulk-only offers are priced per item
+        assert_eq!((prices[2].item_id, prices[2].price, prices[2].amount, prices[2].bulk), (3446, 4, 1, true));
+    }
+
+    #[test]
+    fn test_parse_npc_trade_direction_from_money_actions() {
+        let content = r#"
+Name = "Frodo"
+Behaviour = {
+"sell","sword" -> "I don't sell that."
+"torch" -> Type=2920, Amount=1, Price=2, "Do you want to buy a torch for %P gold?", Topic=1
+"sword" -> Type=3264, Amount=1, Price=25, "Do you want to sell a sword for %P gold? I don't sell them.", Topic=2
+"vial","of","water" -> Type=2874, Data=1, Amount=1, Price=10, "Do you want to buy a vial of water?", Topic=1
+"arrows" -> Type=3447, Amount=10, Price=20, "Ten arrows for %P gold, I sell them cheap.", DeleteMoney, Create(Type)
+
+Topic=1,"yes",CountMoney>=Price -> "Here you are.", DeleteMoney, Create(Type)
+Topic=2,"yes",Count(Type)>=Amount -> "Ok. Here is your money.", Delete(Type), CreateMoney
+}
+"#;
+        let prices = parse_npc_str(content, "frodo").unwrap();
+        let modes: Vec<(i32, &str)> = prices.iter().map(|p| (p.item_id, p.mode.as_str())).collect();
+        assert_eq!(modes, vec![(2920, "buy"), (3264, "sell"), (2874, "buy"), (3447, "buy")]);
+
+        assert_eq!(prices[2].data, Some(1));
+        assert_eq!((prices[3].price, prices[3].amount), (20, 10));
+    }
+
+    #[test]
+    fn test_parse_equipment_named_slots() {
+        let text = "Inventory = {1 Content={3354},\n3 Content={2854 Content={3031 Amount=40}},\n9 Content={3052 Charges=12},\n10 Content={3447 Amount=50},\n11 Content={3031}}";
+        let equipment = parse_equipment(text);
+        assert_eq!(equipment.helmet, Some(EquipmentItem::new(3354)));
+        // Amounts inside a container belong to its contents, not the container
+        assert_eq!(equipment.backpack, Some(EquipmentItem::new(2854)));
+        assert_eq!(equipment.ring.and_then(|item| item.charges), Some(12));
+        assert_eq!(equipment.ammo.and_then(|item| item.count()), Some(50));
+        assert_eq!(equipment.armor, None);
+
+        let json = serde_json::to_string(&equipment).unwrap();
+        assert!(json.starts_with(r#"{"version":2,"slots":[{"type_id":3354},null,{"type_id":2854}"#));
+        assert!(json.ends_with(r#"{"type_id":3052,"charges":12},{"type_id":3447,"amount":50}]}"#));
+        assert_eq!(serde_json::from_str::<Equipment>(&json).unwrap(), equipment);
+
+        // Snapshots stored before the versioned format are still readable
+        let legacy: Equipment = serde_json::from_str("[3354,-1,2854,-1,-1,-1,-1,-1,-1,3447]").unwrap();
+        assert_eq!(legacy.ammo, Some(EquipmentItem::new(3447)));
+        assert_eq!(legacy.armor, None);
+        assert!(serde_json::from_str::<Equipment>(r#"{"version":9,"slots":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_truncated_inputs_do_not_panic() {
+        let sector = "12-5: Content={4526, 2472 ChestQuestNumber=100 KeyNumber=7 Content={2853 Content={3031}, 3354}}";
+        let spells = "static void InitSpells() {\n    Spell = CreateSpell(1, \"exura\");\n    Spell->Mana = 20;\n";
+        let raid = "# Process: two waves\nType = BigRaid\nInterval = 86400\nRace = 5\nCount = (3, 5)\n";
+
+        // Truncated files (cut at a char boundary) must parse or fail cleanly
+        for text in [BOSS_MON, sector, spells, raid] {
+            for (end, _) in text.char_indices().step_by(7) {
+                let prefix = &text[..end];
+                let _ = parse_usr_str(prefix, "truncated.usr");
+                let _ = parse_mon_str(prefix, "truncated");
+                let _ = parse_creature_loot_str(prefix);
+                let _ = parse_creature_skills(prefix);
+                let _ = parse_creature_spells(prefix);
+                let _ = parse_objects_srv_str(prefix);
+                let _ = parse_npc_str(prefix, "truncated");
+                let _ = parse_map_sector_str(prefix, "99999999-99999999-7");
+                let _ = parse_magic_cc_str(prefix);
+                let _ = parse_evt_str(prefix, "truncated");
+                let _ = parse_evt_steps(prefix);
+                let _ = parse_houses_str(prefix, Some(prefix), Some(prefix));
+                let _ = parse_monster_db_str(prefix);
+            }
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_evt_steps_in_order() {
+        let text = "# Process: one wave\nType = BigRaid\nInterval = 86400\n\nDelay = 0\nMessage = \"Orcs are coming!\"\n\nDelay = 120\nPosition = [32000, 32100, 7]\nSpread = 4\nRace = 5\nCount = (3, 5)\n";
+
+        let steps = parse_evt_steps(text).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].delay_seconds, 0);
+        assert_eq!(steps[0].message.as_deref(), Some("Orcs are coming!"));
+        assert_eq!(steps[1].delay_seconds, 120);
+        assert_eq!(steps[1].position, Some((32000, 32100, 7)));
+        assert_eq!(steps[1].spread, Some(4));
+        assert_eq!(steps[1].race, Some(5));
+        assert_eq!(steps[1].count, Some((3, 5)));
+    }
+
+    #[test]
+    fn test_parse_map_sector_non_ascii_content() {
+        let chests = parse_map_sector_str("1-2: Content={2472 ChestQuestNumber=5 Content={3031 String=\"äö\"}, 3354}", "10-10-7").unwrap();
+        assert_eq!(chests.len(), 1);
+        assert_eq!(chests[0].item_ids, vec![3031]);
+        assert_eq!(chests[0].ingame_coords, "321,322,7");
+    }
+
+    #[test]
+    fn test_parse_map_doors_and_chest_keys() {
+        let sector = "1-2: Content={2472 ChestQuestNumber=5 Content={2088 KeyNumber=4601, 3031 Amount=10}}\n\
+                      3-4: Content={4526, 1249 KeyholeNumber=4601}\n\
+                      5-6: Content={1255 DoorLevel=30}\n\
+                      7-8: Content={1257 DoorQuestNumber=5 DoorQuestValue=1}\n";
+        let chests = parse_map_sector_str(sector, "10-10-7").unwrap();
+        assert_eq!(chests[0].keys, vec![KeyItem { type_id: 2088, key_number: 4601 }]);
+
+        let doors = parse_map_doors_str(sector, "10-10-7").unwrap();
+        assert_eq!(doors.len(), 3);
+        assert_eq!((doors[0].position, doors[0].type_id, doors[0].keyhole_number), ((323, 324, 7), 1249, Some(4601)));
+        assert_eq!(doors[1].level, Some(30));
+        assert_eq!((doors[2].quest_number, doors[2].quest_value), (Some(5), Some(1)));
+    }
+
+    #[test]
+    fn test_parse_houses_with_owners_and_areas() {
+        let houses = r#"
+ID = 101
+Name = "Market Street 1"
+RentOffset = 100
+Area = 1
+GuildHouse = false
+Fields = {[32368,32240,7],[32369,32240,7],
+          [32368,32241,7]}
+
+ID = 102
+Name = "Guildhall"
+Area = 9
+GuildHouse = true
+Fields = {[32000,32000,7]}
+"#;
+        let owners = "ID = 101\nOwner = 1001\nLastPayment = 1767225600\n\nID = 102\nOwner = 0\n";
+        let areas = "Area = (1,\"Thais\",20,1)\n";
+
+        let parsed = parse_houses_str(houses, Some(owners), Some(areas)).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].town, "Thais");
+        assert_eq!(parsed[0].size, 3);
+        assert_eq!(parsed[0].rent, 160);
+        assert_eq!(parsed[0].owner_id, Some(1001));
+        assert_eq!(parsed[0].last_payment, Some(1767225600));
+        assert_eq!(parsed[1].town, "Area 9");
+        assert!(parsed[1].guild_house);
+        assert_eq!(parsed[1].owner_id, None);
+    }
+
+    #[test]
+    fn test_parse_monster_db() {
+        let text = "# Race X     Y     Z  Radius Amount Regen\n  21  32400 32180 8  3      4      60\n\n  26  32410 32185 8  2      1      300 # cave end\n0\n";
+        let spawns = parse_monster_db_str(text).unwrap();
+        assert_eq!(spawns.len(), 2);
+        assert_eq!(spawns[0], Spawn { race: 21, position: (32400, 32180, 8), radius: 3, amount: 4, regen_seconds: 60 });
+        assert_eq!(spawns[1].regen_seconds, 300);
+    }
+
+    #[test]
+    fn test_parse_vocation_condition() {
+        let cases = [
+            (r#"Paladin,"light","healing" -> Type=1, Price=170, "Do you want to buy the spell?""#, Some(vec!["Paladin"])),
+            (r#"Knight,Paladin,"spell" -> "Which spell?""#, Some(vec!["Knight", "Paladin"])),
+            (r#"!Knight,Premium,"heal" -> "Only non-knights, Knight, may learn this.""#, Some(vec!["Paladin", "Druid", "Sorcerer"])),
+            (r#"Topic=3,"yes",Level<SpellLevel(String) -> "Knight, you are too weak.""#, Some(vec!["Knight", "Paladin", "Druid", "Sorcerer"])),
+            (r#"Guardian,"spell" -> "Hm.""#, None),
+            (r#""learn the spell" Type=5 Price=100"#, None),
+        ];
+
+        for (line, expected) in cases {
+            let expected = expected.map(|v| v.into_iter().map(String::from).collect::<Vec<_>>());
+            assert_eq!(parse_vocation_condition(line), expected, "line: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_magic_cc_spell_types_from_handlers() {
+        let magic_cc = r#"
+static void InitSpells(void) {
+    Spell = CreateSpell(1, "exura");
+    Spell = CreateSpell(9, "utevo", "res", "para");
+    Spell = CreateSpell(15, "adori", "flam");
+    Spell->Flags = 9;
+    Spell = CreateSpell(19, "exevo", "flam", "hur");
+    Spell->Flags = 1;
+    Spell = CreateSpell(10, "utevo", "lux");
+}
+
+void CastSpell(TCreature *Actor, int SpellNr) {
+    switch (SpellNr) {
+        case 1: // Light Healing
+            Heal(Actor, Mana, SoulPoints, ComputeDamage(Actor, SpellNr, 20, 10));
+            break;
+        case 9:
+            SummonCreature(Actor, Mana, SoulPoints, Param);
+            break;
+        case 15:
+        case 16: {
+            switch (Direction) {
+                case 1: Missile = ANIMATION_FIRE; break;
+            }
+            MassCombat(Actor, Target, Mana, SoulPoints, Damage, EFFECT_FIRE, 1, DAMAGE_FIRE, Missile);
+            break;
+        }
+        case 19:
+            Combat(Actor, Mana, SoulPoints, Damage, DAMAGE_ENERGY);
+            break;
+        default:
+            break;
+    }
+}
+"#;
+        let spells = parse_magic_cc_str(magic_cc).unwrap();
+        let types: Vec<(i32, &str, Option<&str>)> =
+            spells.iter().map(|s| (s.spell_id, s.spell_type.as_str(), s.damage_element.as_deref())).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                (1, "healing", None),
+                (9, "summon", None),
+                (15, "area", Some("Fire")),
+                (19, "attack", Some("Energy")),
+                // No handler: classified by its words
+                (10, "utility", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spell_teaching_flags_unclassified_lines() {
+        let text = r#"Name = "Elane"
+Paladin,"light","healing" -> Type=1, Price=170, "Do you want to learn the spell 'Light Healing'?", Topic=3
+Hunter,"antidote" -> Type=2, Price=150, "Do you want to learn the spell 'Antidote'?", Topic=3
+"#;
+        let teachers = parse_npc_spell_teaching_str(text).unwrap();
+        assert_eq!(teachers.len(), 2);
+        assert_eq!(teachers[0].vocation, "Paladin");
+        assert!(teachers[0].unclassified_line.is_none());
+        assert_eq!(teachers[1].vocation, "Unknown");
+        assert!(teachers[1].unclassified_line.as_deref().unwrap().starts_with("Hunter"));
+    }
+
+    /// Every known .mon spell line must keep its interpretation unless
+    /// spell_interpretations.txt is regenerated on purpose with
+    /// `UPDATE_SNAPSHOTS=1 cargo test -p demonax-core spell_interpretation`.
+    #[test]
+    fn test_spell_interpretation_snapshot() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+        let lines: Vec<&str> = include_str!("../tests/data/mon_spell_lines.txt")
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        let block = lines.iter().map(|line| format!("{} : 1", line)).collect::<Vec<_>>().join(",\n");
+        let spells = parse_creature_spells(&format!("Spells = {{{}}}\n", block)).unwrap();
+        assert_eq!(spells.len(), lines.len(), "every known spell line must parse");
+
+        let actual: String = lines
+            .iter()
+            .zip(&spells)
+            .map(|(line, spell)| {
+                format!(
+                    "{} => {} | {} | {} | {}\n",
+                    line, spell.spell_name, spell.spell_category, spell.shape_name, spell.impact_name
+                )
+            })
+            .collect();
+        let snapshot = dir.join("spell_interpretations.txt");
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&snapshot, &actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&snapshot).unwrap();
+        for (expected, actual) in expected.lines().zip(actual.lines()) {
+            assert_eq!(actual, expected);
+        }
+        assert_eq!(expected.lines().count(), lines.len());
+    }
+}
@@ -0,0 +1,92 @@
+//! Selecting a few players for `process-usr` and `render-equipment`, by a name
+//! glob (`--player-glob "A*"`) and/or a file of character IDs (`--player-ids`).
+//!
+//! The ID file lists IDs separated by newlines, commas or spaces; `#` starts a
+//! comment. With both filters a player must match both. The ID filter is
+//! checked against `.usr` file names (`<id>.usr`) before the files are read, so
+//! re-processing a handful of players doesn't parse every file; the name glob
+//! can only be checked once a file is parsed.
+
+use crate::error::{DemonaxError, Result};
+use glob::{MatchOptions, Pattern};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Players to include; the default includes everyone
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerSelection {
+    ids: Option<BTreeSet<i32>>,
+    name: Option<Pattern>,
+}
+
+impl PlayerSelection {
+    /// A filter from a name glob (case-insensitive) and a file of IDs
+    pub fn new(name_glob: Option<&str>, ids_file: Option<&Path>) -> Result<Self> {
+        let name = name_glob
+            .map(|glob| Pattern::new(glob).map_err(|e| DemonaxError::Validation(format!("Invalid player glob {:?}: {}", glob, e))))
+            .transpose()?;
+        let ids = ids_file
+            .map(|path| {
+                let text = std::fs::read_to_string(path).map_err(|e| {
+                    DemonaxError::Io(std::io::Error::new(e.kind(), format!("Failed to read player IDs from {:?}: {}", path, e)))
+                })?;
+                Self::parse_ids(&text)
+            })
+            .transpose()?;
+        Ok(Self { ids, name })
+    }
+
+    /// IDs separated by newlines, commas or whitespace, with `#` comments
+    pub fn parse_ids(text: &str) -> Result<BTreeSet<i32>> {
+        text.lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse().map_err(|_| DemonaxError::Validation(format!("Player ID {:?} is not a number", id))))
+            .collect()
+    }
+
+    /// Whether every player is included
+    pub fn is_all(&self) -> bool {
+        self.ids.is_none() && self.name.is_none()
+    }
+
+    /// Whether a .usr file may hold an included player, judged by its `<id>.usr`
+    /// name; files named otherwise are kept for [`matches`](Self::matches)
+    pub fn may_match_file(&self, file_name: &str) -> bool {
+        let stem = Path::new(file_name).file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        match (&self.ids, stem.parse::<i32>()) {
+            (Some(ids), Ok(id)) => ids.contains(&id),
+            _ => true,
+        }
+    }
+
+    /// Whether a player is included
+    pub fn matches(&self, player_id: i32, name: &str) -> bool {
+        let options = MatchOptions { case_sensitive: false, ..MatchOptions::new() };
+        self.ids.as_ref().is_none_or(|ids| ids.contains(&player_id))
+            && self.name.as_ref().is_none_or(|pattern| pattern.matches_with(name, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_and_glob_must_both_match() {
+        assert_eq!(PlayerSelection::parse_ids("1001, 1002\n# old\n1003 # bugged\n").unwrap().len(), 3);
+        assert!(PlayerSelection::parse_ids("1001\nalice\n").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let ids = dir.path().join("ids.txt");
+        std::fs::write(&ids, "1001\n1002\n").unwrap();
+        let filter = PlayerSelection::new(Some("A*"), Some(&ids)).unwrap();
+        assert!(filter.matches(1001, "alice") && !filter.matches(1002, "Bob") && !filter.matches(1003, "Anna"));
+        assert!(filter.may_match_file("usr/01/1001.usr") && !filter.may_match_file("usr/03/1003.usr"));
+        assert!(filter.may_match_file("usr/backup.usr"));
+
+        assert!(PlayerSelection::default().is_all() && PlayerSelection::default().matches(7, "Anyone"));
+        assert!(PlayerSelection::new(Some("[a"), None).is_err());
+    }
+}
@@ -12,36 +12,122 @@
 //! [[items]]
 //! type_id = 3155
 //! charges = 35
+//!
+//! # A lottery pool: one of these, drawn by weight for every present
+//! [[pools]]
+//! picks = 1
+//!
+//! [[pools.items]]
+//! type_id = 3079  # Boots of haste
+//! weight = 1
+//!
+//! [[pools.items]]
+//! type_id = 3031
+//! amount = 100
+//! weight = 99
+//! ```
+//!
+//! Event prizes can be given per player from a winners CSV (see [`read_winners_csv`]):
+//! ```csv
+//! player_name,present_config
+//! Alice,prizes/first.toml
+//! Bob,prizes/runner-up.toml
 //! ```
 
+use crate::catalog::ItemCatalog;
 use crate::error::{DemonaxError, Result};
-use crate::file_utils::read_latin1_file;
+use crate::file_utils::{find_files_with_extension, read_latin1_file};
 use crate::inventory::{
-    extract_inventory_section, replace_inventory_section, InventoryItem, InventorySection,
+    carry_capacity, extract_inventory_section, replace_inventory_section, InventoryItem,
+    InventorySection,
 };
+use crate::models::{vocation_name, ParsedUsrFile};
+use crate::mon::diff_lines;
+use crate::parsers::parse_usr_str;
 use encoding_rs::WINDOWS_1252;
-use serde::Deserialize;
-use std::path::Path;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 /// Configuration for the present container.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ContainerConfig {
     pub type_id: i32,
 }
 
 /// Configuration for an item inside the present.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PresentItemConfig {
     pub type_id: i32,
     pub amount: Option<i32>,
     pub charges: Option<i32>,
 }
 
+/// An item of a lottery pool, drawn with a chance of its weight out of the
+/// pool's total weight.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PresentPoolItem {
+    pub type_id: i32,
+    pub amount: Option<i32>,
+    pub charges: Option<i32>,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// A lottery pool: `picks` items drawn by weight for every present, each draw
+/// from the whole pool, so an item can come up more than once.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PresentPool {
+    #[serde(default = "default_picks")]
+    pub picks: u32,
+    pub items: Vec<PresentPoolItem>,
+}
+
+fn default_picks() -> u32 {
+    1
+}
+
+impl PresentPool {
+    /// Sum of the item weights
+    pub fn total_weight(&self) -> u64 {
+        self.items.iter().map(|item| item.weight as u64).sum()
+    }
+
+    /// Draw one item by weight. `None` when the pool has no weight.
+    pub fn draw<R: Rng>(&self, rng: &mut R) -> Option<&PresentPoolItem> {
+        let total = self.total_weight();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.random_range(0..total);
+        self.items.iter().find(|item| {
+            if roll < item.weight as u64 {
+                return true;
+            }
+            roll -= item.weight as u64;
+            false
+        })
+    }
+}
+
 /// Complete present configuration loaded from TOML.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PresentConfig {
     pub container: ContainerConfig,
+    /// Items every present holds
+    #[serde(default)]
     pub items: Vec<PresentItemConfig>,
+    /// Lottery pools drawn anew for every present
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pools: Vec<PresentPool>,
 }
 
 impl PresentConfig {
@@ -54,58 +140,224 @@ impl PresentConfig {
             ))
         })?;
 
-        Self::from_str(&content)
+        content.parse()
+    }
+
+    /// Convert this configuration to an InventoryItem (container with contents).
+    /// Pools are drawn at random, so presents with pools differ between calls.
+    pub fn to_inventory_item(&self) -> InventoryItem {
+        self.draw_inventory_item(&mut StdRng::from_os_rng())
+    }
+
+    /// The present with its pools drawn from `rng`: the fixed items first, then
+    /// the picks of each pool in order.
+    pub fn draw_inventory_item<R: Rng>(&self, rng: &mut R) -> InventoryItem {
+        let item = |type_id: i32, amount: Option<i32>, charges: Option<i32>| {
+            let mut inv_item = InventoryItem::new(type_id);
+            inv_item.amount = amount;
+            inv_item.charges = charges;
+            inv_item
+        };
+
+        let mut contents: Vec<InventoryItem> =
+            self.items.iter().map(|fixed| item(fixed.type_id, fixed.amount, fixed.charges)).collect();
+        for pool in &self.pools {
+            for _ in 0..pool.picks {
+                if let Some(drawn) = pool.draw(rng) {
+                    contents.push(item(drawn.type_id, drawn.amount, drawn.charges));
+                }
+            }
+        }
+
+        InventoryItem::container(self.container.type_id, contents)
+    }
+
+    /// Check that the present fits into its container and that every pool can
+    /// be drawn from. Every present has the same number of items, so one draw
+    /// shows whether they fit.
+    pub fn validate(&self, catalog: &ItemCatalog) -> Result<()> {
+        if let Some(index) = self.pools.iter().position(|pool| pool.total_weight() == 0) {
+            return Err(DemonaxError::Validation(format!("Pool {} has no items with a weight", index + 1)));
+        }
+        self.to_inventory_item().validate(catalog)
+    }
+}
+
+/// How often an item came up in [`preview_present`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PresentItemOdds {
+    pub type_id: i32,
+    /// Sampled presents holding the item at least once
+    pub presents: u64,
+    /// Times the item was put into a sampled present
+    pub count: u64,
+    /// Total amount over the sampled presents, counting items without an amount as 1
+    pub amount: u64,
+    /// Times the item is expected in the sampled presents, from the pool weights
+    pub expected_count: f64,
+}
+
+/// Simulated contents of many presents of a config
+#[derive(Debug, Clone, Serialize)]
+pub struct PresentPreview {
+    pub samples: u64,
+    /// Items by type ID
+    pub items: Vec<PresentItemOdds>,
+}
+
+/// Draw `samples` presents of `config` and count the items, next to the
+/// counts the pool weights lead to. Pass a seed for reproducible results.
+pub fn preview_present(config: &PresentConfig, samples: u64, seed: Option<u64>) -> PresentPreview {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+
+    let mut expected: BTreeMap<i32, f64> = BTreeMap::new();
+    for item in &config.items {
+        *expected.entry(item.type_id).or_default() += 1.0;
+    }
+    for pool in &config.pools {
+        let total = pool.total_weight();
+        if total == 0 {
+            continue;
+        }
+        for item in &pool.items {
+            *expected.entry(item.type_id).or_default() += pool.picks as f64 * item.weight as f64 / total as f64;
+        }
+    }
+
+    let mut odds: BTreeMap<i32, PresentItemOdds> = expected
+        .into_iter()
+        .map(|(type_id, per_present)| {
+            let expected_count = per_present * samples as f64;
+            (type_id, PresentItemOdds { type_id, presents: 0, count: 0, amount: 0, expected_count })
+        })
+        .collect();
+
+    for _ in 0..samples {
+        let present = config.draw_inventory_item(&mut rng);
+        let mut seen = Vec::new();
+        for item in &present.contents {
+            let Some(entry) = odds.get_mut(&item.type_id) else { continue };
+            entry.count += 1;
+            entry.amount += item.amount.unwrap_or(1).max(0) as u64;
+            if !seen.contains(&item.type_id) {
+                seen.push(item.type_id);
+                entry.presents += 1;
+            }
+        }
     }
 
+    PresentPreview { samples, items: odds.into_values().collect() }
+}
+
+impl FromStr for PresentConfig {
+    type Err = DemonaxError;
+
     /// Parse present configuration from a TOML string.
-    pub fn from_str(content: &str) -> Result<Self> {
+    fn from_str(content: &str) -> Result<Self> {
         toml::from_str(content).map_err(|e| {
             DemonaxError::Parse(format!("Failed to parse present config TOML: {}", e))
         })
     }
+}
 
-    /// Convert this configuration to an InventoryItem (container with contents).
-    pub fn to_inventory_item(&self) -> InventoryItem {
-        let contents: Vec<InventoryItem> = self
-            .items
-            .iter()
-            .map(|item| {
-                let mut inv_item = InventoryItem::new(item.type_id);
-                inv_item.amount = item.amount;
-                inv_item.charges = item.charges;
-                inv_item
-            })
-            .collect();
-
-        InventoryItem::container(self.container.type_id, contents)
-    }
+/// Why a present could not be given to a player file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GiftErrorKind {
+    /// No .usr file with the player's name
+    NotFound,
+    /// The file could not be read
+    Read,
+    /// The file or its inventory section could not be parsed
+    Parse,
+    /// The present does not fit its container or the slot
+    DoesNotFit,
+    /// The new content could not be encoded to Windows-1252
+    Encode,
+    /// The file could not be written
+    Write,
 }
 
-/// Result of applying a present to a player file.
-#[derive(Debug, Clone)]
+/// Result of applying a present to a player file. `item` is the present as
+/// written to the inventory slot, e.g. `2854 Content={3456}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
 pub enum GiftResult {
     /// Present was successfully given.
-    Gifted { player_name: String },
+    Gifted { player_name: String, path: PathBuf, slot: i32, item: String },
     /// Player already has something in the target slot.
-    SlotOccupied { player_name: String },
-    /// Error occurred while processing the file.
-    Error { player_name: String, error: String },
+    SlotOccupied { player_name: String, path: PathBuf, slot: i32 },
+    /// The present makes the player carry more than their capacity allows.
+    /// Weights are in hundredths of an ounce.
+    OverCapacity {
+        player_name: String,
+        path: PathBuf,
+        slot: i32,
+        item: String,
+        weight: i64,
+        capacity: i64,
+        /// Whether the present was given anyway
+        gifted: bool,
+    },
+    /// Error occurred while processing the file; `path` is missing when no
+    /// file was found for the player.
+    Error { player_name: String, path: Option<PathBuf>, kind: GiftErrorKind, error: String },
+    /// The present was written, but reading the file back found its inventory
+    /// unparsable or other parts of the file changed.
+    VerificationFailed { player_name: String, path: PathBuf, slot: i32, item: String, error: String },
 }
 
 impl GiftResult {
     pub fn is_gifted(&self) -> bool {
-        matches!(self, GiftResult::Gifted { .. })
+        matches!(self, GiftResult::Gifted { .. } | GiftResult::OverCapacity { gifted: true, .. })
     }
 
     pub fn is_skipped(&self) -> bool {
-        matches!(self, GiftResult::SlotOccupied { .. })
+        matches!(self, GiftResult::SlotOccupied { .. } | GiftResult::OverCapacity { gifted: false, .. })
+    }
+
+    /// Whether the file was written but failed its check afterwards
+    pub fn is_verification_failure(&self) -> bool {
+        matches!(self, GiftResult::VerificationFailed { .. })
+    }
+
+    /// The .usr file, unless none was found for the player
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            GiftResult::Gifted { path, .. }
+            | GiftResult::SlotOccupied { path, .. }
+            | GiftResult::OverCapacity { path, .. }
+            | GiftResult::VerificationFailed { path, .. } => Some(path),
+            GiftResult::Error { path, .. } => path.as_deref(),
+        }
     }
 
     pub fn player_name(&self) -> &str {
         match self {
-            GiftResult::Gifted { player_name } => player_name,
-            GiftResult::SlotOccupied { player_name } => player_name,
+            GiftResult::Gifted { player_name, .. } => player_name,
+            GiftResult::SlotOccupied { player_name, .. } => player_name,
+            GiftResult::OverCapacity { player_name, .. } => player_name,
             GiftResult::Error { player_name, .. } => player_name,
+            GiftResult::VerificationFailed { player_name, .. } => player_name,
+        }
+    }
+
+    /// What happened, without the player name, e.g. "skipped: slot occupied"
+    pub fn outcome(&self) -> String {
+        match self {
+            GiftResult::Gifted { .. } => "gifted".to_string(),
+            GiftResult::SlotOccupied { .. } => "skipped: slot occupied".to_string(),
+            GiftResult::OverCapacity { weight, capacity, gifted, .. } => format!(
+                "{}: over capacity ({:.2} of {:.2} oz)",
+                if *gifted { "gifted" } else { "skipped" },
+                *weight as f64 / 100.0,
+                *capacity as f64 / 100.0
+            ),
+            GiftResult::Error { error, .. } => format!("error: {}", error),
+            GiftResult::VerificationFailed { error, .. } => format!("verification failed: {}", error),
         }
     }
 }
@@ -120,12 +372,163 @@ fn extract_player_name(content: &str) -> String {
         .unwrap_or_else(|| "Unknown".to_string())
 }
 
+/// The .usr files below `usr_dir` as (lower-case player name, path), by path
+pub fn find_usr_files_by_player(usr_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for path in find_files_with_extension(usr_dir, "usr")? {
+        let name = extract_player_name(&read_latin1_file(&path)?);
+        files.push((name.to_lowercase(), path));
+    }
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(files)
+}
+
+/// A row of a winners CSV
+#[derive(Debug, Clone)]
+pub struct Winner {
+    /// Line number in the CSV, counting the header as line 1
+    pub line: usize,
+    pub player_name: String,
+    pub present_config: PathBuf,
+    pub present: PresentConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct WinnerRow {
+    player_name: String,
+    present_config: PathBuf,
+}
+
+/// Read a winners CSV with `player_name` and `present_config` columns. Config
+/// paths are relative to the CSV's directory; every config is loaded (once)
+/// before anything is given, so a bad row stops the run before it starts.
+pub fn read_winners_csv(path: &Path) -> Result<Vec<Winner>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| DemonaxError::Parse(format!("Failed to open winners CSV {:?}: {}", path, e)))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    let mut configs: HashMap<PathBuf, PresentConfig> = HashMap::new();
+    let mut winners = Vec::new();
+    for (index, row) in csv::Reader::from_reader(file).deserialize::<WinnerRow>().enumerate() {
+        let line = index + 2;
+        let row = row.map_err(|e| DemonaxError::Parse(format!("Winners CSV line {}: {}", line, e)))?;
+        if row.player_name.trim().is_empty() {
+            return Err(DemonaxError::Validation(format!("Winners CSV line {}: no player name", line)));
+        }
+        let config_path = base.join(&row.present_config);
+        let present = match configs.get(&config_path) {
+            Some(present) => present.clone(),
+            None => {
+                let present = PresentConfig::from_file(&config_path)
+                    .map_err(|e| DemonaxError::Validation(format!("Winners CSV line {}: {}", line, e)))?;
+                configs.insert(config_path.clone(), present.clone());
+                present
+            }
+        };
+        winners.push(Winner { line, player_name: row.player_name.trim().to_string(), present_config: row.present_config, present });
+    }
+    Ok(winners)
+}
+
+/// Give each winner their present, in CSV order, with the inventory diff of
+/// [`apply_present_with_diff`]. `files` are the .usr files from
+/// [`find_usr_files_by_player`]; winners without one get an error result.
+pub fn apply_winners(
+    winners: &[Winner],
+    files: &[(String, PathBuf)],
+    target_slot: i32,
+    catalog: &ItemCatalog,
+    skip_over_capacity: bool,
+    dry_run: bool,
+) -> Vec<(GiftResult, Vec<String>)> {
+    winners
+        .iter()
+        .map(|winner| {
+            let wanted = winner.player_name.to_lowercase();
+            match files.iter().find(|(name, _)| *name == wanted) {
+                Some((_, path)) => {
+                    apply_present_with_diff(path, &winner.present, target_slot, catalog, skip_over_capacity, dry_run)
+                }
+                None => {
+                    let error = "no .usr file with that name".to_string();
+                    let player_name = winner.player_name.clone();
+                    (GiftResult::Error { player_name, path: None, kind: GiftErrorKind::NotFound, error }, Vec::new())
+                }
+            }
+        })
+        .collect()
+}
+
+/// Give one present per account. The characters of an account are tried from
+/// the most experienced down until one is given the present, e.g. when the
+/// slot of the first is taken; the rest aren't tried and have no result.
+/// Characters without an Account line count as accounts of their own.
+pub fn apply_present_per_account(
+    files: &[PathBuf],
+    config: &PresentConfig,
+    target_slot: i32,
+    catalog: &ItemCatalog,
+    skip_over_capacity: bool,
+    dry_run: bool,
+) -> Vec<(GiftResult, Vec<String>)> {
+    let parsed: Vec<(&PathBuf, Result<ParsedUsrFile>)> = files
+        .par_iter()
+        .map(|path| (path, read_latin1_file(path).and_then(|text| parse_usr_str(&text, &path.to_string_lossy()))))
+        .collect();
+
+    let mut results = Vec::new();
+    let mut accounts: BTreeMap<i32, Vec<(i64, &PathBuf)>> = BTreeMap::new();
+    let mut groups: Vec<Vec<(i64, &PathBuf)>> = Vec::new();
+    for (path, usr) in parsed {
+        match usr {
+            Ok(usr) => match usr.account_id {
+                Some(account_id) => accounts.entry(account_id).or_default().push((usr.skills.experience, path)),
+                None => groups.push(vec![(usr.skills.experience, path)]),
+            },
+            Err(e) => results.push((
+                GiftResult::Error {
+                    player_name: path.display().to_string(),
+                    path: Some(path.to_path_buf()),
+                    kind: GiftErrorKind::Read,
+                    error: format!("Failed to read file: {}", e),
+                },
+                Vec::new(),
+            )),
+        }
+    }
+    groups.extend(accounts.into_values());
+
+    let given: Vec<Vec<(GiftResult, Vec<String>)>> = groups
+        .into_par_iter()
+        .map(|mut characters| {
+            characters.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+            let mut tried = Vec::new();
+            for (_, path) in characters {
+                let (result, diff) =
+                    apply_present_with_diff(path, config, target_slot, catalog, skip_over_capacity, dry_run);
+                // A file that failed verification was written, so don't give a second present
+                let gifted = result.is_gifted() || result.is_verification_failure();
+                tried.push((result, diff));
+                if gifted {
+                    break;
+                }
+            }
+            tried
+        })
+        .collect();
+    results.extend(given.into_iter().flatten());
+    results
+}
+
 /// Apply a present to a single .usr file.
 ///
 /// # Arguments
 /// * `file_path` - Path to the .usr file
 /// * `config` - Present configuration
 /// * `target_slot` - Inventory slot to place the present (default: 10)
+/// * `catalog` - Container capacities and item weights; an empty catalog checks neither
+/// * `skip_over_capacity` - Skip players the present would put over their carry capacity
+///   instead of only reporting them
 /// * `dry_run` - If true, don't modify the file
 ///
 /// # Returns
@@ -134,7 +537,37 @@ pub fn apply_present_to_file(
     file_path: &Path,
     config: &PresentConfig,
     target_slot: i32,
+    catalog: &ItemCatalog,
+    skip_over_capacity: bool,
+    dry_run: bool,
+) -> GiftResult {
+    apply_present_with_diff(file_path, config, target_slot, catalog, skip_over_capacity, dry_run).0
+}
+
+/// Like [`apply_present_to_file`], also returning the change to the file's
+/// inventory section as `-old` / `+new` lines, one slot per line. The diff is
+/// empty when nothing was (or would be) given.
+pub fn apply_present_with_diff(
+    file_path: &Path,
+    config: &PresentConfig,
+    target_slot: i32,
+    catalog: &ItemCatalog,
+    skip_over_capacity: bool,
+    dry_run: bool,
+) -> (GiftResult, Vec<String>) {
+    let mut diff = Vec::new();
+    let result = apply_present(file_path, config, target_slot, catalog, skip_over_capacity, dry_run, &mut diff);
+    (result, diff)
+}
+
+fn apply_present(
+    file_path: &Path,
+    config: &PresentConfig,
+    target_slot: i32,
+    catalog: &ItemCatalog,
+    skip_over_capacity: bool,
     dry_run: bool,
+    diff: &mut Vec<String>,
 ) -> GiftResult {
     // Read the file
     let content = match read_latin1_file(file_path) {
@@ -142,6 +575,8 @@ pub fn apply_present_to_file(
         Err(e) => {
             return GiftResult::Error {
                 player_name: file_path.display().to_string(),
+                path: Some(file_path.to_path_buf()),
+                kind: GiftErrorKind::Read,
                 error: format!("Failed to read file: {}", e),
             };
         }
@@ -150,11 +585,13 @@ pub fn apply_present_to_file(
     let player_name = extract_player_name(&content);
 
     // Extract inventory section
-    let (inv_content, _, _) = match extract_inventory_section(&content) {
+    let (inv_content, inv_start, inv_end) = match extract_inventory_section(&content) {
         Ok(r) => r,
         Err(e) => {
             return GiftResult::Error {
                 player_name,
+                path: Some(file_path.to_path_buf()),
+                kind: GiftErrorKind::Parse,
                 error: format!("Failed to extract inventory: {}", e),
             };
         }
@@ -166,6 +603,8 @@ pub fn apply_present_to_file(
         Err(e) => {
             return GiftResult::Error {
                 player_name,
+                path: Some(file_path.to_path_buf()),
+                kind: GiftErrorKind::Parse,
                 error: format!("Failed to parse inventory: {}", e),
             };
         }
@@ -173,12 +612,52 @@ pub fn apply_present_to_file(
 
     // Check if slot is empty
     if !inventory.is_slot_empty(target_slot) {
-        return GiftResult::SlotOccupied { player_name };
+        return GiftResult::SlotOccupied { player_name, path: file_path.to_path_buf(), slot: target_slot };
     }
 
     // Add present to inventory
     let present_item = config.to_inventory_item();
-    inventory.set_slot(target_slot, present_item);
+    let item = present_item.serialize();
+    if let Err(e) = inventory.set_slot(target_slot, present_item, catalog) {
+        return GiftResult::Error {
+            player_name,
+            path: Some(file_path.to_path_buf()),
+            kind: GiftErrorKind::DoesNotFit,
+            error: format!("Present does not fit: {}", e),
+        };
+    }
+
+    // Check that the player can still carry everything
+    let mut over_capacity = None;
+    if !catalog.is_empty() {
+        let usr = match parse_usr_str(&content, &file_path.to_string_lossy()) {
+            Ok(usr) => usr,
+            Err(e) => {
+                return GiftResult::Error {
+                    player_name,
+                    path: Some(file_path.to_path_buf()),
+                    kind: GiftErrorKind::Parse,
+                    error: format!("Failed to read level: {}", e),
+                };
+            }
+        };
+        let capacity = carry_capacity(usr.skills.level, vocation_name(usr.profession.unwrap_or(0)));
+        let weight = inventory.total_weight(catalog);
+        if weight > capacity {
+            if skip_over_capacity {
+                return GiftResult::OverCapacity {
+                    player_name,
+                    path: file_path.to_path_buf(),
+                    slot: target_slot,
+                    item,
+                    weight,
+                    capacity,
+                    gifted: false,
+                };
+            }
+            over_capacity = Some((weight, capacity));
+        }
+    }
 
     // Serialize new inventory
     let new_inventory = inventory.serialize();
@@ -189,11 +668,15 @@ pub fn apply_present_to_file(
         Err(e) => {
             return GiftResult::Error {
                 player_name,
+                path: Some(file_path.to_path_buf()),
+                kind: GiftErrorKind::Parse,
                 error: format!("Failed to replace inventory: {}", e),
             };
         }
     };
 
+    *diff = diff_lines(&content[inv_start..inv_end], &new_inventory);
+
     // Write file (unless dry run)
     if !dry_run {
         // Encode back to Windows-1252 (Latin-1)
@@ -201,6 +684,8 @@ pub fn apply_present_to_file(
         if had_errors {
             return GiftResult::Error {
                 player_name,
+                path: Some(file_path.to_path_buf()),
+                kind: GiftErrorKind::Encode,
                 error: "Failed to encode file content to Windows-1252".to_string(),
             };
         }
@@ -208,21 +693,71 @@ pub fn apply_present_to_file(
         if let Err(e) = std::fs::write(file_path, &*encoded) {
             return GiftResult::Error {
                 player_name,
+                path: Some(file_path.to_path_buf()),
+                kind: GiftErrorKind::Write,
                 error: format!("Failed to write file: {}", e),
             };
         }
+
+        let verified = read_latin1_file(file_path)
+            .map_err(|e| format!("Failed to read file back: {}", e))
+            .and_then(|written| verify_written_usr(&content, inv_start, inv_end, &new_inventory, &written));
+        if let Err(error) = verified {
+            return GiftResult::VerificationFailed {
+                player_name,
+                path: file_path.to_path_buf(),
+                slot: target_slot,
+                item,
+                error,
+            };
+        }
     }
 
-    GiftResult::Gifted { player_name }
+    let path = file_path.to_path_buf();
+    match over_capacity {
+        Some((weight, capacity)) => {
+            GiftResult::OverCapacity { player_name, path, slot: target_slot, item, weight, capacity, gifted: true }
+        }
+        None => GiftResult::Gifted { player_name, path, slot: target_slot, item },
+    }
+}
+
+/// Check a .usr file read back after writing: its inventory section must parse
+/// and be `new_inventory`, and everything outside the section must be as in
+/// `original`, whose inventory section spanned `inv_start..inv_end`.
+fn verify_written_usr(
+    original: &str,
+    inv_start: usize,
+    inv_end: usize,
+    new_inventory: &str,
+    written: &str,
+) -> std::result::Result<(), String> {
+    let (inv_content, start, end) =
+        extract_inventory_section(written).map_err(|e| format!("Inventory section not found: {}", e))?;
+    InventorySection::parse(&inv_content).map_err(|e| format!("Inventory no longer parses: {}", e))?;
+    if written[start..end] != *new_inventory {
+        return Err("Inventory section differs from what was written".to_string());
+    }
+    if written[..start] != original[..inv_start] {
+        return Err("File changed before the inventory section".to_string());
+    }
+    if written[end..] != original[inv_end..] {
+        return Err("File changed after the inventory section".to_string());
+    }
+    Ok(())
 }
 
 /// Summary of gift distribution results.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct GiftSummary {
     pub total_processed: usize,
     pub gifted: usize,
     pub skipped: usize,
+    /// Players over their carry capacity, gifted or skipped
+    pub over_capacity: usize,
     pub errors: usize,
+    /// Files written whose check afterwards failed; they may need restoring
+    pub verification_failures: usize,
 }
 
 impl GiftSummary {
@@ -235,9 +770,28 @@ impl GiftSummary {
         match result {
             GiftResult::Gifted { .. } => self.gifted += 1,
             GiftResult::SlotOccupied { .. } => self.skipped += 1,
+            GiftResult::OverCapacity { gifted, .. } => {
+                self.over_capacity += 1;
+                if *gifted {
+                    self.gifted += 1;
+                } else {
+                    self.skipped += 1;
+                }
+            }
             GiftResult::Error { .. } => self.errors += 1,
+            GiftResult::VerificationFailed { .. } => self.verification_failures += 1,
         }
     }
+
+    /// Add the counts of another summary, e.g. of one gift of a queue
+    pub fn add_summary(&mut self, other: &GiftSummary) {
+        self.total_processed += other.total_processed;
+        self.gifted += other.gifted;
+        self.skipped += other.skipped;
+        self.over_capacity += other.over_capacity;
+        self.errors += other.errors;
+        self.verification_failures += other.verification_failures;
+    }
 }
 
 #[cfg(test)]
@@ -318,4 +872,160 @@ charges = 35
             "2854 Content={3726 Amount=99, 3155 Charges=35}"
         );
     }
+
+    #[test]
+    fn test_present_overflowing_container() {
+        use crate::catalog::CatalogItem;
+
+        let toml = r#"
+[container]
+type_id = 2853
+
+[[items]]
+type_id = 3726
+amount = 99
+
+[[items]]
+type_id = 3155
+charges = 35
+"#;
+
+        let config = PresentConfig::from_str(toml).unwrap();
+        let bag_of = |capacity: usize| -> ItemCatalog {
+            [CatalogItem { capacity: Some(capacity), ..CatalogItem::new(2853, "Bag") }].into_iter().collect()
+        };
+        assert!(config.validate(&bag_of(2)).is_ok());
+
+        let small_bag = bag_of(1);
+        assert!(matches!(
+            config.validate(&small_bag),
+            Err(DemonaxError::ContainerOverflow { type_id: 2853, capacity: 1, count: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_dry_run_diff_shows_changed_slots() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.usr");
+        let content = "ID          = 1\nName        = \"Alice\"\nInventory   = {1 Content={3354},\n               3 Content={2853}}\n";
+        std::fs::write(&path, content).unwrap();
+
+        let config = PresentConfig::from_str("[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n").unwrap();
+        let (result, diff) = apply_present_with_diff(&path, &config, 10, &ItemCatalog::new(), false, true);
+        assert!(matches!(result, GiftResult::Gifted { .. }));
+        assert_eq!(
+            diff,
+            [
+                "-               3 Content={2853}}",
+                "+               3 Content={2853},",
+                "+               10 Content={2854 Content={3456}}}",
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+        // An occupied slot changes nothing
+        let (_, diff) = apply_present_with_diff(&path, &config, 3, &ItemCatalog::new(), false, true);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_results_carry_file_and_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.usr");
+        std::fs::write(&path, "ID          = 1\nName        = \"Alice\"\nInventory   = {3 Content={2853}}\n").unwrap();
+        let config = PresentConfig::from_str("[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n").unwrap();
+
+        let gifted = apply_present_to_file(&path, &config, 10, &ItemCatalog::new(), false, true);
+        assert_eq!(gifted.path(), Some(path.as_path()));
+        let json = serde_json::to_value(&gifted).unwrap();
+        assert_eq!(json["result"], "gifted");
+        assert_eq!(json["slot"], 10);
+        assert_eq!(json["item"], "2854 Content={3456}");
+
+        let occupied = serde_json::to_value(apply_present_to_file(&path, &config, 3, &ItemCatalog::new(), false, true)).unwrap();
+        assert_eq!((occupied["result"].as_str(), occupied["slot"].as_i64()), (Some("slot_occupied"), Some(3)));
+
+        let missing = apply_present_to_file(&dir.path().join("2.usr"), &config, 10, &ItemCatalog::new(), false, true);
+        assert!(matches!(missing, GiftResult::Error { kind: GiftErrorKind::Read, .. }));
+        assert_eq!(serde_json::to_value(&missing).unwrap()["kind"], "read");
+    }
+
+    #[test]
+    fn test_written_file_is_verified() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.usr");
+        let content = "ID          = 1\nName        = \"Alice\"\nInventory   = {1 Content={3354}}\nDepot       = {}\n";
+        std::fs::write(&path, content).unwrap();
+
+        let config = PresentConfig::from_str("[container]\ntype_id = 2854\n").unwrap();
+        let result = apply_present_to_file(&path, &config, 10, &ItemCatalog::new(), false, false);
+        assert!(matches!(result, GiftResult::Gifted { .. }));
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.ends_with("Depot       = {}\n"));
+
+        let (_, start, end) = extract_inventory_section(content).unwrap();
+        let (new_inventory, _, _) = extract_inventory_section(&written).unwrap();
+        let new_inventory = format!("Inventory   = {{{}}}", new_inventory);
+        let verify = |written: &str| verify_written_usr(content, start, end, &new_inventory, written);
+        assert_eq!(verify(&written), Ok(()));
+        assert!(verify(&written.replace("Alice", "Alicia")).unwrap_err().contains("before the inventory"));
+        assert!(verify(&written.replace("Depot", "Dopet")).unwrap_err().contains("after the inventory"));
+        assert!(verify(&written.replace("{1 Content", "{1 Contents")).is_err());
+
+        let mut summary = GiftSummary::new();
+        summary.add_result(&GiftResult::VerificationFailed {
+            player_name: "Alice".to_string(),
+            path: path.clone(),
+            slot: 10,
+            item: "2854".to_string(),
+            error: "x".to_string(),
+        });
+        assert_eq!((summary.verification_failures, summary.errors, summary.gifted), (1, 0, 0));
+    }
+
+    #[test]
+    fn test_pools_are_drawn_by_weight() {
+        let toml = r#"
+[container]
+type_id = 2854
+
+[[items]]
+type_id = 3726
+
+[[pools]]
+picks = 2
+
+[[pools.items]]
+type_id = 3079
+weight = 1
+
+[[pools.items]]
+type_id = 3031
+amount = 100
+weight = 3
+"#;
+
+        let config = PresentConfig::from_str(toml).unwrap();
+        assert_eq!(config.pools[0].total_weight(), 4);
+        assert_eq!(config.to_inventory_item().contents.len(), 3);
+        assert!(config.validate(&ItemCatalog::new()).is_ok());
+
+        let preview = preview_present(&config, 4000, Some(7));
+        let ids: Vec<i32> = preview.items.iter().map(|item| item.type_id).collect();
+        assert_eq!(ids, vec![3031, 3079, 3726]);
+        let (gold, boots, fixed) = (&preview.items[0], &preview.items[1], &preview.items[2]);
+        assert_eq!((fixed.count, fixed.presents, fixed.expected_count), (4000, 4000, 4000.0));
+        assert_eq!(gold.expected_count, 6000.0);
+        assert_eq!(boots.expected_count, 2000.0);
+        assert_eq!(gold.count + boots.count, 8000);
+        assert_eq!(gold.amount, gold.count * 100);
+        assert!((gold.count as f64 - 6000.0).abs() < 200.0, "{:?}", gold);
+
+        let a = preview_present(&config, 100, Some(1));
+        let b = preview_present(&config, 100, Some(1));
+        assert_eq!(a.items[1].count, b.items[1].count);
+
+        let empty = PresentConfig::from_str("[container]\ntype_id = 2854\n\n[[pools]]\nitems = []\n").unwrap();
+        assert!(empty.validate(&ItemCatalog::new()).is_err());
+    }
 }
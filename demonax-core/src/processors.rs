@@ -2,4 +2,1714 @@
 //!
 //! This module provides high-level processing functions for game data.
 //! All data is stored in SQLite - no intermediate CSV files needed.
-//! Rendering functions query the database directly.
\ No newline at end of file
+//! Rendering functions query the database directly.
+//!
+//! File types are plugged in through the [`GameFileParser`] trait and looked up
+//! by name in a [`ParserRegistry`], so a new file type only needs a parser
+//! implementation and a `register` call instead of a new CLI command.
+//!
+//! The `process_*` functions are what the `demonax update-*` commands run, and
+//! [`give_presents`], [`render_equipment`], [`combat_calc`] and [`level_calc`]
+//! what `give-present`, `render-equipment`, `combat-calc` and `level-calc` run.
+//! They are the entry points for using the library directly:
+//!
+//! ```no_run
+//! use demonax_core::database::Database;
+//! use demonax_core::processors::{process_creatures, process_items_core, GamePaths, Reporter};
+//!
+//! let db = Database::new(std::path::Path::new("demonax.sqlite"))?;
+//! let game = GamePaths::discover("/home/tibia/game")?;
+//! let reporter = Reporter::new(1);
+//! process_creatures(&db, &game, &reporter)?;
+//! process_items_core(&db, &game, &reporter)?;
+//! # Ok::<(), demonax_core::DemonaxError>(())
+//! ```
+
+use crate::catalog::ItemCatalog;
+use crate::combat::{estimate_combat, player_max_hp, CombatEstimate, CreatureCombat, DefenderProfile};
+use crate::database::Database;
+use crate::error::{DemonaxError, Result};
+use crate::file_utils::{find_dated_directories, find_files_with_extension};
+use crate::game_math::{
+    death_experience_loss, death_loss_fraction, experience_for_level, is_promoted, level_for_experience, ExperienceConfig,
+    LevelPlan, MAX_BLESSINGS,
+};
+use crate::geo::{Region, Town, default_regions, default_towns};
+use crate::models::{HarvestingData, House, ItemPrice, MapDoor, QuestChest, Raid, Spawn};
+use crate::parsers::{
+    parse_evt_file, parse_house_files, parse_magic_cc, parse_map_doors_file, parse_map_sector_file, parse_monster_db, parse_npc_file,
+    parse_npc_rune_selling, parse_npc_spell_teaching, parse_objects_srv, parse_raid_log,
+};
+use crate::player_selection::PlayerSelection;
+use crate::present::{
+    apply_present_per_account, apply_present_with_diff, apply_winners, find_usr_files_by_player, read_winners_csv, GiftResult,
+    GiftSummary, PresentConfig,
+};
+use crate::raid::describe_raid_creatures;
+use crate::rendering::{render_player_equipment, ItemImageCache, RenderConfig};
+use crate::runes::{check_rune_items, RuneOverrides};
+use crate::sandbox::PlayerFilter;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// A file or directory the processors expect in a game directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEntry {
+    Dat,
+    /// Creature and raid files (*.mon, *.evt)
+    Mon,
+    Npc,
+    /// Map sectors (*.sec)
+    Map,
+    Usr,
+    ObjectsSrv,
+}
+
+impl GameEntry {
+    pub const ALL: [GameEntry; 6] = [Self::Dat, Self::Mon, Self::Npc, Self::Map, Self::Usr, Self::ObjectsSrv];
+
+    /// Path relative to the game directory
+    pub fn relative_path(self) -> &'static str {
+        match self {
+            Self::Dat => "dat",
+            Self::Mon => "mon",
+            Self::Npc => "npc",
+            Self::Map => "map",
+            Self::Usr => "usr",
+            Self::ObjectsSrv => "dat/objects.srv",
+        }
+    }
+}
+
+impl std::fmt::Display for GameEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.relative_path())
+    }
+}
+
+/// A game directory and which of the expected files it has, checked once by
+/// [`GamePaths::discover`] and shared by all processors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GamePaths {
+    pub root: PathBuf,
+    /// magic.cc from the game sources, if it is next to or inside the game directory
+    pub magic_cc: Option<PathBuf>,
+    /// Game files the processors skip
+    pub exclude: ExcludeConfig,
+    /// Seasons and disabled raids, stored with the raids
+    pub raids: RaidConfig,
+    /// Bestiary monster IDs that differ from their creature's race
+    pub bestiary: BestiaryConfig,
+    /// Creature images that aren't named after the creature
+    pub images: ImageConfig,
+    /// Map regions quests are classified into
+    pub regions: Vec<Region>,
+    /// Towns NPCs, quests, raids, spawns and houses are assigned to
+    pub towns: Vec<Town>,
+    missing: Vec<GameEntry>,
+}
+
+/// .mon files that aren't real monsters: effects, event and test creatures
+pub const DEFAULT_EXCLUDED_CREATURES: &[&str] = &[
+    "deathslicer.mon",
+    "slime2.mon",
+    "illusion.mon",
+    "butterflyblue.mon",
+    "butterflyyellow.mon",
+    "butterflyred.mon",
+    "butterflypurple.mon",
+    "mimic.mon",
+    "halloweenhare.mon",
+    "flamethrower.mon",
+    "magicthrower.mon",
+    "plaguethrower.mon",
+    "shredderthrower.mon",
+    "gamemaster.mon",
+    "human.mon",
+];
+
+/// .evt files that aren't real raids, e.g. seasonal events
+pub const DEFAULT_EXCLUDED_RAIDS: &[&str] = &["halloweenhare.evt"];
+
+/// File names to skip, from the `[exclude]` section of demonax.toml. Lists
+/// left out keep their defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExcludeConfig {
+    /// mon/*.mon files skipped by update-creatures
+    pub creatures: Vec<String>,
+    /// mon/*.evt files skipped by update-raids
+    pub raids: Vec<String>,
+}
+
+impl Default for ExcludeConfig {
+    fn default() -> Self {
+        Self {
+            creatures: DEFAULT_EXCLUDED_CREATURES.iter().map(|name| name.to_string()).collect(),
+            raids: DEFAULT_EXCLUDED_RAIDS.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+impl ExcludeConfig {
+    /// Skip nothing
+    pub fn none() -> Self {
+        Self { creatures: Vec::new(), raids: Vec::new() }
+    }
+}
+
+/// Seasons recognized in .evt file names that no season in the config lists
+pub const SEASON_KEYWORDS: &[(&str, &[&str])] = &[
+    ("halloween", &["halloween", "pumpkin"]),
+    ("christmas", &["christmas", "xmas", "santa"]),
+    ("easter", &["easter"]),
+];
+
+/// Seasonal and disabled raids, from the `[raids]` section of demonax.toml
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RaidConfig {
+    /// Season name to the .evt files run in that season
+    pub seasons: BTreeMap<String, Vec<String>>,
+    /// .evt files of raids that aren't run; they are stored but left out of exports
+    pub disabled: Vec<String>,
+    /// The game server's raid log, relative to the game directory; `update-raids`
+    /// stores the raid starts in it
+    pub log: Option<PathBuf>,
+}
+
+impl RaidConfig {
+    /// Season of an .evt file: the season listing it, or else the first season
+    /// whose keyword is in the file name
+    pub fn season_of(&self, file_name: &str) -> Option<String> {
+        if let Some((season, _)) = self.seasons.iter().find(|(_, files)| files.iter().any(|file| file == file_name)) {
+            return Some(season.clone());
+        }
+        let lowercase = file_name.to_lowercase();
+        SEASON_KEYWORDS
+            .iter()
+            .find(|(_, keywords)| keywords.iter().any(|keyword| lowercase.contains(keyword)))
+            .map(|(season, _)| season.to_string())
+    }
+
+    pub fn is_enabled(&self, file_name: &str) -> bool {
+        !self.disabled.iter().any(|file| file == file_name)
+    }
+}
+
+/// Bestiary monster IDs of the .usr files that aren't the race number of their
+/// creature, from the `[bestiary]` section of demonax.toml. Other monster IDs
+/// are taken to be race numbers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BestiaryConfig {
+    /// (monster ID, race) pairs
+    pub races: Vec<[i32; 2]>,
+}
+
+/// Creatures drawn with another creature's image
+pub const DEFAULT_IMAGE_OVERRIDES: &[(&str, &str)] = &[
+    ("demodras", "dragonlord"),
+    ("dharalion", "elfarcanist"),
+    ("loraith", "yalaharipriest"),
+    ("leon", "heroguardian"),
+    ("grorlam", "stonegolem"),
+    ("necropharus", "necromancer"),
+    ("beholder", "bonelord"),
+    ("theoldwidow", "giantspider"),
+];
+
+/// Creature images that aren't named after the creature's short name, from the
+/// `[images]` section of demonax.toml. Overrides given there replace the
+/// built-in ones.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageConfig {
+    /// Creature short name to image name, without the file extension
+    pub overrides: BTreeMap<String, String>,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            overrides: DEFAULT_IMAGE_OVERRIDES
+                .iter()
+                .map(|(short_name, image)| (short_name.to_string(), image.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl GamePaths {
+    /// Look for the expected subdirectories and files below `root`. Missing ones
+    /// are recorded (see [`missing`](Self::missing)) rather than an error, since
+    /// most commands need only a few of them; a missing `root` is an error.
+    pub fn discover(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        if !root.is_dir() {
+            return Err(DemonaxError::NotFound(format!("Game directory not found at {:?}", root)));
+        }
+
+        let missing = GameEntry::ALL.into_iter().filter(|entry| !root.join(entry.relative_path()).exists()).collect();
+        let magic_cc = [Some(root.join("src/magic.cc")), Some(root.join("magic.cc")), root.parent().map(|p| p.join("src/magic.cc"))]
+            .into_iter()
+            .flatten()
+            .find(|p| p.exists());
+
+        Ok(Self {
+            root,
+            magic_cc,
+            exclude: ExcludeConfig::default(),
+            raids: RaidConfig::default(),
+            bestiary: BestiaryConfig::default(),
+            images: ImageConfig::default(),
+            regions: default_regions(),
+            towns: default_towns(),
+            missing,
+        })
+    }
+
+    /// Expected entries that weren't found
+    pub fn missing(&self) -> &[GameEntry] {
+        &self.missing
+    }
+
+    pub fn has(&self, entry: GameEntry) -> bool {
+        !self.missing.contains(&entry)
+    }
+
+    /// Path of an entry (whether or not it exists)
+    pub fn path(&self, entry: GameEntry) -> PathBuf {
+        self.root.join(entry.relative_path())
+    }
+
+    /// Path of an entry that a processor can't do without
+    pub fn require(&self, entry: GameEntry) -> Result<PathBuf> {
+        if self.has(entry) {
+            Ok(self.path(entry))
+        } else {
+            Err(DemonaxError::NotFound(format!("{} not found in game directory {:?}", entry, self.root)))
+        }
+    }
+
+    /// harvesting.csv in the game directory, or else the working directory
+    pub fn harvesting_csv(&self) -> Option<PathBuf> {
+        [self.root.join("harvesting.csv"), self.root.join("dat/harvesting.csv"), PathBuf::from("./harvesting.csv")]
+            .into_iter()
+            .find(|p| p.exists())
+    }
+}
+
+/// Progress messages of the processors, filtered by a quiet level
+/// (0 = messages and warnings, 1 = warnings only, 2 = nothing).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Reporter {
+    pub quiet: u8,
+}
+
+impl Reporter {
+    pub fn new(quiet: u8) -> Self {
+        Self { quiet }
+    }
+
+    pub fn info(&self, message: impl Display) {
+        if self.quiet == 0 {
+            info!("{}", message);
+        }
+    }
+
+    pub fn warn(&self, message: impl Display) {
+        if self.quiet < 2 {
+            warn!("{}", message);
+        }
+    }
+}
+
+/// Tables [`process_creatures`] deletes and writes again: each parsed creature's
+/// loot, flags, skills and spells, and the tables derived from them or from config
+pub const CREATURES_REPLACES: &[&str] = &[
+    "creature_loot",
+    "creature_flags",
+    "creature_skills",
+    "creature_spells",
+    "item_loot_sources",
+    "bestiary_races",
+    "image_overrides",
+];
+
+/// Creatures, their loot, skills and spells from mon/*.mon. Summon spells are
+/// named once all creatures are known.
+/// Returns the number of creatures stored.
+pub fn process_creatures(db: &Database, game: &GamePaths, reporter: &Reporter) -> Result<usize> {
+    game.require(GameEntry::Mon)?;
+    let processed = db.process_mon_files(&game.root, &game.exclude.creatures, reporter.quiet)? as usize;
+    let summons = db.name_summoned_creatures(&db.get_creature_catalog()?)?;
+    reporter.info(format_args!("Named the creatures of {} summon spells", summons));
+    db.replace_bestiary_races(&game.bestiary.races)?;
+    db.replace_image_overrides(&game.images.overrides)?;
+    Ok(processed)
+}
+
+/// What [`process_items_core`] stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ItemsCoreSummary {
+    pub items: usize,
+    /// `None` when there is no npc directory
+    pub prices: Option<usize>,
+}
+
+/// Items from dat/objects.srv and NPC trade offers from npc/*.npc.
+pub fn process_items_core(db: &Database, game: &GamePaths, reporter: &Reporter) -> Result<ItemsCoreSummary> {
+    let objects_srv_path = game.require(GameEntry::ObjectsSrv)?;
+    reporter.info("Parsing objects.srv");
+    let items = parse_objects_srv(&objects_srv_path)?;
+    reporter.info(format_args!("Found {} items with 'Take' flag", items.len()));
+
+    let inserted_count = db.insert_or_update_items(&items)?;
+    reporter.info(format_args!("Inserted/updated {} items in database", inserted_count));
+
+    if !game.has(GameEntry::Npc) {
+        reporter.warn(format_args!("NPC directory not found at {:?}, skipping price processing", game.path(GameEntry::Npc)));
+        return Ok(ItemsCoreSummary { items: inserted_count, prices: None });
+    }
+
+    let price_count = NpcPriceParser.process(&game.root, db, reporter.quiet)?;
+    reporter.info(format_args!("Inserted {} price entries in database", price_count));
+    process_towns(db, game, reporter)?;
+    Ok(ItemsCoreSummary { items: inserted_count, prices: Some(price_count) })
+}
+
+/// Quest regions whose rewards aren't linked to items unless asked for
+pub const DEFAULT_SKIPPED_QUEST_REGIONS: &[&str] = &["rookgaard"];
+
+/// Link items to the quests that reward them, except quests in `skipped_regions`.
+/// Returns the number of items updated.
+pub fn process_items_quests(db: &Database, skipped_regions: &[String], reporter: &Reporter) -> Result<usize> {
+    db.update_items_with_quest_rewards(skipped_regions, reporter.quiet)
+}
+
+/// Quest chests from map/*.sec, named from an optional quest CSV.
+/// Returns the number of quests stored.
+pub fn process_quest_overview(db: &Database, game: &GamePaths, quest_csv: Option<&Path>, reporter: &Reporter) -> Result<usize> {
+    let quest_names = match quest_csv {
+        Some(csv_path) => {
+            reporter.info(format_args!("Loading quest names from CSV: {:?}", csv_path));
+            match Database::load_quest_names_from_csv(csv_path) {
+                Ok(names) => {
+                    reporter.info(format_args!("Loaded {} quest names from CSV", names.len()));
+                    Some(names)
+                }
+                Err(e) => {
+                    reporter.warn(format_args!("Failed to load quest CSV: {}. Continuing without quest names.", e));
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    game.require(GameEntry::Map)?;
+    let processed = QuestChestParser { quest_names, regions: game.regions.clone() }.process(&game.root, db, reporter.quiet)?;
+    let doors = DoorParser.process(&game.root, db, reporter.quiet)?;
+    reporter.info(format_args!("Stored {} doors", doors));
+    process_towns(db, game, reporter)?;
+    Ok(processed)
+}
+
+/// Tables [`process_towns`] deletes and writes again
+pub const TOWNS_REPLACES: &[&str] = &["towns"];
+
+/// Store the towns of `game` and assign NPCs, quests, raids, spawns and houses to them.
+/// Returns the number of rows given a town.
+pub fn process_towns(db: &Database, game: &GamePaths, reporter: &Reporter) -> Result<usize> {
+    let assigned = db.assign_towns(&game.towns)?;
+    reporter.info(format_args!("Assigned {} NPCs, quests, raids, spawns and houses to {} towns", assigned, game.towns.len()));
+    Ok(assigned)
+}
+
+/// Raids from mon/*.evt. Returns the number of raids stored.
+pub fn process_raids(db: &Database, game: &GamePaths, reporter: &Reporter) -> Result<usize> {
+    game.require(GameEntry::Mon)?;
+    let processed = RaidParser::for_game(game).process(&game.root, db, reporter.quiet)?;
+    if let Some(log) = &game.raids.log {
+        process_raid_log(db, &game.root.join(log), reporter)?;
+    }
+    process_towns(db, game, reporter)?;
+    Ok(processed)
+}
+
+/// Raid starts from the game server's raid log, kept across runs so the log can
+/// be rotated. Returns the number of starts not stored before.
+pub fn process_raid_log(db: &Database, log: &Path, reporter: &Reporter) -> Result<usize> {
+    if !log.exists() {
+        return Err(DemonaxError::NotFound(format!("Raid log not found: {:?}", log)));
+    }
+    let executions = parse_raid_log(log)?;
+    let stored = db.insert_raid_executions(&executions)?;
+    reporter.info(format_args!("Stored {} new raid starts of {} in {:?}", stored, executions.len(), log));
+
+    let known: std::collections::HashSet<String> =
+        db.get_raid_list(None, true)?.into_iter().map(|raid| raid.name.to_lowercase()).collect();
+    let mut unknown: Vec<&str> =
+        executions.iter().map(|e| e.raid_name.as_str()).filter(|name| !known.contains(*name)).collect();
+    unknown.sort_unstable();
+    unknown.dedup();
+    if !unknown.is_empty() {
+        reporter.warn(format_args!("Raid log names raids without an .evt file: {}", unknown.join(", ")));
+    }
+    Ok(stored)
+}
+
+/// Harvesting rules from a CSV file, by default [`GamePaths::harvesting_csv`].
+/// Returns the number of entries stored.
+pub fn process_harvesting(db: &Database, game: &GamePaths, harvesting_csv: Option<&Path>, reporter: &Reporter) -> Result<usize> {
+    let csv_path = match harvesting_csv {
+        Some(path) if path.exists() => path.to_path_buf(),
+        Some(path) => {
+            return Err(DemonaxError::NotFound(format!("Custom harvesting CSV path does not exist: {:?}", path)));
+        }
+        None => game
+            .harvesting_csv()
+            .ok_or_else(|| DemonaxError::NotFound("harvesting.csv not found in any standard location".to_string()))?,
+    };
+    reporter.info(format_args!("Reading harvesting data from {:?}", csv_path));
+
+    let csv_error = |e: csv::Error| DemonaxError::Parse(format!("Invalid harvesting CSV {:?}: {}", csv_path, e));
+    let harvesting_data = csv::Reader::from_path(&csv_path)
+        .map_err(csv_error)?
+        .deserialize()
+        .collect::<std::result::Result<Vec<HarvestingData>, _>>()
+        .map_err(csv_error)?;
+    reporter.info(format_args!("Parsed {} harvesting entries from CSV", harvesting_data.len()));
+
+    let inserted = db.insert_harvesting_data(&harvesting_data)?;
+    reporter.info(format_args!("Inserted {} harvesting entries into database", inserted));
+    Ok(inserted)
+}
+
+/// What [`process_spells`] stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpellsSummary {
+    pub spells: usize,
+    pub teachers: usize,
+    pub rune_sellers: usize,
+}
+
+/// Spells from magic.cc (by default [`GamePaths::magic_cc`]) with optional rune
+/// item overrides, spell teachers and rune sellers from npc/*.npc, and the
+/// content gaps (untaught spells, unsold runes) they leave.
+pub fn process_spells(
+    db: &Database,
+    game: &GamePaths,
+    magic_cc: Option<&Path>,
+    rune_overrides: Option<&Path>,
+    reporter: &Reporter,
+) -> Result<SpellsSummary> {
+    let magic_cc_path = match magic_cc {
+        Some(path) if path.exists() => Some(path.to_path_buf()),
+        Some(path) => {
+            reporter.warn(format_args!("Custom magic.cc path does not exist: {:?}", path));
+            None
+        }
+        None => game.magic_cc.clone(),
+    };
+
+    let mut spells = match magic_cc_path {
+        Some(magic_path) => {
+            reporter.info(format_args!("Parsing magic.cc from {:?}", magic_path));
+            parse_magic_cc(&magic_path)?
+        }
+        None => {
+            reporter.warn("magic.cc not found, skipping spell parsing");
+            reporter.warn("Note: Spell data requires access to game source code");
+            vec![]
+        }
+    };
+
+    if let Some(path) = rune_overrides {
+        let changed = RuneOverrides::from_file(path)?.apply(&mut spells)?;
+        reporter.info(format_args!("Applied {} rune item overrides from {:?}", changed, path));
+    }
+
+    // Cross-check the rune items against objects.srv
+    if spells.iter().any(|spell| spell.is_rune) && game.has(GameEntry::ObjectsSrv) {
+        let mismatches = check_rune_items(&spells, &parse_objects_srv(&game.path(GameEntry::ObjectsSrv))?);
+        for mismatch in &mismatches {
+            reporter.warn(mismatch);
+        }
+        if !mismatches.is_empty() {
+            reporter.warn("Fix mismatched rune items with --rune-overrides");
+        }
+    }
+
+    let mut summary = SpellsSummary::default();
+    if !spells.is_empty() {
+        summary.spells = db.insert_or_update_spells(&spells)?;
+        reporter.info(format_args!("Inserted/updated {} spells", summary.spells));
+    }
+
+    if game.has(GameEntry::Npc) {
+        reporter.info("Parsing .npc files for spell teaching data");
+        let npc_files = find_files_with_extension(&game.path(GameEntry::Npc), "npc")?;
+        let all_teachers: Vec<_> = npc_files
+            .par_iter()
+            .filter_map(|path| match parse_npc_spell_teaching(path) {
+                Ok(teachers) if !teachers.is_empty() => Some(teachers),
+                Ok(_) => None,
+                Err(e) => {
+                    reporter.warn(format_args!("Failed to parse {:?}: {}", path, e));
+                    None
+                }
+            })
+            .flatten()
+            .collect();
+        reporter.info(format_args!("Found {} spell teaching entries", all_teachers.len()));
+
+        for teacher in all_teachers.iter().filter(|t| t.unclassified_line.is_some()) {
+            reporter.warn(format_args!(
+                "Could not determine vocations for {} teaching spell {}: {}",
+                teacher.npc_name,
+                teacher.spell_id,
+                teacher.unclassified_line.as_deref().unwrap_or_default()
+            ));
+        }
+
+        summary.teachers = db.clear_and_insert_spell_teachers(&all_teachers)?;
+        reporter.info(format_args!("Processed {} spell teachers", summary.teachers));
+
+        reporter.info("Parsing .npc files for rune/wand/rod seller data");
+        let all_sellers: Vec<_> = npc_files
+            .par_iter()
+            .filter_map(|path| match parse_npc_rune_selling(path) {
+                Ok(sellers) if !sellers.is_empty() => Some(sellers),
+                Ok(_) => None,
+                Err(e) => {
+                    reporter.warn(format_args!("Failed to parse rune sellers from {:?}: {}", path, e));
+                    None
+                }
+            })
+            .flatten()
+            .collect();
+        reporter.info(format_args!("Found {} rune/wand/rod seller entries", all_sellers.len()));
+
+        summary.rune_sellers = db.clear_and_insert_rune_sellers(&all_sellers)?;
+        reporter.info(format_args!("Processed {} rune/wand/rod sellers", summary.rune_sellers));
+    }
+
+    if spells.is_empty() {
+        return Ok(summary);
+    }
+
+    let (run_id, gap_count) = db.record_content_gaps()?;
+    reporter.info(format_args!("Recorded {} content gaps as run {}", gap_count, run_id));
+
+    if reporter.quiet == 0 {
+        let untaught_spells = db.get_untaught_spells()?;
+        if !untaught_spells.is_empty() {
+            info!("Found {} spells without teachers:", untaught_spells.len());
+            for spell in untaught_spells {
+                info!("  - {} (ID: {}): {} - Level {}, {} mana", spell.name, spell.spell_id, spell.words, spell.level, spell.mana);
+            }
+        }
+
+        let unsold_runes = db.get_unsold_runes()?;
+        if !unsold_runes.is_empty() {
+            info!("Found {} runes without sellers:", unsold_runes.len());
+            for rune in unsold_runes {
+                info!("  - {} (ID: {}): {} - Rune Type ID: {:?}", rune.name, rune.spell_id, rune.words, rune.rune_type_id);
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Ingest every dated usr backup below `root` whose name matches `pattern`
+/// (`{date}` = YYYY-MM-DD), oldest first. Dates already in the database are
+/// skipped, except the newest one, which may have been interrupted.
+/// Returns the number of .usr files processed and the number of backups found.
+pub fn process_usr_batch(db: &Database, root: &Path, pattern: &str, reporter: &Reporter) -> Result<(usize, usize)> {
+    let folders = find_dated_directories(root, pattern)?;
+    if folders.is_empty() {
+        return Err(DemonaxError::NotFound(format!("No folders matching '{}' found in {:?}", pattern, root)));
+    }
+
+    // Re-running the newest date only adds the missing snapshots
+    let done = db.get_snapshot_dates()?;
+    let resume_date = done.last().cloned();
+
+    let mut total = 0;
+    for (date, dir) in &folders {
+        let date = date.format("%Y-%m-%d").to_string();
+        if done.contains(&date) && resume_date.as_ref() != Some(&date) {
+            reporter.info(format_args!("Skipping {:?} - snapshots for {} already exist", dir, date));
+            continue;
+        }
+
+        reporter.info(format_args!("Processing {:?} as {}", dir, date));
+        total += db.process_usr_files(dir, &date, reporter.quiet)? as usize;
+    }
+
+    Ok((total, folders.len()))
+}
+
+/// Run name of [`update_all`] in the run journal
+pub const UPDATE_ALL_RUN: &str = "update-all";
+
+/// Registered parsers run by [`update_all`], in order. Quest rewards are linked
+/// after both items and quests are stored.
+pub const UPDATE_ALL_PARSERS: &[&str] = &["npc-prices", "quests", "doors", "raids", "spawns", "houses"];
+
+/// Every update stage in dependency order, then (with a snapshot date) the .usr
+/// files in batches of `batch_size`. Each stage and batch is journaled, so an
+/// interrupted run resumes where it stopped; `restart` discards the journal first.
+pub fn update_all(
+    db: &Database,
+    game: &GamePaths,
+    snapshot_date: Option<&str>,
+    batch_size: usize,
+    restart: bool,
+    reporter: &Reporter,
+) -> Result<()> {
+    let registry = ParserRegistry::for_game(game);
+    let quiet = reporter.quiet;
+
+    if restart {
+        let cleared = db.clear_run_journal(UPDATE_ALL_RUN)?;
+        reporter.info(format_args!("Cleared {} journaled stages", cleared));
+    }
+
+    let log_stage = |stage: &str, batch: &str, result: Option<usize>| {
+        let label = if batch.is_empty() { stage.to_string() } else { format!("{} {}", stage, batch) };
+        match result {
+            Some(rows) => reporter.info(format_args!("Stage {} wrote {} rows", label, rows)),
+            None => reporter.info(format_args!("Stage {} already completed, skipping", label)),
+        }
+    };
+
+    let result = db.run_journaled(UPDATE_ALL_RUN, "creatures", "", || process_creatures(db, game, reporter))?;
+    log_stage("creatures", "", result);
+
+    let result = db.run_journaled(UPDATE_ALL_RUN, "items", "", || {
+        db.insert_or_update_items(&parse_objects_srv(&game.require(GameEntry::ObjectsSrv)?)?)
+    })?;
+    log_stage("items", "", result);
+
+    for &name in UPDATE_ALL_PARSERS {
+        let result = db.run_journaled(UPDATE_ALL_RUN, name, "", || registry.process(name, &game.root, db, quiet))?;
+        log_stage(name, "", result);
+    }
+
+    process_towns(db, game, reporter)?;
+
+    let skipped_regions: Vec<String> = DEFAULT_SKIPPED_QUEST_REGIONS.iter().map(|name| name.to_string()).collect();
+    let result =
+        db.run_journaled(UPDATE_ALL_RUN, "items-quests", "", || process_items_quests(db, &skipped_regions, reporter))?;
+    log_stage("items-quests", "", result);
+
+    if let Some(snapshot_date) = snapshot_date {
+        let mut files = find_files_with_extension(&game.require(GameEntry::Usr)?, "usr")?;
+        files.sort();
+        for (index, chunk) in files.chunks(batch_size.max(1)).enumerate() {
+            // Batches are keyed by date and position so a new date starts fresh
+            let batch = format!("{}#{}", snapshot_date, index);
+            let result = db.run_journaled(UPDATE_ALL_RUN, "usr", &batch, || {
+                Ok(db.process_usr_file_list(chunk, snapshot_date, quiet)? as usize)
+            })?;
+            log_stage("usr", &batch, result);
+        }
+    }
+
+    // A finished run leaves nothing to resume
+    db.clear_run_journal(UPDATE_ALL_RUN)?;
+    Ok(())
+}
+
+/// An update pipeline of this module, to ask which tables it replaces before running it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdatePipeline<'a> {
+    Creatures,
+    ItemsCore,
+    QuestOverview,
+    Raids,
+    Harvesting,
+    Spells,
+    /// One registered parser by name, then the towns
+    Parser(&'a str),
+    /// [`update_all`]
+    All,
+}
+
+impl UpdatePipeline<'_> {
+    /// Tables whose existing rows the pipeline deletes or rewrites, following the
+    /// stages it runs: the parsers' [`replaces`](GameFileParser::replaces),
+    /// [`CREATURES_REPLACES`] and [`TOWNS_REPLACES`]
+    pub fn replaces(self) -> Vec<&'static str> {
+        let registry = ParserRegistry::with_defaults();
+        let parser = |name: &str| registry.get(name).map(|p| p.replaces()).unwrap_or_default();
+        let mut tables = match self {
+            UpdatePipeline::Creatures => CREATURES_REPLACES.to_vec(),
+            UpdatePipeline::ItemsCore => [parser("npc-prices"), TOWNS_REPLACES].concat(),
+            UpdatePipeline::QuestOverview => [parser("quests"), parser("doors"), TOWNS_REPLACES].concat(),
+            UpdatePipeline::Raids => [parser("raids"), TOWNS_REPLACES].concat(),
+            UpdatePipeline::Harvesting => vec!["harvesting_data"],
+            UpdatePipeline::Spells => vec!["spell_teachers", "rune_sellers"],
+            UpdatePipeline::Parser(name) => [parser(name), TOWNS_REPLACES].concat(),
+            UpdatePipeline::All => {
+                let parsers = UPDATE_ALL_PARSERS.iter().flat_map(|name| parser(name)).copied();
+                CREATURES_REPLACES.iter().copied().chain(parsers).chain(TOWNS_REPLACES.iter().copied()).collect()
+            }
+        };
+        let mut seen = std::collections::HashSet::new();
+        tables.retain(|table| seen.insert(*table));
+        tables
+    }
+}
+
+/// Where the presents of [`give_presents`] come from
+#[derive(Debug, Clone, Copy)]
+pub enum PresentSource<'a> {
+    /// One present config for every player
+    Config(&'a Path),
+    /// A CSV of winners, each with their own present config
+    WinnersCsv(&'a Path),
+}
+
+/// How [`give_presents`] gives the presents
+#[derive(Debug, Clone, Default)]
+pub struct GiftOptions {
+    pub target_slot: i32,
+    /// Skip players the present would put over their capacity instead of gifting them anyway
+    pub skip_over_capacity: bool,
+    /// Check and report without writing any file
+    pub dry_run: bool,
+    /// One present per account, see [`apply_present_per_account`]
+    pub per_account: bool,
+    pub only_players: PlayerFilter,
+}
+
+/// What [`give_presents`] did to each player, with the lines it changed, and in total
+#[derive(Debug)]
+pub struct GiftRun {
+    pub results: Vec<(GiftResult, Vec<String>)>,
+    pub summary: GiftSummary,
+}
+
+/// Items of the database at `db_path` for checking container capacities and
+/// weights; empty, with a warning, when there is no database.
+pub fn item_catalog_or_empty(db_path: &Path, reporter: &Reporter) -> Result<ItemCatalog> {
+    if !db_path.exists() {
+        reporter.warn(format_args!("No database at {:?}, container capacities and weights are not checked", db_path));
+        return Ok(ItemCatalog::new());
+    }
+    Database::open_read_only(db_path)?.get_item_catalog()
+}
+
+/// Give the presents of `source` to the players of the .usr files below `usr_path`.
+/// Every present is checked against its container before any file is written.
+pub fn give_presents(
+    usr_path: &Path,
+    source: PresentSource,
+    catalog: &ItemCatalog,
+    options: &GiftOptions,
+    reporter: &Reporter,
+) -> Result<GiftRun> {
+    let (PresentSource::Config(source_path) | PresentSource::WinnersCsv(source_path)) = source;
+    if options.dry_run {
+        reporter.info(format_args!("Giving presents (DRY RUN) from {:?}", source_path));
+    } else {
+        reporter.info(format_args!("Giving presents from {:?}", source_path));
+    }
+    if !usr_path.exists() {
+        return Err(DemonaxError::NotFound(format!("usr path not found: {:?}", usr_path)));
+    }
+    if !source_path.exists() {
+        return Err(DemonaxError::NotFound(format!("Present config not found: {:?}", source_path)));
+    }
+
+    let GiftOptions { target_slot, skip_over_capacity, dry_run, per_account, ref only_players } = *options;
+    let results = match source {
+        PresentSource::WinnersCsv(winners_csv) => {
+            let mut winners = read_winners_csv(winners_csv)?;
+            for winner in &winners {
+                winner.present.validate(catalog).map_err(|e| {
+                    DemonaxError::Validation(format!("Winners CSV line {}: present does not fit its container: {}", winner.line, e))
+                })?;
+            }
+            winners.retain(|winner| only_players.allows(&winner.player_name));
+            let files = find_usr_files_by_player(usr_path)?;
+            let results = apply_winners(&winners, &files, target_slot, catalog, skip_over_capacity, dry_run);
+            for (winner, (result, _)) in winners.iter().zip(&results) {
+                reporter.info(format_args!(
+                    "Line {}: {} ({:?}): {}",
+                    winner.line,
+                    winner.player_name,
+                    winner.present_config,
+                    result.outcome()
+                ));
+            }
+            results
+        }
+        PresentSource::Config(present_config) => {
+            let config = PresentConfig::from_file(present_config)
+                .map_err(|e| DemonaxError::Config(format!("Failed to load present config: {}", e)))?;
+            config
+                .validate(catalog)
+                .map_err(|e| DemonaxError::Validation(format!("Present does not fit its container: {}", e)))?;
+            reporter.info(format_args!(
+                "Present: container {} with {} items, target slot {}",
+                config.container.type_id,
+                config.items.len(),
+                target_slot
+            ));
+
+            // .usr files are in XX/ subdirectories
+            let usr_files = if only_players.is_all() {
+                find_files_with_extension(usr_path, "usr")?
+            } else {
+                only_players.filter_files(find_usr_files_by_player(usr_path)?).into_iter().map(|(_, path)| path).collect()
+            };
+            reporter.info(format_args!("Found {} .usr files", usr_files.len()));
+
+            let results = if per_account {
+                apply_present_per_account(&usr_files, &config, target_slot, catalog, skip_over_capacity, dry_run)
+            } else {
+                usr_files
+                    .par_iter()
+                    .map(|path| apply_present_with_diff(path, &config, target_slot, catalog, skip_over_capacity, dry_run))
+                    .collect()
+            };
+            for (result, _) in &results {
+                report_gift_result(result, reporter);
+            }
+            results
+        }
+    };
+
+    let mut summary = GiftSummary::new();
+    for (result, _) in &results {
+        summary.add_result(result);
+    }
+    reporter.info("--- Summary ---");
+    reporter.info(format_args!("Total processed: {}", summary.total_processed));
+    reporter.info(format_args!("Gifted: {}", summary.gifted));
+    reporter.info(format_args!("Skipped: {}", summary.skipped));
+    reporter.info(format_args!("Over capacity: {}", summary.over_capacity));
+    reporter.info(format_args!("Errors: {}", summary.errors));
+    reporter.info(format_args!("Verification failures: {}", summary.verification_failures));
+    if dry_run {
+        reporter.info("(DRY RUN - no files were modified)");
+    }
+    Ok(GiftRun { results, summary })
+}
+
+/// Log one player's present; failed verifications are logged at any quiet level
+fn report_gift_result(result: &GiftResult, reporter: &Reporter) {
+    match result {
+        GiftResult::Gifted { player_name, .. } => reporter.info(format_args!("Gifted: {}", player_name)),
+        GiftResult::SlotOccupied { player_name, .. } => reporter.info(format_args!("Skipped (slot occupied): {}", player_name)),
+        GiftResult::OverCapacity { player_name, weight, capacity, gifted, .. } => reporter.warn(format_args!(
+            "{} {}: would carry {:.2} oz of {:.2} oz capacity",
+            if *gifted { "Gifted over capacity" } else { "Skipped (over capacity)" },
+            player_name,
+            *weight as f64 / 100.0,
+            *capacity as f64 / 100.0
+        )),
+        GiftResult::Error { player_name, error, .. } => reporter.warn(format_args!("Error for {}: {}", player_name, error)),
+        GiftResult::VerificationFailed { player_name, error, .. } => {
+            tracing::error!("Verification failed for {}: {}", player_name, error)
+        }
+    }
+}
+
+/// What [`render_equipment`] wrote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EquipmentRenderSummary {
+    pub rendered: usize,
+    pub errors: usize,
+    /// Item images served from the cache
+    pub cache_hits: u64,
+    /// Item images read from disk
+    pub cache_loads: u64,
+}
+
+/// Equipment images of the latest snapshot of `player_id` (default: every player
+/// `players` selects), rendered in parallel with `config`, whose catalog is
+/// replaced with the database's items. `image_cache` is the number of item
+/// images kept in memory.
+pub fn render_equipment(
+    db: &Database,
+    config: RenderConfig,
+    player_id: Option<i32>,
+    players: &PlayerSelection,
+    image_cache: usize,
+    reporter: &Reporter,
+) -> Result<EquipmentRenderSummary> {
+    reporter.info("Rendering player equipment images");
+    if !config.data_dir.exists() {
+        return Err(DemonaxError::NotFound(format!("Data directory not found: {:?}", config.data_dir)));
+    }
+    if let Some(template) = &config.template_path
+        && !template.exists()
+    {
+        return Err(DemonaxError::NotFound(format!("Template image not found: {:?}", template)));
+    }
+    if let Some(blank) = &config.blank_path
+        && !blank.exists()
+    {
+        return Err(DemonaxError::NotFound(format!("Blank image not found: {:?}", blank)));
+    }
+
+    match player_id {
+        Some(player_id) => reporter.info(format_args!("Fetching snapshot for player ID {}", player_id)),
+        None => reporter.info("Fetching all player snapshots from database"),
+    }
+    let mut snapshots = db.get_latest_snapshots(player_id)?;
+    snapshots.retain(|snapshot| players.matches(snapshot.player_id, &snapshot.player_name));
+    if snapshots.is_empty() {
+        return Err(DemonaxError::NotFound("No snapshots found in database".to_string()));
+    }
+    reporter.info(format_args!("Found {} player snapshot(s) to render", snapshots.len()));
+
+    let config = RenderConfig { catalog: db.get_item_catalog()?, ..config };
+    reporter.info("Loading template and blank images");
+    let (template, blank) = config.load_base_images()?;
+
+    // Load the images of items worn by several players up front
+    let cache = ItemImageCache::new(image_cache);
+    let preloaded = cache.preload(&config.data_dir, &snapshots, 2);
+    reporter.info(format_args!("Preloaded {} common item images", preloaded));
+
+    let results: Vec<_> = snapshots
+        .par_iter()
+        .map(|snapshot| {
+            (snapshot, render_player_equipment(snapshot, &config, &template, &blank, &cache, reporter.quiet))
+        })
+        .collect();
+
+    let mut summary = EquipmentRenderSummary::default();
+    for (snapshot, result) in results {
+        match result {
+            Ok(output_path) => {
+                summary.rendered += 1;
+                reporter.info(format_args!("Rendered: {} -> {:?}", snapshot.player_name, output_path));
+            }
+            Err(e) => {
+                summary.errors += 1;
+                reporter.warn(format_args!("Failed to render {}: {}", snapshot.player_name, e));
+            }
+        }
+    }
+    (summary.cache_hits, summary.cache_loads) = cache.stats();
+
+    reporter.info("--- Summary ---");
+    reporter.info(format_args!("Successfully rendered: {}", summary.rendered));
+    reporter.info(format_args!("Errors: {}", summary.errors));
+    reporter.info(format_args!("Item image cache: {} hits, {} loads", summary.cache_hits, summary.cache_loads));
+    reporter.info(format_args!("Output directory: {:?}", config.output_dir));
+    Ok(summary)
+}
+
+/// The player [`combat_calc`] puts against a creature: the latest snapshot of
+/// `player_id`, else a player of `level` and `vocation`. `shielding` and
+/// `blessings` override the snapshot's.
+#[derive(Debug, Clone, Default)]
+pub struct CombatPlayer {
+    pub player_id: Option<i32>,
+    pub level: i32,
+    pub vocation: String,
+    /// Default: the snapshot's, else 10
+    pub shielding: Option<i32>,
+    pub armor: i32,
+    pub shield_defense: i32,
+    /// Default: the snapshot's, else none
+    pub blessings: Option<i32>,
+}
+
+/// A creature's damage against a player, and what dying would cost the player
+#[derive(Debug, Clone)]
+pub struct CombatReport {
+    pub creature: CreatureCombat,
+    /// Player name, or "Player" without a snapshot
+    pub player: String,
+    pub level: i32,
+    pub vocation: String,
+    pub experience: i64,
+    pub defender: DefenderProfile,
+    pub estimate: CombatEstimate,
+    pub promoted: bool,
+    pub blessings: i32,
+}
+
+/// How much damage `creature` does to `player` per turn and how many turns the player lasts.
+pub fn combat_calc(db: &Database, creature: &str, player: &CombatPlayer) -> Result<CombatReport> {
+    let Some(creature_combat) = db.get_creature_combat(creature)? else {
+        return Err(DemonaxError::NotFound(format!("Creature '{}' not found (run update-creatures first)", creature)));
+    };
+
+    let (name, level, experience, vocation, snapshot_shielding, snapshot_blessings) = match player.player_id {
+        Some(player_id) => {
+            let Some(card) = db.get_player_card(player_id)? else {
+                return Err(DemonaxError::NotFound(format!("No snapshots found for player {}", player_id)));
+            };
+            let shielding = card.skills.iter().find(|(name, _)| name == "Shielding").map(|(_, value)| *value);
+            (card.player_name, card.level, card.experience, card.vocation, shielding, card.blessings)
+        }
+        None => ("Player".to_string(), player.level, experience_for_level(player.level), player.vocation.clone(), None, None),
+    };
+    let defender = DefenderProfile {
+        hp: player_max_hp(level, &vocation),
+        armor: player.armor,
+        shielding: player.shielding.or(snapshot_shielding).unwrap_or(10),
+        shield_defense: player.shield_defense,
+    };
+    let estimate = estimate_combat(&creature_combat, &defender);
+    Ok(CombatReport {
+        creature: creature_combat,
+        player: name,
+        level,
+        promoted: is_promoted(&vocation),
+        vocation,
+        experience,
+        defender,
+        estimate,
+        blessings: player.blessings.or(snapshot_blessings).unwrap_or(0).clamp(0, MAX_BLESSINGS),
+    })
+}
+
+impl Display for CombatReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (creature, defender, estimate) = (&self.creature, &self.defender, &self.estimate);
+        writeln!(
+            f,
+            "{} (attack {}, skill {}) vs {} (level {} {}, {} HP, armor {}, shielding {}, shield defense {})",
+            creature.name, creature.attack, creature.skill, self.player, self.level, self.vocation,
+            defender.hp, defender.armor, defender.shielding, defender.shield_defense
+        )?;
+        writeln!(f, "{:<24} {:>8} {:>10} {:>8}", "Source", "Chance", "Avg/turn", "Max")?;
+        writeln!(f, "{:<24} {:>8} {:>10.1} {:>8}", "Melee", "100%", estimate.melee_average, estimate.melee_max)?;
+        for spell in &estimate.spells {
+            writeln!(
+                f,
+                "{:<24} {:>7.1}% {:>10.1} {:>8}",
+                spell.name, spell.chance * 100.0, spell.average_per_turn, spell.max_damage
+            )?;
+        }
+        writeln!(f, "Average damage per turn: {:.1} (worst case {})", estimate.average_per_turn, estimate.max_per_turn)?;
+        match (estimate.turns_to_kill, estimate.worst_case_turns) {
+            (Some(average), Some(worst)) => {
+                writeln!(f, "Turns to kill: {:.1} on average, {} in the worst case", average, worst)?
+            }
+            _ => writeln!(f, "{} does no damage to this player", creature.name)?,
+        }
+        writeln!(
+            f,
+            "Dying costs {} experience ({:.1}%, {}, {} of {} blessings)",
+            death_experience_loss(self.experience, self.promoted, self.blessings),
+            death_loss_fraction(self.promoted, self.blessings) * 100.0,
+            if self.promoted { "promoted" } else { "not promoted" },
+            self.blessings,
+            MAX_BLESSINGS
+        )
+    }
+}
+
+/// What [`level_calc`] plans: from a player's latest snapshot, at the pace of
+/// their last `days` days of snapshots, or from the start of `level`
+#[derive(Debug, Clone, Default)]
+pub struct LevelCalcRequest {
+    pub player: Option<String>,
+    pub level: Option<i32>,
+    /// Default: the level after the current one
+    pub target_level: Option<i32>,
+    /// Base experience per hour to estimate the hours with
+    pub exp_per_hour: Option<i64>,
+    pub days: i64,
+}
+
+/// The experience a player or level needs to reach a target level
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelReport {
+    /// Player name, or "Level N"
+    #[serde(skip)]
+    pub name: String,
+    #[serde(flatten)]
+    pub plan: LevelPlan,
+    /// `None` without a player, or when they gained nothing in the last days
+    pub base_experience_per_day: Option<f64>,
+    /// Days needed at that pace
+    pub days: Option<f64>,
+}
+
+/// Plan the experience to the target level of `request` under the stages of
+/// `experience`. The database at `db_path` is only opened for a player.
+pub fn level_calc(db_path: &Path, request: &LevelCalcRequest, experience: &ExperienceConfig) -> Result<LevelReport> {
+    let (name, current, base_experience_per_day) = match (&request.player, request.level) {
+        (Some(player), _) => {
+            let db = Database::open_read_only(db_path)?;
+            let Some(player_id) = db.find_player_id(player)? else {
+                return Err(DemonaxError::NotFound(format!("Player '{}' not found (run process-usr first)", player)));
+            };
+            let Some(card) = db.get_player_card(player_id)? else {
+                return Err(DemonaxError::NotFound(format!("No snapshots found for player {}", player)));
+            };
+            let pace = experience.base_experience_per_day(&db.get_experience_history(player_id, request.days)?);
+            (card.player_name, card.experience, pace)
+        }
+        (None, Some(level)) => (format!("Level {}", level), experience_for_level(level), None),
+        (None, None) => return Err(DemonaxError::Validation("a player or a level is required".to_string())),
+    };
+    let target_level = request.target_level.unwrap_or(level_for_experience(current) + 1);
+    let plan = experience.level_plan(current, target_level, request.exp_per_hour.map(|per_hour| per_hour as f64));
+    let days = base_experience_per_day.map(|per_day| plan.base_experience_needed / per_day);
+    Ok(LevelReport { name, plan, base_experience_per_day, days })
+}
+
+/// A game file type that can be discovered, parsed and stored.
+pub trait GameFileParser: Send + Sync {
+    /// Record produced by parsing a single file.
+    type Record: Send;
+
+    /// Name used to select the parser, e.g. "raids".
+    fn name(&self) -> &'static str;
+
+    /// Subdirectory of the game directory holding the files, e.g. "mon".
+    fn directory(&self) -> &'static str;
+
+    /// File extension without the leading dot, e.g. "evt".
+    fn extension(&self) -> &'static str;
+
+    /// Whether a discovered file should be parsed. Defaults to all files.
+    fn accept(&self, _path: &Path) -> bool {
+        true
+    }
+
+    /// Parse one file into zero or more records.
+    fn parse(&self, path: &Path) -> Result<Vec<Self::Record>>;
+
+    /// Store all parsed records. Returns the number of rows written.
+    fn persist(&self, db: &Database, records: &[Self::Record], quiet: u8) -> Result<usize>;
+
+    /// Tables whose existing rows [`persist`](Self::persist) deletes and writes
+    /// again, or rewrites from the parsed files. Defaults to none.
+    fn replaces(&self) -> &'static [&'static str] {
+        &[]
+    }
+}
+
+/// Object-safe view of a [`GameFileParser`] used by the registry.
+pub trait FileProcessor: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Human-readable source description, e.g. "mon/*.evt".
+    fn source(&self) -> String;
+
+    /// Tables the processor replaces wholesale, see [`GameFileParser::replaces`].
+    fn replaces(&self) -> &'static [&'static str];
+
+    /// Discover, parse and persist all files below `game_path`.
+    fn process(&self, game_path: &Path, db: &Database, quiet: u8) -> Result<usize>;
+}
+
+impl<P: GameFileParser> FileProcessor for P {
+    fn name(&self) -> &'static str {
+        GameFileParser::name(self)
+    }
+
+    fn source(&self) -> String {
+        format!("{}/*.{}", self.directory(), self.extension())
+    }
+
+    fn replaces(&self) -> &'static [&'static str] {
+        GameFileParser::replaces(self)
+    }
+
+    fn process(&self, game_path: &Path, db: &Database, quiet: u8) -> Result<usize> {
+        let dir = game_path.join(self.directory());
+        let files: Vec<_> = find_files_with_extension(&dir, self.extension())?
+            .into_iter()
+            .filter(|path| self.accept(path))
+            .collect();
+
+        if quiet == 0 {
+            info!("Found {} {} files in {:?}", files.len(), self.extension(), dir);
+        }
+
+        // Parse all files in parallel, skipping files that fail
+        let parse_span = tracing::info_span!("parse", stage = GameFileParser::name(self)).entered();
+        let records: Vec<P::Record> = files
+            .par_iter()
+            .filter_map(|path| match self.parse(path) {
+                Ok(records) => Some(records),
+                Err(e) => {
+                    if quiet < 2 {
+                        warn!(file = %path.display(), "Failed to parse {:?}: {}", path, e);
+                    }
+                    None
+                }
+            })
+            .flatten()
+            .collect();
+        parse_span.exit();
+
+        if quiet == 0 {
+            info!("Parsed {} {} records", records.len(), GameFileParser::name(self));
+        }
+
+        tracing::info_span!("persist", stage = GameFileParser::name(self)).in_scope(|| self.persist(db, &records, quiet))
+    }
+}
+
+/// Named collection of file processors.
+#[derive(Default)]
+pub struct ParserRegistry {
+    processors: Vec<Box<dyn FileProcessor>>,
+}
+
+impl ParserRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with all built-in parsers.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(RaidParser::default());
+        registry.register(NpcPriceParser);
+        registry.register(QuestChestParser::default());
+        registry.register(DoorParser);
+        registry.register(SpawnParser);
+        registry.register(HouseParser::default());
+        registry
+    }
+
+    /// The default parsers, skipping the files excluded for `game`
+    pub fn for_game(game: &GamePaths) -> Self {
+        let mut registry = Self::with_defaults();
+        registry.register(RaidParser::for_game(game));
+        registry.register(QuestChestParser { quest_names: None, regions: game.regions.clone() });
+        registry
+    }
+
+    /// Add a parser. A parser with the same name replaces the existing one.
+    pub fn register<P: GameFileParser + 'static>(&mut self, parser: P) {
+        let name = GameFileParser::name(&parser);
+        self.processors.retain(|p| p.name() != name);
+        self.processors.push(Box::new(parser));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn FileProcessor> {
+        self.processors.iter().find(|p| p.name() == name).map(|p| p.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.processors.iter().map(|p| p.name()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn FileProcessor> {
+        self.processors.iter().map(|p| p.as_ref())
+    }
+
+    /// Run the named parser against a game directory.
+    pub fn process(&self, name: &str, game_path: &Path, db: &Database, quiet: u8) -> Result<usize> {
+        let processor = self.get(name).ok_or_else(|| {
+            DemonaxError::NotFound(format!(
+                "No parser named '{}' (available: {})",
+                name,
+                self.names().join(", ")
+            ))
+        })?;
+        processor.process(game_path, db, quiet)
+    }
+}
+
+/// Raid definitions from mon/*.evt.
+pub struct RaidParser {
+    /// File names to skip, e.g. seasonal events that are not real raids
+    pub excluded_files: Vec<String>,
+    /// Seasons and disabled raids
+    pub settings: RaidConfig,
+}
+
+impl Default for RaidParser {
+    fn default() -> Self {
+        Self {
+            excluded_files: ExcludeConfig::default().raids,
+            settings: RaidConfig::default(),
+        }
+    }
+}
+
+impl RaidParser {
+    /// The raid files and settings of `game`
+    pub fn for_game(game: &GamePaths) -> Self {
+        Self { excluded_files: game.exclude.raids.clone(), settings: game.raids.clone() }
+    }
+}
+
+impl GameFileParser for RaidParser {
+    type Record = Raid;
+
+    fn name(&self) -> &'static str {
+        "raids"
+    }
+
+    fn directory(&self) -> &'static str {
+        "mon"
+    }
+
+    fn extension(&self) -> &'static str {
+        "evt"
+    }
+
+    fn accept(&self, path: &Path) -> bool {
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        !self.excluded_files.iter().any(|excluded| excluded == file_name)
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<Raid>> {
+        let mut raid = parse_evt_file(path)?;
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        raid.season = self.settings.season_of(file_name);
+        raid.enabled = self.settings.is_enabled(file_name);
+        Ok(vec![raid])
+    }
+
+    fn persist(&self, db: &Database, records: &[Raid], _quiet: u8) -> Result<usize> {
+        // Name the creatures, known once update-creatures has run
+        let creatures = db.get_creature_catalog()?;
+        let raids: Vec<Raid> = records
+            .iter()
+            .cloned()
+            .map(|mut raid| {
+                if let Some(described) = describe_raid_creatures(&raid.spawn_composition_json, &creatures) {
+                    raid.creatures = described;
+                }
+                raid
+            })
+            .collect();
+        db.insert_or_update_raids(&raids)
+    }
+}
+
+/// NPC trade offers from npc/*.npc. Replaces all stored prices.
+pub struct NpcPriceParser;
+
+impl GameFileParser for NpcPriceParser {
+    type Record = ItemPrice;
+
+    fn name(&self) -> &'static str {
+        "npc-prices"
+    }
+
+    fn directory(&self) -> &'static str {
+        "npc"
+    }
+
+    fn extension(&self) -> &'static str {
+        "npc"
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<ItemPrice>> {
+        parse_npc_file(path)
+    }
+
+    fn persist(&self, db: &Database, records: &[ItemPrice], _quiet: u8) -> Result<usize> {
+        db.clear_and_insert_item_prices(records)
+    }
+
+    fn replaces(&self) -> &'static [&'static str] {
+        &["item_prices", "npcs"]
+    }
+}
+
+/// Quest chests from map/*.sec, with the region of each chest.
+pub struct QuestChestParser {
+    /// Optional quest names keyed by quest value
+    pub quest_names: Option<HashMap<i32, String>>,
+    pub regions: Vec<Region>,
+}
+
+impl Default for QuestChestParser {
+    fn default() -> Self {
+        Self { quest_names: None, regions: default_regions() }
+    }
+}
+
+impl GameFileParser for QuestChestParser {
+    type Record = QuestChest;
+
+    fn name(&self) -> &'static str {
+        "quests"
+    }
+
+    fn directory(&self) -> &'static str {
+        "map"
+    }
+
+    fn extension(&self) -> &'static str {
+        "sec"
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<QuestChest>> {
+        parse_map_sector_file(path)
+    }
+
+    fn persist(&self, db: &Database, records: &[QuestChest], quiet: u8) -> Result<usize> {
+        db.process_quest_chests(records, self.quest_names.as_ref(), &self.regions, quiet)
+    }
+
+    fn replaces(&self) -> &'static [&'static str] {
+        &["quests", "quest_keys"]
+    }
+}
+
+/// Locked, level and quest doors from map/*.sec, replacing the stored ones.
+pub struct DoorParser;
+
+impl GameFileParser for DoorParser {
+    type Record = MapDoor;
+
+    fn name(&self) -> &'static str {
+        "doors"
+    }
+
+    fn directory(&self) -> &'static str {
+        "map"
+    }
+
+    fn extension(&self) -> &'static str {
+        "sec"
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<MapDoor>> {
+        parse_map_doors_file(path)
+    }
+
+    fn persist(&self, db: &Database, records: &[MapDoor], _quiet: u8) -> Result<usize> {
+        db.replace_doors(records)
+    }
+
+    fn replaces(&self) -> &'static [&'static str] {
+        &["doors"]
+    }
+}
+
+/// Creature spawns from dat/monster.db, replacing the stored ones.
+pub struct SpawnParser;
+
+impl GameFileParser for SpawnParser {
+    type Record = Spawn;
+
+    fn name(&self) -> &'static str {
+        "spawns"
+    }
+
+    fn directory(&self) -> &'static str {
+        "dat"
+    }
+
+    fn extension(&self) -> &'static str {
+        "db"
+    }
+
+    fn accept(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|n| n == "monster.db")
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<Spawn>> {
+        parse_monster_db(path)
+    }
+
+    fn persist(&self, db: &Database, records: &[Spawn], _quiet: u8) -> Result<usize> {
+        db.replace_spawns(records)
+    }
+
+    fn replaces(&self) -> &'static [&'static str] {
+        &["spawns"]
+    }
+}
+
+/// Houses and current owners from dat/houses.dat (plus owners.dat and houseareas.dat).
+/// Persisting also records the day's ownership snapshot.
+pub struct HouseParser {
+    /// Date for the ownership snapshot (YYYY-MM-DD)
+    pub snapshot_date: String,
+}
+
+impl Default for HouseParser {
+    fn default() -> Self {
+        Self {
+            snapshot_date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+impl GameFileParser for HouseParser {
+    type Record = House;
+
+    fn name(&self) -> &'static str {
+        "houses"
+    }
+
+    fn directory(&self) -> &'static str {
+        "dat"
+    }
+
+    fn extension(&self) -> &'static str {
+        "dat"
+    }
+
+    fn accept(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|n| n == "houses.dat")
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<House>> {
+        parse_house_files(path)
+    }
+
+    fn persist(&self, db: &Database, records: &[House], quiet: u8) -> Result<usize> {
+        let written = db.insert_or_update_houses(records)?;
+        let snapshot = db.snapshot_house_owners(&self.snapshot_date)?;
+        if quiet == 0 {
+            info!("Recorded ownership of {} houses for {}", snapshot, self.snapshot_date);
+        }
+        Ok(written)
+    }
+
+    fn replaces(&self) -> &'static [&'static str] {
+        &["house_owners"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::export_house_list;
+    use crate::testdata::{ItemSpec, MonSpec, SyntheticGame, UsrSpec, evt_file, npc_file};
+
+    #[test]
+    fn test_registry_processes_raids_with_exclusions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let game = SyntheticGame::create(temp_dir.path()).unwrap();
+        game.add_evt("orcs", &evt_file(86400, "Orcs!", &[(5, 3, 5)])).unwrap();
+        game.add_evt("halloweenhare", &evt_file(3600, "Hare!", &[(74, 1, 1)])).unwrap();
+        let db = Database::in_memory().unwrap();
+
+        let registry = ParserRegistry::with_defaults();
+        assert_eq!(registry.process("raids", &game.root, &db, 2).unwrap(), 1);
+        assert!(registry.process("mansions", &game.root, &db, 2).is_err());
+
+        let mut paths = GamePaths::discover(&game.root).unwrap();
+        paths.exclude = ExcludeConfig::none();
+        assert_eq!(ParserRegistry::for_game(&paths).process("raids", &game.root, &db, 2).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_raid_seasons_and_disabled_raids() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let game = SyntheticGame::create(temp_dir.path()).unwrap();
+        game.add_evt("orcs", &evt_file(86400, "Orcs!", &[(5, 3, 5)])).unwrap();
+        game.add_evt("santaraid", &evt_file(86400, "Ho ho!", &[(5, 1, 2)])).unwrap();
+        game.add_evt("ghosts", &evt_file(86400, "Boo!", &[(5, 1, 2)])).unwrap();
+        let db = Database::in_memory().unwrap();
+
+        let mut paths = GamePaths::discover(&game.root).unwrap();
+        paths.raids = RaidConfig {
+            seasons: BTreeMap::from([("halloween".to_string(), vec!["ghosts.evt".to_string()])]),
+            disabled: vec!["ghosts.evt".to_string()],
+            ..RaidConfig::default()
+        };
+        assert_eq!(process_raids(&db, &paths, &Reporter::new(2)).unwrap(), 3);
+
+        let seasons: Vec<(String, Option<String>, bool)> = db
+            .get_raid_list(None, true)
+            .unwrap()
+            .into_iter()
+            .map(|raid| (raid.name, raid.season, raid.enabled))
+            .collect();
+        assert_eq!(
+            seasons,
+            vec![
+                ("ghosts".to_string(), Some("halloween".to_string()), false),
+                ("orcs".to_string(), None, true),
+                ("santaraid".to_string(), Some("christmas".to_string()), true),
+            ]
+        );
+        assert_eq!(db.get_raid_list(Some("halloween"), false).unwrap().len(), 0);
+
+        let output = temp_dir.path().join("raids.json");
+        assert_eq!(crate::export::export_raid_list(&db, &output, false, None).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_process_functions_on_a_game_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let game = SyntheticGame::create(temp_dir.path()).unwrap();
+        game.write_objects_srv(&[ItemSpec::new(3031, "a gold coin"), ItemSpec::new(3361, "a leather armor")]).unwrap();
+        game.add_mon(&MonSpec::new("rat", "Rat", 21)).unwrap();
+        game.add_npc("baxter", &npc_file("Baxter", &[(3361, 25)], &[])).unwrap();
+        game.add_evt("orcs", &evt_file(86400, "Orcs!", &[(5, 3, 5)])).unwrap();
+        let db = Database::in_memory().unwrap();
+        let paths = GamePaths::discover(&game.root).unwrap();
+        assert!(paths.missing().is_empty());
+        let reporter = Reporter::new(2);
+
+        assert_eq!(process_creatures(&db, &paths, &reporter).unwrap(), 1);
+        assert_eq!(process_items_core(&db, &paths, &reporter).unwrap(), ItemsCoreSummary { items: 2, prices: Some(1) });
+        assert_eq!(process_raids(&db, &paths, &reporter).unwrap(), 1);
+
+        // Missing inputs are errors rather than silently empty runs
+        assert!(process_harvesting(&db, &paths, Some(&temp_dir.path().join("missing.csv")), &reporter).is_err());
+        assert!(GamePaths::discover(temp_dir.path().join("nowhere")).is_err());
+        let empty_dir = temp_dir.path().join("empty");
+        std::fs::create_dir(&empty_dir).unwrap();
+        let empty = GamePaths::discover(&empty_dir).unwrap();
+        assert_eq!(empty.missing(), &GameEntry::ALL);
+        assert!(matches!(process_items_core(&db, &empty, &reporter), Err(DemonaxError::NotFound(_))));
+        assert_eq!(empty.magic_cc, None);
+    }
+
+    #[test]
+    fn test_register_replaces_parser_with_same_name() {
+        let mut registry = ParserRegistry::with_defaults();
+        let count = registry.names().len();
+        registry.register(RaidParser { excluded_files: Vec::new(), settings: RaidConfig::default() });
+
+        assert_eq!(registry.names().len(), count);
+        assert_eq!(registry.get("raids").unwrap().source(), "mon/*.evt");
+    }
+
+    #[test]
+    fn test_house_parser_snapshot_and_export() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let game = SyntheticGame::create(temp_dir.path()).unwrap();
+        game.add_usr(&UsrSpec::new(1001, "Alice")).unwrap();
+        let dat = game.root.join("dat");
+        std::fs::write(dat.join("houses.dat"), "ID = 1\nName = \"Lakeside\"\nArea = 1\nFields = {[1,1,7],[1,2,7]}\n\nID = 2\nName = \"Hut\"\nArea = 1\nFields = {[5,5,7]}\n").unwrap();
+        std::fs::write(dat.join("owners.dat"), "ID = 1\nOwner = 1001\nLastPayment = 1767225600\n").unwrap();
+        std::fs::write(dat.join("houseareas.dat"), "Area = (1,\"Carlin\",50,0)\n").unwrap();
+        // Other .dat files in the directory are not house files
+        std::fs::write(dat.join("monster.dat"), "").unwrap();
+
+        let db = Database::in_memory().unwrap();
+        db.process_usr_files(&game.usr_dir(), "2026-01-01", 2).unwrap();
+        let parser = HouseParser { snapshot_date: "2026-01-01".to_string() };
+        assert_eq!(parser.process(&game.root, &db, 2).unwrap(), 2);
+        assert_eq!(db.snapshot_house_owners("2026-01-01").unwrap(), 2);
+
+        let output = temp_dir.path().join("web/houses.json");
+        assert_eq!(export_house_list(&db, &output, None).unwrap(), 2);
+        let listing: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(output).unwrap()).unwrap();
+        assert_eq!(listing[0]["name"], "Hut");
+        assert_eq!(listing[0]["owner_name"], serde_json::Value::Null);
+        assert_eq!(listing[1]["owner_name"], "Alice");
+        assert_eq!(listing[1]["rent"], 100);
+        assert_eq!(listing[1]["last_payment"], "2026-01-01");
+    }
+}
@@ -0,0 +1,235 @@
+//! Quest route helpers for the website: per quest, where its chests are, the
+//! doors on the way, the keys that open them and where to get those keys, and
+//! a recommended level.
+//!
+//! A door is on a quest's route when it is within [`ROUTE_RANGE`] fields of
+//! one of the quest's chests on the same floor, or when it is a quest door
+//! checking the quest's own value. A key comes from the quest chests holding a
+//! key with the door's number; the creatures dropping that key type and the
+//! NPCs nearest the first chest selling it are listed too. The recommended
+//! level is the highest of the level doors on the route and the recommended
+//! level of the creatures spawning near the chests (see [`recommended_level`]).
+
+use crate::combat::CreatureCombat;
+use crate::database::Database;
+use crate::error::Result;
+use crate::geo::Position;
+use crate::hunting::{recommended_level, SpawnCreature};
+use crate::models::{KeyItem, MapDoor, NearbyOffer, QuestRow};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// Fields around a chest, on its floor, in which doors and spawns belong to the quest
+pub const ROUTE_RANGE: i32 = 16;
+
+/// Most creatures and NPCs listed per key
+pub const KEY_SOURCE_LIMIT: usize = 5;
+
+/// A reward or key item
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteItem {
+    pub type_id: i32,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoorKind {
+    /// Needs the key with its keyhole number
+    Key,
+    /// Needs a level
+    Level,
+    /// Needs a quest value
+    Quest,
+}
+
+/// A door on a quest's route
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteDoor {
+    pub position: Position,
+    pub type_id: i32,
+    pub kind: DoorKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyhole_number: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quest_number: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quest_value: Option<i32>,
+}
+
+/// A creature dropping a key type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KeyDrop {
+    pub creature: String,
+    /// Drop chance in percent
+    pub chance: f64,
+}
+
+/// A key needed on a quest's route and where it comes from
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteKey {
+    pub key_number: i32,
+    /// Key item, when a quest chest holds a key with this number
+    pub item: Option<RouteItem>,
+    /// Quests whose chests hold the key
+    pub from_quests: Vec<i32>,
+    pub dropped_by: Vec<KeyDrop>,
+    /// NPCs selling the key type, nearest to the first chest first
+    pub sold_by: Vec<NearbyOffer>,
+}
+
+/// One quest's route page
+#[derive(Debug, Clone, Serialize)]
+pub struct QuestRoute {
+    pub id: i32,
+    pub name: String,
+    pub region: Option<String>,
+    pub town: Option<String>,
+    pub chests: Vec<Position>,
+    pub rewards: Vec<RouteItem>,
+    pub recommended_level: Option<i32>,
+    pub doors: Vec<RouteDoor>,
+    pub keys: Vec<RouteKey>,
+    /// Creatures spawning near the chests
+    pub creatures: Vec<String>,
+}
+
+/// Whether `position` is within [`ROUTE_RANGE`] of one of `chests` on the same floor
+fn near_chests(chests: &[Position], position: Position) -> bool {
+    chests.iter().any(|chest| chest.z == position.z && chest.distance(position) <= ROUTE_RANGE)
+}
+
+/// The doors on `quest`'s route, by position
+pub fn route_doors(quest: &QuestRow, doors: &[MapDoor]) -> Vec<RouteDoor> {
+    doors
+        .iter()
+        .filter(|door| door.quest_number == Some(quest.id) || near_chests(&quest.chests, Position::from(door.position)))
+        .map(|door| RouteDoor {
+            position: Position::from(door.position),
+            type_id: door.type_id,
+            kind: if door.keyhole_number.is_some() {
+                DoorKind::Key
+            } else if door.level.is_some() {
+                DoorKind::Level
+            } else {
+                DoorKind::Quest
+            },
+            keyhole_number: door.keyhole_number,
+            level: door.level,
+            quest_number: door.quest_number,
+            quest_value: door.quest_value,
+        })
+        .collect()
+}
+
+/// Build the route of every stored quest
+pub fn quest_routes(db: &Database) -> Result<Vec<QuestRoute>> {
+    let quests = db.get_quest_rows()?;
+    let doors = db.get_doors()?;
+    let spawns = db.get_spawn_creatures()?;
+    let names = db.get_item_names()?;
+    let item = |type_id: i32| RouteItem { type_id, name: names.get(&type_id).cloned() };
+
+    let mut keys: HashMap<i32, Vec<(i32, KeyItem)>> = HashMap::new();
+    for (quest_id, key) in db.get_quest_keys()? {
+        keys.entry(key.key_number).or_default().push((quest_id, key));
+    }
+
+    let mut combat: HashMap<String, Option<CreatureCombat>> = HashMap::new();
+    let mut routes = Vec::new();
+    for quest in &quests {
+        let doors = route_doors(quest, &doors);
+
+        let nearby: Vec<&SpawnCreature> =
+            spawns.iter().filter(|spawn| near_chests(&quest.chests, spawn.position)).collect();
+        let creatures: BTreeSet<&str> = nearby.iter().map(|spawn| spawn.name.as_str()).collect();
+        for spawn in &nearby {
+            if !combat.contains_key(&spawn.short_name) {
+                combat.insert(spawn.short_name.clone(), db.get_creature_combat(&spawn.short_name)?);
+            }
+        }
+        let combats: Vec<&CreatureCombat> =
+            nearby.iter().filter_map(|spawn| combat.get(&spawn.short_name)?.as_ref()).collect();
+        let creature_level = if combats.is_empty() { None } else { recommended_level(&combats) };
+        let door_level = doors.iter().filter_map(|door| door.level).max();
+
+        let key_numbers: BTreeSet<i32> = doors.iter().filter_map(|door| door.keyhole_number).collect();
+        let mut route_keys = Vec::new();
+        for key_number in key_numbers {
+            let sources = keys.get(&key_number).map(Vec::as_slice).unwrap_or_default();
+            let type_id = sources.first().map(|(_, key)| key.type_id);
+            let (mut dropped_by, mut sold_by) = (Vec::new(), Vec::new());
+            if let Some(type_id) = type_id {
+                dropped_by = db
+                    .get_item_droppers(type_id)?
+                    .into_iter()
+                    .take(KEY_SOURCE_LIMIT)
+                    .map(|(creature, chance)| KeyDrop { creature, chance })
+                    .collect();
+                if let Some(chest) = quest.chests.first() {
+                    sold_by = db.nearest_item_sellers(type_id, *chest, KEY_SOURCE_LIMIT)?;
+                }
+            }
+            route_keys.push(RouteKey {
+                key_number,
+                item: type_id.map(item),
+                from_quests: sources.iter().map(|(quest_id, _)| *quest_id).collect(),
+                dropped_by,
+                sold_by,
+            });
+        }
+
+        routes.push(QuestRoute {
+            id: quest.id,
+            name: quest.name.clone(),
+            region: quest.region.clone(),
+            town: quest.town.clone(),
+            chests: quest.chests.clone(),
+            rewards: quest.reward_item_ids.iter().map(|&type_id| item(type_id)).collect(),
+            recommended_level: door_level.max(creature_level),
+            doors,
+            keys: route_keys,
+            creatures: creatures.into_iter().map(str::to_string).collect(),
+        });
+    }
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_doors_near_chests_and_quest_doors() {
+        let quest = QuestRow {
+            id: 5,
+            name: "Dragon Lair".to_string(),
+            region: None,
+            town: None,
+            chests: vec![Position::new(32100, 32100, 8)],
+            reward_item_ids: vec![3031],
+        };
+        let door = |position: (i32, i32, i32), keyhole_number: Option<i32>, level: Option<i32>, quest_number: Option<i32>| MapDoor {
+            position,
+            type_id: 1249,
+            keyhole_number,
+            level,
+            quest_number,
+            quest_value: quest_number.map(|_| 1),
+        };
+        let doors = [
+            door((32110, 32090, 8), Some(4601), None, None),
+            door((32100, 32100, 7), None, Some(30), None),
+            door((32200, 32100, 8), None, Some(50), None),
+            door((33000, 31000, 6), None, None, Some(5)),
+        ];
+
+        let route = route_doors(&quest, &doors);
+        let kinds: Vec<DoorKind> = route.iter().map(|door| door.kind).collect();
+        assert_eq!(kinds, vec![DoorKind::Key, DoorKind::Quest]);
+        assert_eq!(route[0].keyhole_number, Some(4601));
+        assert_eq!(route[1].position, Position::new(33000, 31000, 6));
+    }
+}
@@ -0,0 +1,356 @@
+//! Raid (.evt) file generation from declarative TOML specs.
+//!
+//! Supports TOML specs like:
+//! ```toml
+//! type = "BigRaid"
+//! interval = 86400  # seconds between raids
+//!
+//! # Default spawn area for waves that don't set a position
+//! [area]
+//! position = [32350, 32220, 7]
+//! spread = 5
+//!
+//! [[waves]]
+//! delay = 0  # seconds after the raid starts
+//! message = "Orcs are gathering near the city!"
+//!
+//! [[waves]]
+//! delay = 120
+//! [[waves.spawns]]
+//! creature = "orc warrior"
+//! count = [3, 5]
+//! ```
+//!
+//! Creatures are given by name and resolved to race numbers before the file
+//! is written.
+
+use crate::catalog::CreatureCatalog;
+use crate::error::{DemonaxError, Result};
+use crate::geo::{Position, Sector};
+use crate::models::RaidStep;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Where creatures of a spawn appear.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AreaSpec {
+    pub position: [i32; 3],
+    #[serde(default)]
+    pub spread: i32,
+}
+
+/// Creatures spawned by a wave.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpawnSpec {
+    /// Creature short name (.mon file name) or name
+    pub creature: String,
+    /// (min, max) creatures
+    pub count: [i32; 2],
+    /// Overrides the wave and raid area
+    pub position: Option<[i32; 3]>,
+    pub spread: Option<i32>,
+}
+
+/// One wave of a raid: an optional message and the creatures it spawns.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WaveSpec {
+    /// Seconds after the raid starts
+    pub delay: i64,
+    pub message: Option<String>,
+    /// Default area of this wave's spawns
+    pub area: Option<AreaSpec>,
+    #[serde(default)]
+    pub spawns: Vec<SpawnSpec>,
+}
+
+/// Complete raid spec loaded from TOML.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RaidSpec {
+    #[serde(rename = "type", default = "default_raid_type")]
+    pub raid_type: String,
+    /// Seconds between raids
+    pub interval: Option<i64>,
+    /// Default area of all spawns
+    pub area: Option<AreaSpec>,
+    pub waves: Vec<WaveSpec>,
+}
+
+fn default_raid_type() -> String {
+    "BigRaid".to_string()
+}
+
+impl RaidSpec {
+    /// Load a raid spec from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DemonaxError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read raid spec from {:?}: {}", path, e),
+            ))
+        })?;
+
+        content.parse()
+    }
+
+    /// Resolve creature names and areas into raid steps, in delay order.
+    ///
+    /// `resolve_race` maps a creature name to its race number. When `sectors`
+    /// is given, every spawn position must lie in an existing map sector.
+    /// All problems are reported together in one validation error.
+    pub fn to_steps<F>(&self, mut resolve_race: F, sectors: Option<&HashSet<Sector>>) -> Result<Vec<RaidStep>>
+    where
+        F: FnMut(&str) -> Result<Option<i32>>,
+    {
+        let mut problems = Vec::new();
+        let mut steps = Vec::new();
+
+        if self.waves.is_empty() {
+            problems.push("raid has no waves".to_string());
+        }
+        if let Some(interval) = self.interval
+            && interval <= 0
+        {
+            problems.push(format!("interval must be positive, got {}", interval));
+        }
+
+        let mut waves: Vec<&WaveSpec> = self.waves.iter().collect();
+        waves.sort_by_key(|wave| wave.delay);
+
+        for wave in waves {
+            if wave.delay < 0 {
+                problems.push(format!("wave delay must not be negative, got {}", wave.delay));
+            }
+            if let Some(message) = &wave.message {
+                if message.contains('"') || message.contains('\n') {
+                    problems.push(format!("message must be a single line without quotes: {:?}", message));
+                }
+                steps.push(RaidStep {
+                    delay_seconds: wave.delay,
+                    message: Some(message.clone()),
+                    position: None,
+                    spread: None,
+                    race: None,
+                    count: None,
+                });
+            }
+
+            for spawn in &wave.spawns {
+                let area = wave.area.as_ref().or(self.area.as_ref());
+                let Some(position) = spawn.position.or(area.map(|a| a.position)) else {
+                    problems.push(format!("no position or area for spawn of {}", spawn.creature));
+                    continue;
+                };
+                let spread = spawn.spread.or(area.map(|a| a.spread)).unwrap_or(0);
+
+                let [min, max] = spawn.count;
+                if min < 1 || max < min {
+                    problems.push(format!("invalid count [{}, {}] for {}", min, max, spawn.creature));
+                }
+                if spread < 0 {
+                    problems.push(format!("spread must not be negative for {}", spawn.creature));
+                }
+                if let Some(problem) = check_position(position, spread, sectors) {
+                    problems.push(format!("{} spawn {}", spawn.creature, problem));
+                }
+
+                match resolve_race(&spawn.creature)? {
+                    Some(race) => steps.push(RaidStep {
+                        delay_seconds: wave.delay,
+                        message: None,
+                        position: Some((position[0], position[1], position[2])),
+                        spread: Some(spread),
+                        race: Some(race),
+                        count: Some((min, max)),
+                    }),
+                    None => problems.push(format!("unknown creature '{}'", spawn.creature)),
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(steps)
+        } else {
+            Err(DemonaxError::Validation(problems.join("; ")))
+        }
+    }
+}
+
+impl FromStr for RaidSpec {
+    type Err = DemonaxError;
+
+    /// Parse a raid spec from a TOML string.
+    fn from_str(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| {
+            DemonaxError::Parse(format!("Failed to parse raid spec TOML: {}", e))
+        })
+    }
+}
+
+/// Describe the creatures of a parsed raid by name from its `spawn_composition_json`,
+/// most numerous first, e.g. "5 to 10 Dragon, 2 Demon". Races missing from the
+/// catalog are written as "Race 35". None when the composition is empty or invalid.
+pub fn describe_raid_creatures(spawn_composition_json: &str, creatures: &CreatureCatalog) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Composition {
+        race: i32,
+        min: i32,
+        max: i32,
+    }
+
+    let mut composition: Vec<Composition> = serde_json::from_str(spawn_composition_json).ok()?;
+    if composition.is_empty() {
+        return None;
+    }
+    composition.sort_by(|a, b| b.max.cmp(&a.max).then(a.race.cmp(&b.race)));
+    let described: Vec<String> = composition
+        .iter()
+        .map(|spawn| {
+            let name = creatures.name(spawn.race).map_or_else(|| format!("Race {}", spawn.race), str::to_string);
+            if spawn.min == spawn.max {
+                format!("{} {}", spawn.min, name)
+            } else {
+                format!("{} to {} {}", spawn.min, spawn.max, name)
+            }
+        })
+        .collect();
+    Some(described.join(", "))
+}
+
+/// Check that a spawn area lies on the map. Returns a description of the problem.
+fn check_position(position: [i32; 3], spread: i32, sectors: Option<&HashSet<Sector>>) -> Option<String> {
+    let center = Position::from(position);
+    if !center.is_valid() {
+        return Some(format!("position [{}] is outside the map", center));
+    }
+    let sectors = sectors?;
+    // Both corners of the spread square must be in a sector file
+    for corner in [center.offset(-spread, -spread), center.offset(spread, spread)] {
+        let sector = corner.sector();
+        if !sectors.contains(&sector) {
+            return Some(format!("area [{}] spread {} leaves the map (no sector {})", center, spread, sector));
+        }
+    }
+    None
+}
+
+/// Collect the map sectors from the `<x>-<y>-<z>.sec` files in a directory.
+pub fn map_sectors(map_dir: &Path) -> Result<HashSet<Sector>> {
+    let mut sectors = HashSet::new();
+    for entry in std::fs::read_dir(map_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("sec") {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if let Some(sector) = Sector::from_file_stem(stem) {
+            sectors.insert(sector);
+        }
+    }
+    Ok(sectors)
+}
+
+/// Render a .evt file. Steps are written in order, one `Delay` block each.
+pub fn evt_text(raid_type: &str, interval: Option<i64>, wave_count: usize, steps: &[RaidStep]) -> String {
+    const WAVE_WORDS: [&str; 10] = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+    let waves = match wave_count {
+        1 => "one wave".to_string(),
+        n if n <= WAVE_WORDS.len() => format!("{} waves", WAVE_WORDS[n - 1]),
+        n => format!("{} waves", n),
+    };
+
+    let mut text = format!("# Process: {}\nType = {}\n", waves, raid_type);
+    if let Some(interval) = interval {
+        text.push_str(&format!("Interval = {}\n", interval));
+    }
+    for step in steps {
+        text.push_str(&format!("\nDelay = {}\n", step.delay_seconds));
+        if let Some(message) = &step.message {
+            text.push_str(&format!("Message = \"{}\"\n", message));
+        }
+        if let Some((x, y, z)) = step.position {
+            text.push_str(&format!("Position = [{},{},{}]\n", x, y, z));
+        }
+        if let Some(spread) = step.spread {
+            text.push_str(&format!("Spread = {}\n", spread));
+        }
+        if let Some(race) = step.race {
+            text.push_str(&format!("Race = {}\n", race));
+        }
+        if let Some((min, max)) = step.count {
+            text.push_str(&format!("Count = ({}, {})\n", min, max));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{parse_evt_steps, parse_evt_str};
+
+    const SPEC: &str = r#"
+type = "BigRaid"
+interval = 86400
+
+[area]
+position = [32050, 32050, 7]
+spread = 3
+
+[[waves]]
+delay = 120
+[[waves.spawns]]
+creature = "Orc Warrior"
+count = [2, 4]
+
+[[waves]]
+delay = 0
+message = "Orcs are gathering!"
+"#;
+
+    fn resolve(name: &str) -> Result<Option<i32>> {
+        Ok((name.eq_ignore_ascii_case("orc warrior")).then_some(7))
+    }
+
+    #[test]
+    fn test_generated_evt_parses_back() {
+        let spec: RaidSpec = SPEC.parse().unwrap();
+        let sectors: HashSet<_> = [Sector { x: 1001, y: 1001, z: 7 }].into_iter().collect();
+        let steps = spec.to_steps(resolve, Some(&sectors)).unwrap();
+        let text = evt_text(&spec.raid_type, spec.interval, spec.waves.len(), &steps);
+
+        assert_eq!(parse_evt_steps(&text).unwrap(), steps);
+        assert_eq!(steps[0].message.as_deref(), Some("Orcs are gathering!"));
+        assert_eq!(steps[1].race, Some(7));
+
+        let raid = parse_evt_str(&text, "orcs").unwrap();
+        assert_eq!(raid.waves, "two");
+        assert_eq!(raid.interval_seconds, Some(86400.0));
+        assert_eq!(raid.creatures, "2 to 4 Race 7");
+
+        let orc_warrior = crate::catalog::CatalogCreature {
+            race: 7,
+            name: "Orc Warrior".to_string(),
+            short_name: "orcwarrior".to_string(),
+            hp: 125,
+            experience: 50,
+        };
+        let creatures: CreatureCatalog = [orc_warrior].into_iter().collect();
+        let described = describe_raid_creatures(&raid.spawn_composition_json, &creatures);
+        assert_eq!(described.as_deref(), Some("2 to 4 Orc Warrior"));
+        assert_eq!(describe_raid_creatures("[]", &creatures), None);
+    }
+
+    #[test]
+    fn test_validation_reports_unknown_creatures_and_map_bounds() {
+        let spec: RaidSpec = SPEC.replace("Orc Warrior", "Orc Wizard").parse().unwrap();
+        let sectors: HashSet<_> = [Sector { x: 1000, y: 1000, z: 7 }].into_iter().collect();
+
+        let error = spec.to_steps(resolve, Some(&sectors)).unwrap_err().to_string();
+        assert!(error.contains("unknown creature 'Orc Wizard'"), "{}", error);
+        assert!(error.contains("no sector 1001-1001-7"), "{}", error);
+    }
+}
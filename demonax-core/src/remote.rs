@@ -0,0 +1,309 @@
+//! Game directories on another machine, read over SFTP.
+//!
+//! A game path like `sftp://tibia@game.example.org/home/tibia/game` is mirrored
+//! into a local cache before the Update* commands run, since the parsers work on
+//! local paths. Files whose size and modification time match the cached copy are
+//! not downloaded again, so repeated runs only transfer what changed.
+//!
+//! Connection settings come from the `[sftp]` section of `demonax.toml`. The
+//! server's host key must already be in `known_hosts`.
+
+use crate::error::{DemonaxError, Result};
+use crate::file_utils::throttle_read;
+use serde::Deserialize;
+use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp};
+use std::collections::HashSet;
+use std::io::Read;
+use std::net::TcpStream;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// The `[sftp]` section of `demonax.toml`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SftpConfig {
+    /// User name, unless given in the URL (default: $USER)
+    pub user: Option<String>,
+    /// Port, unless given in the URL (default: 22)
+    pub port: Option<u16>,
+    /// Private key to log in with; without one the SSH agent is used
+    pub identity_file: Option<PathBuf>,
+    /// known_hosts file to check the server's key against (default: ~/.ssh/known_hosts)
+    pub known_hosts: Option<PathBuf>,
+    /// Local directory the remote game directory is mirrored into
+    pub cache_dir: PathBuf,
+    /// Top-level directories of the game directory that aren't mirrored
+    pub exclude: Vec<String>,
+}
+
+impl Default for SftpConfig {
+    fn default() -> Self {
+        Self {
+            user: None,
+            port: None,
+            identity_file: None,
+            known_hosts: None,
+            cache_dir: PathBuf::from("./game-cache"),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// A directory on an SFTP server, from a `sftp://[user@]host[:port]/path` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SftpLocation {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub root: String,
+}
+
+impl SftpLocation {
+    /// Parse an `sftp://` URL. Returns `None` for other paths.
+    pub fn parse(path: &Path) -> Result<Option<Self>> {
+        let Some(url) = path.to_str().and_then(|p| p.strip_prefix("sftp://")) else {
+            return Ok(None);
+        };
+        let invalid = || DemonaxError::Validation(format!("Invalid SFTP URL '{}', expected sftp://[user@]host[:port]/path", path.display()));
+
+        let (authority, root) = url.split_once('/').ok_or_else(invalid)?;
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, rest)) => (Some(user.to_string()), rest),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.split_once(':') {
+            Some((host, port)) => (host, Some(port.parse::<u16>().map_err(|_| invalid())?)),
+            None => (host_port, None),
+        };
+        if host.is_empty() || root.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Some(Self { user, host: host.to_string(), port, root: format!("/{}", root) }))
+    }
+}
+
+impl std::fmt::Display for SftpLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sftp://{}{}", self.host, self.root)
+    }
+}
+
+/// A file below the remote root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteFile {
+    /// Path relative to the remote root
+    pub path: PathBuf,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: u64,
+}
+
+/// What [`SftpSource::mirror`] did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MirrorStats {
+    pub downloaded: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub bytes: u64,
+}
+
+/// An open SFTP connection to a remote game directory
+pub struct SftpSource {
+    sftp: Sftp,
+    root: PathBuf,
+    exclude: Vec<String>,
+}
+
+impl SftpSource {
+    /// Connect and log in, refusing servers whose key isn't in known_hosts.
+    pub fn connect(location: &SftpLocation, config: &SftpConfig) -> Result<Self> {
+        let port = location.port.or(config.port).unwrap_or(22);
+        let user = location
+            .user
+            .clone()
+            .or_else(|| config.user.clone())
+            .or_else(|| std::env::var("USER").ok())
+            .ok_or_else(|| DemonaxError::Config(format!("No SFTP user for {}; set [sftp] user", location.host)))?;
+
+        let mut session = Session::new()?;
+        session.set_tcp_stream(TcpStream::connect((location.host.as_str(), port))?);
+        session.handshake()?;
+        verify_host_key(&session, &location.host, port, config)?;
+
+        match &config.identity_file {
+            Some(key) => session.userauth_pubkey_file(&user, None, key, None)?,
+            None => session.userauth_agent(&user)?,
+        }
+
+        Ok(Self { sftp: session.sftp()?, root: PathBuf::from(&location.root), exclude: config.exclude.clone() })
+    }
+
+    /// All files below the root (except excluded top-level directories), sorted by path
+    pub fn list_files(&self) -> Result<Vec<RemoteFile>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            for (path, stat) in self.sftp.readdir(&dir)? {
+                let Ok(relative) = path.strip_prefix(&self.root).map(Path::to_path_buf) else {
+                    continue;
+                };
+                if is_excluded(&relative, &self.exclude) {
+                    continue;
+                }
+                if stat.is_dir() {
+                    dirs.push(path);
+                } else if stat.is_file() {
+                    files.push(RemoteFile { path: relative, size: stat.size.unwrap_or(0), mtime: stat.mtime.unwrap_or(0) });
+                }
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// Read a file, given relative to the root
+    pub fn read(&self, relative: &Path) -> Result<Vec<u8>> {
+        throttle_read();
+        let mut contents = Vec::new();
+        self.sftp.open(self.root.join(relative))?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Bring `cache_dir` up to date with the remote directory: download new and
+    /// changed files and delete cached files that no longer exist remotely.
+    pub fn mirror(&self, cache_dir: &Path) -> Result<MirrorStats> {
+        let files = self.list_files()?;
+        let mut stats = MirrorStats::default();
+
+        for file in &files {
+            let local = cache_dir.join(&file.path);
+            if !needs_download(file, &local) {
+                stats.unchanged += 1;
+                continue;
+            }
+            if let Some(parent) = local.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            // Write next to the target and rename, so an interrupted run never leaves a truncated file
+            let mut partial = local.clone().into_os_string();
+            partial.push(".part");
+            std::fs::write(&partial, self.read(&file.path)?)?;
+            std::fs::File::options()
+                .write(true)
+                .open(&partial)?
+                .set_modified(UNIX_EPOCH + Duration::from_secs(file.mtime))?;
+            std::fs::rename(&partial, &local)?;
+
+            stats.downloaded += 1;
+            stats.bytes += file.size;
+        }
+
+        let remote: HashSet<&Path> = files.iter().map(|f| f.path.as_path()).collect();
+        for entry in WalkDir::new(cache_dir).into_iter().filter_map(|e| e.ok()) {
+            let Ok(relative) = entry.path().strip_prefix(cache_dir) else {
+                continue;
+            };
+            if entry.file_type().is_file() && !is_excluded(relative, &self.exclude) && !remote.contains(relative) {
+                std::fs::remove_file(entry.path())?;
+                stats.removed += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Whether the cached copy at `local` is missing or differs in size or modification time
+pub fn needs_download(file: &RemoteFile, local: &Path) -> bool {
+    let Ok(metadata) = local.metadata() else {
+        return true;
+    };
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|since| since.as_secs());
+    metadata.len() != file.size || mtime != Some(file.mtime)
+}
+
+fn is_excluded(relative: &Path, exclude: &[String]) -> bool {
+    match relative.components().next() {
+        Some(Component::Normal(first)) => exclude.iter().any(|name| first == name.as_str()),
+        _ => false,
+    }
+}
+
+fn verify_host_key(session: &Session, host: &str, port: u16, config: &SftpConfig) -> Result<()> {
+    let known_hosts_file = match &config.known_hosts {
+        Some(path) => path.clone(),
+        None => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".ssh/known_hosts"))
+            .ok_or_else(|| DemonaxError::Config("No known_hosts file; set [sftp] known_hosts".to_string()))?,
+    };
+
+    let mut known_hosts = session.known_hosts()?;
+    known_hosts.read_file(&known_hosts_file, KnownHostFileKind::OpenSSH)?;
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| DemonaxError::Validation(format!("{} sent no host key", host)))?;
+
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(DemonaxError::Validation(format!(
+            "Host key of {} does not match {}",
+            host,
+            known_hosts_file.display()
+        ))),
+        CheckResult::NotFound | CheckResult::Failure => Err(DemonaxError::Validation(format!(
+            "{} is not in {}; connect once with ssh to add its key",
+            host,
+            known_hosts_file.display()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sftp_urls() {
+        assert_eq!(SftpLocation::parse(Path::new("/home/tibia/game")).unwrap(), None);
+        assert_eq!(
+            SftpLocation::parse(Path::new("sftp://tibia@game.example.org:2222/home/tibia/game")).unwrap(),
+            Some(SftpLocation {
+                user: Some("tibia".to_string()),
+                host: "game.example.org".to_string(),
+                port: Some(2222),
+                root: "/home/tibia/game".to_string(),
+            })
+        );
+        let location = SftpLocation::parse(Path::new("sftp://game.example.org/srv/game")).unwrap().unwrap();
+        assert_eq!((location.user, location.port, location.root.as_str()), (None, None, "/srv/game"));
+        assert!(SftpLocation::parse(Path::new("sftp://game.example.org")).is_err());
+        assert!(SftpLocation::parse(Path::new("sftp://game.example.org:ssh/srv")).is_err());
+    }
+
+    #[test]
+    fn test_unchanged_files_are_not_downloaded() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let local = temp_dir.path().join("dragon.mon");
+        let file = RemoteFile { path: PathBuf::from("mon/dragon.mon"), size: 5, mtime: 1_700_000_000 };
+        assert!(needs_download(&file, &local));
+
+        std::fs::write(&local, "hello").unwrap();
+        let set_mtime = |secs| {
+            std::fs::File::options().write(true).open(&local).unwrap().set_modified(UNIX_EPOCH + Duration::from_secs(secs)).unwrap()
+        };
+        set_mtime(1_700_000_000);
+        assert!(!needs_download(&file, &local));
+        set_mtime(1_700_000_001);
+        assert!(needs_download(&file, &local));
+
+        assert!(is_excluded(Path::new("usr/01/1001.usr"), &["usr".to_string()]));
+        assert!(!is_excluded(Path::new("mon/usr.mon"), &["usr".to_string()]));
+    }
+}
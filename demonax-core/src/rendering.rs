@@ -1,31 +1,248 @@
 //! Equipment rendering module for generating player equipment images.
 
+use crate::catalog::ItemCatalog;
 use crate::error::{DemonaxError, Result};
-use crate::models::PlayerSnapshot;
-use image::{RgbaImage, imageops, open};
+use crate::models::{ItemFlags, PlayerCard, PlayerSnapshot};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ImageEncoder, Rgba, RgbaImage, imageops, open};
+use rayon::prelude::*;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 /// Equipment slot positions (x, y) on the template image
 /// Based on coordinates from render_equipment.sh
+/// Indexed by [`EquipmentSlot::index`](crate::models::EquipmentSlot::index)
 const EQUIPMENT_POSITIONS: [(i32, i32); 10] = [
-    (40, 2),    // Slot 0: helmet
-    (3, 17),    // Slot 1: neck
-    (77, 17),   // Slot 2: bp (backpack)
-    (40, 40),   // Slot 3: armor
-    (77, 53),   // Slot 4: right_hand
-    (3, 54),    // Slot 5: left_hand
-    (40, 77),   // Slot 6: legs
-    (40, 114),  // Slot 7: boots
-    (3, 91),    // Slot 8: ring
-    (77, 90),   // Slot 9: arrows
+    (40, 2),    // Helmet
+    (3, 17),    // Amulet
+    (77, 17),   // Backpack
+    (40, 40),   // Armor
+    (77, 53),   // RightHand
+    (3, 54),    // LeftHand
+    (40, 77),   // Legs
+    (40, 114),  // Boots
+    (3, 91),    // Ring
+    (77, 90),   // Ammo
 ];
 
+/// Built-in eq.png template, used when no `--template` is given
+const DEFAULT_TEMPLATE: &[u8] = include_bytes!("../assets/eq.png");
+/// Built-in blank.png for empty slots, used when no `--blank` is given
+const DEFAULT_BLANK: &[u8] = include_bytes!("../assets/blank.png");
+
+/// 3x5 pixel glyph for a character, one row per byte (bit 2 = left column).
+/// Covers digits, upper-case letters (lower case is drawn upper case) and a
+/// little punctuation; anything else is drawn as a space.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        _ => [0; 5],
+    }
+}
+
+/// Width in pixels of `text` drawn at `scale` (glyphs are 3 wide plus 1 spacing)
+fn text_width(text: &str, scale: i64) -> i64 {
+    (text.chars().count() as i64 * 4 - 1).max(0) * scale
+}
+
+/// Draw `text` with its top-left corner at (`x`, `y`), each font pixel
+/// `scale` pixels wide, with an outline so it stays readable on any background.
+/// Pixels outside the image are clipped.
+fn draw_text(image: &mut RgbaImage, x: i64, y: i64, text: &str, scale: i64, color: Rgba<u8>) {
+    let mut put = |px: i64, py: i64, color: Rgba<u8>| {
+        if px >= 0 && py >= 0 && px < image.width() as i64 && py < image.height() as i64 {
+            image.put_pixel(px as u32, py as u32, color);
+        }
+    };
+
+    for pass in [COUNT_OUTLINE, color] {
+        for (i, c) in text.chars().enumerate() {
+            let glyph_left = x + i as i64 * 4 * scale;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    let (px, py) = (glyph_left + col * scale, y + row as i64 * scale);
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            if pass == COUNT_OUTLINE {
+                                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                                    put(px + sx + dx, py + sy + dy, COUNT_OUTLINE);
+                                }
+                            } else {
+                                put(px + sx, py + sy, color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Count text colour, light grey like the game client
+const COUNT_COLOR: Rgba<u8> = Rgba([223, 223, 223, 255]);
+const COUNT_OUTLINE: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// File format of rendered images
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    /// Lossless WebP; usually smaller than PNG for the pixel-art renders
+    Webp,
+    /// Lossy AVIF at [`ImageOutput::quality`]
+    Avif,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Webp => "webp",
+            Self::Avif => "avif",
+        }
+    }
+}
+
+/// How rendered images are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageOutput {
+    pub format: ImageFormat,
+    /// AVIF quality, 1-100
+    pub quality: u8,
+    /// Recompress PNGs with oxipng; slower, but the files are smaller
+    pub optimize: bool,
+}
+
+impl Default for ImageOutput {
+    fn default() -> Self {
+        Self { format: ImageFormat::Png, quality: 80, optimize: false }
+    }
+}
+
+/// Encode an image in the configured format
+pub fn encode_image(image: &RgbaImage, output: &ImageOutput) -> Result<Vec<u8>> {
+    let encode_error = |e: image::ImageError| {
+        DemonaxError::Io(std::io::Error::other(format!("Failed to encode {} image: {}", output.format.extension(), e)))
+    };
+    let (width, height) = image.dimensions();
+    let mut data = Vec::new();
+
+    match output.format {
+        ImageFormat::Png => {
+            image::codecs::png::PngEncoder::new(&mut data)
+                .write_image(image, width, height, image::ExtendedColorType::Rgba8)
+                .map_err(encode_error)?;
+            if output.optimize {
+                data = oxipng::optimize_from_memory(&data, &oxipng::Options::from_preset(2)).map_err(|e| {
+                    DemonaxError::Io(std::io::Error::other(format!("Failed to optimize png image: {}", e)))
+                })?;
+            }
+        }
+        ImageFormat::Webp => WebPEncoder::new_lossless(&mut data)
+            .write_image(image, width, height, image::ExtendedColorType::Rgba8)
+            .map_err(encode_error)?,
+        ImageFormat::Avif => AvifEncoder::new_with_speed_quality(&mut data, 6, output.quality.clamp(1, 100))
+            .write_image(image, width, height, image::ExtendedColorType::Rgba8)
+            .map_err(encode_error)?,
+    }
+    Ok(data)
+}
+
+/// Encode and write `{stem}.{extension}` in `dir`
+pub(crate) fn save_image(image: &RgbaImage, dir: &Path, stem: impl std::fmt::Display, output: &ImageOutput) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.{}", stem, output.format.extension()));
+    std::fs::write(&path, encode_image(image, output)?)?;
+    Ok(path)
+}
+
 /// Configuration for equipment rendering
 pub struct RenderConfig {
     pub data_dir: PathBuf,
     pub output_dir: PathBuf,
-    pub template_path: PathBuf,
-    pub blank_path: PathBuf,
+    /// eq.png template; `None` uses the built-in one
+    pub template_path: Option<PathBuf>,
+    /// Image for empty slots; `None` uses the built-in one
+    pub blank_path: Option<PathBuf>,
+    /// Draw stack counts and charges in the bottom-right corner of slots
+    pub show_counts: bool,
+    pub image: ImageOutput,
+    /// Item names for warnings, and flags to leave out amounts of items that don't
+    /// stack; may be empty
+    pub catalog: ItemCatalog,
+}
+
+/// Draw `count` right-aligned in the bottom-right corner of the `width` x `height`
+/// box at (`x`, `y`), like the game client does for stacks and charges.
+fn draw_count(image: &mut RgbaImage, x: i64, y: i64, width: u32, height: u32, count: i32) {
+    let text = count.to_string();
+    let left = x + width as i64 - 1 - text_width(&text, 1);
+    let top = y + height as i64 - 1 - 5;
+    draw_text(image, left, top, &text, 1, COUNT_COLOR);
+}
+
+impl RenderConfig {
+    /// Load the template and blank images, falling back to the built-in ones.
+    pub fn load_base_images(&self) -> Result<(RgbaImage, RgbaImage)> {
+        let template = load_base_image(self.template_path.as_deref(), DEFAULT_TEMPLATE, "template")?;
+        let blank = load_base_image(self.blank_path.as_deref(), DEFAULT_BLANK, "blank image")?;
+        Ok((template, blank))
+    }
+}
+
+fn load_base_image(path: Option<&Path>, default: &[u8], what: &str) -> Result<RgbaImage> {
+    let image = match path {
+        Some(path) => open(path).map_err(|e| {
+            DemonaxError::Io(std::io::Error::other(format!("Failed to load {} {}: {}", what, path.display(), e)))
+        })?,
+        None => image::load_from_memory(default)
+            .map_err(|e| DemonaxError::Io(std::io::Error::other(format!("Failed to decode built-in {}: {}", what, e))))?,
+    };
+    Ok(image.to_rgba8())
 }
 
 /// Load an item image from the data directory
@@ -40,8 +257,7 @@ fn load_item_image(data_dir: &Path, item_id: i32) -> Result<RgbaImage> {
     }
 
     let img = open(&item_path)
-        .map_err(|e| DemonaxError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
+        .map_err(|e| DemonaxError::Io(std::io::Error::other(
             format!("Failed to load item image {}: {}", item_path.display(), e),
         )))?
         .to_rgba8();
@@ -49,66 +265,357 @@ fn load_item_image(data_dir: &Path, item_id: i32) -> Result<RgbaImage> {
     Ok(img)
 }
 
+/// Least-recently-used cache of item images, shared by the threads of a render run
+/// so each image is read from disk once rather than once per player.
+pub struct ItemImageCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// Image and the tick it was last used at
+    images: HashMap<i32, (Arc<RgbaImage>, u64)>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl ItemImageCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), state: Mutex::new(CacheState::default()) }
+    }
+
+    /// Get an item image, loading it from `data_dir` if it isn't cached
+    pub fn get(&self, data_dir: &Path, item_id: i32) -> Result<Arc<RgbaImage>> {
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.tick += 1;
+            let tick = state.tick;
+            if let Some(entry) = state.images.get_mut(&item_id) {
+                entry.1 = tick;
+                let image = entry.0.clone();
+                state.hits += 1;
+                return Ok(image);
+            }
+            state.misses += 1;
+        }
+
+        // Load outside the lock so other threads aren't held up by the disk
+        let image = Arc::new(load_item_image(data_dir, item_id)?);
+        self.insert(item_id, image.clone());
+        Ok(image)
+    }
+
+    /// Load the images of the items worn by at least `min_count` of the snapshots,
+    /// most common first and at most as many as fit. Returns the number loaded.
+    pub fn preload(&self, data_dir: &Path, snapshots: &[PlayerSnapshot], min_count: usize) -> usize {
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for snapshot in snapshots {
+            for (_, item) in snapshot.equipment.iter() {
+                if let Some(item) = item {
+                    *counts.entry(item.type_id).or_default() += 1;
+                }
+            }
+        }
+        let mut common: Vec<(i32, usize)> = counts.into_iter().filter(|&(_, count)| count >= min_count).collect();
+        common.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        common.truncate(self.capacity);
+
+        let loaded: Vec<(i32, RgbaImage)> = common
+            .par_iter()
+            .filter_map(|&(item_id, _)| load_item_image(data_dir, item_id).ok().map(|image| (item_id, image)))
+            .collect();
+        let count = loaded.len();
+        for (item_id, image) in loaded {
+            self.insert(item_id, Arc::new(image));
+        }
+        count
+    }
+
+    /// (hits, misses) so far
+    pub fn stats(&self) -> (u64, u64) {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        (state.hits, state.misses)
+    }
+
+    fn insert(&self, item_id: i32, image: Arc<RgbaImage>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.tick += 1;
+        let tick = state.tick;
+        state.images.insert(item_id, (image, tick));
+        if state.images.len() > self.capacity
+            && let Some(oldest) = state.images.iter().min_by_key(|(_, (_, used))| *used).map(|(id, _)| *id)
+        {
+            state.images.remove(&oldest);
+        }
+    }
+}
+
 /// Render equipment for a single player
 pub fn render_player_equipment(
     snapshot: &PlayerSnapshot,
     config: &RenderConfig,
     template: &RgbaImage,
     blank: &RgbaImage,
+    cache: &ItemImageCache,
     quiet: u8,
 ) -> Result<PathBuf> {
     // Clone the template as the base
     let mut base = template.clone();
 
     // Overlay each equipment slot
-    for (idx, &item_id) in snapshot.equipment.iter().enumerate() {
-        if idx >= EQUIPMENT_POSITIONS.len() {
-            break; // Safety check
-        }
-
-        let item_img = if item_id == -1 {
-            // Empty slot, use blank image
-            blank.clone()
-        } else {
+    for (slot, item) in snapshot.equipment.iter() {
+        let loaded;
+        let item_img: &RgbaImage = if let Some(item_id) = item.map(|item| item.type_id) {
             // Try to load item image, fall back to blank if not found
-            match load_item_image(&config.data_dir, item_id) {
-                Ok(img) => img,
+            match cache.get(&config.data_dir, item_id) {
+                Ok(img) => {
+                    loaded = img;
+                    &loaded
+                }
                 Err(e) => {
                     if quiet < 2 {
                         tracing::warn!(
-                            "Item {} not found for player {} (slot {}): {}. Using blank.",
-                            item_id, snapshot.player_name, idx, e
+                            "Item {} ({}) not found for player {} (slot {}): {}. Using blank.",
+                            item_id, config.catalog.name(item_id).unwrap_or("unknown"), snapshot.player_name, slot.name(), e
                         );
                     }
-                    blank.clone()
+                    blank
                 }
             }
+        } else {
+            // Empty slot, use blank image
+            blank
         };
 
-        let (x, y) = EQUIPMENT_POSITIONS[idx];
-        imageops::overlay(&mut base, &item_img, x as i64, y as i64);
-    }
+        let (x, y) = EQUIPMENT_POSITIONS[slot.index()];
+        imageops::overlay(&mut base, item_img, x as i64, y as i64);
 
-    // Ensure output directory exists
-    std::fs::create_dir_all(&config.output_dir)?;
+        // Stacks of one aren't labelled, charges always are. Amounts of items the
+        // catalog knows not to stack are leftovers and not labelled either.
+        let stacks = |type_id: i32| {
+            config.catalog.get(type_id).is_none_or(|known| known.flags.contains(ItemFlags::CUMULATIVE))
+        };
+        if config.show_counts
+            && let Some(item) = item
+            && let Some(count) = item.charges.or(item.amount.filter(|&amount| amount > 1 && stacks(item.type_id)))
+        {
+            draw_count(&mut base, x as i64, y as i64, item_img.width(), item_img.height(), count);
+        }
+    }
 
     // Save the rendered equipment image
-    let output_path = config.output_dir.join(format!("{}.png", snapshot.player_id));
-    base.save(&output_path)
-        .map_err(|e| DemonaxError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to save equipment image: {}", e),
-        )))?;
+    save_image(&base, &config.output_dir, snapshot.player_id, &config.image)
+}
+
+/// Configuration for player card rendering
+pub struct CardConfig {
+    /// Directory with rendered equipment images (`{player_id}.png` or `.webp`, see
+    /// [`render_player_equipment`])
+    pub equipment_dir: PathBuf,
+    pub output_dir: PathBuf,
+    /// Optional background image; defaults to a plain dark card
+    pub background_path: Option<PathBuf>,
+    pub image: ImageOutput,
+}
+
+/// Equipment formats a card can be composed from, in order of preference
+const CARD_EQUIPMENT_FORMATS: [ImageFormat; 2] = [ImageFormat::Png, ImageFormat::Webp];
+
+const CARD_WIDTH: u32 = 300;
+const CARD_HEIGHT: u32 = 165;
+const CARD_BACKGROUND: Rgba<u8> = Rgba([40, 36, 32, 255]);
+const CARD_TITLE: Rgba<u8> = Rgba([255, 214, 120, 255]);
+const CARD_MARGIN: i64 = 8;
+
+/// Compose a player card: the equipment render on the left, name, vocation,
+/// level and the strongest skills on the right.
+pub fn compose_player_card(card: &PlayerCard, equipment: &RgbaImage, background: Option<&RgbaImage>) -> RgbaImage {
+    let mut image = match background {
+        Some(background) => background.clone(),
+        None => RgbaImage::from_pixel(CARD_WIDTH, CARD_HEIGHT, CARD_BACKGROUND),
+    };
+
+    imageops::overlay(&mut image, equipment, CARD_MARGIN, CARD_MARGIN);
+
+    let x = CARD_MARGIN * 2 + equipment.width() as i64;
+    let mut y = CARD_MARGIN + 2;
+    draw_text(&mut image, x, y, &card.player_name, 2, CARD_TITLE);
+    y += 20;
+
+    let mut lines = vec![
+        card.vocation.clone(),
+        format!("Level {}", card.level),
+        format!("Magic level {}", card.magic_level),
+    ];
+    if let Some(blessings) = card.blessings {
+        lines.push(format!("Blessings {}/{}", blessings, crate::game_math::MAX_BLESSINGS));
+    }
+    lines.push(String::new());
+    lines.extend(card.skills.iter().take(3).map(|(name, value)| format!("{} {}", name, value)));
+    match card.missing_spells.len() {
+        0 => {}
+        1 => lines.extend([String::new(), "1 spell to learn".to_string()]),
+        n => lines.extend([String::new(), format!("{} spells to learn", n)]),
+    }
+    for line in &lines {
+        draw_text(&mut image, x, y, line, 1, COUNT_COLOR);
+        y += 10;
+    }
+
+    let date_y = image.height() as i64 - CARD_MARGIN - 5;
+    draw_text(&mut image, x, date_y, &card.snapshot_date, 1, COUNT_COLOR);
+
+    image
+}
+
+/// Render and save a player card as `{player_id}.{extension}` in the output directory
+pub fn render_player_card(card: &PlayerCard, config: &CardConfig) -> Result<PathBuf> {
+    let equipment_path = CARD_EQUIPMENT_FORMATS
+        .iter()
+        .map(|format| config.equipment_dir.join(format!("{}.{}", card.player_id, format.extension())))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| config.equipment_dir.join(format!("{}.png", card.player_id)));
+    let load = |path: &Path| -> Result<RgbaImage> {
+        Ok(open(path)
+            .map_err(|e| DemonaxError::Io(std::io::Error::other(
+                format!("Failed to load image {}: {}", path.display(), e),
+            )))?
+            .to_rgba8())
+    };
 
-    Ok(output_path)
+    let equipment = load(&equipment_path)?;
+    let background = config.background_path.as_deref().map(load).transpose()?;
+    let image = compose_player_card(card, &equipment, background.as_ref());
+
+    save_image(&image, &config.output_dir, card.player_id, &config.image)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::EquipmentSlot;
+
+    #[test]
+    fn test_draw_count_in_bottom_right_corner() {
+        let mut image = RgbaImage::new(32, 32);
+        draw_count(&mut image, 0, 0, 32, 32, 50);
+
+        // "50" is 7 pixels wide and 5 high, ending one pixel inside the corner
+        assert_eq!(*image.get_pixel(24, 26), COUNT_COLOR);
+        assert_eq!(*image.get_pixel(30, 30), COUNT_COLOR);
+        assert_eq!(*image.get_pixel(31, 30), COUNT_OUTLINE);
+        assert_eq!(image.get_pixel(10, 10).0[3], 0);
+    }
+
+    #[test]
+    fn test_compose_player_card() {
+        let card = PlayerCard {
+            player_id: 1001,
+            player_name: "Alice".to_string(),
+            snapshot_date: "2026-01-07".to_string(),
+            level: 45,
+            experience: 1_353_000,
+            vocation: "Elite Knight".to_string(),
+            magic_level: 4,
+            blessings: Some(2),
+            skills: vec![("Sword".to_string(), 78), ("Shielding".to_string(), 70)],
+            missing_spells: vec!["utani hur".to_string()],
+        };
+        let equipment = RgbaImage::from_pixel(112, 149, Rgba([1, 2, 3, 255]));
+
+        let image = compose_player_card(&card, &equipment, None);
+        assert_eq!(image.dimensions(), (CARD_WIDTH, CARD_HEIGHT));
+        assert_eq!(*image.get_pixel(8, 8), Rgba([1, 2, 3, 255]));
+        // "A" of the name starts with its top-centre pixel at double scale
+        assert_eq!(*image.get_pixel(128 + 2, 10), CARD_TITLE);
+    }
+
+    #[test]
+    fn test_image_output_formats() {
+        let mut image = RgbaImage::from_pixel(32, 32, Rgba([40, 36, 32, 255]));
+        draw_count(&mut image, 0, 0, 32, 32, 50);
+
+        let png = encode_image(&image, &ImageOutput::default()).unwrap();
+        let optimized = encode_image(&image, &ImageOutput { optimize: true, ..Default::default() }).unwrap();
+        assert!(optimized.len() <= png.len());
+        assert_eq!(image::load_from_memory(&optimized).unwrap().to_rgba8(), image);
+
+        let webp = encode_image(&image, &ImageOutput { format: ImageFormat::Webp, ..Default::default() }).unwrap();
+        assert_eq!(image::load_from_memory(&webp).unwrap().to_rgba8(), image);
+
+        let avif = encode_image(&image, &ImageOutput { format: ImageFormat::Avif, quality: 50, optimize: false }).unwrap();
+        assert_eq!(&avif[4..12], b"ftypavif");
+    }
+
+    #[test]
+    fn test_item_image_cache_evicts_least_recently_used() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for item_id in [1, 2, 3] {
+            RgbaImage::new(32, 32).save(temp_dir.path().join(format!("{}.png", item_id))).unwrap();
+        }
+        let cache = ItemImageCache::new(2);
+
+        cache.get(temp_dir.path(), 1).unwrap();
+        cache.get(temp_dir.path(), 2).unwrap();
+        cache.get(temp_dir.path(), 1).unwrap();
+        // 2 is the least recently used, so 3 replaces it
+        cache.get(temp_dir.path(), 3).unwrap();
+        cache.get(temp_dir.path(), 1).unwrap();
+        cache.get(temp_dir.path(), 2).unwrap();
+        assert_eq!(cache.stats(), (2, 4));
+        assert!(cache.get(temp_dir.path(), 4).is_err());
+
+        let snapshot = |ids: &[i32]| {
+            let mut equipment = crate::models::Equipment::default();
+            for (slot, &id) in EquipmentSlot::ALL.into_iter().zip(ids) {
+                equipment.set(slot, Some(crate::models::EquipmentItem::new(id)));
+            }
+            PlayerSnapshot { player_id: 1, player_name: "A".to_string(), snapshot_date: "2026-01-07".to_string(), equipment }
+        };
+        let cache = ItemImageCache::new(10);
+        assert_eq!(cache.preload(temp_dir.path(), &[snapshot(&[1, 2]), snapshot(&[1, 3]), snapshot(&[1, 2])], 2), 2);
+        cache.get(temp_dir.path(), 2).unwrap();
+        assert_eq!(cache.stats(), (1, 0));
+    }
+
+    #[test]
+    fn test_render_with_built_in_images() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = RenderConfig {
+            data_dir: temp_dir.path().to_path_buf(),
+            output_dir: temp_dir.path().join("out"),
+            template_path: None,
+            blank_path: None,
+            show_counts: false,
+            image: ImageOutput::default(),
+            catalog: ItemCatalog::new(),
+        };
+        let (template, blank) = config.load_base_images().unwrap();
+        assert_eq!(template.dimensions(), (112, 149));
+        assert_eq!(blank.dimensions(), (32, 32));
+
+        let snapshot = PlayerSnapshot {
+            player_id: 7,
+            player_name: "A".to_string(),
+            snapshot_date: "2026-01-07".to_string(),
+            equipment: crate::models::Equipment::default(),
+        };
+        render_player_equipment(&snapshot, &config, &template, &blank, &ItemImageCache::new(1), 2).unwrap();
+        assert!(temp_dir.path().join("out/7.png").exists());
+
+        let missing = RenderConfig { blank_path: Some(temp_dir.path().join("missing.png")), ..config };
+        assert!(missing.load_base_images().is_err());
+    }
 
     #[test]
     fn test_equipment_positions_count() {
         assert_eq!(EQUIPMENT_POSITIONS.len(), 10, "Should have exactly 10 equipment slots");
     }
+
+    #[test]
+    fn test_equipment_positions_cover_every_slot() {
+        assert_eq!(EQUIPMENT_POSITIONS.len(), EquipmentSlot::ALL.len(), "Should have a position for every equipment slot");
+    }
 }
@@ -0,0 +1,173 @@
+//! Rune spell to rune item linkage: overrides and validation against objects.srv.
+//!
+//! magic.cc only gives a rune's `RuneNr`; the item type ID is computed from it.
+//! When item IDs shift, an override file fixes individual runes:
+//! ```toml
+//! [rune_items]
+//! 21 = 3155                 # spell ID = rune item type ID
+//! "Great Fireball" = 3191   # or spell name
+//! ```
+
+use crate::error::{DemonaxError, Result};
+use crate::models::{Item, Spell};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Rune item type IDs that replace the computed ones, keyed by spell ID or name.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RuneOverrides {
+    #[serde(default)]
+    pub rune_items: HashMap<String, i32>,
+}
+
+impl RuneOverrides {
+    /// Load rune overrides from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DemonaxError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read rune overrides from {:?}: {}", path, e),
+            ))
+        })?;
+
+        content.parse()
+    }
+
+    /// Set the rune item of every overridden spell. Returns the number of spells
+    /// changed; keys that match no rune spell are an error.
+    pub fn apply(&self, spells: &mut [Spell]) -> Result<usize> {
+        let mut unknown = Vec::new();
+        let mut changed = 0;
+        for (key, type_id) in &self.rune_items {
+            let matches = |spell: &Spell| {
+                spell.is_rune && (key.parse() == Ok(spell.spell_id) || spell.name.eq_ignore_ascii_case(key))
+            };
+            match spells.iter_mut().find(|spell| matches(spell)) {
+                Some(spell) => {
+                    spell.rune_type_id = Some(*type_id);
+                    changed += 1;
+                }
+                None => unknown.push(key.as_str()),
+            }
+        }
+
+        if unknown.is_empty() {
+            Ok(changed)
+        } else {
+            unknown.sort();
+            Err(DemonaxError::Validation(format!("rune overrides match no rune spell: {}", unknown.join(", "))))
+        }
+    }
+}
+
+impl FromStr for RuneOverrides {
+    type Err = DemonaxError;
+
+    /// Parse rune overrides from a TOML string.
+    fn from_str(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| {
+            DemonaxError::Parse(format!("Failed to parse rune overrides TOML: {}", e))
+        })
+    }
+}
+
+/// A rune spell whose item is missing from objects.srv or doesn't look like a rune
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuneMismatch {
+    pub spell_id: i32,
+    pub spell_name: String,
+    pub rune_type_id: i32,
+    /// Name of the item with that type ID, `None` if there is none
+    pub item_name: Option<String>,
+}
+
+impl std::fmt::Display for RuneMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.item_name {
+            Some(item) => write!(
+                f,
+                "{} (spell {}) links to item {} \"{}\", which is not a rune",
+                self.spell_name, self.spell_id, self.rune_type_id, item
+            ),
+            None => write!(
+                f,
+                "{} (spell {}) links to item {}, which is not in objects.srv",
+                self.spell_name, self.spell_id, self.rune_type_id
+            ),
+        }
+    }
+}
+
+/// Check every rune spell's item against the items of objects.srv. An item counts
+/// as a rune when it has the `Rune` flag or "rune" in its name.
+pub fn check_rune_items(spells: &[Spell], items: &[Item]) -> Vec<RuneMismatch> {
+    let items: HashMap<i32, &Item> = items.iter().map(|item| (item.type_id, item)).collect();
+
+    spells
+        .iter()
+        .filter_map(|spell| {
+            let type_id = spell.rune_type_id.filter(|_| spell.is_rune)?;
+            let item = items.get(&type_id);
+            let is_rune = item.is_some_and(|item| {
+                item.flags.split(',').any(|flag| flag.trim() == "Rune") || item.name.to_lowercase().contains("rune")
+            });
+            (!is_rune).then(|| RuneMismatch {
+                spell_id: spell.spell_id,
+                spell_name: spell.name.clone(),
+                rune_type_id: type_id,
+                item_name: item.map(|item| item.name.clone()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rune(spell_id: i32, name: &str, rune_type_id: i32) -> Spell {
+        Spell {
+            spell_id,
+            name: name.to_string(),
+            words: String::new(),
+            level: 0,
+            magic_level: None,
+            mana: 0,
+            soul_points: 0,
+            flags: 0,
+            is_rune: true,
+            rune_type_id: Some(rune_type_id),
+            charges: None,
+            spell_type: "attack".to_string(),
+            premium: false,
+            damage_element: None,
+        }
+    }
+
+    fn item(type_id: i32, name: &str, flags: &str) -> Item {
+        Item { type_id, name: name.to_string(), flags: flags.to_string(), attributes: "{}".to_string(), description: None }
+    }
+
+    #[test]
+    fn test_overrides_fix_mismatched_runes() {
+        let mut spells = vec![rune(21, "Sudden Death", 3168), rune(16, "Great Fireball", 3163)];
+        let items = vec![
+            item(3155, "a sudden death rune", "Rune,Take"),
+            item(3163, "a great fireball rune", "Rune,Take"),
+            item(3168, "a dwarven axe", "Take"),
+        ];
+
+        let mismatches = check_rune_items(&spells, &items);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].item_name.as_deref(), Some("a dwarven axe"));
+
+        let overrides: RuneOverrides = "[rune_items]\n21 = 3155\n".parse().unwrap();
+        assert_eq!(overrides.apply(&mut spells).unwrap(), 1);
+        assert!(check_rune_items(&spells, &items).is_empty());
+
+        let unknown: RuneOverrides = "[rune_items]\n\"Fireball\" = 3189\n".parse().unwrap();
+        assert!(unknown.apply(&mut spells).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Trying gifts and edits on test characters before real players.
+//!
+//! Commands that change .usr files take `--only-players "Test Dummy,GM Bob"`,
+//! a [`PlayerFilter`] that leaves every other player's file alone.
+//! [`clone_player`] copies a player under a new name and ID, so a copy of a
+//! real character can be the test dummy.
+
+use crate::error::{DemonaxError, Result};
+use crate::file_utils::read_latin1_file;
+use crate::present::find_usr_files_by_player;
+use encoding_rs::WINDOWS_1252;
+use regex::{NoExpand, Regex};
+use std::path::{Path, PathBuf};
+
+/// The players a command may change, by name (case-insensitive). An empty
+/// filter allows every player.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlayerFilter {
+    names: Vec<String>,
+}
+
+impl PlayerFilter {
+    /// Every player
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Only the given players; blank names are ignored
+    pub fn only<S: AsRef<str>>(names: &[S]) -> Self {
+        let names = names
+            .iter()
+            .map(|name| name.as_ref().trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        Self { names }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn allows(&self, player_name: &str) -> bool {
+        self.is_all() || self.names.contains(&player_name.to_lowercase())
+    }
+
+    /// Keep the files of allowed players, from [`find_usr_files_by_player`]
+    pub fn filter_files(&self, files: Vec<(String, PathBuf)>) -> Vec<(String, PathBuf)> {
+        files.into_iter().filter(|(name, _)| self.allows(name)).collect()
+    }
+}
+
+/// Copy the .usr file of player `name` to `new_name` with ID `new_id` (default:
+/// one above the highest ID below `usr_dir`). The copy goes into the
+/// `<id % 100>/` bucket like the server's files. Returns the new file's path.
+pub fn clone_player(usr_dir: &Path, name: &str, new_name: &str, new_id: Option<i32>) -> Result<PathBuf> {
+    let files = find_usr_files_by_player(usr_dir)?;
+    let (_, source) = files
+        .iter()
+        .find(|(player, _)| *player == name.to_lowercase())
+        .ok_or_else(|| DemonaxError::Validation(format!("No .usr file for player {:?}", name)))?;
+    if new_name.trim().is_empty() || new_name.contains('"') {
+        return Err(DemonaxError::Validation(format!("Invalid player name {:?}", new_name)));
+    }
+    if files.iter().any(|(player, _)| *player == new_name.to_lowercase()) {
+        return Err(DemonaxError::Validation(format!("Player {:?} already exists", new_name)));
+    }
+
+    let ids: Vec<i32> = files
+        .iter()
+        .filter_map(|(_, path)| path.file_stem()?.to_str()?.parse().ok())
+        .collect();
+    let new_id = match new_id {
+        Some(id) if ids.contains(&id) => {
+            return Err(DemonaxError::Validation(format!("Player ID {} is already taken", id)));
+        }
+        Some(id) => id,
+        None => ids.iter().max().map_or(1, |max| max + 1),
+    };
+
+    let content = read_latin1_file(source)?;
+    let id_re = Regex::new(r"(?m)^ID\s*=\s*\d+").unwrap();
+    let name_re = Regex::new(r#"(?m)^Name\s*=\s*"[^"]*""#).unwrap();
+    if !id_re.is_match(&content) || !name_re.is_match(&content) {
+        return Err(DemonaxError::Parse(format!("No ID or Name line in {:?}", source)));
+    }
+    let content = id_re.replace(&content, NoExpand(&format!("ID          = {}", new_id)));
+    let content = name_re.replace(&content, NoExpand(&format!("Name        = \"{}\"", new_name)));
+
+    let (encoded, _, had_errors) = WINDOWS_1252.encode(&content);
+    if had_errors {
+        return Err(DemonaxError::Validation(format!("Player name {:?} is not Windows-1252", new_name)));
+    }
+    let dir = usr_dir.join(format!("{:02}", new_id.rem_euclid(100)));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.usr", new_id));
+    std::fs::write(&path, &*encoded)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_player_filter() {
+        let filter = PlayerFilter::only(&["Test Dummy", " GM Bob ", ""]);
+        assert!(filter.allows("test dummy"));
+        assert!(filter.allows("GM BOB"));
+        assert!(!filter.allows("Alice"));
+        assert!(PlayerFilter::only::<&str>(&[]).is_all());
+        assert!(PlayerFilter::all().allows("Alice"));
+    }
+}
@@ -0,0 +1,215 @@
+//! Hooks for the game server's save script.
+//!
+//! The save script runs `demonax pre-save-hook` before the server writes its
+//! files and `demonax post-save-hook` after. The pre-hook records when the save
+//! started and gives the queued presents (see [`crate::gift_queue`]); the post-hook processes the .usr files written since then as
+//! snapshots and, if any other game file changed, runs [`update_all`].
+//!
+//! Both hooks hold a [`PipelineLock`] next to the database while they run, so a
+//! slow post-hook and the next save's pre-hook can't run at the same time.
+
+use crate::database::Database;
+use crate::error::{DemonaxError, Result};
+use crate::file_utils::find_files_with_extension;
+use crate::gift_queue::flush_gift_queue;
+use crate::present::GiftSummary;
+use crate::processors::{GameEntry, GamePaths, Reporter, update_all};
+use crate::sandbox::PlayerFilter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Locks older than this are left over from a crashed run and are taken over
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(6 * 3600);
+
+/// Game directories whose files are stored by [`update_all`]
+pub const GAME_DATA_ENTRIES: &[GameEntry] = &[GameEntry::Dat, GameEntry::Mon, GameEntry::Npc, GameEntry::Map];
+
+/// Batch size given to [`update_all`]; unused, since the post-hook stores .usr files itself
+const POST_SAVE_BATCH_SIZE: usize = 500;
+
+/// A lock file that exists while a pipeline runs; removed when dropped.
+#[derive(Debug)]
+pub struct PipelineLock {
+    path: PathBuf,
+}
+
+impl PipelineLock {
+    /// Create the lock file, waiting up to `wait` for another pipeline to remove
+    /// it. A lock older than [`STALE_LOCK_AGE`] is taken over.
+    pub fn acquire(path: &Path, wait: Duration) -> Result<Self> {
+        let deadline = SystemTime::now() + wait;
+        loop {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    use std::io::Write;
+                    writeln!(file, "{} {}", std::process::id(), unix_seconds(SystemTime::now()))?;
+                    return Ok(Self { path: path.to_path_buf() });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let age = std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            if age.is_some_and(|age| age > STALE_LOCK_AGE) {
+                tracing::warn!("Taking over stale pipeline lock {:?}", path);
+                let _ = std::fs::remove_file(path);
+                continue;
+            }
+            if SystemTime::now() >= deadline {
+                let holder = std::fs::read_to_string(path).unwrap_or_default();
+                return Err(DemonaxError::Validation(format!(
+                    "Another pipeline holds {:?} (pid and start: {}); remove it if no pipeline is running",
+                    path,
+                    holder.trim()
+                )));
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+    }
+}
+
+impl Drop for PipelineLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// What [`SaveHook::post_save`] did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PostSaveSummary {
+    /// .usr files written by the save
+    pub usr_files: usize,
+    /// New snapshots stored from them
+    pub snapshots: u32,
+    /// Other game files changed by the save
+    pub game_files: usize,
+}
+
+/// Lock and save marker files of a database, e.g. `demonax.sqlite.lock` and
+/// `demonax.sqlite.save`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveHook {
+    pub lock: PathBuf,
+    /// Holds the start of the current save, in Unix seconds
+    pub marker: PathBuf,
+}
+
+impl SaveHook {
+    pub fn for_database(db_path: &Path) -> Self {
+        let sibling = |suffix: &str| {
+            let mut name = db_path.as_os_str().to_os_string();
+            name.push(suffix);
+            PathBuf::from(name)
+        };
+        Self { lock: sibling(".lock"), marker: sibling(".save") }
+    }
+
+    /// Record that a save is starting and apply the queued gifts to the .usr
+    /// files of `game`, backing them up below `backup_dir` first. Only the
+    /// players of `only_players` get presents (see [`flush_gift_queue`]).
+    pub fn pre_save(
+        &self,
+        db: &Database,
+        game: &GamePaths,
+        backup_dir: &Path,
+        only_players: &PlayerFilter,
+        wait: Duration,
+        reporter: &Reporter,
+    ) -> Result<GiftSummary> {
+        let _lock = PipelineLock::acquire(&self.lock, wait)?;
+        let started = unix_seconds(SystemTime::now());
+        std::fs::write(&self.marker, started.to_string())?;
+        reporter.info(format_args!("Marked save start at {}", started));
+
+        flush_gift_queue(db, &game.path(GameEntry::Usr), &db.get_item_catalog()?, backup_dir, only_players, reporter)
+    }
+
+    /// Store what the save changed: the .usr files written since the pre-hook as
+    /// snapshots for `snapshot_date` (default: today), and everything else with
+    /// [`update_all`] if other game files changed. Without a pre-hook mark every
+    /// file counts as changed. Players who already have a snapshot that day keep it.
+    pub fn post_save(
+        &self,
+        db: &Database,
+        game: &GamePaths,
+        snapshot_date: Option<&str>,
+        wait: Duration,
+        reporter: &Reporter,
+    ) -> Result<PostSaveSummary> {
+        let _lock = PipelineLock::acquire(&self.lock, wait)?;
+        let since = match std::fs::read_to_string(&self.marker) {
+            Ok(content) => content.trim().parse::<u64>().map_err(|e| {
+                DemonaxError::Parse(format!("Invalid save marker {:?}: {}", self.marker, e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                reporter.warn(format_args!("No save marker at {:?}, treating every file as changed", self.marker));
+                0
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut summary = PostSaveSummary::default();
+        let changed_game_files = GAME_DATA_ENTRIES
+            .iter()
+            .filter(|&&entry| game.has(entry))
+            .flat_map(|&entry| WalkDir::new(game.path(entry)).into_iter().filter_map(|e| e.ok()))
+            .filter(|entry| entry.file_type().is_file() && modified_since(entry.path(), since))
+            .count();
+        summary.game_files = changed_game_files;
+        if changed_game_files > 0 {
+            reporter.info(format_args!("{} game files changed, running update-all", changed_game_files));
+            update_all(db, game, None, POST_SAVE_BATCH_SIZE, false, reporter)?;
+        }
+
+        if game.has(GameEntry::Usr) {
+            let mut files: Vec<PathBuf> = find_files_with_extension(&game.path(GameEntry::Usr), "usr")?
+                .into_iter()
+                .filter(|path| modified_since(path, since))
+                .collect();
+            files.sort();
+            summary.usr_files = files.len();
+            if !files.is_empty() {
+                let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+                let snapshot_date = snapshot_date.unwrap_or(&today);
+                summary.snapshots = db.process_usr_file_list(&files, snapshot_date, reporter.quiet)?;
+            }
+        }
+
+        match std::fs::remove_file(&self.marker) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(summary),
+        }
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Whether a file was modified at or after `since` (Unix seconds)
+fn modified_since(path: &Path, since: u64) -> bool {
+    std::fs::metadata(path).and_then(|m| m.modified()).is_ok_and(|modified| unix_seconds(modified) >= since)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = SaveHook::for_database(&dir.path().join("demonax.sqlite"));
+        assert_eq!(hook.lock, dir.path().join("demonax.sqlite.lock"));
+
+        let lock = PipelineLock::acquire(&hook.lock, Duration::ZERO).unwrap();
+        let err = PipelineLock::acquire(&hook.lock, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("Another pipeline"));
+        drop(lock);
+        assert!(!hook.lock.exists());
+        PipelineLock::acquire(&hook.lock, Duration::ZERO).unwrap();
+    }
+}
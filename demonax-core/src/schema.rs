@@ -0,0 +1,134 @@
+//! Schema documentation of the live database as Markdown or a Graphviz ERD.
+//!
+//! The structure comes from SQLite itself (see [`Database::describe_schema`]),
+//! the descriptions from the doc map in `database.rs`, so the output always
+//! matches the migrations the database has run.
+//!
+//! [`Database::describe_schema`]: crate::database::Database::describe_schema
+
+use serde::Serialize;
+
+/// A column as reported by `PRAGMA table_info`
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub decl_type: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+    pub primary_key: bool,
+    pub doc: Option<String>,
+}
+
+/// A column referencing another table
+#[derive(Debug, Clone, Serialize)]
+pub struct Reference {
+    pub column: String,
+    pub table: String,
+    pub ref_column: String,
+    /// Declared as a foreign key, rather than a documented implicit relationship
+    pub declared: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub doc: Option<String>,
+    pub columns: Vec<ColumnSchema>,
+    pub references: Vec<Reference>,
+}
+
+/// Render per-table column docs as Markdown.
+pub fn schema_markdown(tables: &[TableSchema]) -> String {
+    let mut text = String::from("# Database Schema\n");
+    for table in tables {
+        text.push_str(&format!("\n## {}\n\n", table.name));
+        if let Some(doc) = &table.doc {
+            text.push_str(&format!("{}\n\n", doc));
+        }
+        text.push_str("| Column | Type | Null | Default | Description |\n|---|---|---|---|---|\n");
+        for column in &table.columns {
+            let mut description = column.doc.clone().unwrap_or_default();
+            if let Some(reference) = table.references.iter().find(|r| r.column == column.name) {
+                let link = format!("→ `{}.{}`", reference.table, reference.ref_column);
+                description = [description, link].iter().filter(|s| !s.is_empty()).cloned().collect::<Vec<_>>().join(" ");
+            }
+            text.push_str(&format!(
+                "| `{}`{} | {} | {} | {} | {} |\n",
+                column.name,
+                if column.primary_key { " (PK)" } else { "" },
+                column.decl_type,
+                if column.not_null || column.primary_key { "no" } else { "yes" },
+                column.default.as_deref().unwrap_or(""),
+                description.replace('|', "\\|"),
+            ));
+        }
+    }
+    text
+}
+
+/// Render an entity-relationship diagram in Graphviz DOT. Declared foreign keys
+/// are solid edges, implicit relationships dashed.
+pub fn schema_dot(tables: &[TableSchema]) -> String {
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+
+    let mut text = String::from("digraph schema {\n    rankdir=LR;\n    node [shape=plaintext, fontname=\"Helvetica\"];\n\n");
+    for table in tables {
+        text.push_str(&format!(
+            "    {} [label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\">\n        <tr><td bgcolor=\"lightgrey\"><b>{}</b></td></tr>\n",
+            table.name,
+            escape(&table.name)
+        ));
+        for column in &table.columns {
+            let name = if column.primary_key { format!("<u>{}</u>", escape(&column.name)) } else { escape(&column.name) };
+            text.push_str(&format!(
+                "        <tr><td port=\"{}\" align=\"left\">{} <font color=\"grey40\">{}</font></td></tr>\n",
+                column.name,
+                name,
+                escape(&column.decl_type)
+            ));
+        }
+        text.push_str("    </table>>];\n");
+    }
+
+    text.push('\n');
+    let known: std::collections::HashSet<&str> = tables.iter().map(|t| t.name.as_str()).collect();
+    for table in tables {
+        for reference in table.references.iter().filter(|r| known.contains(r.table.as_str())) {
+            text.push_str(&format!(
+                "    {}:{} -> {}:{}{};\n",
+                table.name,
+                reference.column,
+                reference.table,
+                reference.ref_column,
+                if reference.declared { "" } else { " [style=dashed]" }
+            ));
+        }
+    }
+    text.push_str("}\n");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Database, TABLE_DOCS};
+
+    #[test]
+    fn test_every_table_is_documented() {
+        let db = Database::in_memory().unwrap();
+        let tables = db.describe_schema().unwrap();
+        assert_eq!(tables.len(), TABLE_DOCS.len());
+        for table in &tables {
+            assert!(table.doc.is_some(), "table {} has no entry in TABLE_DOCS", table.name);
+        }
+
+        let snapshots = tables.iter().find(|t| t.name == "daily_snapshots").unwrap();
+        assert!(snapshots.columns.iter().any(|c| c.name == "profession" && c.doc.is_some()));
+        assert!(snapshots.references.iter().any(|r| r.table == "players" && r.declared));
+
+        let dot = schema_dot(&tables);
+        assert!(dot.contains("daily_snapshots:player_id -> players:id;"));
+        assert!(dot.contains("creature_loot:item_id -> items:type_id [style=dashed];"));
+        assert!(schema_markdown(&tables).contains("| `player_id` | INTEGER | no |  | → `players.id` |"));
+    }
+}
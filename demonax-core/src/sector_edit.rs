@@ -0,0 +1,246 @@
+//! Changing quest chests in map sector (.sec) files, for `edit-chest`.
+//!
+//! A quest chest is one field line of its sector file:
+//! ```text
+//! 12-7: Content={4526, 2472 ChestQuestNumber=1234 Content={3031 Amount=100}}
+//! ```
+//! Only the chest's `ChestQuestNumber` and its `Content={...}` are rewritten.
+//! The rest of the chest line and every other line of the file, including its
+//! line endings, are written back byte for byte.
+
+use crate::error::{DemonaxError, Result};
+use crate::file_utils::{find_files_with_extension, read_latin1_file};
+use crate::geo::{Position, Sector};
+use crate::inventory::parse_item_list;
+use crate::parsers::parse_map_sector_str;
+use encoding_rs::WINDOWS_1252;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// What to change in a quest chest. Fields left `None` stay as they are.
+#[derive(Debug, Clone, Default)]
+pub struct ChestEdit {
+    /// New chest contents in .usr item syntax, replacing all items in the chest.
+    /// Written as given, so attributes such as `KeyNumber=` are kept.
+    pub content: Option<String>,
+    /// New `ChestQuestNumber`
+    pub quest_value: Option<i32>,
+}
+
+/// One changed chest line
+#[derive(Debug, Clone)]
+pub struct ChestChange {
+    pub sector_file: PathBuf,
+    /// 1-based line number in the sector file
+    pub line_number: usize,
+    pub old_line: String,
+    pub new_line: String,
+    /// Copy of the sector file made before writing; `None` on a dry run
+    pub backup: Option<PathBuf>,
+}
+
+/// Find the .sec file of `sector` below `map_dir`. Stems may be zero-padded,
+/// e.g. `1018-0997-03`.
+pub fn find_sector_file(map_dir: &Path, sector: Sector) -> Result<PathBuf> {
+    find_files_with_extension(map_dir, "sec")?
+        .into_iter()
+        .find(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(Sector::from_file_stem)
+                == Some(sector)
+        })
+        .ok_or_else(|| DemonaxError::NotFound(format!("No sector file {}.sec in {}", sector, map_dir.display())))
+}
+
+/// Apply `edit` to a chest line (without its line ending)
+pub fn edit_chest_line(line: &str, edit: &ChestEdit) -> Result<String> {
+    let quest_value_re = Regex::new(r"ChestQuestNumber\s*=\s*(\d+)")
+        .map_err(|e| DemonaxError::Parse(format!("Regex error: {}", e)))?;
+    let quest = quest_value_re
+        .captures(line)
+        .ok_or_else(|| DemonaxError::Parse(format!("No ChestQuestNumber in line '{}'", line)))?;
+    let number = quest.get(1).expect("group 1 always matches");
+    let (before, after) = (&line[..number.start()], &line[number.end()..]);
+
+    let mut result = before.to_string();
+    result.push_str(&match edit.quest_value {
+        Some(value) => value.to_string(),
+        None => number.as_str().to_string(),
+    });
+
+    let Some(content) = &edit.content else {
+        result.push_str(after);
+        return Ok(result);
+    };
+    parse_item_list(content)?;
+    let content = content.trim();
+
+    match chest_content_range(after) {
+        Some((start, end)) => {
+            result.push_str(&after[..start]);
+            result.push_str(content);
+            result.push_str(&after[end..]);
+        }
+        // An empty chest has no Content block yet
+        None => {
+            result.push_str(&format!(" Content={{{}}}", content));
+            result.push_str(after);
+        }
+    }
+    Ok(result)
+}
+
+/// Byte range inside the braces of the `Content={...}` that follows the chest's
+/// quest number. `None` when the chest has none, i.e. when another item or the
+/// end of the field comes first.
+fn chest_content_range(after_quest: &str) -> Option<(usize, usize)> {
+    let content_start = after_quest.find("Content=")?;
+    if after_quest[..content_start].contains([',', '{', '}']) {
+        return None;
+    }
+    let brace_start = content_start + "Content=".len() + after_quest[content_start + "Content=".len()..].find('{')?;
+
+    let mut depth = 0;
+    for (i, ch) in after_quest[brace_start..].char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((brace_start + 1, brace_start + i));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Change the quest chest at `position` in its sector file below `map_dir`.
+///
+/// The file is copied below `backup_dir/<timestamp>/` before it is written,
+/// and read back afterwards to check the chest parses with the new values.
+/// With `dry_run` nothing is copied or written.
+pub fn edit_chest(map_dir: &Path, position: Position, edit: &ChestEdit, backup_dir: &Path, dry_run: bool) -> Result<ChestChange> {
+    let sector = position.sector();
+    let sector_file = find_sector_file(map_dir, sector)?;
+    let text = read_latin1_file(&sector_file)?;
+
+    let origin = sector.origin();
+    let field = format!("{}-{}:", position.x - origin.x, position.y - origin.y);
+
+    let mut lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let index = lines
+        .iter()
+        .position(|line| line.starts_with(&field) && line.contains("ChestQuestNumber"))
+        .ok_or_else(|| DemonaxError::NotFound(format!("No quest chest at {} in {}", position, sector_file.display())))?;
+    let line = lines[index];
+
+    let old_line = line.trim_end_matches(['\r', '\n']);
+    let ending = &line[old_line.len()..];
+    let new_line = edit_chest_line(old_line, edit)?;
+    let replaced = format!("{}{}", new_line, ending);
+    let old_line = old_line.to_string();
+    lines[index] = &replaced;
+    let new_text = lines.concat();
+
+    let sector_name = sector_file.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+    verify_chest(&new_text, &sector_name, position, edit)?;
+
+    let mut change = ChestChange { sector_file, line_number: index + 1, old_line, new_line, backup: None };
+    if dry_run {
+        return Ok(change);
+    }
+
+    let (encoded, _, had_errors) = WINDOWS_1252.encode(&new_text);
+    if had_errors {
+        return Err(DemonaxError::Validation("Failed to encode sector file to Windows-1252".to_string()));
+    }
+
+    let backup_root = backup_dir.join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+    std::fs::create_dir_all(&backup_root)?;
+    let backup = backup_root.join(change.sector_file.strip_prefix(map_dir).unwrap_or(&change.sector_file));
+    if let Some(parent) = backup.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(&change.sector_file, &backup)?;
+    change.backup = Some(backup);
+
+    std::fs::write(&change.sector_file, &encoded)?;
+    verify_chest(&read_latin1_file(&change.sector_file)?, &sector_name, position, edit)?;
+    Ok(change)
+}
+
+/// Check the chest at `position` parses from `text` with the edited values
+fn verify_chest(text: &str, sector_name: &str, position: Position, edit: &ChestEdit) -> Result<()> {
+    let chest = parse_map_sector_str(text, sector_name)?
+        .into_iter()
+        .find(|chest| (chest.ingame_x, chest.ingame_y, chest.sector_level) == (position.x, position.y, position.z))
+        .ok_or_else(|| DemonaxError::Validation(format!("Chest at {} no longer parses", position)))?;
+
+    if let Some(value) = edit.quest_value
+        && chest.quest_value != value
+    {
+        return Err(DemonaxError::Validation(format!(
+            "Chest at {} has quest value {} after writing, expected {}",
+            position, chest.quest_value, value
+        )));
+    }
+
+    if let Some(content) = &edit.content {
+        let field = format!("{}-{}:", chest.offset_x, chest.offset_y);
+        let written = text
+            .lines()
+            .find(|line| line.starts_with(&field) && line.contains("ChestQuestNumber"))
+            .and_then(|line| {
+                let after = &line[line.find("ChestQuestNumber")?..];
+                let (start, end) = chest_content_range(after)?;
+                Some(after[start..end].to_string())
+            })
+            .unwrap_or_default();
+        if written != content.trim() {
+            return Err(DemonaxError::Validation(format!(
+                "Chest at {} holds {{{}}} after writing, expected {{{}}}",
+                position,
+                written,
+                content.trim()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_chest_line() {
+        let line = "12-7: Content={4526, 2472 ChestQuestNumber=1234 KeyNumber=5 Content={3031 Amount=50}}";
+
+        let edit = ChestEdit { content: Some("3031 Amount=100, 2088 KeyNumber=4601".to_string()), quest_value: None };
+        assert_eq!(
+            edit_chest_line(line, &edit).unwrap(),
+            "12-7: Content={4526, 2472 ChestQuestNumber=1234 KeyNumber=5 Content={3031 Amount=100, 2088 KeyNumber=4601}}"
+        );
+
+        let edit = ChestEdit { content: None, quest_value: Some(99) };
+        assert_eq!(
+            edit_chest_line(line, &edit).unwrap(),
+            "12-7: Content={4526, 2472 ChestQuestNumber=99 KeyNumber=5 Content={3031 Amount=50}}"
+        );
+
+        // An empty chest gets a Content block; the item after it keeps its own
+        let empty = "3-4: Content={2472 ChestQuestNumber=7, 2853 Content={3585}}";
+        let edit = ChestEdit { content: Some(" 3031 ".to_string()), quest_value: None };
+        assert_eq!(
+            edit_chest_line(empty, &edit).unwrap(),
+            "3-4: Content={2472 ChestQuestNumber=7 Content={3031}, 2853 Content={3585}}"
+        );
+
+        assert!(edit_chest_line("1-1: Content={4526}", &edit).is_err());
+        let invalid = ChestEdit { content: Some("gold".to_string()), quest_value: None };
+        assert!(edit_chest_line(line, &invalid).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Tiles affected by a creature spell, from its shape parameters.
+//!
+//! Circles (Origin around the caster, Destination around the target) hold the
+//! tiles with dx² + dy² <= r·(r + 1), which gives the classic 37-tile radius 3
+//! ball. Cones (Angle) reach `range` tiles ahead of the caster and hold the tiles
+//! at most `angle` degrees off the facing direction; angle 0 is a straight beam.
+//!
+//! Masks are rows of `x` (affected) and `.` tiles, top to bottom. Circles are
+//! centered on the middle tile; cones face up, with the caster as `c` in the
+//! middle of the bottom row.
+
+use crate::models::SpellShapeType;
+
+/// Radii and ranges beyond this are clamped; nothing further is on screen
+pub const MAX_EXTENT: i32 = 16;
+
+/// Footprint of a spell's area
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpellArea {
+    /// Number of affected tiles
+    pub tiles: i32,
+    pub mask: Vec<String>,
+}
+
+impl SpellArea {
+    /// The mask as a JSON array of row strings
+    pub fn mask_json(&self) -> String {
+        serde_json::to_string(&self.mask).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn from_mask(mask: Vec<String>) -> Self {
+        let tiles = mask.iter().map(|row| row.matches('x').count() as i32).sum();
+        Self { tiles, mask }
+    }
+}
+
+/// Footprint of a spell with the given shape. Actor and Victim spells hit a single tile.
+pub fn spell_area(shape_type: SpellShapeType, shape_params: &[i32]) -> SpellArea {
+    let param = |index: usize| shape_params.get(index).copied().unwrap_or(0).clamp(0, MAX_EXTENT);
+    match shape_type {
+        SpellShapeType::Actor | SpellShapeType::Victim => SpellArea::from_mask(vec!["x".to_string()]),
+        SpellShapeType::Origin => circle(param(0)),
+        SpellShapeType::Destination => circle(param(2)),
+        SpellShapeType::Angle => cone(shape_params.first().copied().unwrap_or(0), param(1)),
+    }
+}
+
+fn circle(radius: i32) -> SpellArea {
+    let rows = (-radius..=radius)
+        .map(|dy| {
+            (-radius..=radius)
+                .map(|dx| if dx * dx + dy * dy <= radius * (radius + 1) { 'x' } else { '.' })
+                .collect()
+        })
+        .collect();
+    SpellArea::from_mask(rows)
+}
+
+fn cone(angle: i32, range: i32) -> SpellArea {
+    let within = |offset: i32, distance: i32| {
+        (offset.abs() as f64).atan2(distance as f64).to_degrees() <= angle as f64 + 1e-9
+    };
+    // The cone is widest at full range; narrower masks save space for beams
+    let half_width = (0..=range).rev().find(|&offset| within(offset, range.max(1))).unwrap_or(0);
+
+    let mut rows: Vec<String> = (1..=range)
+        .rev()
+        .map(|distance| {
+            (-half_width..=half_width)
+                .map(|offset| if within(offset, distance) { 'x' } else { '.' })
+                .collect()
+        })
+        .collect();
+    let caster_row = (-half_width..=half_width).map(|offset| if offset == 0 { 'c' } else { '.' }).collect();
+    rows.push(caster_row);
+    SpellArea::from_mask(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circle_footprints() {
+        assert_eq!(spell_area(SpellShapeType::Origin, &[0, 13]).tiles, 1);
+        assert_eq!(spell_area(SpellShapeType::Origin, &[1, 13]).tiles, 9);
+        let ball = spell_area(SpellShapeType::Destination, &[7, 4, 3, 7]);
+        assert_eq!(ball.tiles, 37);
+        assert_eq!(ball.mask[0], "..xxx..");
+        assert_eq!(ball.mask[3], "xxxxxxx");
+    }
+
+    #[test]
+    fn test_cone_footprints() {
+        let beam = spell_area(SpellShapeType::Angle, &[0, 8, 7]);
+        assert_eq!(beam.tiles, 8);
+        assert_eq!(beam.mask.last().unwrap(), "c");
+
+        // Waves widen 1, 3, 3, 5, 5 tiles away from the caster
+        let wave = spell_area(SpellShapeType::Angle, &[30, 5, 7]);
+        let widths: Vec<usize> = wave.mask.iter().rev().skip(1).map(|row| row.matches('x').count()).collect();
+        assert_eq!(widths, vec![1, 3, 3, 5, 5]);
+        assert_eq!(wave.tiles, 17);
+        assert_eq!(wave.mask[0], "xxxxx");
+    }
+
+    #[test]
+    fn test_single_tile_shapes() {
+        let area = spell_area(SpellShapeType::Victim, &[7, 4, 7]);
+        assert_eq!((area.tiles, area.mask_json().as_str()), (1, r#"["x"]"#));
+    }
+}
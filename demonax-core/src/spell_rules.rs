@@ -0,0 +1,158 @@
+//! Names and categories of creature spells, from a TOML ruleset.
+//!
+//! A built-in ruleset (`assets/spell_rules.toml`) is used unless another one is
+//! set with [`set_spell_rules`], so spells can be renamed without a rebuild:
+//! ```toml
+//! rules = [
+//!     { shape = "Victim", impact = "Damage", damage_type = 4, name = "Fire Strike", category = "Attack" },
+//!     { impact = "Damage", name = "{shape} {impact}", category = "Attack" },
+//! ]
+//! ```
+
+use crate::error::{DemonaxError, Result};
+use crate::models::{SpellImpactType, SpellShapeType};
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{LazyLock, OnceLock};
+
+const BUILT_IN_RULES: &str = include_str!("../assets/spell_rules.toml");
+
+/// Direction of a Speed impact, from the sign of its modifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeedChange {
+    Boost,
+    Slow,
+}
+
+/// One naming rule; conditions left out match any spell.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpellRule {
+    pub shape: Option<SpellShapeType>,
+    pub impact: Option<SpellImpactType>,
+    /// Damage bitmask value (first Damage parameter)
+    pub damage_type: Option<i32>,
+    pub speed: Option<SpeedChange>,
+    /// Display name; `{shape}` and `{impact}` are replaced by the readable names
+    pub name: String,
+    pub category: String,
+}
+
+/// Naming rules in order; the first matching rule names a spell.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpellRuleset {
+    pub rules: Vec<SpellRule>,
+}
+
+impl SpellRuleset {
+    /// The ruleset shipped with demonax-tools
+    pub fn built_in() -> &'static SpellRuleset {
+        static BUILT_IN: LazyLock<SpellRuleset> =
+            LazyLock::new(|| BUILT_IN_RULES.parse().expect("built-in spell rules are valid"));
+        &BUILT_IN
+    }
+
+    /// Load a ruleset from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DemonaxError::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read spell rules from {:?}: {}", path, e),
+            ))
+        })?;
+
+        content.parse()
+    }
+
+    /// Name and category of a spell. `shape_name` and `impact_name` are the
+    /// readable names used for `{shape}` and `{impact}`; a spell no rule matches
+    /// is named after them with category "Other".
+    pub fn interpret(
+        &self,
+        shape_type: SpellShapeType,
+        impact_type: SpellImpactType,
+        impact_params: &[i32],
+        shape_name: &str,
+        impact_name: &str,
+    ) -> (String, String) {
+        let first_param = impact_params.first().copied();
+        let damage_type = (impact_type == SpellImpactType::Damage).then(|| first_param.unwrap_or(1));
+        let speed = (impact_type == SpellImpactType::Speed)
+            .then(|| if first_param.unwrap_or(0) > 0 { SpeedChange::Boost } else { SpeedChange::Slow });
+
+        let matching = self.rules.iter().find(|rule| {
+            rule.shape.is_none_or(|shape| shape == shape_type)
+                && rule.impact.is_none_or(|impact| impact == impact_type)
+                && rule.damage_type.is_none_or(|value| damage_type == Some(value))
+                && rule.speed.is_none_or(|change| speed == Some(change))
+        });
+
+        match matching {
+            Some(rule) => (
+                rule.name.replace("{shape}", shape_name).replace("{impact}", impact_name),
+                rule.category.clone(),
+            ),
+            None => (format!("{} {}", shape_name, impact_name), "Other".to_string()),
+        }
+    }
+}
+
+impl FromStr for SpellRuleset {
+    type Err = DemonaxError;
+
+    /// Parse a ruleset from a TOML string.
+    fn from_str(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| DemonaxError::Parse(format!("Failed to parse spell rules TOML: {}", e)))
+    }
+}
+
+static SPELL_RULES: OnceLock<SpellRuleset> = OnceLock::new();
+
+/// Name creature spells parsed for the rest of the process with `ruleset`
+/// instead of the built-in one. Can only be set once.
+pub fn set_spell_rules(ruleset: SpellRuleset) -> Result<()> {
+    SPELL_RULES
+        .set(ruleset)
+        .map_err(|_| DemonaxError::Validation("spell rules are already set".to_string()))
+}
+
+/// The ruleset set with [`set_spell_rules`], or the built-in one
+pub fn spell_rules() -> &'static SpellRuleset {
+    SPELL_RULES.get().unwrap_or_else(|| SpellRuleset::built_in())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let ruleset: SpellRuleset = r#"
+            rules = [
+                { shape = "Actor", impact = "Healing", name = "Mend", category = "Heal" },
+                { impact = "Speed", speed = "slow", name = "{shape} Slow", category = "Debuff" },
+                { impact = "Healing", name = "Heal", category = "Heal" },
+            ]
+        "#
+        .parse()
+        .unwrap();
+
+        let interpret = |shape, impact, params: &[i32]| ruleset.interpret(shape, impact, params, "Cone", "Paralyze");
+        assert_eq!(interpret(SpellShapeType::Actor, SpellImpactType::Healing, &[40, 20]).0, "Mend");
+        assert_eq!(interpret(SpellShapeType::Victim, SpellImpactType::Healing, &[40, 20]).0, "Heal");
+        assert_eq!(interpret(SpellShapeType::Angle, SpellImpactType::Speed, &[-70, 20]).0, "Cone Slow");
+        assert_eq!(
+            interpret(SpellShapeType::Angle, SpellImpactType::Speed, &[70, 20]),
+            ("Cone Paralyze".to_string(), "Other".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_rule_fields_are_rejected() {
+        let result: Result<SpellRuleset> = r#"rules = [{ shape = "Actor", nme = "Typo", category = "Heal" }]"#.parse();
+        assert!(matches!(result, Err(DemonaxError::Parse(_))));
+    }
+}
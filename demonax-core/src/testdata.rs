@@ -0,0 +1,313 @@
+//! Synthetic game file generator for tests and fixtures.
+//!
+//! Produces minimal but well-formed .usr, .mon, .npc, .evt, .sec and
+//! objects.srv files laid out like a real game directory:
+//! ```text
+//! <root>/dat/objects.srv
+//! <root>/mon/*.mon, *.evt
+//! <root>/npc/*.npc
+//! <root>/map/<x>-<y>-<z>.sec
+//! <root>/usr/<nn>/<id>.usr
+//! ```
+
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+
+/// Player character written as a .usr file.
+#[derive(Debug, Clone)]
+pub struct UsrSpec {
+    pub id: i32,
+    pub name: String,
+    pub level: i32,
+    pub experience: i64,
+    pub magic_level: i32,
+    /// Fist, club, sword, axe, distance, shielding, fishing
+    pub skills: [i32; 7],
+    /// Tries of the skills, in the order of `skills`
+    pub skill_tries: [i64; 7],
+    /// Profession number, written as `Profession = n` when set
+    pub profession: Option<i32>,
+    /// Account number, written as `Account = n` when set
+    pub account: Option<i32>,
+    /// Premium days left, written as `PremiumDays = n` when set
+    pub premium_days: Option<i32>,
+    /// Number of blessings, written as `Blessings = {1,..,n}` when set
+    pub blessings: Option<i32>,
+    /// Learned spell numbers, written as `SpellList = {..}` when not empty
+    pub spells: Vec<i32>,
+    pub quest_values: Vec<(i32, i32)>,
+    pub bestiary: Vec<(i32, i32)>,
+    pub harvesting: Vec<(i32, i32)>,
+    /// Raw inventory slot entries, e.g. `(1, "3354")` or `(3, "2854 Content={3031 Amount=40}")`
+    pub inventory: Vec<(i32, String)>,
+}
+
+impl UsrSpec {
+    pub fn new(id: i32, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            level: 8,
+            experience: 4200,
+            magic_level: 0,
+            skills: [10, 10, 10, 10, 10, 10, 10],
+            skill_tries: [0; 7],
+            profession: None,
+            account: None,
+            premium_days: None,
+            blessings: None,
+            spells: Vec::new(),
+            quest_values: Vec::new(),
+            bestiary: Vec::new(),
+            harvesting: Vec::new(),
+            inventory: Vec::new(),
+        }
+    }
+}
+
+/// Creature written as a .mon file.
+#[derive(Debug, Clone)]
+pub struct MonSpec {
+    pub file_stem: String,
+    pub name: String,
+    pub article: String,
+    pub race: i32,
+    pub hp: i32,
+    pub experience: i32,
+    pub flags: Vec<String>,
+    /// Melee skill (FistFighting) and the top-level Attack, Defend and Armor values
+    pub fist_fighting: i32,
+    pub attack: i32,
+    pub defend: i32,
+    pub armor: i32,
+    /// Raw spell entries, e.g. `"Victim (7, 4, 5) -> Damage (4, 40, 10) : 9"`
+    pub spells: Vec<String>,
+    /// (item_id, amount, chance_raw)
+    pub loot: Vec<(i32, i32, i32)>,
+}
+
+impl MonSpec {
+    pub fn new(file_stem: &str, name: &str, race: i32) -> Self {
+        Self {
+            file_stem: file_stem.to_string(),
+            name: name.to_string(),
+            article: "a".to_string(),
+            race,
+            hp: 100,
+            experience: 50,
+            flags: Vec::new(),
+            fist_fighting: 20,
+            attack: 10,
+            defend: 10,
+            armor: 5,
+            spells: Vec::new(),
+            loot: Vec::new(),
+        }
+    }
+}
+
+/// Item record written into objects.srv.
+#[derive(Debug, Clone)]
+pub struct ItemSpec {
+    pub type_id: i32,
+    pub name: String,
+    pub flags: Vec<String>,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl ItemSpec {
+    /// A takeable item with the given name.
+    pub fn new(type_id: i32, name: &str) -> Self {
+        Self {
+            type_id,
+            name: name.to_string(),
+            flags: vec!["Take".to_string()],
+            attributes: Vec::new(),
+        }
+    }
+}
+
+/// Render a .usr file.
+pub fn usr_file(spec: &UsrSpec) -> String {
+    // Skill tuples have 15 fields; field 1 is the value and field 11 the tries (experience for level)
+    let skill = |id: i32, value: i32, exp: i64| {
+        format!("Skill       = ({}, {}, 0, 0, 0, 0, 0, 0, 0, 0, 0, {}, 0, 0, 0)\n", id, value, exp)
+    };
+
+    let mut text = format!("ID          = {}\nName        = \"{}\"\n", spec.id, spec.name);
+    if let Some(profession) = spec.profession {
+        text.push_str(&format!("Profession  = {}\n", profession));
+    }
+    if let Some(account) = spec.account {
+        text.push_str(&format!("Account     = {}\n", account));
+    }
+    if let Some(premium_days) = spec.premium_days {
+        text.push_str(&format!("PremiumDays = {}\n", premium_days));
+    }
+    text.push_str(&skill(0, spec.level, spec.experience));
+    text.push_str(&skill(1, spec.magic_level, 0));
+    // Skill ids in the order of UsrSpec::skills
+    for ((skill_id, value), tries) in [11, 9, 8, 10, 7, 6, 13].iter().zip(spec.skills.iter()).zip(spec.skill_tries.iter()) {
+        text.push_str(&skill(*skill_id, *value, *tries));
+    }
+    if let Some(blessings) = spec.blessings {
+        let numbers: Vec<String> = (1..=blessings).map(|n| n.to_string()).collect();
+        text.push_str(&format!("Blessings   = {{{}}}\n", numbers.join(",")));
+    }
+    if !spec.spells.is_empty() {
+        let spells: Vec<String> = spec.spells.iter().map(|id| id.to_string()).collect();
+        text.push_str(&format!("SpellList   = {{{}}}\n", spells.join(",")));
+    }
+    text.push_str(&format!("QuestValues = {}\n", pair_list(&spec.quest_values)));
+    text.push_str(&format!("Bestiary    = {}\n", pair_list(&spec.bestiary)));
+    text.push_str(&format!("Harvesting  = {}\n", pair_list(&spec.harvesting)));
+
+    let slots: Vec<String> = spec
+        .inventory
+        .iter()
+        .map(|(slot, content)| format!("{} Content={{{}}}", slot, content))
+        .collect();
+    text.push_str(&format!("Inventory   = {{{}}}\n", slots.join(",\n               ")));
+    text.push_str("Depot       = {}\n");
+    text
+}
+
+fn pair_list(pairs: &[(i32, i32)]) -> String {
+    let entries: Vec<String> = pairs.iter().map(|(a, b)| format!("({},{})", a, b)).collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+/// Render a .mon file.
+pub fn mon_file(spec: &MonSpec) -> String {
+    let mut text = format!(
+        "Name        = \"{}\"\nArticle     = \"{}\"\nRaceNumber  = {}\nExperience  = {}\n",
+        spec.name, spec.article, spec.race, spec.experience
+    );
+    text.push_str(&format!("Flags       = {{{}}}\n", spec.flags.join(",\n               ")));
+    text.push_str(&format!(
+        "Skills      = {{(HitPoints, {hp}, 0, {hp}, 0, 0, 0),\n               (GoStrength, 20, 0, 20, 0, 0, 0),\n               (FistFighting, {fist}, {fist}, {fist}, 50, 1000, 1)}}\n",
+        hp = spec.hp,
+        fist = spec.fist_fighting
+    ));
+    text.push_str(&format!(
+        "Attack      = {}\nDefend      = {}\nArmor       = {}\n",
+        spec.attack, spec.defend, spec.armor
+    ));
+    if !spec.spells.is_empty() {
+        text.push_str(&format!("Spells      = {{{}}}\n", spec.spells.join(",\n               ")));
+    }
+    if !spec.loot.is_empty() {
+        let loot: Vec<String> = spec
+            .loot
+            .iter()
+            .map(|(item, amount, chance)| format!("({}, {}, {})", item, amount, chance))
+            .collect();
+        text.push_str(&format!("Inventory   = {{{}}}\n", loot.join(", ")));
+    }
+    text
+}
+
+/// Render a .npc file with `(item_id, price)` sell and buy offers.
+pub fn npc_file(name: &str, sells: &[(i32, i32)], buys: &[(i32, i32)]) -> String {
+    let mut text = format!("Name = \"{}\"\n\nBehaviour = {{\n", name);
+    for (item_id, price) in sells {
+        text.push_str(&format!("\"sell\",\"item{0}\" -> Type={0}, Amount=1, Price={1}\n", item_id, price));
+    }
+    for (item_id, price) in buys {
+        text.push_str(&format!("\"buy\",\"item{0}\" -> Type={0}, Amount=1, Price={1}\n", item_id, price));
+    }
+    text.push_str("}\n");
+    text
+}
+
+/// Render a single-wave .evt raid file spawning `(race, min, max)` groups.
+pub fn evt_file(interval_seconds: i64, message: &str, spawns: &[(i32, i32, i32)]) -> String {
+    let mut text = format!(
+        "# Process: one wave\nType = BigRaid\nInterval = {}\n\nDelay = 0\nMessage = \"{}\"\n",
+        interval_seconds, message
+    );
+    for (race, min, max) in spawns {
+        text.push_str(&format!("\nDelay = 60\nPosition = [32000,32000,7]\nRace = {}\nCount = ({}, {})\n", race, min, max));
+    }
+    text
+}
+
+/// Render a .sec map sector line for a quest chest at sector offset (x, y).
+pub fn sec_chest_line(offset_x: i32, offset_y: i32, quest_value: i32, item_ids: &[i32]) -> String {
+    let items: Vec<String> = item_ids.iter().map(|id| id.to_string()).collect();
+    format!(
+        "{}-{}: Content={{4526, 2472 ChestQuestNumber={} Content={{{}}}}}",
+        offset_x,
+        offset_y,
+        quest_value,
+        items.join(", ")
+    )
+}
+
+/// Render an objects.srv file.
+pub fn objects_srv(items: &[ItemSpec]) -> String {
+    let mut text = String::from("# Generated objects.srv\n\n");
+    for item in items {
+        text.push_str(&format!("TypeID      = {}\nName        = \"{}\"\n", item.type_id, item.name));
+        text.push_str(&format!("Flags       = {{{}}}\n", item.flags.join(",")));
+        if !item.attributes.is_empty() {
+            let attrs: Vec<String> = item.attributes.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            text.push_str(&format!("Attributes  = {{{}}}\n", attrs.join(",")));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// A synthetic game directory on disk.
+pub struct SyntheticGame {
+    pub root: PathBuf,
+}
+
+impl SyntheticGame {
+    /// Create the directory layout (dat/, mon/, npc/, map/, usr/) under `root`.
+    pub fn create(root: &Path) -> Result<Self> {
+        for dir in ["dat", "mon", "npc", "map", "usr"] {
+            std::fs::create_dir_all(root.join(dir))?;
+        }
+        Ok(Self { root: root.to_path_buf() })
+    }
+
+    pub fn usr_dir(&self) -> PathBuf {
+        self.root.join("usr")
+    }
+
+    /// Write a .usr file into the `usr/<id % 100>/` bucket like the game server does.
+    pub fn add_usr(&self, spec: &UsrSpec) -> Result<PathBuf> {
+        let dir = self.usr_dir().join(format!("{:02}", spec.id % 100));
+        std::fs::create_dir_all(&dir)?;
+        self.write(&dir.join(format!("{}.usr", spec.id)), &usr_file(spec))
+    }
+
+    pub fn add_mon(&self, spec: &MonSpec) -> Result<PathBuf> {
+        self.write(&self.root.join("mon").join(format!("{}.mon", spec.file_stem)), &mon_file(spec))
+    }
+
+    pub fn add_npc(&self, file_stem: &str, text: &str) -> Result<PathBuf> {
+        self.write(&self.root.join("npc").join(format!("{}.npc", file_stem)), text)
+    }
+
+    pub fn add_evt(&self, file_stem: &str, text: &str) -> Result<PathBuf> {
+        self.write(&self.root.join("mon").join(format!("{}.evt", file_stem)), text)
+    }
+
+    /// Write a map sector file from raw lines.
+    pub fn add_sec(&self, sector: (i32, i32, i32), lines: &[String]) -> Result<PathBuf> {
+        let name = format!("{}-{}-{}.sec", sector.0, sector.1, sector.2);
+        self.write(&self.root.join("map").join(name), &(lines.join("\n") + "\n"))
+    }
+
+    pub fn write_objects_srv(&self, items: &[ItemSpec]) -> Result<PathBuf> {
+        self.write(&self.root.join("dat").join("objects.srv"), &objects_srv(items))
+    }
+
+    fn write(&self, path: &Path, text: &str) -> Result<PathBuf> {
+        std::fs::write(path, text)?;
+        Ok(path.to_path_buf())
+    }
+}
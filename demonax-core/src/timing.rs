@@ -0,0 +1,160 @@
+//! Wall-clock time per tracing span, for the `--timing` breakdown.
+//!
+//! Processing stages open spans (`stage`, `parse`, `persist`, `usr_parse`, ...);
+//! [`TimingLayer`] adds up how long each one was open. Spans with a `stage`
+//! field are counted per stage, e.g. `stage:creatures`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Totals of all closed spans with the same key
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpanTiming {
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+/// Shared span totals; clone it to keep a handle after installing the layer.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    spans: Arc<Mutex<HashMap<String, SpanTiming>>>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A layer recording into these timings
+    pub fn layer(&self) -> TimingLayer {
+        TimingLayer { timings: self.clone() }
+    }
+
+    /// Totals per span key, longest total first
+    pub fn snapshot(&self) -> Vec<(String, SpanTiming)> {
+        let spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries: Vec<(String, SpanTiming)> = spans.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        entries.sort_by(|a, b| b.1.total.cmp(&a.1.total).then_with(|| a.0.cmp(&b.0)));
+        entries
+    }
+
+    /// Render the totals as a table. Nested spans are included in their parents' totals.
+    pub fn report(&self) -> String {
+        let entries = self.snapshot();
+        let width = entries.iter().map(|(key, _)| key.len()).max().unwrap_or(0).max(4);
+        let mut text = format!("{:<width$} {:>7} {:>11} {:>11} {:>11}\n", "Span", "Count", "Total", "Mean", "Max");
+        for (key, timing) in &entries {
+            let mean = timing.total / timing.count.max(1) as u32;
+            text.push_str(&format!(
+                "{:<width$} {:>7} {:>11} {:>11} {:>11}\n",
+                key,
+                timing.count,
+                format_duration(timing.total),
+                format_duration(mean),
+                format_duration(timing.max),
+            ));
+        }
+        text
+    }
+
+    fn record(&self, key: String, elapsed: Duration) {
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let timing = spans.entry(key).or_default();
+        timing.count += 1;
+        timing.total += elapsed;
+        timing.max = timing.max.max(elapsed);
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let ms = duration.as_secs_f64() * 1000.0;
+    if ms >= 1000.0 { format!("{:.2}s", ms / 1000.0) } else { format!("{:.1}ms", ms) }
+}
+
+/// Tracing layer that records how long each span was open
+pub struct TimingLayer {
+    timings: Timings,
+}
+
+/// Key and start time, stored in the span's extensions
+struct SpanStart {
+    key: String,
+    start: Instant,
+}
+
+#[derive(Default)]
+struct StageVisitor(Option<String>);
+
+impl Visit for StageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "stage" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "stage" {
+            self.0 = Some(format!("{:?}", value).trim_matches('"').to_string());
+        }
+    }
+}
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = StageVisitor::default();
+        attrs.record(&mut visitor);
+        let key = match visitor.0 {
+            Some(stage) => format!("{}:{}", span.name(), stage),
+            None => span.name().to_string(),
+        };
+        span.extensions_mut().insert(SpanStart { key, start: Instant::now() });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if let Some(start) = span.extensions().get::<SpanStart>() {
+            self.timings.record(start.key.clone(), start.start.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_spans_are_totalled_per_stage() {
+        let timings = Timings::new();
+        let subscriber = tracing_subscriber::registry().with(timings.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            for stage in ["items", "usr", "usr"] {
+                let _stage = tracing::info_span!("stage", stage).entered();
+                let _parse = tracing::info_span!("parse").entered();
+            }
+        });
+
+        let entries: HashMap<String, SpanTiming> = timings.snapshot().into_iter().collect();
+        assert_eq!(entries["stage:usr"].count, 2);
+        assert_eq!(entries["stage:items"].count, 1);
+        assert_eq!(entries["parse"].count, 3);
+        assert!(entries["stage:usr"].total >= entries["stage:usr"].max);
+        assert!(timings.report().lines().next().unwrap().starts_with("Span"));
+    }
+}
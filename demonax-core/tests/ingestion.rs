@@ -0,0 +1,1989 @@
+//! Golden ingestion test: synthesize a small game directory, run every
+//! ingestion stage into an in-memory database and check the stored rows.
+
+use demonax_core::database::Database;
+use demonax_core::export::export_hunting_spots;
+use demonax_core::file_utils::find_files_with_extension;
+use demonax_core::geo::{default_regions, default_towns, Position};
+use demonax_core::hunting::HuntingOverlay;
+use demonax_core::processors::ExcludeConfig;
+use demonax_core::parsers::{
+    parse_evt_file, parse_evt_str, parse_houses_str, parse_magic_cc_str, parse_map_sector_file, parse_npc_file, parse_npc_spell_teaching_str,
+    parse_monster_db_str, parse_npc_str, parse_objects_srv, parse_objects_srv_str, parse_usr_str,
+};
+use demonax_core::testdata::{evt_file, npc_file, sec_chest_line, ItemSpec, MonSpec, SyntheticGame, UsrSpec};
+
+fn build_game(root: &std::path::Path) -> SyntheticGame {
+    let game = SyntheticGame::create(root).unwrap();
+
+    let mut gold = ItemSpec::new(3031, "a gold coin");
+    gold.flags.push("Cumulative".to_string());
+    gold.attributes.push(("Weight".to_string(), "10".to_string()));
+    game.write_objects_srv(&[
+        gold,
+        ItemSpec::new(3354, "a brass helmet"),
+        ItemSpec::new(3361, "a leather armor"),
+        ItemSpec { type_id: 4526, name: "grass".to_string(), flags: vec!["Bank".to_string()], attributes: vec![] },
+    ])
+    .unwrap();
+
+    let mut rat = MonSpec::new("rat", "Rat", 21);
+    rat.hp = 20;
+    rat.experience = 5;
+    rat.loot = vec![(3031, 4, 999)];
+    game.add_mon(&rat).unwrap();
+
+    let mut dragon = MonSpec::new("dragon", "Dragon", 34);
+    dragon.hp = 1000;
+    dragon.experience = 700;
+    dragon.flags = vec!["KickBoxes".to_string(), "SeeInvisible".to_string()];
+    dragon.spells = vec![
+        "Angle (0, 7, 4) -> Damage (4, 200, 30) : 9".to_string(),
+        "Actor (13) -> Healing (40, 20) : 10".to_string(),
+    ];
+    dragon.loot = vec![(3031, 100, 999), (3361, 1, 200)];
+    game.add_mon(&dragon).unwrap();
+
+    // Excluded creature must not be ingested
+    game.add_mon(&MonSpec::new("human", "Human", 1)).unwrap();
+
+    game.add_npc("baxter", &npc_file("Baxter", &[(3354, 150)], &[(3361, 25)])).unwrap();
+    game.add_evt("orcraid", &evt_file(86400, "Orcs are attacking!", &[(5, 3, 5), (8, 1, 1)])).unwrap();
+    game.add_sec((1000, 1000, 7), &[sec_chest_line(12, 5, 100, &[3031, 3354])]).unwrap();
+
+    let mut alice = UsrSpec::new(1001, "Alice");
+    alice.level = 20;
+    alice.experience = 98800;
+    alice.quest_values = vec![(100, 1), (101, 1)];
+    alice.bestiary = vec![(21, 30)];
+    alice.inventory = vec![(1, "3354".to_string()), (3, "2854 Content={3031 Amount=40}".to_string())];
+    game.add_usr(&alice).unwrap();
+    game.add_usr(&UsrSpec::new(1002, "Bob")).unwrap();
+
+    game
+}
+
+fn ingest_all(game: &SyntheticGame, db: &Database) {
+    db.process_mon_files(&game.root, &ExcludeConfig::default().creatures, 2).unwrap();
+
+    let items = parse_objects_srv(&game.root.join("dat/objects.srv")).unwrap();
+    db.insert_or_update_items(&items).unwrap();
+
+    let prices: Vec<_> = find_files_with_extension(&game.root.join("npc"), "npc")
+        .unwrap()
+        .iter()
+        .flat_map(|path| parse_npc_file(path).unwrap())
+        .collect();
+    db.clear_and_insert_item_prices(&prices).unwrap();
+
+    let chests: Vec<_> = find_files_with_extension(&game.root.join("map"), "sec")
+        .unwrap()
+        .iter()
+        .flat_map(|path| parse_map_sector_file(path).unwrap())
+        .collect();
+    db.process_quest_chests(&chests, None, &default_regions(), 2).unwrap();
+    db.update_items_with_quest_rewards(&[], 2).unwrap();
+
+    let raids: Vec<_> = find_files_with_extension(&game.root.join("mon"), "evt")
+        .unwrap()
+        .iter()
+        .map(|path| parse_evt_file(path).unwrap())
+        .collect();
+    db.insert_or_update_raids(&raids).unwrap();
+
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+}
+
+fn count(db: &Database, sql: &str) -> i64 {
+    db.connection().unwrap().query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn test_full_ingestion_row_counts() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+
+    ingest_all(&game, &db);
+
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM creatures"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM creature_loot"), 3);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM creature_flags"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM creature_spells"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM item_loot_sources"), 3);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM items"), 3);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM item_prices"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM quests"), 1);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM raids"), 1);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM players"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_quests"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_bestiary"), 1);
+}
+
+#[test]
+fn test_full_ingestion_key_fields() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+
+    ingest_all(&game, &db);
+    let conn = db.connection().unwrap();
+
+    let (hp, experience): (i32, i32) = conn
+        .query_row("SELECT hp, experience FROM creatures WHERE short_name = 'dragon'", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!((hp, experience), (1000, 700));
+
+    let spell_name: String = conn
+        .query_row("SELECT spell_name FROM creature_spells WHERE spell_order = 0 AND priority = 9", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(spell_name, "Fire Wave");
+
+    // Fire Wave 170-230 at 1 in 9; the healing spell doesn't count
+    let spell_damage: f64 = conn
+        .query_row("SELECT spell_damage_per_turn FROM creatures WHERE short_name = 'dragon'", [], |row| row.get(0))
+        .unwrap();
+    assert!((spell_damage - 200.0 / 9.0).abs() < 1e-9);
+
+    let (mode, price): (String, i32) = conn
+        .query_row("SELECT mode, price FROM item_prices WHERE item_id = 3354", [], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap();
+    assert_eq!((mode.as_str(), price), ("sell", 150));
+
+    let rewarded_from: String = conn
+        .query_row("SELECT rewarded_from FROM items WHERE type_id = 3354", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(rewarded_from, "Quest 100");
+
+    let chest_location: String = conn
+        .query_row("SELECT chest_location FROM quests WHERE id = 100", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(chest_location, "32012,32005,7 (1000-1000-7)");
+
+    let (level, experience, equipment): (i32, i64, String) = conn
+        .query_row(
+            "SELECT level, experience, equipment_json FROM daily_snapshots WHERE player_id = 1001",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((level, experience), (20, 98800));
+    assert_eq!(equipment, r#"{"version":2,"slots":[{"type_id":3354},null,{"type_id":2854},null,null,null,null,null,null,null]}"#);
+}
+
+#[test]
+fn test_usr_reingestion_is_idempotent() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+
+    assert_eq!(db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap(), 2);
+    assert_eq!(db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap(), 0);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM players"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots"), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_quests"), 2);
+}
+
+#[test]
+fn test_spell_teachers_use_spell_names() {
+    let magic_cc = r#"static void InitSpells(void) {
+    Spell = CreateSpell(1, "exura");
+    Spell->Comment = "Light Healing";
+    Spell->Level = 9;
+    Spell->Mana = 25;
+"#;
+    let npc = r#"Name = "Elane"
+Paladin,"light","healing" -> Type=1, Price=170, "Do you want to learn the spell 'Light Healing'?", Topic=3
+Paladin,"antidote" -> Type=2, Price=150, "Do you want to learn the spell 'Antidote'?", Topic=3
+"#;
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_spells(&parse_magic_cc_str(magic_cc).unwrap()).unwrap();
+    db.clear_and_insert_spell_teachers(&parse_npc_spell_teaching_str(npc).unwrap()).unwrap();
+
+    let conn = db.connection().unwrap();
+    let mut stmt = conn
+        .prepare("SELECT spell_name, spell_words, level_required FROM spell_teachers ORDER BY spell_id")
+        .unwrap();
+    let rows: Vec<(String, Option<String>, Option<i32>)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(rows[0], ("Light Healing".to_string(), Some("exura".to_string()), Some(9)));
+    // Spells missing from magic.cc keep a placeholder name
+    assert_eq!(rows[1], ("Spell 2".to_string(), None, None));
+}
+
+#[test]
+fn test_spell_flags_are_decoded() {
+    let magic_cc = r#"static void InitSpells(void) {
+    Spell = CreateSpell(8, "adori");
+    Spell->Comment = "Light Magic Missile";
+    Spell->Flags = 9;
+    Spell = CreateSpell(6, "utani", "hur");
+    Spell->Comment = "Haste";
+    Spell->Flags = 6;
+"#;
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_spells(&parse_magic_cc_str(magic_cc).unwrap()).unwrap();
+
+    let conn = db.connection().unwrap();
+    let mut stmt = conn
+        .prepare("SELECT aggressive, premium, full_multiplier FROM spells ORDER BY id")
+        .unwrap();
+    let rows: Vec<(bool, bool, bool)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    // Bit 0x04 has no column
+    assert_eq!(rows, vec![(false, true, false), (true, false, true)]);
+}
+
+#[test]
+fn test_items_by_category() {
+    use demonax_core::models::{ItemCategory, ItemFlags};
+    use demonax_core::testdata::objects_srv;
+
+    let item = |type_id: i32, name: &str, flags: &[&str]| ItemSpec {
+        type_id,
+        name: name.to_string(),
+        flags: flags.iter().map(|flag| flag.to_string()).collect(),
+        attributes: vec![],
+    };
+    let text = objects_srv(&[
+        item(2854, "a backpack", &["Container", "Take"]),
+        item(3264, "a sword", &["Take", "Weapon"]),
+        item(3277, "a spear", &["Take", "Cumulative", "Throw"]),
+        item(3155, "a sudden death rune", &["Rune", "Take"]),
+        item(3031, "a gold coin", &["Cumulative", "Take", "Unmove"]),
+    ]);
+    let items = parse_objects_srv_str(&text).unwrap();
+    assert_eq!(items[2].flag_set(), ItemFlags::TAKE | ItemFlags::CUMULATIVE | ItemFlags::THROW);
+    assert_eq!(items[4].flag_set().names(), ["Take", "Cumulative"]);
+
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_items(&items).unwrap();
+    let weapons: Vec<i32> = db.list_items_by_category(ItemCategory::Weapon).unwrap().iter().map(|i| i.type_id).collect();
+    assert_eq!(weapons, [3264, 3277]);
+    assert_eq!(db.list_items_by_category(ItemCategory::Rune).unwrap()[0].name, "Sudden Death Rune");
+    assert_eq!(db.list_items_by_category(ItemCategory::Other).unwrap().len(), 1);
+    assert!(db.list_items_by_category(ItemCategory::Food).unwrap().is_empty());
+    assert_eq!("liquid_container".parse::<ItemCategory>().unwrap(), ItemCategory::LiquidContainer);
+    assert!("potion".parse::<ItemCategory>().is_err());
+}
+
+#[test]
+fn test_item_catalog_container_capacities() {
+    use demonax_core::error::DemonaxError;
+    use demonax_core::inventory::{InventoryItem, InventorySection};
+    use demonax_core::testdata::objects_srv;
+
+    let mut bag = ItemSpec::new(2853, "a bag");
+    bag.flags.push("Container".to_string());
+    bag.attributes.push(("Capacity".to_string(), "8".to_string()));
+    let text = objects_srv(&[bag, ItemSpec::new(3031, "a gold coin")]);
+
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_items(&parse_objects_srv_str(&text).unwrap()).unwrap();
+    let catalog = db.get_item_catalog().unwrap();
+    assert_eq!(catalog.capacity(2853), Some(8));
+    assert_eq!(catalog.capacity(3031), None);
+    assert_eq!(catalog.value(3031), Some(1));
+
+    let mut inventory = InventorySection::new();
+    let coins = vec![InventoryItem::with_amount(3031, 100); 9];
+    let err = inventory.set_slot(10, InventoryItem::container(2853, coins), &catalog).unwrap_err();
+    assert!(matches!(err, DemonaxError::ContainerOverflow { capacity: 8, count: 9, .. }));
+}
+
+#[test]
+fn test_present_over_carry_capacity() {
+    use demonax_core::catalog::ItemCatalog;
+    use demonax_core::present::{apply_present_to_file, GiftResult, PresentConfig};
+    use demonax_core::testdata::objects_srv;
+
+    let weighted = |type_id: i32, name: &str, weight: i32| {
+        let mut item = ItemSpec::new(type_id, name);
+        item.attributes.push(("Weight".to_string(), weight.to_string()));
+        item
+    };
+    let mut backpack = weighted(2854, "a backpack", 1800);
+    backpack.attributes.push(("Capacity".to_string(), "20".to_string()));
+    let text = objects_srv(&[backpack, weighted(3456, "a pick", 7000)]);
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_items(&parse_objects_srv_str(&text).unwrap()).unwrap();
+    let catalog = db.get_item_catalog().unwrap();
+    assert_eq!(catalog.weight(3456), 7000);
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let mut rookie = UsrSpec::new(1, "Rookie");
+    rookie.level = 2;
+    // 410 oz at level 2: the backpack and five picks weigh 368 oz
+    rookie.inventory = vec![(3, "2854 Content={3456, 3456, 3456, 3456, 3456}".to_string())];
+    let path = game.add_usr(&rookie).unwrap();
+
+    // Another 88 oz of present is too much
+    let config: PresentConfig = "[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n".parse().unwrap();
+    let skipped = apply_present_to_file(&path, &config, 10, &catalog, true, false);
+    assert!(matches!(skipped, GiftResult::OverCapacity { weight: 45_600, capacity: 41_000, gifted: false, .. }));
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("10 Content="));
+
+    let gifted = apply_present_to_file(&path, &config, 10, &catalog, false, false);
+    assert!(gifted.is_gifted() && matches!(gifted, GiftResult::OverCapacity { .. }));
+    // Without weights nothing is checked
+    let unchecked = apply_present_to_file(&path, &config, 11, &ItemCatalog::new(), true, true);
+    assert!(matches!(unchecked, GiftResult::Gifted { .. }));
+}
+
+#[test]
+fn test_gift_queue_applies_gifts_with_backups_and_ledger() {
+    use demonax_core::catalog::ItemCatalog;
+    use demonax_core::gift_queue::flush_gift_queue;
+    use demonax_core::present::PresentConfig;
+    use demonax_core::processors::Reporter;
+    use demonax_core::sandbox::PlayerFilter;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let alice = game.add_usr(&UsrSpec::new(1, "Alice")).unwrap();
+    let bob = game.add_usr(&UsrSpec::new(2, "Bob")).unwrap();
+    let original = std::fs::read(&alice).unwrap();
+
+    let db = Database::in_memory().unwrap();
+    let config: PresentConfig = "[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n".parse().unwrap();
+    db.queue_gift(Some("alice"), &config, 10, false).unwrap();
+    db.queue_gift(Some("Carol"), &config, 10, false).unwrap();
+    db.queue_gift(None, &config, 10, false).unwrap();
+    assert_eq!(db.get_pending_gifts().unwrap().len(), 3);
+
+    let backups = temp_dir.path().join("backups");
+    let summary = flush_gift_queue(&db, &game.usr_dir(), &ItemCatalog::new(), &backups, &PlayerFilter::all(), &Reporter::new(2))
+        .unwrap();
+    // Alice's slot is taken by her own gift when the gift for everyone comes
+    assert_eq!((summary.gifted, summary.skipped, summary.errors), (2, 1, 1));
+    assert!(std::fs::read_to_string(&bob).unwrap().contains("10 Content="));
+    assert!(db.get_pending_gifts().unwrap().is_empty());
+
+    let backup_run = std::fs::read_dir(&backups).unwrap().next().unwrap().unwrap().path();
+    assert_eq!(std::fs::read(backup_run.join(alice.strip_prefix(game.usr_dir()).unwrap())).unwrap(), original);
+
+    let ledger: Vec<(Option<String>, i64, String)> = {
+        let conn = db.connection().unwrap();
+        let mut stmt = conn.prepare("SELECT player_name, errors, results_json FROM pending_gifts ORDER BY id").unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap().collect::<Result<_, _>>().unwrap()
+    };
+    assert_eq!(ledger[1].1, 1);
+    assert!(ledger[1].2.contains("no .usr file with that name"));
+    assert!(ledger[2].2.contains(r#"{"player_name":"Alice","outcome":"skipped: slot occupied"}"#));
+}
+
+#[test]
+fn test_winners_csv_gives_each_row_its_present() {
+    use demonax_core::catalog::ItemCatalog;
+    use demonax_core::present::{GiftResult, apply_winners, find_usr_files_by_player, read_winners_csv};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let alice = game.add_usr(&UsrSpec::new(1, "Alice")).unwrap();
+    let bob = game.add_usr(&UsrSpec::new(2, "Bob")).unwrap();
+
+    let prizes = temp_dir.path().join("prizes");
+    std::fs::create_dir_all(&prizes).unwrap();
+    std::fs::write(prizes.join("first.toml"), "[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n").unwrap();
+    std::fs::write(prizes.join("second.toml"), "[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3031\namount = 50\n").unwrap();
+    let csv_path = temp_dir.path().join("winners.csv");
+    std::fs::write(&csv_path, "player_name,present_config\nalice,prizes/first.toml\nBob,prizes/second.toml\nCarol,prizes/first.toml\n")
+        .unwrap();
+
+    let winners = read_winners_csv(&csv_path).unwrap();
+    assert_eq!(winners.iter().map(|w| w.line).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+    let files = find_usr_files_by_player(&game.usr_dir()).unwrap();
+    let results = apply_winners(&winners, &files, 10, &ItemCatalog::new(), false, false);
+    assert!(matches!(&results[0].0, GiftResult::Gifted { player_name, .. } if player_name == "Alice"));
+    assert!(matches!(&results[1].0, GiftResult::Gifted { player_name, .. } if player_name == "Bob"));
+    assert_eq!(results[2].0.outcome(), "error: no .usr file with that name");
+    assert!(std::fs::read_to_string(&alice).unwrap().contains("3456"));
+    assert!(std::fs::read_to_string(&bob).unwrap().contains("3031"));
+
+    // A row with a missing config stops the whole run, naming its line
+    std::fs::write(&csv_path, "player_name,present_config\nAlice,prizes/first.toml\nBob,prizes/missing.toml\n").unwrap();
+    let err = read_winners_csv(&csv_path).unwrap_err();
+    assert!(err.to_string().contains("line 3"), "{}", err);
+}
+
+#[test]
+fn test_cloned_test_player_gets_gifts_alone() {
+    use demonax_core::catalog::ItemCatalog;
+    use demonax_core::gift_queue::flush_gift_queue;
+    use demonax_core::present::PresentConfig;
+    use demonax_core::processors::Reporter;
+    use demonax_core::sandbox::{clone_player, PlayerFilter};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let alice = game.add_usr(&UsrSpec::new(1, "Alice")).unwrap();
+    game.add_usr(&UsrSpec::new(42, "Bob")).unwrap();
+
+    let clone = clone_player(&game.usr_dir(), "alice", "Test Alice", None).unwrap();
+    assert_eq!(clone, game.usr_dir().join("43").join("43.usr"));
+    let text = std::fs::read_to_string(&clone).unwrap();
+    assert!(text.starts_with("ID          = 43\nName        = \"Test Alice\"\n"), "{}", text);
+    assert_eq!(text.lines().skip(2).collect::<Vec<_>>(), std::fs::read_to_string(&alice).unwrap().lines().skip(2).collect::<Vec<_>>());
+    assert!(clone_player(&game.usr_dir(), "Alice", "test alice", None).is_err());
+    assert!(clone_player(&game.usr_dir(), "Alice", "Other", Some(42)).is_err());
+
+    let db = Database::in_memory().unwrap();
+    let config: PresentConfig = "[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n".parse().unwrap();
+    db.queue_gift(None, &config, 10, false).unwrap();
+    db.queue_gift(Some("Bob"), &config, 10, false).unwrap();
+
+    let only = PlayerFilter::only(&["Test Alice"]);
+    let backups = temp_dir.path().join("backups");
+    let summary = flush_gift_queue(&db, &game.usr_dir(), &ItemCatalog::new(), &backups, &only, &Reporter::new(2)).unwrap();
+    assert_eq!((summary.total_processed, summary.gifted), (1, 1));
+    assert!(std::fs::read_to_string(&clone).unwrap().contains("10 Content="));
+    assert!(!std::fs::read_to_string(&alice).unwrap().contains("10 Content="));
+    // Both gifts wait for the real flush
+    assert_eq!(db.get_pending_gifts().unwrap().len(), 2);
+}
+
+#[test]
+fn test_accounts_group_characters_for_reports_and_presents() {
+    use demonax_core::catalog::ItemCatalog;
+    use demonax_core::present::{GiftResult, PresentConfig, apply_present_per_account};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let character = |id: i32, name: &str, account: Option<i32>, experience: i64| {
+        let mut spec = UsrSpec::new(id, name);
+        spec.account = account;
+        spec.experience = experience;
+        spec
+    };
+    let mut alice = character(1, "Alice", Some(100), 90_000);
+    alice.inventory = vec![(10, "2853".to_string())];
+    game.add_usr(&alice).unwrap();
+    game.add_usr(&character(2, "Bob", Some(100), 4_200)).unwrap();
+    game.add_usr(&character(3, "Carol", Some(200), 50_000)).unwrap();
+    game.add_usr(&character(4, "Dave", None, 1_000)).unwrap();
+
+    let db = Database::in_memory().unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM accounts"), 2);
+    assert_eq!(count(&db, "SELECT account_id FROM players WHERE name = 'Bob'"), 100);
+
+    let accounts = db.get_account_summaries(None, 30, None).unwrap();
+    let summary: Vec<_> = accounts.iter().map(|a| (a.account_id, a.characters, a.best_character.as_str(), a.total_experience)).collect();
+    assert_eq!(summary, vec![(100, 2, "Alice", 94_200), (200, 1, "Carol", 50_000)]);
+    assert_eq!(accounts[0].active_characters, 2);
+
+    // Alice's slot is taken, so her account's present goes to Bob
+    let config: PresentConfig = "[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n".parse().unwrap();
+    let files = find_files_with_extension(&game.usr_dir(), "usr").unwrap();
+    let results = apply_present_per_account(&files, &config, 10, &ItemCatalog::new(), false, false);
+    let mut gifted: Vec<&str> =
+        results.iter().filter(|(r, _)| r.is_gifted()).map(|(r, _)| r.player_name()).collect();
+    gifted.sort();
+    assert_eq!(gifted, vec!["Bob", "Carol", "Dave"]);
+    assert!(results.iter().any(|(r, _)| matches!(r, GiftResult::SlotOccupied { player_name, .. } if player_name == "Alice")));
+    assert_eq!(results.len(), 4);
+}
+
+#[test]
+fn test_command_processors() {
+    use demonax_core::catalog::ItemCatalog;
+    use demonax_core::processors::{
+        combat_calc, give_presents, level_calc, CombatPlayer, GiftOptions, LevelCalcRequest, PresentSource, Reporter,
+    };
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db_path = temp_dir.path().join("demonax.sqlite");
+    let db = Database::new(&db_path).unwrap();
+    ingest_all(&game, &db);
+
+    let player = CombatPlayer { level: 20, vocation: "Knight".to_string(), armor: 10, shield_defense: 20, ..Default::default() };
+    let report = combat_calc(&db, "Dragon", &player).unwrap();
+    assert_eq!((report.player.as_str(), report.defender.shielding, report.blessings), ("Player", 10, 0));
+    assert!(report.to_string().starts_with("Dragon (attack "));
+    assert!(combat_calc(&db, "Unicorn", &player).is_err());
+
+    let request = LevelCalcRequest { level: Some(10), days: 30, ..Default::default() };
+    let report = level_calc(&db_path, &request, &Default::default()).unwrap();
+    assert_eq!((report.name.as_str(), report.plan.target_level, report.days), ("Level 10", 11, None));
+    assert!(level_calc(&db_path, &LevelCalcRequest::default(), &Default::default()).is_err());
+
+    // A dry run reports every player and writes nothing
+    let present = temp_dir.path().join("present.toml");
+    std::fs::write(&present, "[container]\ntype_id = 2854\n\n[[items]]\ntype_id = 3456\n").unwrap();
+    let files = find_files_with_extension(&game.usr_dir(), "usr").unwrap();
+    let before: Vec<_> = files.iter().map(|path| std::fs::read(path).unwrap()).collect();
+    let options = GiftOptions { target_slot: 10, dry_run: true, ..Default::default() };
+    let run = give_presents(&game.usr_dir(), PresentSource::Config(&present), &ItemCatalog::new(), &options, &Reporter::new(2))
+        .unwrap();
+    assert_eq!((run.summary.total_processed, run.results.len()), (2, 2));
+    let after: Vec<_> = files.iter().map(|path| std::fs::read(path).unwrap()).collect();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn test_premium_days_filter_accounts_and_rune_offers() {
+    use demonax_core::models::{AccountType, RuneSeller};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let mut alice = UsrSpec::new(1, "Alice");
+    (alice.account, alice.premium_days, alice.profession) = (Some(100), Some(12), Some(3));
+    let mut bob = UsrSpec::new(2, "Bob");
+    (bob.account, bob.premium_days, bob.profession) = (Some(200), Some(0), Some(13));
+    game.add_usr(&alice).unwrap();
+    game.add_usr(&bob).unwrap();
+
+    let db = Database::in_memory().unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+    assert_eq!(count(&db, "SELECT premium_days FROM daily_snapshots WHERE player_id = 1"), 12);
+
+    let premium = db.get_account_summaries(None, 30, Some(AccountType::Premium)).unwrap();
+    assert_eq!(premium.iter().map(|a| (a.account_id, a.premium)).collect::<Vec<_>>(), vec![(100, Some(true))]);
+    let free = db.get_account_summaries(None, 30, Some(AccountType::Free)).unwrap();
+    assert_eq!(free.iter().map(|a| a.account_id).collect::<Vec<_>>(), vec![200]);
+
+    let seller = |npc_name: &str, item_id: i32, vocation: Option<&str>, account_type: Option<&str>| RuneSeller {
+        npc_name: npc_name.to_string(),
+        item_id,
+        spell_id: None,
+        vocation: vocation.map(str::to_string),
+        price: 100,
+        charges: None,
+        account_type: account_type.map(str::to_string),
+        item_category: "rune".to_string(),
+    };
+    db.clear_and_insert_rune_sellers(&[
+        seller("Asima", 3155, Some("Sorcerer"), Some("Premium")),
+        seller("Xodet", 3160, Some("Sorcerer"), Some("Free")),
+        seller("Rachel", 3161, Some("Druid"), None),
+    ])
+    .unwrap();
+    let offers = |player_id: i32| count(&db, &format!("SELECT COUNT(*) FROM player_rune_offers WHERE player_id = {}", player_id));
+    // Both are sorcerers (Bob promoted), but only Alice has premium days
+    assert_eq!((offers(1), offers(2)), (2, 1));
+}
+
+#[test]
+fn test_skill_tuples_keep_tries() {
+    let text = "ID          = 7\nName        = \"Tries\"\n\
+                Skill       = (0, 20, 0, 1, 0, 0, 0, 0, 0, 0, 1, 98800, 0, 107800, 100)\n\
+                Skill       = (8, 42, 0, 10, 3, 0, 0, 0, 0, 0, 0, 1500, 1100, 2000, 50)\n\
+                Skill       = (9, 12)\n";
+    let parsed = parse_usr_str(text, "7.usr").unwrap();
+    assert_eq!(parsed.skill_entries.len(), 2);
+    let sword = parsed.skill_entries[1];
+    assert_eq!((sword.skill_id, sword.value, sword.min, sword.modifier), (8, 42, 10, 3));
+    assert_eq!((sword.tries, sword.next_level), (1500, 2000));
+    assert_eq!((parsed.skills.level, parsed.skills.experience, parsed.skills.sword_fighting), (20, 98800, 42));
+    assert_eq!(parsed.skills.club_fighting, -1);
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let mut alice = UsrSpec::new(1, "Alice");
+    alice.skill_tries[2] = 1234;
+    game.add_usr(&alice).unwrap();
+    let db = Database::in_memory().unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_skills"), 9);
+    assert_eq!(count(&db, "SELECT tries FROM daily_skills WHERE skill_id = 8"), 1234);
+}
+
+#[test]
+fn test_learned_spells_find_casters_and_missing_spells() {
+    use demonax_core::models::{Spell, SpellTeacher};
+
+    let spell = |spell_id: i32, name: &str, words: &str, level: i32| Spell {
+        spell_id,
+        name: name.to_string(),
+        words: words.to_string(),
+        level,
+        magic_level: None,
+        mana: 100,
+        soul_points: 0,
+        flags: 0,
+        is_rune: false,
+        rune_type_id: None,
+        charges: None,
+        spell_type: "attack".to_string(),
+        premium: false,
+        damage_element: None,
+    };
+    let teacher = |spell_id: i32| SpellTeacher {
+        npc_name: "Muriel".to_string(),
+        spell_id,
+        vocation: "Sorcerer".to_string(),
+        teaching_price: 100,
+        level_required: None,
+        unclassified_line: None,
+    };
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let mut alice = UsrSpec::new(1, "Alice");
+    (alice.level, alice.profession, alice.spells) = (60, Some(13), vec![1, 24]);
+    let mut bob = UsrSpec::new(2, "Bob");
+    (bob.level, bob.profession, bob.spells) = (20, Some(3), vec![1]);
+    game.add_usr(&alice).unwrap();
+    game.add_usr(&bob).unwrap();
+    game.add_usr(&UsrSpec::new(3, "Carol")).unwrap();
+
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_spells(&[
+        spell(1, "Light", "utevo lux", 8),
+        spell(23, "Great Fireball", "exevo gran flam", 15),
+        spell(24, "Ultimate Explosion", "exevo gran mas vis", 60),
+    ])
+    .unwrap();
+    db.clear_and_insert_spell_teachers(&[teacher(1), teacher(23), teacher(24)]).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM player_spells"), 3);
+
+    let (name, casters) = db.get_spell_casters("EXEVO GRAN MAS VIS").unwrap().unwrap();
+    assert_eq!(name, "Ultimate Explosion");
+    assert_eq!(casters.iter().map(|c| c.player_name.as_str()).collect::<Vec<_>>(), vec!["Alice"]);
+    assert_eq!(casters[0].vocation, "Master Sorcerer");
+    assert_eq!(db.get_spell_casters("light").unwrap().unwrap().1.len(), 2);
+    assert!(db.get_spell_casters("exori").unwrap().is_none());
+
+    // Bob is too low for Ultimate Explosion; Carol has no spell list
+    assert_eq!(db.get_player_card(1).unwrap().unwrap().missing_spells, vec!["exevo gran flam"]);
+    assert_eq!(db.get_player_card(2).unwrap().unwrap().missing_spells, vec!["exevo gran flam"]);
+    assert!(db.get_player_card(3).unwrap().unwrap().missing_spells.is_empty());
+}
+
+#[test]
+fn test_blessings_are_kept_per_snapshot() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let mut alice = UsrSpec::new(1, "Alice");
+    (alice.level, alice.experience, alice.profession, alice.blessings) = (20, 98800, Some(11), Some(2));
+    game.add_usr(&alice).unwrap();
+    let db = Database::in_memory().unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-06", 2).unwrap();
+
+    alice.blessings = Some(5);
+    game.add_usr(&alice).unwrap();
+    game.add_usr(&UsrSpec::new(2, "Bob")).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+
+    assert_eq!(count(&db, "SELECT blessings FROM daily_snapshots WHERE player_id = 1 AND snapshot_date = '2026-01-06'"), 2);
+    let card = db.get_player_card(1).unwrap().unwrap();
+    assert_eq!((card.blessings, card.experience, card.vocation.as_str()), (Some(5), 98800, "Elite Knight"));
+    assert_eq!(db.get_player_card(2).unwrap().unwrap().blessings, None);
+}
+
+#[test]
+fn test_death_loss_from_latest_snapshot() {
+    use demonax_core::game_math::{death_loss, is_promoted};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let mut alice = UsrSpec::new(1, "Alice");
+    (alice.level, alice.experience, alice.profession, alice.blessings) = (20, 98800, Some(1), Some(0));
+    alice.skill_tries[2] = 5000;
+    alice.inventory = vec![(3, "2854".to_string()), (4, "3357".to_string())];
+    game.add_usr(&alice).unwrap();
+    let db = Database::in_memory().unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+
+    let player_id = db.find_player_id("alice").unwrap().unwrap();
+    assert_eq!(db.find_player_id("1").unwrap(), Some(1));
+    assert_eq!(db.find_player_id("Nobody").unwrap(), None);
+    let card = db.get_player_card(player_id).unwrap().unwrap();
+    let (skills, equipment) = db.get_latest_skills_and_equipment(player_id).unwrap().unwrap();
+    let loss = death_loss(card.level, card.experience, is_promoted(&card.vocation), 0, &skills, &equipment);
+
+    assert_eq!((loss.experience_lost, loss.level_after), (9880, 19));
+    let sword = loss.skills.iter().find(|skill| skill.skill_id == 8).unwrap();
+    assert_eq!(sword.tries_lost, 500);
+    assert!((loss.expected_items_lost - 0.1).abs() < 1e-9);
+}
+
+#[test]
+fn test_content_gaps_are_recorded_per_run() {
+    use demonax_core::models::{ContentGapFilter, ContentGapKind};
+
+    let magic_cc = r#"static void InitSpells(void) {
+    Spell = CreateSpell(1, "exura");
+    Spell->Comment = "Light Healing";
+    Spell->Level = 9;
+    Spell->Mana = 25;
+    Spell = CreateSpell(2, "exana", "pox");
+    Spell->Comment = "Antidote";
+    Spell->Level = 10;
+    Spell->Mana = 30;
+"#;
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_spells(&parse_magic_cc_str(magic_cc).unwrap()).unwrap();
+    assert_eq!(db.record_content_gaps().unwrap().1, 2);
+
+    let npc = r#"Name = "Elane"
+Paladin,"antidote" -> Type=2, Price=150, "Do you want to learn the spell 'Antidote'?", Topic=3
+"#;
+    db.clear_and_insert_spell_teachers(&parse_npc_spell_teaching_str(npc).unwrap()).unwrap();
+    let (run_id, count) = db.record_content_gaps().unwrap();
+    assert_eq!(count, 1);
+
+    let gaps = db.get_content_gaps(&ContentGapFilter::default()).unwrap();
+    assert_eq!(gaps.len(), 1);
+    assert_eq!((gaps[0].run_id, gaps[0].spell_name.as_str(), gaps[0].runs), (run_id, "Light Healing", 2));
+
+    // Filters apply within the selected run
+    let filter = ContentGapFilter { max_level: Some(9), ..Default::default() };
+    assert_eq!(db.get_content_gaps(&filter).unwrap().len(), 1);
+    let filter = ContentGapFilter { kind: Some(ContentGapKind::UnsoldRune), ..Default::default() };
+    assert!(db.get_content_gaps(&filter).unwrap().is_empty());
+    let filter = ContentGapFilter { name: Some("exana".to_string()), run_id: Some(run_id - 1), ..Default::default() };
+    assert_eq!(db.get_content_gaps(&filter).unwrap()[0].spell_name, "Antidote");
+
+    let runs = db.get_content_gap_runs().unwrap();
+    assert_eq!(runs.iter().map(|r| r.untaught_spells).collect::<Vec<_>>(), vec![2, 1]);
+}
+
+#[test]
+fn test_tagged_content_diff() {
+    use demonax_core::content::{diff_content, ChangeKind};
+
+    let magic_cc = r#"static void InitSpells(void) {
+    Spell = CreateSpell(1, "exura");
+    Spell->Comment = "Light Healing";
+    Spell->Level = 9;
+    Spell->Mana = 25;
+"#;
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_spells(&parse_magic_cc_str(magic_cc).unwrap()).unwrap();
+    let counts = db.tag_content("patch-1.2", false).unwrap();
+    assert!(counts.contains(&("spells", 1)));
+    assert!(db.tag_content("patch-1.2", false).is_err());
+
+    let magic_cc = magic_cc.replace("Mana = 25", "Mana = 20")
+        + "    Spell = CreateSpell(2, \"exana\", \"pox\");\n    Spell->Comment = \"Antidote\";\n    Spell->Level = 10;\n    Spell->Mana = 30;\n";
+    db.insert_or_update_spells(&parse_magic_cc_str(&magic_cc).unwrap()).unwrap();
+    db.tag_content("patch-1.3", false).unwrap();
+
+    let old = db.get_content_rows(Some("patch-1.2")).unwrap();
+    let new = db.get_content_rows(Some("patch-1.3")).unwrap();
+    assert_eq!(new, db.get_content_rows(None).unwrap());
+
+    let changes = diff_content(&old, &new);
+    let summary: Vec<(&str, ChangeKind)> = changes.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+    assert_eq!(summary, vec![("Antidote", ChangeKind::Added), ("Light Healing", ChangeKind::Changed)]);
+    assert_eq!(changes[1].fields.len(), 1);
+    assert_eq!(changes[1].fields[0].field, "mana");
+    assert!(db.get_content_rows(Some("patch-1.4")).is_err());
+}
+
+#[test]
+fn test_best_prices_with_npc_towns() {
+    let houses = "ID = 101\nName = \"Market Street 1\"\nArea = 1\nFields = {[32368,32240,7],[32369,32240,7]}\n\nID = 201\nName = \"Harbour Lane 1\"\nArea = 2\nFields = {[33210,31810,7]}\n";
+    let areas = "Area = (1,\"Thais\",10,1)\nArea = (2,\"Carlin\",10,1)\n";
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_houses(&parse_houses_str(houses, None, Some(areas)).unwrap()).unwrap();
+
+    let frodo = "Name = \"Frodo\"\nHome = [32380,32220,7]\n\"torch\" -> Type=2920, Amount=1, Price=2, Topic=1\n\"sell\",\"sword\" -> Type=3264, Amount=1, Price=25, Topic=2\nTopic=1,\"yes\" -> DeleteMoney, Create(Type)\nTopic=2,\"yes\" -> Delete(Type), CreateMoney\n";
+    let rachel = "Name = \"Rachel\"\nHome = [33200,31800,7]\n\"buy\",\"torch\" -> Type=2920, Amount=2, Price=3\n\"sell\",\"sword\" -> Type=3264, Amount=1, Price=30\n";
+    let hermit = "Name = \"Hermit\"\nHome = [32000,32000,7]\n\"sell\",\"sword\" -> Type=3264, Amount=1, Price=40\n";
+    let prices: Vec<_> = [(frodo, "frodo"), (rachel, "rachel"), (hermit, "hermit")]
+        .iter()
+        .flat_map(|(text, stem)| parse_npc_str(text, stem).unwrap())
+        .collect();
+    db.clear_and_insert_item_prices(&prices).unwrap();
+
+    // Rachel's torches are cheaper per item
+    let buy = db.best_buy_price(2920).unwrap().unwrap();
+    assert_eq!((buy.npc_name.as_str(), buy.town.as_deref(), buy.price, buy.amount), ("Rachel", Some("Carlin"), 3, 2));
+
+    // The hermit pays most, but lives far from any house
+    let sell = db.best_sell_price(3264).unwrap().unwrap();
+    assert_eq!((sell.npc_name.as_str(), sell.town, sell.price), ("Hermit", None, 40));
+    assert!(db.best_sell_price(2920).unwrap().is_none());
+    assert_eq!(db.get_item_values().unwrap()[&3264], 40);
+
+    // From the Thais market, Frodo is the nearest place to buy torches
+    let market = Position::new(32369, 32241, 7);
+    let npcs: Vec<(String, i32)> = db.nearest_npcs(market, 2).unwrap().into_iter().map(|npc| (npc.npc_name, npc.distance)).collect();
+    assert_eq!(npcs, vec![("Frodo".to_string(), 21), ("Hermit".to_string(), 369)]);
+    let sellers = db.nearest_item_sellers(2920, market, 5).unwrap();
+    let sellers: Vec<&str> = sellers.iter().map(|offer| offer.npc.npc_name.as_str()).collect();
+    assert_eq!(sellers, ["Frodo", "Rachel"]);
+
+    // Assigned towns take over from the nearest house: the hermit lives near the Rookgaard
+    // temple, and Carlin's bounds hold Rachel although Edron's temple is nearer
+    db.insert_or_update_raids(&[parse_evt_str(&evt_file(86400, "Rats!", &[(21, 3, 5)]), "ratraid").unwrap()]).unwrap();
+    let mut towns = default_towns();
+    towns[2].bounds = vec![[33100, 31700], [33300, 31700], [33300, 31900], [33100, 31900]];
+    assert_eq!(db.assign_towns(&towns).unwrap(), 4);
+    assert_eq!(db.best_sell_price(3264).unwrap().unwrap().town.as_deref(), Some("Rookgaard"));
+    assert_eq!(db.best_buy_price(2920).unwrap().unwrap().town.as_deref(), Some("Carlin"));
+    assert_eq!(db.get_raid_list(None, true).unwrap()[0].town.as_deref(), Some("Rookgaard"));
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM towns"), towns.len() as i64);
+}
+
+#[test]
+fn test_validate_new_snapshot_date() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+
+    db.validate_new_snapshot_date("2026-01-07", false).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+
+    assert!(db.validate_new_snapshot_date("2026-01-07", true).is_err());
+    assert!(db.validate_new_snapshot_date("2026-01-06", false).is_err());
+    db.validate_new_snapshot_date("2026-01-06", true).unwrap();
+    db.validate_new_snapshot_date("2026-01-08", false).unwrap();
+}
+
+#[test]
+fn test_snapshot_history_survives_later_runs() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap();
+
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots"), 4);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_quests"), 4);
+}
+
+#[test]
+fn test_run_journal_skips_completed_stages() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+
+    let excluded = ExcludeConfig::default().creatures;
+    let first = db.run_journaled("update-all", "creatures", "", || Ok(db.process_mon_files(&game.root, &excluded, 2)? as usize));
+    assert_eq!(first.unwrap(), Some(2));
+
+    // An interrupted stage is not journaled and runs again on the next attempt
+    let failed = db.run_journaled("update-all", "items", "", || {
+        Err(demonax_core::error::DemonaxError::Parse("interrupted".to_string()))
+    });
+    assert!(failed.is_err());
+
+    let mut ran = Vec::new();
+    for stage in ["creatures", "items"] {
+        if db.run_journaled("update-all", stage, "", || Ok(0)).unwrap().is_some() {
+            ran.push(stage);
+        }
+    }
+    assert_eq!(ran, ["items"]);
+
+    assert_eq!(db.clear_run_journal("update-all").unwrap(), 2);
+    assert_eq!(db.run_journaled("update-all", "creatures", "", || Ok(0)).unwrap(), Some(0));
+}
+
+#[test]
+fn test_file_database_wal_and_read_only() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db_path = temp_dir.path().join("demonax.sqlite");
+
+    let writer = Database::new(&db_path).unwrap();
+    writer.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+    let journal_mode: String = writer
+        .connection()
+        .unwrap()
+        .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(journal_mode, "wal");
+
+    // Readers see committed data while the writer is still open
+    let reader = Database::open_read_only(&db_path).unwrap();
+    assert_eq!(count(&reader, "SELECT COUNT(*) FROM players"), 2);
+    assert!(reader.connection().unwrap().execute("DELETE FROM players", []).is_err());
+
+    assert!(Database::open_read_only(&temp_dir.path().join("missing.sqlite")).is_err());
+}
+
+#[test]
+fn test_hunting_spot_pages() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    // Race 99 has no creature and is left out
+    let monster_db = "21 32100 32220 7 3 4 60\n34 32110 32230 7 2 1 600\n99 32100 32220 7 1 1 60\n21 33000 32000 7 3 4 60\n0\n";
+    db.replace_spawns(&parse_monster_db_str(monster_db).unwrap()).unwrap();
+    db.assign_towns(&default_towns()).unwrap();
+    assert_eq!(db.get_spawn_creatures().unwrap().len(), 3);
+
+    let overlay: HuntingOverlay =
+        "[[spots]]\nname = \"Rookgaard Lair\"\nbounds = [[32000, 32200], [32200, 32200], [32200, 32300], [32000, 32300]]\n"
+            .parse()
+            .unwrap();
+    let output = temp_dir.path().join("site");
+    assert_eq!(export_hunting_spots(&db, &overlay, &output, None).unwrap(), 1);
+
+    let page: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join("hunting/rookgaard-lair.json")).unwrap()).unwrap();
+    assert_eq!(page["town"], "Rookgaard");
+    assert_eq!(page["creatures"][0]["name"], "Rat");
+    assert_eq!(page["creatures"][0]["count"], 4);
+    // 4 rats * 5 exp * 60 per hour + 1 dragon * 700 exp * 6 per hour
+    assert_eq!(page["experience_per_hour"], 5400);
+    assert!(page["recommended_level"].as_i64().unwrap() >= 5);
+}
+
+#[test]
+fn test_player_card_from_latest_snapshot() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let mut alice = UsrSpec::new(1001, "Alice");
+    alice.level = 45;
+    alice.profession = Some(11);
+    alice.skills = [12, 15, 78, 20, 25, 70, 10];
+    game.add_usr(&alice).unwrap();
+    let db = Database::in_memory().unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+
+    let card = db.get_player_card(1001).unwrap().unwrap();
+    assert_eq!((card.level, card.vocation.as_str()), (45, "Elite Knight"));
+    assert_eq!(card.skills[0], ("Sword".to_string(), 78));
+    assert_eq!(card.skills[1], ("Shielding".to_string(), 70));
+    assert!(db.get_player_card(1002).unwrap().is_none());
+
+    // Hidden players are left out of exports and renders
+    assert_eq!(db.get_snapshot_list().unwrap().len(), 1);
+    assert!(db.set_player_hidden(1001, true).unwrap());
+    assert!(!db.set_player_hidden(1002, true).unwrap());
+    assert!(db.is_player_hidden(1001).unwrap());
+    assert!(db.get_snapshot_list().unwrap().is_empty());
+    assert!(db.get_latest_snapshots(None).unwrap().is_empty());
+}
+
+#[test]
+fn test_coverage_report() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let images = temp_dir.path().join("images");
+    std::fs::create_dir_all(&images).unwrap();
+    std::fs::write(images.join("rat.gif"), b"").unwrap();
+
+    let report = db.get_coverage(Some(&images)).unwrap();
+    let find = |subsystem: &str| report.iter().find(|c| c.subsystem == subsystem).unwrap();
+
+    assert_eq!((find("creatures").total, find("creatures").missing), (2, 1));
+    assert_eq!(find("creatures").examples, vec!["Dragon"]);
+    // Gold coins have no NPC price
+    assert_eq!((find("items").total, find("items").missing), (3, 1));
+    assert_eq!(find("quests").examples, vec!["Quest 100"]);
+    // The raid spawns races 5 and 8, which aren't in the creatures table
+    assert_eq!(find("raids").missing, 1);
+    assert_eq!(find("spells").percent_complete, 100.0);
+}
+
+#[test]
+fn test_image_overrides_apply_to_exports_and_coverage() {
+    use demonax_core::processors::ImageConfig;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    // Images are named after the creature until an override is stored
+    let dragon_image = |db: &Database| {
+        db.get_creature_loot_tables().unwrap().into_iter().find(|table| table.short_name == "dragon").unwrap().image
+    };
+    assert_eq!(dragon_image(&db), "dragon");
+    assert_eq!(db.replace_image_overrides(&ImageConfig::default().overrides).unwrap(), 8);
+    assert_eq!(dragon_image(&db), "dragon");
+
+    let overrides = [("dragon".to_string(), "dragonlord".to_string())].into_iter().collect();
+    db.replace_image_overrides(&overrides).unwrap();
+    assert_eq!(dragon_image(&db), "dragonlord");
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM image_overrides"), 1);
+
+    let images = temp_dir.path().join("images");
+    std::fs::create_dir_all(&images).unwrap();
+    std::fs::write(images.join("rat.gif"), b"").unwrap();
+    std::fs::write(images.join("dragon.gif"), b"").unwrap();
+    let report = db.get_coverage(Some(&images)).unwrap();
+    let creatures = report.iter().find(|c| c.subsystem == "creatures").unwrap();
+    assert_eq!(creatures.examples, vec!["Dragon"]);
+
+    std::fs::write(images.join("dragonlord.png"), b"").unwrap();
+    let report = db.get_coverage(Some(&images)).unwrap();
+    assert_eq!(report.iter().find(|c| c.subsystem == "creatures").unwrap().missing, 0);
+}
+
+#[test]
+fn test_bundle_round_trip_keeps_player_data() {
+    use demonax_core::bundle::{read_bundle, write_bundle};
+
+    let magic_cc = r#"static void InitSpells(void) {
+    Spell = CreateSpell(1, "exura");
+    Spell->Comment = "Light Healing";
+    Spell->Level = 9;
+    Spell->Mana = 25;
+"#;
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_spells(&parse_magic_cc_str(magic_cc).unwrap()).unwrap();
+    {
+        let conn = db.connection().unwrap();
+        conn.execute_batch(
+            "INSERT INTO houses (id, name, town, rent, size) VALUES (1, 'Harbour Place 1', 'Carlin', 500, 20);
+             INSERT INTO house_owners (house_id, owner_id) VALUES (1, 42);
+             INSERT INTO towns (name, temple_x, temple_y, temple_z) VALUES ('Carlin', 32360, 31782, 7);
+             INSERT INTO spawns (race, pos_x, pos_y, pos_z, radius, amount, regen_seconds, town)
+                 VALUES (21, 32350, 31790, 7, 2, 3, 60, 'Carlin');
+             INSERT INTO creature_notes (short_name, note) VALUES ('rat', 'Weak');
+             INSERT INTO item_notes (type_id, note) VALUES (3031, 'Currency');
+             INSERT INTO image_overrides (short_name, image_name) VALUES ('rat', 'cave-rat');
+             INSERT INTO quests (id, name) VALUES (5, 'Dragon Lair');
+             INSERT INTO quest_keys (quest_id, key_number, type_id) VALUES (5, 4601, 2088);
+             INSERT INTO doors (pos_x, pos_y, pos_z, type_id, keyhole_number) VALUES (32110, 32090, 8, 1249, 4601);",
+        )
+        .unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.json.zst");
+    write_bundle(&path, &db.dump_bundle().unwrap()).unwrap();
+    let bundle = read_bundle(&path).unwrap();
+    assert!(bundle.tables.iter().all(|t| t.name != "players" && t.name != "house_owners"));
+
+    let copy = Database::in_memory().unwrap();
+    let loaded = copy.load_bundle(&bundle).unwrap();
+    assert_eq!(copy.get_content_rows(None).unwrap(), db.get_content_rows(None).unwrap());
+    assert!(loaded.contains(&("towns".to_string(), 1)));
+    assert!(loaded.contains(&("spawns".to_string(), 1)));
+    assert!(loaded.contains(&("creature_notes".to_string(), 1)));
+    assert!(loaded.contains(&("item_notes".to_string(), 1)));
+    assert!(loaded.contains(&("image_overrides".to_string(), 1)));
+    assert_eq!(copy.get_quest_keys().unwrap().len(), 1);
+    assert_eq!(copy.get_doors().unwrap().len(), 1);
+    let spawn: (i32, i32, Option<String>) = copy
+        .connection()
+        .unwrap()
+        .query_row("SELECT race, radius, town FROM spawns", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap();
+    assert_eq!(spawn, (21, 2, Some("Carlin".to_string())));
+
+    // Reloading replaces houses without cascading to their owners
+    let counts = db.load_bundle(&bundle).unwrap();
+    assert!(counts.contains(&("houses".to_string(), 1)));
+    let conn = db.connection().unwrap();
+    let owners: i64 = conn.query_row("SELECT COUNT(*) FROM house_owners", [], |row| row.get(0)).unwrap();
+    assert_eq!(owners, 1);
+}
+
+#[test]
+fn test_player_renames_are_recorded() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let db = Database::in_memory().unwrap();
+
+    for (date, name) in [("2026-01-07", "Alice"), ("2026-01-08", "Alice"), ("2026-01-09", "Alicia"), ("2026-01-01", "Ally")] {
+        game.add_usr(&UsrSpec::new(1001, name)).unwrap();
+        db.process_usr_files(&game.usr_dir(), date, 2).unwrap();
+    }
+
+    let history: Vec<(String, String, String)> = db
+        .get_name_history(1001)
+        .unwrap()
+        .into_iter()
+        .map(|change| (change.old_name, change.new_name, change.effective_date))
+        .collect();
+    assert_eq!(
+        history,
+        vec![
+            ("Ally".to_string(), "Alice".to_string(), "2026-01-07".to_string()),
+            ("Alice".to_string(), "Alicia".to_string(), "2026-01-09".to_string()),
+        ]
+    );
+    // The backfilled snapshot doesn't undo the rename
+    assert_eq!(db.get_snapshot_list().unwrap()[0].player_name, "Alicia");
+    assert!(db.get_name_history(1002).unwrap().is_empty());
+}
+
+#[test]
+fn test_world_stats_follow_usr_ingestion() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let db = Database::in_memory().unwrap();
+
+    let player = |id: i32, level: i32| {
+        let mut spec = UsrSpec::new(id, &format!("Player {}", id));
+        spec.level = level;
+        spec.experience = 1000 * level as i64;
+        spec
+    };
+    game.add_usr(&player(1001, 20)).unwrap();
+    game.add_usr(&player(1002, 40)).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap();
+    game.add_usr(&player(1003, 10)).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-09", 2).unwrap();
+
+    let stats = db.get_world_stats().unwrap();
+    assert_eq!(stats.len(), 2);
+    assert_eq!((stats[0].players_seen, stats[0].median_level, stats[0].new_players), (2, 30.0, 2));
+    assert_eq!((stats[1].players_seen, stats[1].median_level, stats[1].new_players), (3, 20.0, 1));
+    assert_eq!(stats[1].total_experience, 70_000);
+    assert_eq!(stats[1].total_gold, None);
+
+    // Backfilling an earlier date recounts the new players of later dates
+    db.process_usr_files(&game.usr_dir(), "2026-01-01", 2).unwrap();
+    let new_players: Vec<i64> = db.get_world_stats().unwrap().iter().map(|s| s.new_players).collect();
+    assert_eq!(new_players, vec![3, 0, 0]);
+}
+
+#[test]
+fn test_events_annotate_world_stats() {
+    use demonax_core::export::export_world_stats;
+    use demonax_core::models::EventKind;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let db = Database::in_memory().unwrap();
+    game.add_usr(&UsrSpec::new(1001, "Alice")).unwrap();
+    for date in ["2026-01-08", "2026-01-09", "2026-01-10"] {
+        db.process_usr_files(&game.usr_dir(), date, 2).unwrap();
+    }
+
+    let weekend = db.add_event(EventKind::DoubleExp, "Double XP weekend", "2026-01-09", "2026-01-10", None).unwrap();
+    db.add_event(EventKind::Patch, "Patch 1.2", "2026-01-10", "2026-01-10", Some("New hunting grounds")).unwrap();
+    assert!(db.add_event(EventKind::Other, "Backwards", "2026-01-10", "2026-01-09", None).is_err());
+    assert!(db.add_event(EventKind::Other, "Bad date", "10.01.2026", "2026-01-10", None).is_err());
+
+    let events: Vec<Vec<String>> = db.get_world_stats().unwrap().into_iter().map(|day| day.events).collect();
+    assert_eq!(events, vec![vec![], vec!["Double XP weekend".to_string()], vec!["Double XP weekend".to_string(), "Patch 1.2".to_string()]]);
+    assert_eq!(db.get_events().unwrap()[0].kind, "double_exp");
+
+    // Dates without events leave the field out of the export
+    let output = temp_dir.path().join("world_stats.json");
+    export_world_stats(&db, &output, None).unwrap();
+    let stats: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    assert!(stats[0].get("events").is_none());
+    assert_eq!(stats[2]["events"][1], "Patch 1.2");
+
+    assert!(db.remove_event(weekend).unwrap());
+    assert!(!db.remove_event(weekend).unwrap());
+    assert_eq!(db.get_world_stats().unwrap()[1].events, Vec::<String>::new());
+}
+
+#[test]
+fn test_overwrite_existing_replaces_snapshots() {
+    use demonax_core::database::{ExistingSnapshots, UsrIngestOptions};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let db = Database::in_memory().unwrap();
+
+    let mut spec = UsrSpec::new(1001, "Alice");
+    spec.level = 20;
+    spec.quest_values = vec![(100, 1)];
+    spec.bestiary = vec![(35, 10)];
+    spec.harvesting = vec![(35, 2)];
+    game.add_usr(&spec).unwrap();
+    assert_eq!(db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap(), 1);
+    let children = |db: &Database| {
+        ["daily_skills", "daily_quests", "daily_bestiary", "daily_harvesting", "player_spells"]
+            .map(|table| count(db, &format!("SELECT COUNT(*) FROM {}", table)))
+    };
+    let before = children(&db);
+    assert!(before[1..4].iter().all(|&rows| rows == 1));
+
+    spec.level = 21;
+    game.add_usr(&spec).unwrap();
+    assert_eq!(db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap(), 0);
+    assert_eq!(count(&db, "SELECT level FROM daily_snapshots"), 20);
+
+    let overwrite = UsrIngestOptions { existing: ExistingSnapshots::Overwrite, ..UsrIngestOptions::default() };
+    let replaced = db.process_usr_files_with(&game.usr_dir(), "2026-01-08", 2, &overwrite).unwrap();
+    assert_eq!(replaced, 1);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots"), 1);
+    assert_eq!(count(&db, "SELECT level FROM daily_snapshots"), 21);
+    assert_eq!(children(&db), before);
+    assert_eq!(db.get_world_stats().unwrap()[0].median_level, 21.0);
+
+    // A file that no longer parses keeps its player's old snapshot; the rest of
+    // the date is still replaced
+    let bob = game.add_usr(&UsrSpec::new(1002, "Bob")).unwrap();
+    assert_eq!(db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap(), 1);
+    std::fs::write(&bob, "broken").unwrap();
+    spec.level = 22;
+    game.add_usr(&spec).unwrap();
+    assert_eq!(db.process_usr_files_with(&game.usr_dir(), "2026-01-08", 2, &overwrite).unwrap(), 1);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots"), 2);
+    assert_eq!(count(&db, "SELECT level FROM daily_snapshots WHERE player_id = 1001"), 22);
+}
+
+#[test]
+fn test_player_selection_limits_usr_ingestion() {
+    use demonax_core::database::UsrIngestOptions;
+    use demonax_core::player_selection::PlayerSelection;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let db = Database::in_memory().unwrap();
+    for (id, name) in [(1001, "Alice"), (1002, "Anna"), (1003, "Bob")] {
+        game.add_usr(&UsrSpec::new(id, name)).unwrap();
+    }
+
+    let ids = temp_dir.path().join("ids.txt");
+    std::fs::write(&ids, "1001, 1003\n").unwrap();
+    let by_glob = UsrIngestOptions { players: PlayerSelection::new(Some("a*"), None).unwrap(), ..UsrIngestOptions::default() };
+    assert_eq!(db.process_usr_files_with(&game.usr_dir(), "2026-01-08", 2, &by_glob).unwrap(), 2);
+    let by_both = UsrIngestOptions { players: PlayerSelection::new(Some("A*"), Some(&ids)).unwrap(), ..UsrIngestOptions::default() };
+    assert_eq!(db.process_usr_files_with(&game.usr_dir(), "2026-01-09", 2, &by_both).unwrap(), 1);
+
+    let snapshots: Vec<(i64, String)> = {
+        let conn = db.connection().unwrap();
+        let mut stmt = conn.prepare("SELECT player_id, snapshot_date FROM daily_snapshots ORDER BY snapshot_date, player_id").unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().map(|row| row.unwrap()).collect()
+    };
+    assert_eq!(
+        snapshots,
+        vec![(1001, "2026-01-08".to_string()), (1002, "2026-01-08".to_string()), (1001, "2026-01-09".to_string())]
+    );
+}
+
+#[test]
+fn test_rollback_is_detected_and_marked() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = SyntheticGame::create(temp_dir.path()).unwrap();
+    let db = Database::in_memory().unwrap();
+
+    let store = |date: &str, experience: &[i64]| {
+        for (i, &experience) in experience.iter().enumerate() {
+            let mut spec = UsrSpec::new(1001 + i as i32, &format!("Player {}", i));
+            spec.experience = experience;
+            game.add_usr(&spec).unwrap();
+        }
+        db.process_usr_files(&game.usr_dir(), date, 2).unwrap();
+    };
+    store("2026-01-08", &[10_000, 20_000, 30_000, 40_000, 50_000]);
+    // Two deaths are not a rollback
+    store("2026-01-09", &[9_000, 19_000, 31_000, 41_000, 51_000]);
+    assert!(db.detect_rollback("2026-01-09").unwrap().is_none());
+    store("2026-01-10", &[8_000, 18_000, 29_000, 41_500, 51_500]);
+
+    let events = db.get_events().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].kind.as_str(), events[0].start_date.as_str()), ("rollback", "2026-01-10"));
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots WHERE after_rollback"), 5);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots WHERE after_rollback AND snapshot_date <> '2026-01-10'"), 0);
+
+    // Checking again reuses the event
+    let rollback = db.detect_rollback("2026-01-10").unwrap().unwrap();
+    assert_eq!((rollback.players_compared, rollback.players_lost_experience, rollback.event_id), (5, 3, events[0].id));
+    assert_eq!(db.get_events().unwrap().len(), 1);
+
+    // The pace is measured from the rollback on
+    store("2026-01-11", &[9_000, 19_000, 30_000, 42_000, 52_000]);
+    assert_eq!(db.get_experience_history(1001, 30).unwrap(), vec![("2026-01-10".to_string(), 8_000), ("2026-01-11".to_string(), 9_000)]);
+
+    // Re-ingesting the date from a corrected backup drops the mark and the event
+    use demonax_core::database::{ExistingSnapshots, UsrIngestOptions};
+    for (i, experience) in [10_500, 20_500, 30_500, 40_500, 50_500].into_iter().enumerate() {
+        let mut spec = UsrSpec::new(1001 + i as i32, &format!("Player {}", i));
+        spec.experience = experience;
+        game.add_usr(&spec).unwrap();
+    }
+    let overwrite = UsrIngestOptions { existing: ExistingSnapshots::Overwrite, ..UsrIngestOptions::default() };
+    assert_eq!(db.process_usr_files_with(&game.usr_dir(), "2026-01-10", 2, &overwrite).unwrap(), 5);
+    assert!(db.get_events().unwrap().is_empty());
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots WHERE after_rollback"), 0);
+}
+
+#[test]
+fn test_usr_backup_archives_are_ingested() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let backups = temp_dir.path().join("backups");
+    std::fs::create_dir_all(&backups).unwrap();
+
+    let tar_path = backups.join("usr-2026-01-07.tar.gz");
+    let gz = flate2::write::GzEncoder::new(std::fs::File::create(&tar_path).unwrap(), flate2::Compression::fast());
+    let mut builder = tar::Builder::new(gz);
+    builder.append_dir_all("usr", game.usr_dir()).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let dated = demonax_core::file_utils::find_dated_directories(&backups, "usr-{date}").unwrap();
+    assert_eq!(dated, vec![(chrono::NaiveDate::from_ymd_opt(2026, 1, 7).unwrap(), tar_path.clone())]);
+    let (date, _) = demonax_core::file_utils::infer_snapshot_date(&tar_path).unwrap();
+    assert_eq!(date.to_string(), "2026-01-07");
+
+    let db = Database::in_memory().unwrap();
+    assert_eq!(db.process_usr_files(&tar_path, "2026-01-07", 2).unwrap(), 2);
+    assert_eq!(db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap(), 0);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots"), 2);
+    assert_eq!(count(&db, "SELECT players_seen FROM world_stats WHERE snapshot_date = '2026-01-07'"), 2);
+}
+
+#[test]
+fn test_creature_loot_export() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let output_dir = temp_dir.path().join("web");
+    assert_eq!(demonax_core::export::export_creature_loot(&db, &output_dir, None).unwrap(), 2);
+
+    let json = std::fs::read_to_string(output_dir.join("creatures/dragon.json")).unwrap();
+    assert!(!json.contains('\n'));
+    let table: demonax_core::models::CreatureLootTable = serde_json::from_str(&json).unwrap();
+    assert_eq!(table.name, "Dragon");
+    let items: Vec<_> = table.loot.iter().map(|e| (e.item, e.name.as_deref(), e.amount.as_str(), e.value)).collect();
+    assert_eq!(items, vec![(3031, Some("Gold Coin"), "1-100", Some(1)), (3361, Some("Leather Armor"), "1", None)]);
+    assert!(table.loot[0].chance > table.loot[1].chance);
+    assert!(output_dir.join("creatures/rat.json").exists());
+}
+
+#[test]
+fn test_creature_catalog_cross_references() {
+    use demonax_core::raid::describe_raid_creatures;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let mut necromancer = MonSpec::new("necromancer", "Necromancer", 9);
+    necromancer.spells = vec!["Actor (13) -> Summon (21, 2) : 10".to_string()];
+    necromancer.loot = vec![(3031, 30, 999)];
+    game.add_mon(&necromancer).unwrap();
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let creatures = db.get_creature_catalog().unwrap();
+    assert_eq!(creatures.len(), 3);
+    assert_eq!(creatures.find("rats").map(|c| c.race), Some(21));
+    assert_eq!(creatures.find("a necromanser").map(|c| c.short_name.as_str()), Some("necromancer"));
+
+    assert_eq!(db.name_summoned_creatures(&creatures).unwrap(), 1);
+    let summon_name: String =
+        db.connection().unwrap().query_row("SELECT summon_name FROM creature_spells WHERE summon_race_id = 21", [], |row| row.get(0)).unwrap();
+    assert_eq!(summon_name, "Rat");
+
+    let output_dir = temp_dir.path().join("web");
+    demonax_core::export::export_creature_loot(&db, &output_dir, None).unwrap();
+    let json = std::fs::read_to_string(output_dir.join("creatures/necromancer.json")).unwrap();
+    let table: demonax_core::models::CreatureLootTable = serde_json::from_str(&json).unwrap();
+    assert_eq!((table.race, table.summons), (9, vec!["2 Rat".to_string()]));
+
+    let raid = parse_evt_str(&evt_file(86400, "Rats!", &[(21, 3, 5), (99, 1, 1)]), "ratraid").unwrap();
+    let described = describe_raid_creatures(&raid.spawn_composition_json, &creatures);
+    assert_eq!(described.as_deref(), Some("3 to 5 Rat, 1 Race 99"));
+}
+
+#[test]
+fn test_economy_inflow_from_bestiary_growth() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    // A player's first snapshot says nothing about when the kills happened
+    assert!(db.get_economy_inflow(Some("2026-01-07")).unwrap().is_empty());
+
+    let mut alice = UsrSpec::new(1001, "Alice");
+    alice.bestiary = vec![(21, 50), (34, 2)];
+    game.add_usr(&alice).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap();
+
+    let inflow = db.get_economy_inflow(None).unwrap();
+    let rows: Vec<_> = inflow.iter().map(|r| (r.creature.as_str(), r.kills, r.gold, r.item_value)).collect();
+    // Dragons drop 1-100 coins (50.5 on average), rats 1-4 (2.5); leather armor has no buyer
+    assert_eq!(rows, vec![("Dragon", 2, 101, 0), ("Rat", 20, 50, 0)]);
+    assert!((inflow[0].items - 2.0 * 201.0 / 999.0).abs() < 1e-9);
+    assert_eq!(inflow[1].items, 0.0);
+    assert_eq!(inflow[0].snapshot_date, "2026-01-08");
+}
+
+#[test]
+fn test_economy_inflow_backfill_runs_once() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db_path = temp_dir.path().join("demonax.sqlite");
+    let db = Database::new(&db_path).unwrap();
+    ingest_all(&game, &db);
+    let mut alice = UsrSpec::new(1001, "Alice");
+    alice.bestiary = vec![(21, 50), (34, 2)];
+    game.add_usr(&alice).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap();
+    db.connection().unwrap().execute("DELETE FROM economy_inflow", []).unwrap();
+    drop(db);
+
+    // The empty table is not recomputed on every open
+    let db = Database::new(&db_path).unwrap();
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM economy_inflow"), 0);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM data_migrations WHERE name = 'economy_inflow_backfill'"), 1);
+    db.connection().unwrap().execute("DELETE FROM data_migrations", []).unwrap();
+    drop(db);
+
+    // A database from before the backfill gets it on the next open
+    let db = Database::new(&db_path).unwrap();
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM economy_inflow"), 2);
+}
+
+#[test]
+fn test_bestiary_monster_ids_resolve_to_creatures() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let mut bob = UsrSpec::new(1002, "Bob");
+    // 1034 is a dragon under another monster ID, 77 no creature at all
+    bob.bestiary = vec![(21, 5), (1034, 3), (77, 1)];
+    game.add_usr(&bob).unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-08", 2).unwrap();
+
+    let coverage = db.get_coverage(None).unwrap();
+    let bestiary = coverage.iter().find(|c| c.subsystem == "bestiary").unwrap();
+    assert_eq!((bestiary.total, bestiary.missing), (3, 2));
+    assert_eq!(bestiary.examples, vec!["Monster 77", "Monster 1034"]);
+
+    db.replace_bestiary_races(&[[1034, 34]]).unwrap();
+    let totals: Vec<_> = db
+        .get_bestiary_totals()
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.monster_id, t.name, t.kills, t.players))
+        .collect();
+    assert_eq!(
+        totals,
+        vec![
+            (21, Some("Rat".to_string()), 35, 2),
+            (1034, Some("Dragon".to_string()), 3, 1),
+            (77, None, 1, 1),
+        ]
+    );
+    let coverage = db.get_coverage(None).unwrap();
+    assert_eq!(coverage.iter().find(|c| c.subsystem == "bestiary").unwrap().examples, vec!["Monster 77"]);
+}
+
+#[test]
+fn test_harvesting_rules_resolve_tool_creature_and_reward() {
+    use demonax_core::models::HarvestingData;
+    use demonax_core::testdata::objects_srv;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+    let text = objects_srv(&[ItemSpec::new(5908, "an obsidian knife"), ItemSpec::new(5877, "a green dragon leather")]);
+    db.insert_or_update_items(&parse_objects_srv_str(&text).unwrap()).unwrap();
+
+    let rule = |corpse_id: i32, reward_id: i32, race_id: i32| HarvestingData {
+        tool_id: 5908,
+        corpse_id,
+        next_corpse_id: corpse_id + 1,
+        percent_chance: 10,
+        reward_id,
+        race_id,
+    };
+    db.insert_harvesting_data(&[rule(4025, 5877, 34), rule(4100, 9999, 99)]).unwrap();
+
+    let rules = db.get_harvesting_rules().unwrap();
+    assert_eq!(rules[0].description, "skinning a dead dragon yields green dragon leather");
+    assert_eq!(rules[0].creature_short_name.as_deref(), Some("dragon"));
+    assert_eq!((rules[1].creature_name.as_deref(), rules[1].reward_name.as_deref()), (None, None));
+
+    let coverage = db.get_coverage(None).unwrap();
+    let examples: Vec<_> = coverage.iter().filter(|c| c.subsystem == "harvesting").map(|c| c.examples.clone()).collect();
+    assert_eq!(examples, vec![vec!["Race 99 (corpse 4100)".to_string()], vec!["Race 99: reward 9999".to_string()]]);
+}
+
+#[test]
+fn test_post_save_hook_stores_changed_usr_files() {
+    use demonax_core::processors::{GamePaths, Reporter};
+    use demonax_core::sandbox::PlayerFilter;
+    use demonax_core::save_hook::SaveHook;
+    use std::time::Duration;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+    let paths = GamePaths::discover(&game.root).unwrap();
+    let hook = SaveHook::for_database(&temp_dir.path().join("demonax.sqlite"));
+    let reporter = Reporter::new(2);
+
+    // Nothing was saved after the mark, which is one second in the future
+    hook.pre_save(&db, &paths, &temp_dir.path().join("backups"), &PlayerFilter::all(), Duration::ZERO, &reporter).unwrap();
+    let later = std::fs::read_to_string(&hook.marker).unwrap().trim().parse::<u64>().unwrap() + 1;
+    std::fs::write(&hook.marker, later.to_string()).unwrap();
+    let summary = hook.post_save(&db, &paths, Some("2026-01-08"), Duration::ZERO, &reporter).unwrap();
+    assert_eq!((summary.usr_files, summary.game_files), (0, 0));
+    assert!(!hook.marker.exists() && !hook.lock.exists());
+
+    // Without a mark, every file counts as saved
+    let summary = hook.post_save(&db, &paths, Some("2026-01-08"), Duration::ZERO, &reporter).unwrap();
+    assert_eq!((summary.usr_files, summary.snapshots), (2, 2));
+    assert!(summary.game_files > 0);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM daily_snapshots WHERE snapshot_date = '2026-01-08'"), 2);
+}
+
+#[test]
+fn test_replaced_tables_have_row_counts() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let registry = demonax_core::processors::ParserRegistry::with_defaults();
+    assert_eq!(registry.get("npc-prices").unwrap().replaces(), ["item_prices", "npcs"]);
+    assert!(registry.get("raids").unwrap().replaces().is_empty());
+    assert!(db.row_count("item_prices").unwrap() > 0);
+    assert!(db.row_count("no_such_table").is_err());
+}
+
+#[test]
+fn test_rows_changed_are_recorded_in_audit_log() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    assert_eq!(db.rows_changed(), 0, "migrations are not counted");
+    ingest_all(&game, &db);
+
+    let rows = db.rows_changed();
+    assert!(rows >= db.row_count("items").unwrap() as u64 + db.row_count("creatures").unwrap() as u64);
+    // Other tests write through their own handles in parallel
+    assert!(demonax_core::audit::rows_changed() >= rows);
+
+    let invocation = demonax_core::audit::Invocation::new("update-all", "update-all --game-path game");
+    db.record_audit(&invocation, rows).unwrap();
+    let (command, rows_affected): (String, i64) = db
+        .connection()
+        .unwrap()
+        .query_row("SELECT command, rows_affected FROM audit_log", [], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap();
+    assert_eq!((command.as_str(), rows_affected), ("update-all", rows as i64));
+}
+
+#[test]
+fn test_quests_are_classified_by_region() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    // Quest 25 used to be dropped as Rookgaard-only; its chest is in Rookgaard
+    game.add_sec((1002, 1006, 7), &[sec_chest_line(4, 4, 25, &[3361])]).unwrap();
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let regions: Vec<(i32, Option<String>)> = {
+        let conn = db.connection().unwrap();
+        let mut stmt = conn.prepare("SELECT id, region FROM quests ORDER BY id").unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().collect::<Result<_, _>>().unwrap()
+    };
+    assert_eq!(regions, vec![(25, Some("rookgaard".to_string())), (100, Some("mainland".to_string()))]);
+
+    // Rookgaard rewards are only linked when the region isn't skipped
+    let rewarded = |db: &Database| count(db, "SELECT COUNT(*) FROM items WHERE type_id = 3361 AND rewarded_from IS NOT NULL");
+    db.update_items_with_quest_rewards(&["rookgaard".to_string()], 2).unwrap();
+    assert_eq!(rewarded(&db), 0);
+    db.update_items_with_quest_rewards(&[], 2).unwrap();
+    assert_eq!(rewarded(&db), 1);
+}
+
+#[test]
+fn test_hook_scripts_write_tables_and_exports() {
+    use demonax_core::hooks::{HooksConfig, run_hooks};
+    use demonax_core::processors::Reporter;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db_path = temp_dir.path().join("demonax.sqlite");
+    let db = Database::new(&db_path).unwrap();
+    ingest_all(&game, &db);
+
+    let config = HooksConfig { dir: temp_dir.path().join("hooks"), output_dir: temp_dir.path().join("exports") };
+    let scripts = config.dir.join("process-usr");
+    std::fs::create_dir_all(&scripts).unwrap();
+    std::fs::write(
+        scripts.join("top_levels.rhai"),
+        r#"
+let rows = query("SELECT p.name AS name, MAX(s.level) AS level FROM daily_snapshots s
+                  JOIN players p ON p.id = s.player_id GROUP BY p.id HAVING MAX(s.level) >= ? ORDER BY name", [1]);
+write_table("top_levels", rows);
+export_csv("reports/top_levels.csv", rows);
+export_json("count.json", #{ players: rows.len() });
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(run_hooks(&db_path, &config, "update-all", &Reporter::new(2)).unwrap(), 0);
+    assert_eq!(run_hooks(&db_path, &config, "process-usr", &Reporter::new(2)).unwrap(), 1);
+    let players = count(&db, "SELECT COUNT(DISTINCT player_id) FROM daily_snapshots");
+    assert!(players > 0);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM hook_top_levels"), players);
+
+    let csv = std::fs::read_to_string(config.output_dir.join("reports/top_levels.csv")).unwrap();
+    // Script maps keep their keys sorted
+    assert!(csv.starts_with("level,name\n"));
+    assert_eq!(csv.lines().count() as i64, players + 1);
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(config.output_dir.join("count.json")).unwrap()).unwrap();
+    assert_eq!(json["players"], players);
+
+    // Writes through query() are refused and fail the command
+    std::fs::write(scripts.join("zz_delete.rhai"), r#"query("DELETE FROM players");"#).unwrap();
+    assert!(run_hooks(&db_path, &config, "process-usr", &Reporter::new(2)).is_err());
+    assert_eq!(count(&db, "SELECT COUNT(DISTINCT player_id) FROM daily_snapshots"), players);
+}
+
+#[test]
+fn test_hook_table_columns_may_be_sql_keywords() {
+    let db = Database::in_memory().unwrap();
+    let row = serde_json::json!({ "order": 1, "group": "a", "index": 2.5, "tags": ["x"] });
+    let rows = vec![row.as_object().unwrap().clone()];
+    assert_eq!(db.replace_hook_table("order", &rows).unwrap(), 1);
+
+    let stored: (i64, String, f64, String) = db
+        .connection()
+        .unwrap()
+        .query_row(r#"SELECT "order", "group", "index", tags FROM hook_order"#, [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .unwrap();
+    assert_eq!(stored, (1, "a".to_string(), 2.5, r#"["x"]"#.to_string()));
+    assert!(db.replace_hook_table("top levels", &rows).is_err());
+}
+
+#[test]
+fn test_lint_rules_from_config_and_built_in() {
+    use demonax_core::lint::{LintConfig, LintRuleset, LintSeverity, lint_content};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let mut boss = MonSpec::new("ferumbras", "Ferumbras", 229);
+    boss.article = String::new();
+    game.add_mon(&boss).unwrap();
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let findings = lint_content(&db, LintRuleset::built_in(), LintSeverity::Error).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!((findings[0].rule.as_str(), findings[0].subjects.clone()), ("boss-without-loot", vec!["Ferumbras".to_string()]));
+
+    let config: demonax_core::config::DemonaxConfig = r#"
+[lint]
+built_in = false
+
+[[lint.rules]]
+name = "light-coins"
+table = "items"
+condition = "weight BETWEEN 1 AND 100"
+subject = "name || ' (' || type_id || ')'"
+message = "Too light"
+
+[[lint.rules]]
+name = "nothing"
+table = "creatures"
+condition = "hp < 0"
+severity = "error"
+message = "Never matches"
+"#
+    .parse()
+    .unwrap();
+    let findings = lint_content(&db, &config.lint.ruleset(), LintSeverity::Info).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].severity, LintSeverity::Warning);
+    assert_eq!(findings[0].subjects, vec!["a gold coin (3031)".to_string()]);
+    assert!(lint_content(&db, &config.lint.ruleset(), LintSeverity::Error).unwrap().is_empty());
+
+    let broken: LintConfig =
+        toml::from_str("built_in = false\n[[rules]]\nname = \"bad\"\ntable = \"items\"\ncondition = \"no_such_column\"\nmessage = \"m\"\n")
+            .unwrap();
+    let err = lint_content(&db, &broken.ruleset(), LintSeverity::Info).unwrap_err();
+    assert!(err.to_string().contains("\"bad\""));
+}
+
+#[test]
+fn test_overlay_notes_are_merged_into_exports() {
+    use demonax_core::export::export_item_list;
+    use demonax_core::overlay::{OverlayKind, import_overlay};
+    use demonax_core::processors::Reporter;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let creatures_csv = temp_dir.path().join("creatures.csv");
+    std::fs::write(&creatures_csv, "short_name,note\ndragon,\"Hoards gold, breathes fire.\"\nwyrmling,Not in the game yet\n").unwrap();
+    let summary = import_overlay(&db, &creatures_csv, &Reporter::new(2)).unwrap();
+    assert_eq!((summary.kind, summary.stored, summary.unknown_keys.clone()), (OverlayKind::Creatures, 2, vec!["wyrmling".to_string()]));
+
+    let items_csv = temp_dir.path().join("items.csv");
+    std::fs::write(&items_csv, "type_id,note\n3031,The currency of the realm\n").unwrap();
+    assert!(import_overlay(&db, &items_csv, &Reporter::new(2)).unwrap().unknown_keys.is_empty());
+
+    let tables = db.get_creature_loot_tables().unwrap();
+    let dragon = tables.iter().find(|table| table.short_name == "dragon").unwrap();
+    assert_eq!(dragon.note.as_deref(), Some("Hoards gold, breathes fire."));
+    let gold = dragon.loot.iter().find(|entry| entry.item == 3031).unwrap();
+    assert_eq!(gold.note.as_deref(), Some("The currency of the realm"));
+    assert!(tables.iter().find(|table| table.short_name == "rat").unwrap().note.is_none());
+
+    let output = temp_dir.path().join("items.json");
+    export_item_list(&db, &output, None).unwrap();
+    let items: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    let coin = items.as_array().unwrap().iter().find(|item| item["type_id"] == 3031).unwrap();
+    assert_eq!(coin["note"], "The currency of the realm");
+
+    // An empty note removes it; a bad header is refused
+    std::fs::write(&creatures_csv, "short_name,note\ndragon,\n").unwrap();
+    assert_eq!(import_overlay(&db, &creatures_csv, &Reporter::new(2)).unwrap().removed, 1);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM creature_notes"), 1);
+    std::fs::write(&creatures_csv, "name,note\ndragon,x\n").unwrap();
+    assert!(import_overlay(&db, &creatures_csv, &Reporter::new(2)).is_err());
+}
+
+#[test]
+fn test_raid_log_records_last_seen() {
+    use demonax_core::processors::{process_raid_log, Reporter};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db = Database::in_memory().unwrap();
+    db.insert_or_update_raids(&[
+        parse_evt_str(&evt_file(2 * 86400, "Orcs!", &[(5, 3, 5)]), "orcraid").unwrap(),
+        parse_evt_str(&evt_file(86400, "Rats!", &[(21, 3, 5)]), "ratraid").unwrap(),
+    ])
+    .unwrap();
+
+    let log = temp_dir.path().join("raids.log");
+    std::fs::write(
+        &log,
+        "2026-10-10 18:00:00 Raid orcraid started.\n\
+         2026-10-10 18:00:05 Player Alice logged in.\n\
+         Mon Oct 12 18:00:00 2026: Raid \"OrcRaid\" begins\n\
+         [2026-10-14 06:00:00] raid orcraid executed\n\
+         2026-10-14 07:00:00 Raid ghostship started\n",
+    )
+    .unwrap();
+    assert_eq!(process_raid_log(&db, &log, &Reporter::new(2)).unwrap(), 4);
+    // Rotated logs repeat starts that are already stored
+    assert_eq!(process_raid_log(&db, &log, &Reporter::new(2)).unwrap(), 0);
+
+    let activity = db.get_raid_activity().unwrap();
+    let orcs = &activity[0];
+    assert_eq!((orcs.name.as_str(), orcs.times_seen), ("orcraid", 3));
+    assert_eq!(orcs.last_seen.as_deref(), Some("2026-10-14 06:00:00"));
+    assert_eq!(orcs.expected_next.as_deref(), Some("2026-10-16 06:00:00"));
+    assert!((orcs.observed_interval_days.unwrap() - 1.75).abs() < 1e-9);
+    assert_eq!((activity[1].times_seen, activity[1].last_seen.as_deref()), (0, None));
+
+    let raids = db.get_raid_list(None, true).unwrap();
+    assert_eq!(raids[0].last_seen.as_deref(), Some("2026-10-14 06:00:00"));
+    assert!(raids[1].last_seen.is_none());
+}
+
+#[test]
+fn test_process_log_tails_game_server_log() {
+    use demonax_core::logs::tail_log;
+    use std::io::Write;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    db.process_usr_files(&game.usr_dir(), "2026-01-07", 2).unwrap();
+
+    let log = temp_dir.path().join("game.log");
+    std::fs::write(
+        &log,
+        "2026-01-06 23:59:00 Alice advanced from level 7 to level 8.\n\
+         2026-01-07 18:03:12 Alice died at level 8, killed by Bob.\n\
+         2026-01-07 18:03:13 Player Alice logged out.\n\
+         2026-01-07 18:05:40 Bob advanced from level 29 to level 30.\n\
+         2026-01-07 18:07:02 Bob looted a magic plate armor from a demon",
+    )
+    .unwrap();
+    let first = tail_log(&db, &log).unwrap();
+    assert_eq!((first.events, first.rotated), (3, false));
+
+    // The line being written is read once it is complete
+    let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+    writeln!(file, ".").unwrap();
+    writeln!(file, "2026-01-07 19:00:00 Carol died at level 12, killed by a dragon.").unwrap();
+    drop(file);
+    assert_eq!(tail_log(&db, &log).unwrap().events, 2);
+    assert_eq!(tail_log(&db, &log).unwrap().events, 0);
+
+    // Since the latest snapshot date: yesterday's level advance is left out
+    let events = db.get_log_events(None).unwrap();
+    let kinds: Vec<(&str, &str)> = events.iter().map(|e| (e.kind.as_str(), e.player_name.as_str())).collect();
+    assert_eq!(kinds, vec![("death", "Carol"), ("rare_loot", "Bob"), ("level_up", "Bob"), ("death", "Alice")]);
+    assert_eq!((events[0].player_id, events[0].creature.as_deref()), (None, Some("dragon")));
+    assert_eq!(events[1].item.as_deref(), Some("magic plate armor"));
+    assert_eq!((events[3].player_id, events[3].killer_name.as_deref(), events[3].creature.as_deref()), (Some(1001), Some("Bob"), None));
+
+    db.set_player_hidden(1002, true).unwrap();
+    let events = db.get_log_events(Some("")).unwrap();
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|e| e.killer_name.is_none() && e.creature.as_deref() != Some("Bob")));
+
+    // A rotated log is read from the start
+    std::fs::write(&log, "2026-01-08 08:00:00 Alice advanced from level 8 to level 9.\n").unwrap();
+    let rotated = tail_log(&db, &log).unwrap();
+    assert_eq!((rotated.events, rotated.rotated), (1, true));
+}
+
+#[test]
+fn test_online_samples_give_daily_curves() {
+    use demonax_core::online::{sample_online, OnlineSource};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db = Database::in_memory().unwrap();
+    let whois = temp_dir.path().join("whoisonline.txt");
+    let source = OnlineSource::WhoisFile(whois.clone());
+    for (sampled_at, players) in [
+        ("2026-10-14 20:00:00", "Alice\nBob\n"),
+        ("2026-10-14 20:05:00", "Alice\nBob\nCarol\nDave\n"),
+        ("2026-10-15 08:00:00", "# nobody\n"),
+    ] {
+        std::fs::write(&whois, players).unwrap();
+        db.insert_online_sample(sampled_at, &sample_online(&source).unwrap(), &source.to_string()).unwrap();
+    }
+
+    let days = db.get_online_days().unwrap();
+    let curve: Vec<(&str, i64, i64, f64)> =
+        days.iter().map(|d| (d.date.as_str(), d.samples, d.peak_players, d.average_players)).collect();
+    assert_eq!(curve, vec![("2026-10-14", 2, 4, 3.0), ("2026-10-15", 1, 0, 0.0)]);
+    assert_eq!(days[0].max_players, None);
+
+    let output = temp_dir.path().join("online.json");
+    assert_eq!(demonax_core::export::export_online_days(&db, &output, None).unwrap(), 2);
+}
+
+#[test]
+fn test_edit_chest_rewrites_only_the_chest() {
+    use demonax_core::sector_edit::{edit_chest, ChestEdit};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let sec = game.add_sec((1000, 1000, 7), &[
+        "0-0: Content={4526}".to_string(),
+        sec_chest_line(12, 5, 100, &[3031, 3354]),
+        "13-5: Content={4526, 2853 Content={3585}}".to_string(),
+    ]).unwrap();
+    let original = std::fs::read_to_string(&sec).unwrap();
+    let chest = Position::new(32012, 32005, 7);
+    let backups = temp_dir.path().join("backups");
+
+    let edit = ChestEdit { content: Some("3031 Amount=100".to_string()), quest_value: Some(101) };
+    let dry = edit_chest(&game.root.join("map"), chest, &edit, &backups, true).unwrap();
+    assert_eq!(dry.new_line, "12-5: Content={4526, 2472 ChestQuestNumber=101 Content={3031 Amount=100}}");
+    assert_eq!(std::fs::read_to_string(&sec).unwrap(), original);
+
+    let change = edit_chest(&game.root.join("map"), chest, &edit, &backups, false).unwrap();
+    assert_eq!(std::fs::read_to_string(change.backup.unwrap()).unwrap(), original);
+    let written = std::fs::read_to_string(&sec).unwrap();
+    assert_eq!(written, original.replace(&dry.old_line, &dry.new_line));
+
+    let chests = parse_map_sector_file(&sec).unwrap();
+    assert_eq!((chests[0].quest_value, chests[0].item_ids.clone()), (101, vec![3031]));
+
+    assert!(edit_chest(&game.root.join("map"), Position::new(32013, 32005, 7), &edit, &backups, true).is_err());
+}
+
+#[test]
+fn test_spawn_heatmap_per_floor() {
+    use demonax_core::heatmap::{render_spawn_heatmap, HeatmapFilter, HeatmapFormat};
+    use demonax_core::rendering::ImageOutput;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+    let monster_db = "21 32100 32220 7 3 4 60\n34 32110 32230 7 2 1 600\n21 32100 32220 8 0 2 60\n0\n";
+    db.replace_spawns(&parse_monster_db_str(monster_db).unwrap()).unwrap();
+
+    let output = temp_dir.path().join("heatmap");
+    let floors = render_spawn_heatmap(&db, &HeatmapFilter::default(), 8, &output, HeatmapFormat::Image(ImageOutput::default())).unwrap();
+    assert_eq!(floors.iter().map(|f| f.floor).collect::<Vec<_>>(), vec![7, 8]);
+    assert!(output.join("spawns-floor-07.png").exists());
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output.join("heatmap.json")).unwrap()).unwrap();
+    assert_eq!(manifest[1]["max_density"], 2.0);
+
+    let rats = HeatmapFilter { creatures: vec!["rat".to_string()], ..HeatmapFilter::default() };
+    let output = temp_dir.path().join("rats");
+    let floors = render_spawn_heatmap(&db, &rats, 8, &output, HeatmapFormat::GeoJson).unwrap();
+    assert_eq!(floors.len(), 2);
+    let geojson: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join("spawns-floor-07.geojson")).unwrap()).unwrap();
+    let total: f64 = geojson["features"].as_array().unwrap().iter().map(|f| f["properties"]["density"].as_f64().unwrap()).sum();
+    assert!((total - 4.0).abs() < 0.05);
+}
+
+#[test]
+fn test_quest_routes_tie_doors_keys_and_levels() {
+    use demonax_core::processors::{DoorParser, FileProcessor};
+    use demonax_core::quest_route::{quest_routes, DoorKind};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    // Quest 100's chest at 32012,32005,7 behind a locked and a level door; quest 101 holds the key
+    game.add_sec((1000, 1000, 7), &[
+        sec_chest_line(12, 5, 100, &[3031, 3354]),
+        "10-5: Content={4526, 1249 KeyholeNumber=4601}".to_string(),
+        "8-5: Content={4526, 1255 DoorLevel=20}".to_string(),
+    ]).unwrap();
+    game.add_sec((1010, 1000, 7), &["3-3: Content={2472 ChestQuestNumber=101 Content={2088 KeyNumber=4601}}".to_string()]).unwrap();
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+    assert_eq!(DoorParser.process(&game.root, &db, 2).unwrap(), 2);
+    db.replace_spawns(&parse_monster_db_str("21 32010 32010 7 2 3 60\n21 32500 32500 7 2 3 60\n0\n").unwrap()).unwrap();
+
+    let routes = quest_routes(&db).unwrap();
+    let route = routes.iter().find(|route| route.id == 100).unwrap();
+    assert_eq!(route.chests, vec![Position::new(32012, 32005, 7)]);
+    assert_eq!(route.doors.iter().map(|door| door.kind).collect::<Vec<_>>(), vec![DoorKind::Level, DoorKind::Key]);
+    assert_eq!(route.keys.len(), 1);
+    assert_eq!((route.keys[0].key_number, route.keys[0].from_quests.clone()), (4601, vec![101]));
+    assert_eq!(route.keys[0].item.as_ref().map(|item| item.type_id), Some(2088));
+    assert_eq!(route.creatures, vec!["Rat"]);
+    assert!(route.recommended_level.unwrap() >= 20);
+
+    let other = routes.iter().find(|route| route.id == 101).unwrap();
+    assert!(other.doors.is_empty() && other.keys.is_empty());
+
+    let output = temp_dir.path().join("quest-routes.json");
+    assert_eq!(demonax_core::export::export_quest_routes(&db, &output, None).unwrap(), routes.len());
+}
+
+#[test]
+fn test_coverage_cross_checks_quest_values_and_chests() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let game = build_game(temp_dir.path());
+    game.add_sec((1001, 1000, 7), &[sec_chest_line(3, 3, 102, &[3031])]).unwrap();
+    let mut carol = UsrSpec::new(1003, "Carol");
+    carol.quest_values = vec![(101, 1), (300, 2), (102, 0)];
+    game.add_usr(&carol).unwrap();
+    let db = Database::in_memory().unwrap();
+    ingest_all(&game, &db);
+
+    let report = db.get_coverage(None).unwrap();
+    let find = |check: &str| report.iter().find(|c| c.check.starts_with(check)).unwrap();
+
+    // Alice set 100 and 101, Carol 101 and 300; only 100 and 102 have chests
+    let script_only = find("quest values players set");
+    assert_eq!((script_only.total, script_only.missing), (3, 2));
+    assert_eq!(script_only.examples, vec!["Quest value 101: 2 players", "Quest value 300: 1 player"]);
+
+    // A value of 0 doesn't count as opened
+    let unopened = find("quest chests no player");
+    assert_eq!((unopened.total, unopened.missing), (2, 1));
+    assert_eq!(unopened.examples, vec!["Quest 102"]);
+}
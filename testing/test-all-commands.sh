@@ -17,6 +17,7 @@ PRESENT_CONFIG="$REPO_ROOT/testing/asset/template/present.toml"
 export DEMONAX_DATABASE="$TEST_DB"
 export DEMONAX_LOG_FILE="$TEST_LOG"
 export DEMONAX_GAME_DIR="$GAME_DIR"
+export DEMONAX_YES=1
 
 # Clean previous test database and log
 rm -f "$TEST_DB" "$TEST_LOG"